@@ -1,12 +1,19 @@
-use crate::ffmpeg::convert_to_pcm_s16le;
+use crate::{ffmpeg::convert_to_pcm_s16le, wav::decode_pcm_s16le};
 use anyhow::Result;
+use log::trace;
 
 /// Representation of encoded (compressed) audio.
 pub struct EncodedAudio(Vec<u8>);
 
 impl EncodedAudio {
-    /// Decode into [`DecodedAudio`] with ffmpeg.
+    /// Decode into [`DecodedAudio`].
+    /// Uncompressed 16-bit wav is decoded in-process; anything else goes through ffmpeg.
     pub async fn decode(self) -> Result<DecodedAudio> {
+        if let Some(decoded_buf) = decode_pcm_s16le(&self.0) {
+            return Ok(DecodedAudio::from(decoded_buf));
+        }
+
+        trace!("Falling back to ffmpeg to decode audio");
         let decoded_buf = convert_to_pcm_s16le(self.0).await?;
         Ok(DecodedAudio::from(decoded_buf))
     }
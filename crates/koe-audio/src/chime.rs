@@ -0,0 +1,43 @@
+use crate::DecodedAudio;
+use std::f64::consts::PI;
+
+const SAMPLE_RATE: u32 = 48000;
+/// Length of each tone in seconds.
+const TONE_DURATION: f64 = 0.3;
+/// Peak amplitude relative to the maximum of i16.
+const AMPLITUDE: f64 = 0.25;
+
+/// Frequency of the higher tone (A5).
+const HIGH_TONE: f64 = 880.0;
+/// Frequency of the lower tone (F5).
+const LOW_TONE: f64 = 698.46;
+
+/// Chime played when the bot connects to a voice channel ("ピンポン", high then low).
+pub fn connect_chime() -> DecodedAudio {
+    render_tones(&[HIGH_TONE, LOW_TONE])
+}
+
+/// Chime played before the bot leaves a voice channel (low then high).
+pub fn disconnect_chime() -> DecodedAudio {
+    render_tones(&[LOW_TONE, HIGH_TONE])
+}
+
+/// Render decaying sine tones into 48kHz mono 16-bit signed little-endian samples.
+fn render_tones(frequencies: &[f64]) -> DecodedAudio {
+    let samples_per_tone = (SAMPLE_RATE as f64 * TONE_DURATION) as usize;
+    let mut buf = Vec::with_capacity(frequencies.len() * samples_per_tone * 2);
+
+    for frequency in frequencies {
+        for i in 0..samples_per_tone {
+            let t = i as f64 / SAMPLE_RATE as f64;
+            // Short linear attack to avoid a click, followed by an exponential decay like a bell
+            let attack = (t / 0.005).min(1.0);
+            let envelope = attack * (-t * 8.0).exp();
+            let sample = (2.0 * PI * frequency * t).sin() * envelope * AMPLITUDE;
+
+            buf.extend_from_slice(&((sample * i16::MAX as f64) as i16).to_le_bytes());
+        }
+    }
+
+    DecodedAudio::from(buf)
+}
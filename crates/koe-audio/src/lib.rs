@@ -1,4 +1,6 @@
 mod audio;
+pub mod chime;
 mod ffmpeg;
+mod wav;
 
 pub use audio::{DecodedAudio, EncodedAudio};
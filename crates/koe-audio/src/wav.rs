@@ -0,0 +1,264 @@
+//! In-process decoder for uncompressed wav, which is what VOICEVOX ENGINE returns.
+//! Skipping ffmpeg for these saves spawning a process for every message.
+
+/// Output sampling rate expected by songbird.
+const OUTPUT_SAMPLE_RATE: u32 = 48000;
+
+/// Decode 16-bit PCM wav into 48kHz mono 16-bit signed little-endian samples.
+/// Returns [`None`] for anything else (compressed codecs, other bit depths, malformed headers)
+/// so that the caller can fall back to ffmpeg.
+pub fn decode_pcm_s16le(source: &[u8]) -> Option<Vec<u8>> {
+    if source.len() < 12 || &source[0..4] != b"RIFF" || &source[8..12] != b"WAVE" {
+        return None;
+    }
+
+    let mut format = None;
+    let mut data = None;
+    let mut rest = &source[12..];
+    while rest.len() >= 8 {
+        let id = &rest[0..4];
+        let size = u32::from_le_bytes(rest[4..8].try_into().ok()?) as usize;
+        let body = &rest[8..];
+        // Some encoders write a placeholder size for streamed data chunks; take what is there.
+        let body = &body[..size.min(body.len())];
+
+        match id {
+            b"fmt " => format = Some(Format::parse(body)?),
+            b"data" => data = Some(body),
+            _ => {}
+        }
+
+        // Chunks are padded to an even size.
+        let advance = 8 + size + (size & 1);
+        if advance > rest.len() {
+            break;
+        }
+        rest = &rest[advance..];
+    }
+
+    let format = format?;
+    let data = data?;
+    if format.audio_format != 1 || format.bits_per_sample != 16 || format.channels == 0 {
+        return None;
+    }
+    if format.sample_rate == 0 {
+        return None;
+    }
+
+    let mono = downmix(data, format.channels as usize);
+    let resampled = resample(&mono, format.sample_rate);
+
+    Some(resampled.iter().flat_map(|s| s.to_le_bytes()).collect())
+}
+
+struct Format {
+    audio_format: u16,
+    channels: u16,
+    sample_rate: u32,
+    bits_per_sample: u16,
+}
+
+impl Format {
+    fn parse(body: &[u8]) -> Option<Self> {
+        if body.len() < 16 {
+            return None;
+        }
+
+        Some(Self {
+            audio_format: u16::from_le_bytes(body[0..2].try_into().ok()?),
+            channels: u16::from_le_bytes(body[2..4].try_into().ok()?),
+            sample_rate: u32::from_le_bytes(body[4..8].try_into().ok()?),
+            bits_per_sample: u16::from_le_bytes(body[14..16].try_into().ok()?),
+        })
+    }
+}
+
+/// Average interleaved frames into mono samples.
+fn downmix(data: &[u8], channels: usize) -> Vec<i16> {
+    data.chunks_exact(2 * channels)
+        .map(|frame| {
+            let sum: i32 = frame
+                .chunks_exact(2)
+                .map(|s| i16::from_le_bytes([s[0], s[1]]) as i32)
+                .sum();
+            (sum / channels as i32) as i16
+        })
+        .collect()
+}
+
+/// Resample to [`OUTPUT_SAMPLE_RATE`] with linear interpolation.
+fn resample(samples: &[i16], sample_rate: u32) -> Vec<i16> {
+    if sample_rate == OUTPUT_SAMPLE_RATE || samples.is_empty() {
+        return samples.to_vec();
+    }
+
+    let out_len = (samples.len() as u64 * OUTPUT_SAMPLE_RATE as u64 / sample_rate as u64) as usize;
+    (0..out_len)
+        .map(|i| {
+            // Position in the source, in units of 1/OUTPUT_SAMPLE_RATE source samples.
+            let pos = i as u64 * sample_rate as u64;
+            let index = (pos / OUTPUT_SAMPLE_RATE as u64) as usize;
+            let frac = (pos % OUTPUT_SAMPLE_RATE as u64) as i64;
+
+            let a = samples[index] as i64;
+            let b = *samples.get(index + 1).unwrap_or(&samples[index]) as i64;
+            (a + (b - a) * frac / OUTPUT_SAMPLE_RATE as i64) as i16
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(id: &[u8; 4], declared_size: u32, body: &[u8]) -> Vec<u8> {
+        let mut chunk = id.to_vec();
+        chunk.extend_from_slice(&declared_size.to_le_bytes());
+        chunk.extend_from_slice(body);
+        chunk
+    }
+
+    fn fmt_chunk(
+        audio_format: u16,
+        channels: u16,
+        sample_rate: u32,
+        bits_per_sample: u16,
+    ) -> Vec<u8> {
+        let block_align = channels * bits_per_sample / 8;
+        let mut body = Vec::new();
+        body.extend_from_slice(&audio_format.to_le_bytes());
+        body.extend_from_slice(&channels.to_le_bytes());
+        body.extend_from_slice(&sample_rate.to_le_bytes());
+        body.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+        body.extend_from_slice(&block_align.to_le_bytes());
+        body.extend_from_slice(&bits_per_sample.to_le_bytes());
+        chunk(b"fmt ", 16, &body)
+    }
+
+    fn riff(chunks: &[Vec<u8>]) -> Vec<u8> {
+        let body = chunks.concat();
+        let mut wav = b"RIFF".to_vec();
+        wav.extend_from_slice(&(4 + body.len() as u32).to_le_bytes());
+        wav.extend_from_slice(b"WAVE");
+        wav.extend_from_slice(&body);
+        wav
+    }
+
+    fn pcm(samples: &[i16]) -> Vec<u8> {
+        samples.iter().flat_map(|s| s.to_le_bytes()).collect()
+    }
+
+    fn samples(bytes: &[u8]) -> Vec<i16> {
+        bytes
+            .chunks_exact(2)
+            .map(|s| i16::from_le_bytes([s[0], s[1]]))
+            .collect()
+    }
+
+    #[test]
+    fn decodes_mono_48khz_as_is() {
+        let data = pcm(&[0, 1000, -1000, i16::MAX, i16::MIN]);
+        let wav = riff(&[
+            fmt_chunk(1, 1, 48000, 16),
+            chunk(b"data", data.len() as u32, &data),
+        ]);
+
+        assert_eq!(decode_pcm_s16le(&wav), Some(data));
+    }
+
+    #[test]
+    fn downmixes_stereo_by_averaging() {
+        let data = pcm(&[100, 300, -100, -300, i16::MAX, i16::MAX, i16::MIN, i16::MIN]);
+        assert_eq!(downmix(&data, 2), vec![200, -200, i16::MAX, i16::MIN]);
+        assert_eq!(downmix(&data, 1), samples(&data));
+
+        let wav = riff(&[
+            fmt_chunk(1, 2, 48000, 16),
+            chunk(b"data", data.len() as u32, &data),
+        ]);
+        assert_eq!(
+            decode_pcm_s16le(&wav).map(|x| samples(&x)),
+            Some(vec![200, -200, i16::MAX, i16::MIN])
+        );
+    }
+
+    #[test]
+    fn resamples_24khz_to_48khz() {
+        let source = [0, 1000, -1000, 3000];
+        let output = resample(&source, 24000);
+
+        assert_eq!(output.len(), source.len() * 2);
+        // Every other output sample is a source sample, with midpoints in between.
+        assert_eq!(output, vec![0, 500, 1000, 0, -1000, 1000, 3000, 3000]);
+        assert_eq!(output.first(), source.first());
+        assert_eq!(output.last(), source.last());
+
+        assert!(resample(&[], 24000).is_empty());
+        assert_eq!(resample(&source, 48000), source);
+    }
+
+    #[test]
+    fn accepts_truncated_and_odd_length_data() {
+        // The declared size runs past the end of the file, as written by streaming encoders.
+        let data = pcm(&[10, 20, 30]);
+        let wav = riff(&[
+            fmt_chunk(1, 1, 48000, 16),
+            chunk(b"data", 0xffff_ffff, &data),
+        ]);
+        assert_eq!(
+            decode_pcm_s16le(&wav).map(|x| samples(&x)),
+            Some(vec![10, 20, 30])
+        );
+
+        // A trailing half sample is dropped.
+        let mut data = pcm(&[10, 20]);
+        data.push(0x7f);
+        let wav = riff(&[
+            fmt_chunk(1, 1, 48000, 16),
+            chunk(b"data", data.len() as u32, &data),
+        ]);
+        assert_eq!(
+            decode_pcm_s16le(&wav).map(|x| samples(&x)),
+            Some(vec![10, 20])
+        );
+
+        // Odd-sized chunks before the data are followed by a padding byte.
+        let data = pcm(&[10, 20]);
+        let wav = riff(&[
+            fmt_chunk(1, 1, 48000, 16),
+            chunk(b"LIST", 3, &[1, 2, 3, 0]),
+            chunk(b"data", data.len() as u32, &data),
+        ]);
+        assert_eq!(decode_pcm_s16le(&wav), Some(data));
+    }
+
+    #[test]
+    fn rejects_unsupported_or_malformed_headers() {
+        let data = pcm(&[10, 20]);
+        let wav = |fmt: Vec<u8>| riff(&[fmt, chunk(b"data", data.len() as u32, &data)]);
+
+        // IEEE float, 8-bit and 24-bit PCM, no channels and no sample rate.
+        assert_eq!(decode_pcm_s16le(&wav(fmt_chunk(3, 1, 48000, 32))), None);
+        assert_eq!(decode_pcm_s16le(&wav(fmt_chunk(1, 1, 48000, 8))), None);
+        assert_eq!(decode_pcm_s16le(&wav(fmt_chunk(1, 1, 48000, 24))), None);
+        assert_eq!(decode_pcm_s16le(&wav(fmt_chunk(1, 0, 48000, 16))), None);
+        assert_eq!(decode_pcm_s16le(&wav(fmt_chunk(1, 1, 0, 16))), None);
+
+        // Missing or short chunks.
+        assert_eq!(decode_pcm_s16le(&riff(&[fmt_chunk(1, 1, 48000, 16)])), None);
+        assert_eq!(decode_pcm_s16le(&riff(&[chunk(b"data", 4, &data)])), None);
+        assert_eq!(
+            decode_pcm_s16le(&riff(&[
+                chunk(b"fmt ", 4, &[1, 0, 1, 0]),
+                chunk(b"data", 4, &data)
+            ])),
+            None
+        );
+
+        // Not a RIFF/WAVE file.
+        assert_eq!(decode_pcm_s16le(b"OggS"), None);
+        let mut not_wave = wav(fmt_chunk(1, 1, 48000, 16));
+        not_wave[8..12].copy_from_slice(b"AVI ");
+        assert_eq!(decode_pcm_s16le(&not_wave), None);
+    }
+}
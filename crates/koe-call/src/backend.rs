@@ -0,0 +1,693 @@
+use crate::{
+    cancel_groups, extract_songbird, get_call, pcm_duration, AudioDuration, EnqueuedAt, NotifyEnd,
+    QueueStatus, SharedEventHandler, SpeechGroup, StopTrack, TrackEndAdapter, TrackEndHandler,
+};
+use anyhow::{anyhow, Context as _, Result};
+use serenity::{async_trait, client::Context};
+use songbird::{
+    events::{CoreEvent, Event, EventHandler, TrackEvent},
+    id::{ChannelId, GuildId},
+    input::{Codec, Container, Input, Reader},
+    join::Join,
+    Call, Songbird,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex as StdMutex,
+    },
+    time::{Duration, Instant},
+};
+use tokio::sync::{mpsc, oneshot};
+
+/// ボイスチャンネルへの接続と音声の再生を行う
+/// Discordに接続せずに動作を確かめられるよう、実装を差し替えられるようにしている
+#[async_trait]
+pub trait VoiceBackend: Send + Sync {
+    /// ボイスチャンネルに接続する
+    /// `self_deaf`が`true`の場合はスピーカーミュートの状態で接続する
+    async fn join(&self, guild_id: GuildId, channel_id: ChannelId, self_deaf: bool) -> Result<()>;
+
+    /// ボイスチャンネルから退出する
+    async fn leave(&self, guild_id: GuildId) -> Result<()>;
+
+    /// ボイスチャンネルに接続しているかどうかを返す
+    async fn is_connected(&self, guild_id: GuildId) -> Result<bool>;
+
+    /// 接続先のボイスチャンネルを返す
+    /// 接続していない場合や、接続先が分からない場合は[`None`]を返す
+    async fn current_channel(&self, guild_id: GuildId) -> Result<Option<ChannelId>>;
+
+    /// 再生中のものを含め、キューに残っている音声の数を返す
+    async fn queue_len(&self, guild_id: GuildId) -> Result<usize>;
+
+    /// キューの状態を返す
+    async fn queue_status(&self, guild_id: GuildId) -> Result<QueueStatus>;
+
+    /// 再生中の音声をスキップする
+    /// 区切って追加された読み上げは、続きの音声もまとめて破棄する
+    async fn skip(&self, guild_id: GuildId) -> Result<()>;
+
+    /// キューに入っている音声を破棄して`raw_audio`を再生し、再生が終わるまで待つ
+    /// `timeout`を過ぎても再生が終わらない場合は待つのをやめる
+    async fn play_and_wait(
+        &self,
+        guild_id: GuildId,
+        raw_audio: Vec<u8>,
+        timeout: Duration,
+    ) -> Result<()>;
+
+    /// ボイスチャンネルとの接続状態が変わったとき（接続・再接続・切断）と、音声の再生が終了したときに呼ばれるハンドラを設定する
+    /// すでに設定されているハンドラは削除される
+    async fn set_event_handlers(
+        &self,
+        guild_id: GuildId,
+        connection_handler: Arc<dyn EventHandler>,
+        track_end_handler: Arc<dyn TrackEndHandler>,
+    ) -> Result<()>;
+
+    /// 音声をキューに追加する
+    /// `max_duration`を指定した場合、再生時間がそれを超えたところで再生を打ち切る
+    async fn enqueue(
+        &self,
+        guild_id: GuildId,
+        raw_audio: Vec<u8>,
+        max_duration: Option<Duration>,
+    ) -> Result<()>;
+
+    /// 1件の読み上げを区切って合成した音声を、`chunks`から受け取るたびにキューに追加する
+    /// 先頭の音声を再生している間に続きを合成できるよう、`chunks`が閉じられるまで戻らない
+    /// `max_duration`は区切った音声の合計の再生時間に対する制限
+    async fn enqueue_stream(
+        &self,
+        guild_id: GuildId,
+        mut chunks: mpsc::Receiver<Vec<u8>>,
+        max_duration: Option<Duration>,
+    ) -> Result<()> {
+        let mut remaining = max_duration;
+        while let Some(raw_audio) = chunks.recv().await {
+            if remaining == Some(Duration::ZERO) {
+                break;
+            }
+            let duration = pcm_duration(raw_audio.len());
+            self.enqueue(guild_id, raw_audio, remaining).await?;
+            remaining = remaining.map(|remaining| remaining.saturating_sub(duration));
+        }
+
+        Ok(())
+    }
+}
+
+/// Songbirdを使ってDiscordのボイスチャンネルに接続する実装
+pub struct SongbirdBackend {
+    manager: Arc<Songbird>,
+}
+
+impl SongbirdBackend {
+    pub fn new(manager: Arc<Songbird>) -> Self {
+        Self { manager }
+    }
+
+    pub async fn from_context(ctx: &Context) -> Result<Self> {
+        Ok(Self::new(extract_songbird(ctx).await?))
+    }
+}
+
+#[async_trait]
+impl VoiceBackend for SongbirdBackend {
+    async fn join(&self, guild_id: GuildId, channel_id: ChannelId, self_deaf: bool) -> Result<()> {
+        let call = self.manager.get_or_insert(guild_id);
+
+        // Call::joinを実行するには、2段階のawaitが必要
+        // 詳細は https://docs.rs/songbird/latest/songbird/struct.Call.html#method.join
+        let join_res: Result<Join> = {
+            let mut handler = call.lock().await;
+            handler.deafen(self_deaf).await?;
+
+            let join = handler.join(channel_id).await?;
+
+            Ok(join)
+        };
+        let join = join_res?;
+        join.await?;
+
+        Ok(())
+    }
+
+    async fn leave(&self, guild_id: GuildId) -> Result<()> {
+        self.manager.remove(guild_id).await?;
+
+        Ok(())
+    }
+
+    async fn is_connected(&self, guild_id: GuildId) -> Result<bool> {
+        Ok(self.manager.get(guild_id).is_some())
+    }
+
+    async fn current_channel(&self, guild_id: GuildId) -> Result<Option<ChannelId>> {
+        let call = match self.manager.get(guild_id) {
+            Some(call) => call,
+            None => return Ok(None),
+        };
+
+        let channel_id = call.lock().await.current_channel();
+        Ok(channel_id)
+    }
+
+    async fn queue_len(&self, guild_id: GuildId) -> Result<usize> {
+        let call = get_call(self.manager.clone(), guild_id).await?;
+        let len = call.lock().await.queue().len();
+        Ok(len)
+    }
+
+    async fn queue_status(&self, guild_id: GuildId) -> Result<QueueStatus> {
+        let call = get_call(self.manager.clone(), guild_id).await?;
+
+        let queue = call.lock().await.queue().clone();
+        let oldest_enqueued_at = match queue.current() {
+            Some(track) => track.typemap().read().await.get::<EnqueuedAt>().copied(),
+            None => None,
+        };
+
+        Ok(QueueStatus {
+            len: queue.len(),
+            oldest_enqueued_at,
+        })
+    }
+
+    async fn skip(&self, guild_id: GuildId) -> Result<()> {
+        let call = get_call(self.manager.clone(), guild_id).await?;
+
+        let handler = call.lock().await;
+        let queue = handler.queue();
+        let current_track = match queue.current() {
+            Some(track) => track,
+            None => return Ok(()),
+        };
+
+        let group = current_track
+            .typemap()
+            .read()
+            .await
+            .get::<SpeechGroup>()
+            .cloned();
+        current_track
+            .stop()
+            .context("Failed to stop current track")?;
+
+        // 区切って追加された読み上げは、続きの音声もまとめて破棄する
+        if let Some(group) = group {
+            group.store(true, Ordering::Relaxed);
+
+            let mut rest = Vec::new();
+            for track in queue.current_queue().into_iter().skip(1) {
+                let same_group = track
+                    .typemap()
+                    .read()
+                    .await
+                    .get::<SpeechGroup>()
+                    .is_some_and(|g| Arc::ptr_eq(g, &group));
+                if same_group {
+                    rest.push(track);
+                }
+            }
+
+            queue.modify_queue(|tracks| {
+                tracks.retain(|queued| !rest.iter().any(|track| track.uuid() == queued.uuid()))
+            });
+            for track in rest {
+                // すでに再生が終了している場合は失敗するが、問題はない
+                let _ = track.stop();
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn play_and_wait(
+        &self,
+        guild_id: GuildId,
+        raw_audio: Vec<u8>,
+        timeout: Duration,
+    ) -> Result<()> {
+        let call = get_call(self.manager.clone(), guild_id).await?;
+
+        let (tx, rx) = oneshot::channel();
+        {
+            let mut handler = call.lock().await;
+            cancel_groups(&handler.queue().current_queue()).await;
+            handler.queue().stop();
+
+            let track = handler.enqueue_source(Input::new(
+                false,
+                Reader::from_memory(raw_audio),
+                Codec::Pcm,
+                Container::Raw,
+                None,
+            ));
+            track
+                .add_event(
+                    Event::Track(TrackEvent::End),
+                    NotifyEnd(StdMutex::new(Some(tx))),
+                )
+                .context("Failed to register track end event")?;
+        }
+
+        // 再生が終わらなくても退出などの後続の処理を妨げないよう、結果は無視する
+        let _ = tokio::time::timeout(timeout, rx).await;
+
+        Ok(())
+    }
+
+    async fn set_event_handlers(
+        &self,
+        guild_id: GuildId,
+        connection_handler: Arc<dyn EventHandler>,
+        track_end_handler: Arc<dyn TrackEndHandler>,
+    ) -> Result<()> {
+        let call = get_call(self.manager.clone(), guild_id).await?;
+
+        let mut call = call.lock().await;
+        call.remove_all_global_events();
+        for event in [
+            CoreEvent::DriverConnect,
+            CoreEvent::DriverReconnect,
+            CoreEvent::DriverDisconnect,
+        ] {
+            call.add_global_event(
+                Event::Core(event),
+                SharedEventHandler(connection_handler.clone()),
+            );
+        }
+        call.add_global_event(
+            Event::Track(TrackEvent::End),
+            TrackEndAdapter(track_end_handler),
+        );
+
+        Ok(())
+    }
+
+    async fn enqueue(
+        &self,
+        guild_id: GuildId,
+        raw_audio: Vec<u8>,
+        max_duration: Option<Duration>,
+    ) -> Result<()> {
+        let call = get_call(self.manager.clone(), guild_id).await?;
+        let mut handler = call.lock().await;
+        enqueue_track(&mut handler, raw_audio, max_duration, None).await
+    }
+
+    /// 区切った音声は同じ[`SpeechGroup`]のトラックとして追加し、スキップされたら残りを追加せずに戻る
+    async fn enqueue_stream(
+        &self,
+        guild_id: GuildId,
+        mut chunks: mpsc::Receiver<Vec<u8>>,
+        max_duration: Option<Duration>,
+    ) -> Result<()> {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let mut remaining = max_duration;
+
+        while let Some(raw_audio) = chunks.recv().await {
+            if cancelled.load(Ordering::Relaxed) || remaining == Some(Duration::ZERO) {
+                break;
+            }
+            let duration = pcm_duration(raw_audio.len());
+
+            let call = get_call(self.manager.clone(), guild_id).await?;
+            let mut handler = call.lock().await;
+            enqueue_track(&mut handler, raw_audio, remaining, Some(cancelled.clone())).await?;
+
+            remaining = remaining.map(|remaining| remaining.saturating_sub(duration));
+        }
+
+        Ok(())
+    }
+}
+
+async fn enqueue_track(
+    handler: &mut Call,
+    raw_audio: Vec<u8>,
+    max_duration: Option<Duration>,
+    group: Option<Arc<AtomicBool>>,
+) -> Result<()> {
+    let audio_duration = pcm_duration(raw_audio.len());
+
+    let track = handler.enqueue_source(Input::new(
+        false,
+        Reader::from_memory(raw_audio),
+        Codec::Pcm,
+        Container::Raw,
+        None,
+    ));
+    track
+        .typemap()
+        .write()
+        .await
+        .insert::<EnqueuedAt>(Instant::now());
+    track
+        .typemap()
+        .write()
+        .await
+        .insert::<AudioDuration>(audio_duration);
+    if let Some(group) = group {
+        track.typemap().write().await.insert::<SpeechGroup>(group);
+    }
+
+    if let Some(max_duration) = max_duration {
+        // トラックのイベントは再生時間を基準に発火する
+        track
+            .add_event(Event::Delayed(max_duration), StopTrack)
+            .context("Failed to register max duration event")?;
+    }
+
+    Ok(())
+}
+
+/// Discordに接続せず、接続しているものとして扱うサーバーのみを記録する実装
+/// 音声は再生せずに破棄する
+/// 開発用に、読み上げる文章をログに出力する場合に使う
+#[derive(Default)]
+pub struct NullBackend {
+    guilds: StdMutex<HashSet<GuildId>>,
+}
+
+impl NullBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, HashSet<GuildId>>> {
+        self.guilds
+            .lock()
+            .map_err(|_| anyhow!("NullBackend mutex is poisoned"))
+    }
+}
+
+#[async_trait]
+impl VoiceBackend for NullBackend {
+    async fn join(
+        &self,
+        guild_id: GuildId,
+        _channel_id: ChannelId,
+        _self_deaf: bool,
+    ) -> Result<()> {
+        self.lock()?.insert(guild_id);
+
+        Ok(())
+    }
+
+    async fn leave(&self, guild_id: GuildId) -> Result<()> {
+        self.lock()?.remove(&guild_id);
+
+        Ok(())
+    }
+
+    async fn is_connected(&self, guild_id: GuildId) -> Result<bool> {
+        Ok(self.lock()?.contains(&guild_id))
+    }
+
+    async fn current_channel(&self, _guild_id: GuildId) -> Result<Option<ChannelId>> {
+        Ok(None)
+    }
+
+    async fn queue_len(&self, _guild_id: GuildId) -> Result<usize> {
+        Ok(0)
+    }
+
+    async fn queue_status(&self, _guild_id: GuildId) -> Result<QueueStatus> {
+        Ok(QueueStatus::default())
+    }
+
+    async fn skip(&self, _guild_id: GuildId) -> Result<()> {
+        Ok(())
+    }
+
+    async fn play_and_wait(
+        &self,
+        _guild_id: GuildId,
+        _raw_audio: Vec<u8>,
+        _timeout: Duration,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    async fn set_event_handlers(
+        &self,
+        _guild_id: GuildId,
+        _connection_handler: Arc<dyn EventHandler>,
+        _track_end_handler: Arc<dyn TrackEndHandler>,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    async fn enqueue(
+        &self,
+        _guild_id: GuildId,
+        _raw_audio: Vec<u8>,
+        _max_duration: Option<Duration>,
+    ) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Discordに接続せず、接続状態と再生を要求された音声をメモリ上に記録するだけの実装
+/// テストで、接続や再生の順番を確かめるために使う
+#[cfg(any(test, feature = "test-support"))]
+#[derive(Default)]
+pub struct FakeBackend {
+    calls: StdMutex<HashMap<GuildId, FakeCall>>,
+}
+
+#[cfg(any(test, feature = "test-support"))]
+#[derive(Debug, Clone)]
+pub struct FakeCall {
+    pub channel_id: ChannelId,
+    pub self_deaf: bool,
+    /// キューに追加された音声
+    pub enqueued: Vec<Vec<u8>>,
+    /// [`VoiceBackend::skip`]が呼ばれた回数
+    pub skips: u32,
+    /// `enqueued`のうち、再生し終えたものとして扱う先頭からの数
+    pub finished: usize,
+    /// [`VoiceBackend::play_and_wait`]で再生した音声
+    pub played: Vec<Vec<u8>>,
+    /// [`VoiceBackend::set_event_handlers`]が呼ばれたかどうか
+    pub has_event_handlers: bool,
+}
+
+#[cfg(any(test, feature = "test-support"))]
+impl FakeBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// サーバーの接続状態を返す
+    pub fn call(&self, guild_id: GuildId) -> Option<FakeCall> {
+        self.lock().ok()?.get(&guild_id).cloned()
+    }
+
+    /// キューの先頭の音声を再生し終えたものとして扱い、その音声を返す
+    pub fn finish_track(&self, guild_id: GuildId) -> Option<Vec<u8>> {
+        let mut calls = self.lock().ok()?;
+        let call = calls.get_mut(&guild_id)?;
+        let track = call.enqueued.get(call.finished)?.clone();
+        call.finished += 1;
+        Some(track)
+    }
+
+    fn skips(&self, guild_id: GuildId) -> Result<Option<u32>> {
+        Ok(self.lock()?.get(&guild_id).map(|call| call.skips))
+    }
+
+    /// 接続しているサーバーの状態を`f`で読み書きする
+    /// 接続していない場合はSongbirdBackendと同じくエラーを返す
+    fn with_call<T>(&self, guild_id: GuildId, f: impl FnOnce(&mut FakeCall) -> T) -> Result<T> {
+        let mut calls = self.lock()?;
+        let call = calls
+            .get_mut(&guild_id)
+            .ok_or_else(|| anyhow!("Failed to retrieve call for guild {}", guild_id))?;
+        Ok(f(call))
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, HashMap<GuildId, FakeCall>>> {
+        self.calls
+            .lock()
+            .map_err(|_| anyhow!("FakeBackend mutex is poisoned"))
+    }
+}
+
+#[cfg(any(test, feature = "test-support"))]
+#[async_trait]
+impl VoiceBackend for FakeBackend {
+    async fn join(&self, guild_id: GuildId, channel_id: ChannelId, self_deaf: bool) -> Result<()> {
+        let mut calls = self.lock()?;
+        let call = calls.entry(guild_id).or_insert_with(|| FakeCall {
+            channel_id,
+            self_deaf,
+            enqueued: Vec::new(),
+            skips: 0,
+            finished: 0,
+            played: Vec::new(),
+            has_event_handlers: false,
+        });
+        call.channel_id = channel_id;
+        call.self_deaf = self_deaf;
+
+        Ok(())
+    }
+
+    async fn leave(&self, guild_id: GuildId) -> Result<()> {
+        self.lock()?.remove(&guild_id);
+
+        Ok(())
+    }
+
+    async fn is_connected(&self, guild_id: GuildId) -> Result<bool> {
+        Ok(self.lock()?.contains_key(&guild_id))
+    }
+
+    async fn current_channel(&self, guild_id: GuildId) -> Result<Option<ChannelId>> {
+        Ok(self.lock()?.get(&guild_id).map(|call| call.channel_id))
+    }
+
+    async fn queue_len(&self, guild_id: GuildId) -> Result<usize> {
+        self.with_call(guild_id, |call| call.enqueued.len() - call.finished)
+    }
+
+    async fn queue_status(&self, guild_id: GuildId) -> Result<QueueStatus> {
+        Ok(QueueStatus {
+            len: self.queue_len(guild_id).await?,
+            oldest_enqueued_at: None,
+        })
+    }
+
+    /// 再生中の読み上げをスキップしたものとして扱う
+    /// 区切って追加している途中の読み上げは、残りを追加せずに打ち切る
+    async fn skip(&self, guild_id: GuildId) -> Result<()> {
+        self.with_call(guild_id, |call| {
+            call.skips += 1;
+            if call.finished < call.enqueued.len() {
+                call.finished += 1;
+            }
+        })
+    }
+
+    /// キューに入っている音声を再生し終えたものとして扱い、`raw_audio`をすぐに再生し終える
+    async fn play_and_wait(
+        &self,
+        guild_id: GuildId,
+        raw_audio: Vec<u8>,
+        _timeout: Duration,
+    ) -> Result<()> {
+        self.with_call(guild_id, |call| {
+            call.finished = call.enqueued.len();
+            call.played.push(raw_audio);
+        })
+    }
+
+    async fn set_event_handlers(
+        &self,
+        guild_id: GuildId,
+        _connection_handler: Arc<dyn EventHandler>,
+        _track_end_handler: Arc<dyn TrackEndHandler>,
+    ) -> Result<()> {
+        self.with_call(guild_id, |call| call.has_event_handlers = true)
+    }
+
+    async fn enqueue(
+        &self,
+        guild_id: GuildId,
+        raw_audio: Vec<u8>,
+        _max_duration: Option<Duration>,
+    ) -> Result<()> {
+        self.with_call(guild_id, |call| call.enqueued.push(raw_audio))
+    }
+
+    async fn enqueue_stream(
+        &self,
+        guild_id: GuildId,
+        mut chunks: mpsc::Receiver<Vec<u8>>,
+        max_duration: Option<Duration>,
+    ) -> Result<()> {
+        let skips = self.skips(guild_id)?;
+        while let Some(raw_audio) = chunks.recv().await {
+            if self.skips(guild_id)? != skips {
+                break;
+            }
+            self.enqueue(guild_id, raw_audio, max_duration).await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GUILD_ID: GuildId = GuildId(1);
+
+    #[tokio::test]
+    async fn fake_backend_records_connection_and_audio() {
+        let backend = FakeBackend::new();
+        assert!(!backend.is_connected(GUILD_ID).await.unwrap());
+        assert!(backend.enqueue(GUILD_ID, vec![1], None).await.is_err());
+
+        backend.join(GUILD_ID, ChannelId(2), true).await.unwrap();
+        backend.enqueue(GUILD_ID, vec![1], None).await.unwrap();
+        backend.enqueue(GUILD_ID, vec![2], None).await.unwrap();
+
+        let call = backend.call(GUILD_ID).unwrap();
+        assert_eq!(call.channel_id, ChannelId(2));
+        assert!(call.self_deaf);
+        assert_eq!(call.enqueued, [vec![1], vec![2]]);
+        assert_eq!(backend.queue_len(GUILD_ID).await.unwrap(), 2);
+
+        // スキップすると再生中の音声を飛ばす
+        backend.skip(GUILD_ID).await.unwrap();
+        assert_eq!(backend.queue_len(GUILD_ID).await.unwrap(), 1);
+        assert_eq!(backend.finish_track(GUILD_ID), Some(vec![2]));
+
+        backend.leave(GUILD_ID).await.unwrap();
+        assert!(!backend.is_connected(GUILD_ID).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn fake_backend_stops_stream_after_skip() {
+        let backend = Arc::new(FakeBackend::new());
+        backend.join(GUILD_ID, ChannelId(2), false).await.unwrap();
+
+        let (tx, rx) = mpsc::channel(1);
+        let stream = tokio::spawn({
+            let backend = backend.clone();
+            async move { backend.enqueue_stream(GUILD_ID, rx, None).await }
+        });
+
+        tx.send(vec![1]).await.unwrap();
+        while backend.call(GUILD_ID).unwrap().enqueued.is_empty() {
+            tokio::task::yield_now().await;
+        }
+        backend.skip(GUILD_ID).await.unwrap();
+        // スキップされた後に届いた音声は追加しない
+        let _ = tx.send(vec![2]).await;
+        stream.await.unwrap().unwrap();
+
+        assert_eq!(backend.call(GUILD_ID).unwrap().enqueued, [vec![1]]);
+        assert!(tx.send(vec![3]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn null_backend_discards_audio() {
+        let backend = NullBackend::new();
+        backend.join(GUILD_ID, ChannelId(2), false).await.unwrap();
+        assert!(backend.is_connected(GUILD_ID).await.unwrap());
+
+        backend.enqueue(GUILD_ID, vec![1], None).await.unwrap();
+        assert_eq!(backend.queue_len(GUILD_ID).await.unwrap(), 0);
+
+        backend.leave(GUILD_ID).await.unwrap();
+        assert!(!backend.is_connected(GUILD_ID).await.unwrap());
+    }
+}
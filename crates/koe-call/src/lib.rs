@@ -1,91 +1,145 @@
-use anyhow::{anyhow, Context as _, Result};
-use serenity::client::Context;
+use anyhow::{anyhow, Result};
+use serenity::{async_trait, client::Context};
 use songbird::{
-    id::{ChannelId, GuildId},
-    input::{Codec, Container, Input, Reader},
-    join::Join,
+    events::{Event, EventContext, EventHandler},
+    id::GuildId,
+    tracks::{PlayMode, TrackHandle},
+    typemap::TypeMapKey,
     Call, Songbird,
 };
-use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex as StdMutex,
+    },
+    time::{Duration, Instant},
+};
+use tokio::sync::{oneshot, Mutex};
 
-pub async fn join_deaf(
-    ctx: &Context,
-    guild_id: impl Into<GuildId>,
-    channel_id: impl Into<ChannelId>,
-) -> Result<()> {
-    let manager = extract_songbird(ctx).await?;
-    let guild_id = guild_id.into();
-    let channel_id = channel_id.into();
+pub mod backend;
 
-    let call = manager.get_or_insert(guild_id);
+pub use backend::{NullBackend, SongbirdBackend, VoiceBackend};
 
-    // Call::joinを実行するには、2段階のawaitが必要
-    // 詳細は https://docs.rs/songbird/latest/songbird/struct.Call.html#method.join
-    let join_res: Result<Join> = {
-        let mut handler = call.lock().await;
-        handler.deafen(true).await?;
+/// キューに入っている区切って追加された読み上げについて、続きの音声を追加しないようにする
+async fn cancel_groups(tracks: &[TrackHandle]) {
+    for track in tracks {
+        if let Some(group) = track.typemap().read().await.get::<SpeechGroup>() {
+            group.store(true, Ordering::Relaxed);
+        }
+    }
+}
 
-        let join = handler.join(channel_id).await?;
+#[async_trait]
+pub trait TrackEndHandler: Send + Sync {
+    /// 音声の再生が終了したときに呼ばれる
+    /// `failed`は、音声を最後まで再生する前に再生が終了したかどうかを表す
+    /// スキップや最大再生時間による停止は失敗として扱わない
+    async fn on_track_end(&self, failed: bool);
+}
 
-        Ok(join)
-    };
-    let join = join_res?;
-    join.await?;
+struct TrackEndAdapter(Arc<dyn TrackEndHandler>);
+
+#[async_trait]
+impl EventHandler for TrackEndAdapter {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        if let EventContext::Track(tracks) = ctx {
+            for (state, handle) in *tracks {
+                let audio_duration = handle
+                    .typemap()
+                    .read()
+                    .await
+                    .get::<AudioDuration>()
+                    .copied();
+                let failed = match audio_duration {
+                    // 停止された場合は`PlayMode::Stop`になるため、`PlayMode::End`のみを確認する
+                    Some(duration) => {
+                        state.playing == PlayMode::End
+                            && state.position + TRACK_END_TOLERANCE < duration
+                    }
+                    None => false,
+                };
+                self.0.on_track_end(failed).await;
+            }
+        }
+
+        None
+    }
+}
+
+/// 複数のイベントに同じハンドラを登録するため、共有したハンドラに処理を任せる
+struct SharedEventHandler(Arc<dyn EventHandler>);
 
-    Ok(())
+#[async_trait]
+impl EventHandler for SharedEventHandler {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        self.0.act(ctx).await
+    }
 }
 
-pub async fn leave(ctx: &Context, guild_id: impl Into<GuildId>) -> Result<()> {
-    let manager = extract_songbird(ctx).await?;
-    let guild_id = guild_id.into();
+#[derive(Debug, Clone, Default)]
+pub struct QueueStatus {
+    /// キューに入っている音声の数（再生中のものを含む）
+    pub len: usize,
+    /// キューの先頭にある音声が追加された時刻
+    pub oldest_enqueued_at: Option<Instant>,
+}
 
-    manager.remove(guild_id).await?;
+/// 再生位置が音声の長さにこれだけ満たない場合に、再生に失敗したとみなす
+const TRACK_END_TOLERANCE: Duration = Duration::from_millis(500);
 
-    Ok(())
+/// 48kHz・モノラル・16bitのPCMの長さを返す
+fn pcm_duration(len: usize) -> Duration {
+    const BYTES_PER_SECOND: u64 = 48000 * 2;
+    Duration::from_millis(len as u64 * 1000 / BYTES_PER_SECOND)
 }
 
-pub async fn is_connected(ctx: &Context, guild_id: impl Into<GuildId>) -> Result<bool> {
-    let manager = extract_songbird(ctx).await?;
-    let guild_id = guild_id.into();
+struct AudioDuration;
 
-    let is_connected = manager.get(guild_id).is_some();
+impl TypeMapKey for AudioDuration {
+    type Value = Duration;
+}
 
-    Ok(is_connected)
+/// 1件の読み上げを区切って追加したトラックに共通する値
+/// スキップされたときに`true`にして、続きの音声を追加しないようにする
+struct SpeechGroup;
+
+impl TypeMapKey for SpeechGroup {
+    type Value = Arc<AtomicBool>;
 }
 
-pub async fn enqueue(
-    ctx: &Context,
-    guild_id: impl Into<GuildId>,
-    raw_audio: Vec<u8>,
-) -> Result<()> {
-    let manager = extract_songbird(ctx).await?;
-    let call = get_call(manager, guild_id).await?;
-
-    let mut handler = call.lock().await;
-    handler.enqueue_source(Input::new(
-        false,
-        Reader::from_memory(raw_audio),
-        Codec::Pcm,
-        Container::Raw,
-        None,
-    ));
-
-    Ok(())
+struct EnqueuedAt;
+
+impl TypeMapKey for EnqueuedAt {
+    type Value = Instant;
 }
 
-pub async fn skip(ctx: &Context, guild_id: impl Into<GuildId>) -> Result<()> {
-    let manager = extract_songbird(ctx).await?;
-    let call = get_call(manager, guild_id).await?;
+struct NotifyEnd(StdMutex<Option<oneshot::Sender<()>>>);
 
-    let handler = call.lock().await;
-    let current_track = handler.queue().current();
+#[async_trait]
+impl EventHandler for NotifyEnd {
+    async fn act(&self, _ctx: &EventContext<'_>) -> Option<Event> {
+        if let Some(tx) = self.0.lock().ok().and_then(|mut tx| tx.take()) {
+            let _ = tx.send(());
+        }
 
-    if let Some(track) = current_track {
-        track.stop().context("Failed to stop current track")?;
+        None
     }
+}
+
+struct StopTrack;
 
-    Ok(())
+#[async_trait]
+impl EventHandler for StopTrack {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        if let EventContext::Track(tracks) = ctx {
+            for (_, handle) in *tracks {
+                // すでに再生が終了している場合は失敗するが、問題はない
+                let _ = handle.stop();
+            }
+        }
+
+        None
+    }
 }
 
 async fn extract_songbird(ctx: &Context) -> Result<Arc<Songbird>> {
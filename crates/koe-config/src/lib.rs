@@ -1,22 +1,99 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use serde::Deserialize;
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Config {
     pub discord: DiscordConfig,
-    pub voicevox: VoicevoxConfig,
+    /// VOICEVOX ENGINEで音声を合成する場合に指定する
+    /// `voicevox`・`open_jtalk`・`azure`・`polly`のうち少なくとも1つを指定し、複数指定した場合はサーバーごとに選択できる
+    #[serde(default)]
+    pub voicevox: Option<VoicevoxConfig>,
+    /// Open JTalkで音声を合成する場合に指定する
+    #[serde(default)]
+    pub open_jtalk: Option<OpenJTalkConfig>,
+    /// Azure Cognitive Services Speechで音声を合成する場合に指定する
+    #[serde(default)]
+    pub azure: Option<AzureConfig>,
+    /// Amazon Pollyで音声を合成する場合に指定する
+    #[serde(default)]
+    pub polly: Option<PollyConfig>,
     pub redis: RedisConfig,
+    #[serde(default)]
+    pub reading: ReadingConfig,
+    #[serde(default)]
+    pub call: CallConfig,
+    #[serde(default)]
+    pub startup: StartupConfig,
+    #[serde(default)]
+    pub alert: AlertConfig,
+    #[serde(default)]
+    pub logging: LoggingConfig,
+    #[serde(default)]
+    pub audio_cache: AudioCacheConfig,
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct DiscordConfig {
     pub client_id: u64,
     pub bot_token: String,
+    /// 開発用のサーバーID、指定した場合はスラッシュコマンドをこのサーバーのみに登録する
+    #[serde(default)]
+    pub dev_guild_id: Option<u64>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct VoicevoxConfig {
     pub api_base: String,
+    /// `api_base`のエンジンが応答しない場合に、順に試すエンジンのURL
+    #[serde(default)]
+    pub fallback_api_bases: Vec<String>,
+    /// 失敗したエンジンを後回しにする時間（秒）
+    #[serde(default = "default_fallback_cooldown")]
+    pub fallback_cooldown: u64,
+    /// 同時に行う音声合成の最大の数、0の場合は制限しない
+    #[serde(default)]
+    pub max_concurrency: usize,
+    /// 受け取る音声の最大のサイズ（KiB）、0の場合は制限しない
+    #[serde(default = "default_max_audio_size")]
+    pub max_audio_size: usize,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OpenJTalkConfig {
+    /// Open JTalkの実行ファイル、パスを含まない場合は`PATH`から探す
+    #[serde(default = "default_open_jtalk_binary")]
+    pub binary: String,
+    /// 辞書のディレクトリ
+    pub dictionary_dir: String,
+    /// `.htsvoice`ファイルを置いたディレクトリ
+    pub voice_dir: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AzureConfig {
+    pub subscription_key: String,
+    /// リソースのリージョン（例: `japaneast`）
+    pub region: String,
+    /// 使用する声の言語
+    #[serde(default = "default_azure_locale")]
+    pub locale: String,
+    /// 使用する声の名前（例: `ja-JP-NanamiNeural`）、省略した場合は`locale`のすべての声を名前の順に使う
+    /// プリセットIDはこの順に1から割り当てるため、声を追加する場合は末尾に加える
+    #[serde(default)]
+    pub voices: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PollyConfig {
+    /// リージョン（例: `ap-northeast-1`）
+    pub region: String,
+    /// アクセスキー、省略した場合はAWS SDKの既定の方法で認証情報を探す
+    #[serde(default)]
+    pub access_key_id: Option<String>,
+    #[serde(default)]
+    pub secret_access_key: Option<String>,
+    #[serde(default)]
+    pub session_token: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -24,6 +101,181 @@ pub struct RedisConfig {
     pub url: String,
 }
 
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReadingConfig {
+    /// 日時を読み上げる際に使用するタイムゾーン（UTCからのオフセット、例: `+09:00`）
+    #[serde(default = "default_timezone")]
+    pub timezone: String,
+    /// 1件のメッセージを読み上げる最大の時間（秒）、0の場合は制限しない
+    #[serde(default = "default_max_speech_duration")]
+    pub max_speech_duration: u64,
+    /// サーバーごとのシステム音声が未設定の場合に使う声（プリセットID）、省略時はIDが最小のプリセット
+    #[serde(default)]
+    pub system_voice_preset_id: Option<i64>,
+    /// 読み上げが行われないまま、この時間（分）が経過するとボイスチャンネルから退出する、0の場合は退出しない
+    #[serde(default = "default_idle_timeout")]
+    pub idle_timeout: u64,
+}
+
+impl Default for ReadingConfig {
+    fn default() -> Self {
+        Self {
+            timezone: default_timezone(),
+            max_speech_duration: default_max_speech_duration(),
+            system_voice_preset_id: None,
+            idle_timeout: default_idle_timeout(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CallConfig {
+    /// スピーカーミュートの状態でボイスチャンネルに接続するかどうか
+    #[serde(default = "default_self_deaf")]
+    pub self_deaf: bool,
+    /// 同時に接続できるボイスチャンネルの最大数、0の場合は制限しない
+    #[serde(default)]
+    pub max_connections: usize,
+    /// 予期せず切断された際に再接続を試みる最大の回数、0の場合は再接続しない
+    #[serde(default = "default_max_reconnect_attempts")]
+    pub max_reconnect_attempts: u32,
+}
+
+impl Default for CallConfig {
+    fn default() -> Self {
+        Self {
+            self_deaf: default_self_deaf(),
+            max_connections: 0,
+            max_reconnect_attempts: default_max_reconnect_attempts(),
+        }
+    }
+}
+
+/// ボイスチャンネルへの再接続に失敗し続けた場合などに、Botの運営者へ通知する先
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AlertConfig {
+    /// 通知を送信するテキストチャンネルのID
+    #[serde(default)]
+    pub channel_id: Option<u64>,
+    /// 通知を送信するWebhookのURL
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LoggingConfig {
+    /// 同じ種類のエラーを、サーバーごとにこの時間（秒）に1件だけ記録する、0の場合はすべて記録する
+    #[serde(default = "default_error_rate_limit_window")]
+    pub error_rate_limit_window: u64,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            error_rate_limit_window: default_error_rate_limit_window(),
+        }
+    }
+}
+
+/// 合成した音声をディスクに保存し、再起動した後も同じ文章の音声合成を省く
+#[derive(Debug, Clone, Deserialize)]
+pub struct AudioCacheConfig {
+    /// 音声を保存するディレクトリ、省略した場合は保存しない
+    #[serde(default)]
+    pub dir: Option<String>,
+    /// 保存する音声の合計の最大のサイズ（MiB）
+    #[serde(default = "default_audio_cache_max_size")]
+    pub max_size: u64,
+}
+
+impl Default for AudioCacheConfig {
+    fn default() -> Self {
+        Self {
+            dir: None,
+            max_size: default_audio_cache_max_size(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct StartupConfig {
+    /// 起動直後にサーバーごとの処理を同時に実行する数
+    #[serde(default = "default_startup_concurrency")]
+    pub concurrency: usize,
+    /// サーバーごとの処理を始める前に待つ時間の上限（ミリ秒）、0の場合は待たない
+    #[serde(default = "default_startup_jitter")]
+    pub jitter: u64,
+    /// レート制限などで失敗した場合に再試行する最大の回数
+    #[serde(default = "default_startup_max_retries")]
+    pub max_retries: u32,
+}
+
+impl Default for StartupConfig {
+    fn default() -> Self {
+        Self {
+            concurrency: default_startup_concurrency(),
+            jitter: default_startup_jitter(),
+            max_retries: default_startup_max_retries(),
+        }
+    }
+}
+
+fn default_timezone() -> String {
+    "+09:00".to_string()
+}
+
+fn default_max_speech_duration() -> u64 {
+    30
+}
+
+fn default_idle_timeout() -> u64 {
+    30
+}
+
+fn default_self_deaf() -> bool {
+    true
+}
+
+fn default_fallback_cooldown() -> u64 {
+    60
+}
+
+fn default_max_audio_size() -> usize {
+    10 * 1024
+}
+
+fn default_open_jtalk_binary() -> String {
+    "open_jtalk".to_string()
+}
+
+fn default_azure_locale() -> String {
+    "ja-JP".to_string()
+}
+
+fn default_max_reconnect_attempts() -> u32 {
+    5
+}
+
+fn default_error_rate_limit_window() -> u64 {
+    60
+}
+
+fn default_audio_cache_max_size() -> u64 {
+    1024
+}
+
+fn default_startup_concurrency() -> usize {
+    2
+}
+
+fn default_startup_jitter() -> u64 {
+    1000
+}
+
+fn default_startup_max_retries() -> u32 {
+    5
+}
+
 pub async fn load() -> Result<Config> {
     let config_path = std::env::var("KOE_CONFIG").unwrap_or_else(|_| "/etc/koe.yaml".to_string());
 
@@ -31,7 +283,25 @@ pub async fn load() -> Result<Config> {
         .await
         .with_context(|| format!("Failed to load config file from {}", config_path))?;
 
-    let config = serde_yaml::from_str::<Config>(&yaml).context("Failed to parse config file")?;
+    let mut config =
+        serde_yaml::from_str::<Config>(&yaml).context("Failed to parse config file")?;
+
+    if config.voicevox.is_none()
+        && config.open_jtalk.is_none()
+        && config.azure.is_none()
+        && config.polly.is_none()
+    {
+        bail!("At least one of voicevox, open_jtalk, azure and polly must be configured");
+    }
+
+    // 設定ファイルを書き換えずに開発用のサーバーを切り替えられるよう、環境変数を優先する
+    if let Ok(dev_guild_id) = std::env::var("KOE_DEV_GUILD_ID") {
+        config.discord.dev_guild_id = Some(
+            dev_guild_id
+                .parse()
+                .with_context(|| format!("Invalid KOE_DEV_GUILD_ID: {}", dev_guild_id))?,
+        );
+    }
 
     Ok(config)
 }
@@ -0,0 +1,73 @@
+use anyhow::Result;
+use redis::aio::Connection;
+use redis::AsyncCommands;
+
+#[derive(Debug, Clone)]
+pub struct AutoJoinSetting {
+    pub voice_channel_id: u64,
+    pub text_channel_id: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct GetOption {
+    pub guild_id: u64,
+}
+
+/// 自動接続の設定を返す
+/// 自動接続が無効の場合は[`None`]を返す
+pub async fn get(
+    connection: &mut Connection,
+    option: GetOption,
+) -> Result<Option<AutoJoinSetting>> {
+    let (voice_channel_id, text_channel_id): (Option<u64>, Option<u64>) = redis::cmd("HMGET")
+        .arg(auto_join_key(option.guild_id))
+        .arg("voice_channel")
+        .arg("text_channel")
+        .query_async(connection)
+        .await?;
+
+    Ok(match (voice_channel_id, text_channel_id) {
+        (Some(voice_channel_id), Some(text_channel_id)) => Some(AutoJoinSetting {
+            voice_channel_id,
+            text_channel_id,
+        }),
+        _ => None,
+    })
+}
+
+#[derive(Debug, Clone)]
+pub struct SetOption {
+    pub guild_id: u64,
+    pub setting: AutoJoinSetting,
+}
+
+/// 自動接続を有効にする
+pub async fn set(connection: &mut Connection, option: SetOption) -> Result<()> {
+    connection
+        .hset_multiple::<_, _, _, ()>(
+            auto_join_key(option.guild_id),
+            &[
+                ("voice_channel", option.setting.voice_channel_id),
+                ("text_channel", option.setting.text_channel_id),
+            ],
+        )
+        .await?;
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+pub struct RemoveOption {
+    pub guild_id: u64,
+}
+
+/// 自動接続を無効にする
+pub async fn remove(connection: &mut Connection, option: RemoveOption) -> Result<()> {
+    connection
+        .del::<_, ()>(auto_join_key(option.guild_id))
+        .await?;
+    Ok(())
+}
+
+fn auto_join_key(guild_id: u64) -> String {
+    format!("guild:{}:auto_join", guild_id)
+}
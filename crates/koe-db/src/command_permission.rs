@@ -0,0 +1,105 @@
+use anyhow::Result;
+use redis::aio::Connection;
+use redis::AsyncCommands;
+
+/// コマンドを使うために必要な条件
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Requirement {
+    /// 誰でも使える
+    Everyone,
+    /// Botと同じボイスチャンネル（Botが接続していない場合はいずれかのボイスチャンネル）に接続している
+    InVoiceChannel,
+    /// 指定したロールを持っている
+    Role(u64),
+    /// 「サーバー管理」の権限を持っている
+    ManageGuild,
+}
+
+impl Requirement {
+    pub fn to_field_value(self) -> String {
+        match self {
+            Requirement::Everyone => "everyone".to_string(),
+            Requirement::InVoiceChannel => "in_voice_channel".to_string(),
+            Requirement::Role(role_id) => format!("role:{}", role_id),
+            Requirement::ManageGuild => "manage_guild".to_string(),
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "everyone" => Some(Requirement::Everyone),
+            "in_voice_channel" => Some(Requirement::InVoiceChannel),
+            "manage_guild" => Some(Requirement::ManageGuild),
+            _ => s
+                .strip_prefix("role:")
+                .and_then(|id| id.parse().ok())
+                .map(Requirement::Role),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GetOption {
+    pub guild_id: u64,
+    pub command: String,
+}
+
+/// コマンドを使うために必要な条件を返す
+/// 未設定の場合は[`Requirement::Everyone`]を返す
+pub async fn get(connection: &mut Connection, option: GetOption) -> Result<Requirement> {
+    let resp: Option<String> = connection
+        .hget(command_permission_key(option.guild_id), option.command)
+        .await?;
+    Ok(resp
+        .as_deref()
+        .and_then(Requirement::parse)
+        .unwrap_or(Requirement::Everyone))
+}
+
+#[derive(Debug, Clone)]
+pub struct GetAllOption {
+    pub guild_id: u64,
+}
+
+/// 条件が設定されたコマンドと、その条件をすべて返す
+/// 未設定のコマンドは含まない
+pub async fn get_all(
+    connection: &mut Connection,
+    option: GetAllOption,
+) -> Result<Vec<(String, Requirement)>> {
+    let resp: Vec<(String, String)> = connection
+        .hgetall(command_permission_key(option.guild_id))
+        .await?;
+    Ok(resp
+        .into_iter()
+        .filter_map(|(command, value)| Some((command, Requirement::parse(&value)?)))
+        .collect())
+}
+
+#[derive(Debug, Clone)]
+pub struct SetOption {
+    pub guild_id: u64,
+    pub command: String,
+    pub requirement: Requirement,
+}
+
+/// コマンドを使うために必要な条件を設定する
+/// [`Requirement::Everyone`]を設定した場合は、設定を削除して既定の状態に戻す
+pub async fn set(connection: &mut Connection, option: SetOption) -> Result<()> {
+    let key = command_permission_key(option.guild_id);
+    match option.requirement {
+        Requirement::Everyone => {
+            connection.hdel::<_, _, ()>(key, option.command).await?;
+        }
+        requirement => {
+            connection
+                .hset::<_, _, _, ()>(key, option.command, requirement.to_field_value())
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+fn command_permission_key(guild_id: u64) -> String {
+    format!("guild:{}:command_permissions", guild_id)
+}
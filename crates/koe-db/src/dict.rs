@@ -28,6 +28,33 @@ pub async fn insert(connection: &mut Connection, option: InsertOption) -> Result
     })
 }
 
+#[derive(Debug, Clone)]
+pub struct UpsertOption {
+    pub guild_id: u64,
+    pub word: String,
+    pub read_as: String,
+}
+
+#[derive(Debug, Clone)]
+pub enum UpsertResponse {
+    Inserted,
+    Updated,
+}
+
+/// 辞書に語句を追加する
+/// すでに登録されている場合は読み方を上書きする
+pub async fn upsert(connection: &mut Connection, option: UpsertOption) -> Result<UpsertResponse> {
+    let resp = connection
+        .hset(dict_key(option.guild_id), option.word, option.read_as)
+        .await?;
+
+    Ok(match resp {
+        0 => UpsertResponse::Updated,
+        1 => UpsertResponse::Inserted,
+        x => bail!("Unknown HSET response from Redis: {}", x),
+    })
+}
+
 #[derive(Debug, Clone)]
 pub struct RemoveOption {
     pub guild_id: u64,
@@ -68,6 +95,36 @@ pub async fn get_all(
     Ok(resp)
 }
 
+#[derive(Debug, Clone)]
+pub struct CountOption {
+    pub guild_id: u64,
+}
+
+/// 辞書に登録された語句の数を返す
+/// 辞書が存在しないときは0を返す
+pub async fn count(connection: &mut Connection, option: CountOption) -> Result<usize> {
+    let resp = connection.hlen(dict_key(option.guild_id)).await?;
+    Ok(resp)
+}
+
+#[derive(Debug, Clone)]
+pub struct ClearOption {
+    pub guild_id: u64,
+}
+
+/// 辞書に登録されたすべての語句を削除し、削除した語句の数を返す
+pub async fn clear(connection: &mut Connection, option: ClearOption) -> Result<usize> {
+    let key = dict_key(option.guild_id);
+    let (count,) = redis::pipe()
+        .atomic()
+        .hlen(&key)
+        .del(&key)
+        .ignore()
+        .query_async(connection)
+        .await?;
+    Ok(count)
+}
+
 fn dict_key(guild_id: u64) -> String {
     format!("guild:{}:dict", guild_id)
 }
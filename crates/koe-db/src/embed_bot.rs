@@ -0,0 +1,82 @@
+use anyhow::{bail, Result};
+use redis::aio::Connection;
+use redis::AsyncCommands;
+
+#[derive(Debug, Clone)]
+pub struct AddOption {
+    pub guild_id: u64,
+    pub user_id: u64,
+}
+
+#[derive(Debug, Clone)]
+pub enum AddResponse {
+    Success,
+    AlreadyExists,
+}
+
+/// 埋め込みを読み上げるBotを追加する
+pub async fn add(connection: &mut Connection, option: AddOption) -> Result<AddResponse> {
+    let resp = connection
+        .sadd(embed_bot_key(option.guild_id), option.user_id)
+        .await?;
+
+    Ok(match resp {
+        0 => AddResponse::AlreadyExists,
+        1 => AddResponse::Success,
+        x => bail!("Unknown SADD response from Redis: {}", x),
+    })
+}
+
+#[derive(Debug, Clone)]
+pub struct RemoveOption {
+    pub guild_id: u64,
+    pub user_id: u64,
+}
+
+#[derive(Debug, Clone)]
+pub enum RemoveResponse {
+    Success,
+    DoesNotExist,
+}
+
+/// 埋め込みを読み上げるBotを削除する
+pub async fn remove(connection: &mut Connection, option: RemoveOption) -> Result<RemoveResponse> {
+    let resp = connection
+        .srem(embed_bot_key(option.guild_id), option.user_id)
+        .await?;
+
+    Ok(match resp {
+        0 => RemoveResponse::DoesNotExist,
+        1 => RemoveResponse::Success,
+        x => bail!("Unknown SREM response from Redis: {}", x),
+    })
+}
+
+#[derive(Debug, Clone)]
+pub struct GetAllOption {
+    pub guild_id: u64,
+}
+
+/// 埋め込みを読み上げるBotをすべて返す
+pub async fn get_all(connection: &mut Connection, option: GetAllOption) -> Result<Vec<u64>> {
+    let resp = connection.smembers(embed_bot_key(option.guild_id)).await?;
+    Ok(resp)
+}
+
+#[derive(Debug, Clone)]
+pub struct ContainsOption {
+    pub guild_id: u64,
+    pub user_id: u64,
+}
+
+/// 埋め込みを読み上げるBotに含まれているかを返す
+pub async fn contains(connection: &mut Connection, option: ContainsOption) -> Result<bool> {
+    let resp = connection
+        .sismember(embed_bot_key(option.guild_id), option.user_id)
+        .await?;
+    Ok(resp)
+}
+
+fn embed_bot_key(guild_id: u64) -> String {
+    format!("guild:{}:embed_bots", guild_id)
+}
@@ -0,0 +1,49 @@
+use anyhow::Result;
+use redis::aio::Connection;
+use redis::AsyncCommands;
+
+/// 代わりに読み上げる文言の最大文字数
+pub const MAX_PLACEHOLDER_LENGTH: usize = 30;
+
+#[derive(Debug, Clone)]
+pub struct GetOption {
+    pub guild_id: u64,
+}
+
+/// 読み上げる内容が空になったメッセージの代わりに読み上げる文言を返す
+/// 未設定の場合（メッセージを読み飛ばす場合）は[`None`]を返す
+pub async fn get(connection: &mut Connection, option: GetOption) -> Result<Option<String>> {
+    let resp: Option<String> = connection.get(empty_text_key(option.guild_id)).await?;
+    Ok(resp)
+}
+
+#[derive(Debug, Clone)]
+pub struct SetOption {
+    pub guild_id: u64,
+    pub placeholder: String,
+}
+
+/// 読み上げる内容が空になったメッセージの代わりに読み上げる文言を設定する
+pub async fn set(connection: &mut Connection, option: SetOption) -> Result<()> {
+    connection
+        .set::<_, _, ()>(empty_text_key(option.guild_id), option.placeholder)
+        .await?;
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+pub struct RemoveOption {
+    pub guild_id: u64,
+}
+
+/// 代わりに読み上げる文言を削除し、読み上げる内容が空になったメッセージを読み飛ばすようにする
+pub async fn remove(connection: &mut Connection, option: RemoveOption) -> Result<()> {
+    connection
+        .del::<_, ()>(empty_text_key(option.guild_id))
+        .await?;
+    Ok(())
+}
+
+fn empty_text_key(guild_id: u64) -> String {
+    format!("guild:{}:empty_text_placeholder", guild_id)
+}
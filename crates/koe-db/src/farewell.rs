@@ -0,0 +1,49 @@
+use anyhow::Result;
+use redis::aio::Connection;
+use redis::AsyncCommands;
+
+/// 退出時に読み上げる挨拶の最大文字数
+pub const MAX_FAREWELL_LENGTH: usize = 30;
+
+#[derive(Debug, Clone)]
+pub struct GetOption {
+    pub guild_id: u64,
+}
+
+/// ボイスチャンネルから退出する前に読み上げる挨拶を返す
+/// 未設定の場合（挨拶せずに退出する場合）は[`None`]を返す
+pub async fn get(connection: &mut Connection, option: GetOption) -> Result<Option<String>> {
+    let resp: Option<String> = connection.get(farewell_key(option.guild_id)).await?;
+    Ok(resp)
+}
+
+#[derive(Debug, Clone)]
+pub struct SetOption {
+    pub guild_id: u64,
+    pub text: String,
+}
+
+/// ボイスチャンネルから退出する前に読み上げる挨拶を設定する
+pub async fn set(connection: &mut Connection, option: SetOption) -> Result<()> {
+    connection
+        .set::<_, _, ()>(farewell_key(option.guild_id), option.text)
+        .await?;
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+pub struct RemoveOption {
+    pub guild_id: u64,
+}
+
+/// 挨拶を削除し、挨拶せずに退出するようにする
+pub async fn remove(connection: &mut Connection, option: RemoveOption) -> Result<()> {
+    connection
+        .del::<_, ()>(farewell_key(option.guild_id))
+        .await?;
+    Ok(())
+}
+
+fn farewell_key(guild_id: u64) -> String {
+    format!("guild:{}:farewell", guild_id)
+}
@@ -0,0 +1,146 @@
+use anyhow::{bail, Result};
+use redis::aio::Connection;
+use redis::AsyncCommands;
+
+/// フィルターに登録できる語句の最大数
+pub const MAX_ENTRIES: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterMode {
+    /// 語句を読み上げない
+    Drop,
+    /// 語句を「ピー」に置き換える
+    Bleep,
+}
+
+impl FilterMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FilterMode::Drop => "drop",
+            FilterMode::Bleep => "bleep",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "drop" => Some(FilterMode::Drop),
+            "bleep" => Some(FilterMode::Bleep),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AddOption {
+    pub guild_id: u64,
+    pub word: String,
+}
+
+#[derive(Debug, Clone)]
+pub enum AddResponse {
+    Success,
+    WordAlreadyExists,
+    TooManyEntries,
+}
+
+/// フィルターに語句を追加する
+/// ASCII文字は小文字に揃えて保存する
+pub async fn add(connection: &mut Connection, option: AddOption) -> Result<AddResponse> {
+    let key = filter_key(option.guild_id);
+
+    let count: usize = connection.scard(&key).await?;
+    if count >= MAX_ENTRIES {
+        return Ok(AddResponse::TooManyEntries);
+    }
+
+    let resp = connection
+        .sadd(&key, option.word.to_ascii_lowercase())
+        .await?;
+
+    Ok(match resp {
+        0 => AddResponse::WordAlreadyExists,
+        1 => AddResponse::Success,
+        x => bail!("Unknown SADD response from Redis: {}", x),
+    })
+}
+
+#[derive(Debug, Clone)]
+pub struct RemoveOption {
+    pub guild_id: u64,
+    pub word: String,
+}
+
+#[derive(Debug, Clone)]
+pub enum RemoveResponse {
+    Success,
+    WordDoesNotExist,
+}
+
+/// フィルターから語句を削除する
+pub async fn remove(connection: &mut Connection, option: RemoveOption) -> Result<RemoveResponse> {
+    let resp = connection
+        .srem(
+            filter_key(option.guild_id),
+            option.word.to_ascii_lowercase(),
+        )
+        .await?;
+
+    Ok(match resp {
+        0 => RemoveResponse::WordDoesNotExist,
+        1 => RemoveResponse::Success,
+        x => bail!("Unknown SREM response from Redis: {}", x),
+    })
+}
+
+#[derive(Debug, Clone)]
+pub struct GetAllOption {
+    pub guild_id: u64,
+}
+
+/// フィルターに登録された語句をすべて返す
+/// フィルターが存在しないときは空の[`Vec`]を返す
+pub async fn get_all(connection: &mut Connection, option: GetAllOption) -> Result<Vec<String>> {
+    let mut resp: Vec<String> = connection.smembers(filter_key(option.guild_id)).await?;
+    resp.sort();
+    Ok(resp)
+}
+
+#[derive(Debug, Clone)]
+pub struct GetModeOption {
+    pub guild_id: u64,
+}
+
+/// フィルターのモードを返す
+/// 未設定の場合は[`FilterMode::Bleep`]を返す
+pub async fn get_mode(connection: &mut Connection, option: GetModeOption) -> Result<FilterMode> {
+    let resp: Option<String> = connection.get(mode_key(option.guild_id)).await?;
+
+    let mode = resp
+        .as_deref()
+        .and_then(FilterMode::parse)
+        .unwrap_or(FilterMode::Bleep);
+
+    Ok(mode)
+}
+
+#[derive(Debug, Clone)]
+pub struct SetModeOption {
+    pub guild_id: u64,
+    pub mode: FilterMode,
+}
+
+/// フィルターのモードを設定する
+pub async fn set_mode(connection: &mut Connection, option: SetModeOption) -> Result<()> {
+    connection
+        .set::<_, _, ()>(mode_key(option.guild_id), option.mode.as_str())
+        .await?;
+    Ok(())
+}
+
+fn filter_key(guild_id: u64) -> String {
+    format!("guild:{}:filter", guild_id)
+}
+
+fn mode_key(guild_id: u64) -> String {
+    format!("guild:{}:filter:mode", guild_id)
+}
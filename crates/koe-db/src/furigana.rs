@@ -0,0 +1,37 @@
+/// メッセージの中で読み仮名を指定する書き方
+/// [`crate::guild_settings::ChoiceKey::FuriganaSyntax`]の値として保存する
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FuriganaSyntax {
+    /// `{漢字|かんじ}`
+    Braces,
+    /// `｜漢字《かんじ》`（`|`は半角でもよい）
+    Ruby,
+}
+
+impl FuriganaSyntax {
+    /// 保存する値の一覧
+    pub const NAMES: &'static [&'static str] = &["braces", "ruby"];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FuriganaSyntax::Braces => "braces",
+            FuriganaSyntax::Ruby => "ruby",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "braces" => Some(FuriganaSyntax::Braces),
+            "ruby" => Some(FuriganaSyntax::Ruby),
+            _ => None,
+        }
+    }
+
+    /// 設定の値から書き方を返す
+    /// 未設定の場合や解釈できない値の場合は[`FuriganaSyntax::Braces`]を返す
+    pub fn from_setting(value: Option<&str>) -> Self {
+        value
+            .and_then(FuriganaSyntax::parse)
+            .unwrap_or(FuriganaSyntax::Braces)
+    }
+}
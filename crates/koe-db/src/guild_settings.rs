@@ -0,0 +1,1241 @@
+use anyhow::{bail, Result};
+use redis::aio::Connection;
+use redis::{AsyncCommands, FromRedisValue, Value};
+use std::{collections::HashMap, fmt, ops::RangeInclusive};
+
+/// 同じ文字の繰り返しをまとめる閾値として設定できる範囲
+pub const MIN_REPEAT_THRESHOLD: i64 = 1;
+pub const MAX_REPEAT_THRESHOLD: i64 = 20;
+/// 1件のメッセージで読み上げる最大の単語数として設定できる上限
+pub const MAX_MAX_WORDS: i64 = 200;
+/// メッセージの読み上げ方で、送信者の名前に置き換える部分
+pub const NAME_PLACEHOLDER: &str = "{name}";
+/// メッセージの読み上げ方で、メッセージの内容に置き換える部分
+pub const CONTENT_PLACEHOLDER: &str = "{content}";
+/// メッセージの読み上げ方として設定できる最大の文字数
+pub const MAX_MESSAGE_TEMPLATE_LENGTH: usize = 50;
+/// 読み上げが行われないまま経過すると退出する時間（分）として設定できる上限
+pub const MAX_IDLE_TIMEOUT_MINUTES: i64 = 24 * 60;
+
+/// サーバーの設定項目のうち、真偽値をとるもの
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoolKey {
+    /// リアクションを読み上げる
+    ReadReactions,
+    /// 同じ文字の繰り返しをまとめて読み上げる
+    CollapseRepeats,
+    /// 接続時と退出時にチャイムを鳴らす
+    Chime,
+    /// メンバー全員が別のボイスチャンネルに移動したときに、Botも移動する
+    FollowUsers,
+    /// 声が未設定のメンバーに、ユーザーIDで決まる声を割り当てる（無効な場合はランダムに割り当てる）
+    VoiceByUserId,
+    /// 読み上げ対象のチャンネルでメッセージが削除されたことを読み上げる
+    ReadDeletions,
+    /// 添付ファイルの代替テキストを読み上げる
+    ReadAltText,
+    /// 投票の質問と選択肢を読み上げる
+    ReadPolls,
+    /// メッセージの送信者の名前の前に、送信者のロールの名前を読み上げる
+    ReadAuthorRole,
+    /// コマンドへの応答をボイスチャンネルで読み上げる
+    ReadResponses,
+    /// コマンドへの応答を読み上げる場合に、エラーを伝える応答も読み上げる
+    ReadErrorResponses,
+    /// メッセージの中で指定された読み仮名で読み上げる
+    Furigana,
+    /// `/dict view`で語句の読み方を表示する
+    ShowDictReadings,
+    /// 接続中に、サーバーのイベントが始まったことを読み上げる
+    ReadScheduledEvents,
+    /// 辞書を適用する前に、全角の英数字を半角に、半角カタカナを全角に揃える
+    NormalizeWidth,
+    /// 接続中のボイスチャンネルのテキストチャットも読み上げる
+    ReadVoiceChat,
+}
+
+impl BoolKey {
+    fn field(&self) -> &'static str {
+        match self {
+            BoolKey::ReadReactions => "read_reactions",
+            BoolKey::CollapseRepeats => "collapse_repeats",
+            BoolKey::Chime => "chime",
+            BoolKey::FollowUsers => "follow_users",
+            BoolKey::VoiceByUserId => "voice_by_user_id",
+            BoolKey::ReadDeletions => "read_deletions",
+            BoolKey::ReadAltText => "read_alt_text",
+            BoolKey::ReadPolls => "read_polls",
+            BoolKey::ReadAuthorRole => "read_author_role",
+            BoolKey::ReadResponses => "read_responses",
+            BoolKey::ReadErrorResponses => "read_error_responses",
+            BoolKey::Furigana => "furigana",
+            BoolKey::ShowDictReadings => "show_dict_readings",
+            BoolKey::ReadScheduledEvents => "read_scheduled_events",
+            BoolKey::NormalizeWidth => "normalize_width",
+            BoolKey::ReadVoiceChat => "read_voice_chat",
+        }
+    }
+
+    /// 未設定の場合の値
+    pub fn default_value(&self) -> bool {
+        match self {
+            BoolKey::ReadReactions => false,
+            BoolKey::CollapseRepeats => true,
+            BoolKey::Chime => true,
+            BoolKey::FollowUsers => false,
+            BoolKey::VoiceByUserId => true,
+            BoolKey::ReadDeletions => false,
+            BoolKey::ReadAltText => false,
+            BoolKey::ReadPolls => false,
+            BoolKey::ReadAuthorRole => false,
+            BoolKey::ReadResponses => false,
+            BoolKey::ReadErrorResponses => false,
+            BoolKey::Furigana => false,
+            BoolKey::ShowDictReadings => true,
+            BoolKey::ReadScheduledEvents => false,
+            BoolKey::NormalizeWidth => false,
+            BoolKey::ReadVoiceChat => false,
+        }
+    }
+}
+
+/// サーバーの設定項目のうち、整数値をとるもの
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntKey {
+    /// 同じ文字がこの数を超えて続いた場合にまとめる
+    RepeatThreshold,
+    /// 1件のメッセージで読み上げる最大の単語数、0の場合は制限しない
+    MaxWords,
+}
+
+impl IntKey {
+    fn field(&self) -> &'static str {
+        match self {
+            IntKey::RepeatThreshold => "repeat_threshold",
+            IntKey::MaxWords => "max_words",
+        }
+    }
+
+    /// 未設定の場合の値
+    pub fn default_value(&self) -> i64 {
+        match self {
+            IntKey::RepeatThreshold => 3,
+            IntKey::MaxWords => 0,
+        }
+    }
+
+    /// 設定できる値の範囲
+    pub fn range(&self) -> RangeInclusive<i64> {
+        match self {
+            IntKey::RepeatThreshold => MIN_REPEAT_THRESHOLD..=MAX_REPEAT_THRESHOLD,
+            IntKey::MaxWords => 0..=MAX_MAX_WORDS,
+        }
+    }
+}
+
+/// サーバーの設定項目のうち、文字列をとるもの
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextKey {
+    /// 送信者の名前を読み上げる場合の、名前とメッセージの読み上げ方
+    MessageTemplate,
+}
+
+impl TextKey {
+    fn field(&self) -> &'static str {
+        match self {
+            TextKey::MessageTemplate => "message_template",
+        }
+    }
+
+    /// 未設定の場合の値
+    pub fn default_value(&self) -> &'static str {
+        match self {
+            TextKey::MessageTemplate => "{name}。{content}",
+        }
+    }
+
+    /// 設定できる最大の文字数
+    pub fn max_length(&self) -> usize {
+        match self {
+            TextKey::MessageTemplate => MAX_MESSAGE_TEMPLATE_LENGTH,
+        }
+    }
+
+    /// 値にちょうど1回含める必要がある部分
+    pub fn required_placeholder(&self) -> Option<&'static str> {
+        match self {
+            TextKey::MessageTemplate => Some(CONTENT_PLACEHOLDER),
+        }
+    }
+}
+
+/// サーバーの設定項目のうち、整数値をとり、未設定の場合はBot全体の設定に従うもの
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionalIntKey {
+    /// 読み上げが行われないまま経過するとボイスチャンネルから退出する時間（分）、0の場合は自動で退出しない
+    IdleTimeout,
+}
+
+impl OptionalIntKey {
+    fn field(&self) -> &'static str {
+        match self {
+            OptionalIntKey::IdleTimeout => "idle_timeout",
+        }
+    }
+
+    /// 設定できる値の範囲
+    pub fn range(&self) -> RangeInclusive<i64> {
+        match self {
+            OptionalIntKey::IdleTimeout => 0..=MAX_IDLE_TIMEOUT_MINUTES,
+        }
+    }
+}
+
+/// サーバーの設定項目のうち、決められた選択肢のいずれかをとるもの
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChoiceKey {
+    /// メッセージの中で読み仮名を指定する書き方（[`crate::furigana::FuriganaSyntax`]）
+    FuriganaSyntax,
+    /// 読み上げに使う音声合成のエンジンの名前（設定ファイルの項目名）
+    SpeechProvider,
+}
+
+impl ChoiceKey {
+    fn field(&self) -> &'static str {
+        match self {
+            ChoiceKey::FuriganaSyntax => "furigana_syntax",
+            ChoiceKey::SpeechProvider => "speech_provider",
+        }
+    }
+
+    /// 未設定の場合の値
+    /// [`None`]の場合は、Botの設定ファイルで決まる
+    pub fn default_value(&self) -> Option<&'static str> {
+        match self {
+            ChoiceKey::FuriganaSyntax => Some(crate::furigana::FuriganaSyntax::Braces.as_str()),
+            ChoiceKey::SpeechProvider => None,
+        }
+    }
+
+    /// 設定できる値
+    /// [`None`]の場合は、Botの設定ファイルで決まるため、呼び出し側で確かめる
+    pub fn choices(&self) -> Option<&'static [&'static str]> {
+        match self {
+            ChoiceKey::FuriganaSyntax => Some(crate::furigana::FuriganaSyntax::NAMES),
+            ChoiceKey::SpeechProvider => None,
+        }
+    }
+}
+
+/// サーバーの設定項目のうち、チャンネルIDをとるもの
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelKey {
+    /// 自動で退出したときなどのお知らせを送信するテキストチャンネル
+    /// 未設定の場合は、読み上げているテキストチャンネルに送信する
+    NoticeChannel,
+}
+
+impl ChannelKey {
+    fn field(&self) -> &'static str {
+        match self {
+            ChannelKey::NoticeChannel => "notice_channel",
+        }
+    }
+}
+
+/// サーバーの設定項目
+/// `/settings set`と`/settings reset`で変更できる項目と、専用のコマンドで変更する項目を含む
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingKey {
+    Bool(BoolKey),
+    Int(IntKey),
+    Text(TextKey),
+    OptionalInt(OptionalIntKey),
+    Choice(ChoiceKey),
+    Channel(ChannelKey),
+}
+
+impl SettingKey {
+    pub const ALL: [SettingKey; 23] = [
+        SettingKey::Bool(BoolKey::ReadReactions),
+        SettingKey::Bool(BoolKey::ReadDeletions),
+        SettingKey::Bool(BoolKey::ReadAltText),
+        SettingKey::Bool(BoolKey::ReadPolls),
+        SettingKey::Bool(BoolKey::ReadAuthorRole),
+        SettingKey::Bool(BoolKey::Chime),
+        SettingKey::Bool(BoolKey::FollowUsers),
+        SettingKey::Bool(BoolKey::VoiceByUserId),
+        SettingKey::Bool(BoolKey::CollapseRepeats),
+        SettingKey::Bool(BoolKey::Furigana),
+        SettingKey::Bool(BoolKey::ReadResponses),
+        SettingKey::Bool(BoolKey::ReadErrorResponses),
+        SettingKey::Bool(BoolKey::ShowDictReadings),
+        SettingKey::Bool(BoolKey::ReadScheduledEvents),
+        SettingKey::Bool(BoolKey::NormalizeWidth),
+        SettingKey::Bool(BoolKey::ReadVoiceChat),
+        SettingKey::Int(IntKey::RepeatThreshold),
+        SettingKey::Int(IntKey::MaxWords),
+        SettingKey::Text(TextKey::MessageTemplate),
+        SettingKey::OptionalInt(OptionalIntKey::IdleTimeout),
+        SettingKey::Choice(ChoiceKey::FuriganaSyntax),
+        SettingKey::Choice(ChoiceKey::SpeechProvider),
+        SettingKey::Channel(ChannelKey::NoticeChannel),
+    ];
+
+    /// 設定項目の名前（Redisのフィールド名）
+    pub fn name(&self) -> &'static str {
+        match self {
+            SettingKey::Bool(key) => key.field(),
+            SettingKey::Int(key) => key.field(),
+            SettingKey::Text(key) => key.field(),
+            SettingKey::OptionalInt(key) => key.field(),
+            SettingKey::Choice(key) => key.field(),
+            SettingKey::Channel(key) => key.field(),
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|key| key.name() == s)
+    }
+
+    /// 未設定の場合の値
+    pub fn default_value(&self) -> SettingValue {
+        match self {
+            SettingKey::Bool(key) => SettingValue::Bool(key.default_value()),
+            SettingKey::Int(key) => SettingValue::Int(key.default_value()),
+            SettingKey::Text(key) => SettingValue::Text(key.default_value().to_string()),
+            SettingKey::Choice(key) => match key.default_value() {
+                Some(value) => SettingValue::Text(value.to_string()),
+                None => SettingValue::Unset,
+            },
+            SettingKey::OptionalInt(_) | SettingKey::Channel(_) => SettingValue::Unset,
+        }
+    }
+
+    /// 文字列で指定された値を、設定項目の型に合わせて解釈する
+    pub fn parse_value(&self, s: &str) -> std::result::Result<SettingValue, ParseValueError> {
+        let s = s.trim();
+        match self {
+            SettingKey::Bool(_) => match s.to_ascii_lowercase().as_str() {
+                "true" | "on" | "yes" | "1" | "有効" => Ok(SettingValue::Bool(true)),
+                "false" | "off" | "no" | "0" | "無効" => Ok(SettingValue::Bool(false)),
+                _ => Err(ParseValueError::NotBool),
+            },
+            SettingKey::Int(key) => parse_int(s, key.range()),
+            SettingKey::OptionalInt(key) => parse_int(s, key.range()),
+            SettingKey::Choice(key) => {
+                let value = s.to_ascii_lowercase();
+                match key.choices() {
+                    Some(choices) if !choices.contains(&value.as_str()) => {
+                        Err(ParseValueError::NotChoice { choices })
+                    }
+                    _ => Ok(SettingValue::Text(value)),
+                }
+            }
+            SettingKey::Channel(_) => {
+                // `<#123>`の形式のメンションと、チャンネルIDのみのどちらも受け付ける
+                let id = s
+                    .strip_prefix("<#")
+                    .and_then(|s| s.strip_suffix('>'))
+                    .unwrap_or(s);
+                match id.parse::<u64>() {
+                    Ok(id) if id > 0 => Ok(SettingValue::Channel(id)),
+                    _ => Err(ParseValueError::NotChannel),
+                }
+            }
+            SettingKey::Text(key) => {
+                if s.chars().count() > key.max_length() {
+                    return Err(ParseValueError::TooLong {
+                        max: key.max_length(),
+                    });
+                }
+                if let Some(placeholder) = key.required_placeholder() {
+                    if s.matches(placeholder).count() != 1 {
+                        return Err(ParseValueError::MissingPlaceholder { placeholder });
+                    }
+                }
+                Ok(SettingValue::Text(s.to_string()))
+            }
+        }
+    }
+}
+
+fn parse_int(
+    s: &str,
+    range: RangeInclusive<i64>,
+) -> std::result::Result<SettingValue, ParseValueError> {
+    let value = s.parse::<i64>().map_err(|_| ParseValueError::NotInt)?;
+    if !range.contains(&value) {
+        return Err(ParseValueError::OutOfRange {
+            min: *range.start(),
+            max: *range.end(),
+        });
+    }
+    Ok(SettingValue::Int(value))
+}
+
+/// 設定の値
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SettingValue {
+    Bool(bool),
+    Int(i64),
+    Text(String),
+    Channel(u64),
+    /// 未設定で、既定値もない（Botの設定ファイルに従うか、既定の動作をする）
+    Unset,
+}
+
+/// 文字列で指定された値を解釈できなかった理由
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseValueError {
+    /// 真偽値をとる項目に、真偽値として解釈できない値が指定された
+    NotBool,
+    /// 整数値をとる項目に、整数として解釈できない値が指定された
+    NotInt,
+    /// 整数値が設定できる範囲外
+    OutOfRange { min: i64, max: i64 },
+    /// 文字列が設定できる最大の文字数を超えている
+    TooLong { max: usize },
+    /// 文字列に必要な部分が含まれていないか、2回以上含まれている
+    MissingPlaceholder { placeholder: &'static str },
+    /// 選択肢のいずれでもない
+    NotChoice { choices: &'static [&'static str] },
+    /// チャンネルのメンションやIDとして解釈できない
+    NotChannel,
+}
+
+impl fmt::Display for ParseValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseValueError::NotBool => write!(f, "expected a boolean value"),
+            ParseValueError::NotInt => write!(f, "expected an integer value"),
+            ParseValueError::OutOfRange { min, max } => {
+                write!(f, "expected an integer from {} to {}", min, max)
+            }
+            ParseValueError::TooLong { max } => {
+                write!(f, "expected at most {} characters", max)
+            }
+            ParseValueError::MissingPlaceholder { placeholder } => {
+                write!(f, "expected {} exactly once", placeholder)
+            }
+            ParseValueError::NotChoice { choices } => {
+                write!(f, "expected one of {}", choices.join(", "))
+            }
+            ParseValueError::NotChannel => write!(f, "expected a channel mention or ID"),
+        }
+    }
+}
+
+impl std::error::Error for ParseValueError {}
+
+#[derive(Debug, Clone)]
+pub struct GetBoolOption {
+    pub guild_id: u64,
+    pub key: BoolKey,
+}
+
+/// 設定の値を返す
+/// 未設定の場合は既定値を返す
+pub async fn get_bool(connection: &mut Connection, option: GetBoolOption) -> Result<bool> {
+    let resp: Option<bool> = connection
+        .hget(settings_key(option.guild_id), option.key.field())
+        .await?;
+
+    Ok(resp.unwrap_or_else(|| option.key.default_value()))
+}
+
+#[derive(Debug, Clone)]
+pub struct SetBoolOption {
+    pub guild_id: u64,
+    pub key: BoolKey,
+    pub value: bool,
+}
+
+/// 設定の値を変更する
+pub async fn set_bool(connection: &mut Connection, option: SetBoolOption) -> Result<()> {
+    connection
+        .hset::<_, _, _, ()>(
+            settings_key(option.guild_id),
+            option.key.field(),
+            option.value,
+        )
+        .await?;
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+pub struct GetIntOption {
+    pub guild_id: u64,
+    pub key: IntKey,
+}
+
+/// 設定の値を返す
+/// 未設定の場合は既定値を返す
+pub async fn get_int(connection: &mut Connection, option: GetIntOption) -> Result<i64> {
+    let resp: Option<i64> = connection
+        .hget(settings_key(option.guild_id), option.key.field())
+        .await?;
+
+    Ok(resp.unwrap_or_else(|| option.key.default_value()))
+}
+
+#[derive(Debug, Clone)]
+pub struct SetIntOption {
+    pub guild_id: u64,
+    pub key: IntKey,
+    pub value: i64,
+}
+
+/// 設定の値を変更する
+pub async fn set_int(connection: &mut Connection, option: SetIntOption) -> Result<()> {
+    connection
+        .hset::<_, _, _, ()>(
+            settings_key(option.guild_id),
+            option.key.field(),
+            option.value,
+        )
+        .await?;
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+pub struct GetTextOption {
+    pub guild_id: u64,
+    pub key: TextKey,
+}
+
+/// 設定の値を返す
+/// 未設定の場合は既定値を返す
+pub async fn get_text(connection: &mut Connection, option: GetTextOption) -> Result<String> {
+    let resp: Option<String> = connection
+        .hget(settings_key(option.guild_id), option.key.field())
+        .await?;
+
+    Ok(resp.unwrap_or_else(|| option.key.default_value().to_string()))
+}
+
+#[derive(Debug, Clone)]
+pub struct SetTextOption {
+    pub guild_id: u64,
+    pub key: TextKey,
+    pub value: String,
+}
+
+/// 設定の値を変更する
+pub async fn set_text(connection: &mut Connection, option: SetTextOption) -> Result<()> {
+    connection
+        .hset::<_, _, _, ()>(
+            settings_key(option.guild_id),
+            option.key.field(),
+            option.value,
+        )
+        .await?;
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+pub struct GetOptionalIntOption {
+    pub guild_id: u64,
+    pub key: OptionalIntKey,
+}
+
+/// 設定の値を返す
+/// 未設定の場合は[`None`]を返す
+pub async fn get_optional_int(
+    connection: &mut Connection,
+    option: GetOptionalIntOption,
+) -> Result<Option<i64>> {
+    let resp: Option<i64> = connection
+        .hget(settings_key(option.guild_id), option.key.field())
+        .await?;
+    Ok(resp)
+}
+
+#[derive(Debug, Clone)]
+pub struct SetOptionalIntOption {
+    pub guild_id: u64,
+    pub key: OptionalIntKey,
+    pub value: i64,
+}
+
+/// 設定の値を変更する
+pub async fn set_optional_int(
+    connection: &mut Connection,
+    option: SetOptionalIntOption,
+) -> Result<()> {
+    connection
+        .hset::<_, _, _, ()>(
+            settings_key(option.guild_id),
+            option.key.field(),
+            option.value,
+        )
+        .await?;
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+pub struct GetChoiceOption {
+    pub guild_id: u64,
+    pub key: ChoiceKey,
+}
+
+/// 設定の値を返す
+/// 未設定の場合は既定値を返し、既定値もない場合は[`None`]を返す
+pub async fn get_choice(
+    connection: &mut Connection,
+    option: GetChoiceOption,
+) -> Result<Option<String>> {
+    let resp: Option<String> = connection
+        .hget(settings_key(option.guild_id), option.key.field())
+        .await?;
+    Ok(choice_or_default(option.key, resp))
+}
+
+#[derive(Debug, Clone)]
+pub struct SetChoiceOption {
+    pub guild_id: u64,
+    pub key: ChoiceKey,
+    pub value: String,
+}
+
+/// 設定の値を変更する
+pub async fn set_choice(connection: &mut Connection, option: SetChoiceOption) -> Result<()> {
+    connection
+        .hset::<_, _, _, ()>(
+            settings_key(option.guild_id),
+            option.key.field(),
+            option.value,
+        )
+        .await?;
+    Ok(())
+}
+
+/// 保存された値が選択肢にない場合は、未設定として扱う
+fn choice_or_default(key: ChoiceKey, value: Option<String>) -> Option<String> {
+    value
+        .filter(|value| match key.choices() {
+            Some(choices) => choices.contains(&value.as_str()),
+            None => true,
+        })
+        .or_else(|| key.default_value().map(str::to_string))
+}
+
+#[derive(Debug, Clone)]
+pub struct GetChannelOption {
+    pub guild_id: u64,
+    pub key: ChannelKey,
+}
+
+/// 設定されたチャンネルのIDを返す
+/// 未設定の場合は[`None`]を返す
+pub async fn get_channel(
+    connection: &mut Connection,
+    option: GetChannelOption,
+) -> Result<Option<u64>> {
+    let resp: Option<u64> = connection
+        .hget(settings_key(option.guild_id), option.key.field())
+        .await?;
+    Ok(resp)
+}
+
+#[derive(Debug, Clone)]
+pub struct SetChannelOption {
+    pub guild_id: u64,
+    pub key: ChannelKey,
+    pub channel_id: u64,
+}
+
+/// 設定の値を変更する
+pub async fn set_channel(connection: &mut Connection, option: SetChannelOption) -> Result<()> {
+    connection
+        .hset::<_, _, _, ()>(
+            settings_key(option.guild_id),
+            option.key.field(),
+            option.channel_id,
+        )
+        .await?;
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+pub struct GetOption {
+    pub guild_id: u64,
+    pub key: SettingKey,
+}
+
+/// 設定の値を返す
+/// 未設定の場合は既定値を返す
+pub async fn get(connection: &mut Connection, option: GetOption) -> Result<SettingValue> {
+    let value = match option.key {
+        SettingKey::Bool(key) => SettingValue::Bool(
+            get_bool(
+                connection,
+                GetBoolOption {
+                    guild_id: option.guild_id,
+                    key,
+                },
+            )
+            .await?,
+        ),
+        SettingKey::Int(key) => SettingValue::Int(
+            get_int(
+                connection,
+                GetIntOption {
+                    guild_id: option.guild_id,
+                    key,
+                },
+            )
+            .await?,
+        ),
+        SettingKey::Text(key) => SettingValue::Text(
+            get_text(
+                connection,
+                GetTextOption {
+                    guild_id: option.guild_id,
+                    key,
+                },
+            )
+            .await?,
+        ),
+        SettingKey::OptionalInt(key) => get_optional_int(
+            connection,
+            GetOptionalIntOption {
+                guild_id: option.guild_id,
+                key,
+            },
+        )
+        .await?
+        .map_or(SettingValue::Unset, SettingValue::Int),
+        SettingKey::Choice(key) => get_choice(
+            connection,
+            GetChoiceOption {
+                guild_id: option.guild_id,
+                key,
+            },
+        )
+        .await?
+        .map_or(SettingValue::Unset, SettingValue::Text),
+        SettingKey::Channel(key) => get_channel(
+            connection,
+            GetChannelOption {
+                guild_id: option.guild_id,
+                key,
+            },
+        )
+        .await?
+        .map_or(SettingValue::Unset, SettingValue::Channel),
+    };
+    Ok(value)
+}
+
+#[derive(Debug, Clone)]
+pub struct SetOption {
+    pub guild_id: u64,
+    pub key: SettingKey,
+    pub value: SettingValue,
+}
+
+/// 設定の値を変更する
+/// 値の型が設定項目の型と異なる場合はエラーを返す
+pub async fn set(connection: &mut Connection, option: SetOption) -> Result<()> {
+    match (option.key, option.value) {
+        (SettingKey::Bool(key), SettingValue::Bool(value)) => {
+            set_bool(
+                connection,
+                SetBoolOption {
+                    guild_id: option.guild_id,
+                    key,
+                    value,
+                },
+            )
+            .await
+        }
+        (SettingKey::Int(key), SettingValue::Int(value)) => {
+            set_int(
+                connection,
+                SetIntOption {
+                    guild_id: option.guild_id,
+                    key,
+                    value,
+                },
+            )
+            .await
+        }
+        (SettingKey::Text(key), SettingValue::Text(value)) => {
+            set_text(
+                connection,
+                SetTextOption {
+                    guild_id: option.guild_id,
+                    key,
+                    value,
+                },
+            )
+            .await
+        }
+        (SettingKey::OptionalInt(key), SettingValue::Int(value)) => {
+            set_optional_int(
+                connection,
+                SetOptionalIntOption {
+                    guild_id: option.guild_id,
+                    key,
+                    value,
+                },
+            )
+            .await
+        }
+        (SettingKey::Choice(key), SettingValue::Text(value)) => {
+            set_choice(
+                connection,
+                SetChoiceOption {
+                    guild_id: option.guild_id,
+                    key,
+                    value,
+                },
+            )
+            .await
+        }
+        (SettingKey::Channel(key), SettingValue::Channel(channel_id)) => {
+            set_channel(
+                connection,
+                SetChannelOption {
+                    guild_id: option.guild_id,
+                    key,
+                    channel_id,
+                },
+            )
+            .await
+        }
+        (key, value) => bail!("Type mismatch for setting {}: {:?}", key.name(), value),
+    }
+}
+
+/// サーバーのすべての設定
+/// 1回の`HGETALL`で読み出し、未設定の項目は既定値として扱う
+#[derive(Debug, Clone, Default)]
+pub struct GuildSettings {
+    fields: HashMap<String, Value>,
+}
+
+impl GuildSettings {
+    pub fn get_bool(&self, key: BoolKey) -> bool {
+        self.decode(key.field())
+            .unwrap_or_else(|| key.default_value())
+    }
+
+    pub fn get_int(&self, key: IntKey) -> i64 {
+        self.decode(key.field())
+            .unwrap_or_else(|| key.default_value())
+    }
+
+    pub fn get_text(&self, key: TextKey) -> String {
+        self.decode(key.field())
+            .unwrap_or_else(|| key.default_value().to_string())
+    }
+
+    /// 未設定の場合は[`None`]を返す
+    pub fn get_optional_int(&self, key: OptionalIntKey) -> Option<i64> {
+        self.decode(key.field())
+    }
+
+    /// 未設定の場合は既定値を返し、既定値もない場合は[`None`]を返す
+    pub fn get_choice(&self, key: ChoiceKey) -> Option<String> {
+        choice_or_default(key, self.decode(key.field()))
+    }
+
+    /// 未設定の場合は[`None`]を返す
+    pub fn get_channel(&self, key: ChannelKey) -> Option<u64> {
+        self.decode(key.field())
+    }
+
+    pub fn get(&self, key: SettingKey) -> SettingValue {
+        match key {
+            SettingKey::Bool(key) => SettingValue::Bool(self.get_bool(key)),
+            SettingKey::Int(key) => SettingValue::Int(self.get_int(key)),
+            SettingKey::Text(key) => SettingValue::Text(self.get_text(key)),
+            SettingKey::OptionalInt(key) => self
+                .get_optional_int(key)
+                .map_or(SettingValue::Unset, SettingValue::Int),
+            SettingKey::Choice(key) => self
+                .get_choice(key)
+                .map_or(SettingValue::Unset, SettingValue::Text),
+            SettingKey::Channel(key) => self
+                .get_channel(key)
+                .map_or(SettingValue::Unset, SettingValue::Channel),
+        }
+    }
+
+    /// 保存された値を読み出す
+    /// 未設定の場合や、型の異なる値が保存されている場合は[`None`]を返す
+    fn decode<T: FromRedisValue>(&self, field: &str) -> Option<T> {
+        self.fields
+            .get(field)
+            .and_then(|value| T::from_redis_value(value).ok())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GetAllOption {
+    pub guild_id: u64,
+}
+
+/// サーバーのすべての設定を返す
+pub async fn get_all(connection: &mut Connection, option: GetAllOption) -> Result<GuildSettings> {
+    let fields: HashMap<String, Value> = connection.hgetall(settings_key(option.guild_id)).await?;
+    Ok(GuildSettings { fields })
+}
+
+#[derive(Debug, Clone)]
+pub struct ResetOption {
+    pub guild_id: u64,
+    pub key: SettingKey,
+}
+
+/// 設定を削除し、既定値に戻す
+pub async fn reset(connection: &mut Connection, option: ResetOption) -> Result<()> {
+    connection
+        .hdel::<_, _, ()>(settings_key(option.guild_id), option.key.name())
+        .await?;
+    Ok(())
+}
+
+/// 設定項目ごとに別のキーで保存していた頃のキーの末尾（`guild:{}:`の後）と、移行先の設定項目
+const LEGACY_KEYS: [(&str, SettingKey); 3] = [
+    (
+        "idle_timeout",
+        SettingKey::OptionalInt(OptionalIntKey::IdleTimeout),
+    ),
+    (
+        "furigana:syntax",
+        SettingKey::Choice(ChoiceKey::FuriganaSyntax),
+    ),
+    (
+        "speech_provider",
+        SettingKey::Choice(ChoiceKey::SpeechProvider),
+    ),
+];
+
+/// 設定項目ごとに別のキーで保存していた設定を、サーバーの設定にまとめる
+/// まとめた後に変更された場合に備え、サーバーの設定にすでに値がある項目は上書きしない
+/// 移行した設定の数を返す
+pub async fn migrate_legacy_keys(connection: &mut Connection) -> Result<usize> {
+    let mut migrated = 0;
+    for (suffix, key) in LEGACY_KEYS {
+        let legacy_keys = {
+            let mut iter = connection
+                .scan_match::<_, String>(format!("guild:*:{}", suffix))
+                .await?;
+            let mut legacy_keys = Vec::new();
+            while let Some(legacy_key) = iter.next_item().await {
+                legacy_keys.push(legacy_key);
+            }
+            legacy_keys
+        };
+
+        for legacy_key in legacy_keys {
+            let guild_id = match legacy_guild_id(&legacy_key, suffix) {
+                Some(guild_id) => guild_id,
+                None => continue,
+            };
+            let value: Option<String> = connection.get(&legacy_key).await?;
+            let mut pipe = redis::pipe();
+            pipe.atomic();
+            if let Some(value) = value {
+                pipe.hset_nx(settings_key(guild_id), key.name(), value)
+                    .ignore();
+                migrated += 1;
+            }
+            pipe.del(&legacy_key).ignore();
+            pipe.query_async::<_, ()>(connection).await?;
+        }
+    }
+    Ok(migrated)
+}
+
+/// `guild:{}:{suffix}`の形式のキーから、サーバーのIDを取り出す
+fn legacy_guild_id(key: &str, suffix: &str) -> Option<u64> {
+    key.strip_prefix("guild:")?
+        .strip_suffix(suffix)?
+        .strip_suffix(':')?
+        .parse()
+        .ok()
+}
+
+fn settings_key(guild_id: u64) -> String {
+    format!("guild:{}:settings", guild_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::furigana::FuriganaSyntax;
+    use redis::{FromRedisValue, ToRedisArgs, Value};
+
+    /// `HSET`で保存した値を、`HGET`で読み出したものとして解釈する
+    fn round_trip<T: ToRedisArgs + FromRedisValue>(value: T) -> Option<T> {
+        let args = value.to_redis_args();
+        assert_eq!(args.len(), 1);
+        FromRedisValue::from_redis_value(&Value::Data(args[0].clone())).unwrap()
+    }
+
+    fn stored(value: SettingValue) -> Option<SettingValue> {
+        match value {
+            SettingValue::Bool(x) => round_trip(x).map(SettingValue::Bool),
+            SettingValue::Int(x) => round_trip(x).map(SettingValue::Int),
+            SettingValue::Text(x) => round_trip(x).map(SettingValue::Text),
+            SettingValue::Channel(x) => round_trip(x).map(SettingValue::Channel),
+            // 未設定の場合は保存しない
+            SettingValue::Unset => Some(SettingValue::Unset),
+        }
+    }
+
+    fn to_args(value: SettingValue) -> Vec<Vec<u8>> {
+        match value {
+            SettingValue::Bool(x) => x.to_redis_args(),
+            SettingValue::Int(x) => x.to_redis_args(),
+            SettingValue::Text(x) => x.to_redis_args(),
+            SettingValue::Channel(x) => x.to_redis_args(),
+            SettingValue::Unset => Vec::new(),
+        }
+    }
+
+    /// 既定値とは異なる、設定できる値
+    fn sample_value(key: SettingKey) -> SettingValue {
+        match key {
+            SettingKey::Bool(key) => SettingValue::Bool(!key.default_value()),
+            SettingKey::Int(key) => SettingValue::Int(*key.range().end()),
+            SettingKey::Text(_) => SettingValue::Text("「{content}」と{name}さん".to_string()),
+            SettingKey::OptionalInt(key) => SettingValue::Int(*key.range().end()),
+            SettingKey::Choice(ChoiceKey::FuriganaSyntax) => SettingValue::Text("ruby".to_string()),
+            SettingKey::Choice(ChoiceKey::SpeechProvider) => {
+                SettingValue::Text("polly".to_string())
+            }
+            // DiscordのIDは19桁になることがある
+            SettingKey::Channel(_) => SettingValue::Channel(1_234_567_890_123_456_789),
+        }
+    }
+
+    #[test]
+    fn every_key_round_trips() {
+        for key in SettingKey::ALL {
+            assert_eq!(SettingKey::parse(key.name()), Some(key));
+
+            for value in [key.default_value(), sample_value(key)] {
+                assert_eq!(stored(value.clone()), Some(value.clone()), "{}", key.name());
+            }
+        }
+
+        // チャンネルIDは、`i64`の上限を超える値も保存できる
+        assert_eq!(
+            stored(SettingValue::Channel(u64::MAX)),
+            Some(SettingValue::Channel(u64::MAX))
+        );
+
+        // 未設定のフィールドは`None`として読み出され、既定値になる
+        assert_eq!(Option::<bool>::from_redis_value(&Value::Nil).unwrap(), None);
+    }
+
+    #[test]
+    fn settings_decode_stored_fields() {
+        // `HGETALL`の応答と同じ形で、設定した値を組み立てる
+        let mut fields = HashMap::new();
+        for key in SettingKey::ALL {
+            let args = to_args(sample_value(key));
+            fields.insert(key.name().to_string(), Value::Data(args[0].clone()));
+        }
+        let settings = GuildSettings { fields };
+        for key in SettingKey::ALL {
+            assert_eq!(settings.get(key), sample_value(key), "{}", key.name());
+        }
+
+        // 未設定の項目と、解釈できない値が保存された項目は既定値になる
+        let mut fields = HashMap::new();
+        for field in [
+            IntKey::MaxWords.field(),
+            OptionalIntKey::IdleTimeout.field(),
+            ChoiceKey::FuriganaSyntax.field(),
+            ChannelKey::NoticeChannel.field(),
+        ] {
+            fields.insert(field.to_string(), Value::Data(b"many".to_vec()));
+        }
+        let settings = GuildSettings { fields };
+        for key in SettingKey::ALL {
+            assert_eq!(settings.get(key), key.default_value(), "{}", key.name());
+        }
+        assert_eq!(
+            settings.get_choice(ChoiceKey::FuriganaSyntax).as_deref(),
+            Some("braces")
+        );
+        assert_eq!(settings.get_channel(ChannelKey::NoticeChannel), None);
+    }
+
+    #[test]
+    fn keys_are_unique() {
+        for (i, a) in SettingKey::ALL.iter().enumerate() {
+            for b in &SettingKey::ALL[i + 1..] {
+                assert_ne!(a.name(), b.name());
+            }
+        }
+        assert_eq!(SettingKey::parse("unknown"), None);
+    }
+
+    #[test]
+    fn parse_bool() {
+        let key = SettingKey::Bool(BoolKey::Chime);
+        for s in ["true", "ON", " yes ", "1", "有効"] {
+            assert_eq!(key.parse_value(s), Ok(SettingValue::Bool(true)), "{}", s);
+        }
+        for s in ["false", "Off", "no", "0", "無効"] {
+            assert_eq!(key.parse_value(s), Ok(SettingValue::Bool(false)), "{}", s);
+        }
+        assert_eq!(key.parse_value("maybe"), Err(ParseValueError::NotBool));
+    }
+
+    #[test]
+    fn parse_int() {
+        let key = SettingKey::Int(IntKey::RepeatThreshold);
+        assert_eq!(key.parse_value(" 5 "), Ok(SettingValue::Int(5)));
+        assert_eq!(key.parse_value("five"), Err(ParseValueError::NotInt));
+        assert_eq!(key.parse_value("1.5"), Err(ParseValueError::NotInt));
+        assert_eq!(
+            key.parse_value("0"),
+            Err(ParseValueError::OutOfRange {
+                min: MIN_REPEAT_THRESHOLD,
+                max: MAX_REPEAT_THRESHOLD
+            })
+        );
+        assert_eq!(
+            key.parse_value(&(MAX_REPEAT_THRESHOLD + 1).to_string()),
+            Err(ParseValueError::OutOfRange {
+                min: MIN_REPEAT_THRESHOLD,
+                max: MAX_REPEAT_THRESHOLD
+            })
+        );
+    }
+
+    #[test]
+    fn parse_text() {
+        let key = SettingKey::Text(TextKey::MessageTemplate);
+        assert_eq!(
+            key.parse_value("{name}さん、{content}"),
+            Ok(SettingValue::Text("{name}さん、{content}".to_string()))
+        );
+        assert_eq!(
+            key.parse_value("{name}"),
+            Err(ParseValueError::MissingPlaceholder {
+                placeholder: CONTENT_PLACEHOLDER
+            })
+        );
+        assert_eq!(
+            key.parse_value("{content}{content}"),
+            Err(ParseValueError::MissingPlaceholder {
+                placeholder: CONTENT_PLACEHOLDER
+            })
+        );
+
+        // 文字数は、バイト数ではなく文字の数で数える
+        let max = "あ".repeat(MAX_MESSAGE_TEMPLATE_LENGTH - CONTENT_PLACEHOLDER.len());
+        assert!(key
+            .parse_value(&format!("{}{}", max, CONTENT_PLACEHOLDER))
+            .is_ok());
+        assert_eq!(
+            key.parse_value(&format!("あ{}{}", max, CONTENT_PLACEHOLDER)),
+            Err(ParseValueError::TooLong {
+                max: MAX_MESSAGE_TEMPLATE_LENGTH
+            })
+        );
+    }
+
+    #[test]
+    fn parse_choice() {
+        let key = SettingKey::Choice(ChoiceKey::FuriganaSyntax);
+        assert_eq!(
+            key.parse_value(" Ruby "),
+            Ok(SettingValue::Text("ruby".to_string()))
+        );
+        assert_eq!(
+            key.parse_value("kakko"),
+            Err(ParseValueError::NotChoice {
+                choices: FuriganaSyntax::NAMES
+            })
+        );
+
+        // 選択肢が設定ファイルで決まる項目は、呼び出し側で確かめる
+        let key = SettingKey::Choice(ChoiceKey::SpeechProvider);
+        assert_eq!(
+            key.parse_value("VOICEVOX"),
+            Ok(SettingValue::Text("voicevox".to_string()))
+        );
+    }
+
+    #[test]
+    fn parse_channel() {
+        let key = SettingKey::Channel(ChannelKey::NoticeChannel);
+        for s in ["<#1234567890123456789>", " 1234567890123456789 "] {
+            assert_eq!(
+                key.parse_value(s),
+                Ok(SettingValue::Channel(1_234_567_890_123_456_789)),
+                "{}",
+                s
+            );
+        }
+        for s in ["#general", "<@123>", "<#>", "0", "-1"] {
+            assert_eq!(
+                key.parse_value(s),
+                Err(ParseValueError::NotChannel),
+                "{}",
+                s
+            );
+        }
+    }
+
+    #[test]
+    fn parsed_values_round_trip() {
+        for key in SettingKey::ALL {
+            let value = sample_value(key);
+            let s = match &value {
+                SettingValue::Bool(x) => x.to_string(),
+                SettingValue::Int(x) => x.to_string(),
+                SettingValue::Text(x) => x.clone(),
+                SettingValue::Channel(x) => format!("<#{}>", x),
+                SettingValue::Unset => unreachable!(),
+            };
+            assert_eq!(key.parse_value(&s), Ok(value), "{}", key.name());
+        }
+    }
+
+    #[test]
+    fn legacy_keys_are_migrated_to_settings() {
+        assert_eq!(
+            legacy_guild_id("guild:123:idle_timeout", "idle_timeout"),
+            Some(123)
+        );
+        assert_eq!(
+            legacy_guild_id("guild:123:furigana:syntax", "furigana:syntax"),
+            Some(123)
+        );
+        // パターンの`*`に一致しても、サーバーの設定ではないキーは移行しない
+        assert_eq!(
+            legacy_guild_id("guild:123:user:4:idle_timeout", "idle_timeout"),
+            None
+        );
+        assert_eq!(
+            legacy_guild_id("guild:123:speech_provider", "idle_timeout"),
+            None
+        );
+
+        // 以前のキーに保存されていた値は、移行先の設定項目の値として解釈できる
+        for ((_, key), stored) in LEGACY_KEYS.into_iter().zip(["30", "ruby", "azure"]) {
+            assert!(key.parse_value(stored).is_ok(), "{}", key.name());
+            let fields = HashMap::from([(
+                key.name().to_string(),
+                Value::Data(stored.as_bytes().to_vec()),
+            )]);
+            assert_ne!(
+                GuildSettings { fields }.get(key),
+                key.default_value(),
+                "{}",
+                key.name()
+            );
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use anyhow::Result;
+use redis::aio::Connection;
+use redis::AsyncCommands;
+
+/// コマンドへの応答に使う言語
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    Japanese,
+    English,
+}
+
+impl Language {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Language::Japanese => "ja",
+            Language::English => "en",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "ja" => Some(Language::Japanese),
+            "en" => Some(Language::English),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GetOption {
+    pub guild_id: u64,
+}
+
+/// サーバーで設定された応答の言語を返す
+/// 未設定の場合（Discordの言語設定に合わせる場合）は[`None`]を返す
+pub async fn get(connection: &mut Connection, option: GetOption) -> Result<Option<Language>> {
+    let resp: Option<String> = connection.get(language_key(option.guild_id)).await?;
+    Ok(resp.as_deref().and_then(Language::parse))
+}
+
+#[derive(Debug, Clone)]
+pub struct SetOption {
+    pub guild_id: u64,
+    pub language: Language,
+}
+
+/// サーバーでの応答の言語を設定する
+pub async fn set(connection: &mut Connection, option: SetOption) -> Result<()> {
+    connection
+        .set::<_, _, ()>(language_key(option.guild_id), option.language.as_str())
+        .await?;
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+pub struct RemoveOption {
+    pub guild_id: u64,
+}
+
+/// 応答の言語の設定を削除し、Discordの言語設定に合わせるようにする
+pub async fn remove(connection: &mut Connection, option: RemoveOption) -> Result<()> {
+    connection
+        .del::<_, ()>(language_key(option.guild_id))
+        .await?;
+    Ok(())
+}
+
+fn language_key(guild_id: u64) -> String {
+    format!("guild:{}:language", guild_id)
+}
@@ -1,4 +1,19 @@
+pub mod auto_join;
+pub mod command_permission;
 pub mod dict;
+pub mod embed_bot;
+pub mod empty_text;
+pub mod farewell;
+pub mod filter;
+pub mod furigana;
+pub mod guild_settings;
+pub mod language;
+pub mod mention_only;
+pub mod read_prefix;
+pub mod scale_bounds;
+pub mod system_voice;
+pub mod usage;
+pub mod user_dict;
 pub mod voice;
 
 pub use redis;
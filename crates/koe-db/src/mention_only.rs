@@ -0,0 +1,72 @@
+use anyhow::Result;
+use redis::aio::Connection;
+use redis::AsyncCommands;
+
+/// メンションだけのメッセージの扱い
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MentionOnlyMode {
+    /// メンションされたメンバーやロール、チャンネルの名前を読み上げる
+    Read,
+    /// 読み上げない
+    Skip,
+    /// 「メンション」とだけ読み上げる
+    Note,
+}
+
+impl MentionOnlyMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MentionOnlyMode::Read => "read",
+            MentionOnlyMode::Skip => "skip",
+            MentionOnlyMode::Note => "note",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "read" => Some(MentionOnlyMode::Read),
+            "skip" => Some(MentionOnlyMode::Skip),
+            "note" => Some(MentionOnlyMode::Note),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GetModeOption {
+    pub guild_id: u64,
+}
+
+/// メンションだけのメッセージの扱いを返す
+/// 未設定の場合は[`MentionOnlyMode::Read`]を返す
+pub async fn get_mode(
+    connection: &mut Connection,
+    option: GetModeOption,
+) -> Result<MentionOnlyMode> {
+    let resp: Option<String> = connection.get(mode_key(option.guild_id)).await?;
+
+    let mode = resp
+        .as_deref()
+        .and_then(MentionOnlyMode::parse)
+        .unwrap_or(MentionOnlyMode::Read);
+
+    Ok(mode)
+}
+
+#[derive(Debug, Clone)]
+pub struct SetModeOption {
+    pub guild_id: u64,
+    pub mode: MentionOnlyMode,
+}
+
+/// メンションだけのメッセージの扱いを設定する
+pub async fn set_mode(connection: &mut Connection, option: SetModeOption) -> Result<()> {
+    connection
+        .set::<_, _, ()>(mode_key(option.guild_id), option.mode.as_str())
+        .await?;
+    Ok(())
+}
+
+fn mode_key(guild_id: u64) -> String {
+    format!("guild:{}:mention_only:mode", guild_id)
+}
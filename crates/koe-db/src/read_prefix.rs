@@ -0,0 +1,50 @@
+use anyhow::Result;
+use redis::aio::Connection;
+use redis::AsyncCommands;
+
+/// 読み上げる合図の最大文字数
+pub const MAX_PREFIX_LENGTH: usize = 10;
+
+#[derive(Debug, Clone)]
+pub struct GetOption {
+    pub guild_id: u64,
+}
+
+/// 読み上げるメッセージの先頭に付ける合図を返す
+/// 未設定の場合（すべてのメッセージを読み上げる場合）は[`None`]を返す
+pub async fn get(connection: &mut Connection, option: GetOption) -> Result<Option<String>> {
+    let resp: Option<String> = connection.get(read_prefix_key(option.guild_id)).await?;
+    Ok(resp)
+}
+
+#[derive(Debug, Clone)]
+pub struct SetOption {
+    pub guild_id: u64,
+    pub prefix: String,
+}
+
+/// 読み上げるメッセージの先頭に付ける合図を設定する
+/// 設定すると、合図で始まるメッセージのみを読み上げる
+pub async fn set(connection: &mut Connection, option: SetOption) -> Result<()> {
+    connection
+        .set::<_, _, ()>(read_prefix_key(option.guild_id), option.prefix)
+        .await?;
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+pub struct RemoveOption {
+    pub guild_id: u64,
+}
+
+/// 合図を削除し、すべてのメッセージを読み上げるようにする
+pub async fn remove(connection: &mut Connection, option: RemoveOption) -> Result<()> {
+    connection
+        .del::<_, ()>(read_prefix_key(option.guild_id))
+        .await?;
+    Ok(())
+}
+
+fn read_prefix_key(guild_id: u64) -> String {
+    format!("guild:{}:read_prefix", guild_id)
+}
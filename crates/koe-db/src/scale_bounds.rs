@@ -0,0 +1,99 @@
+use anyhow::Result;
+use redis::aio::Connection;
+use redis::AsyncCommands;
+
+/// サーバーごとに範囲を制限できる声のパラメータ
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScaleKind {
+    /// 話速
+    Speed,
+    /// 音高
+    Pitch,
+}
+
+impl ScaleKind {
+    fn min_field(&self) -> &'static str {
+        match self {
+            ScaleKind::Speed => "speed_min",
+            ScaleKind::Pitch => "pitch_min",
+        }
+    }
+
+    fn max_field(&self) -> &'static str {
+        match self {
+            ScaleKind::Speed => "speed_max",
+            ScaleKind::Pitch => "pitch_max",
+        }
+    }
+}
+
+/// メンバーが設定できる値の範囲
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScaleBounds {
+    pub min: f64,
+    pub max: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct GetOption {
+    pub guild_id: u64,
+    pub kind: ScaleKind,
+}
+
+/// サーバーで設定された範囲を返す
+/// 未設定の場合は[`None`]を返す
+pub async fn get(connection: &mut Connection, option: GetOption) -> Result<Option<ScaleBounds>> {
+    let (min, max): (Option<f64>, Option<f64>) = redis::cmd("HMGET")
+        .arg(scale_bounds_key(option.guild_id))
+        .arg(option.kind.min_field())
+        .arg(option.kind.max_field())
+        .query_async(connection)
+        .await?;
+
+    Ok(match (min, max) {
+        (Some(min), Some(max)) => Some(ScaleBounds { min, max }),
+        _ => None,
+    })
+}
+
+#[derive(Debug, Clone)]
+pub struct SetOption {
+    pub guild_id: u64,
+    pub kind: ScaleKind,
+    pub bounds: ScaleBounds,
+}
+
+/// サーバーで設定する範囲を変更する
+pub async fn set(connection: &mut Connection, option: SetOption) -> Result<()> {
+    connection
+        .hset_multiple::<_, _, _, ()>(
+            scale_bounds_key(option.guild_id),
+            &[
+                (option.kind.min_field(), option.bounds.min),
+                (option.kind.max_field(), option.bounds.max),
+            ],
+        )
+        .await?;
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+pub struct RemoveOption {
+    pub guild_id: u64,
+    pub kind: ScaleKind,
+}
+
+/// サーバーで設定した範囲を削除する
+pub async fn remove(connection: &mut Connection, option: RemoveOption) -> Result<()> {
+    connection
+        .hdel::<_, _, ()>(
+            scale_bounds_key(option.guild_id),
+            &[option.kind.min_field(), option.kind.max_field()],
+        )
+        .await?;
+    Ok(())
+}
+
+fn scale_bounds_key(guild_id: u64) -> String {
+    format!("guild:{}:scale_bounds", guild_id)
+}
@@ -0,0 +1,65 @@
+use anyhow::Result;
+use redis::aio::Connection;
+use redis::AsyncCommands;
+
+#[derive(Debug, Clone, Default)]
+pub struct SystemVoice {
+    /// 声（プリセットID）、未設定の場合は[`None`]
+    pub preset_id: Option<i64>,
+    /// 話速、未設定の場合は[`None`]
+    pub speed_scale: Option<f64>,
+    /// 音高、未設定の場合は[`None`]
+    pub pitch_scale: Option<f64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GetOption {
+    pub guild_id: u64,
+}
+
+/// サーバーのシステム音声の設定を返す
+pub async fn get(connection: &mut Connection, option: GetOption) -> Result<SystemVoice> {
+    let (preset_id, speed_scale, pitch_scale) = connection
+        .hget(
+            system_voice_key(option.guild_id),
+            &["preset_id", "speed_scale", "pitch_scale"],
+        )
+        .await?;
+
+    Ok(SystemVoice {
+        preset_id,
+        speed_scale,
+        pitch_scale,
+    })
+}
+
+#[derive(Debug, Clone)]
+pub struct SetOption {
+    pub guild_id: u64,
+    pub voice: SystemVoice,
+}
+
+/// サーバーのシステム音声を設定する
+/// [`None`]の項目は未設定の状態に戻す
+pub async fn set(connection: &mut Connection, option: SetOption) -> Result<()> {
+    let key = system_voice_key(option.guild_id);
+
+    let mut pipe = redis::pipe();
+    pipe.atomic().del(&key).ignore();
+    if let Some(preset_id) = option.voice.preset_id {
+        pipe.hset(&key, "preset_id", preset_id).ignore();
+    }
+    if let Some(speed_scale) = option.voice.speed_scale {
+        pipe.hset(&key, "speed_scale", speed_scale).ignore();
+    }
+    if let Some(pitch_scale) = option.voice.pitch_scale {
+        pipe.hset(&key, "pitch_scale", pitch_scale).ignore();
+    }
+    pipe.query_async::<_, ()>(connection).await?;
+
+    Ok(())
+}
+
+fn system_voice_key(guild_id: u64) -> String {
+    format!("guild:{}:system_voice", guild_id)
+}
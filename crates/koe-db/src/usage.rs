@@ -0,0 +1,162 @@
+use anyhow::Result;
+use redis::aio::Connection;
+use redis::AsyncCommands;
+
+/// 月ごとの文字数を保持する期間（秒）
+/// 前年の同じ月と比べられるよう、1年より長く残す
+const RETENTION_SECONDS: usize = 400 * 24 * 60 * 60;
+
+#[derive(Debug, Clone)]
+pub struct AddOption {
+    pub guild_id: u64,
+    /// `2024-01`の形式の年月
+    pub month: String,
+    pub chars: u64,
+}
+
+/// 音声合成した文字数を、サーバーごとと全体の月ごとの文字数に加え、サーバーの今月の文字数を返す
+pub async fn add(connection: &mut Connection, option: AddOption) -> Result<u64> {
+    let guild_key = guild_usage_key(&option.month, option.guild_id);
+    let ranking_key = ranking_key(&option.month);
+    let total_key = total_key(&option.month);
+
+    let (resp,) = redis::pipe()
+        .atomic()
+        .incr(&guild_key, option.chars)
+        .expire(&guild_key, RETENTION_SECONDS)
+        .ignore()
+        .zincr(&ranking_key, option.guild_id, option.chars)
+        .ignore()
+        .expire(&ranking_key, RETENTION_SECONDS)
+        .ignore()
+        .incr(&total_key, option.chars)
+        .ignore()
+        .expire(&total_key, RETENTION_SECONDS)
+        .ignore()
+        .query_async(connection)
+        .await?;
+
+    Ok(resp)
+}
+
+#[derive(Debug, Clone)]
+pub struct GetOption {
+    pub guild_id: u64,
+    pub month: String,
+}
+
+/// サーバーで指定された月に音声合成した文字数を返す
+pub async fn get(connection: &mut Connection, option: GetOption) -> Result<u64> {
+    let resp: Option<u64> = connection
+        .get(guild_usage_key(&option.month, option.guild_id))
+        .await?;
+    Ok(resp.unwrap_or(0))
+}
+
+#[derive(Debug, Clone)]
+pub struct GetTopOption {
+    pub month: String,
+    pub limit: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct GetTopResponse {
+    /// すべてのサーバーの合計
+    pub total: u64,
+    /// 文字数の多い順に並べた、サーバーIDと文字数
+    pub guilds: Vec<(u64, u64)>,
+}
+
+/// 指定された月に音声合成した文字数の合計と、文字数の多いサーバーを返す
+pub async fn get_top(connection: &mut Connection, option: GetTopOption) -> Result<GetTopResponse> {
+    if option.limit == 0 {
+        let total: Option<u64> = connection.get(total_key(&option.month)).await?;
+        return Ok(GetTopResponse {
+            total: total.unwrap_or(0),
+            guilds: Vec::new(),
+        });
+    }
+
+    let (total, guilds): (Option<u64>, Vec<(u64, u64)>) = redis::pipe()
+        .get(total_key(&option.month))
+        .zrevrange_withscores(ranking_key(&option.month), 0, option.limit as isize - 1)
+        .query_async(connection)
+        .await?;
+
+    Ok(GetTopResponse {
+        total: total.unwrap_or(0),
+        guilds,
+    })
+}
+
+#[derive(Debug, Clone)]
+pub struct GetLimitOption {
+    pub guild_id: u64,
+}
+
+/// サーバーが1か月に音声合成できる文字数の上限を返す
+/// 上限がない場合は[`None`]を返す
+pub async fn get_limit(connection: &mut Connection, option: GetLimitOption) -> Result<Option<u64>> {
+    let resp: Option<u64> = connection.get(limit_key(option.guild_id)).await?;
+    Ok(resp)
+}
+
+#[derive(Debug, Clone)]
+pub struct SetLimitOption {
+    pub guild_id: u64,
+    /// [`None`]の場合は上限をなくす
+    pub limit: Option<u64>,
+}
+
+/// サーバーが1か月に音声合成できる文字数の上限を設定する
+pub async fn set_limit(connection: &mut Connection, option: SetLimitOption) -> Result<()> {
+    let key = limit_key(option.guild_id);
+    match option.limit {
+        Some(limit) => connection.set::<_, _, ()>(key, limit).await?,
+        None => connection.del::<_, ()>(key).await?,
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+pub struct MarkLimitNotifiedOption {
+    pub guild_id: u64,
+    pub month: String,
+}
+
+/// 上限に達したことをサーバーに知らせたことを記録する
+/// その月にまだ知らせていなかった場合は`true`を返す
+pub async fn mark_limit_notified(
+    connection: &mut Connection,
+    option: MarkLimitNotifiedOption,
+) -> Result<bool> {
+    let key = limit_notified_key(&option.month, option.guild_id);
+    let (resp,) = redis::pipe()
+        .atomic()
+        .set_nx(&key, 1)
+        .expire(&key, RETENTION_SECONDS)
+        .ignore()
+        .query_async(connection)
+        .await?;
+    Ok(resp)
+}
+
+fn guild_usage_key(month: &str, guild_id: u64) -> String {
+    format!("usage:{}:guild:{}", month, guild_id)
+}
+
+fn ranking_key(month: &str) -> String {
+    format!("usage:{}:guilds", month)
+}
+
+fn total_key(month: &str) -> String {
+    format!("usage:{}:total", month)
+}
+
+fn limit_notified_key(month: &str, guild_id: u64) -> String {
+    format!("usage:{}:guild:{}:limit_notified", month, guild_id)
+}
+
+fn limit_key(guild_id: u64) -> String {
+    format!("guild:{}:usage_limit", guild_id)
+}
@@ -0,0 +1,85 @@
+use anyhow::{bail, Result};
+use redis::aio::Connection;
+use redis::AsyncCommands;
+
+/// メンバーごとの辞書に登録できる語句の最大数
+pub const MAX_ENTRIES: usize = 100;
+
+#[derive(Debug, Clone)]
+pub struct InsertOption {
+    pub user_id: u64,
+    pub word: String,
+    pub read_as: String,
+}
+
+#[derive(Debug, Clone)]
+pub enum InsertResponse {
+    Success,
+    WordAlreadyExists,
+    TooManyEntries,
+}
+
+/// メンバーの辞書に語句を追加する
+pub async fn insert(connection: &mut Connection, option: InsertOption) -> Result<InsertResponse> {
+    let key = user_dict_key(option.user_id);
+
+    let count: usize = connection.hlen(&key).await?;
+    if count >= MAX_ENTRIES {
+        return Ok(InsertResponse::TooManyEntries);
+    }
+
+    let resp = connection
+        .hset_nx(&key, option.word, option.read_as)
+        .await?;
+
+    Ok(match resp {
+        0 => InsertResponse::WordAlreadyExists,
+        1 => InsertResponse::Success,
+        x => bail!("Unknown HSETNX response from Redis: {}", x),
+    })
+}
+
+#[derive(Debug, Clone)]
+pub struct RemoveOption {
+    pub user_id: u64,
+    pub word: String,
+}
+
+#[derive(Debug, Clone)]
+pub enum RemoveResponse {
+    Success,
+    WordDoesNotExist,
+}
+
+/// メンバーの辞書から語句を削除する
+pub async fn remove(connection: &mut Connection, option: RemoveOption) -> Result<RemoveResponse> {
+    let resp = connection
+        .hdel(user_dict_key(option.user_id), option.word)
+        .await?;
+
+    Ok(match resp {
+        0 => RemoveResponse::WordDoesNotExist,
+        1 => RemoveResponse::Success,
+        x => bail!("Unknown HDEL response from Redis: {}", x),
+    })
+}
+
+#[derive(Debug, Clone)]
+pub struct GetAllOption {
+    pub user_id: u64,
+}
+
+/// メンバーの辞書全体を語句の順に並べて返す
+/// 辞書が存在しないときは空の[`Vec`]を返す
+pub async fn get_all(
+    connection: &mut Connection,
+    option: GetAllOption,
+) -> Result<Vec<(String, String)>> {
+    let mut resp: Vec<(String, String)> = connection.hgetall(user_dict_key(option.user_id)).await?;
+    resp.sort();
+    Ok(resp)
+}
+
+fn user_dict_key(user_id: u64) -> String {
+    format!("user:{}:dict", user_id)
+}
@@ -3,41 +3,154 @@ use redis::aio::Connection;
 use redis::AsyncCommands;
 
 #[derive(Debug, Clone)]
-pub struct GetOption {
+pub struct SetOption {
     pub guild_id: u64,
     pub user_id: u64,
-    pub fallback: i64,
+    pub value: i64,
+    /// プリセットを提供するエンジンの名前（例: `voicevox`）
+    pub provider: String,
 }
 
-/// ユーザーの声を返す
-/// 未設定の場合は`option.fallback`の値を設定して返す
-pub async fn get(connection: &mut Connection, option: GetOption) -> Result<i64> {
-    let key = voice_key(option.guild_id, option.user_id);
-
-    let (resp,) = redis::pipe()
-        .set_nx(&key, option.fallback)
+/// ユーザーの声を、そのプリセットを提供するエンジンとともに設定する
+pub async fn set(connection: &mut Connection, option: SetOption) -> Result<()> {
+    redis::pipe()
+        .atomic()
+        .set(voice_key(option.guild_id, option.user_id), option.value)
         .ignore()
-        .get(&key)
-        .query_async(connection)
+        .set(
+            voice_provider_key(option.guild_id, option.user_id),
+            option.provider,
+        )
+        .ignore()
+        .query_async::<_, ()>(connection)
         .await?;
+    Ok(())
+}
 
-    Ok(resp)
+#[derive(Debug, Clone)]
+pub struct SetScaleOption {
+    pub guild_id: u64,
+    pub user_id: u64,
+    pub value: f64,
+}
+
+/// ユーザーの声の話速を設定する
+pub async fn set_speed_scale(connection: &mut Connection, option: SetScaleOption) -> Result<()> {
+    let key = speed_scale_key(option.guild_id, option.user_id);
+    connection.set::<_, _, ()>(&key, option.value).await?;
+    Ok(())
+}
+
+/// ユーザーの声の音高を設定する
+pub async fn set_pitch_scale(connection: &mut Connection, option: SetScaleOption) -> Result<()> {
+    let key = pitch_scale_key(option.guild_id, option.user_id);
+    connection.set::<_, _, ()>(&key, option.value).await?;
+    Ok(())
 }
 
 #[derive(Debug, Clone)]
-pub struct SetOption {
+pub struct GetSettingsOption {
     pub guild_id: u64,
     pub user_id: u64,
-    pub value: i64,
 }
 
-/// ユーザーの声を設定する
-pub async fn set(connection: &mut Connection, option: SetOption) -> Result<()> {
-    let key = voice_key(option.guild_id, option.user_id);
-    connection.set(&key, option.value).await?;
+#[derive(Debug, Clone)]
+pub struct VoiceSettings {
+    /// 設定されている声（プリセットID）、未設定の場合は[`None`]
+    pub preset_id: Option<i64>,
+    /// 声を設定したときのエンジンの名前
+    /// エンジンを保存するようになる前に設定された声の場合は[`None`]
+    pub provider: Option<String>,
+    /// 設定されている話速、未設定の場合は[`None`]
+    pub speed_scale: Option<f64>,
+    /// 設定されている音高、未設定の場合は[`None`]
+    pub pitch_scale: Option<f64>,
+}
+
+/// ユーザーの声の設定を返す
+pub async fn get_settings(
+    connection: &mut Connection,
+    option: GetSettingsOption,
+) -> Result<VoiceSettings> {
+    let (preset_id, provider, speed_scale, pitch_scale) = connection
+        .get(&[
+            voice_key(option.guild_id, option.user_id),
+            voice_provider_key(option.guild_id, option.user_id),
+            speed_scale_key(option.guild_id, option.user_id),
+            pitch_scale_key(option.guild_id, option.user_id),
+        ])
+        .await?;
+
+    Ok(VoiceSettings {
+        preset_id,
+        provider,
+        speed_scale,
+        pitch_scale,
+    })
+}
+
+#[derive(Debug, Clone)]
+pub struct GetLanguageOption {
+    pub guild_id: u64,
+    pub user_id: u64,
+}
+
+/// ユーザーが読み上げに使う言語（例: `en`）を返す
+/// 未設定の場合（サーバーで選択されたエンジンを使う場合）は[`None`]を返す
+pub async fn get_language(
+    connection: &mut Connection,
+    option: GetLanguageOption,
+) -> Result<Option<String>> {
+    let key = language_key(option.guild_id, option.user_id);
+    Ok(connection.get(&key).await?)
+}
+
+#[derive(Debug, Clone)]
+pub struct SetLanguageOption {
+    pub guild_id: u64,
+    pub user_id: u64,
+    pub language: String,
+}
+
+/// ユーザーが読み上げに使う言語を設定する
+pub async fn set_language(connection: &mut Connection, option: SetLanguageOption) -> Result<()> {
+    let key = language_key(option.guild_id, option.user_id);
+    connection.set::<_, _, ()>(&key, option.language).await?;
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+pub struct RemoveLanguageOption {
+    pub guild_id: u64,
+    pub user_id: u64,
+}
+
+/// ユーザーが読み上げに使う言語の設定を削除する
+pub async fn remove_language(
+    connection: &mut Connection,
+    option: RemoveLanguageOption,
+) -> Result<()> {
+    let key = language_key(option.guild_id, option.user_id);
+    connection.del::<_, ()>(&key).await?;
     Ok(())
 }
 
 fn voice_key(guild_id: u64, user_id: u64) -> String {
     format!("guild:{}:user:{}:voice", guild_id, user_id)
 }
+
+fn voice_provider_key(guild_id: u64, user_id: u64) -> String {
+    format!("guild:{}:user:{}:voice:provider", guild_id, user_id)
+}
+
+fn speed_scale_key(guild_id: u64, user_id: u64) -> String {
+    format!("guild:{}:user:{}:voice:speed", guild_id, user_id)
+}
+
+fn pitch_scale_key(guild_id: u64, user_id: u64) -> String {
+    format!("guild:{}:user:{}:voice:pitch", guild_id, user_id)
+}
+
+fn language_key(guild_id: u64, user_id: u64) -> String {
+    format!("guild:{}:user:{}:voice:language", guild_id, user_id)
+}
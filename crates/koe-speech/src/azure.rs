@@ -0,0 +1,512 @@
+use crate::{
+    retry::with_retry,
+    speech::{PresetId, SpeechRequest},
+    voicevox::Preset,
+};
+use anyhow::{anyhow, bail, ensure, Context as _, Result};
+use koe_audio::EncodedAudio;
+use reqwest::{header::RETRY_AFTER, RequestBuilder, StatusCode};
+use serde::Deserialize;
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+use tokio::sync::Mutex;
+
+/// アクセストークンの有効期間は10分のため、期限が切れる前に取得し直す
+const TOKEN_LIFETIME: Duration = Duration::from_secs(9 * 60);
+
+/// 音声合成で出力する形式
+/// ffmpegでそのままデコードできるOgg/Opusを使う
+const OUTPUT_FORMAT: &str = "ogg-48khz-16bit-mono-opus";
+
+/// 1回のリクエストの応答を待つ最大の時間
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone)]
+pub struct AzureOption {
+    pub subscription_key: String,
+    /// リソースのリージョン（例: `japaneast`）
+    pub region: String,
+    /// 使用する声の言語（例: `ja-JP`）
+    pub locale: String,
+    /// 使用する声の名前（例: `ja-JP-NanamiNeural`）、空の場合は`locale`のすべての声を使う
+    pub voices: Vec<String>,
+}
+
+/// Azure Cognitive Services Speechの音声合成APIのクライアント
+pub struct AzureClient {
+    client: reqwest::Client,
+    subscription_key: String,
+    /// 音声合成と声の一覧のAPIのURLの共通部分
+    tts_base: String,
+    token_url: String,
+    locale: String,
+    voice_names: Vec<String>,
+    /// 取得したアクセストークンと、その時刻
+    token: Mutex<Option<(String, Instant)>>,
+    /// 使用する声の一覧、最初に取得した後は再起動するまで変えない
+    voices: Mutex<Option<Vec<AzureVoice>>>,
+}
+
+impl AzureClient {
+    pub fn new(option: AzureOption) -> Self {
+        let tts_base = format!(
+            "https://{}.tts.speech.microsoft.com/cognitiveservices",
+            option.region
+        );
+        let token_url = format!(
+            "https://{}.api.cognitive.microsoft.com/sts/v1.0/issueToken",
+            option.region
+        );
+        Self::with_endpoints(option, tts_base, token_url)
+    }
+
+    fn with_endpoints(option: AzureOption, tts_base: String, token_url: String) -> Self {
+        Self {
+            client: reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .expect("Failed to build HTTP client"),
+            subscription_key: option.subscription_key,
+            tts_base,
+            token_url,
+            locale: option.locale,
+            voice_names: option.voices,
+            token: Mutex::new(None),
+            voices: Mutex::new(None),
+        }
+    }
+
+    /// 読み上げる言語（`locale`の言語の部分、例: `ja-JP`の場合は`ja`）
+    pub fn language(&self) -> &str {
+        self.locale.split('-').next().unwrap_or(&self.locale)
+    }
+
+    /// 文章を音声に変換する
+    /// レート制限に達した場合は[`RateLimited`]のエラーを返す
+    pub async fn synthesis(&self, params: AzureSynthesisParams) -> Result<EncodedAudio> {
+        let url = format!("{}/v1", self.tts_base);
+        let ssml = build_ssml(&params);
+
+        let resp = self
+            .send_authorized(|token| {
+                self.client
+                    .post(&url)
+                    .bearer_auth(token)
+                    .header("content-type", "application/ssml+xml")
+                    .header("x-microsoft-outputformat", OUTPUT_FORMAT)
+                    .header("user-agent", "koe")
+                    .body(ssml.clone())
+            })
+            .await?;
+        let resp = resp.bytes().await?;
+
+        Ok(EncodedAudio::from(resp.to_vec()))
+    }
+
+    /// 設定された言語の声の一覧を返す
+    /// 声の名前が設定されている場合は、設定された順にそれらの声のみを返し、そうでなければ名前の順に並べる
+    pub async fn voices(&self) -> Result<Vec<AzureVoice>> {
+        let mut voices = self.voices.lock().await;
+        if let Some(voices) = voices.as_ref() {
+            return Ok(voices.clone());
+        }
+
+        let url = format!("{}/voices/list", self.tts_base);
+        let resp = self
+            .send_authorized(|token| self.client.get(&url).bearer_auth(token))
+            .await?;
+        let available: Vec<AzureVoice> = resp.json().await?;
+        let mut available = available
+            .into_iter()
+            .filter(|v| v.locale == self.locale)
+            .collect::<Vec<_>>();
+
+        let selected = if self.voice_names.is_empty() {
+            available.sort_by(|a, b| a.short_name.cmp(&b.short_name));
+            available
+        } else {
+            self.voice_names
+                .iter()
+                .map(|name| {
+                    available
+                        .iter()
+                        .find(|v| &v.short_name == name)
+                        .cloned()
+                        .ok_or_else(|| anyhow!("Azure voice {} is not available", name))
+                })
+                .collect::<Result<Vec<_>>>()?
+        };
+        ensure!(
+            !selected.is_empty(),
+            "No Azure voice is available for {}",
+            self.locale
+        );
+
+        *voices = Some(selected.clone());
+        Ok(selected)
+    }
+
+    /// 声の一覧をプリセットとして返す
+    /// プリセットIDは[`AzureClient::voices`]の順に1から割り当てる
+    pub async fn presets(&self) -> Result<Vec<Preset>> {
+        let voices = self.voices().await?;
+        let presets = voices
+            .into_iter()
+            .zip(1..)
+            .map(|(voice, id)| Preset {
+                id,
+                name: format!("{}（{}）", voice.local_name, voice.short_name),
+                speaker_uuid: voice.short_name,
+                style_id: id,
+                speed_scale: 1.0,
+                pitch_scale: 0.0,
+                intonation_scale: 1.0,
+                volume_scale: 1.0,
+                pre_phoneme_length: 0.0,
+                post_phoneme_length: 0.0,
+            })
+            .collect();
+        Ok(presets)
+    }
+
+    /// 文章を、指定されたプリセットの声で音声に変換する
+    /// 一時的な失敗やレート制限の場合は、間隔を空けて再試行する
+    pub async fn make_speech(&self, request: SpeechRequest) -> Result<EncodedAudio> {
+        if request.text.trim().is_empty() {
+            bail!("Text to speak must not be empty");
+        }
+
+        let voice = self.voice_name(request.preset_id).await?;
+        let params = AzureSynthesisParams {
+            text: request.text,
+            voice,
+            speed_scale: request.speed_scale,
+            pitch_scale: request.pitch_scale,
+            volume_gain: request.volume_gain,
+        };
+
+        with_retry("Speech synthesis with Azure", || {
+            self.synthesis(params.clone())
+        })
+        .await
+    }
+
+    async fn voice_name(&self, preset_id: PresetId) -> Result<String> {
+        let voices = self.voices().await?;
+        usize::try_from(preset_id.0)
+            .ok()
+            .and_then(|id| id.checked_sub(1))
+            .and_then(|index| voices.get(index))
+            .map(|voice| voice.short_name.clone())
+            .ok_or_else(|| anyhow!("Preset {} is not available", preset_id.0))
+    }
+
+    /// アクセストークンを付けてリクエストを送信する
+    /// 期限の前にトークンが無効になっていた（401）場合は、トークンを取得し直して1回だけ送り直す
+    async fn send_authorized(
+        &self,
+        build: impl Fn(&str) -> RequestBuilder,
+    ) -> Result<reqwest::Response> {
+        let token = self.access_token().await?;
+        let resp = build(&token).send().await?;
+        let resp = if resp.status() == StatusCode::UNAUTHORIZED {
+            self.clear_token(&token).await;
+            let token = self.access_token().await?;
+            build(&token).send().await?
+        } else {
+            resp
+        };
+
+        Ok(check_rate_limit(resp)?.error_for_status()?)
+    }
+
+    /// 無効になったアクセストークンを破棄する
+    /// 他のリクエストがすでに取得し直している場合は、新しいトークンを残す
+    async fn clear_token(&self, rejected: &str) {
+        let mut token = self.token.lock().await;
+        if token.as_ref().map(|(value, _)| value.as_str()) == Some(rejected) {
+            *token = None;
+        }
+    }
+
+    /// 有効なアクセストークンを返す
+    /// 期限が近い場合や未取得の場合は、サブスクリプションキーを使って取得し直す
+    async fn access_token(&self) -> Result<String> {
+        let mut token = self.token.lock().await;
+        if let Some((value, issued_at)) = token.as_ref() {
+            if issued_at.elapsed() < TOKEN_LIFETIME {
+                return Ok(value.clone());
+            }
+        }
+
+        let resp = self
+            .client
+            .post(&self.token_url)
+            .header("ocp-apim-subscription-key", &self.subscription_key)
+            .header("content-length", "0")
+            .send()
+            .await?;
+        let value = check_rate_limit(resp)?
+            .error_for_status()?
+            .text()
+            .await
+            .context("Failed to issue access token")?;
+
+        *token = Some((value.clone(), Instant::now()));
+        Ok(value)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AzureSynthesisParams {
+    pub text: String,
+    /// 声の名前（例: `ja-JP-NanamiNeural`）
+    pub voice: String,
+    /// 話速、1.0が標準
+    pub speed_scale: Option<f64>,
+    /// 音高、0.0が標準（VOICEVOXの`pitchScale`と同じ範囲）
+    pub pitch_scale: Option<f64>,
+    /// 音量の倍率、1.0が標準
+    pub volume_gain: Option<f64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct AzureVoice {
+    /// 音声合成で指定する名前（例: `ja-JP-NanamiNeural`）
+    pub short_name: String,
+    pub local_name: String,
+    pub gender: String,
+    pub locale: String,
+}
+
+/// APIのレート制限に達したことを表すエラー
+/// 時間をおいて再試行すれば成功する可能性がある
+/// Amazon Pollyのスロットリングにも使う
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimited {
+    /// `Retry-After`ヘッダーで指定された、再試行までに待つべき時間
+    pub retry_after: Option<Duration>,
+}
+
+impl fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.retry_after {
+            Some(retry_after) => write!(
+                f,
+                "Rate limited by the speech API, retry after {:?}",
+                retry_after
+            ),
+            None => write!(f, "Rate limited by the speech API"),
+        }
+    }
+}
+
+impl std::error::Error for RateLimited {}
+
+pub(crate) fn check_rate_limit(resp: reqwest::Response) -> Result<reqwest::Response> {
+    if resp.status() != StatusCode::TOO_MANY_REQUESTS {
+        return Ok(resp);
+    }
+
+    let retry_after = resp
+        .headers()
+        .get(RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+        .map(Duration::from_secs);
+    Err(RateLimited { retry_after }.into())
+}
+
+/// 声と話速・音高を指定したSSMLを組み立てる
+fn build_ssml(params: &AzureSynthesisParams) -> String {
+    // 話速は標準を1.0とする倍率、音高はVOICEVOXの`pitchScale`の値を百分率に読み替える
+    let rate = params.speed_scale.unwrap_or(1.0) * 100.0 - 100.0;
+    let pitch = params.pitch_scale.unwrap_or(0.0) * 100.0;
+    let volume = params.volume_gain.unwrap_or(1.0) * 100.0 - 100.0;
+
+    format!(
+        r#"<speak version="1.0" xmlns="http://www.w3.org/2001/10/synthesis" xml:lang="ja-JP"><voice name="{}"><prosody rate="{:+.0}%" pitch="{:+.0}%" volume="{:+.0}%">{}</prosody></voice></speak>"#,
+        escape_xml(&params.voice),
+        rate,
+        pitch,
+        volume,
+        escape_xml(&params.text)
+    )
+}
+
+pub(crate) fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_http::{response, serve};
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    const VOICES: &str = r#"[
+        {"ShortName": "ja-JP-NanamiNeural", "LocalName": "七海", "Gender": "Female", "Locale": "ja-JP"},
+        {"ShortName": "en-US-JennyNeural", "LocalName": "Jenny", "Gender": "Female", "Locale": "en-US"},
+        {"ShortName": "ja-JP-KeitaNeural", "LocalName": "圭太", "Gender": "Male", "Locale": "ja-JP"}
+    ]"#;
+
+    fn client(url: &str, voices: &[&str]) -> AzureClient {
+        AzureClient::with_endpoints(
+            AzureOption {
+                subscription_key: "key".to_string(),
+                region: "test".to_string(),
+                locale: "ja-JP".to_string(),
+                voices: voices.iter().map(|x| x.to_string()).collect(),
+            },
+            format!("{}/tts", url),
+            format!("{}/token", url),
+        )
+    }
+
+    fn request(text: &str, preset_id: i64) -> SpeechRequest {
+        SpeechRequest {
+            text: text.to_string(),
+            preset_id: PresetId(preset_id),
+            speed_scale: None,
+            pitch_scale: None,
+            volume_gain: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn reissues_token_after_unauthorized() {
+        let tokens = Arc::new(AtomicUsize::new(0));
+        let syntheses = Arc::new(AtomicUsize::new(0));
+        let url = {
+            let tokens = tokens.clone();
+            let syntheses = syntheses.clone();
+            serve(move |path| match path {
+                "/token" => {
+                    let n = tokens.fetch_add(1, Ordering::SeqCst);
+                    Some(response("200 OK", &format!("token-{}", n)))
+                }
+                "/tts/voices/list" => Some(response("200 OK", VOICES)),
+                // 最初に取得したトークンは無効になっている
+                "/tts/v1" if syntheses.fetch_add(1, Ordering::SeqCst) == 0 => {
+                    Some(response("401 Unauthorized", ""))
+                }
+                "/tts/v1" => Some(response("200 OK", "audio")),
+                _ => Some(response("404 Not Found", "")),
+            })
+            .await
+        };
+
+        let client = client(&url, &[]);
+        let audio: Vec<u8> = client
+            .make_speech(request("こんにちは", 1))
+            .await
+            .unwrap()
+            .into();
+        assert_eq!(audio, b"audio");
+        assert_eq!(tokens.load(Ordering::SeqCst), 2);
+
+        // 取得し直したトークンを使い続ける
+        client.make_speech(request("こんにちは", 2)).await.unwrap();
+        assert_eq!(tokens.load(Ordering::SeqCst), 2);
+        assert_eq!(syntheses.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn language_is_taken_from_locale() {
+        assert_eq!(client("http://localhost", &[]).language(), "ja");
+
+        let client = AzureClient::new(AzureOption {
+            subscription_key: "key".to_string(),
+            region: "test".to_string(),
+            locale: "en-US".to_string(),
+            voices: Vec::new(),
+        });
+        assert_eq!(client.language(), "en");
+    }
+
+    #[tokio::test]
+    async fn lists_voices_of_the_locale_once() {
+        let lists = Arc::new(AtomicUsize::new(0));
+        let url = {
+            let lists = lists.clone();
+            serve(move |path| match path {
+                "/token" => Some(response("200 OK", "token")),
+                "/tts/voices/list" => {
+                    lists.fetch_add(1, Ordering::SeqCst);
+                    Some(response("200 OK", VOICES))
+                }
+                _ => Some(response("404 Not Found", "")),
+            })
+            .await
+        };
+
+        let client = client(&url, &[]);
+        let presets = client.presets().await.unwrap();
+        let voices = presets
+            .iter()
+            .map(|x| (x.id, x.speaker_uuid.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            voices,
+            [(1, "ja-JP-KeitaNeural"), (2, "ja-JP-NanamiNeural")]
+        );
+        assert_eq!(presets[1].name, "七海（ja-JP-NanamiNeural）");
+
+        client.presets().await.unwrap();
+        assert_eq!(lists.load(Ordering::SeqCst), 1);
+        assert!(client
+            .voice_name(PresetId(3))
+            .await
+            .unwrap_err()
+            .to_string()
+            .contains("Preset 3"));
+    }
+
+    #[tokio::test]
+    async fn uses_configured_voices_in_order() {
+        let url = serve(|path| match path {
+            "/token" => Some(response("200 OK", "token")),
+            "/tts/voices/list" => Some(response("200 OK", VOICES)),
+            _ => Some(response("404 Not Found", "")),
+        })
+        .await;
+
+        let voices = client(&url, &["ja-JP-NanamiNeural"])
+            .voices()
+            .await
+            .unwrap();
+        assert_eq!(voices.len(), 1);
+        assert_eq!(voices[0].short_name, "ja-JP-NanamiNeural");
+
+        // 別の言語の声は使えない
+        let err = client(&url, &["en-US-JennyNeural"])
+            .voices()
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("en-US-JennyNeural"));
+    }
+
+    #[test]
+    fn builds_ssml_with_prosody() {
+        let ssml = build_ssml(&AzureSynthesisParams {
+            text: "<猫> & \"犬\"".to_string(),
+            voice: "ja-JP-NanamiNeural".to_string(),
+            speed_scale: Some(1.5),
+            pitch_scale: Some(-0.1),
+            volume_gain: None,
+        });
+        assert!(ssml.contains(r#"<voice name="ja-JP-NanamiNeural">"#));
+        assert!(ssml.contains(r#"rate="+50%" pitch="-10%" volume="+0%""#));
+        assert!(ssml.contains("&lt;猫&gt; &amp; &quot;犬&quot;"));
+    }
+}
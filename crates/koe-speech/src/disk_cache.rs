@@ -0,0 +1,592 @@
+use crate::{provider::SpeechProvider, speech::SpeechRequest};
+use anyhow::{Context as _, Result};
+use koe_audio::EncodedAudio;
+use log::{info, warn};
+use sha2::{Digest, Sha256};
+use std::{
+    fs::{self, File, FileTimes},
+    io::{self, Write as _},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::SystemTime,
+};
+use tokio::sync::Mutex;
+
+/// キャッシュのファイルの先頭に置く目印
+/// 保存する形式を変えた場合は、古いファイルを読まないよう変更する
+const MAGIC: &[u8; 8] = b"KOEAUD01";
+/// 目印と、音声のバイト数（u64、リトルエンディアン）からなるヘッダーの長さ
+const HEADER_LENGTH: usize = 16;
+/// キャッシュのファイルの拡張子
+const EXTENSION: &str = "audio";
+/// 書き込み中のファイルの拡張子、起動時に残っていれば削除する
+const TEMP_EXTENSION: &str = "tmp";
+
+/// 音声合成の結果をディレクトリに保存し、同じ文章を同じ声で読み上げる際に再利用する
+/// 接続時のお知らせや挨拶などの決まった文章は、再起動した後も音声合成を省ける
+///
+/// 合計のサイズが上限を超えると、最後に読み込んだ時刻（atime）が古いファイルから削除する。
+/// 壊れたファイルや書き込みが途中で止まったファイルは、ヘッダーの長さと一致しないため、保存されていないものとして扱う。
+pub struct DiskCache {
+    inner: Arc<dyn SpeechProvider>,
+    /// キーに含める、音声合成のエンジンの名前
+    namespace: String,
+    /// 複数のエンジンで共有する、保存先のディレクトリと利用状況
+    store: Arc<Store>,
+}
+
+struct Store {
+    dir: PathBuf,
+    max_size: u64,
+    /// 保存しているファイルの合計のサイズ
+    size: AtomicU64,
+    /// 上限を超えた場合の削除を、同時に1つだけ行う
+    prune_lock: Mutex<()>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+}
+
+pub struct DiskCacheOption {
+    /// キャッシュを保存するディレクトリ、存在しない場合は作成する
+    pub dir: PathBuf,
+    /// 保存するファイルの合計の最大のサイズ（バイト）
+    pub max_size: u64,
+    /// 音声合成のエンジンの名前、エンジンを切り替えた場合に別の音声として扱う
+    pub namespace: String,
+}
+
+/// キャッシュの利用状況
+#[derive(Debug, Clone, Copy)]
+pub struct DiskCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// 上限を超えたために削除したファイルの数
+    pub evictions: u64,
+    pub size: u64,
+    pub max_size: u64,
+}
+
+impl DiskCache {
+    /// ディレクトリを確認し、保存されているファイルの合計が上限を超えていれば古いものから削除する
+    pub async fn open(inner: Arc<dyn SpeechProvider>, option: DiskCacheOption) -> Result<Self> {
+        let dir = option.dir;
+        let max_size = option.max_size;
+
+        let scan_dir = dir.clone();
+        let result = tokio::task::spawn_blocking(move || {
+            fs::create_dir_all(&scan_dir)?;
+            prune(&scan_dir, max_size)
+        })
+        .await?
+        .with_context(|| format!("Failed to scan audio cache directory {}", dir.display()))?;
+        info!(
+            "Audio cache: {} bytes in {}, {} files evicted",
+            result.size,
+            dir.display(),
+            result.evicted
+        );
+
+        Ok(Self {
+            inner,
+            namespace: option.namespace,
+            store: Arc::new(Store {
+                dir,
+                max_size,
+                size: AtomicU64::new(result.size),
+                prune_lock: Mutex::new(()),
+                hits: AtomicU64::new(0),
+                misses: AtomicU64::new(0),
+                evictions: AtomicU64::new(result.evicted),
+            }),
+        })
+    }
+
+    /// 同じディレクトリと上限を共有して、別のエンジンの音声を保存する
+    /// エンジンの名前をキーに含めるため、別のエンジンの音声を使うことはない
+    pub fn share(&self, inner: Arc<dyn SpeechProvider>, namespace: String) -> Self {
+        Self {
+            inner,
+            namespace,
+            store: self.store.clone(),
+        }
+    }
+
+    pub fn inner(&self) -> &dyn SpeechProvider {
+        &*self.inner
+    }
+
+    /// ディレクトリを共有するすべてのエンジンを合わせた利用状況を返す
+    pub fn stats(&self) -> DiskCacheStats {
+        let store = &self.store;
+        DiskCacheStats {
+            hits: store.hits.load(Ordering::Relaxed),
+            misses: store.misses.load(Ordering::Relaxed),
+            evictions: store.evictions.load(Ordering::Relaxed),
+            size: store.size(),
+            max_size: store.max_size,
+        }
+    }
+
+    /// 保存されている音声があれば返し、なければ音声合成を行って保存する
+    /// 保存に失敗しても、合成した音声は返す
+    pub async fn make_speech(&self, request: SpeechRequest) -> Result<EncodedAudio> {
+        let path = self.path(&request);
+
+        if let Some(audio) = self.store.load(&path).await {
+            self.store.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(EncodedAudio::from(audio));
+        }
+        self.store.misses.fetch_add(1, Ordering::Relaxed);
+
+        let audio: Vec<u8> = self.inner.synthesize(request).await?.into();
+        if let Err(err) = self.store.store(&path, &audio).await {
+            warn!("Failed to store audio cache {}: {:?}", path.display(), err);
+        }
+
+        Ok(EncodedAudio::from(audio))
+    }
+
+    /// エンジン・声・文章から決まる、キャッシュのファイルのパス
+    /// ファイルが1つのディレクトリに集中しないよう、ハッシュの先頭2文字のディレクトリに分ける
+    fn path(&self, request: &SpeechRequest) -> PathBuf {
+        let mut hasher = Sha256::new();
+        hasher.update(self.namespace.as_bytes());
+        hasher.update([0]);
+        hasher.update(request.preset_id.0.to_le_bytes());
+        for scale in [
+            request.speed_scale,
+            request.pitch_scale,
+            request.volume_gain,
+        ] {
+            match scale {
+                Some(value) => {
+                    hasher.update([1]);
+                    hasher.update(value.to_bits().to_le_bytes());
+                }
+                None => hasher.update([0]),
+            }
+        }
+        hasher.update(request.text.as_bytes());
+
+        let hash = format!("{:x}", hasher.finalize());
+        self.store
+            .dir
+            .join(&hash[..2])
+            .join(format!("{}.{}", hash, EXTENSION))
+    }
+}
+
+impl Store {
+    /// 保存されている音声を読み込み、最後に読み込んだ時刻を更新する
+    /// 存在しない場合や壊れている場合は[`None`]を返し、壊れたファイルは削除する
+    async fn load(&self, path: &Path) -> Option<Vec<u8>> {
+        let path = path.to_path_buf();
+        let result = tokio::task::spawn_blocking(move || read_entry(&path)).await;
+
+        match result {
+            Ok(Ok(entry)) => entry,
+            Ok(Err(ReadError::Corrupted { path, size })) => {
+                warn!("Discarding corrupted audio cache {}", path.display());
+                if tokio::fs::remove_file(&path).await.is_ok() {
+                    self.sub_size(size);
+                }
+                None
+            }
+            Ok(Err(ReadError::Io(err))) => {
+                warn!("Failed to read audio cache: {:?}", err);
+                None
+            }
+            Err(err) => {
+                warn!("Failed to read audio cache: {:?}", err);
+                None
+            }
+        }
+    }
+
+    async fn store(&self, path: &Path, audio: &[u8]) -> Result<()> {
+        let mut buf = Vec::with_capacity(HEADER_LENGTH + audio.len());
+        buf.extend_from_slice(MAGIC);
+        buf.extend_from_slice(&(audio.len() as u64).to_le_bytes());
+        buf.extend_from_slice(audio);
+
+        let path = path.to_path_buf();
+        let (written, replaced) =
+            tokio::task::spawn_blocking(move || write_entry(&path, &buf)).await??;
+        self.size.fetch_add(written, Ordering::Relaxed);
+        self.sub_size(replaced);
+
+        if self.size() > self.max_size {
+            self.prune().await?;
+        }
+        Ok(())
+    }
+
+    /// 上限を超えている場合に、最後に読み込んだ時刻が古いファイルから削除する
+    /// すでに削除を行っている場合は何もしない
+    async fn prune(&self) -> Result<()> {
+        let _guard = match self.prune_lock.try_lock() {
+            Ok(guard) => guard,
+            Err(_) => return Ok(()),
+        };
+
+        let dir = self.dir.clone();
+        let max_size = self.max_size;
+        let result = tokio::task::spawn_blocking(move || prune(&dir, max_size)).await??;
+
+        self.size.store(result.size, Ordering::Relaxed);
+        self.evictions.fetch_add(result.evicted, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn size(&self) -> u64 {
+        self.size.load(Ordering::Relaxed)
+    }
+
+    /// 削除したファイルのサイズを合計から引く
+    fn sub_size(&self, size: u64) {
+        let _ = self
+            .size
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |current| {
+                Some(current.saturating_sub(size))
+            });
+    }
+}
+
+enum ReadError {
+    /// ヘッダーが正しくないか、音声の長さがヘッダーと一致しない
+    Corrupted {
+        path: PathBuf,
+        size: u64,
+    },
+    Io(io::Error),
+}
+
+fn read_entry(path: &Path) -> Result<Option<Vec<u8>>, ReadError> {
+    let mut buf = match fs::read(path) {
+        Ok(buf) => buf,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(ReadError::Io(err)),
+    };
+
+    let valid = buf.len() >= HEADER_LENGTH
+        && &buf[..8] == MAGIC
+        && buf[8..HEADER_LENGTH]
+            .try_into()
+            .map(u64::from_le_bytes)
+            .is_ok_and(|len| len == (buf.len() - HEADER_LENGTH) as u64);
+    if !valid {
+        return Err(ReadError::Corrupted {
+            path: path.to_path_buf(),
+            size: buf.len() as u64,
+        });
+    }
+
+    // atimeを記録しないファイルシステムもあるため、明示的に更新する
+    if let Err(err) = File::open(path)
+        .and_then(|file| file.set_times(FileTimes::new().set_accessed(SystemTime::now())))
+    {
+        warn!(
+            "Failed to update access time of audio cache {}: {:?}",
+            path.display(),
+            err
+        );
+    }
+
+    Ok(Some(buf.split_off(HEADER_LENGTH)))
+}
+
+/// 一時ファイルに書き込んでから置き換え、書き込んだサイズと置き換えたファイルのサイズを返す
+fn write_entry(path: &Path, buf: &[u8]) -> io::Result<(u64, u64)> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let temp_path = path.with_extension(TEMP_EXTENSION);
+    let mut file = File::create(&temp_path)?;
+    file.write_all(buf)?;
+    file.sync_data()?;
+    drop(file);
+
+    let replaced = fs::metadata(path).map_or(0, |metadata| metadata.len());
+    fs::rename(&temp_path, path)?;
+
+    Ok((buf.len() as u64, replaced))
+}
+
+struct PruneResult {
+    /// 削除した後の合計のサイズ
+    size: u64,
+    evicted: u64,
+}
+
+/// ディレクトリのファイルの合計のサイズを求め、上限を超えている場合は古いものから削除する
+/// 削除を繰り返さないよう、上限の9割まで削除する
+/// 書き込みの途中で残った一時ファイルも削除する
+fn prune(dir: &Path, max_size: u64) -> io::Result<PruneResult> {
+    let mut entries = Vec::new();
+    for shard in fs::read_dir(dir)? {
+        let shard = shard?;
+        if !shard.file_type()?.is_dir() {
+            continue;
+        }
+        for entry in fs::read_dir(shard.path())? {
+            let path = entry?.path();
+            match path.extension().and_then(|ext| ext.to_str()) {
+                Some(EXTENSION) => {
+                    let metadata = match fs::metadata(&path) {
+                        Ok(metadata) => metadata,
+                        // 同時に削除された
+                        Err(_) => continue,
+                    };
+                    let accessed = metadata
+                        .accessed()
+                        .or_else(|_| metadata.modified())
+                        .unwrap_or(SystemTime::UNIX_EPOCH);
+                    entries.push((accessed, metadata.len(), path));
+                }
+                Some(TEMP_EXTENSION) => {
+                    let _ = fs::remove_file(&path);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut size = entries.iter().map(|(_, len, _)| len).sum::<u64>();
+    let mut evicted = 0;
+    if size > max_size {
+        let target = max_size / 10 * 9;
+        entries.sort_by_key(|(accessed, _, _)| *accessed);
+        for (_, len, path) in entries {
+            if size <= target {
+                break;
+            }
+            if fs::remove_file(&path).is_ok() {
+                size -= len;
+                evicted += 1;
+            }
+        }
+    }
+
+    Ok(PruneResult { size, evicted })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{speech::PresetId, test_support::MockProvider};
+    use std::time::Duration;
+
+    /// テストごとに空のディレクトリを用意する
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("koe-disk-cache-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn request(text: &str) -> SpeechRequest {
+        SpeechRequest {
+            text: text.to_string(),
+            preset_id: PresetId(1),
+            speed_scale: None,
+            pitch_scale: None,
+            volume_gain: None,
+        }
+    }
+
+    async fn open(dir: &Path, provider: Arc<MockProvider>, max_size: u64) -> DiskCache {
+        DiskCache::open(
+            provider,
+            DiskCacheOption {
+                dir: dir.to_path_buf(),
+                max_size,
+                namespace: "voicevox".to_string(),
+            },
+        )
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn key_depends_on_every_request_field() {
+        let dir = temp_dir("key");
+        let cache = open(&dir, Arc::new(MockProvider::new()), u64::MAX).await;
+
+        let base = cache.path(&request("こんにちは"));
+        assert_eq!(base, cache.path(&request("こんにちは")));
+
+        let name = base.file_stem().unwrap().to_str().unwrap();
+        assert_eq!(name.len(), 64);
+        assert_eq!(base.parent().unwrap(), dir.join(&name[..2]));
+        assert_eq!(base.extension().unwrap(), EXTENSION);
+
+        let variants = [
+            request("こんにちは。"),
+            SpeechRequest {
+                preset_id: PresetId(2),
+                ..request("こんにちは")
+            },
+            SpeechRequest {
+                speed_scale: Some(1.0),
+                ..request("こんにちは")
+            },
+            SpeechRequest {
+                speed_scale: Some(1.1),
+                ..request("こんにちは")
+            },
+            SpeechRequest {
+                pitch_scale: Some(1.0),
+                ..request("こんにちは")
+            },
+            SpeechRequest {
+                volume_gain: Some(1.0),
+                ..request("こんにちは")
+            },
+        ];
+        let mut paths = variants.iter().map(|x| cache.path(x)).collect::<Vec<_>>();
+        paths.push(base);
+        paths.sort();
+        paths.dedup();
+        assert_eq!(paths.len(), variants.len() + 1);
+
+        let other = DiskCache::open(
+            Arc::new(MockProvider::new()),
+            DiskCacheOption {
+                dir: dir.clone(),
+                max_size: u64::MAX,
+                namespace: "azure".to_string(),
+            },
+        )
+        .await
+        .unwrap();
+        assert_ne!(
+            other.path(&request("こんにちは")),
+            cache.path(&request("こんにちは"))
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn reuses_stored_audio_across_instances() {
+        let dir = temp_dir("reuse");
+        let provider = Arc::new(MockProvider::new());
+
+        let cache = open(&dir, provider.clone(), u64::MAX).await;
+        let audio: Vec<u8> = cache.make_speech(request("挨拶")).await.unwrap().into();
+        let cached: Vec<u8> = cache.make_speech(request("挨拶")).await.unwrap().into();
+        assert_eq!(audio, cached);
+        assert_eq!(provider.requests().len(), 1);
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses), (1, 1));
+        assert_eq!(stats.size, (HEADER_LENGTH + audio.len()) as u64);
+
+        // 再起動した後も、保存した音声を使う
+        let reopened = open(&dir, provider.clone(), u64::MAX).await;
+        assert_eq!(reopened.stats().size, stats.size);
+        let reloaded: Vec<u8> = reopened.make_speech(request("挨拶")).await.unwrap().into();
+        assert_eq!(reloaded, audio);
+        assert_eq!(provider.requests().len(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn shared_caches_keep_engines_apart() {
+        let dir = temp_dir("shared");
+        let voicevox = Arc::new(MockProvider::new());
+        let azure = Arc::new(MockProvider::new());
+
+        let cache = open(&dir, voicevox.clone(), u64::MAX).await;
+        let shared = cache.share(azure.clone(), "azure".to_string());
+
+        cache.make_speech(request("挨拶")).await.unwrap();
+        shared.make_speech(request("挨拶")).await.unwrap();
+        shared.make_speech(request("挨拶")).await.unwrap();
+        assert_eq!(voicevox.requests().len(), 1);
+        assert_eq!(azure.requests().len(), 1);
+
+        // 利用状況はディレクトリ全体で数える
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses), (1, 2));
+        assert_eq!(shared.stats().size, stats.size);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn discards_corrupted_entries() {
+        let dir = temp_dir("corrupted");
+        let provider = Arc::new(MockProvider::new());
+        let cache = open(&dir, provider.clone(), u64::MAX).await;
+
+        let path = cache.path(&request("壊れた"));
+        let audio: Vec<u8> = cache.make_speech(request("壊れた")).await.unwrap().into();
+
+        // 目印が壊れたファイル
+        let buf = fs::read(&path).unwrap();
+        let mut corrupted = buf.clone();
+        corrupted[0] ^= 0xff;
+        fs::write(&path, &corrupted).unwrap();
+
+        let resynthesized: Vec<u8> = cache.make_speech(request("壊れた")).await.unwrap().into();
+        assert_eq!(resynthesized, audio);
+        assert_eq!(provider.requests().len(), 2);
+        assert_eq!(fs::read(&path).unwrap(), buf);
+        assert_eq!(cache.stats().size, buf.len() as u64);
+
+        // 書き込みが途中で止まったファイル
+        fs::write(&path, &buf[..buf.len() - 1]).unwrap();
+        assert!(matches!(
+            read_entry(&path),
+            Err(ReadError::Corrupted { size, .. }) if size == buf.len() as u64 - 1
+        ));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn prune_evicts_least_recently_read_entries() {
+        let dir = temp_dir("prune");
+        let now = SystemTime::now();
+
+        // 1000バイトのファイルを、古い順に4つ置く
+        let paths = (0..4)
+            .map(|i| {
+                let path = dir
+                    .join(format!("{:02}", i))
+                    .join(format!("{}.{}", i, EXTENSION));
+                write_entry(&path, &[0; 1000]).unwrap();
+                let accessed = now - Duration::from_secs(60 * (4 - i));
+                File::open(&path)
+                    .unwrap()
+                    .set_times(FileTimes::new().set_accessed(accessed))
+                    .unwrap();
+                path
+            })
+            .collect::<Vec<_>>();
+        let temp_path = dir.join("00").join(format!("left.{}", TEMP_EXTENSION));
+        fs::write(&temp_path, [0; 10]).unwrap();
+
+        // 上限以下なら、一時ファイルのみを削除する
+        let result = prune(&dir, 4000).unwrap();
+        assert_eq!((result.size, result.evicted), (4000, 0));
+        assert!(!temp_path.exists());
+
+        // 上限の9割（2700バイト）以下になるまで、古いものから削除する
+        let result = prune(&dir, 3000).unwrap();
+        assert_eq!((result.size, result.evicted), (2000, 2));
+        assert!(!paths[0].exists());
+        assert!(!paths[1].exists());
+        assert!(paths[2].exists());
+        assert!(paths[3].exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
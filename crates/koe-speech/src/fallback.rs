@@ -0,0 +1,342 @@
+use crate::{
+    retry::with_retry,
+    speech::{make_speech_with_preset, PresetId, SpeechRequest},
+    voicevox::{Preset, VoicevoxClient},
+};
+use anyhow::{anyhow, Context as _, Result};
+use koe_audio::EncodedAudio;
+use log::{info, warn};
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+use tokio::sync::Semaphore;
+
+/// 後に試すエンジンが残っている場合に、1つのエンジンの応答を待つ最大の時間
+/// 最後に試すエンジンは、応答に時間がかかっても待ち続ける
+const ATTEMPT_TIMEOUT: Duration = Duration::from_secs(15);
+/// エンジンから取得したプリセットの一覧を使い回す時間
+/// 1件のメッセージの読み上げで何度も`/presets`を呼び出さないようにする
+const PRESET_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// 複数のVOICEVOX ENGINEを順に試し、応答したエンジンで音声合成を行う
+///
+/// 失敗したエンジンは`cooldown`の間は後回しにし、他のエンジンがすべて失敗した場合のみ試す。
+/// エンジンによってプリセットが異なる場合は、同じIDのプリセットを使い、存在しなければIDが最小のプリセットを使う。
+pub struct FallbackClient {
+    engines: Vec<Engine>,
+    cooldown: Duration,
+    /// 同時に行う音声合成の数を制限する、[`None`]の場合は制限しない
+    semaphore: Option<Semaphore>,
+}
+
+pub struct FallbackOption {
+    /// この順にエンジンを試す
+    pub api_bases: Vec<String>,
+    /// 失敗したエンジンを後回しにする時間
+    pub cooldown: Duration,
+    /// 同時に行う音声合成の最大の数、[`None`]の場合は制限しない
+    pub max_concurrency: Option<usize>,
+    /// 受け取る音声の最大のサイズ（バイト）、[`None`]の場合は制限しない
+    pub max_audio_size: Option<usize>,
+}
+
+struct Engine {
+    /// ログと統計に表示する名前（APIのURL）
+    name: String,
+    client: VoicevoxClient,
+    /// 失敗したために後回しにする期限
+    open_until: Mutex<Option<Instant>>,
+    /// 音声合成に成功した回数
+    served: AtomicU64,
+    /// 取得したプリセットの一覧と、その時刻
+    presets: Mutex<Option<(Vec<Preset>, Instant)>>,
+}
+
+/// エンジンごとの状態
+#[derive(Debug, Clone)]
+pub struct EngineStatus {
+    pub name: String,
+    pub served: u64,
+    /// 失敗したために後回しにしているかどうか
+    pub circuit_open: bool,
+}
+
+impl FallbackClient {
+    pub fn new(option: FallbackOption) -> Self {
+        let engines = option
+            .api_bases
+            .into_iter()
+            .map(|api_base| Engine {
+                name: api_base.clone(),
+                client: VoicevoxClient::new(api_base).with_max_audio_size(option.max_audio_size),
+                open_until: Mutex::new(None),
+                served: AtomicU64::new(0),
+                presets: Mutex::new(None),
+            })
+            .collect();
+
+        Self {
+            engines,
+            cooldown: option.cooldown,
+            semaphore: option
+                .max_concurrency
+                .map(|permits| Semaphore::new(permits.max(1))),
+        }
+    }
+
+    /// 応答したエンジンのプリセットの一覧を返す
+    pub async fn presets(&self) -> Result<Vec<Preset>> {
+        let candidates = self.candidates();
+        let mut last_error = None;
+
+        for (i, engine) in candidates.iter().enumerate() {
+            let is_last = i + 1 == candidates.len();
+            match attempt(is_last, engine.presets()).await {
+                Ok(presets) => {
+                    engine.close();
+                    return Ok(presets);
+                }
+                Err(err) => {
+                    warn!("Failed to get presets from {}: {:?}", engine.name, err);
+                    engine.open(self.cooldown);
+                    last_error = Some(err);
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| anyhow!("No VOICEVOX engines are configured")))
+    }
+
+    /// 応答したエンジンで文章を音声に変換する
+    pub async fn make_speech(&self, request: SpeechRequest) -> Result<EncodedAudio> {
+        let _permit = match &self.semaphore {
+            Some(semaphore) => Some(semaphore.acquire().await?),
+            None => None,
+        };
+
+        let candidates = self.candidates();
+        let mut last_error = None;
+
+        for (i, engine) in candidates.iter().enumerate() {
+            let is_last = i + 1 == candidates.len();
+            match attempt(is_last, engine.make_speech(request.clone())).await {
+                Ok(audio) => {
+                    engine.close();
+                    engine.served.fetch_add(1, Ordering::Relaxed);
+                    if !std::ptr::eq(*engine, &self.engines[0]) {
+                        info!("Speech was served by fallback engine {}", engine.name);
+                    }
+                    return Ok(audio);
+                }
+                Err(err) => {
+                    warn!("Failed to make speech with {}: {:?}", engine.name, err);
+                    engine.open(self.cooldown);
+                    // エンジンが再起動してプリセットが変わった可能性があるため、次は取得し直す
+                    engine.invalidate_presets();
+                    last_error = Some(err);
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| anyhow!("No VOICEVOX engines are configured")))
+    }
+
+    pub fn statuses(&self) -> Vec<EngineStatus> {
+        self.engines
+            .iter()
+            .map(|engine| EngineStatus {
+                name: engine.name.clone(),
+                served: engine.served.load(Ordering::Relaxed),
+                circuit_open: engine.is_open(),
+            })
+            .collect()
+    }
+
+    /// 試す順に並べたエンジン
+    /// 後回しにしているエンジンは、設定された順を保ったまま末尾に移す
+    fn candidates(&self) -> Vec<&Engine> {
+        let (closed, open): (Vec<_>, Vec<_>) =
+            self.engines.iter().partition(|engine| !engine.is_open());
+        closed.into_iter().chain(open).collect()
+    }
+}
+
+impl Engine {
+    /// 一時的な失敗は、次のエンジンに移る前に同じエンジンで再試行する
+    async fn make_speech(&self, request: SpeechRequest) -> Result<EncodedAudio> {
+        with_retry(&format!("Speech synthesis with {}", self.name), || {
+            self.try_make_speech(request.clone())
+        })
+        .await
+    }
+
+    async fn try_make_speech(&self, request: SpeechRequest) -> Result<EncodedAudio> {
+        let presets = self.presets().await?;
+        let preset_id = map_preset_id(&presets, request.preset_id)
+            .context("The engine has no presets available")?;
+        let preset = presets
+            .iter()
+            .find(|p| PresetId(p.id) == preset_id)
+            .context("The engine has no presets available")?;
+
+        make_speech_with_preset(
+            &self.client,
+            preset,
+            SpeechRequest {
+                preset_id,
+                ..request
+            },
+        )
+        .await
+    }
+
+    /// プリセットの一覧を返す
+    /// [`PRESET_CACHE_TTL`]以内に取得した一覧があれば、エンジンに問い合わせずにそれを返す
+    async fn presets(&self) -> Result<Vec<Preset>> {
+        if let Some((presets, fetched_at)) = &*self.presets.lock().unwrap() {
+            if fetched_at.elapsed() < PRESET_CACHE_TTL {
+                return Ok(presets.clone());
+            }
+        }
+
+        let presets = self.client.presets().await?;
+        *self.presets.lock().unwrap() = Some((presets.clone(), Instant::now()));
+        Ok(presets)
+    }
+
+    fn invalidate_presets(&self) {
+        *self.presets.lock().unwrap() = None;
+    }
+
+    fn is_open(&self) -> bool {
+        match *self.open_until.lock().unwrap() {
+            Some(open_until) => Instant::now() < open_until,
+            None => false,
+        }
+    }
+
+    fn open(&self, cooldown: Duration) {
+        *self.open_until.lock().unwrap() = Some(Instant::now() + cooldown);
+    }
+
+    fn close(&self) {
+        *self.open_until.lock().unwrap() = None;
+    }
+}
+
+/// エンジンに同じIDのプリセットがあればそれを、なければIDが最小のプリセットを返す
+fn map_preset_id(presets: &[Preset], preset_id: PresetId) -> Option<PresetId> {
+    if presets.iter().any(|p| PresetId(p.id) == preset_id) {
+        return Some(preset_id);
+    }
+    presets.iter().map(|p| PresetId(p.id)).min_by_key(|id| id.0)
+}
+
+async fn attempt<T>(
+    is_last: bool,
+    future: impl std::future::Future<Output = Result<T>>,
+) -> Result<T> {
+    if is_last {
+        return future.await;
+    }
+    tokio::time::timeout(ATTEMPT_TIMEOUT, future)
+        .await
+        .unwrap_or_else(|_| Err(anyhow!("Timed out after {:?}", ATTEMPT_TIMEOUT)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_http::{response, serve};
+    use std::sync::{
+        atomic::{AtomicBool, AtomicUsize},
+        Arc,
+    };
+
+    const PRESETS: &str = r#"[{"id":1,"name":"preset","speaker_uuid":"uuid","style_id":2,"speedScale":1.0,"pitchScale":0.0,"intonationScale":1.0,"volumeScale":1.0,"prePhonemeLength":0.1,"postPhonemeLength":0.1}]"#;
+
+    /// `/presets`が呼ばれた回数を数え、`fail_synthesis`が`true`の間は音声合成に失敗するエンジンを起動する
+    async fn engine(preset_requests: Arc<AtomicUsize>, fail_synthesis: Arc<AtomicBool>) -> String {
+        serve(move |path| {
+            Some(match path {
+                "/presets" => {
+                    preset_requests.fetch_add(1, Ordering::Relaxed);
+                    response("200 OK", PRESETS)
+                }
+                "/audio_query_from_preset" => response("200 OK", "{}"),
+                "/synthesis" if fail_synthesis.load(Ordering::Relaxed) => {
+                    response("400 Bad Request", "")
+                }
+                "/synthesis" => response("200 OK", "audio"),
+                _ => response("404 Not Found", ""),
+            })
+        })
+        .await
+    }
+
+    fn client(api_base: String) -> FallbackClient {
+        FallbackClient::new(FallbackOption {
+            api_bases: vec![api_base],
+            cooldown: Duration::from_secs(60),
+            max_concurrency: None,
+            max_audio_size: None,
+        })
+    }
+
+    fn request(text: &str) -> SpeechRequest {
+        SpeechRequest {
+            text: text.to_string(),
+            preset_id: PresetId(1),
+            speed_scale: None,
+            pitch_scale: None,
+            volume_gain: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn presets_are_fetched_once_per_ttl() {
+        let preset_requests = Arc::new(AtomicUsize::new(0));
+        let client = client(engine(preset_requests.clone(), Arc::default()).await);
+
+        for text in ["一", "二", "三"] {
+            client.make_speech(request(text)).await.unwrap();
+        }
+        assert_eq!(client.presets().await.unwrap().len(), 1);
+
+        assert_eq!(preset_requests.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn presets_are_refetched_after_failure() {
+        let preset_requests = Arc::new(AtomicUsize::new(0));
+        let fail_synthesis = Arc::new(AtomicBool::new(true));
+        let client = client(engine(preset_requests.clone(), fail_synthesis.clone()).await);
+
+        assert!(client.make_speech(request("一")).await.is_err());
+        assert_eq!(preset_requests.load(Ordering::Relaxed), 1);
+
+        fail_synthesis.store(false, Ordering::Relaxed);
+        client.make_speech(request("二")).await.unwrap();
+        client.make_speech(request("三")).await.unwrap();
+        assert_eq!(preset_requests.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn presets_expire_after_ttl() {
+        let preset_requests = Arc::new(AtomicUsize::new(0));
+        let client = client(engine(preset_requests.clone(), Arc::default()).await);
+
+        client.presets().await.unwrap();
+        // 取得した時刻を、期限が切れる時刻まで戻す
+        if let Some((_, fetched_at)) = &mut *client.engines[0].presets.lock().unwrap() {
+            *fetched_at -= PRESET_CACHE_TTL;
+        }
+        client.presets().await.unwrap();
+
+        assert_eq!(preset_requests.load(Ordering::Relaxed), 2);
+    }
+}
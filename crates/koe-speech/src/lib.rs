@@ -1,2 +1,13 @@
+pub mod azure;
+pub mod disk_cache;
+pub mod fallback;
+pub mod open_jtalk;
+pub mod polly;
+pub mod provider;
+pub mod retry;
 pub mod speech;
+#[cfg(test)]
+mod test_http;
+#[cfg(any(test, feature = "test-support"))]
+pub mod test_support;
 pub mod voicevox;
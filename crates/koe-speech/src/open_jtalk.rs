@@ -0,0 +1,220 @@
+use crate::{
+    speech::{PresetId, SpeechRequest},
+    voicevox::Preset,
+};
+use anyhow::{anyhow, bail, ensure, Context as _, Result};
+use koe_audio::EncodedAudio;
+use std::{
+    path::{Path, PathBuf},
+    process::Stdio,
+    sync::atomic::{AtomicU64, Ordering},
+};
+use tokio::{io::AsyncWriteExt, process::Command};
+
+/// 声のファイルの拡張子
+const VOICE_EXTENSION: &str = "htsvoice";
+
+/// 一時ファイルの名前が重ならないようにするための連番
+static OUTPUT_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone)]
+pub struct OpenJTalkOption {
+    /// Open JTalkの実行ファイル、パスを含まない場合は`PATH`から探す
+    pub binary: PathBuf,
+    /// 辞書のディレクトリ
+    pub dictionary_dir: PathBuf,
+    /// `.htsvoice`ファイルを置いたディレクトリ
+    pub voice_dir: PathBuf,
+}
+
+/// Open JTalkの実行ファイルを呼び出して音声を合成する
+/// 外部のサービスやVOICEVOX ENGINEを用意せずに動かせるが、音質はVOICEVOXに劣る
+pub struct OpenJTalkClient {
+    binary: PathBuf,
+    dictionary_dir: PathBuf,
+    /// 起動時に見つかった声のファイル、ファイル名の順に並べ、プリセットIDは1から順に割り当てる
+    voices: Vec<PathBuf>,
+}
+
+impl OpenJTalkClient {
+    /// 実行ファイル・辞書・声のファイルが見つからない場合は、最初の読み上げを待たずにエラーを返す
+    pub fn new(option: OpenJTalkOption) -> Result<Self> {
+        let binary = find_executable(&option.binary).ok_or_else(|| {
+            anyhow!(
+                "Open JTalk executable {} is not found",
+                option.binary.display()
+            )
+        })?;
+
+        ensure!(
+            option.dictionary_dir.is_dir(),
+            "Open JTalk dictionary directory {} does not exist",
+            option.dictionary_dir.display()
+        );
+
+        let voices = find_voices(&option.voice_dir)?;
+        ensure!(
+            !voices.is_empty(),
+            "No .{} file is found in {}",
+            VOICE_EXTENSION,
+            option.voice_dir.display()
+        );
+
+        Ok(Self {
+            binary,
+            dictionary_dir: option.dictionary_dir,
+            voices,
+        })
+    }
+
+    fn voice(&self, preset_id: PresetId) -> Option<&Path> {
+        let index = usize::try_from(preset_id.0).ok()?.checked_sub(1)?;
+        self.voices.get(index).map(PathBuf::as_path)
+    }
+
+    /// 文章を、指定されたプリセットの声で音声に変換する
+    pub async fn synthesis(&self, request: SpeechRequest) -> Result<EncodedAudio> {
+        if request.text.trim().is_empty() {
+            bail!("Text to speak must not be empty");
+        }
+
+        let voice = self
+            .voice(request.preset_id)
+            .ok_or_else(|| anyhow!("Preset {} is not available", request.preset_id.0))?;
+
+        let output = std::env::temp_dir().join(format!(
+            "koe-open-jtalk-{}-{}.wav",
+            std::process::id(),
+            OUTPUT_SEQUENCE.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        let result = self
+            .run(&request, voice, &output)
+            .await
+            .context("Failed to run Open JTalk");
+        let audio = match result {
+            Ok(()) => tokio::fs::read(&output)
+                .await
+                .context("Failed to read Open JTalk output"),
+            Err(err) => Err(err),
+        };
+        let _ = tokio::fs::remove_file(&output).await;
+
+        Ok(EncodedAudio::from(audio?))
+    }
+
+    async fn run(&self, request: &SpeechRequest, voice: &Path, output: &Path) -> Result<()> {
+        let mut child = Command::new(&self.binary)
+            .arg("-x")
+            .arg(&self.dictionary_dir)
+            .arg("-m")
+            .arg(voice)
+            .arg("-ow")
+            .arg(output)
+            .arg("-r")
+            .arg(speed_rate(request.speed_scale).to_string())
+            .arg("-fm")
+            .arg(pitch_halftone(request.pitch_scale).to_string())
+            .arg("-g")
+            .arg(volume_db(request.volume_gain).to_string())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+
+        // Open JTalkは入力の1行目のみを読み上げるため、改行をまとめる
+        let text = request.text.replace(['\r', '\n'], " ");
+        let mut stdin = child.stdin.take().context("Failed to open stdin")?;
+        stdin.write_all(text.as_bytes()).await?;
+        stdin.write_all(b"\n").await?;
+        drop(stdin);
+
+        let result = child.wait_with_output().await?;
+        if !result.status.success() {
+            bail!(
+                "Open JTalk exited with {}: {}",
+                result.status,
+                String::from_utf8_lossy(&result.stderr).trim()
+            );
+        }
+
+        Ok(())
+    }
+
+    /// 声のファイルごとのプリセットを返す
+    /// 話速などはOpen JTalkの標準の値とする
+    pub fn presets(&self) -> Vec<Preset> {
+        self.voices
+            .iter()
+            .enumerate()
+            .map(|(index, path)| {
+                let name = path
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                Preset {
+                    id: index as i64 + 1,
+                    name: name.clone(),
+                    speaker_uuid: name,
+                    style_id: index as i64 + 1,
+                    speed_scale: 1.0,
+                    pitch_scale: 0.0,
+                    intonation_scale: 1.0,
+                    volume_scale: 1.0,
+                    pre_phoneme_length: 0.0,
+                    post_phoneme_length: 0.0,
+                }
+            })
+            .collect()
+    }
+}
+
+/// 実行ファイルのパスを返す
+/// パスの区切りを含まない場合は、`PATH`に含まれるディレクトリから探す
+fn find_executable(binary: &Path) -> Option<PathBuf> {
+    if binary.components().count() > 1 {
+        return binary.is_file().then(|| binary.to_path_buf());
+    }
+
+    let paths = std::env::var_os("PATH")?;
+    std::env::split_paths(&paths)
+        .map(|dir| dir.join(binary))
+        .find(|path| path.is_file())
+}
+
+fn find_voices(voice_dir: &Path) -> Result<Vec<PathBuf>> {
+    let entries = std::fs::read_dir(voice_dir).with_context(|| {
+        format!(
+            "Failed to read Open JTalk voice directory {}",
+            voice_dir.display()
+        )
+    })?;
+
+    let mut voices = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == VOICE_EXTENSION) {
+            voices.push(path);
+        }
+    }
+    voices.sort();
+
+    Ok(voices)
+}
+
+/// VOICEVOXの話速を、Open JTalkの話速の倍率に変換する
+fn speed_rate(speed_scale: Option<f64>) -> f64 {
+    speed_scale.unwrap_or(1.0).max(0.1)
+}
+
+/// VOICEVOXの音高を、Open JTalkの半音単位の音高に変換する
+/// VOICEVOXの音高は基本周波数の自然対数に加える値のため、半音（2の12乗根）の数に直す
+fn pitch_halftone(pitch_scale: Option<f64>) -> f64 {
+    pitch_scale.unwrap_or(0.0) * 12.0 / std::f64::consts::LN_2
+}
+
+/// 音量の倍率を、Open JTalkのデシベル単位の音量に変換する
+fn volume_db(volume_gain: Option<f64>) -> f64 {
+    20.0 * volume_gain.unwrap_or(1.0).max(0.01).log10()
+}
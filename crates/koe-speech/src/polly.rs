@@ -0,0 +1,424 @@
+use crate::{
+    azure::{escape_xml, RateLimited},
+    retry::{with_retry, TransientError},
+    speech::{PresetId, SpeechRequest},
+    voicevox::Preset,
+};
+use anyhow::{anyhow, bail, Context as _, Result};
+use aws_config::{retry::RetryConfig, timeout::TimeoutConfig, BehaviorVersion, Region};
+use aws_sdk_polly::{
+    config::{Credentials, ProvideCredentials as _},
+    error::{ProvideErrorMetadata as _, SdkError},
+    operation::synthesize_speech::SynthesizeSpeechError,
+    types::{OutputFormat, TextType, VoiceId},
+};
+use koe_audio::EncodedAudio;
+use std::time::Duration;
+
+/// 1回のリクエストの応答を待つ最大の時間
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 設定ファイルで指定された認証情報に付ける名前
+const CONFIG_CREDENTIALS_PROVIDER: &str = "koe-config";
+
+/// 使用できる日本語の声
+/// プリセットIDはこの順に1から割り当てるため、声を追加する場合は末尾に加える
+const VOICES: [PollyVoice; 5] = [
+    PollyVoice::new("Mizuki", Engine::Standard),
+    PollyVoice::new("Takumi", Engine::Standard),
+    PollyVoice::new("Takumi", Engine::Neural),
+    PollyVoice::new("Kazuha", Engine::Neural),
+    PollyVoice::new("Tomoko", Engine::Neural),
+];
+
+#[derive(Debug, Clone)]
+pub struct PollyOption {
+    /// リージョン（例: `ap-northeast-1`）
+    pub region: String,
+    /// アクセスキー、省略した場合はAWS SDKの既定の方法で認証情報を探す
+    pub access_key_id: Option<String>,
+    pub secret_access_key: Option<String>,
+    pub session_token: Option<String>,
+}
+
+/// Amazon Pollyの音声合成APIのクライアント
+pub struct PollyClient {
+    client: aws_sdk_polly::Client,
+}
+
+impl PollyClient {
+    /// 認証情報は、設定ファイルで指定されていればそれを使い、省略した場合はAWS SDKの既定の方法
+    /// （環境変数、共有の設定ファイル、Web Identity、ECS・EKSのコンテナ、EC2のインスタンスメタデータなど）で探す
+    /// いずれも見つからない場合はエラーを返す
+    pub async fn new(option: PollyOption) -> Result<Self> {
+        let mut loader = aws_config::defaults(BehaviorVersion::latest())
+            .region(Region::new(option.region))
+            .timeout_config(
+                TimeoutConfig::builder()
+                    .operation_attempt_timeout(REQUEST_TIMEOUT)
+                    .build(),
+            )
+            // 再試行は他のエンジンと同じく[`with_retry`]で行う
+            .retry_config(RetryConfig::disabled());
+        if let (Some(access_key_id), Some(secret_access_key)) =
+            (option.access_key_id, option.secret_access_key)
+        {
+            loader = loader.credentials_provider(Credentials::new(
+                access_key_id,
+                secret_access_key,
+                option.session_token,
+                None,
+                CONFIG_CREDENTIALS_PROVIDER,
+            ));
+        }
+        let config = loader.load().await;
+
+        // 認証情報が見つからない場合に、接続する前に検出する
+        config
+            .credentials_provider()
+            .context("No AWS credentials provider configured")?
+            .provide_credentials()
+            .await
+            .context("Failed to find AWS credentials")?;
+
+        Ok(Self {
+            client: aws_sdk_polly::Client::new(&config),
+        })
+    }
+
+    #[cfg(test)]
+    fn with_config(config: aws_sdk_polly::Config) -> Self {
+        Self {
+            client: aws_sdk_polly::Client::from_conf(config),
+        }
+    }
+
+    /// 声の一覧をプリセットとして返す
+    /// ほかのエンジンの声と区別できるよう、名前には`polly:`を付ける
+    pub fn presets(&self) -> Vec<Preset> {
+        VOICES
+            .iter()
+            .zip(1..)
+            .map(|(voice, id)| {
+                let name = voice.name();
+                Preset {
+                    id,
+                    name: name.clone(),
+                    speaker_uuid: name,
+                    style_id: id,
+                    speed_scale: 1.0,
+                    pitch_scale: 0.0,
+                    intonation_scale: 1.0,
+                    volume_scale: 1.0,
+                    pre_phoneme_length: 0.0,
+                    post_phoneme_length: 0.0,
+                }
+            })
+            .collect()
+    }
+
+    /// 文章を、指定されたプリセットの声で音声に変換する
+    /// 一時的な失敗やスロットリングの場合は、間隔を空けて再試行する
+    pub async fn make_speech(&self, request: SpeechRequest) -> Result<EncodedAudio> {
+        if request.text.trim().is_empty() {
+            bail!("Text to speak must not be empty");
+        }
+
+        let voice = voice(request.preset_id)?;
+        let params = PollySynthesisParams {
+            text: request.text,
+            voice_id: voice.id.to_string(),
+            engine: voice.engine,
+            speed_scale: request.speed_scale,
+            pitch_scale: request.pitch_scale,
+            volume_gain: request.volume_gain,
+        };
+
+        with_retry("Speech synthesis with Polly", || {
+            self.synthesis(params.clone())
+        })
+        .await
+    }
+
+    /// 文章を音声に変換する
+    /// スロットリングされた場合は[`RateLimited`]のエラーを返す
+    pub async fn synthesis(&self, params: PollySynthesisParams) -> Result<EncodedAudio> {
+        let output = self
+            .client
+            .synthesize_speech()
+            .engine(params.engine.into())
+            .output_format(OutputFormat::OggVorbis)
+            .text(build_ssml(&params))
+            .text_type(TextType::Ssml)
+            .voice_id(VoiceId::from(params.voice_id.as_str()))
+            .send()
+            .await
+            .map_err(classify_sdk_error)?;
+        let audio = output
+            .audio_stream
+            .collect()
+            .await
+            .map_err(|err| anyhow::Error::new(err).context(TransientError))?;
+
+        Ok(EncodedAudio::from(audio.into_bytes().to_vec()))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PollySynthesisParams {
+    pub text: String,
+    /// 声の名前（例: `Takumi`）
+    pub voice_id: String,
+    pub engine: Engine,
+    /// 話速、1.0が標準
+    pub speed_scale: Option<f64>,
+    /// 音高、0.0が標準（VOICEVOXの`pitchScale`と同じ範囲）、ニューラル音声では使わない
+    pub pitch_scale: Option<f64>,
+    /// 音量の倍率、1.0が標準
+    pub volume_gain: Option<f64>,
+}
+
+/// 音声合成の方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Engine {
+    Standard,
+    Neural,
+}
+
+impl From<Engine> for aws_sdk_polly::types::Engine {
+    fn from(engine: Engine) -> Self {
+        match engine {
+            Engine::Standard => aws_sdk_polly::types::Engine::Standard,
+            Engine::Neural => aws_sdk_polly::types::Engine::Neural,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PollyVoice {
+    id: &'static str,
+    engine: Engine,
+}
+
+impl PollyVoice {
+    const fn new(id: &'static str, engine: Engine) -> Self {
+        Self { id, engine }
+    }
+
+    /// プリセットの名前（例: `polly:Takumi`、`polly:Takumi (neural)`）
+    fn name(&self) -> String {
+        match self.engine {
+            Engine::Standard => format!("polly:{}", self.id),
+            Engine::Neural => format!("polly:{} (neural)", self.id),
+        }
+    }
+}
+
+fn voice(preset_id: PresetId) -> Result<PollyVoice> {
+    usize::try_from(preset_id.0)
+        .ok()
+        .and_then(|id| id.checked_sub(1))
+        .and_then(|index| VOICES.get(index))
+        .copied()
+        .ok_or_else(|| anyhow!("Preset {} is not available", preset_id.0))
+}
+
+/// AWS SDKのエラーを、[`with_retry`]が再試行するかを判断できるエラーにする
+/// スロットリング（`ThrottlingException`、429）は[`RateLimited`]、
+/// タイムアウトや接続の失敗、サーバーのエラー（5xx）は[`TransientError`]を文脈に付け加えたエラーとして返す
+fn classify_sdk_error(err: SdkError<SynthesizeSpeechError>) -> anyhow::Error {
+    let status = err.raw_response().map(|resp| resp.status());
+    // Pollyはスロットリングを400で返すことがあるため、エラーの種類も確かめる
+    let throttled = err.code() == Some("ThrottlingException")
+        || status.is_some_and(|status| status.as_u16() == 429);
+    if throttled {
+        return RateLimited { retry_after: None }.into();
+    }
+
+    let transient = matches!(
+        err,
+        SdkError::TimeoutError(_) | SdkError::DispatchFailure(_)
+    ) || status.is_some_and(|status| status.is_server_error());
+    let err = anyhow::Error::new(err);
+    if transient {
+        err.context(TransientError)
+    } else {
+        err
+    }
+}
+
+/// 話速・音高・音量を指定したSSMLを組み立てる
+/// Pollyには話速を直接指定するパラメータがないため、`prosody`で指定する
+fn build_ssml(params: &PollySynthesisParams) -> String {
+    // 話速は標準を100%とする百分率、Pollyで指定できる20%から200%に収める
+    let rate = (params.speed_scale.unwrap_or(1.0) * 100.0).clamp(20.0, 200.0);
+    let volume = 20.0 * params.volume_gain.unwrap_or(1.0).max(0.01).log10();
+
+    // ニューラル音声は音高の指定に対応していない
+    let pitch = match params.engine {
+        Engine::Standard => format!(
+            r#" pitch="{:+.0}%""#,
+            params.pitch_scale.unwrap_or(0.0) * 100.0
+        ),
+        Engine::Neural => String::new(),
+    };
+
+    format!(
+        r#"<speak><prosody rate="{:.0}%" volume="{:+.1}dB"{}>{}</prosody></speak>"#,
+        rate,
+        volume,
+        pitch,
+        escape_xml(&params.text)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        retry::{classify, ErrorClass},
+        test_http::{response, serve},
+    };
+
+    fn request(text: &str, preset_id: i64) -> SpeechRequest {
+        SpeechRequest {
+            text: text.to_string(),
+            preset_id: PresetId(preset_id),
+            speed_scale: None,
+            pitch_scale: None,
+            volume_gain: None,
+        }
+    }
+
+    /// `endpoint`に送信する、固定の認証情報を使うクライアント
+    fn client(endpoint: &str) -> PollyClient {
+        PollyClient::with_config(
+            aws_sdk_polly::Config::builder()
+                .behavior_version(BehaviorVersion::latest())
+                .region(Region::new("ap-northeast-1"))
+                .credentials_provider(Credentials::new(
+                    "AKIDEXAMPLE",
+                    "secret",
+                    None,
+                    None,
+                    "test",
+                ))
+                .endpoint_url(endpoint)
+                .retry_config(RetryConfig::disabled())
+                .build(),
+        )
+    }
+
+    #[test]
+    fn builds_ssml_for_each_engine() {
+        let params = |engine| PollySynthesisParams {
+            text: "<こんにちは>".to_string(),
+            voice_id: "Takumi".to_string(),
+            engine,
+            speed_scale: Some(1.2),
+            pitch_scale: Some(0.05),
+            volume_gain: Some(2.0),
+        };
+
+        assert_eq!(
+            build_ssml(&params(Engine::Standard)),
+            r#"<speak><prosody rate="120%" volume="+6.0dB" pitch="+5%">&lt;こんにちは&gt;</prosody></speak>"#
+        );
+        assert_eq!(
+            build_ssml(&params(Engine::Neural)),
+            r#"<speak><prosody rate="120%" volume="+6.0dB">&lt;こんにちは&gt;</prosody></speak>"#
+        );
+    }
+
+    #[test]
+    fn lists_japanese_voices_with_prefix() {
+        let names = client("http://localhost")
+            .presets()
+            .into_iter()
+            .map(|x| (x.id, x.name))
+            .collect::<Vec<_>>();
+        assert_eq!(names[0], (1, "polly:Mizuki".to_string()));
+        assert_eq!(names[2], (3, "polly:Takumi (neural)".to_string()));
+        assert!(voice(PresetId(6)).is_err());
+    }
+
+    #[tokio::test]
+    async fn synthesizes_speech() {
+        let url = serve(|path| match path {
+            "/v1/speech" => Some(response("200 OK", "OggS")),
+            _ => Some(response("404 Not Found", "")),
+        })
+        .await;
+
+        let audio = client(&url)
+            .make_speech(request("こんにちは", 3))
+            .await
+            .unwrap();
+        assert_eq!(Vec::from(audio), b"OggS");
+    }
+
+    #[tokio::test]
+    async fn throttling_is_retryable() {
+        let url = serve(|_| {
+            Some(
+                "HTTP/1.1 400 Bad Request\r\nx-amzn-ErrorType: ThrottlingException\r\ncontent-type: application/json\r\ncontent-length: 2\r\nconnection: close\r\n\r\n{}"
+                    .to_string(),
+            )
+        })
+        .await;
+
+        let voice = voice(PresetId(1)).unwrap();
+        let result = client(&url)
+            .synthesis(PollySynthesisParams {
+                text: "こんにちは".to_string(),
+                voice_id: voice.id.to_string(),
+                engine: voice.engine,
+                speed_scale: None,
+                pitch_scale: None,
+                volume_gain: None,
+            })
+            .await;
+        let err = match result {
+            Ok(_) => panic!("expected throttling"),
+            Err(err) => err,
+        };
+        assert_eq!(classify(&err), ErrorClass::Retryable { retry_after: None });
+    }
+
+    #[tokio::test]
+    async fn server_errors_are_retryable_and_client_errors_are_not() {
+        let url = serve(|path| match path {
+            "/v1/speech" => Some(response("503 Service Unavailable", "{}")),
+            _ => None,
+        })
+        .await;
+        let err = client(&url)
+            .synthesis(PollySynthesisParams {
+                text: "こんにちは".to_string(),
+                voice_id: "Mizuki".to_string(),
+                engine: Engine::Standard,
+                speed_scale: None,
+                pitch_scale: None,
+                volume_gain: None,
+            })
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(classify(&err), ErrorClass::Retryable { retry_after: None });
+
+        let url = serve(|_| Some(response("400 Bad Request", "{}"))).await;
+        let err = client(&url)
+            .synthesis(PollySynthesisParams {
+                text: "こんにちは".to_string(),
+                voice_id: "Mizuki".to_string(),
+                engine: Engine::Standard,
+                speed_scale: None,
+                pitch_scale: None,
+                volume_gain: None,
+            })
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(classify(&err), ErrorClass::Permanent);
+    }
+}
@@ -0,0 +1,331 @@
+use crate::{
+    azure::AzureClient,
+    disk_cache::{DiskCache, DiskCacheStats},
+    fallback::{EngineStatus, FallbackClient},
+    open_jtalk::OpenJTalkClient,
+    polly::PollyClient,
+    speech::{make_speech, SpeechRequest},
+    voicevox::{Preset, VoicevoxClient},
+};
+use anyhow::Result;
+use async_trait::async_trait;
+use koe_audio::EncodedAudio;
+use std::sync::Arc;
+
+/// 音声合成のエンジンの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProviderKind {
+    Voicevox,
+    OpenJTalk,
+    Azure,
+    Polly,
+}
+
+impl ProviderKind {
+    /// 設定ファイルの項目名と同じ名前
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProviderKind::Voicevox => "voicevox",
+            ProviderKind::OpenJTalk => "open_jtalk",
+            ProviderKind::Azure => "azure",
+            ProviderKind::Polly => "polly",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "voicevox" => Some(ProviderKind::Voicevox),
+            "open_jtalk" => Some(ProviderKind::OpenJTalk),
+            "azure" => Some(ProviderKind::Azure),
+            "polly" => Some(ProviderKind::Polly),
+            _ => None,
+        }
+    }
+
+    /// 表示に使う名前
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            ProviderKind::Voicevox => "VOICEVOX",
+            ProviderKind::OpenJTalk => "Open JTalk",
+            ProviderKind::Azure => "Azure",
+            ProviderKind::Polly => "Amazon Polly",
+        }
+    }
+}
+
+/// 設定ファイルで指定された音声合成のエンジンの一覧
+/// 最初に追加したエンジンを、サーバーで選択されていない場合に使う既定のエンジンとする
+pub struct Providers {
+    entries: Vec<(ProviderKind, Arc<dyn SpeechProvider>)>,
+}
+
+impl Providers {
+    pub fn new(kind: ProviderKind, provider: Arc<dyn SpeechProvider>) -> Self {
+        Self {
+            entries: vec![(kind, provider)],
+        }
+    }
+
+    /// エンジンを追加する、同じ種類のエンジンがすでにある場合は置き換える
+    pub fn add(&mut self, kind: ProviderKind, provider: Arc<dyn SpeechProvider>) {
+        match self.entries.iter_mut().find(|(k, _)| *k == kind) {
+            Some(entry) => entry.1 = provider,
+            None => self.entries.push((kind, provider)),
+        }
+    }
+
+    pub fn default_kind(&self) -> ProviderKind {
+        self.entries[0].0
+    }
+
+    /// 使用できるエンジンの種類を、追加した順に返す
+    pub fn kinds(&self) -> Vec<ProviderKind> {
+        self.entries.iter().map(|(kind, _)| *kind).collect()
+    }
+
+    pub fn get(&self, kind: ProviderKind) -> Option<&Arc<dyn SpeechProvider>> {
+        self.entries
+            .iter()
+            .find(|(k, _)| *k == kind)
+            .map(|(_, provider)| provider)
+    }
+
+    /// 選択されたエンジンを返す
+    /// 選択されていない場合や、選択されたエンジンが設定ファイルから削除された場合は既定のエンジンを返す
+    pub fn resolve(&self, kind: Option<ProviderKind>) -> (ProviderKind, Arc<dyn SpeechProvider>) {
+        let (kind, provider) = kind
+            .and_then(|kind| self.entries.iter().find(|(k, _)| *k == kind))
+            .unwrap_or(&self.entries[0]);
+        (*kind, provider.clone())
+    }
+
+    /// 指定された言語を読み上げられるエンジンのうち、最初に追加したものを返す
+    pub fn find_language(&self, language: &str) -> Option<(ProviderKind, Arc<dyn SpeechProvider>)> {
+        self.entries
+            .iter()
+            .find(|(_, provider)| provider.language() == language)
+            .map(|(kind, provider)| (*kind, provider.clone()))
+    }
+
+    /// 読み上げられる言語を、重複を除いてエンジンを追加した順に返す
+    pub fn languages(&self) -> Vec<String> {
+        let mut languages = Vec::<String>::new();
+        for (_, provider) in &self.entries {
+            if !languages.iter().any(|x| x == provider.language()) {
+                languages.push(provider.language().to_string());
+            }
+        }
+        languages
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (ProviderKind, &Arc<dyn SpeechProvider>)> {
+        self.entries
+            .iter()
+            .map(|(kind, provider)| (*kind, provider))
+    }
+}
+
+/// 文章を音声に変換する
+/// 音声合成のエンジンを差し替えられるようにしている
+#[async_trait]
+pub trait SpeechProvider: Send + Sync {
+    /// 文章を、指定されたプリセットの声で音声に変換する
+    async fn synthesize(&self, request: SpeechRequest) -> Result<EncodedAudio>;
+
+    /// 使用できる声（プリセット）の一覧を返す
+    async fn list_voices(&self) -> Result<Vec<Preset>>;
+
+    /// 使い分けているエンジンごとの状態を返す
+    /// 1つのエンジンのみを使う実装では空になる
+    fn engine_statuses(&self) -> Vec<EngineStatus> {
+        Vec::new()
+    }
+
+    /// 長い文章を文ごとに合成して、合成できたものから順に再生してよいかどうか
+    /// 文ごとに合成すると抑揚が変わってしまうエンジンや、1回の合成に時間がかからないエンジンでは`false`にする
+    fn supports_streaming(&self) -> bool {
+        false
+    }
+
+    /// 音声をディスクに保存している場合は、その利用状況を返す
+    fn disk_cache_stats(&self) -> Option<DiskCacheStats> {
+        None
+    }
+
+    /// 読み上げられる言語（ISO 639-1の言語コード、例: `ja`）
+    fn language(&self) -> &str {
+        "ja"
+    }
+}
+
+#[async_trait]
+impl SpeechProvider for VoicevoxClient {
+    async fn synthesize(&self, request: SpeechRequest) -> Result<EncodedAudio> {
+        make_speech(self, request).await
+    }
+
+    async fn list_voices(&self) -> Result<Vec<Preset>> {
+        self.presets().await
+    }
+
+    fn supports_streaming(&self) -> bool {
+        true
+    }
+}
+
+#[async_trait]
+impl SpeechProvider for FallbackClient {
+    async fn synthesize(&self, request: SpeechRequest) -> Result<EncodedAudio> {
+        self.make_speech(request).await
+    }
+
+    async fn list_voices(&self) -> Result<Vec<Preset>> {
+        self.presets().await
+    }
+
+    fn engine_statuses(&self) -> Vec<EngineStatus> {
+        self.statuses()
+    }
+
+    fn supports_streaming(&self) -> bool {
+        true
+    }
+}
+
+#[async_trait]
+impl SpeechProvider for OpenJTalkClient {
+    async fn synthesize(&self, request: SpeechRequest) -> Result<EncodedAudio> {
+        self.synthesis(request).await
+    }
+
+    async fn list_voices(&self) -> Result<Vec<Preset>> {
+        Ok(self.presets())
+    }
+}
+
+#[async_trait]
+impl SpeechProvider for AzureClient {
+    async fn synthesize(&self, request: SpeechRequest) -> Result<EncodedAudio> {
+        self.make_speech(request).await
+    }
+
+    async fn list_voices(&self) -> Result<Vec<Preset>> {
+        self.presets().await
+    }
+
+    fn language(&self) -> &str {
+        self.language()
+    }
+}
+
+#[async_trait]
+impl SpeechProvider for PollyClient {
+    async fn synthesize(&self, request: SpeechRequest) -> Result<EncodedAudio> {
+        self.make_speech(request).await
+    }
+
+    async fn list_voices(&self) -> Result<Vec<Preset>> {
+        Ok(self.presets())
+    }
+}
+
+#[async_trait]
+impl SpeechProvider for DiskCache {
+    async fn synthesize(&self, request: SpeechRequest) -> Result<EncodedAudio> {
+        self.make_speech(request).await
+    }
+
+    async fn list_voices(&self) -> Result<Vec<Preset>> {
+        self.inner().list_voices().await
+    }
+
+    fn engine_statuses(&self) -> Vec<EngineStatus> {
+        self.inner().engine_statuses()
+    }
+
+    fn supports_streaming(&self) -> bool {
+        self.inner().supports_streaming()
+    }
+
+    fn disk_cache_stats(&self) -> Option<DiskCacheStats> {
+        Some(self.stats())
+    }
+
+    fn language(&self) -> &str {
+        self.inner().language()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::MockProvider;
+
+    #[tokio::test]
+    async fn resolves_to_default_unless_configured() {
+        let mut providers = Providers::new(
+            ProviderKind::Voicevox,
+            Arc::new(MockProvider::new().with_voices(&[1])),
+        );
+        providers.add(
+            ProviderKind::Azure,
+            Arc::new(MockProvider::new().with_voices(&[2])),
+        );
+        assert_eq!(providers.default_kind(), ProviderKind::Voicevox);
+        assert_eq!(
+            providers.kinds(),
+            [ProviderKind::Voicevox, ProviderKind::Azure]
+        );
+
+        let voice_ids = |provider: Arc<dyn SpeechProvider>| async move {
+            let voices = provider.list_voices().await.unwrap();
+            voices.into_iter().map(|x| x.id).collect::<Vec<_>>()
+        };
+
+        let (kind, provider) = providers.resolve(Some(ProviderKind::Azure));
+        assert_eq!(kind, ProviderKind::Azure);
+        assert_eq!(voice_ids(provider).await, [2]);
+
+        // 設定されていないエンジンや未選択の場合は既定のエンジンを使う
+        for selected in [Some(ProviderKind::OpenJTalk), None] {
+            let (kind, provider) = providers.resolve(selected);
+            assert_eq!(kind, ProviderKind::Voicevox);
+            assert_eq!(voice_ids(provider).await, [1]);
+        }
+    }
+
+    #[test]
+    fn finds_first_provider_of_language() {
+        let mut providers = Providers::new(
+            ProviderKind::Voicevox,
+            Arc::new(MockProvider::new().with_voices(&[1])),
+        );
+        providers.add(
+            ProviderKind::OpenJTalk,
+            Arc::new(MockProvider::new().with_voices(&[2])),
+        );
+        providers.add(
+            ProviderKind::Azure,
+            Arc::new(MockProvider::new().with_voices(&[3]).with_language("en")),
+        );
+
+        assert_eq!(providers.languages(), ["ja", "en"]);
+        let kind = |language| providers.find_language(language).map(|(kind, _)| kind);
+        assert_eq!(kind("ja"), Some(ProviderKind::Voicevox));
+        assert_eq!(kind("en"), Some(ProviderKind::Azure));
+        assert_eq!(kind("fr"), None);
+    }
+
+    #[test]
+    fn kind_names_round_trip() {
+        for kind in [
+            ProviderKind::Voicevox,
+            ProviderKind::OpenJTalk,
+            ProviderKind::Azure,
+            ProviderKind::Polly,
+        ] {
+            assert_eq!(ProviderKind::parse(kind.as_str()), Some(kind));
+        }
+        assert_eq!(ProviderKind::parse("google"), None);
+    }
+}
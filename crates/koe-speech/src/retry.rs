@@ -0,0 +1,314 @@
+use crate::azure::RateLimited;
+use anyhow::Result;
+use log::warn;
+use rand::Rng;
+use std::{fmt, future::Future, time::Duration};
+
+/// 一時的な失敗を再試行する最大の回数
+const MAX_RETRIES: u32 = 3;
+/// 1回目の再試行までの時間、以降は再試行するたびに倍にする
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+/// `Retry-After`で指定された時間を待つ上限
+/// これより長く待つ場合は、再試行せずに失敗として扱う
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(10);
+
+/// 再試行しても成功する見込みのない失敗を表すエラー
+/// 不正なリクエストや認証の失敗など、失敗の原因となったエラーの文脈として付け加える
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PermanentError;
+
+impl fmt::Display for PermanentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Speech request failed permanently")
+    }
+}
+
+impl std::error::Error for PermanentError {}
+
+/// 時間をおいて再試行すれば成功する可能性のある失敗を表すエラー
+/// HTTPのステータスから分類できない失敗（AWS SDKのタイムアウトなど）の文脈として付け加える
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransientError;
+
+impl fmt::Display for TransientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Speech request failed temporarily")
+    }
+}
+
+impl std::error::Error for TransientError {}
+
+/// 失敗の分類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    /// 時間をおいて再試行すれば成功する可能性がある
+    Retryable {
+        /// サーバーから指定された、再試行までに待つべき時間
+        retry_after: Option<Duration>,
+    },
+    Permanent,
+}
+
+/// 音声合成APIの呼び出しで発生したエラーを分類する
+/// サーバーのエラー（5xx）、タイムアウト、接続のエラー、レート制限（429）、[`TransientError`]は再試行でき、それ以外は再試行しない
+pub fn classify(err: &anyhow::Error) -> ErrorClass {
+    // 文脈として付け加えたエラーは`chain`では取り出せないため、先に確かめる
+    if err.downcast_ref::<TransientError>().is_some() {
+        return ErrorClass::Retryable { retry_after: None };
+    }
+
+    for cause in err.chain() {
+        if let Some(rate_limited) = cause.downcast_ref::<RateLimited>() {
+            return ErrorClass::Retryable {
+                retry_after: rate_limited.retry_after,
+            };
+        }
+
+        if let Some(err) = cause.downcast_ref::<reqwest::Error>() {
+            if let Some(status) = err.status() {
+                return if status.is_server_error() || status.as_u16() == 429 {
+                    ErrorClass::Retryable { retry_after: None }
+                } else {
+                    ErrorClass::Permanent
+                };
+            }
+            if err.is_timeout() || err.is_connect() || err.is_request() || err.is_body() {
+                return ErrorClass::Retryable { retry_after: None };
+            }
+        }
+    }
+
+    ErrorClass::Permanent
+}
+
+/// `operation`を実行し、一時的な失敗の場合は間隔を空けて再試行する
+/// 間隔は再試行するたびに倍にしてばらつきを加え、`Retry-After`が指定された場合はそれに従う
+/// 再試行できない失敗の場合は、[`PermanentError`]を文脈に付け加えてすぐに返す
+/// 再試行の回数が上限に達した場合は、最後の失敗を返す
+pub async fn with_retry<T, F, Fut>(name: &str, mut operation: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut backoff = INITIAL_BACKOFF;
+    let mut retry = 0;
+
+    loop {
+        let err = match operation().await {
+            Ok(value) => return Ok(value),
+            Err(err) => err,
+        };
+
+        let retry_after = match classify(&err) {
+            ErrorClass::Permanent => return Err(err.context(PermanentError)),
+            ErrorClass::Retryable { .. } if retry >= MAX_RETRIES => return Err(err),
+            ErrorClass::Retryable {
+                retry_after: Some(retry_after),
+            } if retry_after > MAX_RETRY_AFTER => return Err(err),
+            ErrorClass::Retryable { retry_after } => retry_after,
+        };
+
+        let delay = retry_after.unwrap_or_else(|| {
+            // 同時に失敗した要求が一斉に再試行しないよう、間隔の半分までのばらつきを加える
+            let jitter = rand::thread_rng().gen_range(0.0..=0.5);
+            backoff.mul_f64(1.0 + jitter)
+        });
+        warn!(
+            "{} failed, retrying in {:?} (retry {} of {}): {:?}",
+            name,
+            delay,
+            retry + 1,
+            MAX_RETRIES,
+            err
+        );
+        tokio::time::sleep(delay).await;
+        backoff *= 2;
+        retry += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        azure::check_rate_limit,
+        test_http::{response, serve},
+        voicevox::VoicevoxClient,
+    };
+    use anyhow::anyhow;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use tokio::{net::TcpListener, time::Instant};
+
+    fn rate_limited(retry_after: Option<Duration>) -> anyhow::Error {
+        RateLimited { retry_after }.into()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn returns_last_error_after_max_retries() {
+        let calls = AtomicU32::new(0);
+        let err = with_retry("test", || async {
+            let call = calls.fetch_add(1, Ordering::Relaxed);
+            Err::<(), _>(rate_limited(None).context(format!("call {}", call)))
+        })
+        .await
+        .unwrap_err();
+
+        assert_eq!(calls.load(Ordering::Relaxed), MAX_RETRIES + 1);
+        assert_eq!(err.to_string(), format!("call {}", MAX_RETRIES));
+        assert!(err.downcast_ref::<PermanentError>().is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn succeeds_after_transient_failures() {
+        let calls = AtomicU32::new(0);
+        let start = Instant::now();
+        let value = with_retry("test", || async {
+            match calls.fetch_add(1, Ordering::Relaxed) {
+                0 | 1 => Err(rate_limited(None)),
+                _ => Ok(42),
+            }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(value, 42);
+        assert_eq!(calls.load(Ordering::Relaxed), 3);
+        // 1回目は0.5〜0.75秒、2回目は1〜1.5秒待つ
+        let elapsed = start.elapsed();
+        assert!(elapsed >= INITIAL_BACKOFF * 3, "{:?}", elapsed);
+        assert!(elapsed <= INITIAL_BACKOFF.mul_f64(4.5), "{:?}", elapsed);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn honors_retry_after() {
+        let calls = AtomicU32::new(0);
+        let start = Instant::now();
+        with_retry("test", || async {
+            match calls.fetch_add(1, Ordering::Relaxed) {
+                0 => Err(rate_limited(Some(Duration::from_secs(7)))),
+                _ => Ok(()),
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(start.elapsed(), Duration::from_secs(7));
+
+        // 待つ時間が長すぎる場合は再試行しない
+        let calls = AtomicU32::new(0);
+        let result = with_retry("test", || async {
+            calls.fetch_add(1, Ordering::Relaxed);
+            Err::<(), _>(rate_limited(Some(MAX_RETRY_AFTER * 2)))
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn permanent_error_is_not_retried() {
+        let calls = AtomicU32::new(0);
+        let err = with_retry("test", || async {
+            calls.fetch_add(1, Ordering::Relaxed);
+            Err::<(), _>(anyhow!("invalid request"))
+        })
+        .await
+        .unwrap_err();
+
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+        assert!(err.downcast_ref::<PermanentError>().is_some());
+    }
+
+    fn status(line: &str) -> String {
+        response(line, "")
+    }
+
+    async fn presets_error(response: String) -> anyhow::Error {
+        let client = VoicevoxClient::new(serve(move |_| Some(response.clone())).await);
+        client.presets().await.unwrap_err()
+    }
+
+    #[tokio::test]
+    async fn server_errors_are_retryable() {
+        for line in [
+            "500 Internal Server Error",
+            "502 Bad Gateway",
+            "503 Service Unavailable",
+        ] {
+            let err = presets_error(status(line)).await;
+            assert_eq!(
+                classify(&err),
+                ErrorClass::Retryable { retry_after: None },
+                "{}",
+                line
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn client_errors_are_permanent() {
+        for line in [
+            "400 Bad Request",
+            "401 Unauthorized",
+            "403 Forbidden",
+            "404 Not Found",
+        ] {
+            let err = presets_error(status(line)).await;
+            assert_eq!(classify(&err), ErrorClass::Permanent, "{}", line);
+        }
+    }
+
+    #[tokio::test]
+    async fn malformed_response_is_permanent() {
+        let err = presets_error(response("200 OK", "not json")).await;
+        assert_eq!(classify(&err), ErrorClass::Permanent);
+    }
+
+    #[tokio::test]
+    async fn rate_limit_is_retryable() {
+        let client = reqwest::Client::new();
+        let send = |url: String| client.get(url).send();
+
+        // `Retry-After`が指定されている場合は、その時間を待つ
+        let url = serve(|_| {
+            Some(
+                "HTTP/1.1 429 Too Many Requests\r\nretry-after: 3\r\ncontent-length: 0\r\nconnection: close\r\n\r\n"
+                    .to_string(),
+            )
+        })
+        .await;
+        let err = check_rate_limit(send(url).await.unwrap()).unwrap_err();
+        assert_eq!(
+            classify(&err),
+            ErrorClass::Retryable {
+                retry_after: Some(Duration::from_secs(3))
+            }
+        );
+
+        // レート制限を特別に扱わないエンジンからの429も再試行する
+        let err = presets_error(status("429 Too Many Requests")).await;
+        assert_eq!(classify(&err), ErrorClass::Retryable { retry_after: None });
+    }
+
+    #[tokio::test]
+    async fn connection_errors_are_retryable() {
+        // 接続を受け付けていないポート
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+
+        let client = VoicevoxClient::new(format!("http://{}", addr));
+        let err = client.presets().await.unwrap_err();
+        assert_eq!(classify(&err), ErrorClass::Retryable { retry_after: None });
+    }
+
+    #[tokio::test]
+    async fn timeouts_are_retryable() {
+        let url = serve(|_| None).await;
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(100))
+            .build()
+            .unwrap();
+        let err = anyhow::Error::from(client.get(url).send().await.unwrap_err());
+        assert_eq!(classify(&err), ErrorClass::Retryable { retry_after: None });
+    }
+}
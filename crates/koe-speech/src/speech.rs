@@ -1,6 +1,15 @@
 use crate::voicevox::{GenerateQueryFromPresetParams, Preset, SynthesisParams, VoicevoxClient};
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Context as _, Result};
 use koe_audio::EncodedAudio;
+use serde_json::Value;
+use std::ops::RangeInclusive;
+
+/// 設定できる話速の範囲
+pub const SPEED_SCALE_RANGE: RangeInclusive<f64> = 0.5..=2.0;
+/// 設定できる音高の範囲
+pub const PITCH_SCALE_RANGE: RangeInclusive<f64> = -0.15..=0.15;
+/// 音量の倍率をかけた後の音量の範囲
+pub const VOLUME_SCALE_RANGE: RangeInclusive<f64> = 0.0..=2.0;
 
 pub async fn initialize_speakers(client: &VoicevoxClient) -> Result<()> {
     let preset_list = client.presets().await?;
@@ -12,6 +21,19 @@ pub async fn initialize_speakers(client: &VoicevoxClient) -> Result<()> {
 
 pub async fn make_speech(client: &VoicevoxClient, option: SpeechRequest) -> Result<EncodedAudio> {
     let preset = get_preset(client, option.preset_id).await?;
+    make_speech_with_preset(client, &preset, option).await
+}
+
+/// 取得済みのプリセットを使って文章を音声に変換する
+/// プリセットの一覧を取得し直さないため、`preset`は`client`のエンジンのものである必要がある
+pub(crate) async fn make_speech_with_preset(
+    client: &VoicevoxClient,
+    preset: &Preset,
+    option: SpeechRequest,
+) -> Result<EncodedAudio> {
+    if option.text.trim().is_empty() {
+        bail!("Text to speak must not be empty");
+    }
 
     let query = client
         .generate_query_from_preset(GenerateQueryFromPresetParams {
@@ -19,6 +41,12 @@ pub async fn make_speech(client: &VoicevoxClient, option: SpeechRequest) -> Resu
             text: option.text,
         })
         .await?;
+    let query = override_scales(
+        query,
+        option.speed_scale,
+        option.pitch_scale,
+        option.volume_gain,
+    )?;
 
     let audio = client
         .synthesis(SynthesisParams {
@@ -36,6 +64,34 @@ pub async fn list_preset_ids(client: &VoicevoxClient) -> Result<Vec<PresetId>> {
     Ok(ids)
 }
 
+/// プリセットの話速・音高を指定された値で上書きし、音量に倍率をかける
+fn override_scales(
+    query: String,
+    speed_scale: Option<f64>,
+    pitch_scale: Option<f64>,
+    volume_gain: Option<f64>,
+) -> Result<String> {
+    if speed_scale.is_none() && pitch_scale.is_none() && volume_gain.is_none() {
+        return Ok(query);
+    }
+
+    let mut query: Value = serde_json::from_str(&query).context("Failed to parse audio query")?;
+    if let Some(speed_scale) = speed_scale {
+        query["speedScale"] = speed_scale.into();
+    }
+    if let Some(pitch_scale) = pitch_scale {
+        query["pitchScale"] = pitch_scale.into();
+    }
+    if let Some(volume_gain) = volume_gain {
+        let volume_scale = query["volumeScale"].as_f64().unwrap_or(1.0) * volume_gain;
+        query["volumeScale"] = volume_scale
+            .clamp(*VOLUME_SCALE_RANGE.start(), *VOLUME_SCALE_RANGE.end())
+            .into();
+    }
+
+    Ok(query.to_string())
+}
+
 async fn get_preset(client: &VoicevoxClient, id: PresetId) -> Result<Preset> {
     let preset_list = client.presets().await?;
 
@@ -51,6 +107,12 @@ async fn get_preset(client: &VoicevoxClient, id: PresetId) -> Result<Preset> {
 pub struct SpeechRequest {
     pub text: String,
     pub preset_id: PresetId,
+    /// 話速、[`None`]の場合はプリセットの値を使う
+    pub speed_scale: Option<f64>,
+    /// 音高、[`None`]の場合はプリセットの値を使う
+    pub pitch_scale: Option<f64>,
+    /// プリセットの音量にかける倍率、[`None`]の場合はプリセットの値をそのまま使う
+    pub volume_gain: Option<f64>,
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
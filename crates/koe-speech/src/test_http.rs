@@ -0,0 +1,83 @@
+//! テストで使う、最小限のHTTPサーバー
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+
+/// リクエストのパスに応じて`respond`が返す応答を返すHTTPサーバーを起動し、そのURLを返す
+/// `respond`が[`None`]を返した場合は、リクエストを受け取った後に応答しない
+pub(crate) async fn serve(
+    respond: impl Fn(&str) -> Option<String> + Send + Sync + 'static,
+) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let respond = std::sync::Arc::new(respond);
+
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let respond = respond.clone();
+            tokio::spawn(async move {
+                let path = match read_request(&mut socket).await {
+                    Some(path) => path,
+                    None => return,
+                };
+
+                match respond(&path) {
+                    Some(response) => {
+                        let _ = socket.write_all(response.as_bytes()).await;
+                        let _ = socket.shutdown().await;
+                    }
+                    None => std::future::pending().await,
+                }
+            });
+        }
+    });
+
+    format!("http://{}", addr)
+}
+
+/// ステータスと本文から応答を組み立てる
+pub(crate) fn response(status: &str, body: &str) -> String {
+    format!(
+        "HTTP/1.1 {}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )
+}
+
+/// リクエストを本文の終わりまで読み、パス（クエリを除く）を返す
+async fn read_request(socket: &mut tokio::net::TcpStream) -> Option<String> {
+    let mut request = Vec::new();
+    let mut buf = [0; 1024];
+
+    let header_end = loop {
+        if let Some(i) = request.windows(4).position(|x| x == b"\r\n\r\n") {
+            break i + 4;
+        }
+        match socket.read(&mut buf).await {
+            Ok(0) | Err(_) => return None,
+            Ok(n) => request.extend_from_slice(&buf[..n]),
+        }
+    };
+
+    let header = String::from_utf8_lossy(&request[..header_end]).into_owned();
+    let content_length = header
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.trim().parse::<usize>().ok())
+        .unwrap_or(0);
+    while request.len() < header_end + content_length {
+        match socket.read(&mut buf).await {
+            Ok(0) | Err(_) => return None,
+            Ok(n) => request.extend_from_slice(&buf[..n]),
+        }
+    }
+
+    let target = header.split(' ').nth(1)?;
+    let path = target.split('?').next().unwrap_or(target);
+    Some(path.to_string())
+}
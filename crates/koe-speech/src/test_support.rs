@@ -0,0 +1,200 @@
+//! 音声合成のエンジンを用意せずに、読み上げの処理を確かめるための実装
+
+use crate::{provider::SpeechProvider, speech::SpeechRequest, voicevox::Preset};
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use koe_audio::EncodedAudio;
+use std::{collections::HashSet, sync::Mutex, time::Duration};
+
+/// 文章をそのまま音声のデータとして返す実装
+///
+/// 返す音声は48kHzモノラルのWAVで、デコードすると文章のUTF-8のバイト列になる。
+/// 再生された音声を[`MockProvider::text_of`]で文章に戻すことで、読み上げた順番を確かめられる。
+pub struct MockProvider {
+    voices: Vec<Preset>,
+    latency: Box<dyn Fn(&SpeechRequest) -> Duration + Send + Sync>,
+    failing_texts: HashSet<String>,
+    streaming: bool,
+    language: String,
+    requests: Mutex<Vec<SpeechRequest>>,
+}
+
+impl MockProvider {
+    /// プリセットID 1の声のみを持ち、すぐに応答する
+    pub fn new() -> Self {
+        Self {
+            voices: vec![preset(1)],
+            latency: Box::new(|_| Duration::ZERO),
+            failing_texts: HashSet::new(),
+            streaming: false,
+            language: "ja".to_string(),
+            requests: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// 使用できるプリセットIDを指定する
+    pub fn with_voices(mut self, ids: &[i64]) -> Self {
+        self.voices = ids.iter().copied().map(preset).collect();
+        self
+    }
+
+    /// 文章ごとに、合成にかかる時間を指定する
+    pub fn with_latency(
+        mut self,
+        latency: impl Fn(&SpeechRequest) -> Duration + Send + Sync + 'static,
+    ) -> Self {
+        self.latency = Box::new(latency);
+        self
+    }
+
+    /// 指定した文章の合成に失敗する
+    pub fn failing_on(mut self, text: &str) -> Self {
+        self.failing_texts.insert(text.to_string());
+        self
+    }
+
+    /// 文ごとに合成して再生できるものとして扱う
+    pub fn with_streaming(mut self) -> Self {
+        self.streaming = true;
+        self
+    }
+
+    /// 読み上げられる言語を指定する
+    pub fn with_language(mut self, language: &str) -> Self {
+        self.language = language.to_string();
+        self
+    }
+
+    /// これまでに受け付けたリクエスト（失敗したものを含む）を、受け付けた順に返す
+    pub fn requests(&self) -> Vec<SpeechRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// 文章に対応する音声を返す
+    pub fn audio_for(text: &str) -> EncodedAudio {
+        let mut data = text.as_bytes().to_vec();
+        // 16bitのサンプルの境界に揃える
+        if data.len() % 2 == 1 {
+            data.push(0);
+        }
+
+        let mut buf = Vec::with_capacity(44 + data.len());
+        buf.extend_from_slice(b"RIFF");
+        buf.extend_from_slice(&(36 + data.len() as u32).to_le_bytes());
+        buf.extend_from_slice(b"WAVE");
+        buf.extend_from_slice(b"fmt ");
+        buf.extend_from_slice(&16u32.to_le_bytes());
+        buf.extend_from_slice(&1u16.to_le_bytes());
+        buf.extend_from_slice(&1u16.to_le_bytes());
+        buf.extend_from_slice(&48000u32.to_le_bytes());
+        buf.extend_from_slice(&96000u32.to_le_bytes());
+        buf.extend_from_slice(&2u16.to_le_bytes());
+        buf.extend_from_slice(&16u16.to_le_bytes());
+        buf.extend_from_slice(b"data");
+        buf.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        buf.extend_from_slice(&data);
+
+        EncodedAudio::from(buf)
+    }
+
+    /// [`MockProvider::audio_for`]の音声をデコードしたものを、文章に戻す
+    pub fn text_of(raw_audio: &[u8]) -> String {
+        let text = raw_audio.strip_suffix(&[0]).unwrap_or(raw_audio);
+        String::from_utf8_lossy(text).into_owned()
+    }
+}
+
+impl Default for MockProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl SpeechProvider for MockProvider {
+    async fn synthesize(&self, request: SpeechRequest) -> Result<EncodedAudio> {
+        self.requests.lock().unwrap().push(request.clone());
+
+        let latency = (self.latency)(&request);
+        if !latency.is_zero() {
+            tokio::time::sleep(latency).await;
+        }
+
+        if self.failing_texts.contains(&request.text) {
+            bail!("Mock synthesis failed: {}", request.text);
+        }
+        if !self.voices.iter().any(|x| x.id == request.preset_id.0) {
+            bail!("Preset {} is not available", request.preset_id.0);
+        }
+
+        Ok(Self::audio_for(&request.text))
+    }
+
+    async fn list_voices(&self) -> Result<Vec<Preset>> {
+        Ok(self.voices.clone())
+    }
+
+    fn supports_streaming(&self) -> bool {
+        self.streaming
+    }
+
+    fn language(&self) -> &str {
+        &self.language
+    }
+}
+
+fn preset(id: i64) -> Preset {
+    Preset {
+        id,
+        name: format!("mock-{}", id),
+        speaker_uuid: format!("mock-{}", id),
+        style_id: id,
+        speed_scale: 1.0,
+        pitch_scale: 0.0,
+        intonation_scale: 1.0,
+        volume_scale: 1.0,
+        pre_phoneme_length: 0.0,
+        post_phoneme_length: 0.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::speech::PresetId;
+
+    fn request(text: &str, preset_id: i64) -> SpeechRequest {
+        SpeechRequest {
+            text: text.to_string(),
+            preset_id: PresetId(preset_id),
+            speed_scale: None,
+            pitch_scale: None,
+            volume_gain: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn audio_decodes_back_to_text() {
+        let provider = MockProvider::new();
+        for text in ["こんにちは", "odd", ""] {
+            let audio = provider.synthesize(request(text, 1)).await.unwrap();
+            let raw: Vec<u8> = audio.decode().await.unwrap().into();
+            assert_eq!(MockProvider::text_of(&raw), text);
+        }
+    }
+
+    #[tokio::test]
+    async fn records_requests_including_failures() {
+        let provider = MockProvider::new().failing_on("失敗");
+        assert!(provider.synthesize(request("成功", 1)).await.is_ok());
+        assert!(provider.synthesize(request("失敗", 1)).await.is_err());
+        assert!(provider.synthesize(request("成功", 2)).await.is_err());
+
+        let texts = provider
+            .requests()
+            .into_iter()
+            .map(|x| x.text)
+            .collect::<Vec<_>>();
+        assert_eq!(texts, ["成功", "失敗", "成功"]);
+    }
+}
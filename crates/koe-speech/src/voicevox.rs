@@ -1,21 +1,46 @@
-use anyhow::Result;
+use crate::retry::PermanentError;
+use anyhow::{anyhow, Result};
 use koe_audio::EncodedAudio;
 use reqwest::Url;
 use serde::Deserialize;
+use std::time::Duration;
+
+/// VOICEVOX ENGINEへの接続を待つ最大の時間
+/// エンジンに到達できない場合に、読み上げが止まったままにならず音声合成のエラーとして扱われるようにする
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// 1回のリクエストの応答を待つ最大の時間
+/// 接続した後にエンジンが応答しなくなった場合も、タイムアウトとして再試行できるようにする
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
 
 pub struct VoicevoxClient {
     client: reqwest::Client,
     api_base: String,
+    /// 受け取る音声の最大のサイズ（バイト）、[`None`]の場合は制限しない
+    max_audio_size: Option<usize>,
 }
 
 impl VoicevoxClient {
     pub fn new(api_base: String) -> Self {
+        let client = reqwest::Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .expect("Failed to build HTTP client");
+
         Self {
-            client: reqwest::Client::new(),
+            client,
             api_base,
+            max_audio_size: None,
         }
     }
 
+    /// 受け取る音声の最大のサイズ（バイト）を指定する
+    /// これを超える応答は、受け取りを途中で打ち切って音声合成のエラーとする
+    pub fn with_max_audio_size(mut self, max_audio_size: Option<usize>) -> Self {
+        self.max_audio_size = max_audio_size;
+        self
+    }
+
     pub async fn generate_query_from_preset(
         &self,
         params: GenerateQueryFromPresetParams,
@@ -53,11 +78,34 @@ impl VoicevoxClient {
             .body(params.query)
             .send()
             .await?
-            .error_for_status()?
-            .bytes()
-            .await?;
+            .error_for_status()?;
+
+        Ok(EncodedAudio::from(self.read_audio(resp).await?))
+    }
+
+    /// 応答の本文を少しずつ受け取り、最大のサイズを超えた時点で打ち切る
+    /// 同じ文章からは同じ大きさの音声が合成されるため、再試行しないエラーとする
+    async fn read_audio(&self, mut resp: reqwest::Response) -> Result<Vec<u8>> {
+        let max_audio_size = match self.max_audio_size {
+            Some(size) => size,
+            None => return Ok(resp.bytes().await?.to_vec()),
+        };
+
+        if let Some(length) = resp.content_length() {
+            if length > max_audio_size as u64 {
+                return Err(oversized(max_audio_size));
+            }
+        }
 
-        Ok(EncodedAudio::from(resp.to_vec()))
+        let mut audio = Vec::new();
+        while let Some(chunk) = resp.chunk().await? {
+            if audio.len() + chunk.len() > max_audio_size {
+                return Err(oversized(max_audio_size));
+            }
+            audio.extend_from_slice(&chunk);
+        }
+
+        Ok(audio)
     }
 
     pub async fn presets(&self) -> Result<Vec<Preset>> {
@@ -86,6 +134,21 @@ impl VoicevoxClient {
         Ok(())
     }
 
+    pub async fn version(&self) -> Result<String> {
+        let url = Url::parse(&self.get_endpoint("/version"))?;
+
+        let resp = self
+            .client
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(resp)
+    }
+
     fn get_endpoint(&self, path: impl AsRef<str>) -> String {
         self.api_base.clone() + path.as_ref()
     }
@@ -122,3 +185,12 @@ pub struct Preset {
     #[serde(rename = "postPhonemeLength")]
     pub post_phoneme_length: f64,
 }
+
+/// 最大のサイズを超える音声を受け取ったことを表すエラー
+fn oversized(max_audio_size: usize) -> anyhow::Error {
+    anyhow!(
+        "Synthesized audio exceeds the limit of {} bytes",
+        max_audio_size
+    )
+    .context(PermanentError)
+}
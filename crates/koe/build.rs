@@ -0,0 +1,70 @@
+//! `/version`で表示するビルド時の情報を、環境変数としてバイナリに埋め込む
+
+use std::{
+    env, fs,
+    path::Path,
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+fn main() {
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let workspace_dir = Path::new(&manifest_dir).join("../..");
+
+    // Dockerでのビルドなど`.git`がない環境では、`KOE_GIT_COMMIT`で指定されたものを使う
+    println!("cargo:rerun-if-env-changed=KOE_GIT_COMMIT");
+    println!(
+        "cargo:rerun-if-changed={}",
+        workspace_dir.join(".git/HEAD").display()
+    );
+    let git_commit = env::var("KOE_GIT_COMMIT")
+        .ok()
+        .filter(|commit| !commit.is_empty())
+        .or_else(git_commit)
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=KOE_GIT_COMMIT={}", git_commit);
+
+    let build_timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    println!("cargo:rustc-env=KOE_BUILD_TIMESTAMP={}", build_timestamp);
+
+    let lock_path = workspace_dir.join("Cargo.lock");
+    println!("cargo:rerun-if-changed={}", lock_path.display());
+    let lock = fs::read_to_string(lock_path).unwrap_or_default();
+    for (name, var) in [
+        ("serenity", "KOE_SERENITY_VERSION"),
+        ("songbird", "KOE_SONGBIRD_VERSION"),
+    ] {
+        let version = locked_version(&lock, name).unwrap_or_else(|| "unknown".to_string());
+        println!("cargo:rustc-env={}={}", var, version);
+    }
+}
+
+/// 現在のコミットの短いハッシュを返す
+fn git_commit() -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--short=12", "HEAD"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let commit = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    (!commit.is_empty()).then_some(commit)
+}
+
+/// `Cargo.lock`に記録された、依存クレートのバージョンを返す
+fn locked_version(lock: &str, name: &str) -> Option<String> {
+    let mut lines = lock.lines();
+    while let Some(line) = lines.next() {
+        if line == format!("name = \"{}\"", name) {
+            let version = lines
+                .next()?
+                .strip_prefix("version = \"")?
+                .strip_suffix('"')?;
+            return Some(version.to_string());
+        }
+    }
+    None
+}
@@ -0,0 +1,41 @@
+use crate::app_state;
+use anyhow::{Context as _, Result};
+use log::warn;
+use serenity::client::Context;
+
+/// Botの運営者に、設定ファイルの`alert`で指定されたチャンネルとWebhookで通知する
+/// 通知先が設定されていない場合は何もしない
+/// 一方への送信に失敗しても、もう一方には送信する
+pub async fn notify_operator(ctx: &Context, content: &str) -> Result<()> {
+    let state = app_state::get(ctx).await?;
+
+    if let Some(channel_id) = state.alert_channel_id {
+        if let Err(err) = channel_id.say(&ctx.http, content).await {
+            warn!(
+                "Failed to send operator alert to channel {}: {:?}",
+                channel_id, err
+            );
+        }
+    }
+
+    if let Some(webhook_url) = &state.alert_webhook_url {
+        if let Err(err) = execute_webhook(ctx, webhook_url, content).await {
+            warn!("Failed to send operator alert to webhook: {:?}", err);
+        }
+    }
+
+    Ok(())
+}
+
+async fn execute_webhook(ctx: &Context, webhook_url: &str, content: &str) -> Result<()> {
+    let webhook = ctx
+        .http
+        .get_webhook_from_url(webhook_url)
+        .await
+        .context("Failed to get webhook")?;
+    webhook
+        .execute(&ctx.http, false, |execute| execute.content(content))
+        .await
+        .context("Failed to execute webhook")?;
+    Ok(())
+}
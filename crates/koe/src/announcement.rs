@@ -0,0 +1,19 @@
+use crate::speech_queue::{self, EnqueueOption, Voice};
+use anyhow::Result;
+use serenity::{client::Context, model::id::GuildId};
+
+/// Botからのお知らせをボイスチャンネルで読み上げる
+/// サーバーのシステム音声が設定されている場合はその声を使う
+pub async fn announce(ctx: &Context, guild_id: GuildId, text: impl Into<String>) -> Result<()> {
+    speech_queue::enqueue(
+        ctx,
+        EnqueueOption {
+            guild_id,
+            text: text.into(),
+            voice: Voice::System,
+        },
+    )
+    .await?;
+
+    Ok(())
+}
@@ -1,26 +1,150 @@
+use crate::{
+    autocomplete::cache::TtlCache,
+    cache::{DictCache, SettingsCache, UserDictCache},
+    command::responder::Responder,
+    message::skip::SkipCounter,
+    rate_limit::CommandRateLimiter,
+    speech_queue::sequencer::Sequencer,
+    startup::StartupLimiter,
+};
 use anyhow::{anyhow, Result};
-use dashmap::DashMap;
+use dashmap::{DashMap, DashSet};
+use koe_call::VoiceBackend;
 use koe_db::redis;
-use koe_speech::voicevox::VoicevoxClient;
+use koe_speech::{
+    provider::{ProviderKind, Providers},
+    voicevox::{Preset, VoicevoxClient},
+};
 use serenity::{
     client::{Client, Context},
     model::{
         channel::Message,
-        id::{ChannelId, GuildId},
+        id::{ChannelId, GuildId, InteractionId, ScheduledEventId, UserId},
     },
     prelude::TypeMapKey,
 };
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    sync::{atomic::AtomicU64, Arc},
+    time::{Duration, Instant},
+};
+use time::UtcOffset;
 
 pub struct AppState {
     pub redis_client: redis::Client,
-    pub voicevox_client: VoicevoxClient,
+    /// 話者の初期化と状態の確認に使う、設定ファイルの`voicevox.api_base`のエンジン
+    /// VOICEVOX ENGINEが設定されていない場合は[`None`]になる
+    pub voicevox_client: Option<VoicevoxClient>,
+    /// 音声合成とプリセットの一覧の取得に使う、設定ファイルで指定されたエンジン
+    /// サーバーごとに選択されたエンジンは[`crate::speech_provider::for_guild`]で選ぶ
+    pub speech_providers: Providers,
+    /// ボイスチャンネルへの接続と音声の再生に使う
+    pub voice_backend: Arc<dyn VoiceBackend>,
+    /// コマンドへの応答に使う
+    pub responder: Arc<dyn Responder>,
     pub connected_guild_states: DashMap<GuildId, ConnectedGuildState>,
+    /// `/leave`によって手動で切断された時刻
+    pub manual_leave_times: DashMap<GuildId, Instant>,
+    /// 日時を読み上げる際に使用するタイムゾーン
+    pub timezone: UtcOffset,
+    /// 1件のメッセージを読み上げる最大の時間
+    pub max_speech_duration: Option<Duration>,
+    /// Botの所有者（チームの場合はそのメンバー）
+    pub owner_ids: Vec<UserId>,
+    /// スラッシュコマンドを登録する開発用のサーバー、`None`の場合はグローバルコマンドとして登録する
+    pub dev_guild_id: Option<GuildId>,
+    /// サーバーごとのシステム音声が未設定の場合に使う声（プリセットID）
+    pub system_voice_preset_id: Option<i64>,
+    /// 読み上げが行われないまま、この時間が経過するとボイスチャンネルから退出する
+    pub idle_timeout: Option<Duration>,
+    pub dict_cache: DictCache,
+    pub user_dict_cache: UserDictCache,
+    pub settings_cache: SettingsCache,
+    /// オートコンプリートで候補として表示するプリセット
+    pub preset_cache: TtlCache<ProviderKind, Arc<Vec<Preset>>>,
+    /// スピーカーミュートの状態でボイスチャンネルに接続するかどうか
+    pub self_deaf: bool,
+    /// 同時に接続できるボイスチャンネルの最大数
+    pub max_connections: Option<usize>,
+    /// 予期せず切断された際に再接続を試みる最大の回数
+    pub max_reconnect_attempts: u32,
+    /// 再接続に失敗し続けた場合などに、Botの運営者へ通知するテキストチャンネル
+    pub alert_channel_id: Option<ChannelId>,
+    /// 再接続に失敗し続けた場合などに、Botの運営者へ通知するWebhookのURL
+    pub alert_webhook_url: Option<String>,
+    /// 読み上げなかったメッセージの数
+    pub skip_counter: SkipCounter,
+    /// 音声の再生に続けて失敗したために、接続の回復を試みた回数
+    pub recovery_attempts_total: AtomicU64,
+    /// 起動直後にサーバーごとに行う処理の同時実行数を制限する
+    pub startup_limiter: StartupLimiter,
+    /// メンバーごと・コマンドごとの実行回数を制限する
+    pub command_rate_limiter: CommandRateLimiter,
+    /// 確認のボタンが操作されるのを待っているコマンドのインタラクション
+    /// 操作されるか期限を過ぎると取り除かれる
+    pub pending_confirmations: DashSet<InteractionId>,
+    /// 開始したことを読み上げ済みで、まだ終了していないイベント
+    /// 開催中のイベントが編集されたときに、もう一度読み上げないようにする
+    pub active_scheduled_events: DashSet<ScheduledEventId>,
+    /// 開発用に、音声に変換せずに読み上げる文章をログに出力するかどうか
+    /// 有効な場合はボイスチャンネルにも接続しない
+    pub print_speech: bool,
+    /// Botを起動した時刻
+    pub started_at: Instant,
 }
 
 pub struct ConnectedGuildState {
-    pub bound_text_channel: ChannelId,
+    pub voice_channel: ChannelId,
+    /// 読み上げ対象のテキストチャンネル
+    /// 空になることはなく、先頭のチャンネルにBotからのお知らせを送信する
+    pub bound_text_channels: Vec<ChannelId>,
     pub last_message_read: Option<Message>,
+    /// 最後にリアクションを読み上げた時刻
+    pub last_reaction_announced: Option<Instant>,
+    /// 削除されたことをまだお知らせしていないメッセージの数
+    pub pending_deletions: usize,
+    /// ボイスチャンネルに接続した時刻
+    pub connected_at: Instant,
+    /// 最後に音声をキューに追加した時刻
+    pub last_activity: Instant,
+    /// 最後に発生した音声合成のエラー
+    pub last_speech_error: Option<String>,
+    /// 続けて再生に失敗した音声の数
+    pub consecutive_track_failures: u32,
+    /// この接続で接続の回復を試みた回数
+    pub recovery_attempts: u32,
+    /// `/voice boost`で一時的に変更したメンバーの音量の倍率
+    pub volume_boosts: HashMap<UserId, f64>,
+    /// 接続しているDiscordの音声サーバー
+    pub voice_server: Option<String>,
+    /// 接続中のボイスチャンネルから最近抜けたメンバーと、その時刻
+    pub departed_members: HashMap<UserId, Instant>,
+    /// メンバーの移動に追従するかどうかの確認を予定しているか
+    pub follow_scheduled: bool,
+    /// 読み上げキューに追加する順番を、受け付けた順に保つ
+    pub speech_sequencer: Arc<Sequencer>,
+}
+
+impl AppState {
+    /// 同時に接続できるボイスチャンネルの数が上限に達しているかどうかを返す
+    pub fn is_at_connection_limit(&self) -> bool {
+        match self.max_connections {
+            Some(max) => self.connected_guild_states.len() >= max,
+            None => false,
+        }
+    }
+}
+
+impl ConnectedGuildState {
+    /// 読み上げ対象のテキストチャンネルかどうかを返す
+    pub fn is_bound(&self, channel_id: ChannelId) -> bool {
+        self.bound_text_channels.contains(&channel_id)
+    }
+
+    /// Botからのお知らせを送信するテキストチャンネルを返す
+    pub fn primary_text_channel(&self) -> ChannelId {
+        self.bound_text_channels[0]
+    }
 }
 
 impl TypeMapKey for AppState {
@@ -0,0 +1,50 @@
+use anyhow::Result;
+use dashmap::DashMap;
+use std::{
+    future::Future,
+    hash::Hash,
+    time::{Duration, Instant},
+};
+
+/// 候補の元になるデータを一定時間保持する
+/// 入力のたびにRedisやVOICEVOX ENGINEに問い合わせないようにするために使う
+pub struct TtlCache<K, V> {
+    ttl: Duration,
+    entries: DashMap<K, (V, Instant)>,
+}
+
+impl<K, V> TtlCache<K, V>
+where
+    K: Eq + Hash + Clone,
+    V: Clone,
+{
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: DashMap::new(),
+        }
+    }
+
+    /// キャッシュされた値を返す
+    /// キャッシュが存在しないか、有効期間が過ぎている場合は`fetch`で取得してキャッシュする
+    pub async fn get_or_try_insert_with<F, Fut>(&self, key: K, fetch: F) -> Result<V>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<V>>,
+    {
+        if let Some(entry) = self.entries.get(&key) {
+            let (value, fetched_at) = entry.value();
+            if fetched_at.elapsed() < self.ttl {
+                return Ok(value.clone());
+            }
+        }
+
+        // `fetch`の間はロックを保持しないよう、取得してから挿入する
+        let value = fetch().await?;
+        self.entries
+            .retain(|_, (_, fetched_at)| fetched_at.elapsed() < self.ttl);
+        self.entries.insert(key, (value.clone(), Instant::now()));
+
+        Ok(value)
+    }
+}
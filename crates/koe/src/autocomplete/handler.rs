@@ -0,0 +1,248 @@
+use crate::{
+    app_state,
+    messages::{self, Key},
+    speech_provider,
+};
+use anyhow::{Context as _, Result};
+use log::warn;
+use serde_json::Value;
+use serenity::{
+    client::Context,
+    model::{
+        application::interaction::{
+            application_command::CommandDataOption, autocomplete::AutocompleteInteraction,
+        },
+        id::UserId,
+    },
+};
+use std::time::Duration;
+
+/// 一度に返せる候補の最大数
+const MAX_CHOICES: usize = 25;
+
+/// 候補の名前の最大文字数
+const MAX_CHOICE_NAME_LENGTH: usize = 100;
+
+/// 候補を返すまでの制限時間
+/// Discordの応答期限（3秒）に間に合わない場合は、候補なしで応答する
+const PROVIDER_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// オートコンプリートの候補
+#[derive(Debug, Clone)]
+pub struct Choice {
+    pub name: String,
+    pub value: ChoiceValue,
+}
+
+#[derive(Debug, Clone)]
+pub enum ChoiceValue {
+    String(String),
+    Integer(i64),
+}
+
+/// 入力中のオプション
+struct FocusedOption<'a> {
+    command: &'a str,
+    /// サブコマンドがない場合は空文字列
+    subcommand: &'a str,
+    option: &'a str,
+    /// 入力途中の値
+    input: String,
+}
+
+pub async fn handle(ctx: &Context, interaction: &AutocompleteInteraction) -> Result<()> {
+    let choices = match find_focused_option(interaction) {
+        Some(focused) => {
+            match tokio::time::timeout(PROVIDER_TIMEOUT, provide(ctx, interaction, &focused)).await
+            {
+                Ok(choices) => choices?,
+                Err(_) => {
+                    warn!(
+                        "Autocomplete provider timed out: /{} {} {}",
+                        focused.command, focused.subcommand, focused.option
+                    );
+                    Vec::new()
+                }
+            }
+        }
+        None => Vec::new(),
+    };
+
+    interaction
+        .create_autocomplete_response(&ctx.http, |response| {
+            for choice in choices.into_iter().take(MAX_CHOICES) {
+                let name = truncate(&choice.name, MAX_CHOICE_NAME_LENGTH);
+                match choice.value {
+                    ChoiceValue::String(value) => response.add_string_choice(name, value),
+                    ChoiceValue::Integer(value) => response.add_int_choice(name, value),
+                };
+            }
+            response
+        })
+        .await
+        .context("Failed to create autocomplete response")?;
+
+    Ok(())
+}
+
+/// コマンド名とオプション名から、候補を返す関数を選ぶ
+/// 該当する関数がない場合は候補なしとする
+async fn provide(
+    ctx: &Context,
+    interaction: &AutocompleteInteraction,
+    focused: &FocusedOption<'_>,
+) -> Result<Vec<Choice>> {
+    match (focused.command, focused.subcommand, focused.option) {
+        ("dict", "remove", "word") => provide_dict_words(ctx, interaction, &focused.input).await,
+        ("voice", "set", "preset") => {
+            provide_presets(ctx, interaction, Some(interaction.user.id), &focused.input).await
+        }
+        ("settings", "system-voice", "preset") => {
+            provide_presets(ctx, interaction, None, &focused.input).await
+        }
+        ("settings", "provider", "name") => provide_speech_providers(ctx, &focused.input).await,
+        ("voice", "language", "language") => provide_languages(ctx, &focused.input).await,
+        _ => Ok(Vec::new()),
+    }
+}
+
+/// サーバーの辞書に登録された語句のうち、入力を含むもの
+async fn provide_dict_words(
+    ctx: &Context,
+    interaction: &AutocompleteInteraction,
+    input: &str,
+) -> Result<Vec<Choice>> {
+    let guild_id = match interaction.guild_id {
+        Some(id) => id,
+        None => return Ok(Vec::new()),
+    };
+
+    let state = app_state::get(ctx).await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+    let dict = state.dict_cache.get_or_fetch(&mut conn, guild_id).await?;
+
+    let input = input.to_lowercase();
+    let mut words = dict
+        .iter()
+        .map(|(word, _)| word)
+        // 候補の値は100文字までのため、それより長い語句は選択できない
+        .filter(|word| word.chars().count() <= MAX_CHOICE_NAME_LENGTH)
+        .filter(|word| word.to_lowercase().contains(&input))
+        .collect::<Vec<_>>();
+    words.sort();
+
+    Ok(words
+        .into_iter()
+        .map(|word| Choice {
+            name: word.clone(),
+            value: ChoiceValue::String(word.clone()),
+        })
+        .collect())
+}
+
+/// 設定ファイルで指定された音声合成のエンジンのうち、名前が入力を含むもの
+async fn provide_speech_providers(ctx: &Context, input: &str) -> Result<Vec<Choice>> {
+    let state = app_state::get(ctx).await?;
+    let input = input.to_lowercase();
+
+    Ok(state
+        .speech_providers
+        .kinds()
+        .into_iter()
+        .filter(|kind| {
+            kind.display_name().to_lowercase().contains(&input) || kind.as_str().contains(&input)
+        })
+        .map(|kind| Choice {
+            name: kind.display_name().to_string(),
+            value: ChoiceValue::String(kind.as_str().to_string()),
+        })
+        .collect())
+}
+
+/// 設定ファイルで指定されたエンジンが読み上げられる言語のうち、入力を含むもの
+async fn provide_languages(ctx: &Context, input: &str) -> Result<Vec<Choice>> {
+    let state = app_state::get(ctx).await?;
+    let input = input.to_lowercase();
+
+    Ok(state
+        .speech_providers
+        .languages()
+        .into_iter()
+        .filter(|language| language.contains(&input))
+        .map(|language| Choice {
+            name: language.clone(),
+            value: ChoiceValue::String(language),
+        })
+        .collect())
+}
+
+/// 使用できるプリセットのうち、名前かIDが入力を含むもの
+/// `member`が指定された場合は、そのメンバーの読み上げに使うエンジンのプリセットを返す
+async fn provide_presets(
+    ctx: &Context,
+    interaction: &AutocompleteInteraction,
+    member: Option<UserId>,
+    input: &str,
+) -> Result<Vec<Choice>> {
+    let guild_id = match interaction.guild_id {
+        Some(id) => id,
+        None => return Ok(Vec::new()),
+    };
+    let lang = messages::resolve(ctx, interaction.guild_id, &interaction.locale).await;
+
+    let state = app_state::get(ctx).await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+    let (kind, provider) = match member {
+        Some(user_id) => speech_provider::for_member(&state, &mut conn, guild_id, user_id).await?,
+        None => speech_provider::for_guild(&state, &mut conn, guild_id).await?,
+    };
+    let presets = speech_provider::cached_voices(&state, kind, provider).await?;
+
+    Ok(presets
+        .iter()
+        .filter(|preset| preset.name.contains(input) || preset.id.to_string().contains(input))
+        .map(|preset| Choice {
+            name: messages::format(
+                lang,
+                Key::PresetChoice,
+                &[("name", &preset.name), ("id", &preset.id)],
+            ),
+            value: ChoiceValue::Integer(preset.id),
+        })
+        .collect())
+}
+
+fn find_focused_option(interaction: &AutocompleteInteraction) -> Option<FocusedOption<'_>> {
+    // サブコマンドがある場合は、入力中のオプションはその下にある
+    let (subcommand, option) = match find_focused(&interaction.data.options) {
+        Some(option) => ("", option),
+        None => interaction.data.options.iter().find_map(|subcommand| {
+            find_focused(&subcommand.options).map(|option| (subcommand.name.as_str(), option))
+        })?,
+    };
+
+    let input = match &option.value {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Number(n)) => n.to_string(),
+        _ => String::new(),
+    };
+
+    Some(FocusedOption {
+        command: interaction.data.name.as_str(),
+        subcommand,
+        option: option.name.as_str(),
+        input,
+    })
+}
+
+fn find_focused(options: &[CommandDataOption]) -> Option<&CommandDataOption> {
+    options.iter().find(|option| option.focused)
+}
+
+fn truncate(text: &str, max_length: usize) -> String {
+    if text.chars().count() > max_length {
+        text.chars().take(max_length - 1).collect::<String>() + "…"
+    } else {
+        text.to_string()
+    }
+}
@@ -0,0 +1,216 @@
+use anyhow::Result;
+use dashmap::DashMap;
+use koe_db::{
+    dict,
+    guild_settings::{self, GuildSettings},
+    redis, user_dict,
+};
+use serenity::model::id::{GuildId, UserId};
+use std::{
+    future::Future,
+    hash::Hash,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// キャッシュする最大の件数
+const MAX_ENTRIES: usize = 1000;
+/// キャッシュの有効期間
+/// Redisを直接編集した場合などでも、この時間が経過すれば新しい値が読み込まれる
+const TTL: Duration = Duration::from_secs(10 * 60);
+
+pub type Dict = Arc<Vec<(String, String)>>;
+
+/// サーバーごとの辞書
+pub type DictCache = RedisCache<GuildId, Dict>;
+/// メンバーごとの辞書
+pub type UserDictCache = RedisCache<UserId, Dict>;
+/// サーバーごとの設定
+pub type SettingsCache = RedisCache<GuildId, Arc<GuildSettings>>;
+
+/// Redisから読み込んだ値をメモリ上に保持し、メッセージごとのRedisへの問い合わせを減らす
+/// 値を変更したときは[`RedisCache::invalidate`]でキャッシュを破棄する
+pub struct RedisCache<K, V> {
+    entries: DashMap<K, Cached<V>>,
+}
+
+struct Cached<V> {
+    value: V,
+    fetched_at: Instant,
+}
+
+impl<K, V> Default for RedisCache<K, V>
+where
+    K: Eq + Hash,
+{
+    fn default() -> Self {
+        Self {
+            entries: DashMap::new(),
+        }
+    }
+}
+
+impl<K, V> RedisCache<K, V>
+where
+    K: Eq + Hash + Copy,
+    V: Clone,
+{
+    /// キャッシュされた値を返す
+    /// キャッシュが存在しないか、有効期間が過ぎている場合は[`None`]を返す
+    pub fn get(&self, key: K) -> Option<V> {
+        let entry = self.entries.get(&key)?;
+        if entry.fetched_at.elapsed() >= TTL {
+            return None;
+        }
+        Some(entry.value.clone())
+    }
+
+    /// 値をキャッシュする
+    /// キャッシュが上限に達している場合は、最も古いものを削除する
+    pub fn insert(&self, key: K, value: V) -> V {
+        if self.entries.len() >= MAX_ENTRIES && !self.entries.contains_key(&key) {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|entry| entry.fetched_at)
+                .map(|entry| *entry.key());
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+
+        self.entries.insert(
+            key,
+            Cached {
+                value: value.clone(),
+                fetched_at: Instant::now(),
+            },
+        );
+        value
+    }
+
+    /// 値が変更されたときに、キャッシュを破棄する
+    pub fn invalidate(&self, key: K) {
+        self.entries.remove(&key);
+    }
+
+    /// キャッシュされた値を返す
+    /// キャッシュが存在しないか、有効期間が過ぎている場合は`fetch`で読み込んでキャッシュする
+    async fn get_or_try_insert_with<F, Fut>(&self, key: K, fetch: F) -> Result<V>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<V>>,
+    {
+        if let Some(value) = self.get(key) {
+            return Ok(value);
+        }
+        Ok(self.insert(key, fetch().await?))
+    }
+}
+
+impl DictCache {
+    pub async fn get_or_fetch(
+        &self,
+        conn: &mut redis::aio::Connection,
+        guild_id: GuildId,
+    ) -> Result<Dict> {
+        self.get_or_try_insert_with(guild_id, || async {
+            let dict = dict::get_all(
+                conn,
+                dict::GetAllOption {
+                    guild_id: guild_id.into(),
+                },
+            )
+            .await?;
+            Ok(Arc::new(dict))
+        })
+        .await
+    }
+}
+
+impl UserDictCache {
+    pub async fn get_or_fetch(
+        &self,
+        conn: &mut redis::aio::Connection,
+        user_id: UserId,
+    ) -> Result<Dict> {
+        self.get_or_try_insert_with(user_id, || async {
+            let dict = user_dict::get_all(
+                conn,
+                user_dict::GetAllOption {
+                    user_id: user_id.into(),
+                },
+            )
+            .await?;
+            Ok(Arc::new(dict))
+        })
+        .await
+    }
+}
+
+impl SettingsCache {
+    pub async fn get_or_fetch(
+        &self,
+        conn: &mut redis::aio::Connection,
+        guild_id: GuildId,
+    ) -> Result<Arc<GuildSettings>> {
+        self.get_or_try_insert_with(guild_id, || async {
+            let settings = guild_settings::get_all(
+                conn,
+                guild_settings::GetAllOption {
+                    guild_id: guild_id.into(),
+                },
+            )
+            .await?;
+            Ok(Arc::new(settings))
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalidated_entries_are_not_returned() {
+        let cache = RedisCache::<GuildId, u32>::default();
+        assert_eq!(cache.get(GuildId(1)), None);
+
+        cache.insert(GuildId(1), 10);
+        assert_eq!(cache.get(GuildId(1)), Some(10));
+        cache.insert(GuildId(1), 20);
+        assert_eq!(cache.get(GuildId(1)), Some(20));
+
+        cache.invalidate(GuildId(1));
+        assert_eq!(cache.get(GuildId(1)), None);
+    }
+
+    #[test]
+    fn expired_entries_are_not_returned() {
+        let cache = RedisCache::<UserId, u32>::default();
+        cache.insert(UserId(1), 10);
+        cache.entries.get_mut(&UserId(1)).unwrap().fetched_at -= TTL;
+        assert_eq!(cache.get(UserId(1)), None);
+    }
+
+    #[test]
+    fn oldest_entry_is_evicted_at_capacity() {
+        let cache = RedisCache::<GuildId, u64>::default();
+        for i in 0..MAX_ENTRIES as u64 {
+            cache.insert(GuildId(i), i);
+            cache.entries.get_mut(&GuildId(i)).unwrap().fetched_at -=
+                Duration::from_millis(MAX_ENTRIES as u64 - i);
+        }
+
+        // 既存の値の更新では削除しない
+        cache.insert(GuildId(5), 50);
+        assert_eq!(cache.entries.len(), MAX_ENTRIES);
+        assert_eq!(cache.get(GuildId(0)), Some(0));
+
+        cache.insert(GuildId(MAX_ENTRIES as u64), 0);
+        assert_eq!(cache.entries.len(), MAX_ENTRIES);
+        assert_eq!(cache.get(GuildId(0)), None);
+        assert_eq!(cache.get(GuildId(1)), Some(1));
+    }
+}
@@ -0,0 +1,43 @@
+use crate::{app_state, connection};
+use anyhow::{Context as _, Result};
+use log::info;
+use serenity::{client::Context, model::channel::GuildChannel};
+
+/// 読み上げ対象のテキストチャンネルが削除された場合は読み上げ対象から外す
+/// 読み上げ対象のテキストチャンネルがなくなる場合は読み上げを終了する
+pub async fn handle_delete(ctx: &Context, channel: &GuildChannel) -> Result<()> {
+    let guild_id = channel.guild_id;
+
+    let state = app_state::get(ctx).await?;
+    let is_last_channel = match state.connected_guild_states.get_mut(&guild_id) {
+        Some(mut guild_state) => {
+            if !guild_state.is_bound(channel.id) {
+                return Ok(());
+            }
+            // 読み上げ対象のテキストチャンネルが空にならないよう、最後の1つは退出によって取り除く
+            if guild_state.bound_text_channels.len() == 1 {
+                true
+            } else {
+                guild_state
+                    .bound_text_channels
+                    .retain(|id| *id != channel.id);
+                false
+            }
+        }
+        None => return Ok(()),
+    };
+
+    info!(
+        "Bound text channel {} was deleted in guild {}",
+        channel.id.as_u64(),
+        guild_id.as_u64()
+    );
+
+    if is_last_channel {
+        connection::leave(ctx, guild_id)
+            .await
+            .context("Failed to leave voice channel")?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,523 @@
+use super::{
+    handler::{format_duration, r, r_ephemeral, respond, sanitize_response, CommandResponse},
+    model::{AdminInspectOption, AdminUsageLimitOption, Command},
+    parser::{
+        find_integer, find_string, find_subcommand, required, unknown_subcommand, CommandParseError,
+    },
+    registry::{subcommand, Category, CommandSpec, OptionKind, OptionSpec, SlashCommand, STRING},
+};
+use crate::{
+    app_state,
+    messages::{self, Key},
+};
+use anyhow::{bail, Context as _, Result};
+use async_trait::async_trait;
+use koe_db::{language::Language, redis, usage};
+use serenity::{
+    builder::CreateEmbed,
+    client::Context,
+    model::{
+        application::interaction::application_command::{
+            ApplicationCommandInteraction, CommandDataOption,
+        },
+        id::GuildId,
+    },
+};
+use std::{sync::atomic::Ordering, time::Duration};
+
+/// `/admin`の定義
+const SPEC: CommandSpec = CommandSpec {
+    name: "admin",
+    description: "Botの管理（Botの所有者のみ）",
+    description_en: "Bot administration (bot owner only)",
+    category: Category::Other,
+    aliases: &[],
+    manage_guild_only: false,
+    dm_allowed: false,
+    options: &[
+        subcommand(
+            "status",
+            "Botの稼働状況を表示",
+            "Show the health of the bot",
+            &[],
+        ),
+        subcommand(
+            "inspect",
+            "サーバーでの読み上げの状況を表示",
+            "Show the reading status in a server",
+            &[OptionSpec::new("guild_id", "サーバーのID", "Server ID", STRING).required()],
+        ),
+        subcommand(
+            "usage-limit",
+            "サーバーが1か月に読み上げられる文字数の上限を設定",
+            "Set the monthly character limit of a server",
+            &[
+                OptionSpec::new("guild_id", "サーバーのID", "Server ID", STRING).required(),
+                OptionSpec::new(
+                    "chars",
+                    "1か月に読み上げられる文字数（0で上限をなくす）",
+                    "Characters per month (0 to remove the limit)",
+                    OptionKind::Integer {
+                        min: Some(0),
+                        max: None,
+                        autocomplete: false,
+                    },
+                )
+                .required(),
+            ],
+        ),
+    ],
+};
+
+pub(super) struct AdminCommand;
+
+#[async_trait]
+impl SlashCommand for AdminCommand {
+    fn spec(&self) -> &'static CommandSpec {
+        &SPEC
+    }
+
+    fn parse(&self, options: &[CommandDataOption]) -> Result<Command, CommandParseError> {
+        parse_admin(find_subcommand("admin", options)?)
+    }
+
+    async fn run(
+        &self,
+        ctx: &Context,
+        cmd: &ApplicationCommandInteraction,
+        lang: Language,
+        command: Command,
+    ) -> Result<()> {
+        match command {
+            Command::AdminStatus => handle_status(ctx, cmd, lang)
+                .await
+                .context("Failed to execute /admin status"),
+            Command::AdminInspect(option) => handle_inspect(ctx, cmd, lang, option)
+                .await
+                .context("Failed to execute /admin inspect"),
+            Command::AdminUsageLimit(option) => handle_usage_limit(ctx, cmd, lang, option)
+                .await
+                .context("Failed to execute /admin usage-limit"),
+            command => bail!("Unexpected command for /admin: {:?}", command),
+        }
+    }
+}
+
+async fn handle_status(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+) -> Result<()> {
+    // 1つの依存先が応答しない場合でも、Discordの応答期限である3秒以内に返答できるようにする
+    const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+    let state = app_state::get(ctx).await?;
+
+    if !state.owner_ids.contains(&cmd.user.id) {
+        r(ctx, cmd, messages::text(lang, Key::OwnerOnly)).await?;
+        return Ok(());
+    }
+
+    let redis_check = async {
+        let mut conn = state.redis_client.get_async_connection().await?;
+        redis::cmd("PING")
+            .query_async::<_, String>(&mut conn)
+            .await?;
+        anyhow::Ok(())
+    };
+    // Open JTalkで音声を合成する場合は、確認するエンジンがない
+    let voicevox_check = async {
+        match &state.voicevox_client {
+            Some(client) => client.version().await.map(Some),
+            None => Ok(None),
+        }
+    };
+    let queue_check = async {
+        let mut total = 0;
+        for guild_id in state
+            .connected_guild_states
+            .iter()
+            .map(|entry| *entry.key())
+            .collect::<Vec<_>>()
+        {
+            total += state.voice_backend.queue_len(guild_id.into()).await?;
+        }
+        anyhow::Ok(total)
+    };
+
+    let (redis_result, voicevox_result, queue_result) = tokio::join!(
+        tokio::time::timeout(CHECK_TIMEOUT, redis_check),
+        tokio::time::timeout(CHECK_TIMEOUT, voicevox_check),
+        tokio::time::timeout(CHECK_TIMEOUT, queue_check),
+    );
+
+    {
+        let mut embed = CreateEmbed::default();
+        embed.title(messages::text(lang, Key::AdminStatusTitle));
+
+        embed.field(
+            "Redis",
+            match redis_result {
+                Ok(Ok(())) => messages::text(lang, Key::HealthOk),
+                Ok(Err(err)) => messages::format(
+                    lang,
+                    Key::HealthError,
+                    &[("error", &sanitize_response(&err.to_string()))],
+                ),
+                Err(_) => messages::text(lang, Key::HealthTimeout),
+            },
+            false,
+        );
+        let voicevox_status = match voicevox_result {
+            Ok(Ok(None)) => None,
+            Ok(Ok(Some(version))) => Some(messages::format(
+                lang,
+                Key::HealthOkWithVersion,
+                &[("version", &version)],
+            )),
+            Ok(Err(err)) => Some(messages::format(
+                lang,
+                Key::HealthError,
+                &[("error", &sanitize_response(&err.to_string()))],
+            )),
+            Err(_) => Some(messages::text(lang, Key::HealthTimeout)),
+        };
+        if let Some(voicevox_status) = voicevox_status {
+            embed.field("VOICEVOX ENGINE", voicevox_status, false);
+        }
+        let engine_statuses = state
+            .speech_providers
+            .iter()
+            .flat_map(|(_, provider)| provider.engine_statuses())
+            .collect::<Vec<_>>();
+        if engine_statuses.len() > 1 {
+            embed.field(
+                messages::text(lang, Key::SpeechEngines),
+                engine_statuses
+                    .iter()
+                    .map(|status| {
+                        messages::format(
+                            lang,
+                            if status.circuit_open {
+                                Key::SpeechEngineUnavailable
+                            } else {
+                                Key::SpeechEngineAvailable
+                            },
+                            &[("name", &status.name), ("count", &status.served)],
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                false,
+            );
+        }
+        // 音声のキャッシュはすべてのエンジンで共有しているため、1つ目の統計を表示する
+        if let Some(stats) = state
+            .speech_providers
+            .iter()
+            .find_map(|(_, provider)| provider.disk_cache_stats())
+        {
+            embed.field(
+                messages::text(lang, Key::AudioCache),
+                messages::format(
+                    lang,
+                    Key::AudioCacheStats,
+                    &[
+                        ("hits", &stats.hits),
+                        ("misses", &stats.misses),
+                        ("evictions", &stats.evictions),
+                        ("size", &(stats.size / 1024 / 1024)),
+                        ("max_size", &(stats.max_size / 1024 / 1024)),
+                    ],
+                ),
+                false,
+            );
+        }
+        embed.field(
+            messages::text(lang, Key::ConnectedGuilds),
+            match state.max_connections {
+                Some(max) => format!("{} / {}", state.connected_guild_states.len(), max),
+                None => state.connected_guild_states.len().to_string(),
+            },
+            true,
+        );
+        embed.field(
+            messages::text(lang, Key::QueuedMessages),
+            match queue_result {
+                Ok(Ok(total)) => total.to_string(),
+                Ok(Err(err)) => messages::format(
+                    lang,
+                    Key::CheckError,
+                    &[("error", &sanitize_response(&err.to_string()))],
+                ),
+                Err(_) => messages::text(lang, Key::CheckTimeout),
+            },
+            true,
+        );
+
+        embed.field(
+            messages::text(lang, Key::Recoveries),
+            messages::format(
+                lang,
+                Key::Times,
+                &[(
+                    "count",
+                    &state.recovery_attempts_total.load(Ordering::Relaxed),
+                )],
+            ),
+            true,
+        );
+
+        let skip_counts = state.skip_counter.counts();
+        embed.field(
+            messages::text(lang, Key::SkippedMessages),
+            if skip_counts.is_empty() {
+                messages::text(lang, Key::Nothing)
+            } else {
+                skip_counts
+                    .iter()
+                    .map(|(reason, count)| {
+                        format!("{}: {}", messages::text(lang, reason.label()), count)
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            },
+            false,
+        );
+
+        respond(
+            ctx,
+            cmd,
+            CommandResponse {
+                embeds: vec![embed],
+                ephemeral: true,
+                ..Default::default()
+            },
+        )
+        .await?;
+    };
+
+    Ok(())
+}
+
+async fn handle_inspect(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+    option: AdminInspectOption,
+) -> Result<()> {
+    let state = app_state::get(ctx).await?;
+
+    if !state.owner_ids.contains(&cmd.user.id) {
+        r(ctx, cmd, messages::text(lang, Key::OwnerOnly)).await?;
+        return Ok(());
+    }
+
+    let guild_id = match option.guild_id.trim().parse::<u64>() {
+        Ok(id) => GuildId(id),
+        Err(_) => {
+            r_ephemeral(
+                ctx,
+                cmd,
+                messages::format(lang, Key::InvalidGuildId, &[("id", &option.guild_id)]),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+    let guild_name = guild_id
+        .name(&ctx.cache)
+        .unwrap_or_else(|| guild_id.to_string());
+
+    let (
+        voice_channel,
+        bound_text_channels,
+        connected_at,
+        last_message_read,
+        voice_server,
+        last_speech_error,
+        recovery_attempts,
+    ) = match state.connected_guild_states.get(&guild_id) {
+        Some(guild_state) => (
+            guild_state.voice_channel,
+            guild_state.bound_text_channels.clone(),
+            guild_state.connected_at,
+            guild_state
+                .last_message_read
+                .as_ref()
+                .map(|msg| msg.timestamp.unix_timestamp()),
+            guild_state.voice_server.clone(),
+            guild_state.last_speech_error.clone(),
+            guild_state.recovery_attempts,
+        ),
+        None => {
+            r_ephemeral(
+                ctx,
+                cmd,
+                messages::format(lang, Key::InspectNotConnected, &[("guild", &guild_name)]),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let connected = state.voice_backend.is_connected(guild_id.into()).await?;
+    let queue = state.voice_backend.queue_status(guild_id.into()).await?;
+
+    {
+        let mut embed = CreateEmbed::default();
+        embed.title(messages::format(
+            lang,
+            Key::InspectTitle,
+            &[("guild", &guild_name)],
+        ));
+        embed.footer(|footer| footer.text(guild_id));
+        embed.field(
+            messages::text(lang, Key::ConnectionState),
+            match (connected, voice_server) {
+                (true, Some(server)) => messages::format(
+                    lang,
+                    Key::ConnectionStateConnectedTo,
+                    &[("server", &server)],
+                ),
+                (true, None) => messages::text(lang, Key::ConnectionStateConnected),
+                (false, _) => messages::text(lang, Key::ConnectionStateDisconnected),
+            },
+            false,
+        );
+        embed.field(
+            messages::text(lang, Key::StatusVoiceChannel),
+            format!("<#{}>", voice_channel),
+            true,
+        );
+        embed.field(
+            messages::text(lang, Key::StatusBoundChannels),
+            bound_text_channels
+                .iter()
+                .map(|id| format!("<#{}>", id))
+                .collect::<Vec<_>>()
+                .join(", "),
+            true,
+        );
+        embed.field(
+            messages::text(lang, Key::StatusConnectedFor),
+            format_duration(lang, connected_at.elapsed()),
+            true,
+        );
+        embed.field(
+            messages::text(lang, Key::LastMessageRead),
+            match last_message_read {
+                Some(timestamp) => format!("<t:{}:R>", timestamp),
+                None => messages::text(lang, Key::Nothing),
+            },
+            true,
+        );
+        embed.field(
+            messages::text(lang, Key::QueuedMessages),
+            match queue.oldest_enqueued_at {
+                Some(enqueued_at) => messages::format(
+                    lang,
+                    Key::QueueWithOldest,
+                    &[
+                        ("count", &queue.len),
+                        ("age", &format_duration(lang, enqueued_at.elapsed())),
+                    ],
+                ),
+                None => messages::format(lang, Key::QueueCount, &[("count", &queue.len)]),
+            },
+            false,
+        );
+        embed.field(
+            messages::text(lang, Key::LastSpeechError),
+            match last_speech_error {
+                Some(err) => sanitize_response(&err),
+                None => messages::text(lang, Key::Nothing),
+            },
+            false,
+        );
+        embed.field(
+            messages::text(lang, Key::Recoveries),
+            messages::format(lang, Key::Times, &[("count", &recovery_attempts)]),
+            true,
+        );
+
+        respond(
+            ctx,
+            cmd,
+            CommandResponse {
+                embeds: vec![embed],
+                ephemeral: true,
+                ..Default::default()
+            },
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+async fn handle_usage_limit(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+    option: AdminUsageLimitOption,
+) -> Result<()> {
+    let state = app_state::get(ctx).await?;
+
+    if !state.owner_ids.contains(&cmd.user.id) {
+        r(ctx, cmd, messages::text(lang, Key::OwnerOnly)).await?;
+        return Ok(());
+    }
+
+    let guild_id = match option.guild_id.trim().parse::<u64>() {
+        Ok(id) => GuildId(id),
+        Err(_) => {
+            r_ephemeral(
+                ctx,
+                cmd,
+                messages::format(lang, Key::InvalidGuildId, &[("id", &option.guild_id)]),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+    let guild_name = guild_id
+        .name(&ctx.cache)
+        .unwrap_or_else(|| guild_id.to_string());
+
+    let limit = u64::try_from(option.chars).ok().filter(|&chars| chars > 0);
+    let mut conn = state.redis_client.get_async_connection().await?;
+    usage::set_limit(
+        &mut conn,
+        usage::SetLimitOption {
+            guild_id: guild_id.into(),
+            limit,
+        },
+    )
+    .await?;
+
+    let msg = match limit {
+        Some(limit) => messages::format(
+            lang,
+            Key::UsageLimitSet,
+            &[("guild", &guild_name), ("limit", &limit)],
+        ),
+        None => messages::format(lang, Key::UsageLimitRemoved, &[("guild", &guild_name)]),
+    };
+    r_ephemeral(ctx, cmd, msg).await?;
+    Ok(())
+}
+
+fn parse_admin(option_admin: &CommandDataOption) -> Result<Command, CommandParseError> {
+    match option_admin.name.as_str() {
+        "status" => Ok(Command::AdminStatus),
+        "inspect" => Ok(Command::AdminInspect(AdminInspectOption {
+            guild_id: required(find_string(&option_admin.options, "guild_id")?, "guild_id")?,
+        })),
+        "usage-limit" => Ok(Command::AdminUsageLimit(AdminUsageLimitOption {
+            guild_id: required(find_string(&option_admin.options, "guild_id")?, "guild_id")?,
+            chars: required(find_integer(&option_admin.options, "chars")?, "chars")?,
+        })),
+        _ => Err(unknown_subcommand("admin", option_admin)),
+    }
+}
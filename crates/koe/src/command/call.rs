@@ -0,0 +1,999 @@
+use super::{
+    confirm,
+    handler::{
+        confirm, format_duration, guild_only, r, r_ephemeral, respond, sanitize_response,
+        CommandResponse,
+    },
+    model::Command,
+    parser::CommandParseError,
+    registry::{Category, CommandSpec, SlashCommand},
+};
+use crate::{
+    app_state,
+    component_interaction::custom_id::ConfirmAction,
+    connection,
+    messages::{self, Key},
+};
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+use koe_call::VoiceBackend;
+use koe_db::language::Language;
+use serenity::{
+    builder::CreateEmbed,
+    client::Context,
+    model::{
+        application::interaction::application_command::{
+            ApplicationCommandInteraction, CommandDataOption,
+        },
+        id::{ChannelId, GuildId, UserId},
+        Permissions,
+    },
+};
+
+/// `/join`の定義
+const JOIN: CommandSpec = CommandSpec {
+    name: "join",
+    description: "ボイスチャンネルに接続し、読み上げを開始",
+    description_en: "Join your voice channel and start reading",
+    category: Category::Connection,
+    aliases: &["kjoin"],
+    manage_guild_only: false,
+    dm_allowed: false,
+    options: &[],
+};
+
+/// `/leave`の定義
+const LEAVE: CommandSpec = CommandSpec {
+    name: "leave",
+    description: "ボイスチャンネルから退出",
+    description_en: "Leave the voice channel",
+    category: Category::Connection,
+    aliases: &["kleave"],
+    manage_guild_only: false,
+    dm_allowed: false,
+    options: &[],
+};
+
+/// `/skip`の定義
+const SKIP: CommandSpec = CommandSpec {
+    name: "skip",
+    description: "読み上げ中のメッセージをスキップ",
+    description_en: "Skip the message being read",
+    category: Category::Connection,
+    aliases: &["kskip"],
+    manage_guild_only: false,
+    dm_allowed: false,
+    options: &[],
+};
+
+/// `/move`の定義
+const MOVE: CommandSpec = CommandSpec {
+    name: "move",
+    description: "読み上げを続けたまま、あなたのいるボイスチャンネルに移動",
+    description_en: "Move to your voice channel while continuing to read",
+    category: Category::Connection,
+    aliases: &[],
+    manage_guild_only: false,
+    dm_allowed: false,
+    options: &[],
+};
+
+/// `/summon`の定義
+const SUMMON: CommandSpec = CommandSpec {
+    name: "summon",
+    description: "あなたのいるボイスチャンネルに呼び出す（未接続の場合は読み上げを開始）",
+    description_en: "Bring the bot to your voice channel (starts reading if not connected)",
+    category: Category::Connection,
+    aliases: &[],
+    manage_guild_only: false,
+    dm_allowed: false,
+    options: &[],
+};
+
+/// `/status`の定義
+const STATUS: CommandSpec = CommandSpec {
+    name: "status",
+    description: "読み上げの状況を表示",
+    description_en: "Show the reading status",
+    category: Category::Connection,
+    aliases: &[],
+    manage_guild_only: false,
+    dm_allowed: false,
+    options: &[],
+};
+
+pub(super) struct JoinCommand;
+
+#[async_trait]
+impl SlashCommand for JoinCommand {
+    fn spec(&self) -> &'static CommandSpec {
+        &JOIN
+    }
+
+    fn parse(&self, _: &[CommandDataOption]) -> Result<Command, CommandParseError> {
+        Ok(Command::Join)
+    }
+
+    async fn run(
+        &self,
+        ctx: &Context,
+        cmd: &ApplicationCommandInteraction,
+        lang: Language,
+        _: Command,
+    ) -> Result<()> {
+        handle_join(ctx, cmd, lang)
+            .await
+            .context("Failed to execute /join")
+    }
+}
+
+pub(super) struct LeaveCommand;
+
+#[async_trait]
+impl SlashCommand for LeaveCommand {
+    fn spec(&self) -> &'static CommandSpec {
+        &LEAVE
+    }
+
+    fn parse(&self, _: &[CommandDataOption]) -> Result<Command, CommandParseError> {
+        Ok(Command::Leave)
+    }
+
+    async fn run(
+        &self,
+        ctx: &Context,
+        cmd: &ApplicationCommandInteraction,
+        lang: Language,
+        _: Command,
+    ) -> Result<()> {
+        handle_leave(ctx, cmd, lang)
+            .await
+            .context("Failed to execute /leave")
+    }
+}
+
+pub(super) struct SkipCommand;
+
+#[async_trait]
+impl SlashCommand for SkipCommand {
+    fn spec(&self) -> &'static CommandSpec {
+        &SKIP
+    }
+
+    fn parse(&self, _: &[CommandDataOption]) -> Result<Command, CommandParseError> {
+        Ok(Command::Skip)
+    }
+
+    async fn run(
+        &self,
+        ctx: &Context,
+        cmd: &ApplicationCommandInteraction,
+        lang: Language,
+        _: Command,
+    ) -> Result<()> {
+        handle_skip(ctx, cmd, lang)
+            .await
+            .context("Failed to execute /skip")
+    }
+}
+
+pub(super) struct MoveCommand;
+
+#[async_trait]
+impl SlashCommand for MoveCommand {
+    fn spec(&self) -> &'static CommandSpec {
+        &MOVE
+    }
+
+    fn parse(&self, _: &[CommandDataOption]) -> Result<Command, CommandParseError> {
+        Ok(Command::Move)
+    }
+
+    async fn run(
+        &self,
+        ctx: &Context,
+        cmd: &ApplicationCommandInteraction,
+        lang: Language,
+        _: Command,
+    ) -> Result<()> {
+        handle_move(ctx, cmd, lang)
+            .await
+            .context("Failed to execute /move")
+    }
+}
+
+pub(super) struct SummonCommand;
+
+#[async_trait]
+impl SlashCommand for SummonCommand {
+    fn spec(&self) -> &'static CommandSpec {
+        &SUMMON
+    }
+
+    fn parse(&self, _: &[CommandDataOption]) -> Result<Command, CommandParseError> {
+        Ok(Command::Summon)
+    }
+
+    async fn run(
+        &self,
+        ctx: &Context,
+        cmd: &ApplicationCommandInteraction,
+        lang: Language,
+        _: Command,
+    ) -> Result<()> {
+        handle_summon(ctx, cmd, lang)
+            .await
+            .context("Failed to execute /summon")
+    }
+}
+
+pub(super) struct StatusCommand;
+
+#[async_trait]
+impl SlashCommand for StatusCommand {
+    fn spec(&self) -> &'static CommandSpec {
+        &STATUS
+    }
+
+    fn parse(&self, _: &[CommandDataOption]) -> Result<Command, CommandParseError> {
+        Ok(Command::Status)
+    }
+
+    async fn run(
+        &self,
+        ctx: &Context,
+        cmd: &ApplicationCommandInteraction,
+        lang: Language,
+        _: Command,
+    ) -> Result<()> {
+        handle_status(ctx, cmd, lang)
+            .await
+            .context("Failed to execute /status")
+    }
+}
+
+/// 読み上げに必要な権限と、Discordのクライアントで表示される名前
+const VOICE_PERMISSION_NAMES: [(Permissions, Key); 3] = [
+    (Permissions::CONNECT, Key::PermissionConnect),
+    (Permissions::SPEAK, Key::PermissionSpeak),
+    (Permissions::MUTE_MEMBERS, Key::PermissionMuteMembers),
+];
+
+async fn handle_join(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r(ctx, cmd, guild_only(lang, "`/join`, `/kjoin`")).await?;
+            return Ok(());
+        }
+    };
+    let user_id = cmd.user.id;
+    let text_channel_id = cmd.channel_id;
+
+    let voice_channel_id = match get_user_voice_channel(ctx, &guild_id, &user_id)? {
+        Some(channel) => channel,
+        None => {
+            r(
+                ctx,
+                cmd,
+                messages::format(
+                    lang,
+                    Key::JoinVoiceChannelFirst,
+                    &[("command", &cmd.data.name)],
+                ),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let state = app_state::get(ctx).await?;
+    let current_channels = state
+        .connected_guild_states
+        .get(&guild_id)
+        .map(|guild_state| {
+            (
+                guild_state.voice_channel,
+                guild_state.bound_text_channels.clone(),
+            )
+        });
+    let action = join_action(
+        current_channels
+            .as_ref()
+            .map(|(voice, texts)| (*voice, texts.as_slice())),
+        voice_channel_id,
+        text_channel_id,
+        state.is_at_connection_limit(),
+    );
+
+    let msg = match action {
+        JoinAction::AlreadyReadingHere => messages::text(lang, Key::AlreadyReadingHere),
+        JoinAction::ConnectionLimitReached => messages::text(lang, Key::ConnectionLimitReached),
+        // すでに接続している場合は、キューを保ったまま接続先と読み上げ対象を切り替える
+        JoinAction::Rebind => {
+            connection::rebind(ctx, guild_id, text_channel_id).await?;
+            messages::format(lang, Key::Rebound, &[("text", &text_channel_id)])
+        }
+        JoinAction::MoveAndRebind => {
+            if reply_if_permissions_missing(ctx, cmd, lang, voice_channel_id).await? {
+                return Ok(());
+            }
+            if let Err(err) = connection::move_to(ctx, guild_id, voice_channel_id).await {
+                return reply_join_error(ctx, cmd, lang, err).await;
+            }
+            connection::rebind(ctx, guild_id, text_channel_id).await?;
+            messages::format(
+                lang,
+                Key::MovedAndRebound,
+                &[("voice", &voice_channel_id), ("text", &text_channel_id)],
+            )
+        }
+        JoinAction::Join => {
+            if reply_if_permissions_missing(ctx, cmd, lang, voice_channel_id).await? {
+                return Ok(());
+            }
+            if let Err(err) =
+                connection::join(ctx, guild_id, voice_channel_id, text_channel_id).await
+            {
+                return reply_join_error(ctx, cmd, lang, err).await;
+            }
+            messages::text(lang, Key::Joined)
+        }
+    };
+
+    r(ctx, cmd, msg).await?;
+    Ok(())
+}
+
+/// `/join`で行う操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum JoinAction {
+    /// すでに同じボイスチャンネルで、同じテキストチャンネルのみを読み上げている
+    AlreadyReadingHere,
+    /// 接続先はそのままで、読み上げ対象のテキストチャンネルを切り替える
+    Rebind,
+    /// 接続先と読み上げ対象のテキストチャンネルを切り替える
+    MoveAndRebind,
+    /// 同時に接続できるボイスチャンネルの数が上限に達している
+    ConnectionLimitReached,
+    Join,
+}
+
+/// 現在の接続状態（ボイスチャンネルと読み上げ対象のテキストチャンネル）から、`/join`で行う操作を決める
+fn join_action(
+    current: Option<(ChannelId, &[ChannelId])>,
+    voice_channel_id: ChannelId,
+    text_channel_id: ChannelId,
+    at_connection_limit: bool,
+) -> JoinAction {
+    match current {
+        Some((current_voice, current_texts))
+            if current_voice == voice_channel_id && current_texts == [text_channel_id] =>
+        {
+            JoinAction::AlreadyReadingHere
+        }
+        Some((current_voice, _)) if current_voice == voice_channel_id => JoinAction::Rebind,
+        Some(_) => JoinAction::MoveAndRebind,
+        None if at_connection_limit => JoinAction::ConnectionLimitReached,
+        None => JoinAction::Join,
+    }
+}
+
+async fn handle_leave(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r(ctx, cmd, guild_only(lang, "`/leave`, `/kleave`")).await?;
+            return Ok(());
+        }
+    };
+
+    let state = app_state::get(ctx).await?;
+
+    match leave_action(&*state.voice_backend, guild_id).await? {
+        LeaveAction::NotConnected => {
+            r(ctx, cmd, messages::text(lang, Key::NotConnected)).await?;
+        }
+        LeaveAction::Confirm { queue_len } => {
+            confirm(
+                ctx,
+                cmd,
+                lang,
+                messages::format(lang, Key::ConfirmLeave, &[("count", &queue_len)]),
+                ConfirmAction::Leave,
+            )
+            .await?;
+        }
+        LeaveAction::Leave => {
+            let msg = confirm::execute(ctx, lang, ConfirmAction::Leave, guild_id).await?;
+            r(ctx, cmd, msg).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// `/leave`で行う操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LeaveAction {
+    NotConnected,
+    /// 読み上げ中の音声が残っている場合は、誤って切断しないよう確認する
+    Confirm {
+        queue_len: usize,
+    },
+    Leave,
+}
+
+async fn leave_action(backend: &dyn VoiceBackend, guild_id: GuildId) -> Result<LeaveAction> {
+    if !backend.is_connected(guild_id.into()).await? {
+        return Ok(LeaveAction::NotConnected);
+    }
+
+    let queue_len = backend.queue_len(guild_id.into()).await?;
+    if queue_len > 0 {
+        return Ok(LeaveAction::Confirm { queue_len });
+    }
+
+    Ok(LeaveAction::Leave)
+}
+
+async fn handle_skip(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r(ctx, cmd, guild_only(lang, "`/skip`, `/kskip`")).await?;
+            return Ok(());
+        }
+    };
+
+    let state = app_state::get(ctx).await?;
+
+    if !state.voice_backend.is_connected(guild_id.into()).await? {
+        {
+            r(ctx, cmd, messages::text(lang, Key::NotConnected)).await?;
+            return Ok(());
+        };
+    }
+
+    state.voice_backend.skip(guild_id.into()).await?;
+
+    r(ctx, cmd, messages::text(lang, Key::Skipped)).await?;
+    Ok(())
+}
+
+async fn handle_move(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r(ctx, cmd, guild_only(lang, "`/move`")).await?;
+            return Ok(());
+        }
+    };
+
+    let state = app_state::get(ctx).await?;
+    let current_voice_channel_id = match state.connected_guild_states.get(&guild_id) {
+        Some(guild_state) => guild_state.voice_channel,
+        None => {
+            r(ctx, cmd, messages::text(lang, Key::NotConnected)).await?;
+            return Ok(());
+        }
+    };
+
+    let voice_channel_id = match get_user_voice_channel(ctx, &guild_id, &cmd.user.id)? {
+        Some(channel) => channel,
+        None => {
+            r(
+                ctx,
+                cmd,
+                messages::format(
+                    lang,
+                    Key::JoinVoiceChannelFirst,
+                    &[("command", &cmd.data.name)],
+                ),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+
+    if voice_channel_id == current_voice_channel_id {
+        r(ctx, cmd, messages::text(lang, Key::AlreadyInVoiceChannel)).await?;
+        return Ok(());
+    }
+
+    if reply_if_permissions_missing(ctx, cmd, lang, voice_channel_id).await? {
+        return Ok(());
+    }
+
+    if let Err(err) = connection::move_to(ctx, guild_id, voice_channel_id).await {
+        return reply_join_error(ctx, cmd, lang, err).await;
+    }
+
+    r(
+        ctx,
+        cmd,
+        messages::format(lang, Key::Moved, &[("channel", &voice_channel_id)]),
+    )
+    .await?;
+    Ok(())
+}
+
+/// 接続していない場合は`/join`と同じように接続し、接続している場合は`/move`と同じように移動する
+async fn handle_summon(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r(ctx, cmd, guild_only(lang, "`/summon`")).await?;
+            return Ok(());
+        }
+    };
+
+    let state = app_state::get(ctx).await?;
+    if state.connected_guild_states.contains_key(&guild_id) {
+        handle_move(ctx, cmd, lang).await
+    } else {
+        handle_join(ctx, cmd, lang).await
+    }
+}
+
+async fn handle_status(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r_ephemeral(ctx, cmd, guild_only(lang, "`/status`")).await?;
+            return Ok(());
+        }
+    };
+
+    let state = app_state::get(ctx).await?;
+    let (voice_channel, bound_text_channels, connected_at, last_speech_error, recovery_attempts) =
+        match state.connected_guild_states.get(&guild_id) {
+            Some(guild_state) => (
+                guild_state.voice_channel,
+                guild_state.bound_text_channels.clone(),
+                guild_state.connected_at,
+                guild_state.last_speech_error.clone(),
+                guild_state.recovery_attempts,
+            ),
+            None => {
+                r_ephemeral(ctx, cmd, messages::text(lang, Key::StatusNotConnected)).await?;
+                return Ok(());
+            }
+        };
+
+    let queue = state.voice_backend.queue_status(guild_id.into()).await?;
+
+    {
+        let mut embed = CreateEmbed::default();
+        embed.title(messages::text(lang, Key::StatusTitle));
+        embed.field(
+            messages::text(lang, Key::StatusVoiceChannel),
+            format!("<#{}>", voice_channel),
+            true,
+        );
+        embed.field(
+            messages::text(lang, Key::StatusBoundChannels),
+            bound_text_channels
+                .iter()
+                .map(|id| format!("<#{}>", id))
+                .collect::<Vec<_>>()
+                .join(", "),
+            true,
+        );
+        embed.field(
+            messages::text(lang, Key::StatusConnectedFor),
+            format_duration(lang, connected_at.elapsed()),
+            true,
+        );
+        embed.field(
+            messages::text(lang, Key::QueuedMessages),
+            match queue.oldest_enqueued_at {
+                Some(enqueued_at) => messages::format(
+                    lang,
+                    Key::QueueWithOldest,
+                    &[
+                        ("count", &queue.len),
+                        ("age", &format_duration(lang, enqueued_at.elapsed())),
+                    ],
+                ),
+                None => messages::format(lang, Key::QueueCount, &[("count", &queue.len)]),
+            },
+            false,
+        );
+        embed.field(
+            messages::text(lang, Key::LastSpeechError),
+            match last_speech_error {
+                Some(err) => sanitize_response(&err),
+                None => messages::text(lang, Key::Nothing),
+            },
+            false,
+        );
+        embed.field(
+            messages::text(lang, Key::Recoveries),
+            messages::format(lang, Key::Times, &[("count", &recovery_attempts)]),
+            true,
+        );
+
+        respond(
+            ctx,
+            cmd,
+            CommandResponse {
+                embeds: vec![embed],
+                ephemeral: true,
+                ..Default::default()
+            },
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+fn get_user_voice_channel(
+    ctx: &Context,
+    guild_id: &GuildId,
+    user_id: &UserId,
+) -> Result<Option<ChannelId>> {
+    let guild = guild_id
+        .to_guild_cached(&ctx.cache)
+        .context("Failed to find guild in the cache")?;
+
+    let channel_id = guild
+        .voice_states
+        .get(user_id)
+        .and_then(|voice_state| voice_state.channel_id);
+
+    Ok(channel_id)
+}
+
+/// Botが`voice_channel_id`で読み上げるための権限が不足している場合に、不足している権限を返信する
+/// 返信した場合は`true`を返す
+async fn reply_if_permissions_missing(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+    voice_channel_id: ChannelId,
+) -> Result<bool> {
+    // 権限を計算できない場合は接続を試み、失敗した際のエラーから原因を伝える
+    let missing = match connection::missing_permissions(ctx, voice_channel_id) {
+        Some(missing) if !missing.is_empty() => missing,
+        _ => return Ok(false),
+    };
+
+    let names = VOICE_PERMISSION_NAMES
+        .iter()
+        .filter(|(permission, _)| missing.contains(*permission))
+        .map(|(_, name)| {
+            messages::format(
+                lang,
+                Key::QuotedName,
+                &[("name", &messages::text(lang, *name))],
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(&messages::text(lang, Key::ListSeparator));
+    r(
+        ctx,
+        cmd,
+        messages::format(
+            lang,
+            Key::PermissionsMissing,
+            &[("channel", &voice_channel_id), ("permissions", &names)],
+        ),
+    )
+    .await?;
+
+    Ok(true)
+}
+
+/// 接続に失敗した原因を利用者に伝えられる場合は返信し、そうでなければエラーを返す
+async fn reply_join_error(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+    err: anyhow::Error,
+) -> Result<()> {
+    match connection::describe_join_error(&err) {
+        Some(key) => {
+            r(ctx, cmd, messages::text(lang, key)).await?;
+            Ok(())
+        }
+        None => Err(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        command::{
+            handler,
+            responder::{Sent, SentMessage},
+        },
+        test_support::{self, guild_command, TestContext},
+    };
+    use koe_call::backend::FakeBackend;
+    use serde_json::json;
+
+    const GUILD_ID: GuildId = GuildId(1);
+    const VOICE: ChannelId = ChannelId(10);
+    const OTHER_VOICE: ChannelId = ChannelId(11);
+    const TEXT: ChannelId = ChannelId(20);
+    const OTHER_TEXT: ChannelId = ChannelId(21);
+
+    #[test]
+    fn join_connects_when_not_connected() {
+        assert_eq!(join_action(None, VOICE, TEXT, false), JoinAction::Join);
+        assert_eq!(
+            join_action(None, VOICE, TEXT, true),
+            JoinAction::ConnectionLimitReached
+        );
+    }
+
+    #[test]
+    fn join_switches_channels_when_connected() {
+        assert_eq!(
+            join_action(Some((VOICE, &[TEXT])), VOICE, TEXT, false),
+            JoinAction::AlreadyReadingHere
+        );
+        assert_eq!(
+            join_action(Some((VOICE, &[OTHER_TEXT])), VOICE, TEXT, false),
+            JoinAction::Rebind
+        );
+        // `/channels add`で追加したチャンネルも読み上げている場合は、送信したチャンネルのみに戻す
+        assert_eq!(
+            join_action(Some((VOICE, &[TEXT, OTHER_TEXT])), VOICE, TEXT, false),
+            JoinAction::Rebind
+        );
+        assert_eq!(
+            join_action(Some((OTHER_VOICE, &[TEXT])), VOICE, TEXT, false),
+            JoinAction::MoveAndRebind
+        );
+        // 接続済みのサーバーでは、接続数の上限に関わらず切り替えられる
+        assert_eq!(
+            join_action(Some((OTHER_VOICE, &[TEXT])), VOICE, TEXT, true),
+            JoinAction::MoveAndRebind
+        );
+    }
+
+    #[tokio::test]
+    async fn leave_requires_connection() {
+        let backend = FakeBackend::new();
+        assert_eq!(
+            leave_action(&backend, GUILD_ID).await.unwrap(),
+            LeaveAction::NotConnected
+        );
+
+        backend
+            .join(GUILD_ID.into(), VOICE.into(), false)
+            .await
+            .unwrap();
+        assert_eq!(
+            leave_action(&backend, GUILD_ID).await.unwrap(),
+            LeaveAction::Leave
+        );
+
+        backend.leave(GUILD_ID.into()).await.unwrap();
+        assert_eq!(
+            leave_action(&backend, GUILD_ID).await.unwrap(),
+            LeaveAction::NotConnected
+        );
+    }
+
+    #[tokio::test]
+    async fn leave_confirms_until_queue_is_played() {
+        let backend = FakeBackend::new();
+        backend
+            .join(GUILD_ID.into(), VOICE.into(), false)
+            .await
+            .unwrap();
+        for audio in [vec![1], vec![2]] {
+            backend.enqueue(GUILD_ID.into(), audio, None).await.unwrap();
+        }
+        assert_eq!(
+            leave_action(&backend, GUILD_ID).await.unwrap(),
+            LeaveAction::Confirm { queue_len: 2 }
+        );
+
+        // 追加した順に再生される
+        assert_eq!(backend.finish_track(GUILD_ID.into()), Some(vec![1]));
+        assert_eq!(
+            leave_action(&backend, GUILD_ID).await.unwrap(),
+            LeaveAction::Confirm { queue_len: 1 }
+        );
+        assert_eq!(backend.finish_track(GUILD_ID.into()), Some(vec![2]));
+        assert_eq!(backend.finish_track(GUILD_ID.into()), None);
+        assert_eq!(
+            leave_action(&backend, GUILD_ID).await.unwrap(),
+            LeaveAction::Leave
+        );
+    }
+
+    fn text(key: Key) -> SentMessage {
+        SentMessage {
+            content: Some(messages::text(Language::Japanese, key)),
+            embed_titles: Vec::new(),
+            has_components: false,
+            ephemeral: false,
+        }
+    }
+
+    async fn join_fake(t: &TestContext) {
+        t.backend
+            .join(
+                test_support::GUILD_ID.into(),
+                test_support::VOICE_CHANNEL_ID.into(),
+                false,
+            )
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn join_asks_to_join_a_voice_channel_first() {
+        let t = TestContext::new().await;
+        t.cache_guild(false);
+        handler::handle(&t.ctx, &guild_command("join", json!([]), true))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            t.responder.sent(),
+            [Sent::Response(SentMessage {
+                content: Some(messages::format(
+                    Language::Japanese,
+                    Key::JoinVoiceChannelFirst,
+                    &[("command", &"join")],
+                )),
+                ..text(Key::JoinVoiceChannelFirst)
+            })]
+        );
+        assert!(t.backend.call(test_support::GUILD_ID.into()).is_none());
+    }
+
+    #[tokio::test]
+    async fn join_connects_to_the_members_voice_channel() {
+        let t = TestContext::new().await;
+        t.cache_guild(true);
+
+        // 接続後に接続時のチャイムの設定をRedisから取得する際に失敗するが、接続は済んでいる
+        assert!(
+            handler::handle(&t.ctx, &guild_command("join", json!([]), true))
+                .await
+                .is_err()
+        );
+
+        let call = t.backend.call(test_support::GUILD_ID.into()).unwrap();
+        assert_eq!(call.channel_id, test_support::VOICE_CHANNEL_ID.into());
+        assert!(call.has_event_handlers);
+        let guild_state = t
+            .state
+            .connected_guild_states
+            .get(&GuildId(test_support::GUILD_ID))
+            .unwrap();
+        assert_eq!(
+            guild_state.voice_channel,
+            ChannelId(test_support::VOICE_CHANNEL_ID)
+        );
+        assert_eq!(
+            guild_state.bound_text_channels,
+            [ChannelId(test_support::TEXT_CHANNEL_ID)]
+        );
+    }
+
+    #[tokio::test]
+    async fn join_does_nothing_when_already_reading_here() {
+        let t = TestContext::new().await;
+        t.cache_guild(true);
+        assert!(
+            handler::handle(&t.ctx, &guild_command("join", json!([]), true))
+                .await
+                .is_err()
+        );
+
+        handler::handle(&t.ctx, &guild_command("join", json!([]), true))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            t.responder.sent().last(),
+            Some(&Sent::Response(text(Key::AlreadyReadingHere)))
+        );
+    }
+
+    #[tokio::test]
+    async fn leave_replies_when_not_connected() {
+        let t = TestContext::new().await;
+        handler::handle(&t.ctx, &guild_command("leave", json!([]), true))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            t.responder.sent(),
+            [
+                Sent::Defer { ephemeral: false },
+                Sent::Followup(text(Key::NotConnected)),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn leave_confirms_while_audio_is_queued() {
+        let t = TestContext::new().await;
+        join_fake(&t).await;
+        t.backend
+            .enqueue(test_support::GUILD_ID.into(), vec![1], None)
+            .await
+            .unwrap();
+
+        let cmd = guild_command("leave", json!([]), true);
+        handler::handle(&t.ctx, &cmd).await.unwrap();
+
+        assert_eq!(
+            t.responder.sent(),
+            [
+                Sent::Defer { ephemeral: false },
+                Sent::Followup(SentMessage {
+                    content: Some(messages::format(
+                        Language::Japanese,
+                        Key::ConfirmLeave,
+                        &[("count", &1)],
+                    )),
+                    has_components: true,
+                    ..text(Key::ConfirmLeave)
+                }),
+            ]
+        );
+        assert!(t.state.pending_confirmations.contains(&cmd.id));
+        // 確認するまでは接続したままにする
+        assert!(t.backend.call(test_support::GUILD_ID.into()).is_some());
+    }
+
+    #[tokio::test]
+    async fn leave_disconnects_when_queue_is_empty() {
+        let t = TestContext::new().await;
+        join_fake(&t).await;
+
+        handler::handle(&t.ctx, &guild_command("leave", json!([]), true))
+            .await
+            .unwrap();
+
+        // 退出時の挨拶とチャイムはRedisに接続できず失敗するが、退出は続ける
+        assert_eq!(
+            t.responder.sent(),
+            [
+                Sent::Defer { ephemeral: false },
+                Sent::Followup(text(Key::Left)),
+            ]
+        );
+        assert!(t.backend.call(test_support::GUILD_ID.into()).is_none());
+        assert!(t
+            .state
+            .manual_leave_times
+            .contains_key(&GuildId(test_support::GUILD_ID)));
+    }
+}
@@ -0,0 +1,217 @@
+use super::{
+    handler::{guild_only, r},
+    model::{ChannelsOption, Command},
+    parser::{find_channel, find_subcommand, unknown_subcommand, CommandParseError},
+    registry::{subcommand, Category, CommandSpec, OptionSpec, SlashCommand, TEXT_CHANNEL},
+};
+use crate::{
+    app_state,
+    messages::{self, Key},
+};
+use anyhow::{bail, Context as _, Result};
+use async_trait::async_trait;
+use koe_db::language::Language;
+use serenity::{
+    client::Context,
+    model::application::interaction::application_command::{
+        ApplicationCommandInteraction, CommandDataOption,
+    },
+};
+
+/// `/channels`の定義
+const SPEC: CommandSpec = CommandSpec {
+    name: "channels",
+    description: "読み上げ対象のテキストチャンネルの設定",
+    description_en: "Configure the text channels being read",
+    category: Category::Connection,
+    aliases: &[],
+    manage_guild_only: false,
+    dm_allowed: false,
+    options: &[
+        subcommand(
+            "add",
+            "読み上げ対象にテキストチャンネルを追加",
+            "Add a text channel to be read",
+            &[OptionSpec::new(
+                "channel",
+                "追加するテキストチャンネル（省略時はこのチャンネル）",
+                "Text channel to add (defaults to this channel)",
+                TEXT_CHANNEL,
+            )],
+        ),
+        subcommand(
+            "remove",
+            "読み上げ対象からテキストチャンネルを削除",
+            "Stop reading a text channel",
+            &[OptionSpec::new(
+                "channel",
+                "削除するテキストチャンネル（省略時はこのチャンネル）",
+                "Text channel to remove (defaults to this channel)",
+                TEXT_CHANNEL,
+            )],
+        ),
+        subcommand(
+            "list",
+            "読み上げ対象のテキストチャンネルを表示",
+            "Show the text channels being read",
+            &[],
+        ),
+    ],
+};
+
+pub(super) struct ChannelsCommand;
+
+#[async_trait]
+impl SlashCommand for ChannelsCommand {
+    fn spec(&self) -> &'static CommandSpec {
+        &SPEC
+    }
+
+    fn parse(&self, options: &[CommandDataOption]) -> Result<Command, CommandParseError> {
+        parse_channels(find_subcommand("channels", options)?)
+    }
+
+    async fn run(
+        &self,
+        ctx: &Context,
+        cmd: &ApplicationCommandInteraction,
+        lang: Language,
+        command: Command,
+    ) -> Result<()> {
+        match command {
+            Command::ChannelsAdd(option) => handle_add(ctx, cmd, lang, option)
+                .await
+                .context("Failed to execute /channels add"),
+            Command::ChannelsRemove(option) => handle_remove(ctx, cmd, lang, option)
+                .await
+                .context("Failed to execute /channels remove"),
+            Command::ChannelsList => handle_list(ctx, cmd, lang)
+                .await
+                .context("Failed to execute /channels list"),
+            command => bail!("Unexpected command for /channels: {:?}", command),
+        }
+    }
+}
+
+/// 同時に読み上げられるテキストチャンネルの最大数
+const MAX_BOUND_TEXT_CHANNELS: usize = 5;
+
+async fn handle_add(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+    option: ChannelsOption,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r(ctx, cmd, guild_only(lang, "`/channels`")).await?;
+            return Ok(());
+        }
+    };
+    let channel_id = option.channel.unwrap_or(cmd.channel_id);
+
+    let state = app_state::get(ctx).await?;
+    let msg = match state.connected_guild_states.get_mut(&guild_id) {
+        Some(mut guild_state) => {
+            if guild_state.is_bound(channel_id) {
+                messages::format(lang, Key::ChannelAlreadyBound, &[("channel", &channel_id)])
+            } else if guild_state.bound_text_channels.len() >= MAX_BOUND_TEXT_CHANNELS {
+                messages::format(
+                    lang,
+                    Key::TooManyBoundChannels,
+                    &[("max", &MAX_BOUND_TEXT_CHANNELS)],
+                )
+            } else {
+                guild_state.bound_text_channels.push(channel_id);
+                messages::format(lang, Key::ChannelBound, &[("channel", &channel_id)])
+            }
+        }
+        None => messages::text(lang, Key::NotConnected),
+    };
+
+    r(ctx, cmd, msg).await?;
+    Ok(())
+}
+
+async fn handle_remove(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+    option: ChannelsOption,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r(ctx, cmd, guild_only(lang, "`/channels`")).await?;
+            return Ok(());
+        }
+    };
+    let channel_id = option.channel.unwrap_or(cmd.channel_id);
+
+    let state = app_state::get(ctx).await?;
+    let msg = match state.connected_guild_states.get_mut(&guild_id) {
+        Some(mut guild_state) => {
+            if !guild_state.is_bound(channel_id) {
+                messages::format(lang, Key::ChannelNotBound, &[("channel", &channel_id)])
+            } else if guild_state.bound_text_channels.len() == 1 {
+                messages::text(lang, Key::CannotUnbindLastChannel)
+            } else {
+                guild_state
+                    .bound_text_channels
+                    .retain(|id| *id != channel_id);
+                messages::format(lang, Key::ChannelUnbound, &[("channel", &channel_id)])
+            }
+        }
+        None => messages::text(lang, Key::NotConnected),
+    };
+
+    r(ctx, cmd, msg).await?;
+    Ok(())
+}
+
+async fn handle_list(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r(ctx, cmd, guild_only(lang, "`/channels`")).await?;
+            return Ok(());
+        }
+    };
+
+    let state = app_state::get(ctx).await?;
+    let msg = match state.connected_guild_states.get(&guild_id) {
+        Some(guild_state) => {
+            let channel_list = guild_state
+                .bound_text_channels
+                .iter()
+                .map(|id| format!("<#{}>", id))
+                .collect::<Vec<_>>()
+                .join(", ");
+            messages::format(lang, Key::BoundChannelList, &[("channels", &channel_list)])
+        }
+        None => messages::text(lang, Key::NotConnected),
+    };
+
+    r(ctx, cmd, msg).await?;
+    Ok(())
+}
+
+fn parse_channels(option_channels: &CommandDataOption) -> Result<Command, CommandParseError> {
+    let parse_channel = || -> Result<ChannelsOption, CommandParseError> {
+        Ok(ChannelsOption {
+            channel: find_channel(&option_channels.options, "channel")?,
+        })
+    };
+
+    match option_channels.name.as_str() {
+        "add" => Ok(Command::ChannelsAdd(parse_channel()?)),
+        "remove" => Ok(Command::ChannelsRemove(parse_channel()?)),
+        "list" => Ok(Command::ChannelsList),
+        _ => Err(unknown_subcommand("channels", option_channels)),
+    }
+}
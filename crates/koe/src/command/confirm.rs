@@ -0,0 +1,103 @@
+use crate::{
+    app_state,
+    component_interaction::custom_id::{ConfirmAction, CustomId},
+    connection,
+    messages::{self, Key},
+};
+use anyhow::Result;
+use koe_db::{dict::ClearOption, language::Language};
+use serenity::{
+    builder::{CreateActionRow, CreateButton, CreateComponents},
+    client::Context,
+    model::{
+        application::component::ButtonStyle,
+        id::{GuildId, UserId},
+    },
+};
+use std::time::{Duration, Instant};
+
+/// 確認のボタンを操作できる期間
+/// これを過ぎるとボタンを取り除き、操作を取り消したものとみなす
+pub const CONFIRM_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 「はい」と「いいえ」のボタンを作る
+pub fn build_components(
+    lang: Language,
+    action: ConfirmAction,
+    guild_id: GuildId,
+    invoker: UserId,
+) -> CreateComponents {
+    let button = |accepted: bool| {
+        let mut button = CreateButton::default();
+        button
+            .style(if accepted {
+                ButtonStyle::Danger
+            } else {
+                ButtonStyle::Secondary
+            })
+            .label(messages::text(
+                lang,
+                if accepted {
+                    Key::ConfirmYes
+                } else {
+                    Key::ConfirmNo
+                },
+            ))
+            .custom_id(CustomId::Confirm {
+                action,
+                accepted,
+                guild_id,
+                invoker,
+            });
+        button
+    };
+
+    let mut row = CreateActionRow::default();
+    row.add_button(button(true)).add_button(button(false));
+
+    let mut components = CreateComponents::default();
+    components.add_action_row(row);
+    components
+}
+
+/// 確認された操作を実行し、結果を伝えるメッセージを返す
+/// 確認が不要な場合（`/leave`でキューが空の場合など）も、この関数で実行する
+pub async fn execute(
+    ctx: &Context,
+    lang: Language,
+    action: ConfirmAction,
+    guild_id: GuildId,
+) -> Result<String> {
+    let state = app_state::get(ctx).await?;
+
+    match action {
+        ConfirmAction::Leave => {
+            // 確認している間に切断された場合に備える
+            if !state.voice_backend.is_connected(guild_id.into()).await? {
+                return Ok(messages::text(lang, Key::NotConnected));
+            }
+
+            connection::leave(ctx, guild_id).await?;
+            state.manual_leave_times.insert(guild_id, Instant::now());
+
+            Ok(messages::text(lang, Key::Left))
+        }
+        ConfirmAction::DictClear => {
+            let mut conn = state.redis_client.get_async_connection().await?;
+            let count = koe_db::dict::clear(
+                &mut conn,
+                ClearOption {
+                    guild_id: guild_id.into(),
+                },
+            )
+            .await?;
+            state.dict_cache.invalidate(guild_id);
+
+            Ok(messages::format(
+                lang,
+                Key::DictCleared,
+                &[("count", &count)],
+            ))
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use super::{
+    handler::{r_ephemeral, respond, CommandResponse},
+    model::{Command, ReadMessageOption, ShowVoiceOption},
+    parser::{missing, CommandParseError},
+    registry::{ContextMenuCommand, ContextMenuSpec},
+    setup::{READ_MESSAGE_COMMAND_NAME, SHOW_VOICE_COMMAND_NAME},
+    voice,
+};
+use crate::{
+    app_state, message,
+    messages::{self, Key},
+};
+use anyhow::{bail, Context as _, Result};
+use async_trait::async_trait;
+use koe_db::language::Language;
+use serenity::{
+    client::Context,
+    model::application::{
+        command::CommandType,
+        interaction::application_command::{
+            ApplicationCommandInteraction, CommandData, ResolvedTarget,
+        },
+    },
+};
+
+/// メッセージのコンテキストメニューの「このメッセージを読み上げ」の定義
+const READ_MESSAGE: ContextMenuSpec = ContextMenuSpec {
+    name: READ_MESSAGE_COMMAND_NAME,
+    name_en: "Read this message",
+    kind: CommandType::Message,
+    description: "メッセージを1回だけ読み上げる",
+    description_en: "Read a message once",
+};
+
+/// ユーザーのコンテキストメニューの「読み上げの設定を表示」の定義
+const SHOW_VOICE: ContextMenuSpec = ContextMenuSpec {
+    name: SHOW_VOICE_COMMAND_NAME,
+    name_en: "Show reading settings",
+    kind: CommandType::User,
+    description: "メンバーの声の設定を表示する",
+    description_en: "Show a member's voice settings",
+};
+
+pub(super) struct ReadMessageCommand;
+
+#[async_trait]
+impl ContextMenuCommand for ReadMessageCommand {
+    fn spec(&self) -> &'static ContextMenuSpec {
+        &READ_MESSAGE
+    }
+
+    fn parse(&self, data: &CommandData) -> Result<Command, CommandParseError> {
+        match data.target() {
+            Some(ResolvedTarget::Message(message)) => {
+                Ok(Command::ReadMessage(ReadMessageOption { message }))
+            }
+            _ => Err(missing("message")),
+        }
+    }
+
+    async fn run(
+        &self,
+        ctx: &Context,
+        cmd: &ApplicationCommandInteraction,
+        lang: Language,
+        command: Command,
+    ) -> Result<()> {
+        match command {
+            Command::ReadMessage(option) => handle_read_message(ctx, cmd, lang, option)
+                .await
+                .context("Failed to execute read message command"),
+            command => bail!("Unexpected command for read message: {:?}", command),
+        }
+    }
+}
+
+pub(super) struct ShowVoiceCommand;
+
+#[async_trait]
+impl ContextMenuCommand for ShowVoiceCommand {
+    fn spec(&self) -> &'static ContextMenuSpec {
+        &SHOW_VOICE
+    }
+
+    fn parse(&self, data: &CommandData) -> Result<Command, CommandParseError> {
+        match data.target() {
+            Some(ResolvedTarget::User(user, member)) => Ok(Command::ShowVoice(ShowVoiceOption {
+                user,
+                nick: member.and_then(|member| member.nick),
+            })),
+            _ => Err(missing("user")),
+        }
+    }
+
+    async fn run(
+        &self,
+        ctx: &Context,
+        cmd: &ApplicationCommandInteraction,
+        lang: Language,
+        command: Command,
+    ) -> Result<()> {
+        match command {
+            Command::ShowVoice(option) => handle_show_voice(ctx, cmd, lang, option)
+                .await
+                .context("Failed to execute show voice command"),
+            command => bail!("Unexpected command for show voice: {:?}", command),
+        }
+    }
+}
+
+async fn handle_read_message(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+    option: ReadMessageOption,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r_ephemeral(ctx, cmd, messages::text(lang, Key::GuildOnlyThisCommand)).await?;
+            return Ok(());
+        }
+    };
+
+    let state = app_state::get(ctx).await?;
+
+    if !state.voice_backend.is_connected(guild_id.into()).await? {
+        r_ephemeral(ctx, cmd, messages::text(lang, Key::NotConnected)).await?;
+        return Ok(());
+    }
+
+    let ticket = match state.connected_guild_states.get(&guild_id) {
+        Some(guild_state) => guild_state.speech_sequencer.issue(),
+        None => {
+            r_ephemeral(ctx, cmd, messages::text(lang, Key::NotConnected)).await?;
+            return Ok(());
+        }
+    };
+    let mut message = *option.message;
+    // 解決済みのメッセージにはサーバーのIDが含まれないため補う
+    message.guild_id = Some(guild_id);
+    let skipped = message::handler::speak(ctx, message, ticket).await?;
+
+    if skipped.is_none() {
+        r_ephemeral(ctx, cmd, messages::text(lang, Key::ReadingMessage)).await?;
+    } else {
+        r_ephemeral(ctx, cmd, messages::text(lang, Key::NothingToRead)).await?;
+    }
+    Ok(())
+}
+
+async fn handle_show_voice(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+    option: ShowVoiceOption,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r_ephemeral(ctx, cmd, messages::text(lang, Key::GuildOnlyThisCommand)).await?;
+            return Ok(());
+        }
+    };
+
+    let name = option.nick.unwrap_or(option.user.name);
+    let embed = voice::build_voice_info_embed(ctx, lang, guild_id, option.user.id, &name).await?;
+    respond(
+        ctx,
+        cmd,
+        CommandResponse {
+            embeds: vec![embed],
+            ephemeral: true,
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    Ok(())
+}
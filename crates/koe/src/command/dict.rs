@@ -0,0 +1,681 @@
+use super::{
+    dict_entry,
+    dict_file::{self, ConflictPolicy, DictFileFormat},
+    dict_view,
+    handler::{confirm, guild_only, r, r_ephemeral, respond, sanitize_response, CommandResponse},
+    model::{
+        Command, DictAddFormOption, DictAddOption, DictExportOption, DictImportOption,
+        DictRemoveOption,
+    },
+    parser::{
+        find_attachment, find_boolean, find_string, find_subcommand, invalid, required,
+        unknown_subcommand, CommandParseError,
+    },
+    registry::{
+        subcommand, Category, Choice, CommandSpec, OptionKind, OptionSpec, SlashCommand, STRING,
+    },
+};
+use crate::{
+    app_state,
+    component_interaction::custom_id::{
+        ConfirmAction, CustomId, DICT_ADD_READ_AS_INPUT, DICT_ADD_WORD_INPUT,
+    },
+    messages::{self, Key},
+};
+use anyhow::{bail, Context as _, Result};
+use async_trait::async_trait;
+use koe_db::{
+    dict::{
+        GetAllOption, InsertOption, InsertResponse, RemoveOption, RemoveResponse, UpsertOption,
+        UpsertResponse,
+    },
+    language::Language,
+};
+use serenity::{
+    builder::{CreateActionRow, CreateComponents},
+    client::Context,
+    model::{
+        application::{
+            component::InputTextStyle,
+            interaction::{
+                application_command::{ApplicationCommandInteraction, CommandDataOption},
+                InteractionResponseType,
+            },
+        },
+        channel::AttachmentType,
+    },
+};
+
+/// `/dict`の定義
+const SPEC: CommandSpec = CommandSpec {
+    name: "dict",
+    description: "読み上げ辞書の閲覧と編集",
+    description_en: "View and edit the reading dictionary",
+    category: Category::Dictionary,
+    aliases: &[],
+    manage_guild_only: false,
+    dm_allowed: false,
+    options: &[
+        subcommand(
+            "add",
+            "辞書に項目を追加（省略するとフォームで入力）",
+            "Add a word to the dictionary (omit options to use a form)",
+            &[
+                OptionSpec::new(
+                    "word",
+                    "読み方を指定したい語句",
+                    "Word to set the reading of",
+                    STRING,
+                ),
+                OptionSpec::new("read-as", "語句の読み方", "Reading of the word", STRING),
+            ],
+        ),
+        subcommand(
+            "remove",
+            "辞書から項目を削除",
+            "Remove a word from the dictionary",
+            &[OptionSpec::new(
+                "word",
+                "削除したい語句",
+                "Word to remove",
+                OptionKind::String {
+                    choices: &[],
+                    autocomplete: true,
+                },
+            )
+            .required()],
+        ),
+        subcommand("view", "辞書を表示", "Show the dictionary", &[]),
+        subcommand(
+            "count",
+            "辞書に登録された語句の数を表示",
+            "Show the number of words in the dictionary",
+            &[],
+        ),
+        subcommand(
+            "clear",
+            "辞書に登録された語句をすべて削除",
+            "Remove all words from the dictionary",
+            &[],
+        ),
+        subcommand(
+            "export",
+            "辞書をファイルに書き出す",
+            "Export the dictionary to a file",
+            &[
+                OptionSpec::new(
+                    "format",
+                    "ファイルの形式（既定値: JSON）",
+                    "File format (default: JSON)",
+                    OptionKind::String {
+                        choices: &[Choice::new("JSON", "json"), Choice::new("CSV", "csv")],
+                        autocomplete: false,
+                    },
+                ),
+                OptionSpec::new(
+                    "bom",
+                    "CSVの先頭にBOMを付ける（Excelで開く場合に指定）",
+                    "Add a BOM to the CSV (for opening in Excel)",
+                    OptionKind::Boolean,
+                ),
+            ],
+        ),
+        subcommand(
+            "import",
+            "ファイルから辞書に語句を追加",
+            "Import words into the dictionary from a file",
+            &[
+                OptionSpec::new(
+                    "file",
+                    "`/dict export`で書き出したJSONまたはCSVのファイル",
+                    "JSON or CSV file exported with `/dict export`",
+                    OptionKind::Attachment,
+                )
+                .required(),
+                OptionSpec::new(
+                    "conflict",
+                    "すでに登録されている語句の扱い（既定値: スキップ）",
+                    "What to do with words already registered (default: skip)",
+                    OptionKind::String {
+                        choices: &[
+                            Choice::localized(
+                                "登録済みの読み方を残す",
+                                "Keep the existing reading",
+                                "skip",
+                            ),
+                            Choice::localized(
+                                "ファイルの読み方で上書きする",
+                                "Overwrite with the reading in the file",
+                                "overwrite",
+                            ),
+                            Choice::localized(
+                                "何も登録せずに中止する",
+                                "Cancel without importing anything",
+                                "error",
+                            ),
+                        ],
+                        autocomplete: false,
+                    },
+                ),
+            ],
+        ),
+    ],
+};
+
+pub(super) struct DictCommand;
+
+#[async_trait]
+impl SlashCommand for DictCommand {
+    fn spec(&self) -> &'static CommandSpec {
+        &SPEC
+    }
+
+    fn parse(&self, options: &[CommandDataOption]) -> Result<Command, CommandParseError> {
+        parse_dict(find_subcommand("dict", options)?)
+    }
+
+    async fn run(
+        &self,
+        ctx: &Context,
+        cmd: &ApplicationCommandInteraction,
+        lang: Language,
+        command: Command,
+    ) -> Result<()> {
+        match command {
+            Command::DictAdd(option) => handle_add(ctx, cmd, lang, option)
+                .await
+                .context("Failed to execute /dict add"),
+            Command::DictAddForm(option) => handle_add_form(ctx, cmd, lang, option)
+                .await
+                .context("Failed to execute /dict add"),
+            Command::DictRemove(option) => handle_remove(ctx, cmd, lang, option)
+                .await
+                .context("Failed to execute /dict remove"),
+            Command::DictView => handle_view(ctx, cmd, lang)
+                .await
+                .context("Failed to execute /dict view"),
+            Command::DictCount => handle_count(ctx, cmd, lang)
+                .await
+                .context("Failed to execute /dict count"),
+            Command::DictClear => handle_clear(ctx, cmd, lang)
+                .await
+                .context("Failed to execute /dict clear"),
+            Command::DictExport(option) => handle_export(ctx, cmd, lang, option)
+                .await
+                .context("Failed to execute /dict export"),
+            Command::DictImport(option) => handle_import(ctx, cmd, lang, option)
+                .await
+                .context("Failed to execute /dict import"),
+            command => bail!("Unexpected command for /dict: {:?}", command),
+        }
+    }
+}
+
+async fn handle_add(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+    option: DictAddOption,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r(ctx, cmd, guild_only(lang, "`/dict add`")).await?;
+            return Ok(());
+        }
+    };
+
+    let msg = dict_entry::add(ctx, lang, guild_id, &option.word, &option.read_as).await?;
+    r(ctx, cmd, msg).await?;
+    Ok(())
+}
+
+async fn handle_add_form(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+    option: DictAddFormOption,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r(ctx, cmd, guild_only(lang, "`/dict add`")).await?;
+            return Ok(());
+        }
+    };
+
+    let mut word_row = CreateActionRow::default();
+    word_row.create_input_text(|input| {
+        input
+            .custom_id(DICT_ADD_WORD_INPUT)
+            .label(messages::text(lang, Key::DictFormWord))
+            .style(InputTextStyle::Short)
+            .required(true);
+        if let Some(word) = &option.word {
+            input.value(word);
+        }
+        input
+    });
+    let mut read_as_row = CreateActionRow::default();
+    read_as_row.create_input_text(|input| {
+        input
+            .custom_id(DICT_ADD_READ_AS_INPUT)
+            .label(messages::text(lang, Key::DictFormReadAs))
+            .style(InputTextStyle::Paragraph)
+            .required(true);
+        if let Some(read_as) = &option.read_as {
+            input.value(read_as);
+        }
+        input
+    });
+
+    let mut components = CreateComponents::default();
+    components.add_action_row(word_row);
+    components.add_action_row(read_as_row);
+
+    cmd.create_interaction_response(&ctx.http, |create_response| {
+        create_response
+            .kind(InteractionResponseType::Modal)
+            .interaction_response_data(|create_modal| {
+                create_modal
+                    .custom_id(CustomId::DictAdd { guild_id })
+                    .title(messages::text(lang, Key::DictFormTitle))
+                    .set_components(components)
+            })
+    })
+    .await
+    .context("Failed to create modal")?;
+
+    Ok(())
+}
+
+async fn handle_remove(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+    option: DictRemoveOption,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r(ctx, cmd, guild_only(lang, "`/dict remove`")).await?;
+            return Ok(());
+        }
+    };
+
+    let state = app_state::get(ctx).await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+
+    let resp = koe_db::dict::remove(
+        &mut conn,
+        RemoveOption {
+            guild_id: guild_id.into(),
+            word: option.word.clone(),
+        },
+    )
+    .await?;
+    state.dict_cache.invalidate(guild_id);
+
+    let msg = match resp {
+        RemoveResponse::Success => messages::format(
+            lang,
+            Key::DictRemoved,
+            &[("word", &sanitize_response(&option.word))],
+        ),
+        RemoveResponse::WordDoesNotExist => messages::format(
+            lang,
+            Key::DictWordNotFound,
+            &[("word", &sanitize_response(&option.word))],
+        ),
+    };
+    r(ctx, cmd, msg).await?;
+    Ok(())
+}
+
+async fn handle_view(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r(ctx, cmd, guild_only(lang, "`/dict view`")).await?;
+            return Ok(());
+        }
+    };
+
+    let (embed, components) = dict_view::build_page(ctx, lang, guild_id, 0, cmd.user.id).await?;
+    respond(
+        ctx,
+        cmd,
+        CommandResponse {
+            embeds: vec![embed],
+            components: Some(components),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    Ok(())
+}
+
+async fn handle_count(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r(ctx, cmd, guild_only(lang, "`/dict count`")).await?;
+            return Ok(());
+        }
+    };
+
+    let state = app_state::get(ctx).await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+
+    let count = koe_db::dict::count(
+        &mut conn,
+        koe_db::dict::CountOption {
+            guild_id: guild_id.into(),
+        },
+    )
+    .await?;
+
+    r_ephemeral(
+        ctx,
+        cmd,
+        messages::format(lang, Key::DictCount, &[("count", &count)]),
+    )
+    .await?;
+    Ok(())
+}
+
+async fn handle_clear(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r(ctx, cmd, guild_only(lang, "`/dict clear`")).await?;
+            return Ok(());
+        }
+    };
+
+    let state = app_state::get(ctx).await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+
+    let count = koe_db::dict::count(
+        &mut conn,
+        koe_db::dict::CountOption {
+            guild_id: guild_id.into(),
+        },
+    )
+    .await?;
+    if count == 0 {
+        r(ctx, cmd, messages::text(lang, Key::DictAlreadyEmpty)).await?;
+        return Ok(());
+    }
+
+    confirm(
+        ctx,
+        cmd,
+        lang,
+        messages::format(lang, Key::ConfirmDictClear, &[("count", &count)]),
+        ConfirmAction::DictClear,
+    )
+    .await?;
+    Ok(())
+}
+
+async fn handle_export(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+    option: DictExportOption,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r(ctx, cmd, guild_only(lang, "`/dict export`")).await?;
+            return Ok(());
+        }
+    };
+
+    let state = app_state::get(ctx).await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+
+    let mut dict = koe_db::dict::get_all(
+        &mut conn,
+        GetAllOption {
+            guild_id: guild_id.into(),
+        },
+    )
+    .await?;
+    dict.sort();
+
+    let data = dict_file::export(&dict, option.format, option.bom)?;
+    let filename = format!("dict-{}.{}", guild_id.as_u64(), option.format.extension());
+
+    respond(
+        ctx,
+        cmd,
+        CommandResponse {
+            content: Some(messages::format(
+                lang,
+                Key::DictExported,
+                &[("count", &dict.len())],
+            )),
+            file: Some(AttachmentType::Bytes {
+                data: data.into(),
+                filename,
+            }),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    Ok(())
+}
+
+async fn handle_import(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+    option: DictImportOption,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r(ctx, cmd, guild_only(lang, "`/dict import`")).await?;
+            return Ok(());
+        }
+    };
+
+    let format = match DictFileFormat::from_filename(&option.file.filename) {
+        Some(format) => format,
+        None => {
+            r(ctx, cmd, messages::text(lang, Key::DictImportBadExtension)).await?;
+            return Ok(());
+        }
+    };
+    if option.file.size > dict_file::MAX_IMPORT_FILE_SIZE {
+        r(ctx, cmd, messages::text(lang, Key::DictImportTooLarge)).await?;
+        return Ok(());
+    }
+
+    let data = option
+        .file
+        .download()
+        .await
+        .context("Failed to download attachment")?;
+    let imported = match dict_file::import(&data, format, lang) {
+        Ok(imported) => imported,
+        Err(msg) => {
+            r(
+                ctx,
+                cmd,
+                messages::format(lang, Key::DictImportUnreadable, &[("error", &msg)]),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let state = app_state::get(ctx).await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+
+    if option.conflict == ConflictPolicy::Error {
+        let dict = koe_db::dict::get_all(
+            &mut conn,
+            GetAllOption {
+                guild_id: guild_id.into(),
+            },
+        )
+        .await?;
+        let conflicts = imported
+            .entries
+            .iter()
+            .filter(|(word, _)| dict.iter().any(|(registered, _)| registered == word))
+            .map(|(word, _)| sanitize_response(word))
+            .collect::<Vec<_>>();
+
+        if !conflicts.is_empty() {
+            r(
+                ctx,
+                cmd,
+                messages::format(
+                    lang,
+                    Key::DictImportConflicts,
+                    &[("list", &summarize_list(lang, &conflicts))],
+                ),
+            )
+            .await?;
+            return Ok(());
+        }
+    }
+
+    let (mut inserted, mut updated, mut skipped) = (0, 0, 0);
+    for (word, read_as) in imported.entries {
+        match option.conflict {
+            ConflictPolicy::Skip | ConflictPolicy::Error => {
+                let resp = koe_db::dict::insert(
+                    &mut conn,
+                    InsertOption {
+                        guild_id: guild_id.into(),
+                        word,
+                        read_as,
+                    },
+                )
+                .await?;
+                match resp {
+                    InsertResponse::Success => inserted += 1,
+                    InsertResponse::WordAlreadyExists => skipped += 1,
+                }
+            }
+            ConflictPolicy::Overwrite => {
+                let resp = koe_db::dict::upsert(
+                    &mut conn,
+                    UpsertOption {
+                        guild_id: guild_id.into(),
+                        word,
+                        read_as,
+                    },
+                )
+                .await?;
+                match resp {
+                    UpsertResponse::Inserted => inserted += 1,
+                    UpsertResponse::Updated => updated += 1,
+                }
+            }
+        }
+    }
+    state.dict_cache.invalidate(guild_id);
+
+    let mut msg = messages::format(
+        lang,
+        Key::DictImported,
+        &[
+            ("inserted", &inserted),
+            ("updated", &updated),
+            ("skipped", &skipped),
+        ],
+    );
+    if !imported.errors.is_empty() {
+        msg += "\n\n";
+        msg += &messages::format(
+            lang,
+            Key::DictImportErrors,
+            &[("list", &summarize_list(lang, &imported.errors))],
+        );
+    }
+    r(ctx, cmd, msg).await?;
+
+    Ok(())
+}
+
+/// 箇条書きにして返す
+/// 応答が長くなりすぎないよう、先頭の10件のみを含める
+fn summarize_list(lang: Language, items: &[String]) -> String {
+    const MAX_ITEMS: usize = 10;
+
+    let mut list = items
+        .iter()
+        .take(MAX_ITEMS)
+        .map(|item| format!("- {}", item))
+        .collect::<Vec<_>>()
+        .join("\n");
+    if items.len() > MAX_ITEMS {
+        list += "\n- ";
+        list += &messages::format(
+            lang,
+            Key::ListMore,
+            &[("count", &(items.len() - MAX_ITEMS))],
+        );
+    }
+
+    list
+}
+
+fn parse_dict(option_dict: &CommandDataOption) -> Result<Command, CommandParseError> {
+    let options = &option_dict.options;
+
+    match option_dict.name.as_str() {
+        "add" => match (
+            find_string(options, "word")?,
+            find_string(options, "read-as")?,
+        ) {
+            (Some(word), Some(read_as)) => Ok(Command::DictAdd(DictAddOption { word, read_as })),
+            (word, read_as) => Ok(Command::DictAddForm(DictAddFormOption { word, read_as })),
+        },
+        "remove" => Ok(Command::DictRemove(DictRemoveOption {
+            word: required(find_string(options, "word")?, "word")?,
+        })),
+        "view" => Ok(Command::DictView),
+        "count" => Ok(Command::DictCount),
+        "clear" => Ok(Command::DictClear),
+        "export" => {
+            let format = match find_string(options, "format")? {
+                Some(x) => DictFileFormat::parse(&x).ok_or_else(|| invalid("format", x))?,
+                None => DictFileFormat::Json,
+            };
+            let bom = find_boolean(options, "bom")?.unwrap_or(false);
+
+            Ok(Command::DictExport(DictExportOption { format, bom }))
+        }
+        "import" => {
+            let file = required(find_attachment(options, "file")?, "file")?;
+            let conflict = match find_string(options, "conflict")? {
+                Some(x) => ConflictPolicy::parse(&x).ok_or_else(|| invalid("conflict", x))?,
+                None => ConflictPolicy::Skip,
+            };
+
+            Ok(Command::DictImport(DictImportOption { file, conflict }))
+        }
+        _ => Err(unknown_subcommand("dict", option_dict)),
+    }
+}
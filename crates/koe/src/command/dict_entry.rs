@@ -0,0 +1,55 @@
+use super::handler::sanitize_response;
+use crate::{
+    app_state,
+    messages::{self, Key},
+};
+use anyhow::Result;
+use koe_db::{
+    dict::{InsertOption, InsertResponse},
+    language::Language,
+};
+use serenity::{client::Context, model::id::GuildId};
+
+/// 辞書に語句を登録し、結果を伝えるメッセージを返す
+/// `/dict add`のオプションとフォームのどちらから登録する場合も、この関数を使う
+pub async fn add(
+    ctx: &Context,
+    lang: Language,
+    guild_id: GuildId,
+    word: &str,
+    read_as: &str,
+) -> Result<String> {
+    if word.is_empty() || read_as.is_empty() {
+        return Ok(messages::text(lang, Key::DictAddNeedsBoth));
+    }
+
+    let state = app_state::get(ctx).await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+
+    let resp = koe_db::dict::insert(
+        &mut conn,
+        InsertOption {
+            guild_id: guild_id.into(),
+            word: word.to_string(),
+            read_as: read_as.to_string(),
+        },
+    )
+    .await?;
+    state.dict_cache.invalidate(guild_id);
+
+    Ok(match resp {
+        InsertResponse::Success => messages::format(
+            lang,
+            Key::DictAdded,
+            &[
+                ("word", &sanitize_response(word)),
+                ("read_as", &sanitize_response(read_as)),
+            ],
+        ),
+        InsertResponse::WordAlreadyExists => messages::format(
+            lang,
+            Key::DictWordExists,
+            &[("word", &sanitize_response(word))],
+        ),
+    })
+}
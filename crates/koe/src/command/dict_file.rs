@@ -0,0 +1,313 @@
+use crate::messages::{self, Key};
+use anyhow::{Context as _, Result};
+use koe_db::language::Language;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+
+/// Excelが文字コードをUTF-8と判別できるよう、CSVの先頭に付けるBOM
+const UTF8_BOM: &str = "\u{FEFF}";
+
+/// CSVの見出し行
+const CSV_HEADER: [&str; 2] = ["word", "read_as"];
+
+/// `/dict import`で読み込めるファイルの最大サイズ（バイト）
+pub const MAX_IMPORT_FILE_SIZE: u64 = 1024 * 1024;
+
+/// `/dict export`と`/dict import`で扱うファイルの形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DictFileFormat {
+    Json,
+    Csv,
+}
+
+impl DictFileFormat {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "json" => Some(DictFileFormat::Json),
+            "csv" => Some(DictFileFormat::Csv),
+            _ => None,
+        }
+    }
+
+    /// ファイル名の拡張子から形式を判別する
+    pub fn from_filename(filename: &str) -> Option<Self> {
+        let (_, extension) = filename.rsplit_once('.')?;
+        Self::parse(&extension.to_ascii_lowercase())
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            DictFileFormat::Json => "json",
+            DictFileFormat::Csv => "csv",
+        }
+    }
+}
+
+/// `/dict import`で、すでに辞書に登録されている語句を読み込んだときの扱い
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// 登録済みの読み方を残す
+    Skip,
+    /// ファイルの読み方で上書きする
+    Overwrite,
+    /// 何も登録せずにインポートを中止する
+    Error,
+}
+
+impl ConflictPolicy {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "skip" => Some(ConflictPolicy::Skip),
+            "overwrite" => Some(ConflictPolicy::Overwrite),
+            "error" => Some(ConflictPolicy::Error),
+            _ => None,
+        }
+    }
+}
+
+/// 読み込んだ辞書
+#[derive(Debug, Clone, Default)]
+pub struct ImportedDict {
+    /// 読み込めた語句と読み方
+    pub entries: Vec<(String, String)>,
+    /// 読み込めなかった行の説明（行番号を含む）
+    pub errors: Vec<String>,
+}
+
+/// 辞書を`format`の形式で書き出す
+/// `bom`が`true`の場合、CSVの先頭にBOMを付ける（JSONでは無視する）
+pub fn export(dict: &[(String, String)], format: DictFileFormat, bom: bool) -> Result<Vec<u8>> {
+    match format {
+        DictFileFormat::Json => export_json(dict),
+        DictFileFormat::Csv => Ok(export_csv(dict, bom).into_bytes()),
+    }
+}
+
+fn export_json(dict: &[(String, String)]) -> Result<Vec<u8>> {
+    let entries = dict
+        .iter()
+        .map(|(word, read_as)| json!({ "word": word, "read_as": read_as }))
+        .collect::<Vec<_>>();
+
+    serde_json::to_vec_pretty(&entries).context("Failed to serialize dictionary to JSON")
+}
+
+/// RFC 4180に従ってCSVを書き出す
+fn export_csv(dict: &[(String, String)], bom: bool) -> String {
+    let mut csv = String::new();
+    if bom {
+        csv.push_str(UTF8_BOM);
+    }
+
+    write_csv_record(&mut csv, &CSV_HEADER);
+    for (word, read_as) in dict {
+        write_csv_record(&mut csv, &[word, read_as]);
+    }
+
+    csv
+}
+
+fn write_csv_record(csv: &mut String, fields: &[&str]) {
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            csv.push(',');
+        }
+        csv.push_str(&escape_csv_field(field));
+    }
+    csv.push_str("\r\n");
+}
+
+/// カンマ・ダブルクォート・改行を含むフィールドはダブルクォートで囲み、ダブルクォートを重ねてエスケープする
+fn escape_csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// `format`の形式のファイルから辞書を読み込む
+/// ファイル全体を読み込めない場合は、利用者向けの説明を`lang`の言語で`Err`で返す
+pub fn import(data: &[u8], format: DictFileFormat, lang: Language) -> Result<ImportedDict, String> {
+    let text = std::str::from_utf8(data).map_err(|_| messages::text(lang, Key::ImportNotUtf8))?;
+    let text = text.strip_prefix(UTF8_BOM).unwrap_or(text);
+
+    match format {
+        DictFileFormat::Json => import_json(text, lang),
+        DictFileFormat::Csv => import_csv(text, lang),
+    }
+}
+
+fn import_json(text: &str, lang: Language) -> Result<ImportedDict, String> {
+    let items = match serde_json::from_str::<Value>(text) {
+        Ok(Value::Array(items)) => items,
+        Ok(_) => return Err(messages::text(lang, Key::ImportJsonNotArray)),
+        Err(err) => {
+            return Err(messages::format(
+                lang,
+                Key::ImportJsonInvalid,
+                &[("line", &err.line())],
+            ))
+        }
+    };
+
+    let mut builder = ImportedDictBuilder::new(lang);
+    for (i, item) in items.iter().enumerate() {
+        let location = messages::format(lang, Key::ImportJsonItem, &[("index", &(i + 1))]);
+        let word = item.get("word").and_then(Value::as_str);
+        let read_as = item.get("read_as").and_then(Value::as_str);
+        match (word, read_as) {
+            (Some(word), Some(read_as)) => builder.push(&location, word, read_as),
+            _ => builder.error(&location, Key::ImportJsonItemInvalid),
+        }
+    }
+
+    Ok(builder.finish())
+}
+
+fn import_csv(text: &str, lang: Language) -> Result<ImportedDict, String> {
+    let mut records = parse_csv(text, lang)?.into_iter();
+
+    match records.next() {
+        Some((_, header)) if header.iter().map(|x| x.trim()).eq(CSV_HEADER) => {}
+        _ => {
+            return Err(messages::format(
+                lang,
+                Key::ImportCsvBadHeader,
+                &[("header", &CSV_HEADER.join(","))],
+            ))
+        }
+    }
+
+    let mut builder = ImportedDictBuilder::new(lang);
+    for (line, fields) in records {
+        let location = messages::format(lang, Key::ImportCsvLine, &[("line", &line)]);
+        match fields.as_slice() {
+            [word, read_as] => builder.push(&location, word, read_as),
+            _ => builder.error(&location, Key::ImportCsvWrongColumns),
+        }
+    }
+
+    Ok(builder.finish())
+}
+
+struct ImportedDictBuilder {
+    lang: Language,
+    dict: ImportedDict,
+    words: HashSet<String>,
+}
+
+impl ImportedDictBuilder {
+    fn new(lang: Language) -> Self {
+        Self {
+            lang,
+            dict: ImportedDict::default(),
+            words: HashSet::new(),
+        }
+    }
+
+    fn push(&mut self, location: &str, word: &str, read_as: &str) {
+        if word.is_empty() || read_as.is_empty() {
+            self.error(location, Key::ImportEntryEmpty);
+            return;
+        }
+        if !self.words.insert(word.to_string()) {
+            self.error(location, Key::ImportEntryDuplicate);
+            return;
+        }
+
+        self.dict
+            .entries
+            .push((word.to_string(), read_as.to_string()));
+    }
+
+    fn error(&mut self, location: &str, key: Key) {
+        self.dict
+            .errors
+            .push(format!("{}: {}", location, messages::text(self.lang, key)));
+    }
+
+    fn finish(self) -> ImportedDict {
+        self.dict
+    }
+}
+
+/// RFC 4180に従ってCSVを読み込み、各レコードとその開始行の番号を返す
+/// 空行は読み飛ばす
+fn parse_csv(text: &str, lang: Language) -> Result<Vec<(usize, Vec<String>)>, String> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut field_quoted = false;
+    let mut line = 1;
+    let mut record_line = 1;
+
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                _ => {
+                    if c == '\n' {
+                        line += 1;
+                    }
+                    field.push(c);
+                }
+            }
+            continue;
+        }
+
+        match c {
+            '"' if field.is_empty() && !field_quoted => {
+                in_quotes = true;
+                field_quoted = true;
+            }
+            '"' => {
+                return Err(messages::format(
+                    lang,
+                    Key::ImportCsvUnquoted,
+                    &[("line", &line)],
+                ))
+            }
+            ',' => {
+                record.push(std::mem::take(&mut field));
+                field_quoted = false;
+            }
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\r' | '\n' => {
+                record.push(std::mem::take(&mut field));
+                field_quoted = false;
+                push_record(&mut records, record_line, std::mem::take(&mut record));
+                line += 1;
+                record_line = line;
+            }
+            _ => field.push(c),
+        }
+    }
+
+    if in_quotes {
+        return Err(messages::format(
+            lang,
+            Key::ImportCsvUnclosedQuote,
+            &[("line", &record_line)],
+        ));
+    }
+    if !field.is_empty() || field_quoted || !record.is_empty() {
+        record.push(field);
+        push_record(&mut records, record_line, record);
+    }
+
+    Ok(records)
+}
+
+fn push_record(records: &mut Vec<(usize, Vec<String>)>, line: usize, record: Vec<String>) {
+    let is_blank = matches!(record.as_slice(), [field] if field.is_empty());
+    if !is_blank {
+        records.push((line, record));
+    }
+}
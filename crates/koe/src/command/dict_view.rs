@@ -0,0 +1,120 @@
+use super::handler::sanitize_response;
+use crate::{
+    app_state,
+    component_interaction::custom_id::CustomId,
+    messages::{self, Key},
+};
+use anyhow::Result;
+use koe_db::{
+    dict::GetAllOption,
+    guild_settings::{self, BoolKey},
+    language::Language,
+};
+use serenity::{
+    builder::{CreateActionRow, CreateButton, CreateComponents, CreateEmbed},
+    client::Context,
+    model::{
+        application::component::ButtonStyle,
+        id::{GuildId, UserId},
+    },
+};
+
+/// 1ページに表示する語句の数（埋め込みのフィールドは25個まで）
+const PAGE_SIZE: usize = 20;
+
+/// 辞書の`page`ページ目（0始まり）を表示する埋め込みと、ページを切り替えるボタンを作る
+/// `page`が範囲外の場合は最後のページを表示する
+pub async fn build_page(
+    ctx: &Context,
+    lang: Language,
+    guild_id: GuildId,
+    page: usize,
+    invoker: UserId,
+) -> Result<(CreateEmbed, CreateComponents)> {
+    let state = app_state::get(ctx).await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+
+    let mut dict = koe_db::dict::get_all(
+        &mut conn,
+        GetAllOption {
+            guild_id: guild_id.into(),
+        },
+    )
+    .await?;
+    dict.sort();
+    let show_readings = guild_settings::get_bool(
+        &mut conn,
+        guild_settings::GetBoolOption {
+            guild_id: guild_id.into(),
+            key: BoolKey::ShowDictReadings,
+        },
+    )
+    .await?;
+
+    let page_count = dict.len().div_ceil(PAGE_SIZE).max(1);
+    let page = page.min(page_count - 1);
+
+    let mut embed = CreateEmbed::default();
+
+    let guild_name = guild_id
+        .name(&ctx.cache)
+        .unwrap_or_else(|| messages::text(lang, Key::ServerFallbackName));
+    embed.title(messages::format(
+        lang,
+        Key::DictTitle,
+        &[("guild", &guild_name)],
+    ));
+
+    let entries = dict.into_iter().skip(page * PAGE_SIZE).take(PAGE_SIZE);
+    if show_readings {
+        embed.fields(entries.map(|(word, read_as)| (word, sanitize_response(&read_as), false)));
+    } else {
+        // 埋め込みのフィールドは値を空にできないため、語句だけを一覧にする
+        embed.description(
+            entries
+                .map(|(word, _)| sanitize_response(&word))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        );
+    }
+
+    let mut components = CreateComponents::default();
+    if page_count > 1 {
+        embed.footer(|footer| {
+            footer.text(messages::format(
+                lang,
+                Key::PageFooter,
+                &[("page", &(page + 1)), ("total", &page_count)],
+            ))
+        });
+
+        let page_button = |label: String, target: usize, disabled: bool| {
+            let mut button = CreateButton::default();
+            button
+                .style(ButtonStyle::Secondary)
+                .label(label)
+                .custom_id(CustomId::DictPage {
+                    page: target,
+                    guild_id,
+                    invoker,
+                })
+                .disabled(disabled);
+            button
+        };
+
+        let mut action_row = CreateActionRow::default();
+        action_row.add_button(page_button(
+            messages::text(lang, Key::PreviousPage),
+            page.saturating_sub(1),
+            page == 0,
+        ));
+        action_row.add_button(page_button(
+            messages::text(lang, Key::NextPage),
+            (page + 1).min(page_count - 1),
+            page + 1 == page_count,
+        ));
+        components.add_action_row(action_row);
+    }
+
+    Ok((embed, components))
+}
@@ -0,0 +1,343 @@
+use super::{
+    handler::{guild_only, r, sanitize_response},
+    model::{Command, FilterAddOption, FilterModeOption, FilterRemoveOption},
+    parser::{
+        find_string, find_subcommand, invalid, required, unknown_subcommand, CommandParseError,
+    },
+    registry::{
+        subcommand, Category, Choice, CommandSpec, OptionKind, OptionSpec, SlashCommand, STRING,
+    },
+};
+use crate::{
+    app_state,
+    messages::{self, Key},
+};
+use anyhow::{bail, Context as _, Result};
+use async_trait::async_trait;
+use koe_db::{
+    filter::{self, FilterMode},
+    language::Language,
+};
+use serenity::{
+    builder::CreateEmbed,
+    client::Context,
+    model::application::interaction::{
+        application_command::{ApplicationCommandInteraction, CommandDataOption},
+        InteractionResponseType,
+    },
+};
+
+/// `/filter`の定義
+const SPEC: CommandSpec = CommandSpec {
+    name: "filter",
+    description: "読み上げない語句の閲覧と編集",
+    description_en: "View and edit words that are not read",
+    category: Category::Dictionary,
+    aliases: &[],
+    manage_guild_only: true,
+    dm_allowed: false,
+    options: &[
+        subcommand(
+            "add",
+            "フィルターに語句を追加",
+            "Add a word to the filter",
+            &[
+                OptionSpec::new("word", "読み上げたくない語句", "Word not to read", STRING)
+                    .required(),
+            ],
+        ),
+        subcommand(
+            "remove",
+            "フィルターから語句を削除",
+            "Remove a word from the filter",
+            &[OptionSpec::new("word", "削除したい語句", "Word to remove", STRING).required()],
+        ),
+        subcommand("list", "フィルターを表示", "Show the filter", &[]),
+        subcommand(
+            "mode",
+            "フィルターに一致した語句の扱いを設定",
+            "Set how words matching the filter are handled",
+            &[OptionSpec::new(
+                "mode",
+                "語句の扱い",
+                "How to handle the words",
+                OptionKind::String {
+                    choices: &[
+                        Choice::localized("読み上げない", "Do not read", "drop"),
+                        Choice::localized("「ピー」に置き換える", "Replace with a bleep", "bleep"),
+                    ],
+                    autocomplete: false,
+                },
+            )
+            .required()],
+        ),
+    ],
+};
+
+pub(super) struct FilterCommand;
+
+#[async_trait]
+impl SlashCommand for FilterCommand {
+    fn spec(&self) -> &'static CommandSpec {
+        &SPEC
+    }
+
+    fn parse(&self, options: &[CommandDataOption]) -> Result<Command, CommandParseError> {
+        parse_filter(find_subcommand("filter", options)?)
+    }
+
+    async fn run(
+        &self,
+        ctx: &Context,
+        cmd: &ApplicationCommandInteraction,
+        lang: Language,
+        command: Command,
+    ) -> Result<()> {
+        match command {
+            Command::FilterAdd(option) => handle_add(ctx, cmd, lang, option)
+                .await
+                .context("Failed to execute /filter add"),
+            Command::FilterRemove(option) => handle_remove(ctx, cmd, lang, option)
+                .await
+                .context("Failed to execute /filter remove"),
+            Command::FilterList => handle_list(ctx, cmd, lang)
+                .await
+                .context("Failed to execute /filter list"),
+            Command::FilterMode(option) => handle_mode(ctx, cmd, lang, option)
+                .await
+                .context("Failed to execute /filter mode"),
+            command => bail!("Unexpected command for /filter: {:?}", command),
+        }
+    }
+}
+
+async fn handle_add(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+    option: FilterAddOption,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r(ctx, cmd, guild_only(lang, "`/filter add`")).await?;
+            return Ok(());
+        }
+    };
+
+    let state = app_state::get(ctx).await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+
+    let resp = filter::add(
+        &mut conn,
+        filter::AddOption {
+            guild_id: guild_id.into(),
+            word: option.word.clone(),
+        },
+    )
+    .await?;
+
+    let msg = match resp {
+        filter::AddResponse::Success => messages::format(
+            lang,
+            Key::FilterAdded,
+            &[("word", &sanitize_response(&option.word))],
+        ),
+        filter::AddResponse::WordAlreadyExists => messages::format(
+            lang,
+            Key::FilterWordExists,
+            &[("word", &sanitize_response(&option.word))],
+        ),
+        filter::AddResponse::TooManyEntries => {
+            messages::format(lang, Key::FilterTooMany, &[("max", &filter::MAX_ENTRIES)])
+        }
+    };
+    r(ctx, cmd, msg).await?;
+    Ok(())
+}
+
+async fn handle_remove(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+    option: FilterRemoveOption,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r(ctx, cmd, guild_only(lang, "`/filter remove`")).await?;
+            return Ok(());
+        }
+    };
+
+    let state = app_state::get(ctx).await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+
+    let resp = filter::remove(
+        &mut conn,
+        filter::RemoveOption {
+            guild_id: guild_id.into(),
+            word: option.word.clone(),
+        },
+    )
+    .await?;
+
+    let msg = match resp {
+        filter::RemoveResponse::Success => messages::format(
+            lang,
+            Key::FilterRemoved,
+            &[("word", &sanitize_response(&option.word))],
+        ),
+        filter::RemoveResponse::WordDoesNotExist => messages::format(
+            lang,
+            Key::FilterWordNotFound,
+            &[("word", &sanitize_response(&option.word))],
+        ),
+    };
+    r(ctx, cmd, msg).await?;
+    Ok(())
+}
+
+async fn handle_list(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r(ctx, cmd, guild_only(lang, "`/filter list`")).await?;
+            return Ok(());
+        }
+    };
+
+    let state = app_state::get(ctx).await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+
+    let words = filter::get_all(
+        &mut conn,
+        filter::GetAllOption {
+            guild_id: guild_id.into(),
+        },
+    )
+    .await?;
+    let mode = filter::get_mode(
+        &mut conn,
+        filter::GetModeOption {
+            guild_id: guild_id.into(),
+        },
+    )
+    .await?;
+
+    {
+        let mut embed = CreateEmbed::default();
+
+        let guild_name = guild_id
+            .name(&ctx.cache)
+            .unwrap_or_else(|| messages::text(lang, Key::ServerFallbackName));
+        embed.title(messages::format(
+            lang,
+            Key::FilterTitle,
+            &[("guild", &guild_name)],
+        ));
+
+        let word_list = if words.is_empty() {
+            messages::text(lang, Key::FilterEmpty)
+        } else {
+            words
+                .iter()
+                .map(|word| sanitize_response(word))
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        embed.description(word_list);
+
+        embed.footer(|footer| {
+            footer.text(messages::format(
+                lang,
+                Key::FilterModeFooter,
+                &[("mode", &messages::text(lang, filter_mode_label(mode)))],
+            ))
+        });
+
+        cmd.create_interaction_response(&ctx.http, |create_response| {
+            create_response
+                .kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|create_message| create_message.add_embed(embed))
+        })
+        .await
+        .context("Failed to create interaction response")?;
+    };
+
+    Ok(())
+}
+
+async fn handle_mode(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+    option: FilterModeOption,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r(ctx, cmd, guild_only(lang, "`/filter mode`")).await?;
+            return Ok(());
+        }
+    };
+
+    let state = app_state::get(ctx).await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+
+    filter::set_mode(
+        &mut conn,
+        filter::SetModeOption {
+            guild_id: guild_id.into(),
+            mode: option.mode,
+        },
+    )
+    .await?;
+
+    r(
+        ctx,
+        cmd,
+        messages::format(
+            lang,
+            Key::FilterModeChanged,
+            &[(
+                "mode",
+                &messages::text(lang, filter_mode_label(option.mode)),
+            )],
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+fn filter_mode_label(mode: FilterMode) -> Key {
+    match mode {
+        FilterMode::Drop => Key::FilterModeDrop,
+        FilterMode::Bleep => Key::FilterModeBleep,
+    }
+}
+
+fn parse_filter(option_filter: &CommandDataOption) -> Result<Command, CommandParseError> {
+    let options = &option_filter.options;
+
+    match option_filter.name.as_str() {
+        "add" => Ok(Command::FilterAdd(FilterAddOption {
+            word: required(find_string(options, "word")?, "word")?,
+        })),
+        "remove" => Ok(Command::FilterRemove(FilterRemoveOption {
+            word: required(find_string(options, "word")?, "word")?,
+        })),
+        "list" => Ok(Command::FilterList),
+        "mode" => {
+            let mode = required(find_string(options, "mode")?, "mode")?;
+            let mode = FilterMode::parse(&mode).ok_or_else(|| invalid("mode", mode))?;
+
+            Ok(Command::FilterMode(FilterModeOption { mode }))
+        }
+        _ => Err(unknown_subcommand("filter", option_filter)),
+    }
+}
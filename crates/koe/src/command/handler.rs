@@ -1,380 +1,864 @@
 use super::{
-    model::{Command, DictAddOption, DictRemoveOption},
-    parser::parse,
+    confirm::{self, CONFIRM_TIMEOUT},
+    model::{Command, VoiceParamsOption},
+    parser::CommandParseError,
+    permission,
+    registry::Registered,
+    responder::ResponseMessage,
+    split,
 };
-use crate::{app_state, component_interaction::custom_id};
-use anyhow::{anyhow, bail, Context as _, Result};
+use crate::{
+    app_state,
+    component_interaction::custom_id::ConfirmAction,
+    messages::{self, Key},
+    rate_limit::{MAX_MUTATIONS_PER_WINDOW, MAX_READS_PER_WINDOW},
+    speech_queue::{self, EnqueueOption, Voice},
+};
+use anyhow::{anyhow, bail, Result};
+use discord_md::generate::{ToMarkdownString, ToMarkdownStringOption};
 use koe_db::{
-    dict::{GetAllOption, InsertOption, InsertResponse, RemoveOption, RemoveResponse},
-    voice::GetOption,
+    command_permission::Requirement,
+    furigana::FuriganaSyntax,
+    guild_settings::{self, BoolKey, SettingValue},
+    language::Language,
+    mention_only::MentionOnlyMode,
+    redis,
+    scale_bounds::{self, ScaleKind},
+};
+use koe_speech::{
+    provider::SpeechProvider,
+    speech::{PITCH_SCALE_RANGE, SPEED_SCALE_RANGE},
+    voicevox::Preset,
 };
-use rand::seq::SliceRandom;
+use log::warn;
 use serenity::{
-    builder::{
-        CreateActionRow, CreateComponents, CreateEmbed, CreateSelectMenu, CreateSelectMenuOption,
-    },
+    builder::{CreateComponents, CreateEmbed},
     client::Context,
     model::{
-        application::interaction::{
-            application_command::ApplicationCommandInteraction, InteractionResponseType,
-            MessageFlags,
+        application::{
+            command::CommandOptionType,
+            interaction::application_command::ApplicationCommandInteraction,
         },
-        id::{ChannelId, GuildId, UserId},
+        channel::AttachmentType,
+        id::GuildId,
     },
+    utils::ContentSafeOptions,
+};
+use std::{
+    fmt::Display,
+    ops::RangeInclusive,
+    time::{Duration, Instant},
 };
 
+/// 埋め込みの説明文の最大文字数
+pub(super) const MAX_EMBED_DESCRIPTION_LENGTH: usize = 4096;
+
 pub async fn handle(ctx: &Context, cmd: &ApplicationCommandInteraction) -> Result<()> {
-    match parse(cmd) {
-        Command::Join => handle_join(ctx, cmd)
-            .await
-            .context("Failed to execute /join")?,
-        Command::Leave => handle_leave(ctx, cmd)
-            .await
-            .context("Failed to execute /leave")?,
-        Command::Skip => handle_skip(ctx, cmd)
-            .await
-            .context("Failed to execute /skip")?,
-        Command::Voice => handle_voice(ctx, cmd)
-            .await
-            .context("Failed to execute /voice")?,
-        Command::DictAdd(option) => handle_dict_add(ctx, cmd, option)
-            .await
-            .context("Failed to execute /dict add")?,
-        Command::DictRemove(option) => handle_dict_remove(ctx, cmd, option)
-            .await
-            .context("Failed to execute /dict remove")?,
-        Command::DictView => handle_dict_view(ctx, cmd)
-            .await
-            .context("Failed to execute /dict view")?,
-        Command::Help => handle_help(ctx, cmd)
-            .await
-            .context("Failed to execute /help")?,
-        Command::Unknown => {
-            bail!("Unknown command: {:?}", cmd);
+    let lang = messages::resolve(ctx, cmd.guild_id, &cmd.locale).await;
+
+    let parsed = Registered::find(&cmd.data)
+        .and_then(|registered| Ok((registered, registered.parse(&cmd.data)?)));
+    let (registered, command) = match parsed {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            r_ephemeral(ctx, cmd, describe_parse_error(lang, &err)).await?;
+            bail!("Failed to parse command: {}: {:?}", err, cmd);
         }
     };
 
-    Ok(())
-}
+    // DMで使えないコマンドはDiscordが表示しないが、古いクライアントなどから送信された場合に備える
+    if cmd.guild_id.is_none() && !registered.allows_dm() {
+        respond_before_defer(ctx, cmd, messages::text(lang, Key::GuildOnlyThisCommand)).await?;
+        return Ok(());
+    }
 
-async fn handle_join(ctx: &Context, cmd: &ApplicationCommandInteraction) -> Result<()> {
-    let guild_id = match cmd.guild_id {
-        Some(id) => id,
-        None => {
-            r(ctx, cmd, "`/join`, `/kjoin` はサーバー内でのみ使えます。").await?;
-            return Ok(());
-        }
+    let state = app_state::get(ctx).await?;
+    let max = if command.is_read_only() {
+        MAX_READS_PER_WINDOW
+    } else {
+        MAX_MUTATIONS_PER_WINDOW
     };
-    let user_id = cmd.user.id;
-    let text_channel_id = cmd.channel_id;
+    if !state
+        .command_rate_limiter
+        .check(cmd.user.id, &cmd.data.name, max, Instant::now())
+    {
+        respond_before_defer(ctx, cmd, messages::text(lang, Key::RateLimited)).await?;
+        return Ok(());
+    }
 
-    let voice_channel_id = match get_user_voice_channel(ctx, &guild_id, &user_id)? {
-        Some(channel) => channel,
-        None => {
-            r(
-                ctx,
-                cmd,
-                "ボイスチャンネルに接続してから `/join` を送信してください。",
-            )
-            .await?;
-            return Ok(());
+    let unmet_requirement = match permission::unmet_requirement(ctx, cmd, &command).await {
+        Ok(requirement) => requirement,
+        Err(err) => {
+            // 応答しないとDiscordに「アプリケーションが応答しませんでした」と表示されるため、先に伝える
+            let _ = respond_before_defer(ctx, cmd, messages::text(lang, Key::ErrorOccurred)).await;
+            return Err(err.context("Failed to check command permission"));
         }
     };
+    if let Some(requirement) = unmet_requirement {
+        let name = command.permission_name().unwrap_or_default();
+        let msg = messages::format(
+            lang,
+            Key::PermissionDenied,
+            &[
+                ("command", &name),
+                ("requirement", &requirement_label(lang, requirement)),
+            ],
+        );
 
-    koe_call::join_deaf(ctx, guild_id, voice_channel_id).await?;
-
-    let state = app_state::get(ctx).await?;
-    state.connected_guild_states.insert(
-        guild_id,
-        app_state::ConnectedGuildState {
-            bound_text_channel: text_channel_id,
-            last_message_read: None,
-        },
-    );
-
-    r(ctx, cmd, "接続しました。").await?;
-    Ok(())
-}
+        respond_before_defer(ctx, cmd, msg).await?;
+        return Ok(());
+    }
 
-async fn handle_leave(ctx: &Context, cmd: &ApplicationCommandInteraction) -> Result<()> {
-    let guild_id = match cmd.guild_id {
-        Some(id) => id,
-        None => {
-            r(ctx, cmd, "`/leave`, `/kleave` はサーバー内でのみ使えます。").await?;
-            return Ok(());
-        }
-    };
+    // 時間のかかるコマンドは、応答期限を過ぎないよう先に応答を保留する
+    if command.is_slow() {
+        state
+            .responder
+            .defer(ctx, cmd, command.is_ephemeral_when_deferred())
+            .await?;
+    }
 
-    if !koe_call::is_connected(ctx, guild_id).await? {
-        {
-            r(ctx, cmd, "どのボイスチャンネルにも接続していません。").await?;
-            return Ok(());
-        };
+    let result = registered.run(ctx, cmd, lang, command).await;
+    if result.is_err() {
+        // すでに応答している場合は失敗するが、問題はない
+        let _ = respond(
+            ctx,
+            cmd,
+            CommandResponse {
+                content: Some(messages::text(lang, Key::ErrorOccurred)),
+                error: true,
+                ..Default::default()
+            },
+        )
+        .await;
     }
 
-    koe_call::leave(ctx, guild_id).await?;
+    result
+}
 
+/// 応答を保留する前に、実行者のみに表示される応答を送信する
+/// 保留していないため、`respond`を通さずに直接応答する
+async fn respond_before_defer(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    msg: String,
+) -> Result<()> {
     let state = app_state::get(ctx).await?;
-    state.connected_guild_states.remove(&guild_id);
-
-    r(ctx, cmd, "切断しました。").await?;
-    Ok(())
+    state
+        .responder
+        .create_response(
+            ctx,
+            cmd,
+            ResponseMessage {
+                content: Some(msg),
+                ephemeral: true,
+                ..Default::default()
+            },
+        )
+        .await
 }
 
-async fn handle_skip(ctx: &Context, cmd: &ApplicationCommandInteraction) -> Result<()> {
-    let guild_id = match cmd.guild_id {
-        Some(id) => id,
-        None => {
-            r(ctx, cmd, "`/skip`, `/kskip` はサーバー内でのみ使えます。").await?;
-            return Ok(());
+/// コマンドを解釈できなかった理由を、メンバーに伝える文章にする
+fn describe_parse_error(lang: Language, err: &CommandParseError) -> String {
+    match err {
+        CommandParseError::UnknownCommand { name } => {
+            messages::format(lang, Key::ParseUnknownCommand, &[("command", name)])
         }
-    };
+        CommandParseError::UnknownSubcommand {
+            command,
+            received: Some(received),
+        } => messages::format(
+            lang,
+            Key::ParseUnknownCommand,
+            &[("command", &format!("{} {}", command, received))],
+        ),
+        CommandParseError::UnknownSubcommand {
+            command,
+            received: None,
+        } => messages::format(lang, Key::ParseMissingSubcommand, &[("command", command)]),
+        CommandParseError::MissingOption { name } => {
+            messages::format(lang, Key::ParseMissingOption, &[("name", name)])
+        }
+        CommandParseError::WrongType {
+            name,
+            expected,
+            actual,
+        } => messages::format(
+            lang,
+            Key::ParseWrongType,
+            &[
+                ("name", name),
+                (
+                    "expected",
+                    &messages::text(lang, option_type_label(*expected)),
+                ),
+                ("actual", &messages::text(lang, option_type_label(*actual))),
+            ],
+        ),
+        CommandParseError::InvalidValue { name, value } => messages::format(
+            lang,
+            Key::ParseInvalidValue,
+            &[("name", name), ("value", &sanitize_response(value))],
+        ),
+    }
+}
 
-    if !koe_call::is_connected(ctx, guild_id).await? {
-        {
-            r(ctx, cmd, "どのボイスチャンネルにも接続していません。").await?;
-            return Ok(());
-        };
+fn option_type_label(kind: CommandOptionType) -> Key {
+    match kind {
+        CommandOptionType::String => Key::TypeString,
+        CommandOptionType::Integer => Key::TypeInteger,
+        CommandOptionType::Number => Key::TypeNumber,
+        CommandOptionType::Boolean => Key::TypeBoolean,
+        CommandOptionType::User => Key::TypeUser,
+        CommandOptionType::Channel => Key::TypeChannel,
+        CommandOptionType::Role => Key::TypeRole,
+        CommandOptionType::Mentionable => Key::TypeMentionable,
+        CommandOptionType::Attachment => Key::TypeAttachment,
+        CommandOptionType::SubCommand | CommandOptionType::SubCommandGroup => Key::TypeSubcommand,
+        _ => Key::TypeUnknown,
     }
+}
 
-    koe_call::skip(ctx, guild_id).await?;
+/// サーバー内でのみ使えるコマンドが、DMで送信された場合の応答
+pub(super) fn guild_only(lang: Language, command: &str) -> String {
+    messages::format(lang, Key::GuildOnly, &[("command", &command)])
+}
 
-    r(ctx, cmd, "読み上げ中のメッセージをスキップしました。").await?;
-    Ok(())
+// Helper function to create text message response
+pub(super) async fn r(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    text: impl ToString,
+) -> Result<()> {
+    respond(
+        ctx,
+        cmd,
+        CommandResponse {
+            content: Some(text.to_string()),
+            ..Default::default()
+        },
+    )
+    .await
 }
 
-async fn handle_voice(ctx: &Context, cmd: &ApplicationCommandInteraction) -> Result<()> {
-    let guild_id = match cmd.guild_id {
-        Some(id) => id,
-        None => {
-            r(ctx, cmd, "`/voice` はサーバー内でのみ使えます。").await?;
-            return Ok(());
-        }
-    };
+/// コマンドを送信したメンバーのみに見える応答を送信する
+pub(super) async fn r_ephemeral(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    text: impl ToString,
+) -> Result<()> {
+    respond(
+        ctx,
+        cmd,
+        CommandResponse {
+            content: Some(text.to_string()),
+            ephemeral: true,
+            ..Default::default()
+        },
+    )
+    .await
+}
 
-    let state = app_state::get(ctx).await?;
+/// 取り消せない操作を実行する前に、「はい」と「いいえ」のボタンで確認する
+/// ボタンはコマンドを送信したメンバーのみが操作でき、[`CONFIRM_TIMEOUT`]を過ぎると取り除く
+pub(super) async fn confirm(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+    prompt: String,
+    action: ConfirmAction,
+) -> Result<()> {
+    let guild_id = cmd
+        .guild_id
+        .ok_or_else(|| anyhow!("Confirmation is only available in guilds"))?;
 
-    let available_presets = state.voicevox_client.presets().await?;
-    let fallback_preset_id = available_presets
-        .choose(&mut rand::thread_rng())
-        .map(|p| p.id)
-        .ok_or_else(|| anyhow!("No presets available"))?;
+    let state = app_state::get(ctx).await?;
+    state.pending_confirmations.insert(cmd.id);
 
-    let mut conn = state.redis_client.get_async_connection().await?;
-    let current_preset = koe_db::voice::get(
-        &mut conn,
-        GetOption {
-            guild_id: guild_id.into(),
-            user_id: cmd.user.id.into(),
-            fallback: fallback_preset_id,
+    respond(
+        ctx,
+        cmd,
+        CommandResponse {
+            content: Some(prompt),
+            components: Some(confirm::build_components(
+                lang,
+                action,
+                guild_id,
+                cmd.user.id,
+            )),
+            ..Default::default()
         },
     )
     .await?;
 
-    {
-        let option_list = available_presets
-            .iter()
-            .map(|p| {
-                let mut option = CreateSelectMenuOption::default();
-                option
-                    .label(&p.name)
-                    .value(p.id)
-                    .default_selection(p.id == current_preset);
-                option
-            })
-            .collect::<Vec<_>>();
+    let ctx = ctx.clone();
+    let cmd = cmd.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(CONFIRM_TIMEOUT).await;
+
+        let state = match app_state::get(&ctx).await {
+            Ok(state) => state,
+            Err(err) => {
+                warn!("Failed to get app state: {:?}", err);
+                return;
+            }
+        };
+        // すでにボタンが操作されている場合は何もしない
+        if state.pending_confirmations.remove(&cmd.id).is_none() {
+            return;
+        }
 
-        let mut select = CreateSelectMenu::default();
-        select.custom_id(custom_id::CUSTOM_ID_VOICE);
-        select.options(|create_options| create_options.set_options(option_list));
-
-        let mut action_row = CreateActionRow::default();
-        action_row.add_select_menu(select);
-
-        let mut components = CreateComponents::default();
-        components.add_action_row(action_row);
-
-        cmd.create_interaction_response(&ctx.http, |create_response| {
-            create_response
-                .kind(InteractionResponseType::ChannelMessageWithSource)
-                .interaction_response_data(|create_message| {
-                    create_message
-                        .flags(MessageFlags::EPHEMERAL)
-                        .set_components(components)
-                })
-        })
-        .await
-        .context("Failed to create interaction response")?;
-    };
+        let result = cmd
+            .edit_original_interaction_response(&ctx.http, |edit| {
+                edit.content(messages::text(lang, Key::ConfirmTimedOut))
+                    .set_components(CreateComponents::default())
+            })
+            .await;
+        if let Err(err) = result {
+            warn!("Failed to remove confirmation buttons: {:?}", err);
+        }
+    });
 
     Ok(())
 }
 
-async fn handle_dict_add(
+/// コマンドへの応答の内容
+#[derive(Default)]
+pub(super) struct CommandResponse {
+    pub(super) content: Option<String>,
+    pub(super) embeds: Vec<CreateEmbed>,
+    pub(super) file: Option<AttachmentType<'static>>,
+    pub(super) components: Option<CreateComponents>,
+    /// コマンドを送信したメンバーのみに見える応答にするかどうか
+    /// 応答を保留したコマンドでは、保留した時点の設定が優先される
+    pub(super) ephemeral: bool,
+    /// エラーを伝える応答かどうか
+    /// `/settings read-responses`でエラーメッセージも読み上げる設定の場合のみ読み上げる
+    pub(super) error: bool,
+}
+
+/// コマンドに応答する
+/// `handle`で応答を保留したコマンドの場合は、保留中の応答を置き換える
+pub(super) async fn respond(
     ctx: &Context,
     cmd: &ApplicationCommandInteraction,
-    option: DictAddOption,
+    response: CommandResponse,
 ) -> Result<()> {
-    let guild_id = match cmd.guild_id {
-        Some(id) => id,
-        None => {
-            r(ctx, cmd, "`/dict add` はサーバー内でのみ使えます。").await?;
-            return Ok(());
+    // 文章のみの応答は、設定に応じて読み上げる
+    // 埋め込みやファイルを含む応答と、実行者のみに見える応答は読み上げない
+    let text_to_read = match &response.content {
+        Some(content)
+            if !response.ephemeral
+                && response.embeds.is_empty()
+                && response.file.is_none()
+                && response.components.is_none() =>
+        {
+            Some(content.clone())
         }
+        _ => None,
+    };
+    let is_error = response.error;
+    let ephemeral = response.ephemeral;
+
+    // Discordの上限を超える本文と埋め込みは、続けてフォローアップで送信する
+    let mut contents = response
+        .content
+        .map(|content| split::split_content(&content, split::MAX_CONTENT_LENGTH))
+        .unwrap_or_default()
+        .into_iter();
+    let mut embed_groups = split::group_embeds(response.embeds).into_iter();
+
+    // 本文が1つのメッセージに収まる場合は、最初の埋め込みを同じメッセージに含める
+    let first_content = contents.next();
+    let first_embeds = if contents.len() == 0 {
+        embed_groups.next().unwrap_or_default()
+    } else {
+        Vec::new()
     };
 
     let state = app_state::get(ctx).await?;
-    let mut conn = state.redis_client.get_async_connection().await?;
+    let first = ResponseMessage {
+        content: first_content,
+        embeds: first_embeds,
+        file: response.file,
+        components: response.components,
+        ephemeral,
+    };
+    if Command::try_from(cmd).is_ok_and(|command| command.is_slow()) {
+        state.responder.create_followup(ctx, cmd, first).await?;
+    } else {
+        state.responder.create_response(ctx, cmd, first).await?;
+    }
 
-    let resp = koe_db::dict::insert(
-        &mut conn,
-        InsertOption {
-            guild_id: guild_id.into(),
-            word: option.word.clone(),
-            read_as: option.read_as.clone(),
-        },
-    )
-    .await?;
+    let overflow = contents
+        .map(|content| (Some(content), Vec::new()))
+        .chain(embed_groups.map(|embeds| (None, embeds)));
+    for (content, embeds) in overflow {
+        state
+            .responder
+            .create_followup(
+                ctx,
+                cmd,
+                ResponseMessage {
+                    content,
+                    embeds,
+                    ephemeral,
+                    ..Default::default()
+                },
+            )
+            .await?;
+    }
+
+    if let Some(text) = text_to_read {
+        if let Err(err) = read_response(ctx, cmd, &text, is_error).await {
+            warn!("Failed to read command response: {:?}", err);
+        }
+    }
 
-    let msg = match resp {
-        InsertResponse::Success => format!(
-            "{}の読み方を{}として辞書に登録しました。",
-            sanitize_response(&option.word),
-            sanitize_response(&option.read_as)
-        ),
-        InsertResponse::WordAlreadyExists => format!(
-            "すでに{}は辞書に登録されています。",
-            sanitize_response(&option.word)
-        ),
-    };
-    r(ctx, cmd, msg).await?;
     Ok(())
 }
 
-async fn handle_dict_remove(
+/// `/settings read-responses`が有効で、ボイスチャンネルに接続している場合に、応答を読み上げる
+async fn read_response(
     ctx: &Context,
     cmd: &ApplicationCommandInteraction,
-    option: DictRemoveOption,
+    text: &str,
+    is_error: bool,
 ) -> Result<()> {
     let guild_id = match cmd.guild_id {
         Some(id) => id,
-        None => {
-            r(ctx, cmd, "`/dict remove` はサーバー内でのみ使えます。").await?;
-            return Ok(());
-        }
+        None => return Ok(()),
     };
 
     let state = app_state::get(ctx).await?;
+    if !state.voice_backend.is_connected(guild_id.into()).await? {
+        return Ok(());
+    }
+
     let mut conn = state.redis_client.get_async_connection().await?;
+    let mut keys = vec![BoolKey::ReadResponses];
+    if is_error {
+        keys.push(BoolKey::ReadErrorResponses);
+    }
+    for key in keys {
+        let enabled = guild_settings::get_bool(
+            &mut conn,
+            guild_settings::GetBoolOption {
+                guild_id: guild_id.into(),
+                key,
+            },
+        )
+        .await?;
+        if !enabled {
+            return Ok(());
+        }
+    }
 
-    let resp = koe_db::dict::remove(
-        &mut conn,
-        RemoveOption {
-            guild_id: guild_id.into(),
-            word: option.word.clone(),
+    // メンションを名前に置き換え、書式の記号を読み上げないようにする
+    let options = ContentSafeOptions::new()
+        .show_discriminator(false)
+        .display_as_member_from(guild_id);
+    let text = serenity::utils::content_safe(&ctx.cache, text, &options, &[]);
+    let text = discord_md::parse(&text).to_markdown_string(
+        &ToMarkdownStringOption::new()
+            .omit_format(true)
+            .omit_spoiler(true),
+    );
+
+    speech_queue::enqueue(
+        ctx,
+        EnqueueOption {
+            guild_id,
+            text,
+            voice: Voice::System,
         },
     )
     .await?;
-
-    let msg = match resp {
-        RemoveResponse::Success => format!(
-            "辞書から{}を削除しました。",
-            sanitize_response(&option.word)
-        ),
-        RemoveResponse::WordDoesNotExist => format!(
-            "{}は辞書に登録されていません。",
-            sanitize_response(&option.word)
-        ),
-    };
-    r(ctx, cmd, msg).await?;
     Ok(())
 }
 
-async fn handle_dict_view(ctx: &Context, cmd: &ApplicationCommandInteraction) -> Result<()> {
-    let guild_id = match cmd.guild_id {
-        Some(id) => id,
-        None => {
-            r(ctx, cmd, "`/dict view` はサーバー内でのみ使えます。").await?;
-            return Ok(());
+pub(super) fn scale_kind_label(kind: ScaleKind) -> Key {
+    match kind {
+        ScaleKind::Speed => Key::Speed,
+        ScaleKind::Pitch => Key::Pitch,
+    }
+}
+
+pub(super) fn requirement_label(lang: Language, requirement: Requirement) -> String {
+    match requirement {
+        Requirement::Everyone => messages::text(lang, Key::RequirementEveryone),
+        Requirement::InVoiceChannel => messages::text(lang, Key::RequirementInVoiceChannel),
+        Requirement::Role(role_id) => {
+            messages::format(lang, Key::RequirementRole, &[("role", &role_id)])
         }
-    };
+        Requirement::ManageGuild => messages::text(lang, Key::RequirementManageGuild),
+    }
+}
 
-    let state = app_state::get(ctx).await?;
-    let mut conn = state.redis_client.get_async_connection().await?;
+pub(super) fn setting_value_label(lang: Language, value: &SettingValue) -> String {
+    match value {
+        SettingValue::Bool(true) => messages::text(lang, Key::SettingEnabled),
+        SettingValue::Bool(false) => messages::text(lang, Key::SettingDisabled),
+        SettingValue::Int(value) => value.to_string(),
+        SettingValue::Text(value) => format!("「{}」", sanitize_response(value)),
+        SettingValue::Channel(channel_id) => format!("<#{}>", channel_id),
+        SettingValue::Unset => messages::text(lang, Key::SettingNotSet),
+    }
+}
+
+pub(super) fn furigana_syntax_label(syntax: FuriganaSyntax) -> Key {
+    match syntax {
+        FuriganaSyntax::Braces => Key::FuriganaSyntaxBraces,
+        FuriganaSyntax::Ruby => Key::FuriganaSyntaxRuby,
+    }
+}
+
+pub(super) fn mention_only_mode_label(mode: MentionOnlyMode) -> Key {
+    match mode {
+        MentionOnlyMode::Read => Key::MentionOnlyRead,
+        MentionOnlyMode::Skip => Key::MentionOnlySkip,
+        MentionOnlyMode::Note => Key::MentionOnlyNote,
+    }
+}
+
+/// 話速と音高が設定できる範囲にあるかを確認し、範囲外の場合はエラーメッセージを返す
+pub(super) fn validate_scales(
+    lang: Language,
+    option: &VoiceParamsOption,
+    speed_range: &RangeInclusive<f64>,
+    pitch_range: &RangeInclusive<f64>,
+) -> Option<String> {
+    if let Some(speed) = option.speed {
+        if !speed_range.contains(&speed) {
+            return Some(scale_out_of_range(lang, ScaleKind::Speed, speed_range));
+        }
+    }
+    if let Some(pitch) = option.pitch {
+        if !pitch_range.contains(&pitch) {
+            return Some(scale_out_of_range(lang, ScaleKind::Pitch, pitch_range));
+        }
+    }
+    None
+}
+
+pub(super) fn scale_out_of_range(
+    lang: Language,
+    kind: ScaleKind,
+    range: &RangeInclusive<f64>,
+) -> String {
+    messages::format(
+        lang,
+        Key::ScaleOutOfRange,
+        &[
+            ("kind", &messages::text(lang, scale_kind_label(kind))),
+            ("min", range.start()),
+            ("max", range.end()),
+        ],
+    )
+}
+
+/// 声のパラメータについて、システム全体の範囲
+pub(super) fn global_scale_range(kind: ScaleKind) -> RangeInclusive<f64> {
+    match kind {
+        ScaleKind::Speed => SPEED_SCALE_RANGE,
+        ScaleKind::Pitch => PITCH_SCALE_RANGE,
+    }
+}
 
-    let dict = koe_db::dict::get_all(
-        &mut conn,
-        GetAllOption {
+/// メンバーが設定できる声のパラメータの範囲を返す
+/// サーバーで範囲が設定されていても、システム全体の範囲を超えることはない
+pub(super) async fn get_scale_range(
+    conn: &mut redis::aio::Connection,
+    guild_id: GuildId,
+    kind: ScaleKind,
+) -> Result<RangeInclusive<f64>> {
+    let global = global_scale_range(kind);
+    let bounds = scale_bounds::get(
+        conn,
+        scale_bounds::GetOption {
             guild_id: guild_id.into(),
+            kind,
         },
     )
     .await?;
 
-    {
-        let mut embed = CreateEmbed::default();
+    Ok(match bounds {
+        Some(bounds) => {
+            let min = bounds.min.clamp(*global.start(), *global.end());
+            let max = bounds.max.clamp(*global.start(), *global.end());
+            if min <= max {
+                min..=max
+            } else {
+                global
+            }
+        }
+        None => global,
+    })
+}
 
-        let guild_name = guild_id
-            .name(&ctx.cache)
-            .unwrap_or_else(|| "サーバー".to_string());
-        embed.title(format!("📕 {}の辞書", guild_name));
+/// プリセットを探し、存在しない場合は使用できるプリセットの一覧を含むエラーメッセージを返す
+pub(super) async fn find_preset(
+    lang: Language,
+    provider: &dyn SpeechProvider,
+    preset_id: i64,
+) -> Result<std::result::Result<Preset, String>> {
+    let available_presets = provider.list_voices().await?;
 
-        embed.fields(
-            dict.into_iter()
-                .map(|(word, read_as)| (word, sanitize_response(&read_as), false)),
-        );
+    if let Some(preset) = available_presets.iter().find(|p| p.id == preset_id) {
+        return Ok(Ok(preset.clone()));
+    }
 
-        cmd.create_interaction_response(&ctx.http, |create_response| {
-            create_response
-                .kind(InteractionResponseType::ChannelMessageWithSource)
-                .interaction_response_data(|create_message| create_message.add_embed(embed))
-        })
-        .await
-        .context("Failed to create interaction response")?;
+    let preset_list = available_presets
+        .iter()
+        .map(|p| format!("{}: {}", p.id, p.name))
+        .collect::<Vec<_>>()
+        .join("\n");
+    Ok(Err(messages::format(
+        lang,
+        Key::PresetNotFound,
+        &[("id", &preset_id), ("list", &preset_list)],
+    )))
+}
+
+pub(super) fn voice_params_summary(
+    lang: Language,
+    preset: Option<&Preset>,
+    option: &VoiceParamsOption,
+) -> String {
+    let labeled = |label: Key, value: &(dyn Display + Sync)| {
+        messages::format(
+            lang,
+            Key::LabeledValue,
+            &[("label", &messages::text(lang, label)), ("value", value)],
+        )
     };
 
-    Ok(())
+    let mut settings = Vec::new();
+    if let Some(preset) = preset {
+        settings.push(labeled(Key::Voice, &sanitize_response(&preset.name)));
+    }
+    if let Some(speed) = option.speed {
+        settings.push(labeled(Key::Speed, &speed));
+    }
+    if let Some(pitch) = option.pitch {
+        settings.push(labeled(Key::Pitch, &pitch));
+    }
+    settings.join(&messages::text(lang, Key::ListSeparator))
 }
 
-async fn handle_help(ctx: &Context, cmd: &ApplicationCommandInteraction) -> Result<()> {
-    r(
-        ctx,
-        cmd,
-        "使い方はこちらをご覧ください:\nhttps://github.com/ciffelia/koe/blob/main/docs/user_guide.md",
-    )
-    .await?;
-    Ok(())
+/// 行を改行でつなげ、`max_length`文字を超える場合は収まらない行の数を末尾に示す
+pub(super) fn summarize_lines(lang: Language, lines: &[String], max_length: usize) -> String {
+    let mut text = String::new();
+    for (i, line) in lines.iter().enumerate() {
+        let rest = format!(
+            "\n…{}",
+            messages::format(lang, Key::ListMore, &[("count", &(lines.len() - i))])
+        );
+        if text.chars().count() + line.chars().count() + 1 + rest.chars().count() > max_length {
+            text.push_str(rest.trim_start());
+            return text;
+        }
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(line);
+    }
+    text
 }
 
-fn get_user_voice_channel(
-    ctx: &Context,
-    guild_id: &GuildId,
-    user_id: &UserId,
-) -> Result<Option<ChannelId>> {
-    let guild = guild_id
-        .to_guild_cached(&ctx.cache)
-        .context("Failed to find guild in the cache")?;
-
-    let channel_id = guild
-        .voice_states
-        .get(user_id)
-        .and_then(|voice_state| voice_state.channel_id);
-
-    Ok(channel_id)
+pub(super) fn sanitize_response(text: &str) -> String {
+    format!("`{}`", text.replace('`', ""))
 }
 
-// Helper function to create text message response
-async fn r(ctx: &Context, cmd: &ApplicationCommandInteraction, text: impl ToString) -> Result<()> {
-    cmd.create_interaction_response(&ctx.http, |create_response| {
-        create_response
-            .kind(InteractionResponseType::ChannelMessageWithSource)
-            .interaction_response_data(|create_message| create_message.content(text))
-    })
-    .await
-    .context("Failed to create interaction response")?;
-
-    Ok(())
+pub(super) fn format_duration(lang: Language, duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match secs {
+        0..=59 => messages::format(lang, Key::DurationSeconds, &[("secs", &secs)]),
+        60..=3599 => messages::format(
+            lang,
+            Key::DurationMinutes,
+            &[("mins", &(secs / 60)), ("secs", &(secs % 60))],
+        ),
+        _ => messages::format(
+            lang,
+            Key::DurationHours,
+            &[("hours", &(secs / 3600)), ("mins", &(secs % 3600 / 60))],
+        ),
+    }
 }
 
-fn sanitize_response(text: &str) -> String {
-    format!("`{}`", text.replace('`', ""))
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        command::responder::{Sent, SentMessage},
+        test_support::{dm_command, guild_command, TestContext},
+    };
+    use serde_json::json;
+
+    fn text(key: Key, ephemeral: bool) -> SentMessage {
+        SentMessage {
+            content: Some(messages::text(Language::Japanese, key)),
+            embed_titles: Vec::new(),
+            has_components: false,
+            ephemeral,
+        }
+    }
+
+    fn titled_embeds(count: usize) -> Vec<CreateEmbed> {
+        (0..count)
+            .map(|i| {
+                let mut embed = CreateEmbed::default();
+                embed.title(i);
+                embed
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn replies_with_the_command_result() {
+        let t = TestContext::new().await;
+        handle(&t.ctx, &dm_command("help")).await.unwrap();
+
+        let sent = t.responder.sent();
+        assert_eq!(sent.len(), 1);
+        match &sent[0] {
+            Sent::Response(message) => {
+                assert!(message.content.as_ref().unwrap().contains("user_guide.md"));
+                assert!(!message.embed_titles.is_empty());
+                assert!(!message.ephemeral);
+            }
+            other => panic!("unexpected response: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_unknown_commands_ephemerally() {
+        let t = TestContext::new().await;
+        assert!(handle(&t.ctx, &dm_command("unknown")).await.is_err());
+
+        assert_eq!(
+            t.responder.sent(),
+            [Sent::Response(SentMessage {
+                content: Some(messages::format(
+                    Language::Japanese,
+                    Key::ParseUnknownCommand,
+                    &[("command", &"unknown")],
+                )),
+                ..text(Key::ParseUnknownCommand, true)
+            })]
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_guild_only_commands_in_dms() {
+        let t = TestContext::new().await;
+        handle(&t.ctx, &dm_command("join")).await.unwrap();
+
+        assert_eq!(
+            t.responder.sent(),
+            [Sent::Response(text(Key::GuildOnlyThisCommand, true))]
+        );
+    }
+
+    #[tokio::test]
+    async fn replies_when_the_permission_check_fails() {
+        let t = TestContext::new().await;
+        // 「サーバー管理」の権限がないメンバーは、接続できないRedisに条件を問い合わせる
+        let result = handle(&t.ctx, &guild_command("leave", json!([]), false)).await;
+        assert!(result.is_err());
+
+        assert_eq!(
+            t.responder.sent(),
+            [Sent::Response(text(Key::ErrorOccurred, true))]
+        );
+    }
+
+    #[tokio::test]
+    async fn defers_slow_commands_before_replying() {
+        let t = TestContext::new().await;
+        handle(&t.ctx, &guild_command("leave", json!([]), true))
+            .await
+            .unwrap();
+
+        // 保留した応答は、最初のフォローアップで置き換える
+        assert_eq!(
+            t.responder.sent(),
+            [
+                Sent::Defer { ephemeral: false },
+                Sent::Followup(text(Key::NotConnected, false)),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn sends_overflowing_content_as_followups() {
+        let t = TestContext::new().await;
+        let content = "あ".repeat(split::MAX_CONTENT_LENGTH + 10);
+        respond(
+            &t.ctx,
+            &guild_command("join", json!([]), true),
+            CommandResponse {
+                content: Some(content.clone()),
+                embeds: titled_embeds(1),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        let sent = t.responder.sent();
+        let (first, rest) = match sent.as_slice() {
+            [Sent::Response(first), rest @ ..] => (first, rest),
+            other => panic!("unexpected responses: {:?}", other),
+        };
+        // 本文が1つのメッセージに収まらない場合は、埋め込みを本文の後に送る
+        assert!(first.embed_titles.is_empty());
+        let followups = rest
+            .iter()
+            .map(|sent| match sent {
+                Sent::Followup(message) => message,
+                other => panic!("unexpected response: {:?}", other),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(followups.len(), 2);
+        assert_eq!(
+            format!(
+                "{}{}",
+                first.content.as_ref().unwrap(),
+                followups[0].content.as_ref().unwrap()
+            ),
+            content
+        );
+        assert_eq!(followups[1].content, None);
+        assert_eq!(followups[1].embed_titles, [Some("0".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn sends_overflowing_embeds_after_the_deferred_response() {
+        let t = TestContext::new().await;
+        respond(
+            &t.ctx,
+            &guild_command("leave", json!([]), true),
+            CommandResponse {
+                content: Some("content".to_string()),
+                embeds: titled_embeds(11),
+                ephemeral: true,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        let sent = t.responder.sent();
+        assert_eq!(sent.len(), 2);
+        match (&sent[0], &sent[1]) {
+            (Sent::Followup(first), Sent::Followup(second)) => {
+                assert_eq!(first.content.as_deref(), Some("content"));
+                assert_eq!(first.embed_titles.len(), 10);
+                assert_eq!(second.embed_titles, [Some("10".to_string())]);
+                assert!(first.ephemeral && second.ephemeral);
+            }
+            other => panic!("unexpected responses: {:?}", other),
+        }
+    }
 }
@@ -0,0 +1,184 @@
+use super::registry::{self, Category, OptionKind, OptionSpec};
+use crate::messages::{self, Key};
+use koe_db::language::Language;
+use serenity::builder::CreateEmbed;
+use std::mem;
+
+/// 1つの埋め込みに含められるフィールドの最大数
+const MAX_EMBED_FIELDS: usize = 25;
+/// 1つのメッセージに含められる埋め込みの最大数
+const MAX_EMBEDS_PER_MESSAGE: usize = 10;
+/// 1つのメッセージに含まれる埋め込みの文字数の合計の上限
+const MAX_TOTAL_EMBED_LENGTH: usize = 6000;
+
+/// `/help`で表示する1つのコマンド（またはサブコマンド）
+struct HelpEntry {
+    name: String,
+    value: String,
+}
+
+impl HelpEntry {
+    fn len(&self) -> usize {
+        self.name.chars().count() + self.value.chars().count()
+    }
+}
+
+/// `/help`で表示する埋め込みを、分類ごとに組み立てる
+/// Discordの制限を超えないよう、1つのメッセージで送信できる単位に分けて返す
+pub fn build_messages(lang: Language) -> Vec<Vec<CreateEmbed>> {
+    let mut messages = Vec::new();
+    let mut current = Vec::new();
+    let mut current_length = 0;
+
+    for category in Category::ALL {
+        let entries = build_entries(lang, category);
+        let category_name = messages::text(lang, category_label(category));
+
+        for (i, chunk) in entries.chunks(MAX_EMBED_FIELDS).enumerate() {
+            let title = if i == 0 {
+                category_name.clone()
+            } else {
+                messages::format(lang, Key::HelpContinued, &[("category", &category_name)])
+            };
+            let length = title.chars().count() + chunk.iter().map(HelpEntry::len).sum::<usize>();
+
+            if current.len() >= MAX_EMBEDS_PER_MESSAGE
+                || (!current.is_empty() && current_length + length > MAX_TOTAL_EMBED_LENGTH)
+            {
+                messages.push(mem::take(&mut current));
+                current_length = 0;
+            }
+
+            let mut embed = CreateEmbed::default();
+            embed.title(title);
+            for entry in chunk {
+                embed.field(&entry.name, &entry.value, false);
+            }
+            current.push(embed);
+            current_length += length;
+        }
+    }
+
+    if !current.is_empty() {
+        messages.push(current);
+    }
+    messages
+}
+
+fn build_entries(lang: Language, category: Category) -> Vec<HelpEntry> {
+    let mut entries = Vec::new();
+
+    for spec in registry::COMMANDS
+        .iter()
+        .map(|x| x.spec())
+        .filter(|x| x.category == category)
+    {
+        let description = localize(lang, spec.description, spec.description_en);
+        let has_subcommands = spec.options.iter().any(|option| {
+            matches!(
+                option.kind,
+                OptionKind::SubCommand(_) | OptionKind::SubCommandGroup(_)
+            )
+        });
+
+        if !has_subcommands {
+            let name = std::iter::once(spec.name)
+                .chain(spec.aliases.iter().copied())
+                .map(|name| format!("/{}", name))
+                .collect::<Vec<_>>()
+                .join(", ");
+            entries.push(HelpEntry {
+                name,
+                value: describe(lang, description, spec.options),
+            });
+            continue;
+        }
+
+        for option in spec.options {
+            match option.kind {
+                OptionKind::SubCommand(options) => entries.push(HelpEntry {
+                    name: format!("/{} {}", spec.name, option.name),
+                    value: describe(
+                        lang,
+                        localize(lang, option.description, option.description_en),
+                        options,
+                    ),
+                }),
+                OptionKind::SubCommandGroup(subcommands) => {
+                    for subcommand in subcommands {
+                        let options = match subcommand.kind {
+                            OptionKind::SubCommand(options) => options,
+                            _ => &[],
+                        };
+                        entries.push(HelpEntry {
+                            name: format!("/{} {} {}", spec.name, option.name, subcommand.name),
+                            value: describe(
+                                lang,
+                                localize(lang, subcommand.description, subcommand.description_en),
+                                options,
+                            ),
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    if category == Category::Other {
+        for spec in registry::CONTEXT_MENUS.iter().map(|x| x.spec()) {
+            entries.push(HelpEntry {
+                name: messages::format(
+                    lang,
+                    Key::HelpContextMenu,
+                    &[("name", &localize(lang, spec.name, spec.name_en))],
+                ),
+                value: localize(lang, spec.description, spec.description_en).to_string(),
+            });
+        }
+    }
+
+    entries
+}
+
+/// コマンドの説明に、指定できるオプションの一覧を添える
+fn describe(lang: Language, description: &str, options: &[OptionSpec]) -> String {
+    if options.is_empty() {
+        return description.to_string();
+    }
+
+    let option_list = options
+        .iter()
+        .map(|option| {
+            let name = format!("`{}`", option.name);
+            if option.required {
+                messages::format(lang, Key::HelpRequiredOption, &[("name", &name)])
+            } else {
+                name
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    format!(
+        "{}\n{}",
+        description,
+        messages::format(lang, Key::HelpOptions, &[("options", &option_list)])
+    )
+}
+
+fn localize<'a>(lang: Language, ja: &'a str, en: &'a str) -> &'a str {
+    match lang {
+        Language::Japanese => ja,
+        Language::English => en,
+    }
+}
+
+fn category_label(category: Category) -> Key {
+    match category {
+        Category::Connection => Key::HelpCategoryConnection,
+        Category::Dictionary => Key::HelpCategoryDictionary,
+        Category::Voice => Key::HelpCategoryVoice,
+        Category::Other => Key::HelpCategoryOther,
+    }
+}
@@ -0,0 +1,202 @@
+use super::{
+    handler::{format_duration, respond, CommandResponse},
+    help,
+    model::Command,
+    parser::CommandParseError,
+    registry::{Category, CommandSpec, SlashCommand},
+    responder::ResponseMessage,
+};
+use crate::{
+    app_state,
+    messages::{self, Key},
+};
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+use koe_db::language::Language;
+use serenity::{
+    builder::CreateEmbed,
+    client::Context,
+    model::application::interaction::application_command::{
+        ApplicationCommandInteraction, CommandDataOption,
+    },
+};
+
+/// `/help`の定義
+const HELP: CommandSpec = CommandSpec {
+    name: "help",
+    description: "使い方を表示",
+    description_en: "Show how to use the bot",
+    category: Category::Other,
+    aliases: &[],
+    manage_guild_only: false,
+    dm_allowed: true,
+    options: &[],
+};
+
+/// `/version`の定義
+const VERSION: CommandSpec = CommandSpec {
+    name: "version",
+    description: "Botのバージョンと稼働状況を表示",
+    description_en: "Show the bot's version and runtime information",
+    category: Category::Other,
+    aliases: &[],
+    manage_guild_only: false,
+    dm_allowed: true,
+    options: &[],
+};
+
+pub(super) struct HelpCommand;
+
+#[async_trait]
+impl SlashCommand for HelpCommand {
+    fn spec(&self) -> &'static CommandSpec {
+        &HELP
+    }
+
+    fn parse(&self, _: &[CommandDataOption]) -> Result<Command, CommandParseError> {
+        Ok(Command::Help)
+    }
+
+    async fn run(
+        &self,
+        ctx: &Context,
+        cmd: &ApplicationCommandInteraction,
+        lang: Language,
+        _: Command,
+    ) -> Result<()> {
+        handle_help(ctx, cmd, lang)
+            .await
+            .context("Failed to execute /help")
+    }
+}
+
+pub(super) struct VersionCommand;
+
+#[async_trait]
+impl SlashCommand for VersionCommand {
+    fn spec(&self) -> &'static CommandSpec {
+        &VERSION
+    }
+
+    fn parse(&self, _: &[CommandDataOption]) -> Result<Command, CommandParseError> {
+        Ok(Command::Version)
+    }
+
+    async fn run(
+        &self,
+        ctx: &Context,
+        cmd: &ApplicationCommandInteraction,
+        lang: Language,
+        _: Command,
+    ) -> Result<()> {
+        handle_version(ctx, cmd, lang)
+            .await
+            .context("Failed to execute /version")
+    }
+}
+
+/// 使い方の説明のURL
+const USER_GUIDE_URL: &str = "https://github.com/ciffelia/koe/blob/main/docs/user_guide.md";
+
+async fn handle_version(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+) -> Result<()> {
+    let state = app_state::get(ctx).await?;
+
+    let mut embed = CreateEmbed::default();
+    embed.title(messages::format(
+        lang,
+        Key::VersionTitle,
+        &[("version", &env!("CARGO_PKG_VERSION"))],
+    ));
+    embed.field(
+        messages::text(lang, Key::VersionCommit),
+        format!("`{}`", env!("KOE_GIT_COMMIT")),
+        true,
+    );
+    // Discordのタイムスタンプ表記にして、閲覧するメンバーのタイムゾーンで表示する
+    embed.field(
+        messages::text(lang, Key::VersionBuiltAt),
+        format!("<t:{}:f>", env!("KOE_BUILD_TIMESTAMP")),
+        true,
+    );
+    embed.field(
+        messages::text(lang, Key::VersionLibraries),
+        format!(
+            "serenity {} / songbird {}",
+            env!("KOE_SERENITY_VERSION"),
+            env!("KOE_SONGBIRD_VERSION")
+        ),
+        false,
+    );
+    embed.field(
+        messages::text(lang, Key::VersionUptime),
+        format_duration(lang, state.started_at.elapsed()),
+        true,
+    );
+    embed.field(
+        messages::text(lang, Key::VersionGuilds),
+        ctx.cache.guild_count(),
+        true,
+    );
+    embed.field(
+        messages::text(lang, Key::VersionVoiceConnections),
+        state.connected_guild_states.len(),
+        true,
+    );
+
+    respond(
+        ctx,
+        cmd,
+        CommandResponse {
+            embeds: vec![embed],
+            ephemeral: true,
+            ..Default::default()
+        },
+    )
+    .await?;
+    Ok(())
+}
+
+async fn handle_help(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+) -> Result<()> {
+    let mut embed_groups = help::build_messages(lang).into_iter();
+
+    respond(
+        ctx,
+        cmd,
+        CommandResponse {
+            content: Some(messages::format(
+                lang,
+                Key::Help,
+                &[("url", &USER_GUIDE_URL)],
+            )),
+            embeds: embed_groups.next().unwrap_or_default(),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    // 1つのメッセージに収まらない分は、続けてフォローアップで送信する
+    let state = app_state::get(ctx).await?;
+    for embeds in embed_groups {
+        state
+            .responder
+            .create_followup(
+                ctx,
+                cmd,
+                ResponseMessage {
+                    embeds,
+                    ..Default::default()
+                },
+            )
+            .await?;
+    }
+
+    Ok(())
+}
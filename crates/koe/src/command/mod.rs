@@ -1,4 +1,25 @@
+mod admin;
+mod call;
+mod channels;
+pub mod confirm;
+mod context_menu;
+mod dict;
+pub mod dict_entry;
+mod dict_file;
+pub mod dict_view;
+mod filter;
 pub mod handler;
+mod help;
+mod info;
 mod model;
+mod mydict;
 mod parser;
+mod permission;
+mod registry;
+pub mod responder;
+mod settings;
+mod settings_view;
 pub mod setup;
+mod split;
+mod usage;
+mod voice;
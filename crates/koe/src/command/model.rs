@@ -1,14 +1,215 @@
+use super::dict_file::{ConflictPolicy, DictFileFormat};
+use koe_db::{
+    command_permission::Requirement, filter::FilterMode, furigana::FuriganaSyntax,
+    guild_settings::SettingKey, language::Language, mention_only::MentionOnlyMode,
+};
+use serenity::model::{
+    channel::{Attachment, Message},
+    id::{ChannelId, UserId},
+    user::User,
+};
+
 #[derive(Debug, Clone)]
 pub enum Command {
     Join,
     Leave,
     Skip,
-    Voice,
+    Move,
+    Summon,
+    Status,
+    ChannelsAdd(ChannelsOption),
+    ChannelsRemove(ChannelsOption),
+    ChannelsList,
+    VoiceSet(VoiceParamsOption),
+    VoiceInfo,
+    VoiceBoost(VoiceBoostOption),
+    VoiceRandomize(VoiceRandomizeOption),
+    VoiceLanguage(VoiceLanguageOption),
+    VoicePreviewUser(VoicePreviewUserOption),
     DictAdd(DictAddOption),
+    DictAddForm(DictAddFormOption),
     DictRemove(DictRemoveOption),
     DictView,
+    DictCount,
+    DictClear,
+    DictExport(DictExportOption),
+    DictImport(DictImportOption),
+    MyDictAdd(DictAddOption),
+    MyDictRemove(DictRemoveOption),
+    MyDictView,
+    FilterAdd(FilterAddOption),
+    FilterRemove(FilterRemoveOption),
+    FilterList,
+    FilterMode(FilterModeOption),
+    SettingsView,
+    SettingsSet(SettingsSetOption),
+    SettingsReset(SettingsResetOption),
+    SettingsAutoJoin(SettingsAutoJoinOption),
+    SettingsCollapseRepeats(SettingsCollapseRepeatsOption),
+    SettingsFurigana(SettingsFuriganaOption),
+    SettingsReadResponses(SettingsReadResponsesOption),
+    SettingsEmptyText(SettingsEmptyTextOption),
+    SettingsIdleTimeout(SettingsIdleTimeoutOption),
+    SettingsReadPrefix(SettingsReadPrefixOption),
+    SettingsProvider(SettingsProviderOption),
+    SettingsFarewell(SettingsFarewellOption),
+    SettingsMentionOnly(SettingsMentionOnlyOption),
+    SettingsLanguage(SettingsLanguageOption),
+    SettingsVoiceRange(SettingsVoiceRangeOption),
+    SettingsSystemVoice(VoiceParamsOption),
+    SettingsEmbedBotAdd(SettingsEmbedBotOption),
+    SettingsEmbedBotRemove(SettingsEmbedBotOption),
+    SettingsEmbedBotList,
+    SettingsPermissionsSet(SettingsPermissionsSetOption),
+    SettingsPermissionsView,
+    AdminStatus,
+    AdminInspect(AdminInspectOption),
+    AdminUsageLimit(AdminUsageLimitOption),
+    Usage,
     Help,
-    Unknown,
+    Version,
+    ReadMessage(ReadMessageOption),
+    ShowVoice(ShowVoiceOption),
+}
+
+impl Command {
+    /// 応答までに時間がかかる可能性があるコマンドかどうか
+    /// 該当するコマンドは、応答を保留してから結果を送信する
+    pub fn is_slow(&self) -> bool {
+        matches!(
+            self,
+            Command::Leave
+                | Command::DictView
+                | Command::DictCount
+                | Command::DictExport(_)
+                | Command::DictImport(_)
+                | Command::ReadMessage(_)
+                | Command::VoicePreviewUser(_)
+        )
+    }
+
+    /// 設定や状態を変更せず、表示するだけのコマンドかどうか
+    /// 実行回数の制限を緩くする
+    pub fn is_read_only(&self) -> bool {
+        matches!(
+            self,
+            Command::Status
+                | Command::ChannelsList
+                | Command::VoiceInfo
+                | Command::DictView
+                | Command::DictExport(_)
+                | Command::MyDictView
+                | Command::FilterList
+                | Command::SettingsView
+                | Command::SettingsEmbedBotList
+                | Command::SettingsPermissionsView
+                | Command::AdminStatus
+                | Command::AdminInspect(_)
+                | Command::Usage
+                | Command::Help
+                | Command::Version
+                | Command::ShowVoice(_)
+        )
+    }
+
+    /// 応答を実行者のみに表示するコマンドのうち、応答を保留するもの
+    /// 保留した応答の表示範囲は、保留した時点で決まる
+    pub fn is_ephemeral_when_deferred(&self) -> bool {
+        matches!(self, Command::ReadMessage(_) | Command::VoicePreviewUser(_))
+    }
+
+    /// 実行できるメンバーを`/settings permissions`で制限できるコマンドの名前
+    /// 制限できないコマンドの場合は[`None`]を返す
+    pub fn permission_name(&self) -> Option<&'static str> {
+        match self {
+            Command::Join => Some("join"),
+            Command::Leave => Some("leave"),
+            Command::Skip => Some("skip"),
+            Command::Move => Some("move"),
+            Command::Summon => Some("summon"),
+            Command::Status => Some("status"),
+            Command::ChannelsAdd(_) | Command::ChannelsRemove(_) | Command::ChannelsList => {
+                Some("channels")
+            }
+            Command::VoiceSet(_)
+            | Command::VoiceInfo
+            | Command::VoiceBoost(_)
+            | Command::VoiceRandomize(_)
+            | Command::VoiceLanguage(_)
+            | Command::VoicePreviewUser(_) => Some("voice"),
+            Command::DictAdd(_)
+            | Command::DictAddForm(_)
+            | Command::DictRemove(_)
+            | Command::DictView
+            | Command::DictCount
+            | Command::DictClear
+            | Command::DictExport(_)
+            | Command::DictImport(_) => Some("dict"),
+            Command::MyDictAdd(_) | Command::MyDictRemove(_) | Command::MyDictView => {
+                Some("mydict")
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReadMessageOption {
+    pub message: Box<Message>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ShowVoiceOption {
+    pub user: User,
+    /// サーバーでのニックネーム
+    pub nick: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ChannelsOption {
+    /// 省略された場合は[`None`]になり、コマンドを送信したチャンネルを対象とする
+    pub channel: Option<ChannelId>,
+}
+
+/// 声の設定、指定されなかった項目は[`None`]になる
+#[derive(Debug, Clone)]
+pub struct VoiceParamsOption {
+    pub preset: Option<i64>,
+    pub speed: Option<f64>,
+    pub pitch: Option<f64>,
+}
+
+impl VoiceParamsOption {
+    pub fn is_empty(&self) -> bool {
+        self.preset.is_none() && self.speed.is_none() && self.pitch.is_none()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VoiceBoostOption {
+    pub user: UserId,
+    /// 音量の倍率、1.0で元に戻す
+    pub amount: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct VoicePreviewUserOption {
+    /// 声を試聴するメンバー
+    pub user: UserId,
+}
+
+#[derive(Debug, Clone)]
+pub struct VoiceRandomizeOption {
+    /// 話速もランダムに決めるかどうか
+    pub speed: bool,
+    /// 音高もランダムに決めるかどうか
+    pub pitch: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct VoiceLanguageOption {
+    /// 読み上げに使う言語（例: en）、[`None`]の場合はサーバーで選択されたエンジンを使う
+    pub language: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -17,7 +218,158 @@ pub struct DictAddOption {
     pub read_as: String,
 }
 
+/// `/dict add`で語句と読み方の一方または両方が省略された場合に表示するフォームの初期値
+#[derive(Debug, Clone)]
+pub struct DictAddFormOption {
+    pub word: Option<String>,
+    pub read_as: Option<String>,
+}
+
 #[derive(Debug, Clone)]
 pub struct DictRemoveOption {
     pub word: String,
 }
+
+#[derive(Debug, Clone)]
+pub struct DictExportOption {
+    pub format: DictFileFormat,
+    /// CSVの先頭にBOMを付けるかどうか
+    pub bom: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct DictImportOption {
+    pub file: Attachment,
+    pub conflict: ConflictPolicy,
+}
+
+#[derive(Debug, Clone)]
+pub struct FilterAddOption {
+    pub word: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct FilterRemoveOption {
+    pub word: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct FilterModeOption {
+    pub mode: FilterMode,
+}
+
+#[derive(Debug, Clone)]
+pub struct SettingsAutoJoinOption {
+    /// 自動接続するボイスチャンネル、[`None`]の場合は自動接続を無効にする
+    pub voice_channel: Option<ChannelId>,
+    pub text_channel: Option<ChannelId>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SettingsCollapseRepeatsOption {
+    pub enabled: bool,
+    pub threshold: Option<i64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SettingsFuriganaOption {
+    pub enabled: bool,
+    /// 読み仮名を指定する書き方、[`None`]の場合は変更しない
+    pub syntax: Option<FuriganaSyntax>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SettingsReadResponsesOption {
+    pub enabled: bool,
+    /// エラーを伝える応答も読み上げるかどうか、[`None`]の場合は変更しない
+    pub errors: Option<bool>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SettingsIdleTimeoutOption {
+    /// 退出するまでの時間（分）、`0`の場合は自動で退出しない、[`None`]の場合はBot全体の設定に従う
+    pub minutes: Option<i64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AdminInspectOption {
+    /// 対象のサーバーのID、整数のオプションでは表せない大きさのため文字列で受け取る
+    pub guild_id: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct AdminUsageLimitOption {
+    /// 対象のサーバーのID、整数のオプションでは表せない大きさのため文字列で受け取る
+    pub guild_id: String,
+    /// 1か月に読み上げられる文字数、`0`の場合は上限をなくす
+    pub chars: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct SettingsEmptyTextOption {
+    /// 代わりに読み上げる文言、[`None`]の場合はメッセージを読み飛ばす
+    pub placeholder: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SettingsReadPrefixOption {
+    /// 読み上げるメッセージの先頭に付ける合図、[`None`]の場合はすべてのメッセージを読み上げる
+    pub prefix: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SettingsProviderOption {
+    /// 音声合成のエンジンの名前（例: voicevox）
+    pub name: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct SettingsFarewellOption {
+    /// 退出する前に読み上げる挨拶、[`None`]の場合は挨拶せずに退出する
+    pub text: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SettingsSetOption {
+    pub key: SettingKey,
+    /// 設定する値、設定項目の型に合わせた解釈は実行時に行う
+    pub value: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct SettingsResetOption {
+    pub key: SettingKey,
+}
+
+#[derive(Debug, Clone)]
+pub struct SettingsMentionOnlyOption {
+    pub mode: MentionOnlyMode,
+}
+
+#[derive(Debug, Clone)]
+pub struct SettingsLanguageOption {
+    /// 応答の言語、[`None`]の場合はDiscordの言語設定に合わせる
+    pub language: Option<Language>,
+}
+
+/// 省略された項目はシステム全体の範囲を使う
+/// すべて省略された場合は、サーバーでの設定を削除する
+#[derive(Debug, Clone)]
+pub struct SettingsVoiceRangeOption {
+    pub speed_min: Option<f64>,
+    pub speed_max: Option<f64>,
+    pub pitch_min: Option<f64>,
+    pub pitch_max: Option<f64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SettingsEmbedBotOption {
+    pub bot: UserId,
+}
+
+#[derive(Debug, Clone)]
+pub struct SettingsPermissionsSetOption {
+    /// [`Command::permission_name`]で返される名前
+    pub command: String,
+    pub requirement: Requirement,
+}
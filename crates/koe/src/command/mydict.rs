@@ -0,0 +1,242 @@
+use super::{
+    handler::{
+        r_ephemeral, respond, sanitize_response, summarize_lines, CommandResponse,
+        MAX_EMBED_DESCRIPTION_LENGTH,
+    },
+    model::{Command, DictAddOption, DictRemoveOption},
+    parser::{find_string, find_subcommand, required, unknown_subcommand, CommandParseError},
+    registry::{subcommand, Category, CommandSpec, OptionSpec, SlashCommand, STRING},
+};
+use crate::{
+    app_state,
+    messages::{self, Key},
+};
+use anyhow::{bail, Context as _, Result};
+use async_trait::async_trait;
+use koe_db::{language::Language, user_dict};
+use serenity::{
+    builder::CreateEmbed,
+    client::Context,
+    model::application::interaction::application_command::{
+        ApplicationCommandInteraction, CommandDataOption,
+    },
+};
+
+/// `/mydict`の定義
+const SPEC: CommandSpec = CommandSpec {
+    name: "mydict",
+    description: "自分のメッセージにだけ使う読み上げ辞書の閲覧と編集",
+    description_en: "View and edit your personal dictionary used only for your messages",
+    category: Category::Dictionary,
+    aliases: &[],
+    manage_guild_only: false,
+    dm_allowed: true,
+    options: &[
+        subcommand(
+            "add",
+            "自分の辞書に項目を追加",
+            "Add a word to your dictionary",
+            &[
+                OptionSpec::new(
+                    "word",
+                    "読み方を指定したい語句",
+                    "Word to set the reading of",
+                    STRING,
+                )
+                .required(),
+                OptionSpec::new("read-as", "語句の読み方", "Reading of the word", STRING)
+                    .required(),
+            ],
+        ),
+        subcommand(
+            "remove",
+            "自分の辞書から項目を削除",
+            "Remove a word from your dictionary",
+            &[OptionSpec::new("word", "削除したい語句", "Word to remove", STRING).required()],
+        ),
+        subcommand("view", "自分の辞書を表示", "Show your dictionary", &[]),
+    ],
+};
+
+pub(super) struct MyDictCommand;
+
+#[async_trait]
+impl SlashCommand for MyDictCommand {
+    fn spec(&self) -> &'static CommandSpec {
+        &SPEC
+    }
+
+    fn parse(&self, options: &[CommandDataOption]) -> Result<Command, CommandParseError> {
+        parse_mydict(find_subcommand("mydict", options)?)
+    }
+
+    async fn run(
+        &self,
+        ctx: &Context,
+        cmd: &ApplicationCommandInteraction,
+        lang: Language,
+        command: Command,
+    ) -> Result<()> {
+        match command {
+            Command::MyDictAdd(option) => handle_add(ctx, cmd, lang, option)
+                .await
+                .context("Failed to execute /mydict add"),
+            Command::MyDictRemove(option) => handle_remove(ctx, cmd, lang, option)
+                .await
+                .context("Failed to execute /mydict remove"),
+            Command::MyDictView => handle_view(ctx, cmd, lang)
+                .await
+                .context("Failed to execute /mydict view"),
+            command => bail!("Unexpected command for /mydict: {:?}", command),
+        }
+    }
+}
+
+async fn handle_add(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+    option: DictAddOption,
+) -> Result<()> {
+    let state = app_state::get(ctx).await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+
+    let resp = user_dict::insert(
+        &mut conn,
+        user_dict::InsertOption {
+            user_id: cmd.user.id.into(),
+            word: option.word.clone(),
+            read_as: option.read_as.clone(),
+        },
+    )
+    .await?;
+    state.user_dict_cache.invalidate(cmd.user.id);
+
+    let msg = match resp {
+        user_dict::InsertResponse::Success => messages::format(
+            lang,
+            Key::MyDictAdded,
+            &[
+                ("word", &sanitize_response(&option.word)),
+                ("read_as", &sanitize_response(&option.read_as)),
+            ],
+        ),
+        user_dict::InsertResponse::WordAlreadyExists => messages::format(
+            lang,
+            Key::MyDictWordExists,
+            &[("word", &sanitize_response(&option.word))],
+        ),
+        user_dict::InsertResponse::TooManyEntries => messages::format(
+            lang,
+            Key::MyDictTooMany,
+            &[("max", &user_dict::MAX_ENTRIES)],
+        ),
+    };
+    r_ephemeral(ctx, cmd, msg).await?;
+    Ok(())
+}
+
+async fn handle_remove(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+    option: DictRemoveOption,
+) -> Result<()> {
+    let state = app_state::get(ctx).await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+
+    let resp = user_dict::remove(
+        &mut conn,
+        user_dict::RemoveOption {
+            user_id: cmd.user.id.into(),
+            word: option.word.clone(),
+        },
+    )
+    .await?;
+    state.user_dict_cache.invalidate(cmd.user.id);
+
+    let msg = match resp {
+        user_dict::RemoveResponse::Success => messages::format(
+            lang,
+            Key::MyDictRemoved,
+            &[("word", &sanitize_response(&option.word))],
+        ),
+        user_dict::RemoveResponse::WordDoesNotExist => messages::format(
+            lang,
+            Key::MyDictWordNotFound,
+            &[("word", &sanitize_response(&option.word))],
+        ),
+    };
+    r_ephemeral(ctx, cmd, msg).await?;
+    Ok(())
+}
+
+async fn handle_view(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+) -> Result<()> {
+    let state = app_state::get(ctx).await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+
+    let dict = user_dict::get_all(
+        &mut conn,
+        user_dict::GetAllOption {
+            user_id: cmd.user.id.into(),
+        },
+    )
+    .await?;
+
+    if dict.is_empty() {
+        r_ephemeral(ctx, cmd, messages::text(lang, Key::MyDictEmpty)).await?;
+        return Ok(());
+    }
+
+    let lines = dict
+        .iter()
+        .map(|(word, read_as)| {
+            format!(
+                "{} → {}",
+                sanitize_response(word),
+                sanitize_response(read_as)
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let mut embed = CreateEmbed::default();
+    embed.title(messages::format(
+        lang,
+        Key::MyDictTitle,
+        &[("name", &cmd.user.name)],
+    ));
+    embed.description(summarize_lines(lang, &lines, MAX_EMBED_DESCRIPTION_LENGTH));
+
+    respond(
+        ctx,
+        cmd,
+        CommandResponse {
+            embeds: vec![embed],
+            ephemeral: true,
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    Ok(())
+}
+
+fn parse_mydict(option_mydict: &CommandDataOption) -> Result<Command, CommandParseError> {
+    let options = &option_mydict.options;
+
+    match option_mydict.name.as_str() {
+        "add" => Ok(Command::MyDictAdd(DictAddOption {
+            word: required(find_string(options, "word")?, "word")?,
+            read_as: required(find_string(options, "read-as")?, "read-as")?,
+        })),
+        "remove" => Ok(Command::MyDictRemove(DictRemoveOption {
+            word: required(find_string(options, "word")?, "word")?,
+        })),
+        "view" => Ok(Command::MyDictView),
+        _ => Err(unknown_subcommand("mydict", option_mydict)),
+    }
+}
@@ -1,63 +1,346 @@
-use super::model::{Command, DictAddOption, DictRemoveOption};
-use serenity::model::application::interaction::application_command::{
-    ApplicationCommandInteraction, CommandDataOptionValue,
+use super::{model::Command, registry::Registered};
+use serenity::model::{
+    application::{
+        command::CommandOptionType,
+        interaction::application_command::{
+            ApplicationCommandInteraction, CommandDataOption, CommandDataOptionValue,
+        },
+    },
+    channel::Attachment,
+    id::{ChannelId, RoleId, UserId},
 };
+use std::fmt;
 
-pub fn parse(cmd: &ApplicationCommandInteraction) -> Command {
-    match cmd.data.name.as_str() {
-        "join" | "kjoin" => Command::Join,
-        "leave" | "kleave" => Command::Leave,
-        "skip" | "kskip" => Command::Skip,
-        "voice" => Command::Voice,
-        "dict" => parse_dict(cmd),
-        "help" => Command::Help,
-        _ => Command::Unknown,
+/// コマンドを解釈できなかった理由
+#[derive(Debug, Clone, PartialEq)]
+pub enum CommandParseError {
+    /// 存在しないコマンド
+    UnknownCommand { name: String },
+    /// 存在しないサブコマンド、サブコマンドが指定されなかった場合は`received`が[`None`]になる
+    UnknownSubcommand {
+        command: String,
+        received: Option<String>,
+    },
+    /// 必須のオプションが指定されなかった
+    MissingOption { name: String },
+    /// オプションの型が異なる
+    WrongType {
+        name: String,
+        expected: CommandOptionType,
+        actual: CommandOptionType,
+    },
+    /// オプションの値が選択肢のいずれでもない
+    InvalidValue { name: String, value: String },
+}
+
+impl fmt::Display for CommandParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandParseError::UnknownCommand { name } => write!(f, "Unknown command: {}", name),
+            CommandParseError::UnknownSubcommand { command, received } => write!(
+                f,
+                "Unknown subcommand of {}: {}",
+                command,
+                received.as_deref().unwrap_or("(none)")
+            ),
+            CommandParseError::MissingOption { name } => write!(f, "Missing option: {}", name),
+            CommandParseError::WrongType {
+                name,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Wrong type for option {}: expected {:?}, got {:?}",
+                name, expected, actual
+            ),
+            CommandParseError::InvalidValue { name, value } => {
+                write!(f, "Invalid value for option {}: {}", name, value)
+            }
+        }
     }
 }
 
-fn parse_dict(cmd: &ApplicationCommandInteraction) -> Command {
-    let option_dict = match cmd.data.options.get(0) {
+impl std::error::Error for CommandParseError {}
+
+impl TryFrom<&ApplicationCommandInteraction> for Command {
+    type Error = CommandParseError;
+
+    fn try_from(cmd: &ApplicationCommandInteraction) -> Result<Self, Self::Error> {
+        Registered::find(&cmd.data)?.parse(&cmd.data)
+    }
+}
+
+/// 指定されたサブコマンド（またはサブコマンドグループ）を返す
+pub(super) fn find_subcommand<'a>(
+    command: &str,
+    options: &'a [CommandDataOption],
+) -> Result<&'a CommandDataOption, CommandParseError> {
+    options
+        .first()
+        .filter(|option| {
+            matches!(
+                option.kind,
+                CommandOptionType::SubCommand | CommandOptionType::SubCommandGroup
+            )
+        })
+        .ok_or_else(|| CommandParseError::UnknownSubcommand {
+            command: command.to_string(),
+            received: None,
+        })
+}
+
+pub(super) fn unknown_subcommand(command: &str, option: &CommandDataOption) -> CommandParseError {
+    CommandParseError::UnknownSubcommand {
+        command: command.to_string(),
+        received: Some(option.name.clone()),
+    }
+}
+
+pub(super) fn missing(name: &str) -> CommandParseError {
+    CommandParseError::MissingOption {
+        name: name.to_string(),
+    }
+}
+
+pub(super) fn invalid(name: &str, value: String) -> CommandParseError {
+    CommandParseError::InvalidValue {
+        name: name.to_string(),
+        value,
+    }
+}
+
+/// 必須のオプションが指定されていることを確かめる
+pub(super) fn required<T>(value: Option<T>, name: &str) -> Result<T, CommandParseError> {
+    value.ok_or_else(|| missing(name))
+}
+
+/// オプションの値を取り出す
+/// 指定されていない場合は[`None`]を、型が異なる場合はエラーを返す
+fn find_option<'a, T>(
+    options: &'a [CommandDataOption],
+    name: &str,
+    expected: CommandOptionType,
+    extract: impl FnOnce(&'a CommandDataOptionValue) -> Option<T>,
+) -> Result<Option<T>, CommandParseError> {
+    let option = match options.iter().find(|x| x.name == name) {
         Some(option) => option,
-        None => return Command::Unknown,
+        None => return Ok(None),
     };
+    let value = option.resolved.as_ref().ok_or_else(|| missing(name))?;
+
+    match extract(value) {
+        Some(x) => Ok(Some(x)),
+        None => Err(CommandParseError::WrongType {
+            name: name.to_string(),
+            expected,
+            actual: option.kind,
+        }),
+    }
+}
+
+pub(super) fn find_string(
+    options: &[CommandDataOption],
+    name: &str,
+) -> Result<Option<String>, CommandParseError> {
+    find_option(options, name, CommandOptionType::String, |x| match x {
+        CommandDataOptionValue::String(x) => Some(x.clone()),
+        _ => None,
+    })
+}
+
+pub(super) fn find_integer(
+    options: &[CommandDataOption],
+    name: &str,
+) -> Result<Option<i64>, CommandParseError> {
+    find_option(options, name, CommandOptionType::Integer, |x| match x {
+        CommandDataOptionValue::Integer(x) => Some(*x),
+        _ => None,
+    })
+}
+
+pub(super) fn find_number(
+    options: &[CommandDataOption],
+    name: &str,
+) -> Result<Option<f64>, CommandParseError> {
+    find_option(options, name, CommandOptionType::Number, |x| match x {
+        CommandDataOptionValue::Number(x) => Some(*x),
+        _ => None,
+    })
+}
+
+pub(super) fn find_boolean(
+    options: &[CommandDataOption],
+    name: &str,
+) -> Result<Option<bool>, CommandParseError> {
+    find_option(options, name, CommandOptionType::Boolean, |x| match x {
+        CommandDataOptionValue::Boolean(x) => Some(*x),
+        _ => None,
+    })
+}
+
+pub(super) fn find_user(
+    options: &[CommandDataOption],
+    name: &str,
+) -> Result<Option<UserId>, CommandParseError> {
+    find_option(options, name, CommandOptionType::User, |x| match x {
+        CommandDataOptionValue::User(user, _) => Some(user.id),
+        _ => None,
+    })
+}
+
+pub(super) fn find_role(
+    options: &[CommandDataOption],
+    name: &str,
+) -> Result<Option<RoleId>, CommandParseError> {
+    find_option(options, name, CommandOptionType::Role, |x| match x {
+        CommandDataOptionValue::Role(role) => Some(role.id),
+        _ => None,
+    })
+}
+
+pub(super) fn find_channel(
+    options: &[CommandDataOption],
+    name: &str,
+) -> Result<Option<ChannelId>, CommandParseError> {
+    find_option(options, name, CommandOptionType::Channel, |x| match x {
+        CommandDataOptionValue::Channel(channel) => Some(channel.id),
+        _ => None,
+    })
+}
 
-    match option_dict.name.as_str() {
-        "add" => {
-            let option_word = match option_dict.options.get(0) {
-                Some(x) => x,
-                None => return Command::Unknown,
-            };
-            let option_read_as = match option_dict.options.get(1) {
-                Some(x) => x,
-                None => return Command::Unknown,
-            };
-            let word = match &option_word.resolved {
-                Some(CommandDataOptionValue::String(x)) => x,
-                _ => return Command::Unknown,
-            };
-            let read_as = match &option_read_as.resolved {
-                Some(CommandDataOptionValue::String(x)) => x,
-                _ => return Command::Unknown,
-            };
-
-            Command::DictAdd(DictAddOption {
-                word: word.clone(),
-                read_as: read_as.clone(),
+pub(super) fn find_attachment(
+    options: &[CommandDataOption],
+    name: &str,
+) -> Result<Option<Attachment>, CommandParseError> {
+    find_option(options, name, CommandOptionType::Attachment, |x| match x {
+        CommandDataOptionValue::Attachment(x) => Some(x.clone()),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+    use serenity::model::application::interaction::application_command::CommandData;
+
+    fn command_data(name: &str, options: Value) -> CommandData {
+        serde_json::from_value(json!({
+            "id": "100",
+            "name": name,
+            "type": 1,
+            "options": options,
+        }))
+        .unwrap()
+    }
+
+    fn parse(name: &str, options: Value) -> Result<Command, CommandParseError> {
+        let data = command_data(name, options);
+        Registered::find(&data)?.parse(&data)
+    }
+
+    fn subcommand(name: &str, options: Value) -> Value {
+        json!([{ "name": name, "type": 1, "options": options }])
+    }
+
+    #[test]
+    fn missing_option() {
+        assert_eq!(
+            parse("dict", subcommand("remove", json!([]))).err(),
+            Some(CommandParseError::MissingOption {
+                name: "word".to_string()
             })
-        }
-        "remove" => {
-            let option_word = match option_dict.options.get(0) {
-                Some(x) => x,
-                None => return Command::Unknown,
-            };
-            let word = match &option_word.resolved {
-                Some(CommandDataOptionValue::String(x)) => x,
-                _ => return Command::Unknown,
-            };
-
-            Command::DictRemove(DictRemoveOption { word: word.clone() })
-        }
-        "view" => Command::DictView,
-        _ => Command::Unknown,
+        );
+
+        // IDが指定されていても、その対象が解決できない場合は指定されていないものとして扱う
+        let data = command_data("test", json!([{ "name": "user", "type": 6, "value": "1" }]));
+        assert_eq!(
+            find_user(&data.options, "user").err(),
+            Some(CommandParseError::MissingOption {
+                name: "user".to_string()
+            })
+        );
+        assert_eq!(find_user(&data.options, "other"), Ok(None));
+    }
+
+    #[test]
+    fn wrong_type() {
+        let options = json!([{ "name": "word", "type": 4, "value": 5 }]);
+        assert_eq!(
+            parse("dict", subcommand("remove", options)).err(),
+            Some(CommandParseError::WrongType {
+                name: "word".to_string(),
+                expected: CommandOptionType::String,
+                actual: CommandOptionType::Integer,
+            })
+        );
+
+        let data = command_data(
+            "test",
+            json!([{ "name": "speed", "type": 5, "value": true }]),
+        );
+        assert_eq!(
+            find_number(&data.options, "speed").err(),
+            Some(CommandParseError::WrongType {
+                name: "speed".to_string(),
+                expected: CommandOptionType::Number,
+                actual: CommandOptionType::Boolean,
+            })
+        );
+    }
+
+    #[test]
+    fn unknown_subcommand() {
+        assert_eq!(
+            parse("dict", subcommand("rename", json!([]))).err(),
+            Some(CommandParseError::UnknownSubcommand {
+                command: "dict".to_string(),
+                received: Some("rename".to_string()),
+            })
+        );
+
+        // サブコマンドの代わりに通常のオプションが送られた場合
+        let options = json!([{ "name": "word", "type": 3, "value": "テスト" }]);
+        assert_eq!(
+            parse("dict", options).err(),
+            Some(CommandParseError::UnknownSubcommand {
+                command: "dict".to_string(),
+                received: None,
+            })
+        );
+    }
+
+    #[test]
+    fn invalid_value() {
+        let options = json!([{ "name": "mode", "type": 3, "value": "unknown" }]);
+        assert_eq!(
+            parse("filter", subcommand("mode", options)).err(),
+            Some(CommandParseError::InvalidValue {
+                name: "mode".to_string(),
+                value: "unknown".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn valid_options_are_extracted() {
+        let data = command_data(
+            "test",
+            json!([
+                { "name": "word", "type": 3, "value": "テスト" },
+                { "name": "count", "type": 4, "value": 3 },
+                { "name": "speed", "type": 10, "value": 1.5 },
+                { "name": "enabled", "type": 5, "value": false },
+            ]),
+        );
+        let options = &data.options;
+        assert_eq!(find_string(options, "word"), Ok(Some("テスト".to_string())));
+        assert_eq!(find_integer(options, "count"), Ok(Some(3)));
+        assert_eq!(find_number(options, "speed"), Ok(Some(1.5)));
+        assert_eq!(find_boolean(options, "enabled"), Ok(Some(false)));
+        assert_eq!(
+            required(find_string(options, "word").unwrap(), "word"),
+            Ok("テスト".to_string())
+        );
     }
 }
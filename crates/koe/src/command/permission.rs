@@ -0,0 +1,84 @@
+use super::model::Command;
+use crate::app_state;
+use anyhow::{Context as _, Result};
+use koe_db::command_permission::{self, Requirement};
+use serenity::{
+    client::Context,
+    model::{
+        application::interaction::application_command::ApplicationCommandInteraction, id::RoleId,
+        Permissions,
+    },
+};
+
+/// 実行できるメンバーを`/settings permissions`で制限できるコマンドの名前
+/// [`Command::permission_name`]が返す名前と一致させる
+pub const PERMISSION_COMMAND_NAMES: [&str; 10] = [
+    "join", "leave", "skip", "move", "summon", "status", "channels", "voice", "dict", "mydict",
+];
+
+/// コマンドを送信したメンバーが、コマンドを使うための条件を満たしているかを確かめる
+/// 満たしていない場合は、満たしていない条件を返す
+///
+/// 「サーバー管理」の権限を持つメンバーは、設定にかかわらずすべてのコマンドを使える
+pub async fn unmet_requirement(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    command: &Command,
+) -> Result<Option<Requirement>> {
+    let (guild_id, member) = match (cmd.guild_id, &cmd.member) {
+        (Some(guild_id), Some(member)) => (guild_id, member),
+        _ => return Ok(None),
+    };
+    let name = match command.permission_name() {
+        Some(name) => name,
+        None => return Ok(None),
+    };
+
+    // サーバーの管理者は常に実行できるため、Redisに問い合わせない
+    let is_manager = member
+        .permissions
+        .is_some_and(|permissions| permissions.contains(Permissions::MANAGE_GUILD));
+    if is_manager {
+        return Ok(None);
+    }
+
+    let state = app_state::get(ctx).await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+
+    let requirement = command_permission::get(
+        &mut conn,
+        command_permission::GetOption {
+            guild_id: guild_id.into(),
+            command: name.to_string(),
+        },
+    )
+    .await?;
+
+    let satisfied = match requirement {
+        Requirement::Everyone => true,
+        Requirement::InVoiceChannel => {
+            let guild = guild_id
+                .to_guild_cached(&ctx.cache)
+                .context("Failed to find guild in the cache")?;
+            let user_channel = guild
+                .voice_states
+                .get(&cmd.user.id)
+                .and_then(|voice_state| voice_state.channel_id);
+            let bot_channel = state
+                .connected_guild_states
+                .get(&guild_id)
+                .map(|guild_state| guild_state.voice_channel);
+
+            // 接続していない場合は、いずれかのボイスチャンネルにいればよい
+            match (user_channel, bot_channel) {
+                (Some(user_channel), Some(bot_channel)) => user_channel == bot_channel,
+                (Some(_), None) => true,
+                (None, _) => false,
+            }
+        }
+        Requirement::Role(role_id) => member.roles.contains(&RoleId(role_id)),
+        Requirement::ManageGuild => false,
+    };
+
+    Ok(if satisfied { None } else { Some(requirement) })
+}
@@ -0,0 +1,793 @@
+use super::{
+    admin::AdminCommand,
+    call::{JoinCommand, LeaveCommand, MoveCommand, SkipCommand, StatusCommand, SummonCommand},
+    channels::ChannelsCommand,
+    context_menu::{ReadMessageCommand, ShowVoiceCommand},
+    dict::DictCommand,
+    filter::FilterCommand,
+    info::{HelpCommand, VersionCommand},
+    model::Command,
+    mydict::MyDictCommand,
+    parser::CommandParseError,
+    settings::SettingsCommand,
+    usage::UsageCommand,
+    voice::VoiceCommand,
+};
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use koe_db::language::Language;
+use koe_speech::speech::{PITCH_SCALE_RANGE, SPEED_SCALE_RANGE};
+use serenity::{
+    client::Context,
+    model::{
+        application::{
+            command::CommandType,
+            interaction::application_command::{
+                ApplicationCommandInteraction, CommandData, CommandDataOption,
+            },
+        },
+        channel::ChannelType,
+    },
+};
+use std::collections::HashSet;
+
+/// `/help`でコマンドをまとめて表示する分類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    Connection,
+    Dictionary,
+    Voice,
+    Other,
+}
+
+impl Category {
+    pub const ALL: [Category; 4] = [
+        Category::Connection,
+        Category::Dictionary,
+        Category::Voice,
+        Category::Other,
+    ];
+}
+
+/// スラッシュコマンドの定義
+/// コマンドの登録と`/help`の表示の両方に使う
+#[derive(Debug)]
+pub struct CommandSpec {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub description_en: &'static str,
+    pub category: Category,
+    /// 同じ動作をする別名のコマンド（`/kjoin`など）
+    pub aliases: &'static [&'static str],
+    /// 「サーバー管理」の権限を持つメンバーのみに表示するかどうか
+    pub manage_guild_only: bool,
+    /// DMでも使えるかどうか、サーバーに依存しないコマンドのみ`true`にする
+    pub dm_allowed: bool,
+    pub options: &'static [OptionSpec],
+}
+
+/// コマンドのオプション（サブコマンドを含む）の定義
+#[derive(Debug)]
+pub struct OptionSpec {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub description_en: &'static str,
+    pub kind: OptionKind,
+    pub required: bool,
+}
+
+impl OptionSpec {
+    pub(super) const fn new(
+        name: &'static str,
+        description: &'static str,
+        description_en: &'static str,
+        kind: OptionKind,
+    ) -> Self {
+        Self {
+            name,
+            description,
+            description_en,
+            kind,
+            required: false,
+        }
+    }
+
+    pub(super) const fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+}
+
+#[derive(Debug)]
+pub enum OptionKind {
+    SubCommand(&'static [OptionSpec]),
+    SubCommandGroup(&'static [OptionSpec]),
+    String {
+        choices: &'static [Choice],
+        autocomplete: bool,
+    },
+    Integer {
+        min: Option<i64>,
+        max: Option<i64>,
+        autocomplete: bool,
+    },
+    Number {
+        min: Option<f64>,
+        max: Option<f64>,
+    },
+    Boolean,
+    User,
+    Role,
+    Channel(&'static [ChannelType]),
+    Attachment,
+}
+
+/// 文字列のオプションの選択肢
+#[derive(Debug)]
+pub struct Choice {
+    pub name: &'static str,
+    /// 英語の言語設定で表示する名前、[`None`]の場合は`name`をそのまま表示する
+    pub name_en: Option<&'static str>,
+    pub value: &'static str,
+}
+
+impl Choice {
+    pub(super) const fn new(name: &'static str, value: &'static str) -> Self {
+        Self {
+            name,
+            name_en: None,
+            value,
+        }
+    }
+
+    pub(super) const fn localized(
+        name: &'static str,
+        name_en: &'static str,
+        value: &'static str,
+    ) -> Self {
+        Self {
+            name,
+            name_en: Some(name_en),
+            value,
+        }
+    }
+}
+
+/// コンテキストメニューに表示するコマンドの定義
+#[derive(Debug)]
+pub struct ContextMenuSpec {
+    pub name: &'static str,
+    pub name_en: &'static str,
+    pub kind: CommandType,
+    /// `/help`に表示する説明
+    pub description: &'static str,
+    pub description_en: &'static str,
+}
+
+pub(super) const fn subcommand(
+    name: &'static str,
+    description: &'static str,
+    description_en: &'static str,
+    options: &'static [OptionSpec],
+) -> OptionSpec {
+    OptionSpec::new(
+        name,
+        description,
+        description_en,
+        OptionKind::SubCommand(options),
+    )
+}
+
+pub(super) const fn group(
+    name: &'static str,
+    description: &'static str,
+    description_en: &'static str,
+    subcommands: &'static [OptionSpec],
+) -> OptionSpec {
+    OptionSpec::new(
+        name,
+        description,
+        description_en,
+        OptionKind::SubCommandGroup(subcommands),
+    )
+}
+
+pub(super) const STRING: OptionKind = OptionKind::String {
+    choices: &[],
+    autocomplete: false,
+};
+
+pub(super) const TEXT_CHANNEL: OptionKind = OptionKind::Channel(&[ChannelType::Text]);
+
+pub(super) const SPEED_SCALE: OptionKind = OptionKind::Number {
+    min: Some(*SPEED_SCALE_RANGE.start()),
+    max: Some(*SPEED_SCALE_RANGE.end()),
+};
+
+pub(super) const PITCH_SCALE: OptionKind = OptionKind::Number {
+    min: Some(*PITCH_SCALE_RANGE.start()),
+    max: Some(*PITCH_SCALE_RANGE.end()),
+};
+
+/// `/voice set`と`/settings system-voice`で指定する声の設定
+pub(super) const VOICE_PARAMS_OPTIONS: &[OptionSpec] = &[
+    OptionSpec::new(
+        "preset",
+        "声のプリセットID",
+        "Voice preset ID",
+        OptionKind::Integer {
+            min: None,
+            max: None,
+            autocomplete: true,
+        },
+    ),
+    OptionSpec::new("speed", "話速", "Speed", SPEED_SCALE),
+    OptionSpec::new("pitch", "音高", "Pitch", PITCH_SCALE),
+];
+
+/// スラッシュコマンド
+/// 定義・オプションの解釈・実行をまとめ、[`COMMANDS`]に並べることで登録・`/help`・実行の対象になる
+#[async_trait]
+pub trait SlashCommand: Sync {
+    /// コマンドの名前・説明・オプションの定義
+    fn spec(&self) -> &'static CommandSpec;
+
+    /// 指定されたオプションを解釈する
+    fn parse(&self, options: &[CommandDataOption]) -> Result<Command, CommandParseError>;
+
+    /// [`SlashCommand::parse`]で解釈したコマンドを実行する
+    async fn run(
+        &self,
+        ctx: &Context,
+        cmd: &ApplicationCommandInteraction,
+        lang: Language,
+        command: Command,
+    ) -> Result<()>;
+}
+
+/// コンテキストメニューのコマンド
+#[async_trait]
+pub trait ContextMenuCommand: Sync {
+    fn spec(&self) -> &'static ContextMenuSpec;
+
+    /// 対象のメッセージまたはユーザーを取り出す
+    fn parse(&self, data: &CommandData) -> Result<Command, CommandParseError>;
+
+    async fn run(
+        &self,
+        ctx: &Context,
+        cmd: &ApplicationCommandInteraction,
+        lang: Language,
+        command: Command,
+    ) -> Result<()>;
+}
+
+/// 登録するスラッシュコマンド、`/help`ではこの順に表示する
+pub const COMMANDS: &[&dyn SlashCommand] = &[
+    &JoinCommand,
+    &LeaveCommand,
+    &SkipCommand,
+    &MoveCommand,
+    &SummonCommand,
+    &ChannelsCommand,
+    &StatusCommand,
+    &VoiceCommand,
+    &DictCommand,
+    &MyDictCommand,
+    &FilterCommand,
+    &SettingsCommand,
+    &AdminCommand,
+    &UsageCommand,
+    &HelpCommand,
+    &VersionCommand,
+];
+
+/// 登録するコンテキストメニューのコマンド
+pub const CONTEXT_MENUS: &[&dyn ContextMenuCommand] = &[&ReadMessageCommand, &ShowVoiceCommand];
+
+/// 実行されたコマンドに対応する、登録済みのコマンド
+#[derive(Clone, Copy)]
+pub enum Registered {
+    Slash(&'static dyn SlashCommand),
+    ContextMenu(&'static dyn ContextMenuCommand),
+}
+
+impl Registered {
+    /// コマンドの種類と名前（別名を含む）から、登録済みのコマンドを探す
+    pub fn find(data: &CommandData) -> Result<Self, CommandParseError> {
+        let name = data.name.as_str();
+        let found = match data.kind {
+            CommandType::ChatInput => COMMANDS
+                .iter()
+                .find(|command| {
+                    let spec = command.spec();
+                    spec.name == name || spec.aliases.contains(&name)
+                })
+                .map(|command| Registered::Slash(*command)),
+            kind => CONTEXT_MENUS
+                .iter()
+                .find(|command| command.spec().kind == kind && command.spec().name == name)
+                .map(|command| Registered::ContextMenu(*command)),
+        };
+
+        found.ok_or_else(|| CommandParseError::UnknownCommand {
+            name: name.to_string(),
+        })
+    }
+
+    pub fn parse(&self, data: &CommandData) -> Result<Command, CommandParseError> {
+        match self {
+            Registered::Slash(command) => command.parse(&data.options),
+            Registered::ContextMenu(command) => command.parse(data),
+        }
+    }
+
+    /// DMでも使えるかどうか
+    /// コンテキストメニューのコマンドはDMでは使えない
+    pub fn allows_dm(&self) -> bool {
+        match self {
+            Registered::Slash(command) => command.spec().dm_allowed,
+            Registered::ContextMenu(_) => false,
+        }
+    }
+
+    pub async fn run(
+        &self,
+        ctx: &Context,
+        cmd: &ApplicationCommandInteraction,
+        lang: Language,
+        command: Command,
+    ) -> Result<()> {
+        match self {
+            Registered::Slash(x) => x.run(ctx, cmd, lang, command).await,
+            Registered::ContextMenu(x) => x.run(ctx, cmd, lang, command).await,
+        }
+    }
+}
+
+/// 定義に重複した名前がないことを確かめる
+/// Discordは名前が重複したコマンドの登録を拒否するため、登録する前に原因を特定できるようにする
+pub fn validate() -> Result<()> {
+    let commands = COMMANDS.iter().map(|x| x.spec()).collect::<Vec<_>>();
+    let context_menus = CONTEXT_MENUS.iter().map(|x| x.spec()).collect::<Vec<_>>();
+    validate_specs(&commands, &context_menus)
+}
+
+fn validate_specs(commands: &[&CommandSpec], context_menus: &[&ContextMenuSpec]) -> Result<()> {
+    let mut names = HashSet::new();
+    for name in commands
+        .iter()
+        .flat_map(|spec| std::iter::once(spec.name).chain(spec.aliases.iter().copied()))
+    {
+        if !names.insert(name) {
+            bail!("Duplicate command name: {}", name);
+        }
+    }
+
+    for spec in commands {
+        validate_options(spec.name, spec.options)?;
+    }
+
+    let mut context_menu_names = HashSet::new();
+    for spec in context_menus {
+        if !context_menu_names.insert((spec.kind, spec.name)) {
+            bail!("Duplicate context menu name: {}", spec.name);
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_options(path: &str, options: &[OptionSpec]) -> Result<()> {
+    let mut names = HashSet::new();
+    for option in options {
+        if !names.insert(option.name) {
+            bail!("Duplicate option name in /{}: {}", path, option.name);
+        }
+
+        match option.kind {
+            OptionKind::SubCommand(options) | OptionKind::SubCommandGroup(options) => {
+                validate_options(&format!("{} {}", path, option.name), options)?;
+            }
+            OptionKind::String { choices, .. } => {
+                let mut values = HashSet::new();
+                for choice in choices {
+                    if !values.insert(choice.value) {
+                        bail!(
+                            "Duplicate choice value in /{} {}: {}",
+                            path,
+                            option.name,
+                            choice.value
+                        );
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    /// Discordから送信されるコマンドの内容を組み立てる
+    /// メンバー・ロール・チャンネル・添付ファイルのオプションは、それぞれID 1〜4を指定したものとして解決する
+    fn command_data(kind: CommandType, name: &str, options: Value) -> CommandData {
+        let kind = match kind {
+            CommandType::ChatInput => 1,
+            CommandType::User => 2,
+            _ => 3,
+        };
+        serde_json::from_value(json!({
+            "id": "100",
+            "name": name,
+            "type": kind,
+            "options": options,
+            "resolved": {
+                "users": {
+                    "1": { "id": "1", "username": "user", "discriminator": "0001", "avatar": null }
+                },
+                "roles": {
+                    "2": {
+                        "id": "2", "guild_id": "5", "name": "role", "color": 0, "hoist": false, "position": 1,
+                        "permissions": "0", "managed": false, "mentionable": false
+                    }
+                },
+                "channels": {
+                    "3": { "id": "3", "name": "channel", "type": 0, "permissions": "0" }
+                },
+                "attachments": {
+                    "4": {
+                        "id": "4", "filename": "dict.json", "size": 2,
+                        "url": "https://example.com/dict.json",
+                        "proxy_url": "https://example.com/dict.json"
+                    }
+                }
+            }
+        }))
+        .unwrap()
+    }
+
+    /// オプションの定義から、必須のオプションを指定した値を組み立てる
+    fn required_values(options: &[OptionSpec]) -> Value {
+        let values = options
+            .iter()
+            .filter(|option| option.required)
+            .map(|option| {
+                let (kind, value) = match option.kind {
+                    OptionKind::String { choices, .. } => (
+                        3,
+                        json!(choices.first().map_or("テスト", |choice| choice.value)),
+                    ),
+                    OptionKind::Integer { min, .. } => (4, json!(min.unwrap_or(1))),
+                    OptionKind::Boolean => (5, json!(true)),
+                    OptionKind::User => (6, json!("1")),
+                    OptionKind::Channel(_) => (7, json!("3")),
+                    OptionKind::Role => (8, json!("2")),
+                    OptionKind::Number { min, .. } => (10, json!(min.unwrap_or(1.0))),
+                    OptionKind::Attachment => (11, json!("4")),
+                    OptionKind::SubCommand(_) | OptionKind::SubCommandGroup(_) => {
+                        unreachable!("subcommands are never required")
+                    }
+                };
+                json!({ "name": option.name, "type": kind, "value": value })
+            })
+            .collect();
+        Value::Array(values)
+    }
+
+    /// サブコマンドをたどり、実行できるすべての経路と、そのオプションを返す
+    fn invocations(options: &'static [OptionSpec]) -> Vec<(Vec<&'static str>, Value)> {
+        let has_subcommands = options.iter().any(|option| {
+            matches!(
+                option.kind,
+                OptionKind::SubCommand(_) | OptionKind::SubCommandGroup(_)
+            )
+        });
+        if !has_subcommands {
+            return vec![(Vec::new(), required_values(options))];
+        }
+
+        let mut result = Vec::new();
+        for option in options {
+            let (kind, children) = match option.kind {
+                OptionKind::SubCommand(children) => (1, children),
+                OptionKind::SubCommandGroup(children) => (2, children),
+                _ => continue,
+            };
+            for (mut path, values) in invocations(children) {
+                path.insert(0, option.name);
+                let value = json!([{ "name": option.name, "type": kind, "options": values }]);
+                result.push((path, value));
+            }
+        }
+        result
+    }
+
+    /// スラッシュコマンドの経路と、解釈した結果の[`Command`]の名前
+    /// 登録されたすべての経路を列挙し、実行時に呼び出す処理との対応を変えていないことを確かめる
+    const EXPECTED: &[(&str, &str)] = &[
+        ("join", "Join"),
+        ("leave", "Leave"),
+        ("skip", "Skip"),
+        ("move", "Move"),
+        ("summon", "Summon"),
+        ("channels add", "ChannelsAdd"),
+        ("channels remove", "ChannelsRemove"),
+        ("channels list", "ChannelsList"),
+        ("status", "Status"),
+        ("voice set", "VoiceSet"),
+        ("voice info", "VoiceInfo"),
+        ("voice boost", "VoiceBoost"),
+        ("voice randomize", "VoiceRandomize"),
+        ("voice language", "VoiceLanguage"),
+        ("voice preview-user", "VoicePreviewUser"),
+        ("dict add", "DictAddForm"),
+        ("dict remove", "DictRemove"),
+        ("dict view", "DictView"),
+        ("dict count", "DictCount"),
+        ("dict clear", "DictClear"),
+        ("dict export", "DictExport"),
+        ("dict import", "DictImport"),
+        ("mydict add", "MyDictAdd"),
+        ("mydict remove", "MyDictRemove"),
+        ("mydict view", "MyDictView"),
+        ("filter add", "FilterAdd"),
+        ("filter remove", "FilterRemove"),
+        ("filter list", "FilterList"),
+        ("filter mode", "FilterMode"),
+        ("settings view", "SettingsView"),
+        ("settings set", "SettingsSet"),
+        ("settings reset", "SettingsReset"),
+        ("settings auto-join", "SettingsAutoJoin"),
+        ("settings collapse-repeats", "SettingsCollapseRepeats"),
+        ("settings furigana", "SettingsFurigana"),
+        ("settings read-responses", "SettingsReadResponses"),
+        ("settings idle-timeout", "SettingsIdleTimeout"),
+        ("settings empty-text", "SettingsEmptyText"),
+        ("settings farewell", "SettingsFarewell"),
+        ("settings mention-only", "SettingsMentionOnly"),
+        ("settings read-prefix", "SettingsReadPrefix"),
+        ("settings provider", "SettingsProvider"),
+        ("settings language", "SettingsLanguage"),
+        ("settings voice-range", "SettingsVoiceRange"),
+        ("settings system-voice", "SettingsSystemVoice"),
+        ("settings embed-bots add", "SettingsEmbedBotAdd"),
+        ("settings embed-bots remove", "SettingsEmbedBotRemove"),
+        ("settings embed-bots list", "SettingsEmbedBotList"),
+        ("settings permissions set", "SettingsPermissionsSet"),
+        ("settings permissions view", "SettingsPermissionsView"),
+        ("admin status", "AdminStatus"),
+        ("admin inspect", "AdminInspect"),
+        ("admin usage-limit", "AdminUsageLimit"),
+        ("usage", "Usage"),
+        ("help", "Help"),
+        ("version", "Version"),
+    ];
+
+    fn variant_name(command: &Command) -> String {
+        let debug = format!("{:?}", command);
+        debug
+            .split(|c: char| !c.is_alphanumeric())
+            .next()
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn registered_commands_are_valid() {
+        validate().unwrap();
+    }
+
+    const fn spec(
+        name: &'static str,
+        aliases: &'static [&'static str],
+        options: &'static [OptionSpec],
+    ) -> CommandSpec {
+        CommandSpec {
+            name,
+            description: "",
+            description_en: "",
+            category: Category::Connection,
+            aliases,
+            manage_guild_only: false,
+            dm_allowed: false,
+            options,
+        }
+    }
+
+    const fn context_menu(name: &'static str, kind: CommandType) -> ContextMenuSpec {
+        ContextMenuSpec {
+            name,
+            name_en: name,
+            kind,
+            description: "",
+            description_en: "",
+        }
+    }
+
+    fn error(commands: &[&CommandSpec], context_menus: &[&ContextMenuSpec]) -> String {
+        validate_specs(commands, context_menus)
+            .unwrap_err()
+            .to_string()
+    }
+
+    #[test]
+    fn duplicate_command_names_are_rejected() {
+        const A: CommandSpec = spec("a", &["ka"], &[]);
+        const B: CommandSpec = spec("b", &[], &[]);
+        assert!(validate_specs(&[&A, &B], &[]).is_ok());
+
+        const A2: CommandSpec = spec("a", &[], &[]);
+        assert_eq!(error(&[&A, &A2], &[]), "Duplicate command name: a");
+
+        // 別名が他のコマンドの名前と重なる場合
+        const KA: CommandSpec = spec("ka", &[], &[]);
+        assert_eq!(error(&[&A, &KA], &[]), "Duplicate command name: ka");
+        const B_KA: CommandSpec = spec("b", &["ka"], &[]);
+        assert_eq!(error(&[&A, &B_KA], &[]), "Duplicate command name: ka");
+    }
+
+    #[test]
+    fn duplicate_options_are_rejected() {
+        const OPTION: OptionSpec = OptionSpec::new("value", "", "", OptionKind::Boolean);
+        const SUB: OptionSpec = subcommand("set", "", "", &[OPTION, OPTION]);
+        const NESTED: CommandSpec = spec("a", &[], &[SUB]);
+        assert_eq!(
+            error(&[&NESTED], &[]),
+            "Duplicate option name in /a set: value"
+        );
+
+        const SUB_SET: OptionSpec = subcommand("set", "", "", &[]);
+        const SUBS: CommandSpec = spec("a", &[], &[SUB_SET, SUB_SET]);
+        assert_eq!(error(&[&SUBS], &[]), "Duplicate option name in /a: set");
+
+        const MODE: OptionSpec = OptionSpec::new(
+            "mode",
+            "",
+            "",
+            OptionKind::String {
+                choices: &[Choice::new("オン", "on"), Choice::new("有効", "on")],
+                autocomplete: false,
+            },
+        );
+        const CHOICES: CommandSpec = spec("a", &[], &[MODE]);
+        assert_eq!(
+            error(&[&CHOICES], &[]),
+            "Duplicate choice value in /a mode: on"
+        );
+    }
+
+    #[test]
+    fn duplicate_context_menus_are_rejected() {
+        const USER: ContextMenuSpec = context_menu("読み上げ", CommandType::User);
+        const MESSAGE: ContextMenuSpec = context_menu("読み上げ", CommandType::Message);
+        // 種類が異なれば、同じ名前を使える
+        assert!(validate_specs(&[], &[&USER, &MESSAGE]).is_ok());
+        assert_eq!(
+            error(&[], &[&USER, &USER]),
+            "Duplicate context menu name: 読み上げ"
+        );
+    }
+
+    #[test]
+    fn every_subcommand_parses_through_registry() {
+        let mut actual = Vec::new();
+        for command in COMMANDS {
+            let spec = command.spec();
+            for (path, options) in invocations(spec.options) {
+                let data = command_data(CommandType::ChatInput, spec.name, options);
+                let registered = Registered::find(&data).unwrap();
+                let parsed = registered.parse(&data).unwrap_or_else(|err| {
+                    panic!("/{} {}: {}", spec.name, path.join(" "), err);
+                });
+
+                let path = std::iter::once(spec.name)
+                    .chain(path)
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                actual.push((path, variant_name(&parsed)));
+            }
+        }
+
+        let expected = EXPECTED
+            .iter()
+            .map(|(path, variant)| (path.to_string(), variant.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn aliases_resolve_to_same_command() {
+        for command in COMMANDS {
+            let spec = command.spec();
+            for alias in spec.aliases {
+                let data = command_data(CommandType::ChatInput, alias, json!([]));
+                match Registered::find(&data).unwrap() {
+                    Registered::Slash(found) => assert_eq!(found.spec().name, spec.name),
+                    Registered::ContextMenu(_) => panic!("/{} resolved to a context menu", alias),
+                }
+            }
+        }
+
+        let data = command_data(CommandType::ChatInput, "kjoin", json!([]));
+        let command = Registered::find(&data).unwrap().parse(&data).unwrap();
+        assert!(matches!(command, Command::Join));
+    }
+
+    #[test]
+    fn unknown_command_is_rejected() {
+        let data = command_data(CommandType::ChatInput, "unknown", json!([]));
+        assert_eq!(
+            Registered::find(&data).err(),
+            Some(CommandParseError::UnknownCommand {
+                name: "unknown".to_string()
+            })
+        );
+
+        // 同じ名前でも、種類が異なるコマンドとしては扱わない
+        let data = command_data(CommandType::Message, "join", json!([]));
+        assert!(Registered::find(&data).is_err());
+    }
+
+    #[test]
+    fn context_menus_resolve_by_kind() {
+        let data = serde_json::from_value::<CommandData>(json!({
+            "id": "100",
+            "name": super::super::setup::SHOW_VOICE_COMMAND_NAME,
+            "type": 2,
+            "target_id": "1",
+            "resolved": {
+                "users": {
+                    "1": { "id": "1", "username": "user", "discriminator": "0001", "avatar": null }
+                },
+                "members": {
+                    "1": {
+                        "nick": "nick", "roles": [], "joined_at": "2024-01-01T00:00:00+00:00",
+                        "deaf": false, "mute": false
+                    }
+                }
+            }
+        }))
+        .unwrap();
+
+        let registered = Registered::find(&data).unwrap();
+        assert!(!registered.allows_dm());
+        match registered.parse(&data).unwrap() {
+            Command::ShowVoice(option) => {
+                assert_eq!(option.user.id.0, 1);
+                assert_eq!(option.nick.as_deref(), Some("nick"));
+            }
+            command => panic!("unexpected command: {:?}", command),
+        }
+
+        // 対象が解決できない場合は、オプションが不足しているものとして扱う
+        let data = command_data(
+            CommandType::Message,
+            super::super::setup::READ_MESSAGE_COMMAND_NAME,
+            json!([]),
+        );
+        assert_eq!(
+            Registered::find(&data).unwrap().parse(&data).err(),
+            Some(CommandParseError::MissingOption {
+                name: "message".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn only_commands_marked_for_dm_are_allowed_in_dm() {
+        for command in COMMANDS {
+            let spec = command.spec();
+            let data = command_data(CommandType::ChatInput, spec.name, json!([]));
+            assert_eq!(
+                Registered::find(&data).unwrap().allows_dm(),
+                spec.dm_allowed
+            );
+        }
+    }
+}
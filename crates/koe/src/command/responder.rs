@@ -0,0 +1,229 @@
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+use serenity::{
+    builder::{CreateComponents, CreateEmbed},
+    client::Context,
+    model::{
+        application::interaction::{
+            application_command::ApplicationCommandInteraction, InteractionResponseType,
+        },
+        channel::AttachmentType,
+    },
+};
+
+/// コマンドへの応答として送信する1件のメッセージ
+#[derive(Default)]
+pub struct ResponseMessage {
+    pub content: Option<String>,
+    pub embeds: Vec<CreateEmbed>,
+    pub file: Option<AttachmentType<'static>>,
+    pub components: Option<CreateComponents>,
+    /// コマンドを送信したメンバーのみに見えるメッセージにするかどうか
+    pub ephemeral: bool,
+}
+
+/// コマンドのインタラクションに応答する
+/// Discordに接続せずにコマンドの応答を確かめられるよう、実装を差し替えられるようにしている
+#[async_trait]
+pub trait Responder: Send + Sync {
+    /// インタラクションに応答する
+    async fn create_response(
+        &self,
+        ctx: &Context,
+        cmd: &ApplicationCommandInteraction,
+        message: ResponseMessage,
+    ) -> Result<()>;
+
+    /// 応答を保留する
+    /// `ephemeral`が`true`の場合は、保留中の表示と以降の応答をコマンドを送信したメンバーのみに見せる
+    async fn defer(
+        &self,
+        ctx: &Context,
+        cmd: &ApplicationCommandInteraction,
+        ephemeral: bool,
+    ) -> Result<()>;
+
+    /// フォローアップを送信する
+    /// 応答を保留した後の最初のフォローアップは、保留中の応答を置き換える
+    async fn create_followup(
+        &self,
+        ctx: &Context,
+        cmd: &ApplicationCommandInteraction,
+        message: ResponseMessage,
+    ) -> Result<()>;
+}
+
+/// DiscordのAPIを使って応答する実装
+pub struct DiscordResponder;
+
+#[async_trait]
+impl Responder for DiscordResponder {
+    async fn create_response(
+        &self,
+        ctx: &Context,
+        cmd: &ApplicationCommandInteraction,
+        message: ResponseMessage,
+    ) -> Result<()> {
+        cmd.create_interaction_response(&ctx.http, |create_response| {
+            create_response
+                .kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|create_message| {
+                    create_message.ephemeral(message.ephemeral);
+                    if let Some(content) = message.content {
+                        create_message.content(content);
+                    }
+                    if !message.embeds.is_empty() {
+                        create_message.add_embeds(message.embeds);
+                    }
+                    if let Some(file) = message.file {
+                        create_message.add_file(file);
+                    }
+                    if let Some(components) = message.components {
+                        create_message.set_components(components);
+                    }
+                    create_message
+                })
+        })
+        .await
+        .context("Failed to create interaction response")?;
+        Ok(())
+    }
+
+    async fn defer(
+        &self,
+        ctx: &Context,
+        cmd: &ApplicationCommandInteraction,
+        ephemeral: bool,
+    ) -> Result<()> {
+        cmd.create_interaction_response(&ctx.http, |create_response| {
+            create_response
+                .kind(InteractionResponseType::DeferredChannelMessageWithSource)
+                .interaction_response_data(|create_message| create_message.ephemeral(ephemeral))
+        })
+        .await
+        .context("Failed to defer interaction response")?;
+        Ok(())
+    }
+
+    async fn create_followup(
+        &self,
+        ctx: &Context,
+        cmd: &ApplicationCommandInteraction,
+        message: ResponseMessage,
+    ) -> Result<()> {
+        cmd.create_followup_message(&ctx.http, |create_message| {
+            create_message.ephemeral(message.ephemeral);
+            if let Some(content) = message.content {
+                create_message.content(content);
+            }
+            if !message.embeds.is_empty() {
+                create_message.add_embeds(message.embeds);
+            }
+            if let Some(file) = message.file {
+                create_message.add_file(file);
+            }
+            if let Some(components) = message.components {
+                create_message.set_components(components);
+            }
+            create_message
+        })
+        .await
+        .context("Failed to create followup message")?;
+        Ok(())
+    }
+}
+
+/// Discordに送信せず、応答をメモリ上に記録するだけの実装
+/// テストで、コマンドの応答の内容と順番を確かめるために使う
+#[cfg(test)]
+#[derive(Default)]
+pub struct FakeResponder {
+    sent: std::sync::Mutex<Vec<Sent>>,
+}
+
+/// [`FakeResponder`]が記録した応答
+#[cfg(test)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Sent {
+    Response(SentMessage),
+    Defer { ephemeral: bool },
+    Followup(SentMessage),
+}
+
+#[cfg(test)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SentMessage {
+    pub content: Option<String>,
+    /// 埋め込みのタイトル
+    pub embed_titles: Vec<Option<String>>,
+    pub has_components: bool,
+    pub ephemeral: bool,
+}
+
+#[cfg(test)]
+impl FakeResponder {
+    /// 記録した応答を、送信した順に返す
+    pub fn sent(&self) -> Vec<Sent> {
+        self.sent.lock().unwrap().clone()
+    }
+
+    fn record(&self, sent: Sent) {
+        self.sent.lock().unwrap().push(sent);
+    }
+}
+
+#[cfg(test)]
+impl From<ResponseMessage> for SentMessage {
+    fn from(message: ResponseMessage) -> Self {
+        SentMessage {
+            content: message.content,
+            embed_titles: message
+                .embeds
+                .iter()
+                .map(|embed| {
+                    embed
+                        .0
+                        .get("title")
+                        .and_then(|title| title.as_str())
+                        .map(str::to_string)
+                })
+                .collect(),
+            has_components: message.components.is_some(),
+            ephemeral: message.ephemeral,
+        }
+    }
+}
+
+#[cfg(test)]
+#[async_trait]
+impl Responder for FakeResponder {
+    async fn create_response(
+        &self,
+        _ctx: &Context,
+        _cmd: &ApplicationCommandInteraction,
+        message: ResponseMessage,
+    ) -> Result<()> {
+        self.record(Sent::Response(message.into()));
+        Ok(())
+    }
+
+    async fn defer(
+        &self,
+        _ctx: &Context,
+        _cmd: &ApplicationCommandInteraction,
+        ephemeral: bool,
+    ) -> Result<()> {
+        self.record(Sent::Defer { ephemeral });
+        Ok(())
+    }
+
+    async fn create_followup(
+        &self,
+        _ctx: &Context,
+        _cmd: &ApplicationCommandInteraction,
+        message: ResponseMessage,
+    ) -> Result<()> {
+        self.record(Sent::Followup(message.into()));
+        Ok(())
+    }
+}
@@ -0,0 +1,2148 @@
+use super::{
+    handler::{
+        find_preset, furigana_syntax_label, get_scale_range, global_scale_range, guild_only,
+        mention_only_mode_label, r, requirement_label, respond, sanitize_response,
+        scale_kind_label, scale_out_of_range, setting_value_label, validate_scales,
+        voice_params_summary, CommandResponse,
+    },
+    model::{
+        Command, SettingsAutoJoinOption, SettingsCollapseRepeatsOption, SettingsEmbedBotOption,
+        SettingsEmptyTextOption, SettingsFarewellOption, SettingsFuriganaOption,
+        SettingsIdleTimeoutOption, SettingsLanguageOption, SettingsMentionOnlyOption,
+        SettingsPermissionsSetOption, SettingsProviderOption, SettingsReadPrefixOption,
+        SettingsReadResponsesOption, SettingsResetOption, SettingsSetOption,
+        SettingsVoiceRangeOption, VoiceParamsOption,
+    },
+    parser::{
+        find_boolean, find_channel, find_integer, find_number, find_role, find_string,
+        find_subcommand, find_user, invalid, required, unknown_subcommand, CommandParseError,
+    },
+    permission::PERMISSION_COMMAND_NAMES,
+    registry::{
+        group, subcommand, Category, Choice, CommandSpec, OptionKind, OptionSpec, SlashCommand,
+        PITCH_SCALE, SPEED_SCALE, STRING, TEXT_CHANNEL, VOICE_PARAMS_OPTIONS,
+    },
+    settings_view,
+    setup::{MAX_REPEAT_THRESHOLD, MIN_REPEAT_THRESHOLD},
+    voice,
+};
+use crate::{
+    app_state, default_voice,
+    messages::{self, Key},
+};
+use anyhow::{bail, Context as _, Result};
+use async_trait::async_trait;
+use koe_db::{
+    auto_join::{self, AutoJoinSetting},
+    command_permission::{self, Requirement},
+    embed_bot, empty_text, farewell,
+    furigana::FuriganaSyntax,
+    guild_settings::{
+        self, BoolKey, ChannelKey, ChoiceKey, IntKey, OptionalIntKey, ParseValueError, SettingKey,
+        SettingValue, TextKey, MAX_IDLE_TIMEOUT_MINUTES,
+    },
+    language::{self, Language},
+    mention_only,
+    mention_only::MentionOnlyMode,
+    read_prefix,
+    scale_bounds::{self, ScaleBounds, ScaleKind},
+    system_voice,
+};
+use koe_speech::{
+    provider::ProviderKind,
+    speech::{PITCH_SCALE_RANGE, SPEED_SCALE_RANGE},
+};
+use serenity::{
+    builder::CreateEmbed,
+    client::Context,
+    model::{
+        application::interaction::application_command::{
+            ApplicationCommandInteraction, CommandDataOption,
+        },
+        channel::ChannelType,
+        id::ChannelId,
+    },
+};
+use std::time::Duration;
+
+/// `/settings set`と`/settings reset`で指定する設定項目
+/// 値は[`koe_db::guild_settings::SettingKey::name`]と一致させる
+const SETTING_KEY: OptionKind = OptionKind::String {
+    choices: &[
+        Choice::localized("リアクションの読み上げ", "Read reactions", "read_reactions"),
+        Choice::localized(
+            "メッセージの削除の読み上げ",
+            "Announce deleted messages",
+            "read_deletions",
+        ),
+        Choice::localized("代替テキストの読み上げ", "Read alt text", "read_alt_text"),
+        Choice::localized("投票の読み上げ", "Read polls", "read_polls"),
+        Choice::localized("ロールの読み上げ", "Read author roles", "read_author_role"),
+        Choice::localized("チャイム", "Chime", "chime"),
+        Choice::localized("メンバーの移動への追従", "Follow members", "follow_users"),
+        Choice::localized(
+            "ユーザーIDによる声の割り当て",
+            "Assign voices by user ID",
+            "voice_by_user_id",
+        ),
+        Choice::localized("繰り返しをまとめる", "Collapse repeats", "collapse_repeats"),
+        Choice::localized("読み仮名の指定", "Furigana", "furigana"),
+        Choice::localized("応答の読み上げ", "Read command responses", "read_responses"),
+        Choice::localized(
+            "エラーメッセージの読み上げ",
+            "Read error messages",
+            "read_error_responses",
+        ),
+        Choice::localized(
+            "辞書の読み方の表示",
+            "Show dictionary readings",
+            "show_dict_readings",
+        ),
+        Choice::localized(
+            "イベントの開始の読み上げ",
+            "Announce scheduled events",
+            "read_scheduled_events",
+        ),
+        Choice::localized(
+            "文字幅の統一",
+            "Normalize character width",
+            "normalize_width",
+        ),
+        Choice::localized(
+            "ボイスチャンネルのチャットの読み上げ",
+            "Read voice channel chat",
+            "read_voice_chat",
+        ),
+        Choice::localized(
+            "繰り返しをまとめる閾値",
+            "Repeat threshold",
+            "repeat_threshold",
+        ),
+        Choice::localized("読み上げる最大の単語数", "Max words", "max_words"),
+        Choice::localized(
+            "メッセージの読み上げ方",
+            "Message template",
+            "message_template",
+        ),
+        Choice::localized(
+            "自動で退出するまでの時間（分）",
+            "Idle timeout (minutes)",
+            "idle_timeout",
+        ),
+        Choice::localized(
+            "読み仮名を指定する書き方",
+            "Furigana syntax",
+            "furigana_syntax",
+        ),
+        Choice::localized(
+            "お知らせを送信するチャンネル",
+            "Notice channel",
+            "notice_channel",
+        ),
+    ],
+    autocomplete: false,
+};
+
+/// `/settings`の定義
+const SPEC: CommandSpec = CommandSpec {
+    name: "settings",
+    description: "サーバーの設定",
+    description_en: "Server settings",
+    category: Category::Other,
+    aliases: &[],
+    manage_guild_only: true,
+    dm_allowed: false,
+    options: &[
+        subcommand("view", "サーバーの設定をまとめて表示", "Show all server settings", &[]),
+        subcommand(
+            "set",
+            "設定項目の値を変更",
+            "Change the value of a setting",
+            &[
+                OptionSpec::new("key", "設定項目", "Setting", SETTING_KEY).required(),
+                OptionSpec::new(
+                    "value",
+                    "設定する値（有効・無効はtrue・false、閾値は整数、チャンネルは#チャンネル）",
+                    "Value to set (true or false for toggles, an integer for thresholds, #channel for channels)",
+                    STRING,
+                )
+                .required(),
+            ],
+        ),
+        subcommand(
+            "reset",
+            "設定項目を既定値に戻す",
+            "Reset a setting to its default",
+            &[OptionSpec::new("key", "設定項目", "Setting", SETTING_KEY).required()],
+        ),
+        subcommand(
+            "auto-join",
+            "メンバーがボイスチャンネルに参加したときに自動で接続（チャンネル省略で無効化）",
+            "Join automatically when a member joins a voice channel (omit channels to disable)",
+            &[
+                OptionSpec::new(
+                    "channel",
+                    "自動で接続するボイスチャンネル",
+                    "Voice channel to join automatically",
+                    OptionKind::Channel(&[ChannelType::Voice, ChannelType::Stage]),
+                ),
+                OptionSpec::new(
+                    "text",
+                    "読み上げるテキストチャンネル",
+                    "Text channel to read",
+                    TEXT_CHANNEL,
+                ),
+            ],
+        ),
+        subcommand(
+            "collapse-repeats",
+            "同じ文字の繰り返し（wwwww、！！！！など）をまとめて読み上げ",
+            "Collapse repeated characters (such as wwwww or !!!!) when reading",
+            &[
+                OptionSpec::new(
+                    "enabled",
+                    "有効にするかどうか",
+                    "Whether to enable it",
+                    OptionKind::Boolean,
+                )
+                .required(),
+                OptionSpec::new(
+                    "threshold",
+                    "この数を超えて続いた文字をまとめる（既定値: 3）",
+                    "Collapse characters repeated more than this many times (default: 3)",
+                    OptionKind::Integer {
+                        min: Some(MIN_REPEAT_THRESHOLD),
+                        max: Some(MAX_REPEAT_THRESHOLD),
+                        autocomplete: false,
+                    },
+                ),
+            ],
+        ),
+        subcommand(
+            "furigana",
+            "メッセージの中で指定された読み仮名で読み上げ",
+            "Read words with readings specified in messages",
+            &[
+                OptionSpec::new(
+                    "enabled",
+                    "有効にするかどうか",
+                    "Whether to enable it",
+                    OptionKind::Boolean,
+                )
+                .required(),
+                OptionSpec::new(
+                    "syntax",
+                    "読み仮名を指定する書き方（既定値: {漢字|かんじ}）",
+                    "How readings are written (default: {漢字|かんじ})",
+                    OptionKind::String {
+                        choices: &[
+                            Choice::localized("{漢字|かんじ}", "{漢字|かんじ}", "braces"),
+                            Choice::localized("｜漢字《かんじ》", "｜漢字《かんじ》", "ruby"),
+                        ],
+                        autocomplete: false,
+                    },
+                ),
+            ],
+        ),
+        subcommand(
+            "read-responses",
+            "コマンドへの応答をボイスチャンネルで読み上げ",
+            "Read command responses aloud in the voice channel",
+            &[
+                OptionSpec::new(
+                    "enabled",
+                    "有効にするかどうか",
+                    "Whether to enable it",
+                    OptionKind::Boolean,
+                )
+                .required(),
+                OptionSpec::new(
+                    "errors",
+                    "エラーメッセージも読み上げるかどうか（既定値: 読み上げない）",
+                    "Whether to read error messages too (default: no)",
+                    OptionKind::Boolean,
+                ),
+            ],
+        ),
+        subcommand(
+            "idle-timeout",
+            "読み上げがないまま経過するとボイスチャンネルから退出する時間（時間省略で既定値に戻す）",
+            "Leave the voice channel after this long without reading (omit to use the default)",
+            &[OptionSpec::new(
+                "minutes",
+                "退出するまでの時間（分）、0で自動で退出しない",
+                "Minutes until leaving, or 0 to never leave automatically",
+                OptionKind::Integer {
+                    min: Some(0),
+                    max: Some(MAX_IDLE_TIMEOUT_MINUTES),
+                    autocomplete: false,
+                },
+            )],
+        ),
+        subcommand(
+            "empty-text",
+            "読み上げる内容がないメッセージの代わりに読み上げる文言（文言省略で読み飛ばし）",
+            "Text to read for messages with nothing to read (omit to skip them)",
+            &[OptionSpec::new(
+                "placeholder",
+                "代わりに読み上げる文言",
+                "Text to read instead",
+                STRING,
+            )],
+        ),
+        subcommand(
+            "farewell",
+            "ボイスチャンネルから退出する前に読み上げる挨拶（挨拶省略で挨拶しない）",
+            "Farewell to say before leaving the voice channel (omit to disable)",
+            &[OptionSpec::new(
+                "text",
+                "読み上げる挨拶",
+                "Farewell to say",
+                STRING,
+            )],
+        ),
+        subcommand(
+            "mention-only",
+            "メンションだけのメッセージの扱いを設定",
+            "Set how messages with only mentions are handled",
+            &[OptionSpec::new(
+                "mode",
+                "メッセージの扱い",
+                "How to handle the messages",
+                OptionKind::String {
+                    choices: &[
+                        Choice::localized("名前を読み上げる", "Read the names", "read"),
+                        Choice::localized("読み上げない", "Do not read", "skip"),
+                        Choice::localized(
+                            "「メンション」と読み上げる",
+                            "Read \"mention\"",
+                            "note",
+                        ),
+                    ],
+                    autocomplete: false,
+                },
+            )
+            .required()],
+        ),
+        subcommand(
+            "read-prefix",
+            "指定した合図で始まるメッセージのみを読み上げる（合図省略ですべて読み上げ）",
+            "Read only messages starting with a prefix (omit to read all messages)",
+            &[OptionSpec::new(
+                "prefix",
+                "読み上げるメッセージの先頭に付ける合図（例: >>）",
+                "Prefix that messages to be read start with (e.g. >>)",
+                STRING,
+            )],
+        ),
+        subcommand(
+            "provider",
+            "読み上げに使う音声合成のエンジンを設定",
+            "Set the speech synthesis engine used for reading",
+            &[OptionSpec::new(
+                "name",
+                "音声合成のエンジン",
+                "Speech synthesis engine",
+                OptionKind::String {
+                    choices: &[],
+                    autocomplete: true,
+                },
+            )
+            .required()],
+        ),
+        subcommand(
+            "language",
+            "応答の言語を設定（自動または言語省略でDiscordの言語設定に合わせる）",
+            "Set the response language (auto or omit to follow each member's Discord language)",
+            &[OptionSpec::new(
+                "language",
+                "応答の言語",
+                "Response language",
+                OptionKind::String {
+                    choices: &[
+                        Choice::new("日本語", "ja"),
+                        Choice::localized("英語", "English", "en"),
+                        Choice::localized("自動", "Auto", "auto"),
+                    ],
+                    autocomplete: false,
+                },
+            )],
+        ),
+        subcommand(
+            "voice-range",
+            "メンバーが設定できる話速と音高の範囲（すべて省略で制限を解除）",
+            "Range of speed and pitch members can set (omit all to remove limits)",
+            &[
+                OptionSpec::new(
+                    "speed-min",
+                    "話速の下限",
+                    "Minimum speed",
+                    SPEED_SCALE,
+                ),
+                OptionSpec::new(
+                    "speed-max",
+                    "話速の上限",
+                    "Maximum speed",
+                    SPEED_SCALE,
+                ),
+                OptionSpec::new(
+                    "pitch-min",
+                    "音高の下限",
+                    "Minimum pitch",
+                    PITCH_SCALE,
+                ),
+                OptionSpec::new(
+                    "pitch-max",
+                    "音高の上限",
+                    "Maximum pitch",
+                    PITCH_SCALE,
+                ),
+            ],
+        ),
+        subcommand(
+            "system-voice",
+            "Botからのお知らせを読み上げる声を設定（すべて省略で既定の声に戻す）",
+            "Set the voice for announcements from the bot (omit all to reset)",
+            VOICE_PARAMS_OPTIONS,
+        ),
+        group(
+            "embed-bots",
+            "埋め込みを読み上げるBotの設定",
+            "Configure bots whose embeds are read",
+            &[
+                subcommand(
+                    "add",
+                    "埋め込みを読み上げるBotを追加",
+                    "Add a bot whose embeds are read",
+                    &[OptionSpec::new(
+                        "bot",
+                        "埋め込みを読み上げたいBot",
+                        "Bot whose embeds to read",
+                        OptionKind::User,
+                    )
+                    .required()],
+                ),
+                subcommand(
+                    "remove",
+                    "埋め込みを読み上げるBotを削除",
+                    "Remove a bot whose embeds are read",
+                    &[
+                        OptionSpec::new("bot", "削除したいBot", "Bot to remove", OptionKind::User)
+                            .required(),
+                    ],
+                ),
+                subcommand(
+                    "list",
+                    "埋め込みを読み上げるBotを表示",
+                    "Show the bots whose embeds are read",
+                    &[],
+                ),
+            ],
+        ),
+        group(
+            "permissions",
+            "コマンドを使えるメンバーの設定",
+            "Configure who can use each command",
+            &[
+                subcommand(
+                    "set",
+                    "コマンドを使えるメンバーを設定",
+                    "Set who can use a command",
+                    &[
+                        OptionSpec::new(
+                            "command",
+                            "設定するコマンド",
+                            "Command to configure",
+                            // `PERMISSION_COMMAND_NAMES`と一致させる
+                            OptionKind::String {
+                                choices: &[
+                                    Choice::new("/join", "join"),
+                                    Choice::new("/leave", "leave"),
+                                    Choice::new("/skip", "skip"),
+                                    Choice::new("/move", "move"),
+                                    Choice::new("/summon", "summon"),
+                                    Choice::new("/status", "status"),
+                                    Choice::new("/channels", "channels"),
+                                    Choice::new("/voice", "voice"),
+                                    Choice::new("/dict", "dict"),
+                                    Choice::new("/mydict", "mydict"),
+                                ],
+                                autocomplete: false,
+                            },
+                        )
+                        .required(),
+                        OptionSpec::new(
+                            "level",
+                            "コマンドを使えるメンバー",
+                            "Who can use the command",
+                            OptionKind::String {
+                                choices: &[
+                                    Choice::localized(
+                                        "すべてのメンバー",
+                                        "Everyone",
+                                        "everyone",
+                                    ),
+                                    Choice::localized(
+                                        "Botと同じボイスチャンネルのメンバー",
+                                        "Members in the bot's voice channel",
+                                        "in_voice_channel",
+                                    ),
+                                    Choice::localized(
+                                        "指定したロールを持つメンバー",
+                                        "Members with the specified role",
+                                        "role",
+                                    ),
+                                    Choice::localized(
+                                        "「サーバー管理」の権限を持つメンバー",
+                                        "Members with Manage Server",
+                                        "manage_guild",
+                                    ),
+                                ],
+                                autocomplete: false,
+                            },
+                        )
+                        .required(),
+                        OptionSpec::new(
+                            "role",
+                            "コマンドを使えるロール（levelでロールを選んだ場合のみ）",
+                            "Role that can use the command (only when level is role)",
+                            OptionKind::Role,
+                        ),
+                    ],
+                ),
+                subcommand(
+                    "view",
+                    "コマンドを使えるメンバーの設定を表示",
+                    "Show who can use each command",
+                    &[],
+                ),
+            ],
+        ),
+    ],
+};
+
+pub(super) struct SettingsCommand;
+
+#[async_trait]
+impl SlashCommand for SettingsCommand {
+    fn spec(&self) -> &'static CommandSpec {
+        &SPEC
+    }
+
+    fn parse(&self, options: &[CommandDataOption]) -> Result<Command, CommandParseError> {
+        parse_settings(find_subcommand("settings", options)?)
+    }
+
+    async fn run(
+        &self,
+        ctx: &Context,
+        cmd: &ApplicationCommandInteraction,
+        lang: Language,
+        command: Command,
+    ) -> Result<()> {
+        match command {
+            Command::SettingsView => settings_view::handle_view(ctx, cmd, lang)
+                .await
+                .context("Failed to execute /settings view"),
+            Command::SettingsSet(option) => handle_set(ctx, cmd, lang, option)
+                .await
+                .context("Failed to execute /settings set"),
+            Command::SettingsReset(option) => handle_reset(ctx, cmd, lang, option)
+                .await
+                .context("Failed to execute /settings reset"),
+            Command::SettingsAutoJoin(option) => handle_auto_join(ctx, cmd, lang, option)
+                .await
+                .context("Failed to execute /settings auto-join"),
+            Command::SettingsReadResponses(option) => handle_read_responses(ctx, cmd, lang, option)
+                .await
+                .context("Failed to execute /settings read-responses"),
+            Command::SettingsCollapseRepeats(option) => {
+                handle_collapse_repeats(ctx, cmd, lang, option)
+                    .await
+                    .context("Failed to execute /settings collapse-repeats")
+            }
+            Command::SettingsFurigana(option) => handle_furigana(ctx, cmd, lang, option)
+                .await
+                .context("Failed to execute /settings furigana"),
+            Command::SettingsEmptyText(option) => handle_empty_text(ctx, cmd, lang, option)
+                .await
+                .context("Failed to execute /settings empty-text"),
+            Command::SettingsIdleTimeout(option) => handle_idle_timeout(ctx, cmd, lang, option)
+                .await
+                .context("Failed to execute /settings idle-timeout"),
+            Command::SettingsReadPrefix(option) => handle_read_prefix(ctx, cmd, lang, option)
+                .await
+                .context("Failed to execute /settings read-prefix"),
+            Command::SettingsProvider(option) => handle_provider(ctx, cmd, lang, option)
+                .await
+                .context("Failed to execute /settings provider"),
+            Command::SettingsFarewell(option) => handle_farewell(ctx, cmd, lang, option)
+                .await
+                .context("Failed to execute /settings farewell"),
+            Command::SettingsMentionOnly(option) => handle_mention_only(ctx, cmd, lang, option)
+                .await
+                .context("Failed to execute /settings mention-only"),
+            Command::SettingsLanguage(option) => handle_language(ctx, cmd, lang, option)
+                .await
+                .context("Failed to execute /settings language"),
+            Command::SettingsVoiceRange(option) => handle_voice_range(ctx, cmd, lang, option)
+                .await
+                .context("Failed to execute /settings voice-range"),
+            Command::SettingsSystemVoice(option) => handle_system_voice(ctx, cmd, lang, option)
+                .await
+                .context("Failed to execute /settings system-voice"),
+            Command::SettingsEmbedBotAdd(option) => handle_embed_bot_add(ctx, cmd, lang, option)
+                .await
+                .context("Failed to execute /settings embed-bots add"),
+            Command::SettingsEmbedBotRemove(option) => {
+                handle_embed_bot_remove(ctx, cmd, lang, option)
+                    .await
+                    .context("Failed to execute /settings embed-bots remove")
+            }
+            Command::SettingsEmbedBotList => handle_embed_bot_list(ctx, cmd, lang)
+                .await
+                .context("Failed to execute /settings embed-bots list"),
+            Command::SettingsPermissionsSet(option) => {
+                handle_permissions_set(ctx, cmd, lang, option)
+                    .await
+                    .context("Failed to execute /settings permissions set")
+            }
+            Command::SettingsPermissionsView => handle_permissions_view(ctx, cmd, lang)
+                .await
+                .context("Failed to execute /settings permissions view"),
+            command => bail!("Unexpected command for /settings: {:?}", command),
+        }
+    }
+}
+
+async fn handle_set(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+    option: SettingsSetOption,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r(ctx, cmd, guild_only(lang, "`/settings`")).await?;
+            return Ok(());
+        }
+    };
+
+    let label = messages::text(lang, setting_label(option.key));
+    let value = match option.key.parse_value(&option.value) {
+        Ok(value) => value,
+        Err(err) => {
+            let msg = match err {
+                ParseValueError::NotBool => {
+                    messages::format(lang, Key::SettingNotBool, &[("label", &label)])
+                }
+                ParseValueError::NotInt => {
+                    messages::format(lang, Key::SettingNotInt, &[("label", &label)])
+                }
+                ParseValueError::OutOfRange { min, max } => messages::format(
+                    lang,
+                    Key::SettingOutOfRange,
+                    &[("label", &label), ("min", &min), ("max", &max)],
+                ),
+                ParseValueError::TooLong { max } => messages::format(
+                    lang,
+                    Key::SettingTooLong,
+                    &[("label", &label), ("max", &max)],
+                ),
+                ParseValueError::MissingPlaceholder { placeholder } => messages::format(
+                    lang,
+                    Key::SettingMissingPlaceholder,
+                    &[("label", &label), ("placeholder", &placeholder)],
+                ),
+                ParseValueError::NotChoice { choices } => messages::format(
+                    lang,
+                    Key::SettingNotChoice,
+                    &[
+                        ("label", &label),
+                        (
+                            "choices",
+                            &choices
+                                .iter()
+                                .map(|choice| format!("`{}`", choice))
+                                .collect::<Vec<_>>()
+                                .join(&messages::text(lang, Key::ListSeparator)),
+                        ),
+                    ],
+                ),
+                ParseValueError::NotChannel => {
+                    messages::format(lang, Key::SettingNotChannel, &[("label", &label)])
+                }
+            };
+            r(ctx, cmd, msg).await?;
+            return Ok(());
+        }
+    };
+
+    // 別のサーバーのチャンネルや、メッセージを送信できないチャンネルは指定できない
+    if let SettingValue::Channel(channel_id) = value {
+        let is_text_channel = ChannelId(channel_id)
+            .to_channel_cached(&ctx.cache)
+            .and_then(|channel| channel.guild())
+            .is_some_and(|channel| channel.guild_id == guild_id && channel.is_text_based());
+        if !is_text_channel {
+            r(
+                ctx,
+                cmd,
+                messages::format(lang, Key::SettingNotChannel, &[("label", &label)]),
+            )
+            .await?;
+            return Ok(());
+        }
+    }
+
+    let state = app_state::get(ctx).await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+
+    guild_settings::set(
+        &mut conn,
+        guild_settings::SetOption {
+            guild_id: guild_id.into(),
+            key: option.key,
+            value: value.clone(),
+        },
+    )
+    .await?;
+    state.settings_cache.invalidate(guild_id);
+
+    r(
+        ctx,
+        cmd,
+        messages::format(
+            lang,
+            Key::SettingUpdated,
+            &[
+                ("label", &label),
+                ("value", &setting_value_label(lang, &value)),
+            ],
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+async fn handle_reset(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+    option: SettingsResetOption,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r(ctx, cmd, guild_only(lang, "`/settings`")).await?;
+            return Ok(());
+        }
+    };
+
+    let state = app_state::get(ctx).await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+
+    guild_settings::reset(
+        &mut conn,
+        guild_settings::ResetOption {
+            guild_id: guild_id.into(),
+            key: option.key,
+        },
+    )
+    .await?;
+    state.settings_cache.invalidate(guild_id);
+
+    r(
+        ctx,
+        cmd,
+        messages::format(
+            lang,
+            Key::SettingReset,
+            &[
+                ("label", &messages::text(lang, setting_label(option.key))),
+                (
+                    "value",
+                    &setting_value_label(lang, &option.key.default_value()),
+                ),
+            ],
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+async fn handle_auto_join(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+    option: SettingsAutoJoinOption,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r(ctx, cmd, guild_only(lang, "`/settings auto-join`")).await?;
+            return Ok(());
+        }
+    };
+
+    let state = app_state::get(ctx).await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+
+    match (option.voice_channel, option.text_channel) {
+        (Some(voice_channel_id), Some(text_channel_id)) => {
+            auto_join::set(
+                &mut conn,
+                auto_join::SetOption {
+                    guild_id: guild_id.into(),
+                    setting: AutoJoinSetting {
+                        voice_channel_id: voice_channel_id.into(),
+                        text_channel_id: text_channel_id.into(),
+                    },
+                },
+            )
+            .await?;
+
+            r(
+                ctx,
+                cmd,
+                messages::format(
+                    lang,
+                    Key::AutoJoinEnabled,
+                    &[("voice", &voice_channel_id), ("text", &text_channel_id)],
+                ),
+            )
+            .await?;
+        }
+        (None, None) => {
+            auto_join::remove(
+                &mut conn,
+                auto_join::RemoveOption {
+                    guild_id: guild_id.into(),
+                },
+            )
+            .await?;
+
+            r(ctx, cmd, messages::text(lang, Key::AutoJoinDisabled)).await?;
+        }
+        _ => {
+            r(ctx, cmd, messages::text(lang, Key::AutoJoinNeedsBoth)).await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn handle_read_responses(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+    option: SettingsReadResponsesOption,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r(ctx, cmd, guild_only(lang, "`/settings`")).await?;
+            return Ok(());
+        }
+    };
+
+    let state = app_state::get(ctx).await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+
+    guild_settings::set_bool(
+        &mut conn,
+        guild_settings::SetBoolOption {
+            guild_id: guild_id.into(),
+            key: BoolKey::ReadResponses,
+            value: option.enabled,
+        },
+    )
+    .await?;
+    if let Some(errors) = option.errors {
+        guild_settings::set_bool(
+            &mut conn,
+            guild_settings::SetBoolOption {
+                guild_id: guild_id.into(),
+                key: BoolKey::ReadErrorResponses,
+                value: errors,
+            },
+        )
+        .await?;
+    }
+    state.settings_cache.invalidate(guild_id);
+
+    let msg = if !option.enabled {
+        messages::text(lang, Key::ReadResponsesDisabled)
+    } else {
+        let errors = guild_settings::get_bool(
+            &mut conn,
+            guild_settings::GetBoolOption {
+                guild_id: guild_id.into(),
+                key: BoolKey::ReadErrorResponses,
+            },
+        )
+        .await?;
+        messages::format(
+            lang,
+            Key::ReadResponsesEnabled,
+            &[(
+                "errors",
+                &setting_value_label(lang, &SettingValue::Bool(errors)),
+            )],
+        )
+    };
+    r(ctx, cmd, msg).await?;
+    Ok(())
+}
+
+async fn handle_collapse_repeats(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+    option: SettingsCollapseRepeatsOption,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r(ctx, cmd, guild_only(lang, "`/settings`")).await?;
+            return Ok(());
+        }
+    };
+
+    if let Some(threshold) = option.threshold {
+        if !(MIN_REPEAT_THRESHOLD..=MAX_REPEAT_THRESHOLD).contains(&threshold) {
+            r(
+                ctx,
+                cmd,
+                messages::format(
+                    lang,
+                    Key::RepeatThresholdOutOfRange,
+                    &[
+                        ("min", &MIN_REPEAT_THRESHOLD),
+                        ("max", &MAX_REPEAT_THRESHOLD),
+                    ],
+                ),
+            )
+            .await?;
+            return Ok(());
+        }
+    }
+
+    let state = app_state::get(ctx).await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+
+    guild_settings::set_bool(
+        &mut conn,
+        guild_settings::SetBoolOption {
+            guild_id: guild_id.into(),
+            key: BoolKey::CollapseRepeats,
+            value: option.enabled,
+        },
+    )
+    .await?;
+    if let Some(threshold) = option.threshold {
+        guild_settings::set_int(
+            &mut conn,
+            guild_settings::SetIntOption {
+                guild_id: guild_id.into(),
+                key: IntKey::RepeatThreshold,
+                value: threshold,
+            },
+        )
+        .await?;
+    }
+    state.settings_cache.invalidate(guild_id);
+
+    let msg = if !option.enabled {
+        messages::text(lang, Key::CollapseRepeatsDisabled)
+    } else {
+        let threshold = guild_settings::get_int(
+            &mut conn,
+            guild_settings::GetIntOption {
+                guild_id: guild_id.into(),
+                key: IntKey::RepeatThreshold,
+            },
+        )
+        .await?;
+        messages::format(
+            lang,
+            Key::CollapseRepeatsEnabled,
+            &[("threshold", &threshold)],
+        )
+    };
+    r(ctx, cmd, msg).await?;
+    Ok(())
+}
+
+async fn handle_furigana(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+    option: SettingsFuriganaOption,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r(ctx, cmd, guild_only(lang, "`/settings`")).await?;
+            return Ok(());
+        }
+    };
+
+    let state = app_state::get(ctx).await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+
+    guild_settings::set_bool(
+        &mut conn,
+        guild_settings::SetBoolOption {
+            guild_id: guild_id.into(),
+            key: BoolKey::Furigana,
+            value: option.enabled,
+        },
+    )
+    .await?;
+    if let Some(syntax) = option.syntax {
+        guild_settings::set_choice(
+            &mut conn,
+            guild_settings::SetChoiceOption {
+                guild_id: guild_id.into(),
+                key: ChoiceKey::FuriganaSyntax,
+                value: syntax.as_str().to_string(),
+            },
+        )
+        .await?;
+    }
+    state.settings_cache.invalidate(guild_id);
+
+    let msg = if !option.enabled {
+        messages::text(lang, Key::FuriganaDisabled)
+    } else {
+        let syntax = guild_settings::get_choice(
+            &mut conn,
+            guild_settings::GetChoiceOption {
+                guild_id: guild_id.into(),
+                key: ChoiceKey::FuriganaSyntax,
+            },
+        )
+        .await?;
+        let syntax = FuriganaSyntax::from_setting(syntax.as_deref());
+        messages::format(
+            lang,
+            Key::FuriganaEnabled,
+            &[(
+                "syntax",
+                &messages::text(lang, furigana_syntax_label(syntax)),
+            )],
+        )
+    };
+    r(ctx, cmd, msg).await?;
+    Ok(())
+}
+
+async fn handle_idle_timeout(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+    option: SettingsIdleTimeoutOption,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r(ctx, cmd, guild_only(lang, "`/settings`")).await?;
+            return Ok(());
+        }
+    };
+
+    let state = app_state::get(ctx).await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+
+    let msg = match option.minutes {
+        Some(minutes) => {
+            let minutes = match minutes {
+                0..=MAX_IDLE_TIMEOUT_MINUTES => minutes,
+                _ => {
+                    r(
+                        ctx,
+                        cmd,
+                        messages::format(
+                            lang,
+                            Key::IdleTimeoutOutOfRange,
+                            &[("max", &MAX_IDLE_TIMEOUT_MINUTES)],
+                        ),
+                    )
+                    .await?;
+                    return Ok(());
+                }
+            };
+
+            guild_settings::set_optional_int(
+                &mut conn,
+                guild_settings::SetOptionalIntOption {
+                    guild_id: guild_id.into(),
+                    key: OptionalIntKey::IdleTimeout,
+                    value: minutes,
+                },
+            )
+            .await?;
+
+            if minutes == 0 {
+                messages::text(lang, Key::IdleTimeoutDisabled)
+            } else {
+                messages::format(lang, Key::IdleTimeoutSet, &[("minutes", &minutes)])
+            }
+        }
+        None => {
+            guild_settings::reset(
+                &mut conn,
+                guild_settings::ResetOption {
+                    guild_id: guild_id.into(),
+                    key: SettingKey::OptionalInt(OptionalIntKey::IdleTimeout),
+                },
+            )
+            .await?;
+
+            messages::format(
+                lang,
+                Key::IdleTimeoutReset,
+                &[("timeout", &idle_timeout_label(lang, state.idle_timeout))],
+            )
+        }
+    };
+    state.settings_cache.invalidate(guild_id);
+
+    r(ctx, cmd, msg).await?;
+    Ok(())
+}
+
+/// 読み上げが行われないまま経過すると退出する時間を、表示用の文字列にする
+pub(super) fn idle_timeout_label(lang: Language, timeout: Option<Duration>) -> String {
+    match timeout {
+        Some(timeout) => messages::format(
+            lang,
+            Key::Minutes,
+            &[("minutes", &(timeout.as_secs() / 60))],
+        ),
+        None => messages::text(lang, Key::IdleTimeoutNever),
+    }
+}
+
+async fn handle_empty_text(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+    option: SettingsEmptyTextOption,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r(ctx, cmd, guild_only(lang, "`/settings`")).await?;
+            return Ok(());
+        }
+    };
+
+    let state = app_state::get(ctx).await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+
+    let placeholder = option
+        .placeholder
+        .map(|x| x.trim().to_string())
+        .filter(|x| !x.is_empty());
+
+    match placeholder {
+        Some(placeholder) => {
+            if placeholder.chars().count() > empty_text::MAX_PLACEHOLDER_LENGTH {
+                r(
+                    ctx,
+                    cmd,
+                    messages::format(
+                        lang,
+                        Key::EmptyTextTooLong,
+                        &[("max", &empty_text::MAX_PLACEHOLDER_LENGTH)],
+                    ),
+                )
+                .await?;
+                return Ok(());
+            }
+
+            empty_text::set(
+                &mut conn,
+                empty_text::SetOption {
+                    guild_id: guild_id.into(),
+                    placeholder: placeholder.clone(),
+                },
+            )
+            .await?;
+
+            r(
+                ctx,
+                cmd,
+                messages::format(lang, Key::EmptyTextSet, &[("placeholder", &placeholder)]),
+            )
+            .await?;
+        }
+        None => {
+            empty_text::remove(
+                &mut conn,
+                empty_text::RemoveOption {
+                    guild_id: guild_id.into(),
+                },
+            )
+            .await?;
+
+            r(ctx, cmd, messages::text(lang, Key::EmptyTextRemoved)).await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn handle_farewell(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+    option: SettingsFarewellOption,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r(ctx, cmd, guild_only(lang, "`/settings`")).await?;
+            return Ok(());
+        }
+    };
+
+    let state = app_state::get(ctx).await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+
+    let text = option
+        .text
+        .map(|x| x.trim().to_string())
+        .filter(|x| !x.is_empty());
+
+    match text {
+        Some(text) => {
+            if text.chars().count() > farewell::MAX_FAREWELL_LENGTH {
+                r(
+                    ctx,
+                    cmd,
+                    messages::format(
+                        lang,
+                        Key::EmptyTextTooLong,
+                        &[("max", &farewell::MAX_FAREWELL_LENGTH)],
+                    ),
+                )
+                .await?;
+                return Ok(());
+            }
+
+            farewell::set(
+                &mut conn,
+                farewell::SetOption {
+                    guild_id: guild_id.into(),
+                    text: text.clone(),
+                },
+            )
+            .await?;
+
+            r(
+                ctx,
+                cmd,
+                messages::format(lang, Key::FarewellSet, &[("text", &text)]),
+            )
+            .await?;
+        }
+        None => {
+            farewell::remove(
+                &mut conn,
+                farewell::RemoveOption {
+                    guild_id: guild_id.into(),
+                },
+            )
+            .await?;
+
+            r(ctx, cmd, messages::text(lang, Key::FarewellRemoved)).await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn handle_mention_only(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+    option: SettingsMentionOnlyOption,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r(ctx, cmd, guild_only(lang, "`/settings`")).await?;
+            return Ok(());
+        }
+    };
+
+    let state = app_state::get(ctx).await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+
+    mention_only::set_mode(
+        &mut conn,
+        mention_only::SetModeOption {
+            guild_id: guild_id.into(),
+            mode: option.mode,
+        },
+    )
+    .await?;
+
+    r(
+        ctx,
+        cmd,
+        messages::format(
+            lang,
+            Key::MentionOnlyChanged,
+            &[(
+                "mode",
+                &messages::text(lang, mention_only_mode_label(option.mode)),
+            )],
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+/// サーバーで使う音声合成のエンジンを変更する
+/// 実行したメンバーの声が新しいエンジンで使えない場合は、その場で使える声に設定し直す
+/// ほかのメンバーは、声を選び直すまで新しいエンジンで使える声で読み上げる（設定した声は変更しない）
+async fn handle_provider(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+    option: SettingsProviderOption,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r(ctx, cmd, guild_only(lang, "`/settings`")).await?;
+            return Ok(());
+        }
+    };
+
+    let state = app_state::get(ctx).await?;
+
+    let selected = ProviderKind::parse(option.name.trim())
+        .filter(|kind| state.speech_providers.get(*kind).is_some());
+    let kind = match selected {
+        Some(kind) => kind,
+        None => {
+            let list = state
+                .speech_providers
+                .kinds()
+                .into_iter()
+                .map(|kind| kind.display_name())
+                .collect::<Vec<_>>()
+                .join(", ");
+            r(
+                ctx,
+                cmd,
+                messages::format(
+                    lang,
+                    Key::SpeechProviderUnavailable,
+                    &[("name", &sanitize_response(&option.name)), ("list", &list)],
+                ),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let mut conn = state.redis_client.get_async_connection().await?;
+    guild_settings::set_choice(
+        &mut conn,
+        guild_settings::SetChoiceOption {
+            guild_id: guild_id.into(),
+            key: ChoiceKey::SpeechProvider,
+            value: kind.as_str().to_string(),
+        },
+    )
+    .await?;
+    state.settings_cache.invalidate(guild_id);
+
+    let mut response = messages::format(
+        lang,
+        Key::SpeechProviderSet,
+        &[("provider", &kind.display_name())],
+    );
+
+    // `/voice language`で言語を設定している場合は、変更したエンジンとは別のエンジンを使うことがある
+    let (member_kind, available_presets) =
+        crate::speech_provider::list_member_voices(&state, &mut conn, guild_id, cmd.user.id)
+            .await?;
+    let available_preset_ids = available_presets
+        .into_iter()
+        .map(|p| p.id)
+        .collect::<Vec<_>>();
+    let settings = koe_db::voice::get_settings(
+        &mut conn,
+        koe_db::voice::GetSettingsOption {
+            guild_id: guild_id.into(),
+            user_id: cmd.user.id.into(),
+        },
+    )
+    .await?;
+    let usable = crate::speech_provider::usable_preset_id(
+        &settings,
+        member_kind,
+        state.speech_providers.default_kind(),
+        &available_preset_ids,
+    );
+    if settings.preset_id.is_some() && usable.is_none() {
+        let fallback_preset_id = default_voice::choose_preset_id(
+            &mut conn,
+            guild_id,
+            cmd.user.id,
+            &available_preset_ids,
+        )
+        .await?;
+        koe_db::voice::set(
+            &mut conn,
+            koe_db::voice::SetOption {
+                guild_id: guild_id.into(),
+                user_id: cmd.user.id.into(),
+                value: fallback_preset_id,
+                provider: member_kind.as_str().to_string(),
+            },
+        )
+        .await?;
+        response.push('\n');
+        response.push_str(&messages::format(
+            lang,
+            Key::VoiceResetForProvider,
+            &[("id", &fallback_preset_id)],
+        ));
+    }
+
+    r(ctx, cmd, response).await?;
+    Ok(())
+}
+
+async fn handle_read_prefix(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+    option: SettingsReadPrefixOption,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r(ctx, cmd, guild_only(lang, "`/settings`")).await?;
+            return Ok(());
+        }
+    };
+
+    let state = app_state::get(ctx).await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+
+    let prefix = option
+        .prefix
+        .map(|x| x.trim().to_string())
+        .filter(|x| !x.is_empty());
+
+    match prefix {
+        Some(prefix) => {
+            if prefix.chars().count() > read_prefix::MAX_PREFIX_LENGTH {
+                r(
+                    ctx,
+                    cmd,
+                    messages::format(
+                        lang,
+                        Key::ReadPrefixTooLong,
+                        &[("max", &read_prefix::MAX_PREFIX_LENGTH)],
+                    ),
+                )
+                .await?;
+                return Ok(());
+            }
+
+            read_prefix::set(
+                &mut conn,
+                read_prefix::SetOption {
+                    guild_id: guild_id.into(),
+                    prefix: prefix.clone(),
+                },
+            )
+            .await?;
+
+            r(
+                ctx,
+                cmd,
+                messages::format(
+                    lang,
+                    Key::ReadPrefixSet,
+                    &[("prefix", &sanitize_response(&prefix))],
+                ),
+            )
+            .await?;
+        }
+        None => {
+            read_prefix::remove(
+                &mut conn,
+                read_prefix::RemoveOption {
+                    guild_id: guild_id.into(),
+                },
+            )
+            .await?;
+
+            r(ctx, cmd, messages::text(lang, Key::ReadPrefixRemoved)).await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn handle_language(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+    option: SettingsLanguageOption,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r(ctx, cmd, guild_only(lang, "`/settings`")).await?;
+            return Ok(());
+        }
+    };
+
+    let state = app_state::get(ctx).await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+
+    // 変更後の言語で応答する
+    let msg = match option.language {
+        Some(new_lang) => {
+            language::set(
+                &mut conn,
+                language::SetOption {
+                    guild_id: guild_id.into(),
+                    language: new_lang,
+                },
+            )
+            .await?;
+
+            messages::format(
+                new_lang,
+                Key::LanguageSet,
+                &[(
+                    "language",
+                    &messages::text(new_lang, language_label(new_lang)),
+                )],
+            )
+        }
+        None => {
+            language::remove(
+                &mut conn,
+                language::RemoveOption {
+                    guild_id: guild_id.into(),
+                },
+            )
+            .await?;
+
+            let new_lang = messages::resolve(ctx, None, &cmd.locale).await;
+            messages::text(new_lang, Key::LanguageReset)
+        }
+    };
+    r(ctx, cmd, msg).await?;
+
+    Ok(())
+}
+
+async fn handle_voice_range(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+    option: SettingsVoiceRangeOption,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r(ctx, cmd, guild_only(lang, "`/settings`")).await?;
+            return Ok(());
+        }
+    };
+
+    let requests = [
+        (ScaleKind::Speed, option.speed_min, option.speed_max),
+        (ScaleKind::Pitch, option.pitch_min, option.pitch_max),
+    ];
+    let is_reset = requests
+        .iter()
+        .all(|(_, min, max)| min.is_none() && max.is_none());
+
+    // 変更する前に、すべての値を確認する
+    let mut updates = Vec::new();
+    for (kind, min, max) in requests {
+        if min.is_none() && max.is_none() {
+            continue;
+        }
+
+        let global = global_scale_range(kind);
+        let bounds = ScaleBounds {
+            min: min.unwrap_or(*global.start()),
+            max: max.unwrap_or(*global.end()),
+        };
+        if !global.contains(&bounds.min) || !global.contains(&bounds.max) {
+            r(ctx, cmd, scale_out_of_range(lang, kind, &global)).await?;
+            return Ok(());
+        }
+        if bounds.min > bounds.max {
+            r(
+                ctx,
+                cmd,
+                messages::format(
+                    lang,
+                    Key::ScaleMinAboveMax,
+                    &[("kind", &messages::text(lang, scale_kind_label(kind)))],
+                ),
+            )
+            .await?;
+            return Ok(());
+        }
+
+        updates.push((kind, bounds));
+    }
+
+    let state = app_state::get(ctx).await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+
+    if is_reset {
+        for kind in [ScaleKind::Speed, ScaleKind::Pitch] {
+            scale_bounds::remove(
+                &mut conn,
+                scale_bounds::RemoveOption {
+                    guild_id: guild_id.into(),
+                    kind,
+                },
+            )
+            .await?;
+        }
+    }
+    for (kind, bounds) in updates {
+        scale_bounds::set(
+            &mut conn,
+            scale_bounds::SetOption {
+                guild_id: guild_id.into(),
+                kind,
+                bounds,
+            },
+        )
+        .await?;
+    }
+
+    let speed_range = get_scale_range(&mut conn, guild_id, ScaleKind::Speed).await?;
+    let pitch_range = get_scale_range(&mut conn, guild_id, ScaleKind::Pitch).await?;
+    r(
+        ctx,
+        cmd,
+        messages::format(
+            lang,
+            Key::VoiceRangeSet,
+            &[
+                ("speed_min", &speed_range.start()),
+                ("speed_max", &speed_range.end()),
+                ("pitch_min", &pitch_range.start()),
+                ("pitch_max", &pitch_range.end()),
+            ],
+        ),
+    )
+    .await?;
+
+    Ok(())
+}
+
+async fn handle_system_voice(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+    option: VoiceParamsOption,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r(ctx, cmd, guild_only(lang, "`/settings`")).await?;
+            return Ok(());
+        }
+    };
+
+    if let Some(msg) = validate_scales(lang, &option, &SPEED_SCALE_RANGE, &PITCH_SCALE_RANGE) {
+        r(ctx, cmd, msg).await?;
+        return Ok(());
+    }
+
+    let state = app_state::get(ctx).await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+
+    let preset = match option.preset {
+        Some(preset_id) => {
+            let (_, provider) =
+                crate::speech_provider::for_guild(&state, &mut conn, guild_id).await?;
+            match find_preset(lang, &*provider, preset_id).await? {
+                Ok(preset) => Some(preset),
+                Err(msg) => {
+                    r(ctx, cmd, msg).await?;
+                    return Ok(());
+                }
+            }
+        }
+        None => None,
+    };
+
+    system_voice::set(
+        &mut conn,
+        system_voice::SetOption {
+            guild_id: guild_id.into(),
+            voice: system_voice::SystemVoice {
+                preset_id: option.preset,
+                speed_scale: option.speed,
+                pitch_scale: option.pitch,
+            },
+        },
+    )
+    .await?;
+
+    if option.is_empty() {
+        r(ctx, cmd, messages::text(lang, Key::SystemVoiceReset)).await?;
+        return Ok(());
+    }
+
+    r(
+        ctx,
+        cmd,
+        messages::format(
+            lang,
+            Key::SystemVoiceSet,
+            &[(
+                "summary",
+                &voice_params_summary(lang, preset.as_ref(), &option),
+            )],
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+async fn handle_embed_bot_add(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+    option: SettingsEmbedBotOption,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r(ctx, cmd, guild_only(lang, "`/settings`")).await?;
+            return Ok(());
+        }
+    };
+
+    let is_bot = match cmd.data.resolved.users.get(&option.bot) {
+        Some(user) => user.bot,
+        None => false,
+    };
+    if !is_bot {
+        r(ctx, cmd, messages::text(lang, Key::SpecifyBot)).await?;
+        return Ok(());
+    }
+
+    let state = app_state::get(ctx).await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+
+    let resp = embed_bot::add(
+        &mut conn,
+        embed_bot::AddOption {
+            guild_id: guild_id.into(),
+            user_id: option.bot.into(),
+        },
+    )
+    .await?;
+
+    let msg = match resp {
+        embed_bot::AddResponse::Success => {
+            messages::format(lang, Key::EmbedBotAdded, &[("bot", &option.bot)])
+        }
+        embed_bot::AddResponse::AlreadyExists => {
+            messages::format(lang, Key::EmbedBotExists, &[("bot", &option.bot)])
+        }
+    };
+    r(ctx, cmd, msg).await?;
+    Ok(())
+}
+
+async fn handle_embed_bot_remove(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+    option: SettingsEmbedBotOption,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r(ctx, cmd, guild_only(lang, "`/settings`")).await?;
+            return Ok(());
+        }
+    };
+
+    let state = app_state::get(ctx).await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+
+    let resp = embed_bot::remove(
+        &mut conn,
+        embed_bot::RemoveOption {
+            guild_id: guild_id.into(),
+            user_id: option.bot.into(),
+        },
+    )
+    .await?;
+
+    let msg = match resp {
+        embed_bot::RemoveResponse::Success => {
+            messages::format(lang, Key::EmbedBotRemoved, &[("bot", &option.bot)])
+        }
+        embed_bot::RemoveResponse::DoesNotExist => {
+            messages::format(lang, Key::EmbedBotNotFound, &[("bot", &option.bot)])
+        }
+    };
+    r(ctx, cmd, msg).await?;
+    Ok(())
+}
+
+async fn handle_embed_bot_list(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r(ctx, cmd, guild_only(lang, "`/settings`")).await?;
+            return Ok(());
+        }
+    };
+
+    let state = app_state::get(ctx).await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+
+    let bots = embed_bot::get_all(
+        &mut conn,
+        embed_bot::GetAllOption {
+            guild_id: guild_id.into(),
+        },
+    )
+    .await?;
+
+    let msg = if bots.is_empty() {
+        messages::text(lang, Key::EmbedBotListEmpty)
+    } else {
+        let bot_list = bots
+            .iter()
+            .map(|id| format!("<@{}>", id))
+            .collect::<Vec<_>>()
+            .join(", ");
+        messages::format(lang, Key::EmbedBotList, &[("bots", &bot_list)])
+    };
+    r(ctx, cmd, msg).await?;
+    Ok(())
+}
+
+async fn handle_permissions_set(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+    option: SettingsPermissionsSetOption,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r(ctx, cmd, guild_only(lang, "`/settings`")).await?;
+            return Ok(());
+        }
+    };
+
+    let state = app_state::get(ctx).await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+
+    command_permission::set(
+        &mut conn,
+        command_permission::SetOption {
+            guild_id: guild_id.into(),
+            command: option.command.clone(),
+            requirement: option.requirement,
+        },
+    )
+    .await?;
+
+    r(
+        ctx,
+        cmd,
+        messages::format(
+            lang,
+            Key::PermissionSet,
+            &[
+                ("command", &option.command),
+                ("requirement", &requirement_label(lang, option.requirement)),
+            ],
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+async fn handle_permissions_view(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r(ctx, cmd, guild_only(lang, "`/settings`")).await?;
+            return Ok(());
+        }
+    };
+
+    let state = app_state::get(ctx).await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+
+    let configured = command_permission::get_all(
+        &mut conn,
+        command_permission::GetAllOption {
+            guild_id: guild_id.into(),
+        },
+    )
+    .await?;
+
+    let lines = PERMISSION_COMMAND_NAMES
+        .iter()
+        .map(|name| {
+            let requirement = configured
+                .iter()
+                .find(|(command, _)| command == name)
+                .map_or(Requirement::Everyone, |(_, requirement)| *requirement);
+            messages::format(
+                lang,
+                Key::LabeledValue,
+                &[
+                    ("label", &format!("`/{}`", name)),
+                    ("value", &requirement_label(lang, requirement)),
+                ],
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let mut embed = CreateEmbed::default();
+    let guild_name = guild_id
+        .name(&ctx.cache)
+        .unwrap_or_else(|| messages::text(lang, Key::ServerFallbackName));
+    embed.title(messages::format(
+        lang,
+        Key::PermissionsTitle,
+        &[("guild", &guild_name)],
+    ));
+    embed.description(lines);
+    embed.footer(|footer| footer.text(messages::text(lang, Key::PermissionsFooter)));
+
+    respond(
+        ctx,
+        cmd,
+        CommandResponse {
+            embeds: vec![embed],
+            ..Default::default()
+        },
+    )
+    .await?;
+    Ok(())
+}
+
+fn language_label(lang: Language) -> Key {
+    match lang {
+        Language::Japanese => Key::LanguageJapanese,
+        Language::English => Key::LanguageEnglish,
+    }
+}
+
+fn setting_label(key: SettingKey) -> Key {
+    match key {
+        SettingKey::Bool(BoolKey::ReadReactions) => Key::ToggleReadReactions,
+        SettingKey::Bool(BoolKey::ReadDeletions) => Key::ToggleReadDeletions,
+        SettingKey::Bool(BoolKey::ReadAltText) => Key::ToggleReadAltText,
+        SettingKey::Bool(BoolKey::ReadPolls) => Key::ToggleReadPolls,
+        SettingKey::Bool(BoolKey::ReadAuthorRole) => Key::ToggleReadAuthorRole,
+        SettingKey::Bool(BoolKey::ShowDictReadings) => Key::ToggleShowDictReadings,
+        SettingKey::Bool(BoolKey::ReadScheduledEvents) => Key::ToggleReadScheduledEvents,
+        SettingKey::Bool(BoolKey::NormalizeWidth) => Key::ToggleNormalizeWidth,
+        SettingKey::Bool(BoolKey::ReadVoiceChat) => Key::ToggleReadVoiceChat,
+        SettingKey::Bool(BoolKey::Chime) => Key::ToggleChime,
+        SettingKey::Bool(BoolKey::FollowUsers) => Key::ToggleFollowUsers,
+        SettingKey::Bool(BoolKey::VoiceByUserId) => Key::ToggleVoiceByUserId,
+        SettingKey::Bool(BoolKey::CollapseRepeats) => Key::SettingCollapseRepeatsLabel,
+        SettingKey::Bool(BoolKey::Furigana) => Key::ToggleFurigana,
+        SettingKey::Bool(BoolKey::ReadResponses) => Key::ToggleReadResponses,
+        SettingKey::Bool(BoolKey::ReadErrorResponses) => Key::ToggleReadErrorResponses,
+        SettingKey::Int(IntKey::RepeatThreshold) => Key::SettingRepeatThresholdLabel,
+        SettingKey::Int(IntKey::MaxWords) => Key::SettingMaxWordsLabel,
+        SettingKey::Text(TextKey::MessageTemplate) => Key::SettingMessageTemplateLabel,
+        SettingKey::OptionalInt(OptionalIntKey::IdleTimeout) => Key::SettingIdleTimeoutLabel,
+        SettingKey::Choice(ChoiceKey::FuriganaSyntax) => Key::SettingFuriganaSyntaxLabel,
+        SettingKey::Choice(ChoiceKey::SpeechProvider) => Key::SettingSpeechProviderLabel,
+        SettingKey::Channel(ChannelKey::NoticeChannel) => Key::SettingNoticeChannelLabel,
+    }
+}
+
+fn parse_settings(option_settings: &CommandDataOption) -> Result<Command, CommandParseError> {
+    let options = &option_settings.options;
+
+    // 音声合成のエンジンは、設定ファイルで有効なエンジンか確かめる`/settings provider`でのみ変更する
+    let parse_key = || -> Result<SettingKey, CommandParseError> {
+        let key = required(find_string(options, "key")?, "key")?;
+        SettingKey::parse(&key)
+            .filter(|key| *key != SettingKey::Choice(ChoiceKey::SpeechProvider))
+            .ok_or_else(|| invalid("key", key))
+    };
+
+    match option_settings.name.as_str() {
+        "view" => Ok(Command::SettingsView),
+        "set" => Ok(Command::SettingsSet(SettingsSetOption {
+            key: parse_key()?,
+            value: required(find_string(options, "value")?, "value")?,
+        })),
+        "reset" => Ok(Command::SettingsReset(SettingsResetOption {
+            key: parse_key()?,
+        })),
+        "auto-join" => Ok(Command::SettingsAutoJoin(SettingsAutoJoinOption {
+            voice_channel: find_channel(options, "channel")?,
+            text_channel: find_channel(options, "text")?,
+        })),
+        "collapse-repeats" => Ok(Command::SettingsCollapseRepeats(
+            SettingsCollapseRepeatsOption {
+                enabled: required(find_boolean(options, "enabled")?, "enabled")?,
+                threshold: find_integer(options, "threshold")?,
+            },
+        )),
+        "furigana" => Ok(Command::SettingsFurigana(SettingsFuriganaOption {
+            enabled: required(find_boolean(options, "enabled")?, "enabled")?,
+            syntax: match find_string(options, "syntax")? {
+                Some(syntax) => {
+                    Some(FuriganaSyntax::parse(&syntax).ok_or_else(|| invalid("syntax", syntax))?)
+                }
+                None => None,
+            },
+        })),
+        "read-responses" => Ok(Command::SettingsReadResponses(
+            SettingsReadResponsesOption {
+                enabled: required(find_boolean(options, "enabled")?, "enabled")?,
+                errors: find_boolean(options, "errors")?,
+            },
+        )),
+        "idle-timeout" => Ok(Command::SettingsIdleTimeout(SettingsIdleTimeoutOption {
+            minutes: find_integer(options, "minutes")?,
+        })),
+        "empty-text" => Ok(Command::SettingsEmptyText(SettingsEmptyTextOption {
+            placeholder: find_string(options, "placeholder")?,
+        })),
+        "read-prefix" => Ok(Command::SettingsReadPrefix(SettingsReadPrefixOption {
+            prefix: find_string(options, "prefix")?,
+        })),
+        "provider" => Ok(Command::SettingsProvider(SettingsProviderOption {
+            name: required(find_string(options, "name")?, "name")?,
+        })),
+        "farewell" => Ok(Command::SettingsFarewell(SettingsFarewellOption {
+            text: find_string(options, "text")?,
+        })),
+        "mention-only" => {
+            let mode = required(find_string(options, "mode")?, "mode")?;
+            let mode = MentionOnlyMode::parse(&mode).ok_or_else(|| invalid("mode", mode))?;
+
+            Ok(Command::SettingsMentionOnly(SettingsMentionOnlyOption {
+                mode,
+            }))
+        }
+        "language" => {
+            // `auto`は省略した場合と同じく、Discordの言語設定に合わせる
+            let language = match find_string(options, "language")? {
+                Some(language) if language == "auto" => None,
+                Some(language) => {
+                    Some(Language::parse(&language).ok_or_else(|| invalid("language", language))?)
+                }
+                None => None,
+            };
+
+            Ok(Command::SettingsLanguage(SettingsLanguageOption {
+                language,
+            }))
+        }
+        "voice-range" => Ok(Command::SettingsVoiceRange(SettingsVoiceRangeOption {
+            speed_min: find_number(options, "speed-min")?,
+            speed_max: find_number(options, "speed-max")?,
+            pitch_min: find_number(options, "pitch-min")?,
+            pitch_max: find_number(options, "pitch-max")?,
+        })),
+        "system-voice" => Ok(Command::SettingsSystemVoice(voice::parse_voice_params(
+            option_settings,
+        )?)),
+        "embed-bots" => parse_settings_embed_bots(find_subcommand("settings embed-bots", options)?),
+        "permissions" => {
+            parse_settings_permissions(find_subcommand("settings permissions", options)?)
+        }
+        _ => Err(unknown_subcommand("settings", option_settings)),
+    }
+}
+
+fn parse_settings_embed_bots(
+    option_subcommand: &CommandDataOption,
+) -> Result<Command, CommandParseError> {
+    let parse_bot = || -> Result<SettingsEmbedBotOption, CommandParseError> {
+        Ok(SettingsEmbedBotOption {
+            bot: required(find_user(&option_subcommand.options, "bot")?, "bot")?,
+        })
+    };
+
+    match option_subcommand.name.as_str() {
+        "add" => Ok(Command::SettingsEmbedBotAdd(parse_bot()?)),
+        "remove" => Ok(Command::SettingsEmbedBotRemove(parse_bot()?)),
+        "list" => Ok(Command::SettingsEmbedBotList),
+        _ => Err(unknown_subcommand("settings embed-bots", option_subcommand)),
+    }
+}
+
+fn parse_settings_permissions(
+    option_subcommand: &CommandDataOption,
+) -> Result<Command, CommandParseError> {
+    let options = &option_subcommand.options;
+
+    match option_subcommand.name.as_str() {
+        "set" => {
+            let command = required(find_string(options, "command")?, "command")?;
+            if !PERMISSION_COMMAND_NAMES.contains(&command.as_str()) {
+                return Err(invalid("command", command));
+            }
+
+            let level = required(find_string(options, "level")?, "level")?;
+            let requirement = match level.as_str() {
+                "everyone" => Requirement::Everyone,
+                "in_voice_channel" => Requirement::InVoiceChannel,
+                "role" => Requirement::Role(required(find_role(options, "role")?, "role")?.into()),
+                "manage_guild" => Requirement::ManageGuild,
+                _ => return Err(invalid("level", level)),
+            };
+
+            Ok(Command::SettingsPermissionsSet(
+                SettingsPermissionsSetOption {
+                    command,
+                    requirement,
+                },
+            ))
+        }
+        "view" => Ok(Command::SettingsPermissionsView),
+        _ => Err(unknown_subcommand(
+            "settings permissions",
+            option_subcommand,
+        )),
+    }
+}
@@ -0,0 +1,441 @@
+use super::{
+    handler::{
+        furigana_syntax_label, guild_only, mention_only_mode_label, r, respond, sanitize_response,
+        setting_value_label, summarize_lines, voice_params_summary, CommandResponse,
+        MAX_EMBED_DESCRIPTION_LENGTH,
+    },
+    model::VoiceParamsOption,
+    settings,
+};
+use crate::{
+    app_state, idle,
+    messages::{self, Key},
+    speech_provider,
+};
+use anyhow::Result;
+use koe_db::{
+    auto_join, embed_bot, empty_text, farewell,
+    furigana::FuriganaSyntax,
+    guild_settings::{
+        self, BoolKey, ChannelKey, ChoiceKey, IntKey, SettingKey, SettingValue, TextKey,
+    },
+    language::{self, Language},
+    mention_only, read_prefix,
+    scale_bounds::{self, ScaleBounds, ScaleKind},
+    system_voice,
+};
+use koe_speech::speech::{PITCH_SCALE_RANGE, SPEED_SCALE_RANGE};
+use serenity::{
+    builder::CreateEmbed, client::Context,
+    model::application::interaction::application_command::ApplicationCommandInteraction,
+};
+
+pub(super) async fn handle_view(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r(ctx, cmd, guild_only(lang, "`/settings`")).await?;
+            return Ok(());
+        }
+    };
+
+    let state = app_state::get(ctx).await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+    let db_guild_id = u64::from(guild_id);
+
+    let mut lines = Vec::new();
+    let mut push = |name: &str, value: String| {
+        lines.push(messages::format(
+            lang,
+            Key::LabeledValue,
+            &[("label", &format!("`{}`", name)), ("value", &value)],
+        ));
+    };
+
+    let auto_join = auto_join::get(
+        &mut conn,
+        auto_join::GetOption {
+            guild_id: db_guild_id,
+        },
+    )
+    .await?;
+    push(
+        "auto-join",
+        match auto_join {
+            Some(setting) => messages::format(
+                lang,
+                Key::SettingAutoJoin,
+                &[
+                    ("voice", &setting.voice_channel_id),
+                    ("text", &setting.text_channel_id),
+                ],
+            ),
+            None => messages::text(lang, Key::SettingDisabled),
+        },
+    );
+
+    // `/settings set`で変更する項目は、指定する名前で表示する
+    for key in [
+        BoolKey::ReadReactions,
+        BoolKey::ReadDeletions,
+        BoolKey::ReadAltText,
+        BoolKey::ReadPolls,
+        BoolKey::ReadAuthorRole,
+        BoolKey::ShowDictReadings,
+        BoolKey::ReadScheduledEvents,
+        BoolKey::NormalizeWidth,
+        BoolKey::ReadVoiceChat,
+        BoolKey::Chime,
+        BoolKey::FollowUsers,
+        BoolKey::VoiceByUserId,
+    ] {
+        let enabled = guild_settings::get_bool(
+            &mut conn,
+            guild_settings::GetBoolOption {
+                guild_id: db_guild_id,
+                key,
+            },
+        )
+        .await?;
+        push(
+            SettingKey::Bool(key).name(),
+            setting_value_label(lang, &SettingValue::Bool(enabled)),
+        );
+    }
+
+    let collapse_repeats = guild_settings::get_bool(
+        &mut conn,
+        guild_settings::GetBoolOption {
+            guild_id: db_guild_id,
+            key: BoolKey::CollapseRepeats,
+        },
+    )
+    .await?;
+    let repeat_threshold = guild_settings::get_int(
+        &mut conn,
+        guild_settings::GetIntOption {
+            guild_id: db_guild_id,
+            key: IntKey::RepeatThreshold,
+        },
+    )
+    .await?;
+    push(
+        "collapse-repeats",
+        if collapse_repeats {
+            messages::format(
+                lang,
+                Key::SettingCollapseRepeats,
+                &[("threshold", &repeat_threshold)],
+            )
+        } else {
+            messages::text(lang, Key::SettingDisabled)
+        },
+    );
+
+    let furigana_enabled = guild_settings::get_bool(
+        &mut conn,
+        guild_settings::GetBoolOption {
+            guild_id: db_guild_id,
+            key: BoolKey::Furigana,
+        },
+    )
+    .await?;
+    let furigana_syntax = guild_settings::get_choice(
+        &mut conn,
+        guild_settings::GetChoiceOption {
+            guild_id: db_guild_id,
+            key: ChoiceKey::FuriganaSyntax,
+        },
+    )
+    .await?;
+    let furigana_syntax = FuriganaSyntax::from_setting(furigana_syntax.as_deref());
+    push(
+        "furigana",
+        if furigana_enabled {
+            messages::format(
+                lang,
+                Key::SettingFurigana,
+                &[(
+                    "syntax",
+                    &messages::text(lang, furigana_syntax_label(furigana_syntax)),
+                )],
+            )
+        } else {
+            messages::text(lang, Key::SettingDisabled)
+        },
+    );
+
+    let max_words = guild_settings::get_int(
+        &mut conn,
+        guild_settings::GetIntOption {
+            guild_id: db_guild_id,
+            key: IntKey::MaxWords,
+        },
+    )
+    .await?;
+    push(
+        "max-words",
+        match max_words {
+            0 => messages::text(lang, Key::SettingDisabled),
+            max => max.to_string(),
+        },
+    );
+
+    let read_responses = guild_settings::get_bool(
+        &mut conn,
+        guild_settings::GetBoolOption {
+            guild_id: db_guild_id,
+            key: BoolKey::ReadResponses,
+        },
+    )
+    .await?;
+    let read_error_responses = guild_settings::get_bool(
+        &mut conn,
+        guild_settings::GetBoolOption {
+            guild_id: db_guild_id,
+            key: BoolKey::ReadErrorResponses,
+        },
+    )
+    .await?;
+    push(
+        "read-responses",
+        if read_responses {
+            messages::format(
+                lang,
+                Key::SettingReadResponses,
+                &[(
+                    "errors",
+                    &setting_value_label(lang, &SettingValue::Bool(read_error_responses)),
+                )],
+            )
+        } else {
+            messages::text(lang, Key::SettingDisabled)
+        },
+    );
+
+    let empty_text = empty_text::get(
+        &mut conn,
+        empty_text::GetOption {
+            guild_id: db_guild_id,
+        },
+    )
+    .await?;
+    push(
+        "empty-text",
+        match empty_text {
+            Some(placeholder) => format!("「{}」", sanitize_response(&placeholder)),
+            None => messages::text(lang, Key::SettingSkip),
+        },
+    );
+
+    let idle_timeout = idle::resolve_timeout(ctx, guild_id).await?;
+    push(
+        "idle-timeout",
+        settings::idle_timeout_label(lang, idle_timeout),
+    );
+
+    let notice_channel = guild_settings::get_channel(
+        &mut conn,
+        guild_settings::GetChannelOption {
+            guild_id: db_guild_id,
+            key: ChannelKey::NoticeChannel,
+        },
+    )
+    .await?;
+    push(
+        SettingKey::Channel(ChannelKey::NoticeChannel).name(),
+        match notice_channel {
+            Some(channel_id) => setting_value_label(lang, &SettingValue::Channel(channel_id)),
+            None => messages::text(lang, Key::SettingNoticeChannelDefault),
+        },
+    );
+
+    let farewell = farewell::get(
+        &mut conn,
+        farewell::GetOption {
+            guild_id: db_guild_id,
+        },
+    )
+    .await?;
+    push(
+        "farewell",
+        match farewell {
+            Some(text) => format!("「{}」", sanitize_response(&text)),
+            None => messages::text(lang, Key::Nothing),
+        },
+    );
+
+    let message_template = guild_settings::get_text(
+        &mut conn,
+        guild_settings::GetTextOption {
+            guild_id: db_guild_id,
+            key: TextKey::MessageTemplate,
+        },
+    )
+    .await?;
+    push(
+        "message-template",
+        setting_value_label(lang, &SettingValue::Text(message_template)),
+    );
+
+    let mention_only_mode = mention_only::get_mode(
+        &mut conn,
+        mention_only::GetModeOption {
+            guild_id: db_guild_id,
+        },
+    )
+    .await?;
+    push(
+        "mention-only",
+        messages::text(lang, mention_only_mode_label(mention_only_mode)),
+    );
+
+    let read_prefix = read_prefix::get(
+        &mut conn,
+        read_prefix::GetOption {
+            guild_id: db_guild_id,
+        },
+    )
+    .await?;
+    push(
+        "read-prefix",
+        match read_prefix {
+            Some(prefix) => format!("`{}`", prefix.replace('`', "")),
+            None => messages::text(lang, Key::SettingReadAll),
+        },
+    );
+
+    let (provider, _) = speech_provider::for_guild(&state, &mut conn, guild_id).await?;
+    push("provider", provider.display_name().to_string());
+
+    let guild_language = language::get(
+        &mut conn,
+        language::GetOption {
+            guild_id: db_guild_id,
+        },
+    )
+    .await?;
+    push(
+        "language",
+        match guild_language {
+            Some(Language::Japanese) => messages::text(lang, Key::LanguageJapanese),
+            Some(Language::English) => messages::text(lang, Key::LanguageEnglish),
+            None => messages::text(lang, Key::SettingLanguageFollow),
+        },
+    );
+
+    let speed_bounds = scale_bounds::get(
+        &mut conn,
+        scale_bounds::GetOption {
+            guild_id: db_guild_id,
+            kind: ScaleKind::Speed,
+        },
+    )
+    .await?;
+    let pitch_bounds = scale_bounds::get(
+        &mut conn,
+        scale_bounds::GetOption {
+            guild_id: db_guild_id,
+            kind: ScaleKind::Pitch,
+        },
+    )
+    .await?;
+    push(
+        "voice-range",
+        if speed_bounds.is_none() && pitch_bounds.is_none() {
+            messages::text(lang, Key::DefaultValue)
+        } else {
+            let speed = speed_bounds.unwrap_or(ScaleBounds {
+                min: *SPEED_SCALE_RANGE.start(),
+                max: *SPEED_SCALE_RANGE.end(),
+            });
+            let pitch = pitch_bounds.unwrap_or(ScaleBounds {
+                min: *PITCH_SCALE_RANGE.start(),
+                max: *PITCH_SCALE_RANGE.end(),
+            });
+            messages::format(
+                lang,
+                Key::SettingVoiceRange,
+                &[
+                    ("speed_min", &speed.min),
+                    ("speed_max", &speed.max),
+                    ("pitch_min", &pitch.min),
+                    ("pitch_max", &pitch.max),
+                ],
+            )
+        },
+    );
+
+    let system_voice = system_voice::get(
+        &mut conn,
+        system_voice::GetOption {
+            guild_id: db_guild_id,
+        },
+    )
+    .await?;
+    let system_voice = VoiceParamsOption {
+        preset: system_voice.preset_id,
+        speed: system_voice.speed_scale,
+        pitch: system_voice.pitch_scale,
+    };
+    push(
+        "system-voice",
+        if system_voice.is_empty() {
+            messages::text(lang, Key::DefaultValue)
+        } else {
+            let (kind, provider) = speech_provider::for_guild(&state, &mut conn, guild_id).await?;
+            let presets = speech_provider::cached_voices(&state, kind, provider).await?;
+            let preset = system_voice
+                .preset
+                .and_then(|id| presets.iter().find(|preset| preset.id == id));
+            voice_params_summary(lang, preset, &system_voice)
+        },
+    );
+
+    let embed_bots = embed_bot::get_all(
+        &mut conn,
+        embed_bot::GetAllOption {
+            guild_id: db_guild_id,
+        },
+    )
+    .await?;
+    push(
+        "embed-bots",
+        if embed_bots.is_empty() {
+            messages::text(lang, Key::Nothing)
+        } else {
+            embed_bots
+                .iter()
+                .map(|bot| format!("<@{}>", bot))
+                .collect::<Vec<_>>()
+                .join(&messages::text(lang, Key::ListSeparator))
+        },
+    );
+
+    let mut embed = CreateEmbed::default();
+    let guild_name = guild_id
+        .name(&ctx.cache)
+        .unwrap_or_else(|| messages::text(lang, Key::ServerFallbackName));
+    embed.title(messages::format(
+        lang,
+        Key::SettingsViewTitle,
+        &[("guild", &guild_name)],
+    ));
+    embed.description(summarize_lines(lang, &lines, MAX_EMBED_DESCRIPTION_LENGTH));
+    embed.footer(|footer| footer.text(messages::text(lang, Key::SettingsViewFooter)));
+
+    respond(
+        ctx,
+        cmd,
+        CommandResponse {
+            embeds: vec![embed],
+            ..Default::default()
+        },
+    )
+    .await?;
+    Ok(())
+}
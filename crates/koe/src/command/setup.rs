@@ -1,96 +1,318 @@
+use super::registry::{self, CommandSpec, OptionKind, OptionSpec};
+use crate::app_state;
 use anyhow::{Context as _, Result};
+use log::info;
 use serenity::{
+    builder::{
+        CreateApplicationCommand, CreateApplicationCommandOption, CreateApplicationCommands,
+    },
     client::Context,
-    model::{application::command::CommandOptionType, id::GuildId},
+    model::{
+        application::command::{Command, CommandOptionType},
+        id::GuildId,
+        Permissions,
+    },
 };
 
+/// `/voice boost`で指定できる音量の倍率の範囲
+pub const MIN_VOLUME_BOOST: f64 = 0.25;
+pub const MAX_VOLUME_BOOST: f64 = 2.0;
+
+/// `/settings collapse-repeats`で指定できる閾値の範囲
+pub use koe_db::guild_settings::{MAX_REPEAT_THRESHOLD, MIN_REPEAT_THRESHOLD};
+
+/// メッセージのコンテキストメニューに表示する、メッセージを読み上げるコマンドの名前
+pub const READ_MESSAGE_COMMAND_NAME: &str = "この発言を読み上げ";
+/// ユーザーのコンテキストメニューに表示する、メンバーの声の設定を表示するコマンドの名前
+pub const SHOW_VOICE_COMMAND_NAME: &str = "読み上げ設定を表示";
+
+/// 英語の名前と説明を表示するDiscordの言語設定
+const ENGLISH_LOCALES: [&str; 2] = ["en-US", "en-GB"];
+
+/// スラッシュコマンドを登録する
+/// 開発用のサーバーが設定されている場合はそのサーバーのみに、そうでなければグローバルコマンドとして登録する
+pub async fn setup_commands(ctx: &Context) -> Result<()> {
+    registry::validate().context("Invalid command definitions")?;
+
+    let state = app_state::get(ctx).await?;
+
+    match state.dev_guild_id {
+        Some(guild_id) => setup_guild_commands(ctx, guild_id).await,
+        None => setup_global_commands(ctx).await,
+    }
+}
+
+/// 開発用のサーバー以外にサーバーごとのコマンドが残っていれば削除する
+/// 以前のバージョンや開発用の設定で登録したコマンドが、グローバルコマンドと重複して表示されないようにする
+pub async fn clean_up_guild_commands(ctx: &Context, guild_id: GuildId) -> Result<()> {
+    let state = app_state::get(ctx).await?;
+    if state.dev_guild_id == Some(guild_id) {
+        return Ok(());
+    }
+
+    clear_guild_commands(ctx, guild_id).await
+}
+
+/// スラッシュコマンドを`guild_id`のサーバーのみに登録する
+/// 登録済みのコマンドはすべて置き換えられ、定義から削除されたコマンドは削除される
 pub async fn setup_guild_commands(ctx: &Context, guild_id: GuildId) -> Result<()> {
-    guild_id
-        .set_application_commands(&ctx.http, |commands| {
-            commands
-                .create_application_command(|command| {
-                    command.name("help").description("使い方を表示")
-                })
-                .create_application_command(|command| {
-                    command
-                        .name("join")
-                        .description("ボイスチャンネルに接続し、読み上げを開始")
-                })
-                .create_application_command(|command| {
-                    command
-                        .name("kjoin")
-                        .description("ボイスチャンネルに接続し、読み上げを開始")
-                })
-                .create_application_command(|command| {
-                    command
-                        .name("leave")
-                        .description("ボイスチャンネルから退出")
-                })
-                .create_application_command(|command| {
-                    command
-                        .name("kleave")
-                        .description("ボイスチャンネルから退出")
-                })
-                .create_application_command(|command| {
-                    command
-                        .name("skip")
-                        .description("読み上げ中のメッセージをスキップ")
-                })
-                .create_application_command(|command| {
-                    command
-                        .name("kskip")
-                        .description("読み上げ中のメッセージをスキップ")
-                })
-                .create_application_command(|command| {
-                    command.name("voice").description("話者の設定")
-                })
-                .create_application_command(|command| {
-                    command
-                        .name("dict")
-                        .description("読み上げ辞書の閲覧と編集")
-                        .create_option(|option| {
-                            option
-                                .name("add")
-                                .description("辞書に項目を追加")
-                                .kind(CommandOptionType::SubCommand)
-                                .create_sub_option(|option| {
-                                    option
-                                        .name("word")
-                                        .description("読み方を指定したい語句")
-                                        .kind(CommandOptionType::String)
-                                        .required(true)
-                                })
-                                .create_sub_option(|option| {
-                                    option
-                                        .name("read-as")
-                                        .description("語句の読み方")
-                                        .kind(CommandOptionType::String)
-                                        .required(true)
-                                })
-                        })
-                        .create_option(|option| {
-                            option
-                                .name("remove")
-                                .description("辞書から項目を削除")
-                                .kind(CommandOptionType::SubCommand)
-                                .create_sub_option(|option| {
-                                    option
-                                        .name("word")
-                                        .description("削除したい語句")
-                                        .kind(CommandOptionType::String)
-                                        .required(true)
-                                })
-                        })
-                        .create_option(|option| {
-                            option
-                                .name("view")
-                                .description("辞書を表示")
-                                .kind(CommandOptionType::SubCommand)
-                        })
-                })
-        })
+    let before = guild_id
+        .get_application_commands(&ctx.http)
+        .await
+        .context("Failed to get guild application commands")?;
+    let after = guild_id
+        .set_application_commands(&ctx.http, create_commands)
         .await
         .context("Failed to set guild application commands")?;
 
+    log_changes(&format!("guild {}", guild_id), &before, &after);
+    Ok(())
+}
+
+/// スラッシュコマンドをすべてのサーバーで使えるグローバルコマンドとして登録する
+/// 登録済みのコマンドはすべて置き換えられ、定義から削除されたコマンドは削除される
+pub async fn setup_global_commands(ctx: &Context) -> Result<()> {
+    let before = Command::get_global_application_commands(&ctx.http)
+        .await
+        .context("Failed to get global application commands")?;
+    let after = Command::set_global_application_commands(&ctx.http, create_commands)
+        .await
+        .context("Failed to set global application commands")?;
+
+    log_changes("global", &before, &after);
+    Ok(())
+}
+
+/// `guild_id`のサーバーに登録されたスラッシュコマンドをすべて削除する
+pub async fn clear_guild_commands(ctx: &Context, guild_id: GuildId) -> Result<()> {
+    let before = guild_id
+        .get_application_commands(&ctx.http)
+        .await
+        .context("Failed to get guild application commands")?;
+    if before.is_empty() {
+        return Ok(());
+    }
+
+    guild_id
+        .set_application_commands(&ctx.http, |commands| commands)
+        .await
+        .context("Failed to clear guild application commands")?;
+
+    log_changes(&format!("guild {}", guild_id), &before, &[]);
     Ok(())
 }
+
+/// 登録前後のコマンドを比べて、作成・更新・削除したコマンドの名前をログに出力する
+/// Discordはコマンドの内容が変わった場合にのみバージョンを更新するため、バージョンで更新の有無を判断する
+fn log_changes(scope: &str, before: &[Command], after: &[Command]) {
+    let find = |commands: &[Command], command: &Command| {
+        commands
+            .iter()
+            .find(|x| x.name == command.name && x.kind == command.kind)
+            .map(|x| x.version)
+    };
+
+    let mut created = Vec::new();
+    let mut updated = Vec::new();
+    for command in after {
+        match find(before, command) {
+            None => created.push(command.name.as_str()),
+            Some(version) if version != command.version => updated.push(command.name.as_str()),
+            Some(_) => {}
+        }
+    }
+    let deleted = before
+        .iter()
+        .filter(|command| find(after, command).is_none())
+        .map(|command| command.name.as_str())
+        .collect::<Vec<_>>();
+
+    if created.is_empty() && updated.is_empty() && deleted.is_empty() {
+        info!("Application commands ({}) are up to date", scope);
+    } else {
+        info!(
+            "Updated application commands ({}): created {:?}, updated {:?}, deleted {:?}",
+            scope, created, updated, deleted
+        );
+    }
+}
+
+/// [`registry::COMMANDS`]と[`registry::CONTEXT_MENUS`]の定義から、登録するコマンドを組み立てる
+fn create_commands(commands: &mut CreateApplicationCommands) -> &mut CreateApplicationCommands {
+    for spec in registry::COMMANDS.iter().map(|x| x.spec()) {
+        for name in std::iter::once(spec.name).chain(spec.aliases.iter().copied()) {
+            commands.create_application_command(|command| create_command(command, name, spec));
+        }
+    }
+
+    for spec in registry::CONTEXT_MENUS.iter().map(|x| x.spec()) {
+        commands.create_application_command(|command| {
+            command
+                .name(spec.name)
+                .name_en(spec.name_en)
+                .kind(spec.kind)
+                .dm_permission(false)
+        });
+    }
+
+    commands
+}
+
+fn create_command<'a>(
+    command: &'a mut CreateApplicationCommand,
+    name: &str,
+    spec: &CommandSpec,
+) -> &'a mut CreateApplicationCommand {
+    command
+        .name(name)
+        .description(spec.description)
+        .description_en(spec.description_en)
+        .dm_permission(spec.dm_allowed);
+    if spec.manage_guild_only {
+        command.default_member_permissions(Permissions::MANAGE_GUILD);
+    }
+    for option_spec in spec.options {
+        command.create_option(|option| create_option(option, option_spec));
+    }
+
+    command
+}
+
+fn create_option<'a>(
+    option: &'a mut CreateApplicationCommandOption,
+    spec: &OptionSpec,
+) -> &'a mut CreateApplicationCommandOption {
+    option
+        .name(spec.name)
+        .description(spec.description)
+        .description_en(spec.description_en);
+    if spec.required {
+        option.required(true);
+    }
+
+    match spec.kind {
+        OptionKind::SubCommand(sub_options) | OptionKind::SubCommandGroup(sub_options) => {
+            option.kind(match spec.kind {
+                OptionKind::SubCommandGroup(_) => CommandOptionType::SubCommandGroup,
+                _ => CommandOptionType::SubCommand,
+            });
+            for sub_option_spec in sub_options {
+                option.create_sub_option(|sub_option| create_option(sub_option, sub_option_spec));
+            }
+        }
+        OptionKind::String {
+            choices,
+            autocomplete,
+        } => {
+            option.kind(CommandOptionType::String);
+            if autocomplete {
+                option.set_autocomplete(true);
+            }
+            for choice in choices {
+                match choice.name_en {
+                    Some(name_en) => {
+                        option.add_string_choice_en(choice.name, name_en, choice.value)
+                    }
+                    None => option.add_string_choice(choice.name, choice.value),
+                };
+            }
+        }
+        OptionKind::Integer {
+            min,
+            max,
+            autocomplete,
+        } => {
+            option.kind(CommandOptionType::Integer);
+            if autocomplete {
+                option.set_autocomplete(true);
+            }
+            if let Some(min) = min {
+                option.min_int_value(min);
+            }
+            if let Some(max) = max {
+                option.max_int_value(max);
+            }
+        }
+        OptionKind::Number { min, max } => {
+            option.kind(CommandOptionType::Number);
+            if let Some(min) = min {
+                option.min_number_value(min);
+            }
+            if let Some(max) = max {
+                option.max_number_value(max);
+            }
+        }
+        OptionKind::Boolean => {
+            option.kind(CommandOptionType::Boolean);
+        }
+        OptionKind::User => {
+            option.kind(CommandOptionType::User);
+        }
+        OptionKind::Role => {
+            option.kind(CommandOptionType::Role);
+        }
+        OptionKind::Channel(channel_types) => {
+            option
+                .kind(CommandOptionType::Channel)
+                .channel_types(channel_types);
+        }
+        OptionKind::Attachment => {
+            option.kind(CommandOptionType::Attachment);
+        }
+    }
+
+    option
+}
+
+/// 英語の言語設定のクライアントで表示する、コマンドやオプションの名前と説明を設定する
+/// 日本語の名前と説明は既定値として設定し、それ以外の言語設定でもそのまま表示する
+trait LocalizeEnglish {
+    fn name_en(&mut self, name: &str) -> &mut Self;
+    fn description_en(&mut self, description: &str) -> &mut Self;
+}
+
+impl LocalizeEnglish for CreateApplicationCommand {
+    fn name_en(&mut self, name: &str) -> &mut Self {
+        for locale in ENGLISH_LOCALES {
+            self.name_localized(locale, name);
+        }
+        self
+    }
+
+    fn description_en(&mut self, description: &str) -> &mut Self {
+        for locale in ENGLISH_LOCALES {
+            self.description_localized(locale, description);
+        }
+        self
+    }
+}
+
+impl LocalizeEnglish for CreateApplicationCommandOption {
+    fn name_en(&mut self, name: &str) -> &mut Self {
+        for locale in ENGLISH_LOCALES {
+            self.name_localized(locale, name);
+        }
+        self
+    }
+
+    fn description_en(&mut self, description: &str) -> &mut Self {
+        for locale in ENGLISH_LOCALES {
+            self.description_localized(locale, description);
+        }
+        self
+    }
+}
+
+trait LocalizeEnglishChoice {
+    fn add_string_choice_en(&mut self, name: &str, name_en: &str, value: &str) -> &mut Self;
+}
+
+impl LocalizeEnglishChoice for CreateApplicationCommandOption {
+    fn add_string_choice_en(&mut self, name: &str, name_en: &str, value: &str) -> &mut Self {
+        self.add_string_choice_localized(
+            name,
+            value,
+            ENGLISH_LOCALES.map(|locale| (locale, name_en)),
+        )
+    }
+}
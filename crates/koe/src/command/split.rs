@@ -0,0 +1,208 @@
+use serenity::{builder::CreateEmbed, json::Value};
+
+/// メッセージの本文の最大文字数
+pub const MAX_CONTENT_LENGTH: usize = 2000;
+/// 1つのメッセージに含められる埋め込みの最大数
+const MAX_EMBEDS_PER_MESSAGE: usize = 10;
+/// 1つのメッセージに含まれる埋め込みの文字数の合計の上限
+const MAX_EMBED_TOTAL_LENGTH: usize = 6000;
+
+/// 本文を`max`文字以下に分割する
+/// なるべく行の区切りで分割し、1行が`max`文字を超える場合のみ行の途中で分割する
+pub fn split_content(content: &str, max: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut current_len = 0;
+    // `current`に1行以上含まれているか（空行のみの場合も含む）
+    let mut has_line = false;
+
+    for line in content.split('\n') {
+        let mut line = line;
+        let mut line_len = line.chars().count();
+
+        if has_line {
+            // 区切りの改行は、分割する場合はどちらのまとまりにも含めない
+            if current_len + 1 + line_len > max {
+                push_chunk(&mut chunks, std::mem::take(&mut current));
+                current_len = 0;
+            } else {
+                current.push('\n');
+                current_len += 1;
+            }
+        }
+
+        // 1行だけで上限を超える場合は、文字の境界で区切る
+        while line_len > max {
+            let (head, tail) = line.split_at(byte_offset(line, max));
+            push_chunk(&mut chunks, head.to_string());
+            line = tail;
+            line_len -= max;
+        }
+
+        current.push_str(line);
+        current_len += line_len;
+        has_line = true;
+    }
+
+    push_chunk(&mut chunks, current);
+
+    chunks
+}
+
+/// 分割したまとまりを追加する
+fn push_chunk(chunks: &mut Vec<String>, chunk: String) {
+    // 空のメッセージは送信できない
+    if !chunk.is_empty() {
+        chunks.push(chunk);
+    }
+}
+
+/// `chars`文字目のバイト位置を返す
+fn byte_offset(s: &str, chars: usize) -> usize {
+    s.char_indices().nth(chars).map_or(s.len(), |(i, _)| i)
+}
+
+/// 埋め込みを、1つのメッセージに収まるまとまりに分ける
+/// 埋め込みの順序は保ち、上限を超える埋め込みは1つずつ別のメッセージにする
+pub fn group_embeds(embeds: Vec<CreateEmbed>) -> Vec<Vec<CreateEmbed>> {
+    let mut groups = Vec::new();
+    let mut current = Vec::new();
+    let mut current_len = 0;
+
+    for embed in embeds {
+        let len = embed_length(&embed);
+        if !current.is_empty()
+            && (current.len() >= MAX_EMBEDS_PER_MESSAGE
+                || current_len + len > MAX_EMBED_TOTAL_LENGTH)
+        {
+            groups.push(std::mem::take(&mut current));
+            current_len = 0;
+        }
+        current.push(embed);
+        current_len += len;
+    }
+
+    if !current.is_empty() {
+        groups.push(current);
+    }
+    groups
+}
+
+/// Discordが上限の判定に使う、埋め込みのタイトル・説明・フィールド・フッター・作成者の文字数の合計を返す
+fn embed_length(embed: &CreateEmbed) -> usize {
+    let str_len = |value: Option<&Value>| {
+        value
+            .and_then(Value::as_str)
+            .map_or(0, |s| s.chars().count())
+    };
+
+    let fields = embed
+        .0
+        .get("fields")
+        .and_then(Value::as_array)
+        .map_or(0, |fields| {
+            fields
+                .iter()
+                .map(|field| str_len(field.get("name")) + str_len(field.get("value")))
+                .sum()
+        });
+
+    str_len(embed.0.get("title"))
+        + str_len(embed.0.get("description"))
+        + fields
+        + str_len(embed.0.get("footer").and_then(|footer| footer.get("text")))
+        + str_len(embed.0.get("author").and_then(|author| author.get("name")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_short_content() {
+        assert_eq!(split_content("こんにちは", 10), ["こんにちは"]);
+        assert_eq!(split_content("", 10), Vec::<String>::new());
+    }
+
+    #[test]
+    fn splits_at_line_breaks() {
+        assert_eq!(
+            split_content("あいう\nえお\nかきくけ", 7),
+            ["あいう\nえお", "かきくけ"]
+        );
+    }
+
+    #[test]
+    fn keeps_blank_lines_and_indentation() {
+        assert_eq!(split_content("a\n\n  b\n\nc", 6), ["a\n\n  b", "\nc"]);
+        assert_eq!(split_content("\n\na\n", 10), ["\n\na\n"]);
+    }
+
+    #[test]
+    fn splits_long_line_at_char_boundary() {
+        let chunks = split_content("あいうえおかきくけこさ", 4);
+        assert_eq!(chunks, ["あいうえ", "おかきく", "けこさ"]);
+        assert!(chunks.iter().all(|x| x.chars().count() <= 4));
+
+        // 絵文字のような4バイトの文字も途中で区切らない
+        assert_eq!(split_content("😀😀😀", 2), ["😀😀", "😀"]);
+    }
+
+    #[test]
+    fn splits_long_line_after_short_line() {
+        assert_eq!(
+            split_content("短い\nとても長い行です\n次", 4),
+            ["短い", "とても長", "い行です", "次"]
+        );
+    }
+
+    fn embed(description: &str) -> CreateEmbed {
+        let mut embed = CreateEmbed::default();
+        embed.description(description);
+        embed
+    }
+
+    fn descriptions(groups: &[Vec<CreateEmbed>]) -> Vec<Vec<String>> {
+        groups
+            .iter()
+            .map(|group| {
+                group
+                    .iter()
+                    .map(|x| x.0["description"].as_str().unwrap().to_string())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn groups_embeds_by_count() {
+        let embeds = (0..12).map(|i| embed(&i.to_string())).collect();
+        let groups = group_embeds(embeds);
+        assert_eq!(groups.iter().map(Vec::len).collect::<Vec<_>>(), [10, 2]);
+        assert_eq!(descriptions(&groups)[1], ["10", "11"]);
+    }
+
+    #[test]
+    fn groups_embeds_by_total_length() {
+        let long = "あ".repeat(4000);
+        let groups = group_embeds(vec![embed(&long), embed(&long), embed("a")]);
+        assert_eq!(groups.iter().map(Vec::len).collect::<Vec<_>>(), [1, 2]);
+
+        // 1つで上限を超える埋め込みは、単独のメッセージにする
+        let too_long = "あ".repeat(7000);
+        let groups = group_embeds(vec![embed("a"), embed(&too_long), embed("b")]);
+        assert_eq!(groups.iter().map(Vec::len).collect::<Vec<_>>(), [1, 1, 1]);
+    }
+
+    #[test]
+    fn counts_every_part_of_embed() {
+        let mut embed = CreateEmbed::default();
+        embed
+            .title("タイトル")
+            .description("説明")
+            .field("名前", "値です", false)
+            .footer(|f| f.text("フッター"))
+            .author(|a| a.name("作成者"));
+        assert_eq!(embed_length(&embed), 4 + 2 + 2 + 3 + 4 + 3);
+    }
+}
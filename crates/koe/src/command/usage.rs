@@ -0,0 +1,171 @@
+use super::{
+    handler::{r_ephemeral, respond, CommandResponse},
+    model::Command,
+    parser::CommandParseError,
+    registry::{Category, CommandSpec, SlashCommand},
+};
+use crate::{
+    app_state,
+    messages::{self, Key},
+};
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+use koe_db::{language::Language, usage};
+use serenity::{
+    builder::CreateEmbed,
+    client::Context,
+    model::{
+        application::interaction::application_command::{
+            ApplicationCommandInteraction, CommandDataOption,
+        },
+        id::GuildId,
+    },
+};
+
+/// `/usage`の定義
+const SPEC: CommandSpec = CommandSpec {
+    name: "usage",
+    description: "今月読み上げた文字数を表示",
+    description_en: "Show the number of characters read this month",
+    category: Category::Other,
+    aliases: &[],
+    manage_guild_only: false,
+    dm_allowed: false,
+    options: &[],
+};
+
+pub(super) struct UsageCommand;
+
+#[async_trait]
+impl SlashCommand for UsageCommand {
+    fn spec(&self) -> &'static CommandSpec {
+        &SPEC
+    }
+
+    fn parse(&self, _: &[CommandDataOption]) -> Result<Command, CommandParseError> {
+        Ok(Command::Usage)
+    }
+
+    async fn run(
+        &self,
+        ctx: &Context,
+        cmd: &ApplicationCommandInteraction,
+        lang: Language,
+        _: Command,
+    ) -> Result<()> {
+        handle_usage(ctx, cmd, lang)
+            .await
+            .context("Failed to execute /usage")
+    }
+}
+
+/// 文字数の多いサーバーとして表示する数
+const USAGE_TOP_GUILDS: usize = 10;
+
+async fn handle_usage(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r_ephemeral(ctx, cmd, messages::text(lang, Key::GuildOnlyThisCommand)).await?;
+            return Ok(());
+        }
+    };
+
+    let state = app_state::get(ctx).await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+    let month = crate::usage::current_month(&state);
+
+    let used = usage::get(
+        &mut conn,
+        usage::GetOption {
+            guild_id: guild_id.into(),
+            month: month.clone(),
+        },
+    )
+    .await?;
+    let limit = usage::get_limit(
+        &mut conn,
+        usage::GetLimitOption {
+            guild_id: guild_id.into(),
+        },
+    )
+    .await?;
+
+    let mut embed = CreateEmbed::default();
+    embed.title(messages::format(
+        lang,
+        Key::UsageTitle,
+        &[("month", &month)],
+    ));
+    embed.field(
+        messages::text(lang, Key::UsageGuild),
+        messages::format(lang, Key::UsageChars, &[("chars", &used)]),
+        true,
+    );
+    embed.field(
+        messages::text(lang, Key::UsageLimit),
+        match limit {
+            Some(limit) => messages::format(lang, Key::UsageChars, &[("chars", &limit)]),
+            None => messages::text(lang, Key::UsageNoLimit),
+        },
+        true,
+    );
+
+    // Botの所有者には、すべてのサーバーの合計と文字数の多いサーバーも表示する
+    if state.owner_ids.contains(&cmd.user.id) {
+        let top = usage::get_top(
+            &mut conn,
+            usage::GetTopOption {
+                month,
+                limit: USAGE_TOP_GUILDS,
+            },
+        )
+        .await?;
+        embed.field(
+            messages::text(lang, Key::UsageTotal),
+            messages::format(lang, Key::UsageChars, &[("chars", &top.total)]),
+            false,
+        );
+        if !top.guilds.is_empty() {
+            let lines = top
+                .guilds
+                .iter()
+                .enumerate()
+                .map(|(i, (guild_id, chars))| {
+                    let guild_id = GuildId(*guild_id);
+                    let name = guild_id
+                        .name(&ctx.cache)
+                        .unwrap_or_else(|| guild_id.to_string());
+                    format!(
+                        "{}. {} ({}): {}",
+                        i + 1,
+                        name,
+                        guild_id,
+                        messages::format(lang, Key::UsageChars, &[("chars", chars)])
+                    )
+                })
+                .collect::<Vec<_>>();
+            embed.field(
+                messages::text(lang, Key::UsageTopGuilds),
+                lines.join("\n"),
+                false,
+            );
+        }
+    }
+
+    respond(
+        ctx,
+        cmd,
+        CommandResponse {
+            embeds: vec![embed],
+            ephemeral: true,
+            ..Default::default()
+        },
+    )
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,881 @@
+use super::{
+    handler::{
+        find_preset, get_scale_range, guild_only, r_ephemeral, respond, sanitize_response,
+        validate_scales, voice_params_summary, CommandResponse,
+    },
+    model::{
+        Command, VoiceBoostOption, VoiceLanguageOption, VoiceParamsOption, VoicePreviewUserOption,
+        VoiceRandomizeOption,
+    },
+    parser::{
+        find_boolean, find_integer, find_number, find_string, find_subcommand, find_user, required,
+        unknown_subcommand, CommandParseError,
+    },
+    registry::{
+        subcommand, Category, CommandSpec, OptionKind, OptionSpec, SlashCommand,
+        VOICE_PARAMS_OPTIONS,
+    },
+    setup::{MAX_VOLUME_BOOST, MIN_VOLUME_BOOST},
+};
+use crate::{
+    app_state,
+    component_interaction::custom_id,
+    default_voice,
+    messages::{self, Key},
+    speech_provider,
+    speech_queue::{self, EnqueueOption, Voice},
+};
+use anyhow::{anyhow, bail, Context as _, Result};
+use async_trait::async_trait;
+use koe_db::{
+    language::Language,
+    scale_bounds::ScaleKind,
+    voice::{
+        GetLanguageOption, GetSettingsOption, RemoveLanguageOption, SetLanguageOption, SetOption,
+        SetScaleOption,
+    },
+};
+use rand::{seq::SliceRandom, Rng};
+use serenity::{
+    builder::{
+        CreateActionRow, CreateComponents, CreateEmbed, CreateSelectMenu, CreateSelectMenuOption,
+    },
+    client::Context,
+    model::{
+        application::interaction::application_command::{
+            ApplicationCommandInteraction, CommandDataOption,
+        },
+        id::{GuildId, UserId},
+    },
+};
+use std::ops::RangeInclusive;
+
+/// `/voice`の定義
+const SPEC: CommandSpec = CommandSpec {
+    name: "voice",
+    description: "話者の設定",
+    description_en: "Voice settings",
+    category: Category::Voice,
+    aliases: &[],
+    manage_guild_only: false,
+    dm_allowed: false,
+    options: &[
+        subcommand(
+            "set",
+            "声を設定（すべて省略で一覧から選択）",
+            "Set your voice (omit all options to choose from a list)",
+            VOICE_PARAMS_OPTIONS,
+        ),
+        subcommand(
+            "info",
+            "現在の声の設定を表示",
+            "Show your current voice settings",
+            &[],
+        ),
+        subcommand(
+            "boost",
+            "接続中に限り、メンバーの読み上げの音量を変更（「メンバーをミュート」の権限が必要）",
+            "Change a member's reading volume while connected (requires Mute Members)",
+            &[
+                OptionSpec::new(
+                    "user",
+                    "音量を変更するメンバー",
+                    "Member whose volume to change",
+                    OptionKind::User,
+                )
+                .required(),
+                OptionSpec::new(
+                    "amount",
+                    "音量の倍率（1で元に戻す）",
+                    "Volume multiplier (1 to reset)",
+                    OptionKind::Number {
+                        min: Some(MIN_VOLUME_BOOST),
+                        max: Some(MAX_VOLUME_BOOST),
+                    },
+                )
+                .required(),
+            ],
+        ),
+        subcommand(
+            "randomize",
+            "声をランダムに選んで設定",
+            "Set a randomly chosen voice",
+            &[
+                OptionSpec::new(
+                    "speed",
+                    "話速もランダムにするかどうか（既定値: しない）",
+                    "Whether to randomize the speed too (default: no)",
+                    OptionKind::Boolean,
+                ),
+                OptionSpec::new(
+                    "pitch",
+                    "音高もランダムにするかどうか（既定値: しない）",
+                    "Whether to randomize the pitch too (default: no)",
+                    OptionKind::Boolean,
+                ),
+            ],
+        ),
+        subcommand(
+            "language",
+            "読み上げに使う言語を設定（省略でサーバーの設定に合わせる）",
+            "Set the language used to read your messages (omit to follow the server)",
+            &[OptionSpec::new(
+                "language",
+                "言語コード（例: en）",
+                "Language code (e.g. en)",
+                OptionKind::String {
+                    choices: &[],
+                    autocomplete: true,
+                },
+            )],
+        ),
+        subcommand(
+            "preview-user",
+            "メンバーの声で見本の文章を読み上げる（「メンバーをミュート」の権限が必要）",
+            "Read a sample phrase in a member's voice (requires Mute Members)",
+            &[OptionSpec::new(
+                "user",
+                "声を試聴するメンバー",
+                "Member whose voice to preview",
+                OptionKind::User,
+            )
+            .required()],
+        ),
+    ],
+};
+
+pub(super) struct VoiceCommand;
+
+#[async_trait]
+impl SlashCommand for VoiceCommand {
+    fn spec(&self) -> &'static CommandSpec {
+        &SPEC
+    }
+
+    fn parse(&self, options: &[CommandDataOption]) -> Result<Command, CommandParseError> {
+        parse_voice(find_subcommand("voice", options)?)
+    }
+
+    async fn run(
+        &self,
+        ctx: &Context,
+        cmd: &ApplicationCommandInteraction,
+        lang: Language,
+        command: Command,
+    ) -> Result<()> {
+        match command {
+            Command::VoiceSet(option) => handle_set(ctx, cmd, lang, option)
+                .await
+                .context("Failed to execute /voice set"),
+            Command::VoiceInfo => handle_info(ctx, cmd, lang)
+                .await
+                .context("Failed to execute /voice info"),
+            Command::VoiceBoost(option) => handle_boost(ctx, cmd, lang, option)
+                .await
+                .context("Failed to execute /voice boost"),
+            Command::VoiceRandomize(option) => handle_randomize(ctx, cmd, lang, option)
+                .await
+                .context("Failed to execute /voice randomize"),
+            Command::VoiceLanguage(option) => handle_language(ctx, cmd, lang, option)
+                .await
+                .context("Failed to execute /voice language"),
+            Command::VoicePreviewUser(option) => handle_preview_user(ctx, cmd, lang, option)
+                .await
+                .context("Failed to execute /voice preview-user"),
+            command => bail!("Unexpected command for /voice: {:?}", command),
+        }
+    }
+}
+
+/// `/voice randomize`で選ぶ話速の範囲
+/// 極端な値では聞き取りにくいため、設定できる範囲より狭くする
+const RANDOM_SPEED_RANGE: RangeInclusive<f64> = 0.9..=1.4;
+/// `/voice randomize`で選ぶ音高の範囲
+const RANDOM_PITCH_RANGE: RangeInclusive<f64> = -0.06..=0.06;
+
+/// `/voice preview-user`で読み上げる見本の文章
+const VOICE_PREVIEW_TEXT: &str = "この声で読み上げます。よろしくお願いします。";
+
+async fn handle_set(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+    option: VoiceParamsOption,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r_ephemeral(ctx, cmd, guild_only(lang, "`/voice set`")).await?;
+            return Ok(());
+        }
+    };
+
+    if !option.is_empty() {
+        return handle_set_params(ctx, cmd, lang, guild_id, option).await;
+    }
+
+    let state = app_state::get(ctx).await?;
+
+    let mut conn = state.redis_client.get_async_connection().await?;
+    let (kind, available_presets) =
+        speech_provider::list_member_voices(&state, &mut conn, guild_id, cmd.user.id).await?;
+
+    let settings = koe_db::voice::get_settings(
+        &mut conn,
+        GetSettingsOption {
+            guild_id: guild_id.into(),
+            user_id: cmd.user.id.into(),
+        },
+    )
+    .await?;
+    let current_preset = default_voice::resolve_preset_id(
+        &state,
+        &mut conn,
+        guild_id,
+        cmd.user.id,
+        &settings,
+        kind,
+        &available_presets.iter().map(|p| p.id).collect::<Vec<_>>(),
+    )
+    .await?;
+
+    {
+        let option_list = available_presets
+            .iter()
+            .map(|p| {
+                let mut option = CreateSelectMenuOption::default();
+                option
+                    .label(&p.name)
+                    .value(p.id)
+                    .default_selection(p.id == current_preset);
+                option
+            })
+            .collect::<Vec<_>>();
+
+        let mut select = CreateSelectMenu::default();
+        select.custom_id(custom_id::CUSTOM_ID_VOICE);
+        select.options(|create_options| create_options.set_options(option_list));
+
+        let mut action_row = CreateActionRow::default();
+        action_row.add_select_menu(select);
+
+        let mut components = CreateComponents::default();
+        components.add_action_row(action_row);
+
+        respond(
+            ctx,
+            cmd,
+            CommandResponse {
+                components: Some(components),
+                ephemeral: true,
+                ..Default::default()
+            },
+        )
+        .await?;
+    };
+
+    Ok(())
+}
+
+/// `/voice set`でオプションが指定された場合に、指定された項目のみを設定する
+async fn handle_set_params(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+    guild_id: GuildId,
+    option: VoiceParamsOption,
+) -> Result<()> {
+    let state = app_state::get(ctx).await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+
+    let speed_range = get_scale_range(&mut conn, guild_id, ScaleKind::Speed).await?;
+    let pitch_range = get_scale_range(&mut conn, guild_id, ScaleKind::Pitch).await?;
+    if let Some(msg) = validate_scales(lang, &option, &speed_range, &pitch_range) {
+        r_ephemeral(ctx, cmd, msg).await?;
+        return Ok(());
+    }
+
+    let preset = match option.preset {
+        Some(preset_id) => {
+            let (kind, provider) =
+                speech_provider::for_member(&state, &mut conn, guild_id, cmd.user.id).await?;
+            match find_preset(lang, &*provider, preset_id).await? {
+                Ok(preset) => Some((kind, preset)),
+                Err(msg) => {
+                    r_ephemeral(ctx, cmd, msg).await?;
+                    return Ok(());
+                }
+            }
+        }
+        None => None,
+    };
+
+    if let Some((kind, preset)) = &preset {
+        koe_db::voice::set(
+            &mut conn,
+            SetOption {
+                guild_id: guild_id.into(),
+                user_id: cmd.user.id.into(),
+                value: preset.id,
+                provider: kind.as_str().to_string(),
+            },
+        )
+        .await?;
+    }
+    if let Some(speed) = option.speed {
+        koe_db::voice::set_speed_scale(
+            &mut conn,
+            SetScaleOption {
+                guild_id: guild_id.into(),
+                user_id: cmd.user.id.into(),
+                value: speed,
+            },
+        )
+        .await?;
+    }
+    if let Some(pitch) = option.pitch {
+        koe_db::voice::set_pitch_scale(
+            &mut conn,
+            SetScaleOption {
+                guild_id: guild_id.into(),
+                user_id: cmd.user.id.into(),
+                value: pitch,
+            },
+        )
+        .await?;
+    }
+
+    r_ephemeral(
+        ctx,
+        cmd,
+        messages::format(
+            lang,
+            Key::VoiceSet,
+            &[(
+                "summary",
+                &voice_params_summary(lang, preset.as_ref().map(|(_, p)| p), &option),
+            )],
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+async fn handle_randomize(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+    option: VoiceRandomizeOption,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r_ephemeral(ctx, cmd, guild_only(lang, "`/voice randomize`")).await?;
+            return Ok(());
+        }
+    };
+
+    let state = app_state::get(ctx).await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+
+    let (_, available_presets) =
+        speech_provider::list_member_voices(&state, &mut conn, guild_id, cmd.user.id).await?;
+    let preset_id = available_presets
+        .choose(&mut rand::thread_rng())
+        .map(|preset| preset.id)
+        .ok_or_else(|| anyhow!("No presets available"))?;
+
+    let speed = if option.speed {
+        let allowed = get_scale_range(&mut conn, guild_id, ScaleKind::Speed).await?;
+        Some(random_scale(&RANDOM_SPEED_RANGE, &allowed))
+    } else {
+        None
+    };
+    let pitch = if option.pitch {
+        let allowed = get_scale_range(&mut conn, guild_id, ScaleKind::Pitch).await?;
+        Some(random_scale(&RANDOM_PITCH_RANGE, &allowed))
+    } else {
+        None
+    };
+
+    // 選んだ値の設定と応答は`/voice set`と共通にする
+    handle_set_params(
+        ctx,
+        cmd,
+        lang,
+        guild_id,
+        VoiceParamsOption {
+            preset: Some(preset_id),
+            speed,
+            pitch,
+        },
+    )
+    .await
+}
+
+/// `preferred`の範囲からランダムに話速・音高を選ぶ
+/// サーバーで設定できる範囲（`allowed`）と重ならない場合は、`allowed`の範囲から選ぶ
+fn random_scale(preferred: &RangeInclusive<f64>, allowed: &RangeInclusive<f64>) -> f64 {
+    let start = preferred.start().max(*allowed.start());
+    let end = preferred.end().min(*allowed.end());
+    let range = if start <= end {
+        start..=end
+    } else {
+        allowed.clone()
+    };
+
+    let value = rand::thread_rng().gen_range(range.clone());
+    // 表示しやすいよう小数第2位までに丸める
+    ((value * 100.0).round() / 100.0).clamp(*range.start(), *range.end())
+}
+
+/// メンバーが読み上げに使う言語を設定する
+/// 設定ファイルで指定されたエンジンのいずれかが読み上げられる言語のみを受け付ける
+async fn handle_language(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+    option: VoiceLanguageOption,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r_ephemeral(ctx, cmd, guild_only(lang, "`/voice language`")).await?;
+            return Ok(());
+        }
+    };
+
+    let state = app_state::get(ctx).await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+
+    let supported = state.speech_providers.languages();
+    let language = match choose_language(option.language, &supported) {
+        LanguageChoice::Set(language) => language,
+        LanguageChoice::Unsupported(language) => {
+            r_ephemeral(
+                ctx,
+                cmd,
+                messages::format(
+                    lang,
+                    Key::VoiceLanguageUnsupported,
+                    &[
+                        ("language", &sanitize_response(&language)),
+                        ("list", &supported.join(", ")),
+                    ],
+                ),
+            )
+            .await?;
+            return Ok(());
+        }
+        LanguageChoice::Remove => {
+            koe_db::voice::remove_language(
+                &mut conn,
+                RemoveLanguageOption {
+                    guild_id: guild_id.into(),
+                    user_id: cmd.user.id.into(),
+                },
+            )
+            .await?;
+            r_ephemeral(ctx, cmd, messages::text(lang, Key::VoiceLanguageRemoved)).await?;
+            return Ok(());
+        }
+    };
+
+    koe_db::voice::set_language(
+        &mut conn,
+        SetLanguageOption {
+            guild_id: guild_id.into(),
+            user_id: cmd.user.id.into(),
+            language: language.clone(),
+        },
+    )
+    .await?;
+
+    r_ephemeral(
+        ctx,
+        cmd,
+        messages::format(lang, Key::VoiceLanguageSet, &[("language", &language)]),
+    )
+    .await?;
+    Ok(())
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum LanguageChoice {
+    /// 言語の設定を削除する
+    Remove,
+    Set(String),
+    /// 読み上げられるエンジンがない
+    Unsupported(String),
+}
+
+/// `/voice language`で指定された言語を、大文字・小文字を区別せずに`supported`と照合する
+fn choose_language(input: Option<String>, supported: &[String]) -> LanguageChoice {
+    let language = input
+        .map(|x| x.trim().to_lowercase())
+        .filter(|x| !x.is_empty());
+    match language {
+        None => LanguageChoice::Remove,
+        Some(language) if supported.contains(&language) => LanguageChoice::Set(language),
+        Some(language) => LanguageChoice::Unsupported(language),
+    }
+}
+
+async fn handle_info(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r_ephemeral(ctx, cmd, guild_only(lang, "`/voice info`")).await?;
+            return Ok(());
+        }
+    };
+
+    let embed = build_voice_info_embed(ctx, lang, guild_id, cmd.user.id, &cmd.user.name).await?;
+    respond(
+        ctx,
+        cmd,
+        CommandResponse {
+            embeds: vec![embed],
+            ephemeral: true,
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// メンバーの声の設定を表示する埋め込みを作る
+/// 一度も読み上げたことがないメンバーは、すべての項目を既定値として表示する
+pub(super) async fn build_voice_info_embed(
+    ctx: &Context,
+    lang: Language,
+    guild_id: GuildId,
+    user_id: UserId,
+    user_name: &str,
+) -> Result<CreateEmbed> {
+    let state = app_state::get(ctx).await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+
+    let settings = koe_db::voice::get_settings(
+        &mut conn,
+        GetSettingsOption {
+            guild_id: guild_id.into(),
+            user_id: user_id.into(),
+        },
+    )
+    .await?;
+
+    let language = koe_db::voice::get_language(
+        &mut conn,
+        GetLanguageOption {
+            guild_id: guild_id.into(),
+            user_id: user_id.into(),
+        },
+    )
+    .await?;
+    let (kind, available_presets) =
+        speech_provider::list_member_voices(&state, &mut conn, guild_id, user_id).await?;
+    let current_preset = speech_provider::usable_preset_id(
+        &settings,
+        kind,
+        state.speech_providers.default_kind(),
+        &available_presets.iter().map(|p| p.id).collect::<Vec<_>>(),
+    )
+    .and_then(|id| available_presets.iter().find(|p| p.id == id));
+
+    let mut embed = CreateEmbed::default();
+    embed.title(messages::format(
+        lang,
+        Key::VoiceInfoTitle,
+        &[("name", &user_name)],
+    ));
+
+    match (settings.preset_id, current_preset) {
+        (Some(_), Some(preset)) => {
+            embed.field(
+                messages::text(lang, Key::Voice),
+                messages::format(lang, Key::ConfiguredValue, &[("value", &preset.name)]),
+                false,
+            );
+            embed.field(
+                messages::text(lang, Key::Speed),
+                match settings.speed_scale {
+                    Some(speed) => {
+                        messages::format(lang, Key::ConfiguredValue, &[("value", &speed)])
+                    }
+                    None => messages::format(
+                        lang,
+                        Key::PresetDefaultValue,
+                        &[("value", &preset.speed_scale)],
+                    ),
+                },
+                true,
+            );
+            embed.field(
+                messages::text(lang, Key::Pitch),
+                match settings.pitch_scale {
+                    Some(pitch) => {
+                        messages::format(lang, Key::ConfiguredValue, &[("value", &pitch)])
+                    }
+                    None => messages::format(
+                        lang,
+                        Key::PresetDefaultValue,
+                        &[("value", &preset.pitch_scale)],
+                    ),
+                },
+                true,
+            );
+        }
+        (Some(id), None) => {
+            embed.field(
+                messages::text(lang, Key::Voice),
+                messages::format(lang, Key::PresetUnavailable, &[("id", &id)]),
+                false,
+            );
+        }
+        (None, _) => {
+            embed.field(
+                messages::text(lang, Key::Voice),
+                messages::text(lang, Key::VoiceNotAssigned),
+                false,
+            );
+            embed.field(
+                messages::text(lang, Key::Speed),
+                match settings.speed_scale {
+                    Some(speed) => {
+                        messages::format(lang, Key::ConfiguredValue, &[("value", &speed)])
+                    }
+                    None => messages::text(lang, Key::DefaultValue),
+                },
+                true,
+            );
+            embed.field(
+                messages::text(lang, Key::Pitch),
+                match settings.pitch_scale {
+                    Some(pitch) => {
+                        messages::format(lang, Key::ConfiguredValue, &[("value", &pitch)])
+                    }
+                    None => messages::text(lang, Key::DefaultValue),
+                },
+                true,
+            );
+        }
+    }
+
+    if let Some(language) = language {
+        let supported = state.speech_providers.languages();
+        embed.field(
+            messages::text(lang, Key::VoiceLanguage),
+            if supported.contains(&language) {
+                format!("`{}`", language)
+            } else {
+                messages::format(
+                    lang,
+                    Key::VoiceLanguageUnavailable,
+                    &[("language", &sanitize_response(&language))],
+                )
+            },
+            false,
+        );
+    }
+
+    Ok(embed)
+}
+
+async fn handle_boost(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+    option: VoiceBoostOption,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r_ephemeral(ctx, cmd, guild_only(lang, "`/voice boost`")).await?;
+            return Ok(());
+        }
+    };
+
+    let is_moderator = cmd
+        .member
+        .as_ref()
+        .and_then(|member| member.permissions)
+        .is_some_and(|permissions| permissions.mute_members());
+    if !is_moderator {
+        r_ephemeral(ctx, cmd, messages::text(lang, Key::BoostRequiresPermission)).await?;
+        return Ok(());
+    }
+
+    if !(MIN_VOLUME_BOOST..=MAX_VOLUME_BOOST).contains(&option.amount) {
+        r_ephemeral(
+            ctx,
+            cmd,
+            messages::format(
+                lang,
+                Key::BoostOutOfRange,
+                &[("min", &MIN_VOLUME_BOOST), ("max", &MAX_VOLUME_BOOST)],
+            ),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let state = app_state::get(ctx).await?;
+    {
+        let mut guild_state = match state.connected_guild_states.get_mut(&guild_id) {
+            Some(guild_state) => guild_state,
+            None => {
+                r_ephemeral(ctx, cmd, messages::text(lang, Key::NotConnected)).await?;
+                return Ok(());
+            }
+        };
+
+        if option.amount == 1.0 {
+            guild_state.volume_boosts.remove(&option.user);
+        } else {
+            guild_state.volume_boosts.insert(option.user, option.amount);
+        }
+    }
+
+    let msg = if option.amount == 1.0 {
+        messages::format(lang, Key::BoostReset, &[("user", &option.user)])
+    } else {
+        messages::format(
+            lang,
+            Key::BoostSet,
+            &[("user", &option.user), ("amount", &option.amount)],
+        )
+    };
+    r_ephemeral(ctx, cmd, msg).await?;
+    Ok(())
+}
+
+/// メンバーの声の設定を、読み上げと同じように既定値で補って見本の文章を読み上げる
+async fn handle_preview_user(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    lang: Language,
+    option: VoicePreviewUserOption,
+) -> Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            r_ephemeral(ctx, cmd, guild_only(lang, "`/voice preview-user`")).await?;
+            return Ok(());
+        }
+    };
+
+    let is_moderator = cmd
+        .member
+        .as_ref()
+        .and_then(|member| member.permissions)
+        .is_some_and(|permissions| permissions.mute_members());
+    if !is_moderator {
+        r_ephemeral(
+            ctx,
+            cmd,
+            messages::text(lang, Key::PreviewUserRequiresPermission),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let state = app_state::get(ctx).await?;
+    if !state.connected_guild_states.contains_key(&guild_id) {
+        r_ephemeral(ctx, cmd, messages::text(lang, Key::NotConnected)).await?;
+        return Ok(());
+    }
+
+    speech_queue::enqueue(
+        ctx,
+        EnqueueOption {
+            guild_id,
+            text: VOICE_PREVIEW_TEXT.to_string(),
+            voice: Voice::Member(option.user),
+        },
+    )
+    .await?;
+
+    r_ephemeral(
+        ctx,
+        cmd,
+        messages::format(lang, Key::PreviewUserEnqueued, &[("user", &option.user)]),
+    )
+    .await?;
+    Ok(())
+}
+
+fn parse_voice(option_voice: &CommandDataOption) -> Result<Command, CommandParseError> {
+    let options = &option_voice.options;
+
+    match option_voice.name.as_str() {
+        "set" => Ok(Command::VoiceSet(parse_voice_params(option_voice)?)),
+        "info" => Ok(Command::VoiceInfo),
+        "boost" => Ok(Command::VoiceBoost(VoiceBoostOption {
+            user: required(find_user(options, "user")?, "user")?,
+            amount: required(find_number(options, "amount")?, "amount")?,
+        })),
+        "randomize" => Ok(Command::VoiceRandomize(VoiceRandomizeOption {
+            speed: find_boolean(options, "speed")?.unwrap_or(false),
+            pitch: find_boolean(options, "pitch")?.unwrap_or(false),
+        })),
+        "language" => Ok(Command::VoiceLanguage(VoiceLanguageOption {
+            language: find_string(options, "language")?,
+        })),
+        "preview-user" => Ok(Command::VoicePreviewUser(VoicePreviewUserOption {
+            user: required(find_user(options, "user")?, "user")?,
+        })),
+        _ => Err(unknown_subcommand("voice", option_voice)),
+    }
+}
+
+pub(super) fn parse_voice_params(
+    option: &CommandDataOption,
+) -> Result<VoiceParamsOption, CommandParseError> {
+    let options = &option.options;
+
+    Ok(VoiceParamsOption {
+        preset: find_integer(options, "preset")?,
+        speed: find_number(options, "speed")?,
+        pitch: find_number(options, "pitch")?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn supported() -> Vec<String> {
+        vec!["ja".to_string(), "en".to_string()]
+    }
+
+    #[test]
+    fn accepts_supported_languages_case_insensitively() {
+        assert_eq!(
+            choose_language(Some(" EN ".to_string()), &supported()),
+            LanguageChoice::Set("en".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_languages_no_engine_supports() {
+        assert_eq!(
+            choose_language(Some("fr".to_string()), &supported()),
+            LanguageChoice::Unsupported("fr".to_string())
+        );
+    }
+
+    #[test]
+    fn removes_the_language_when_omitted() {
+        assert_eq!(choose_language(None, &supported()), LanguageChoice::Remove);
+        assert_eq!(
+            choose_language(Some("  ".to_string()), &supported()),
+            LanguageChoice::Remove
+        );
+    }
+}
@@ -1 +1,245 @@
+use serenity::model::id::{GuildId, UserId};
+use std::fmt;
+
 pub const CUSTOM_ID_VOICE: &str = "voice";
+
+/// `/dict add`のフォームの入力欄
+pub const DICT_ADD_WORD_INPUT: &str = "word";
+pub const DICT_ADD_READ_AS_INPUT: &str = "read_as";
+
+/// Koeが作成したメッセージコンポーネントの`custom_id`の接頭辞
+const PREFIX: &str = "koe";
+
+/// メッセージコンポーネントの`custom_id`
+/// `koe:<種類>:<引数>...`の形式で表し、引数は`:`で区切る（例: `koe:dict_page:3:<サーバーID>:<ユーザーID>`）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CustomId {
+    /// `/voice set`で表示する声の選択肢
+    /// 以前のバージョンで送信したメッセージも操作できるよう、`custom_id`は[`CUSTOM_ID_VOICE`]のままにしている
+    Voice,
+    /// `/dict add`のフォーム
+    DictAdd { guild_id: GuildId },
+    /// `/dict view`のページの切り替え
+    DictPage {
+        page: usize,
+        guild_id: GuildId,
+        /// コマンドを送信したメンバー
+        invoker: UserId,
+    },
+    /// 取り消せない操作の確認（はい・いいえ）
+    Confirm {
+        action: ConfirmAction,
+        accepted: bool,
+        guild_id: GuildId,
+        /// コマンドを送信したメンバー
+        invoker: UserId,
+    },
+}
+
+/// 実行する前に確認する操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfirmAction {
+    /// 読み上げ中の音声が残っている状態での`/leave`
+    Leave,
+    /// `/dict clear`
+    DictClear,
+}
+
+impl ConfirmAction {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ConfirmAction::Leave => "leave",
+            ConfirmAction::DictClear => "dict_clear",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "leave" => Some(ConfirmAction::Leave),
+            "dict_clear" => Some(ConfirmAction::DictClear),
+            _ => None,
+        }
+    }
+}
+
+impl CustomId {
+    /// `custom_id`を解釈する
+    /// 形式が正しくない場合は[`None`]を返す
+    pub fn parse(s: &str) -> Option<Self> {
+        if s == CUSTOM_ID_VOICE {
+            return Some(CustomId::Voice);
+        }
+
+        let mut parts = s.split(':');
+        if parts.next()? != PREFIX {
+            return None;
+        }
+
+        let custom_id = match parts.next()? {
+            "dict_add" => CustomId::DictAdd {
+                guild_id: GuildId(parts.next()?.parse().ok()?),
+            },
+            "dict_page" => CustomId::DictPage {
+                page: parts.next()?.parse().ok()?,
+                guild_id: GuildId(parts.next()?.parse().ok()?),
+                invoker: UserId(parts.next()?.parse().ok()?),
+            },
+            "confirm" => CustomId::Confirm {
+                action: ConfirmAction::parse(parts.next()?)?,
+                accepted: match parts.next()? {
+                    "yes" => true,
+                    "no" => false,
+                    _ => return None,
+                },
+                guild_id: GuildId(parts.next()?.parse().ok()?),
+                invoker: UserId(parts.next()?.parse().ok()?),
+            },
+            _ => return None,
+        };
+
+        // 引数が余っているものは受け付けない
+        if parts.next().is_some() {
+            return None;
+        }
+
+        Some(custom_id)
+    }
+
+    /// 操作できるメンバーが限られている場合は、そのメンバーを返す
+    pub fn invoker(&self) -> Option<UserId> {
+        match self {
+            CustomId::Voice | CustomId::DictAdd { .. } => None,
+            CustomId::DictPage { invoker, .. } | CustomId::Confirm { invoker, .. } => {
+                Some(*invoker)
+            }
+        }
+    }
+}
+
+impl fmt::Display for CustomId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CustomId::Voice => write!(f, "{}", CUSTOM_ID_VOICE),
+            CustomId::DictAdd { guild_id } => write!(f, "{}:dict_add:{}", PREFIX, guild_id),
+            CustomId::DictPage {
+                page,
+                guild_id,
+                invoker,
+            } => write!(f, "{}:dict_page:{}:{}:{}", PREFIX, page, guild_id, invoker),
+            CustomId::Confirm {
+                action,
+                accepted,
+                guild_id,
+                invoker,
+            } => write!(
+                f,
+                "{}:confirm:{}:{}:{}:{}",
+                PREFIX,
+                action.as_str(),
+                if *accepted { "yes" } else { "no" },
+                guild_id,
+                invoker
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all_custom_ids() -> Vec<CustomId> {
+        let guild_id = GuildId(123456789012345678);
+        let invoker = UserId(876543210987654321);
+        let mut ids = vec![
+            CustomId::Voice,
+            CustomId::DictAdd { guild_id },
+            CustomId::DictPage {
+                page: 0,
+                guild_id,
+                invoker,
+            },
+            CustomId::DictPage {
+                page: 42,
+                guild_id,
+                invoker,
+            },
+        ];
+        for action in [ConfirmAction::Leave, ConfirmAction::DictClear] {
+            for accepted in [true, false] {
+                ids.push(CustomId::Confirm {
+                    action,
+                    accepted,
+                    guild_id,
+                    invoker,
+                });
+            }
+        }
+        ids
+    }
+
+    #[test]
+    fn round_trip() {
+        for id in all_custom_ids() {
+            let s = id.to_string();
+            // Discordの`custom_id`は100文字まで
+            assert!(s.len() <= 100, "{}", s);
+            assert_eq!(CustomId::parse(&s), Some(id), "{}", s);
+        }
+    }
+
+    #[test]
+    fn format() {
+        assert_eq!(CustomId::Voice.to_string(), "voice");
+        assert_eq!(
+            CustomId::DictPage {
+                page: 3,
+                guild_id: GuildId(10),
+                invoker: UserId(20),
+            }
+            .to_string(),
+            "koe:dict_page:3:10:20"
+        );
+        assert_eq!(
+            CustomId::Confirm {
+                action: ConfirmAction::DictClear,
+                accepted: false,
+                guild_id: GuildId(10),
+                invoker: UserId(20),
+            }
+            .to_string(),
+            "koe:confirm:dict_clear:no:10:20"
+        );
+    }
+
+    #[test]
+    fn rejects_malformed() {
+        for s in [
+            "",
+            "koe",
+            "koe:",
+            "other:dict_page:3:10:20",
+            "koe:unknown:3",
+            "koe:dict_page:3:10",
+            "koe:dict_page:3:10:20:30",
+            "koe:dict_page:-1:10:20",
+            "koe:dict_page:x:10:20",
+            "koe:dict_add:",
+            "koe:confirm:leave:maybe:10:20",
+            "koe:confirm:shutdown:yes:10:20",
+            "voice:1",
+        ] {
+            assert_eq!(CustomId::parse(s), None, "{}", s);
+        }
+    }
+
+    #[test]
+    fn invoker_restriction() {
+        let restricted = all_custom_ids()
+            .into_iter()
+            .filter(|id| id.invoker().is_some())
+            .count();
+        assert_eq!(restricted, 6);
+        assert_eq!(CustomId::Voice.invoker(), None);
+    }
+}
@@ -1,30 +1,154 @@
-use super::custom_id;
-use crate::app_state;
+use super::custom_id::{ConfirmAction, CustomId, DICT_ADD_READ_AS_INPUT, DICT_ADD_WORD_INPUT};
+use crate::{
+    app_state,
+    command::{confirm, dict_entry, dict_view},
+    messages::{self, Key},
+    speech_provider,
+};
 use anyhow::{anyhow, bail, Context as _, Result};
-use koe_db::voice::SetOption;
+use koe_db::{language::Language, voice::SetOption};
 use serenity::{
+    builder::CreateComponents,
     client::Context,
-    model::application::interaction::{
-        message_component::MessageComponentInteraction, InteractionResponseType,
+    model::{
+        application::{
+            component::ActionRowComponent,
+            interaction::{
+                message_component::MessageComponentInteraction, modal::ModalSubmitInteraction,
+                InteractionResponseType,
+            },
+        },
+        id::{GuildId, UserId},
     },
 };
+use std::time::{Duration, SystemTime};
+
+/// メッセージコンポーネントを操作できる期間
+/// これより古いメッセージのコンポーネントは操作できないようにする
+const COMPONENT_LIFETIME: Duration = Duration::from_secs(15 * 60);
 
 pub async fn handle(ctx: &Context, interaction: &MessageComponentInteraction) -> Result<()> {
-    if interaction.data.custom_id == custom_id::CUSTOM_ID_VOICE {
-        handle_voice(ctx, interaction)
-            .await
-            .context(r#"Failed to handle "voice" message component interaction"#)?;
-    } else {
-        bail!(
+    let custom_id = match CustomId::parse(&interaction.data.custom_id) {
+        Some(custom_id) => custom_id,
+        None => bail!(
             "Unknown message component interaction custom_id: {}",
             interaction.data.custom_id
+        ),
+    };
+
+    let lang = messages::resolve(ctx, interaction.guild_id, &interaction.locale).await;
+
+    if let Some(invoker) = custom_id.invoker() {
+        if interaction.user.id != invoker {
+            r_ephemeral(ctx, interaction, messages::text(lang, Key::InvokerOnly)).await?;
+            return Ok(());
+        }
+    }
+
+    match custom_id {
+        CustomId::Voice => handle_voice(ctx, interaction, lang)
+            .await
+            .context(r#"Failed to handle "voice" message component interaction"#)?,
+        CustomId::DictPage {
+            page,
+            guild_id,
+            invoker,
+        } => {
+            if is_expired(interaction) {
+                disable_components(ctx, interaction, lang).await?;
+                return Ok(());
+            }
+            handle_dict_page(ctx, interaction, lang, page, guild_id, invoker)
+                .await
+                .context(r#"Failed to handle "dict_page" message component interaction"#)?
+        }
+        CustomId::Confirm {
+            action,
+            accepted,
+            guild_id,
+            ..
+        } => handle_confirm(ctx, interaction, lang, action, accepted, guild_id)
+            .await
+            .context(r#"Failed to handle "confirm" message component interaction"#)?,
+        CustomId::DictAdd { .. } => bail!(
+            "Modal custom_id used in message component: {}",
+            interaction.data.custom_id
+        ),
+    }
+
+    Ok(())
+}
+
+/// フォームの送信を処理する
+pub async fn handle_modal_submit(
+    ctx: &Context,
+    interaction: &ModalSubmitInteraction,
+) -> Result<()> {
+    let lang = messages::resolve(ctx, interaction.guild_id, &interaction.locale).await;
+
+    match CustomId::parse(&interaction.data.custom_id) {
+        Some(CustomId::DictAdd { guild_id }) => handle_dict_add(ctx, interaction, lang, guild_id)
+            .await
+            .context(r#"Failed to handle "dict_add" modal submit interaction"#)?,
+        _ => bail!(
+            "Unknown modal submit interaction custom_id: {}",
+            interaction.data.custom_id
+        ),
+    }
+
+    Ok(())
+}
+
+async fn handle_dict_add(
+    ctx: &Context,
+    interaction: &ModalSubmitInteraction,
+    lang: Language,
+    guild_id: GuildId,
+) -> Result<()> {
+    if interaction.guild_id != Some(guild_id) {
+        bail!(
+            "Guild ID in custom_id does not match: {:?} != {}",
+            interaction.guild_id,
+            guild_id
         );
     }
 
+    let find_input = |custom_id: &str| {
+        interaction
+            .data
+            .components
+            .iter()
+            .flat_map(|row| &row.components)
+            .find_map(|component| match component {
+                ActionRowComponent::InputText(input) if input.custom_id == custom_id => {
+                    Some(input.value.trim().to_string())
+                }
+                _ => None,
+            })
+            .unwrap_or_default()
+    };
+    let word = find_input(DICT_ADD_WORD_INPUT);
+    let read_as = find_input(DICT_ADD_READ_AS_INPUT);
+
+    let msg = dict_entry::add(ctx, lang, guild_id, &word, &read_as).await?;
+
+    interaction
+        .create_interaction_response(&ctx.http, |create_response| {
+            create_response
+                .kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|create_message| create_message.content(msg))
+        })
+        .await
+        .context("Failed to create interaction response")?;
+
     Ok(())
 }
 
-async fn handle_voice(ctx: &Context, interaction: &MessageComponentInteraction) -> Result<()> {
+async fn handle_voice(
+    ctx: &Context,
+    interaction: &MessageComponentInteraction,
+    lang: Language,
+) -> Result<()> {
     let guild_id = interaction
         .guild_id
         .ok_or_else(|| anyhow!("Failed to get guild ID"))?;
@@ -32,25 +156,28 @@ async fn handle_voice(ctx: &Context, interaction: &MessageComponentInteraction)
     let selected_preset_id = interaction
         .data
         .values
-        .get(0)
+        .first()
         .ok_or_else(|| anyhow!("Value not available in message component interaction"))?
         .parse::<i64>()?;
 
     let state = app_state::get(ctx).await?;
 
-    let available_presets = state.voicevox_client.presets().await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+    let (kind, available_presets) =
+        speech_provider::list_member_voices(&state, &mut conn, guild_id, interaction.user.id)
+            .await?;
     let selected_preset = available_presets
         .into_iter()
         .find(|p| p.id == selected_preset_id)
         .ok_or_else(|| anyhow!("Preset {} not available", selected_preset_id))?;
 
-    let mut conn = state.redis_client.get_async_connection().await?;
     koe_db::voice::set(
         &mut conn,
         SetOption {
             guild_id: guild_id.into(),
             user_id: interaction.user.id.into(),
             value: selected_preset_id,
+            provider: kind.as_str().to_string(),
         },
     )
     .await?;
@@ -58,15 +185,134 @@ async fn handle_voice(ctx: &Context, interaction: &MessageComponentInteraction)
     r(
         ctx,
         interaction,
-        format!(
-            "<@{}>さんの声を`{}`に変更しました。",
-            interaction.user.id, selected_preset.name
+        messages::format(
+            lang,
+            Key::VoiceChanged,
+            &[
+                ("user", &interaction.user.id),
+                ("voice", &selected_preset.name),
+            ],
         ),
     )
     .await?;
     Ok(())
 }
 
+async fn handle_dict_page(
+    ctx: &Context,
+    interaction: &MessageComponentInteraction,
+    lang: Language,
+    page: usize,
+    guild_id: GuildId,
+    invoker: UserId,
+) -> Result<()> {
+    if interaction.guild_id != Some(guild_id) {
+        bail!(
+            "Guild ID in custom_id does not match: {:?} != {}",
+            interaction.guild_id,
+            guild_id
+        );
+    }
+
+    let (embed, components) = dict_view::build_page(ctx, lang, guild_id, page, invoker).await?;
+
+    interaction
+        .create_interaction_response(&ctx.http, |create_response| {
+            create_response
+                .kind(InteractionResponseType::UpdateMessage)
+                .interaction_response_data(|create_message| {
+                    create_message.set_embed(embed).set_components(components)
+                })
+        })
+        .await
+        .context("Failed to update message")?;
+
+    Ok(())
+}
+
+async fn handle_confirm(
+    ctx: &Context,
+    interaction: &MessageComponentInteraction,
+    lang: Language,
+    action: ConfirmAction,
+    accepted: bool,
+    guild_id: GuildId,
+) -> Result<()> {
+    if interaction.guild_id != Some(guild_id) {
+        bail!(
+            "Guild ID in custom_id does not match: {:?} != {}",
+            interaction.guild_id,
+            guild_id
+        );
+    }
+
+    // 期限を過ぎた確認や、Botの再起動前に送信した確認は受け付けない
+    let state = app_state::get(ctx).await?;
+    let pending = interaction
+        .message
+        .interaction
+        .as_ref()
+        .and_then(|origin| state.pending_confirmations.remove(&origin.id))
+        .is_some();
+    if !pending {
+        disable_components(ctx, interaction, lang).await?;
+        return Ok(());
+    }
+
+    let msg = if accepted {
+        confirm::execute(ctx, lang, action, guild_id).await?
+    } else {
+        messages::text(lang, Key::ConfirmCancelled)
+    };
+
+    interaction
+        .create_interaction_response(&ctx.http, |create_response| {
+            create_response
+                .kind(InteractionResponseType::UpdateMessage)
+                .interaction_response_data(|create_message| {
+                    create_message
+                        .content(msg)
+                        .set_components(CreateComponents::default())
+                })
+        })
+        .await
+        .context("Failed to update message")?;
+
+    Ok(())
+}
+
+/// コンポーネントを含むメッセージが、操作できる期間を過ぎているかどうかを返す
+fn is_expired(interaction: &MessageComponentInteraction) -> bool {
+    let created_at = interaction.message.timestamp.unix_timestamp();
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64);
+
+    now - created_at > COMPONENT_LIFETIME.as_secs() as i64
+}
+
+/// 操作できる期間を過ぎたメッセージから、コンポーネントを取り除く
+async fn disable_components(
+    ctx: &Context,
+    interaction: &MessageComponentInteraction,
+    lang: Language,
+) -> Result<()> {
+    interaction
+        .create_interaction_response(&ctx.http, |create_response| {
+            create_response
+                .kind(InteractionResponseType::UpdateMessage)
+                .interaction_response_data(|create_message| {
+                    create_message
+                        .content(messages::text(lang, Key::ComponentExpired))
+                        .set_components(CreateComponents::default())
+                })
+        })
+        .await
+        .context("Failed to disable message components")?;
+
+    Ok(())
+}
+
 // Helper function to create text message response
 async fn r(
     ctx: &Context,
@@ -84,3 +330,22 @@ async fn r(
 
     Ok(())
 }
+
+async fn r_ephemeral(
+    ctx: &Context,
+    interaction: &MessageComponentInteraction,
+    text: impl ToString,
+) -> Result<()> {
+    interaction
+        .create_interaction_response(&ctx.http, |create_response| {
+            create_response
+                .kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|create_message| {
+                    create_message.content(text).ephemeral(true)
+                })
+        })
+        .await
+        .context("Failed to create interaction response")?;
+
+    Ok(())
+}
@@ -0,0 +1,597 @@
+use crate::{
+    alert, app_state,
+    error::report_error,
+    messages::Key,
+    notice,
+    speech_queue::{self, Voice},
+};
+use anyhow::{Context as _, Result};
+use koe_audio::chime;
+use koe_db::{
+    farewell,
+    guild_settings::{self, BoolKey},
+};
+use log::{info, warn};
+use serenity::{
+    async_trait,
+    client::Context,
+    http::HttpError,
+    model::{
+        channel::{Channel, ChannelType, GuildChannel},
+        id::{ChannelId, GuildId},
+        Permissions,
+    },
+    Error as SerenityError,
+};
+use songbird::{
+    error::JoinError,
+    events::{
+        context_data::{ConnectData, DisconnectData, DisconnectReason},
+        Event, EventContext, EventHandler as VoiceEventHandler,
+    },
+    model::CloseCode,
+};
+use std::{
+    collections::HashMap,
+    sync::{atomic::Ordering, Arc},
+    time::{Duration, Instant},
+};
+
+/// 予期せず切断された際に、最初に再接続を試みるまでの時間
+/// 以降は失敗するたびに倍にし、[`MAX_RECONNECT_DELAY`]を上限とする
+const INITIAL_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(16);
+
+/// 音声の再生にこの回数続けて失敗した場合に、接続の回復を試みる
+const TRACK_FAILURE_THRESHOLD: u32 = 3;
+/// 1回の接続で接続の回復を試みる最大の回数
+const MAX_RECOVERY_ATTEMPTS: u32 = 3;
+
+/// 退出時のチャイムの再生を待つ最大の時間
+const DISCONNECT_CHIME_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// 退出時の挨拶の音声合成と再生を待つ最大の時間
+/// 音声合成が応答しない場合でも、これを過ぎると挨拶せずに退出する
+const FAREWELL_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Discord APIのエラーコード
+/// 詳細は https://discord.com/developers/docs/topics/opcodes-and-status-codes#json
+const DISCORD_MISSING_ACCESS: isize = 50001;
+const DISCORD_MISSING_PERMISSIONS: isize = 50013;
+
+/// ステージチャンネルでスピーカーになれなかった際に、再試行するまでの時間
+const STAGE_SPEAKER_RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// ボイスチャンネルに接続し、`text_channel_id`のメッセージの読み上げを開始する
+pub async fn join(
+    ctx: &Context,
+    guild_id: GuildId,
+    voice_channel_id: ChannelId,
+    text_channel_id: ChannelId,
+) -> Result<()> {
+    let state = app_state::get(ctx).await?;
+
+    if let Err(err) = connect(ctx, guild_id, voice_channel_id).await {
+        // ステージチャンネルでスピーカーになれなかった場合など、読み上げられない状態で接続したままにしない
+        state.voice_backend.leave(guild_id.into()).await?;
+        return Err(err);
+    }
+    state
+        .voice_backend
+        .set_event_handlers(
+            guild_id.into(),
+            Arc::new(ConnectionHandler {
+                ctx: ctx.clone(),
+                guild_id,
+            }),
+            Arc::new(TrackEndHandler {
+                ctx: ctx.clone(),
+                guild_id,
+            }),
+        )
+        .await?;
+
+    state.connected_guild_states.insert(
+        guild_id,
+        app_state::ConnectedGuildState {
+            voice_channel: voice_channel_id,
+            bound_text_channels: vec![text_channel_id],
+            last_message_read: None,
+            last_reaction_announced: None,
+            pending_deletions: 0,
+            connected_at: Instant::now(),
+            last_activity: Instant::now(),
+            last_speech_error: None,
+            consecutive_track_failures: 0,
+            recovery_attempts: 0,
+            departed_members: HashMap::new(),
+            voice_server: None,
+            volume_boosts: HashMap::new(),
+            follow_scheduled: false,
+            speech_sequencer: Default::default(),
+        },
+    );
+
+    if is_chime_enabled(ctx, guild_id).await? {
+        let chime = chime::connect_chime().into();
+        state
+            .voice_backend
+            .enqueue(guild_id.into(), chime, None)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// 読み上げを続けたまま、別のボイスチャンネルに移動する
+pub async fn move_to(ctx: &Context, guild_id: GuildId, voice_channel_id: ChannelId) -> Result<()> {
+    // 移動に伴うボイスステートの更新をモデレーターによる移動と誤認しないよう、先に状態を更新する
+    let state = app_state::get(ctx).await?;
+    if let Some(mut guild_state) = state.connected_guild_states.get_mut(&guild_id) {
+        guild_state.voice_channel = voice_channel_id;
+        guild_state.departed_members.clear();
+    }
+
+    connect(ctx, guild_id, voice_channel_id).await?;
+
+    Ok(())
+}
+
+/// 接続したまま、読み上げ対象のテキストチャンネルを`text_channel_id`のみに変更する
+pub async fn rebind(ctx: &Context, guild_id: GuildId, text_channel_id: ChannelId) -> Result<()> {
+    let state = app_state::get(ctx).await?;
+    if let Some(mut guild_state) = state.connected_guild_states.get_mut(&guild_id) {
+        guild_state.bound_text_channels = vec![text_channel_id];
+        guild_state.last_message_read = None;
+    }
+
+    Ok(())
+}
+
+/// ボイスチャンネルから退出し、読み上げを終了する
+pub async fn leave(ctx: &Context, guild_id: GuildId) -> Result<()> {
+    // 退出に伴うボイスステートの更新を強制的な切断と誤認しないよう、先に状態を削除する
+    let state = app_state::get(ctx).await?;
+    state.connected_guild_states.remove(&guild_id);
+
+    // 挨拶やチャイムの再生に失敗しても退出できるよう、エラーは報告するのみにとどめる
+    match tokio::time::timeout(FAREWELL_TIMEOUT, play_farewell(ctx, guild_id)).await {
+        Ok(result) => {
+            if let Err(err) = result.context("Failed to play farewell") {
+                report_error(err);
+            }
+        }
+        Err(_) => warn!("Timed out playing farewell in guild {}", guild_id),
+    }
+
+    if let Err(err) = play_disconnect_chime(ctx, guild_id)
+        .await
+        .context("Failed to play disconnect chime")
+    {
+        report_error(err);
+    }
+
+    state.voice_backend.leave(guild_id.into()).await?;
+
+    Ok(())
+}
+
+/// サーバーで退出時の挨拶が設定されている場合は、読み上げ終わるまで待つ
+async fn play_farewell(ctx: &Context, guild_id: GuildId) -> Result<()> {
+    let state = app_state::get(ctx).await?;
+    if !state.voice_backend.is_connected(guild_id.into()).await? {
+        return Ok(());
+    }
+
+    let mut conn = state.redis_client.get_async_connection().await?;
+    let text = match farewell::get(
+        &mut conn,
+        farewell::GetOption {
+            guild_id: guild_id.into(),
+        },
+    )
+    .await?
+    {
+        Some(text) => text,
+        None => return Ok(()),
+    };
+
+    let raw_audio = match speech_queue::prepare(ctx, guild_id, &text, Voice::System).await? {
+        Some(audio) => audio,
+        None => return Ok(()),
+    };
+    state
+        .voice_backend
+        .play_and_wait(guild_id.into(), raw_audio, FAREWELL_TIMEOUT)
+        .await?;
+
+    Ok(())
+}
+
+async fn play_disconnect_chime(ctx: &Context, guild_id: GuildId) -> Result<()> {
+    let state = app_state::get(ctx).await?;
+    if !state.voice_backend.is_connected(guild_id.into()).await?
+        || !is_chime_enabled(ctx, guild_id).await?
+    {
+        return Ok(());
+    }
+
+    let chime = chime::disconnect_chime().into();
+    state
+        .voice_backend
+        .play_and_wait(guild_id.into(), chime, DISCONNECT_CHIME_TIMEOUT)
+        .await?;
+
+    Ok(())
+}
+
+async fn is_chime_enabled(ctx: &Context, guild_id: GuildId) -> Result<bool> {
+    let state = app_state::get(ctx).await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+
+    guild_settings::get_bool(
+        &mut conn,
+        guild_settings::GetBoolOption {
+            guild_id: guild_id.into(),
+            key: BoolKey::Chime,
+        },
+    )
+    .await
+}
+
+/// Botがボイスチャンネルで読み上げるために必要な権限のうち、不足しているものを返す
+/// ステージチャンネルでは、スピーカーになるために「メンバーをミュート」の権限が必要になる
+/// キャッシュから権限を計算できない場合は[`None`]を返す
+pub fn missing_permissions(ctx: &Context, voice_channel_id: ChannelId) -> Option<Permissions> {
+    let channel = ctx.cache.guild_channel(voice_channel_id)?;
+    let permissions = channel
+        .permissions_for_user(&ctx.cache, ctx.cache.current_user_id())
+        .ok()?;
+
+    let required = match channel.kind {
+        ChannelType::Stage => Permissions::CONNECT | Permissions::MUTE_MEMBERS,
+        _ => Permissions::CONNECT | Permissions::SPEAK,
+    };
+    Some(required & !permissions)
+}
+
+/// 接続に失敗した原因が権限の不足などと判別できる場合に、利用者向けの説明の種類を返す
+pub fn describe_join_error(err: &anyhow::Error) -> Option<Key> {
+    for cause in err.chain() {
+        if let Some(SerenityError::Http(http_err)) = cause.downcast_ref::<SerenityError>() {
+            if let HttpError::UnsuccessfulRequest(resp) = http_err.as_ref() {
+                match resp.error.code {
+                    DISCORD_MISSING_ACCESS => return Some(Key::JoinMissingAccess),
+                    DISCORD_MISSING_PERMISSIONS => return Some(Key::JoinMissingPermissions),
+                    _ => {}
+                }
+            }
+        }
+
+        if let Some(JoinError::TimedOut) = cause.downcast_ref::<JoinError>() {
+            return Some(Key::JoinTimedOut);
+        }
+    }
+
+    None
+}
+
+async fn connect(ctx: &Context, guild_id: GuildId, voice_channel_id: ChannelId) -> Result<()> {
+    let state = app_state::get(ctx).await?;
+    state
+        .voice_backend
+        .join(guild_id.into(), voice_channel_id.into(), state.self_deaf)
+        .await?;
+
+    if let Some(channel) = get_stage_channel(ctx, voice_channel_id).await? {
+        become_speaker(ctx, &channel).await?;
+    }
+
+    Ok(())
+}
+
+/// ステージチャンネルで聴衆からスピーカーになる
+async fn become_speaker(ctx: &Context, channel: &GuildChannel) -> Result<()> {
+    // 接続直後はステージが開始されておらず失敗することがあるため、1回だけ再試行する
+    if let Err(err) = channel
+        .edit_own_voice_state(&ctx.http, |state| state.suppress(false))
+        .await
+    {
+        warn!(
+            "Failed to become a speaker in stage channel {}, retrying: {:?}",
+            channel.id, err
+        );
+        tokio::time::sleep(STAGE_SPEAKER_RETRY_DELAY).await;
+
+        channel
+            .edit_own_voice_state(&ctx.http, |state| state.suppress(false))
+            .await
+            .context("Failed to become a speaker in stage channel")?;
+    }
+
+    Ok(())
+}
+
+async fn get_stage_channel(ctx: &Context, channel_id: ChannelId) -> Result<Option<GuildChannel>> {
+    let channel = match channel_id.to_channel(ctx).await? {
+        Channel::Guild(channel) if channel.kind == ChannelType::Stage => channel,
+        _ => return Ok(None),
+    };
+    Ok(Some(channel))
+}
+
+#[derive(Clone)]
+struct ConnectionHandler {
+    ctx: Context,
+    guild_id: GuildId,
+}
+
+#[async_trait]
+impl VoiceEventHandler for ConnectionHandler {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        match ctx {
+            EventContext::DriverConnect(data) => self.on_connect(data, false).await,
+            EventContext::DriverReconnect(data) => self.on_connect(data, true).await,
+            EventContext::DriverDisconnect(data) => self.on_disconnect(data),
+            _ => {}
+        }
+
+        None
+    }
+}
+
+impl ConnectionHandler {
+    async fn on_connect(&self, data: &ConnectData<'_>, reconnected: bool) {
+        info!(
+            "Voice connection {} in guild {} (server: {}, session: {})",
+            if reconnected {
+                "re-established"
+            } else {
+                "established"
+            },
+            self.guild_id,
+            data.server,
+            data.session_id
+        );
+
+        let state = match app_state::get(&self.ctx).await {
+            Ok(state) => state,
+            Err(err) => {
+                report_error(err);
+                return;
+            }
+        };
+
+        let (previous_server, voice_channel_id) = {
+            let mut guild_state = match state.connected_guild_states.get_mut(&self.guild_id) {
+                Some(guild_state) => guild_state,
+                None => return,
+            };
+            let previous_server = guild_state.voice_server.replace(data.server.to_string());
+            if reconnected {
+                guild_state.consecutive_track_failures = 0;
+            }
+            (previous_server, guild_state.voice_channel)
+        };
+
+        if !reconnected {
+            return;
+        }
+        if previous_server.as_deref() != Some(data.server) {
+            info!(
+                "Voice server changed in guild {}: {:?} -> {}",
+                self.guild_id, previous_server, data.server
+            );
+        }
+
+        // リージョンの変更などで再接続した後も、songbirdの接続が読み上げ中のチャンネルを指しているか確かめる
+        let ctx = self.ctx.clone();
+        let guild_id = self.guild_id;
+        tokio::spawn(async move {
+            if let Err(err) = validate_connection(&ctx, guild_id, voice_channel_id)
+                .await
+                .context("Failed to validate voice connection after reconnect")
+            {
+                report_error(err);
+            }
+        });
+    }
+
+    fn on_disconnect(&self, data: &DisconnectData<'_>) {
+        let should_reconnect = match &data.reason {
+            // 退出やチャンネルの移動など、Bot自身が要求した切断
+            None => false,
+            // モデレーターによって切断された
+            Some(DisconnectReason::WsClosed(Some(CloseCode::Disconnected))) => false,
+            Some(_) => true,
+        };
+        if !should_reconnect {
+            return;
+        }
+
+        let channel_id = match data.channel_id {
+            Some(id) => ChannelId(id.0),
+            None => return,
+        };
+
+        warn!(
+            "Voice connection in guild {} was closed unexpectedly: {:?}",
+            self.guild_id, data.reason
+        );
+
+        let ctx = self.ctx.clone();
+        let guild_id = self.guild_id;
+        let reason = format!("{:?}", data.reason);
+        tokio::spawn(async move {
+            if let Err(err) = reconnect(&ctx, guild_id, channel_id, &reason)
+                .await
+                .context("Failed to reconnect to voice channel")
+            {
+                report_error(err);
+            }
+        });
+    }
+}
+
+/// songbirdの接続先が`voice_channel_id`と異なる場合は接続し直す
+async fn validate_connection(
+    ctx: &Context,
+    guild_id: GuildId,
+    voice_channel_id: ChannelId,
+) -> Result<()> {
+    let state = app_state::get(ctx).await?;
+    let current_channel_id = state.voice_backend.current_channel(guild_id.into()).await?;
+    if current_channel_id == Some(voice_channel_id.into()) {
+        return Ok(());
+    }
+
+    warn!(
+        "Voice connection in guild {} points to {:?} instead of {} after reconnect",
+        guild_id, current_channel_id, voice_channel_id
+    );
+    connect(ctx, guild_id, voice_channel_id).await
+}
+
+struct TrackEndHandler {
+    ctx: Context,
+    guild_id: GuildId,
+}
+
+#[async_trait]
+impl koe_call::TrackEndHandler for TrackEndHandler {
+    async fn on_track_end(&self, failed: bool) {
+        let state = match app_state::get(&self.ctx).await {
+            Ok(state) => state,
+            Err(err) => {
+                report_error(err);
+                return;
+            }
+        };
+
+        let voice_channel_id = {
+            let mut guild_state = match state.connected_guild_states.get_mut(&self.guild_id) {
+                Some(guild_state) => guild_state,
+                None => return,
+            };
+
+            if !failed {
+                guild_state.consecutive_track_failures = 0;
+                return;
+            }
+
+            guild_state.consecutive_track_failures += 1;
+            warn!(
+                "Track ended before finishing in guild {} ({} in a row)",
+                self.guild_id, guild_state.consecutive_track_failures
+            );
+
+            if guild_state.consecutive_track_failures < TRACK_FAILURE_THRESHOLD {
+                return;
+            }
+            if guild_state.recovery_attempts >= MAX_RECOVERY_ATTEMPTS {
+                warn!(
+                    "Tracks keep failing in guild {}, but recovery attempts are exhausted",
+                    self.guild_id
+                );
+                return;
+            }
+
+            guild_state.consecutive_track_failures = 0;
+            guild_state.recovery_attempts += 1;
+            info!(
+                "Recovering voice connection in guild {} (attempt {})",
+                self.guild_id, guild_state.recovery_attempts
+            );
+            guild_state.voice_channel
+        };
+        state
+            .recovery_attempts_total
+            .fetch_add(1, Ordering::Relaxed);
+
+        let ctx = self.ctx.clone();
+        let guild_id = self.guild_id;
+        tokio::spawn(async move {
+            // 同じチャンネルへの再接続を要求すると、songbirdは問題がある場合にのみ接続し直す
+            if let Err(err) = connect(&ctx, guild_id, voice_channel_id)
+                .await
+                .context("Failed to recover voice connection")
+            {
+                report_error(err);
+            }
+        });
+    }
+}
+
+/// 設定された回数まで再接続を試み、すべて失敗した場合は読み上げを終了して利用者と運営者に通知する
+/// `reason`は切断された理由で、運営者への通知に含める
+async fn reconnect(
+    ctx: &Context,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    reason: &str,
+) -> Result<()> {
+    let max_attempts = app_state::get(ctx).await?.max_reconnect_attempts;
+    let mut delay = INITIAL_RECONNECT_DELAY;
+    let mut last_error = None;
+
+    for attempt in 1..=max_attempts {
+        tokio::time::sleep(delay).await;
+        delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+
+        // 待機中に`/leave`などで読み上げが終了した場合は再接続しない
+        let state = app_state::get(ctx).await?;
+        if !state.connected_guild_states.contains_key(&guild_id) {
+            return Ok(());
+        }
+
+        match connect(ctx, guild_id, channel_id).await {
+            Ok(()) => {
+                info!(
+                    "Reconnected to voice channel in guild {} (attempt {})",
+                    guild_id, attempt
+                );
+                return Ok(());
+            }
+            Err(err) => {
+                warn!(
+                    "Failed to reconnect to voice channel in guild {} (attempt {}): {:?}",
+                    guild_id, attempt, err
+                );
+                last_error = Some(err);
+            }
+        }
+    }
+
+    let state = app_state::get(ctx).await?;
+    let bound_text_channel = match state.connected_guild_states.remove(&guild_id) {
+        Some((_, guild_state)) => guild_state.primary_text_channel(),
+        None => return Ok(()),
+    };
+    state.voice_backend.leave(guild_id.into()).await?;
+
+    let last_error = match &last_error {
+        Some(err) => format!("{:#}", err),
+        None => "再接続を試みていません".to_string(),
+    };
+    let alert_content = format!(
+        "サーバー `{}` でボイスチャンネルとの接続が切断され、{}回の再接続に失敗したため読み上げを終了しました。\n切断の理由: `{}`\n最後のエラー: `{}`",
+        guild_id, max_attempts, reason, last_error
+    );
+    if let Err(err) = alert::notify_operator(ctx, &alert_content)
+        .await
+        .context("Failed to notify operator of reconnect failure")
+    {
+        report_error(err);
+    }
+
+    notice::send(
+        ctx,
+        guild_id,
+        bound_text_channel,
+        "ボイスチャンネルとの接続が切断されたため再接続を試みましたが、失敗しました。`/join` で再度接続してください。",
+    )
+    .await
+    .context("Failed to send message")?;
+
+    Ok(())
+}
@@ -0,0 +1,94 @@
+use crate::{app_state::AppState, speech_provider};
+use anyhow::{anyhow, Result};
+use koe_db::{
+    guild_settings::{self, BoolKey},
+    redis::aio::Connection,
+    voice::{self, VoiceSettings},
+};
+use koe_speech::provider::ProviderKind;
+use rand::seq::SliceRandom;
+use serenity::model::id::{GuildId, UserId};
+
+/// メンバーの読み上げに使うプリセットを返す
+/// 声が未設定の場合は、[`choose_preset_id`]で選んだプリセットを`kind`のエンジンの声として保存する
+/// 設定された声が`kind`のエンジンで使えない場合は、設定を変更せずにユーザーIDで決まるプリセットを使う
+/// `/voice language`で一時的に別のエンジンを使う場合などに、もとのエンジンの声を上書きしないため
+pub async fn resolve_preset_id(
+    state: &AppState,
+    conn: &mut Connection,
+    guild_id: GuildId,
+    user_id: UserId,
+    settings: &VoiceSettings,
+    kind: ProviderKind,
+    preset_ids: &[i64],
+) -> Result<i64> {
+    let default_kind = state.speech_providers.default_kind();
+    if let Some(preset_id) =
+        speech_provider::usable_preset_id(settings, kind, default_kind, preset_ids)
+    {
+        return Ok(preset_id);
+    }
+
+    if settings.preset_id.is_some() {
+        return preset_id_by_user_id(user_id, preset_ids)
+            .ok_or_else(|| anyhow!("No presets available"));
+    }
+
+    let preset_id = choose_preset_id(conn, guild_id, user_id, preset_ids).await?;
+    voice::set(
+        conn,
+        voice::SetOption {
+            guild_id: guild_id.into(),
+            user_id: user_id.into(),
+            value: preset_id,
+            provider: kind.as_str().to_string(),
+        },
+    )
+    .await?;
+    Ok(preset_id)
+}
+
+/// 声が未設定のメンバーに割り当てるプリセットを選ぶ
+/// サーバーの設定で有効な場合は、使用できるすべてのプリセットの中からユーザーIDで決まるものを選ぶ
+/// プリセットの数が変わると選ばれるプリセットも変わるが、一度割り当てた声は保存されるため影響はない
+pub async fn choose_preset_id(
+    conn: &mut Connection,
+    guild_id: GuildId,
+    user_id: UserId,
+    preset_ids: &[i64],
+) -> Result<i64> {
+    let by_user_id = guild_settings::get_bool(
+        conn,
+        guild_settings::GetBoolOption {
+            guild_id: guild_id.into(),
+            key: BoolKey::VoiceByUserId,
+        },
+    )
+    .await?;
+
+    let preset_id = if by_user_id {
+        preset_id_by_user_id(user_id, preset_ids)
+    } else {
+        preset_ids.choose(&mut rand::thread_rng()).copied()
+    };
+
+    preset_id.ok_or_else(|| anyhow!("No presets available"))
+}
+
+/// 使用できるすべてのプリセットの中から、ユーザーIDで決まるものを返す
+fn preset_id_by_user_id(user_id: UserId, preset_ids: &[i64]) -> Option<i64> {
+    let mut preset_ids = preset_ids.to_vec();
+    preset_ids.sort_unstable();
+    preset_ids
+        .get((mix(user_id.0) % preset_ids.len().max(1) as u64) as usize)
+        .copied()
+}
+
+/// ユーザーIDの偏りをならすためのハッシュ関数（SplitMix64）
+/// 再起動やRustのバージョンによって結果が変わらないよう、標準ライブラリのハッシュ関数は使わない
+fn mix(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
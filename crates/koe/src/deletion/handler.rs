@@ -0,0 +1,85 @@
+use crate::{announcement, app_state, error::report_error};
+use anyhow::{Context as _, Result};
+use koe_db::guild_settings::{self, BoolKey, GetBoolOption};
+use serenity::{
+    client::Context,
+    model::id::{ChannelId, GuildId},
+};
+use std::time::Duration;
+
+/// 最初の削除からお知らせを読み上げるまでの待ち時間
+/// この間に削除されたメッセージはまとめて1回だけお知らせする
+const NOTICE_DELAY: Duration = Duration::from_secs(3);
+
+/// メッセージが削除されたことを読み上げる
+/// 削除されたメッセージの内容は読み上げない
+pub async fn handle_delete(
+    ctx: &Context,
+    guild_id: Option<GuildId>,
+    channel_id: ChannelId,
+    count: usize,
+) -> Result<()> {
+    let guild_id = match guild_id {
+        Some(id) => id,
+        None => return Ok(()),
+    };
+
+    let state = app_state::get(ctx).await?;
+    match state.connected_guild_states.get(&guild_id) {
+        Some(guild_state) if guild_state.is_bound(channel_id) => {}
+        _ => return Ok(()),
+    }
+
+    let mut conn = state.redis_client.get_async_connection().await?;
+    let enabled = guild_settings::get_bool(
+        &mut conn,
+        GetBoolOption {
+            guild_id: guild_id.into(),
+            key: BoolKey::ReadDeletions,
+        },
+    )
+    .await?;
+    if !enabled {
+        return Ok(());
+    }
+
+    let is_first = match state.connected_guild_states.get_mut(&guild_id) {
+        Some(mut guild_state) => {
+            let is_first = guild_state.pending_deletions == 0;
+            guild_state.pending_deletions += count;
+            is_first
+        }
+        None => return Ok(()),
+    };
+    if !is_first {
+        return Ok(());
+    }
+
+    let ctx = ctx.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(NOTICE_DELAY).await;
+        if let Err(err) = announce_deletions(&ctx, guild_id)
+            .await
+            .context("Failed to announce message deletion")
+        {
+            report_error(err);
+        }
+    });
+
+    Ok(())
+}
+
+async fn announce_deletions(ctx: &Context, guild_id: GuildId) -> Result<()> {
+    let state = app_state::get(ctx).await?;
+    let count = match state.connected_guild_states.get_mut(&guild_id) {
+        Some(mut guild_state) => std::mem::take(&mut guild_state.pending_deletions),
+        None => return Ok(()),
+    };
+
+    let text = match count {
+        0 => return Ok(()),
+        1 => "メッセージが削除されました",
+        _ => "複数のメッセージが削除されました",
+    };
+    announcement::announce(ctx, guild_id, text).await
+}
@@ -1,9 +1,135 @@
-use log::error;
+use dashmap::{mapref::entry::Entry, DashMap};
+use log::{error, warn};
+use once_cell::sync::OnceCell;
 use sentry::integrations::anyhow::capture_anyhow;
+use serenity::model::id::GuildId;
+use std::time::{Duration, Instant};
+
+/// 同じ種類のエラーを続けて記録しないようにする
+/// 設定されていない場合は、すべてのエラーを記録する
+static LIMITER: OnceCell<ErrorLogLimiter> = OnceCell::new();
+
+/// 音声合成のエンジンやRedisが停止している間に、同じエラーでログが埋め尽くされないよう、
+/// エラーの種類とサーバーごとに、`window`の間は最初の1件だけを記録する
+/// 記録しなかったエラーの数は、`window`が過ぎた後にまとめて記録する
+pub fn init_rate_limit(window: Duration) {
+    if window.is_zero() {
+        return;
+    }
+    if LIMITER.set(ErrorLogLimiter::new(window)).is_err() {
+        return;
+    }
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(window);
+        loop {
+            interval.tick().await;
+            if let Some(limiter) = LIMITER.get() {
+                limiter.flush(Instant::now());
+            }
+        }
+    });
+}
 
 pub fn report_error(err: impl Into<anyhow::Error>) {
+    report_guild_error(None, err);
+}
+
+/// サーバーで発生したエラーを記録する
+/// 記録を間引く場合は、サーバーごとに数える
+pub fn report_guild_error(guild_id: Option<GuildId>, err: impl Into<anyhow::Error>) {
     let err = err.into();
 
+    if let Some(limiter) = LIMITER.get() {
+        if !limiter.check(ErrorKind::new(guild_id, &err), Instant::now()) {
+            return;
+        }
+    }
+
     error!("{:?}", err);
     capture_anyhow(&err);
 }
+
+/// 記録を間引く単位
+/// 最も外側の文脈と根本の原因の両方が一致するエラーを、同じ種類として扱う
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ErrorKind {
+    guild_id: Option<GuildId>,
+    context: String,
+    root_cause: String,
+}
+
+impl ErrorKind {
+    fn new(guild_id: Option<GuildId>, err: &anyhow::Error) -> Self {
+        Self {
+            guild_id,
+            context: err.to_string(),
+            root_cause: err.root_cause().to_string(),
+        }
+    }
+}
+
+struct ErrorLogLimiter {
+    window: Duration,
+    /// 種類ごとの、最後に記録した時刻と、その後に記録しなかった数
+    entries: DashMap<ErrorKind, (Instant, u64)>,
+}
+
+impl ErrorLogLimiter {
+    fn new(window: Duration) -> Self {
+        Self {
+            window,
+            entries: DashMap::new(),
+        }
+    }
+
+    /// エラーを記録してよいかを返す
+    /// 記録しない場合は、記録しなかった数に加える
+    fn check(&self, kind: ErrorKind, now: Instant) -> bool {
+        match self.entries.entry(kind) {
+            Entry::Vacant(entry) => {
+                entry.insert((now, 0));
+                true
+            }
+            Entry::Occupied(mut entry) => {
+                let (logged_at, suppressed) = *entry.get();
+                if now.duration_since(logged_at) < self.window {
+                    entry.get_mut().1 += 1;
+                    return false;
+                }
+
+                log_suppressed(entry.key(), suppressed, self.window);
+                entry.insert((now, 0));
+                true
+            }
+        }
+    }
+
+    /// `window`が過ぎた種類について、記録しなかった数を記録し、記録を削除する
+    fn flush(&self, now: Instant) {
+        self.entries.retain(|kind, (logged_at, suppressed)| {
+            if now.duration_since(*logged_at) < self.window {
+                return true;
+            }
+            log_suppressed(kind, *suppressed, self.window);
+            false
+        });
+    }
+}
+
+fn log_suppressed(kind: &ErrorKind, suppressed: u64, window: Duration) {
+    if suppressed == 0 {
+        return;
+    }
+
+    match kind.guild_id {
+        Some(guild_id) => warn!(
+            "Suppressed {} similar errors in guild {} within {:?}: {}: {}",
+            suppressed, guild_id, window, kind.context, kind.root_cause
+        ),
+        None => warn!(
+            "Suppressed {} similar errors within {:?}: {}: {}",
+            suppressed, window, kind.context, kind.root_cause
+        ),
+    }
+}
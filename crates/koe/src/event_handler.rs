@@ -1,6 +1,6 @@
-use crate::error::report_error;
-use crate::{command, voice_state};
-use crate::{component_interaction, message};
+use crate::error::{report_error, report_guild_error};
+use crate::{app_state, autocomplete, channel, command, deletion, idle, voice_state};
+use crate::{component_interaction, message, reaction, scheduled_event};
 use anyhow::Context as _;
 use log::info;
 use serenity::{
@@ -8,9 +8,10 @@ use serenity::{
     client::{Context, EventHandler},
     model::{
         application::interaction::Interaction,
-        channel::Message,
+        channel::{GuildChannel, Message, Reaction},
         gateway::{Activity, Ready},
-        guild::Guild,
+        guild::{Guild, ScheduledEvent},
+        id::{ChannelId, GuildId, MessageId},
         voice::VoiceState,
     },
 };
@@ -25,20 +26,55 @@ impl EventHandler for Handler {
         ctx.set_activity(Activity::playing("テキストチャット 読み上げBot"))
             .await;
 
-        for guild in &ready.guilds {
-            if let Err(err) = command::setup::setup_guild_commands(&ctx, guild.id)
-                .await
-                .context("Failed to set guild application commands")
-            {
+        idle::spawn_watcher(&ctx);
+
+        let state = match app_state::get(&ctx).await {
+            Ok(state) => state,
+            Err(err) => {
                 report_error(err);
+                return;
             }
+        };
+        state.startup_limiter.begin(ready.guilds.len());
+
+        if let Err(err) = state
+            .startup_limiter
+            .run("Setting application commands", || {
+                command::setup::setup_commands(&ctx)
+            })
+            .await
+            .context("Failed to set application commands")
+        {
+            report_error(err);
         }
     }
 
     async fn guild_create(&self, ctx: Context, guild: Guild, _is_new: bool) {
-        if let Err(err) = command::setup::setup_guild_commands(&ctx, guild.id)
+        let state = match app_state::get(&ctx).await {
+            Ok(state) => state,
+            Err(err) => {
+                report_error(err);
+                return;
+            }
+        };
+
+        if let Err(err) = state
+            .startup_limiter
+            .run("Cleaning up guild application commands", || {
+                command::setup::clean_up_guild_commands(&ctx, guild.id)
+            })
             .await
-            .context("Failed to set guild application commands")
+            .context("Failed to clean up guild application commands")
+        {
+            report_error(err);
+        }
+        state.startup_limiter.record_progress();
+    }
+
+    async fn channel_delete(&self, ctx: Context, channel: &GuildChannel) {
+        if let Err(err) = channel::handler::handle_delete(&ctx, channel)
+            .await
+            .context("Failed to handle channel deletion")
         {
             report_error(err);
         }
@@ -51,7 +87,7 @@ impl EventHandler for Handler {
                     .await
                     .context("Failed to respond to slash command")
                 {
-                    report_error(err);
+                    report_guild_error(command.guild_id, err);
                 }
             }
             Interaction::MessageComponent(component_interaction) => {
@@ -59,6 +95,23 @@ impl EventHandler for Handler {
                     component_interaction::handler::handle(&ctx, &component_interaction)
                         .await
                         .context("Failed to respond to message components interaction")
+                {
+                    report_guild_error(component_interaction.guild_id, err);
+                }
+            }
+            Interaction::Autocomplete(autocomplete_interaction) => {
+                if let Err(err) = autocomplete::handler::handle(&ctx, &autocomplete_interaction)
+                    .await
+                    .context("Failed to respond to autocomplete interaction")
+                {
+                    report_error(err);
+                }
+            }
+            Interaction::ModalSubmit(modal_interaction) => {
+                if let Err(err) =
+                    component_interaction::handler::handle_modal_submit(&ctx, &modal_interaction)
+                        .await
+                        .context("Failed to respond to modal submit interaction")
                 {
                     report_error(err);
                 }
@@ -68,9 +121,72 @@ impl EventHandler for Handler {
     }
 
     async fn message(&self, ctx: Context, msg: Message) {
+        let guild_id = msg.guild_id;
         if let Err(err) = message::handler::handle(&ctx, msg)
             .await
             .context("Failed to handle message")
+        {
+            report_guild_error(guild_id, err);
+        }
+    }
+
+    async fn message_delete(
+        &self,
+        ctx: Context,
+        channel_id: ChannelId,
+        _deleted_message_id: MessageId,
+        guild_id: Option<GuildId>,
+    ) {
+        if let Err(err) = deletion::handler::handle_delete(&ctx, guild_id, channel_id, 1)
+            .await
+            .context("Failed to handle message deletion")
+        {
+            report_guild_error(guild_id, err);
+        }
+    }
+
+    async fn message_delete_bulk(
+        &self,
+        ctx: Context,
+        channel_id: ChannelId,
+        multiple_deleted_messages_ids: Vec<MessageId>,
+        guild_id: Option<GuildId>,
+    ) {
+        if let Err(err) = deletion::handler::handle_delete(
+            &ctx,
+            guild_id,
+            channel_id,
+            multiple_deleted_messages_ids.len(),
+        )
+        .await
+        .context("Failed to handle bulk message deletion")
+        {
+            report_guild_error(guild_id, err);
+        }
+    }
+
+    async fn reaction_add(&self, ctx: Context, add_reaction: Reaction) {
+        if let Err(err) = reaction::handler::handle_add(&ctx, &add_reaction)
+            .await
+            .context("Failed to handle reaction")
+        {
+            report_guild_error(add_reaction.guild_id, err);
+        }
+    }
+
+    async fn guild_scheduled_event_update(&self, ctx: Context, event: ScheduledEvent) {
+        if let Err(err) = scheduled_event::handler::handle_update(&ctx, &event)
+            .await
+            .context("Failed to handle scheduled event update")
+        {
+            report_guild_error(Some(event.guild_id), err);
+        }
+    }
+
+    async fn guild_scheduled_event_delete(&self, ctx: Context, event: ScheduledEvent) {
+        if let Err(err) = scheduled_event::handler::handle_delete(&ctx, &event)
+            .await
+            .context("Failed to handle scheduled event deletion")
         {
             report_error(err);
         }
@@ -79,12 +195,35 @@ impl EventHandler for Handler {
     async fn voice_state_update(
         &self,
         ctx: Context,
-        _old_voice_state: Option<VoiceState>,
+        old_voice_state: Option<VoiceState>,
         new_voice_state: VoiceState,
     ) {
-        if let Err(err) = voice_state::handler::handle_update(&ctx, new_voice_state.guild_id)
+        if let Err(err) = voice_state::handler::handle_self_disconnect(&ctx, &new_voice_state)
+            .await
+            .context("Failed to handle disconnection")
+        {
+            report_error(err);
+        }
+
+        if let Err(err) =
+            voice_state::handler::handle_self_move(&ctx, old_voice_state.as_ref(), &new_voice_state)
+                .await
+                .context("Failed to handle channel move")
+        {
+            report_error(err);
+        }
+
+        if let Err(err) =
+            voice_state::handler::handle_update(&ctx, old_voice_state.as_ref(), &new_voice_state)
+                .await
+                .context("Failed to handle voice state update")
+        {
+            report_error(err);
+        }
+
+        if let Err(err) = voice_state::handler::handle_auto_join(&ctx, &new_voice_state)
             .await
-            .context("Failed to handle voice state update")
+            .context("Failed to handle auto join")
         {
             report_error(err);
         }
@@ -0,0 +1,117 @@
+use crate::{
+    app_state, connection,
+    error::report_error,
+    messages::{self, Key},
+    notice,
+};
+use anyhow::{Context as _, Result};
+use koe_db::guild_settings::{self, OptionalIntKey};
+use log::info;
+use serenity::{client::Context, model::id::GuildId};
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+/// 読み上げが行われていないサーバーを確認する間隔
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+static WATCHER_STARTED: AtomicBool = AtomicBool::new(false);
+
+/// 一定時間読み上げが行われていないボイスチャンネルから退出するタスクを開始する
+/// `ready`イベントは再接続のたびに発生するため、2回目以降の呼び出しでは何もしない
+pub fn spawn_watcher(ctx: &Context) {
+    if WATCHER_STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    let ctx = ctx.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+
+            if let Err(err) = leave_idle_guilds(&ctx)
+                .await
+                .context("Failed to leave idle guilds")
+            {
+                report_error(err);
+            }
+        }
+    });
+}
+
+/// サーバーで有効な、読み上げが行われないまま経過すると退出する時間を返す
+/// `/settings idle-timeout`が未設定の場合はBot全体の設定に従う
+/// 自動で退出しない場合は[`None`]を返す
+pub async fn resolve_timeout(ctx: &Context, guild_id: GuildId) -> Result<Option<Duration>> {
+    let state = app_state::get(ctx).await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+
+    let minutes = guild_settings::get_optional_int(
+        &mut conn,
+        guild_settings::GetOptionalIntOption {
+            guild_id: guild_id.into(),
+            key: OptionalIntKey::IdleTimeout,
+        },
+    )
+    .await?;
+
+    Ok(match minutes {
+        Some(minutes) if minutes <= 0 => None,
+        Some(minutes) => Some(Duration::from_secs(minutes as u64 * 60)),
+        None => state.idle_timeout,
+    })
+}
+
+async fn leave_idle_guilds(ctx: &Context) -> Result<()> {
+    let state = app_state::get(ctx).await?;
+
+    let connected_guilds = state
+        .connected_guild_states
+        .iter()
+        .map(|entry| {
+            (
+                *entry.key(),
+                entry.last_activity.elapsed(),
+                entry.primary_text_channel(),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    for (guild_id, idle_for, text_channel_id) in connected_guilds {
+        let idle_timeout = match resolve_timeout(ctx, guild_id).await? {
+            Some(timeout) => timeout,
+            None => continue,
+        };
+        if idle_for < idle_timeout {
+            continue;
+        }
+
+        info!(
+            "Leaving voice channel in guild {} because nothing has been read for {} seconds (idle timeout: {} seconds)",
+            guild_id,
+            idle_for.as_secs(),
+            idle_timeout.as_secs()
+        );
+
+        connection::leave(ctx, guild_id).await?;
+        state.manual_leave_times.insert(guild_id, Instant::now());
+
+        let lang = messages::resolve(ctx, Some(guild_id), "").await;
+        notice::send(
+            ctx,
+            guild_id,
+            text_channel_id,
+            messages::format(
+                lang,
+                Key::IdleDisconnected,
+                &[("minutes", &(idle_timeout.as_secs() / 60))],
+            ),
+        )
+        .await
+        .context("Failed to send idle disconnect message")?;
+    }
+
+    Ok(())
+}
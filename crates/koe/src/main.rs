@@ -1,30 +1,73 @@
-use crate::error::report_error;
+use crate::{
+    autocomplete::cache::TtlCache,
+    command::responder::DiscordResponder,
+    error::report_error,
+    rate_limit::{CommandRateLimiter, COMMAND_RATE_LIMIT_WINDOW},
+    startup::StartupLimiter,
+};
 use anyhow::{Context, Result};
-use dashmap::DashMap;
-use koe_db::redis;
-use koe_speech::{speech::initialize_speakers, voicevox::VoicevoxClient};
-use log::info;
+use dashmap::{DashMap, DashSet};
+use koe_call::{NullBackend, SongbirdBackend, VoiceBackend};
+use koe_db::{guild_settings, redis};
+use koe_speech::{
+    azure::{AzureClient, AzureOption},
+    disk_cache::{DiskCache, DiskCacheOption},
+    fallback::{FallbackClient, FallbackOption},
+    open_jtalk::{OpenJTalkClient, OpenJTalkOption},
+    polly::{PollyClient, PollyOption},
+    provider::{ProviderKind, Providers, SpeechProvider},
+    speech::initialize_speakers,
+    voicevox::VoicevoxClient,
+};
+use log::{info, warn};
 use sentry::integrations::anyhow::capture_anyhow;
-use serenity::{model::gateway::GatewayIntents, Client};
-use songbird::SerenityInit;
+use serenity::{
+    model::{
+        gateway::GatewayIntents,
+        id::{ChannelId, GuildId},
+    },
+    Client,
+};
+use songbird::{SerenityInit, Songbird};
+use std::{sync::Arc, time::Instant};
+use time::{macros::format_description, UtcOffset};
 use tokio::time::Duration;
 
+mod alert;
+mod announcement;
 mod app_state;
+mod autocomplete;
+mod cache;
+mod channel;
 mod command;
 mod component_interaction;
+mod connection;
+mod default_voice;
+mod deletion;
 mod error;
 mod event_handler;
+mod idle;
 mod message;
+mod messages;
+mod notice;
+mod rate_limit;
+mod reaction;
 mod regex;
+mod scheduled_event;
+mod speech_provider;
+mod speech_queue;
+mod startup;
+#[cfg(test)]
+mod test_support;
+mod usage;
 mod voice_state;
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let _guard = sentry::init(());
 
-    run().await.map_err(|err| {
-        capture_anyhow(&err);
-        err
+    run().await.inspect_err(|err| {
+        capture_anyhow(err);
     })
 }
 
@@ -34,21 +77,194 @@ async fn run() -> Result<()> {
     let config = koe_config::load().await?;
     info!("Config loaded");
 
+    error::init_rate_limit(Duration::from_secs(config.logging.error_rate_limit_window));
+
+    let timezone = UtcOffset::parse(
+        &config.reading.timezone,
+        format_description!("[offset_hour sign:mandatory]:[offset_minute]"),
+    )
+    .with_context(|| format!("Invalid timezone: {}", config.reading.timezone))?;
+
+    // 実行ファイルや声のファイルが見つからない場合は、接続する前に設定の誤りとして終了する
+    // VOICEVOX、Open JTalk、Azure、Pollyの順に並べ、最初のエンジンをサーバーで選択されていない場合に使う
+    let mut voicevox_client = None;
+    let mut configured_providers: Vec<(ProviderKind, Arc<dyn SpeechProvider>)> = Vec::new();
+    if let Some(voicevox) = config.voicevox {
+        voicevox_client = Some(VoicevoxClient::new(voicevox.api_base.clone()));
+        configured_providers.push((
+            ProviderKind::Voicevox,
+            Arc::new(FallbackClient::new(FallbackOption {
+                api_bases: std::iter::once(voicevox.api_base)
+                    .chain(voicevox.fallback_api_bases)
+                    .collect(),
+                cooldown: Duration::from_secs(voicevox.fallback_cooldown),
+                max_concurrency: match voicevox.max_concurrency {
+                    0 => None,
+                    n => Some(n),
+                },
+                max_audio_size: match voicevox.max_audio_size {
+                    0 => None,
+                    kib => Some(kib * 1024),
+                },
+            })),
+        ));
+    }
+    if let Some(open_jtalk) = config.open_jtalk {
+        let client = OpenJTalkClient::new(OpenJTalkOption {
+            binary: open_jtalk.binary.into(),
+            dictionary_dir: open_jtalk.dictionary_dir.into(),
+            voice_dir: open_jtalk.voice_dir.into(),
+        })
+        .context("Invalid open_jtalk config")?;
+        configured_providers.push((ProviderKind::OpenJTalk, Arc::new(client)));
+    }
+    if let Some(azure) = config.azure {
+        let client = AzureClient::new(AzureOption {
+            subscription_key: azure.subscription_key,
+            region: azure.region,
+            locale: azure.locale,
+            voices: azure.voices,
+        });
+        // サブスクリプションキーや声の名前の誤りを、接続する前に検出する
+        client.voices().await.context("Invalid azure config")?;
+        configured_providers.push((ProviderKind::Azure, Arc::new(client)));
+    }
+    if let Some(polly) = config.polly {
+        let client = PollyClient::new(PollyOption {
+            region: polly.region,
+            access_key_id: polly.access_key_id,
+            secret_access_key: polly.secret_access_key,
+            session_token: polly.session_token,
+        })
+        .await
+        .context("Invalid polly config")?;
+        configured_providers.push((ProviderKind::Polly, Arc::new(client)));
+    }
+
+    // 別のエンジンの音声を使わないよう、エンジンの名前をキャッシュのキーに含める
+    // 保存先のディレクトリと上限は、すべてのエンジンで共有する
+    let mut disk_cache: Option<Arc<DiskCache>> = None;
+    let mut speech_providers: Option<Providers> = None;
+    for (kind, provider) in configured_providers {
+        let provider: Arc<dyn SpeechProvider> = match (&config.audio_cache.dir, &disk_cache) {
+            (None, _) => provider,
+            (Some(_), Some(cache)) => Arc::new(cache.share(provider, kind.as_str().to_string())),
+            (Some(dir), None) => {
+                let cache = Arc::new(
+                    DiskCache::open(
+                        provider,
+                        DiskCacheOption {
+                            dir: dir.into(),
+                            max_size: config.audio_cache.max_size * 1024 * 1024,
+                            namespace: kind.as_str().to_string(),
+                        },
+                    )
+                    .await
+                    .context("Invalid audio_cache config")?,
+                );
+                disk_cache = Some(cache.clone());
+                cache
+            }
+        };
+        match &mut speech_providers {
+            Some(providers) => providers.add(kind, provider),
+            None => speech_providers = Some(Providers::new(kind, provider)),
+        }
+    }
+    let speech_providers = speech_providers
+        .context("At least one of voicevox, open_jtalk, azure and polly must be configured")?;
+
     let intents = GatewayIntents::non_privileged() | GatewayIntents::MESSAGE_CONTENT;
 
+    let songbird = Songbird::serenity();
+
+    // 開発用: VOICEVOX ENGINEやボイスチャンネルへの接続なしで、読み上げる文章を確かめられるようにする
+    let print_speech = std::env::var("KOE_PRINT_SPEECH").is_ok_and(|value| value == "1");
+    let voice_backend: Arc<dyn VoiceBackend> = if print_speech {
+        warn!("KOE_PRINT_SPEECH is set: speech will be printed to the log instead of played");
+        Arc::new(NullBackend::new())
+    } else {
+        Arc::new(SongbirdBackend::new(songbird.clone()))
+    };
+
     let mut client = Client::builder(config.discord.bot_token, intents)
         .event_handler(event_handler::Handler)
         .application_id(config.discord.client_id)
-        .register_songbird()
+        .register_songbird_with(songbird.clone())
         .await
         .context("Failed to build serenity client")?;
 
+    let application_info = client
+        .cache_and_http
+        .http
+        .get_current_application_info()
+        .await
+        .context("Failed to get application info")?;
+    let owner_ids = match application_info.team {
+        Some(team) => team.members.into_iter().map(|m| m.user.id).collect(),
+        None => vec![application_info.owner.id],
+    };
+
+    let redis_client = redis::Client::open(config.redis.url)?;
+    {
+        let mut conn = redis_client
+            .get_async_connection()
+            .await
+            .context("Failed to connect to Redis")?;
+        let migrated = guild_settings::migrate_legacy_keys(&mut conn)
+            .await
+            .context("Failed to migrate guild settings")?;
+        if migrated > 0 {
+            info!("Migrated {} guild settings", migrated);
+        }
+    }
+
     app_state::initialize(
         &client,
         app_state::AppState {
-            redis_client: redis::Client::open(config.redis.url)?,
-            voicevox_client: VoicevoxClient::new(config.voicevox.api_base),
+            redis_client,
+            voicevox_client,
+            speech_providers,
+            voice_backend,
+            responder: Arc::new(DiscordResponder),
             connected_guild_states: DashMap::new(),
+            manual_leave_times: DashMap::new(),
+            timezone,
+            max_speech_duration: match config.reading.max_speech_duration {
+                0 => None,
+                secs => Some(Duration::from_secs(secs)),
+            },
+            owner_ids,
+            dev_guild_id: config.discord.dev_guild_id.map(GuildId),
+            system_voice_preset_id: config.reading.system_voice_preset_id,
+            dict_cache: Default::default(),
+            user_dict_cache: Default::default(),
+            settings_cache: Default::default(),
+            preset_cache: TtlCache::new(Duration::from_secs(60)),
+            self_deaf: config.call.self_deaf,
+            max_connections: match config.call.max_connections {
+                0 => None,
+                max => Some(max),
+            },
+            max_reconnect_attempts: config.call.max_reconnect_attempts,
+            alert_channel_id: config.alert.channel_id.map(ChannelId),
+            alert_webhook_url: config.alert.webhook_url,
+            skip_counter: Default::default(),
+            recovery_attempts_total: Default::default(),
+            startup_limiter: StartupLimiter::new(
+                config.startup.concurrency,
+                Duration::from_millis(config.startup.jitter),
+                config.startup.max_retries,
+            ),
+            command_rate_limiter: CommandRateLimiter::new(COMMAND_RATE_LIMIT_WINDOW),
+            pending_confirmations: DashSet::new(),
+            active_scheduled_events: DashSet::new(),
+            print_speech,
+            started_at: Instant::now(),
+            idle_timeout: match config.reading.idle_timeout {
+                0 => None,
+                mins => Some(Duration::from_secs(mins * 60)),
+            },
         },
     )
     .await;
@@ -62,8 +278,10 @@ async fn run() -> Result<()> {
             let data = d.read().await;
             let state = data.get::<app_state::AppState>().unwrap();
 
-            if let Err(err) = initialize_speakers(&state.voicevox_client).await {
-                report_error(err);
+            if let Some(voicevox_client) = &state.voicevox_client {
+                if let Err(err) = initialize_speakers(voicevox_client).await {
+                    report_error(err);
+                }
             }
         });
     }
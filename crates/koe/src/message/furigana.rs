@@ -0,0 +1,61 @@
+use crate::regex::{furigana_braces_regex, furigana_ruby_regex};
+use koe_db::furigana::FuriganaSyntax;
+use regex::Captures;
+
+/// 読み仮名を指定した部分を一時的に置き換える文字の範囲（Unicodeの私用領域）
+const PLACEHOLDER_START: u32 = 0xE000;
+const PLACEHOLDER_END: u32 = 0xF8FF;
+
+/// 読み仮名を指定した部分を、辞書で置き換えられないよう1文字の目印に置き換える
+/// 置き換えた部分の読み仮名を、目印の順に返す
+/// 書き方に合わない部分はそのまま残し、書かれたとおりに読み上げる
+pub fn protect(text: &str, syntax: FuriganaSyntax) -> (String, Vec<String>) {
+    // 目印と区別できないため、私用領域の文字を含むメッセージでは読み仮名を扱わない
+    if text.chars().any(is_placeholder) {
+        return (text.to_string(), Vec::new());
+    }
+
+    let regex = match syntax {
+        FuriganaSyntax::Braces => furigana_braces_regex(),
+        FuriganaSyntax::Ruby => furigana_ruby_regex(),
+    };
+    let max_readings = (PLACEHOLDER_END - PLACEHOLDER_START + 1) as usize;
+
+    let mut readings = Vec::new();
+    let text = regex.replace_all(text, |caps: &Captures| {
+        let reading = caps["reading"].trim();
+        if reading.is_empty() || readings.len() >= max_readings {
+            return caps[0].to_string();
+        }
+
+        let placeholder = char::from_u32(PLACEHOLDER_START + readings.len() as u32).unwrap();
+        readings.push(reading.to_string());
+        placeholder.to_string()
+    });
+
+    (text.into_owned(), readings)
+}
+
+/// [`protect`]で置き換えた目印を、読み仮名に戻す
+pub fn restore(text: &str, readings: &[String]) -> String {
+    if readings.is_empty() {
+        return text.to_string();
+    }
+
+    text.chars()
+        .map(
+            |c| match placeholder_index(c).and_then(|i| readings.get(i)) {
+                Some(reading) => reading.clone(),
+                None => c.to_string(),
+            },
+        )
+        .collect()
+}
+
+fn is_placeholder(c: char) -> bool {
+    (PLACEHOLDER_START..=PLACEHOLDER_END).contains(&(c as u32))
+}
+
+fn placeholder_index(c: char) -> Option<usize> {
+    is_placeholder(c).then(|| (c as u32 - PLACEHOLDER_START) as usize)
+}
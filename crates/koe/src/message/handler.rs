@@ -1,42 +1,120 @@
-use super::read::build_read_text;
-use crate::app_state;
-use anyhow::{anyhow, Context as _, Result};
-use koe_db::voice::GetOption;
-use koe_speech::speech::{list_preset_ids, make_speech, SpeechRequest};
+use super::{read::build_read_text, skip::SkipReason};
+use crate::{
+    app_state::{self, AppState},
+    speech_queue::{self, sequencer::Ticket, Voice},
+};
+use anyhow::Result;
+use koe_db::guild_settings::BoolKey;
 use log::trace;
-use rand::seq::SliceRandom;
-use serenity::{client::Context, model::channel::Message};
+use serenity::{
+    client::Context,
+    model::{channel::Message, id::GuildId},
+};
+use std::time::Instant;
 
-pub async fn handle(ctx: &Context, msg: Message) -> Result<()> {
+pub async fn handle(ctx: &Context, mut msg: Message) -> Result<()> {
     let guild_id = match msg.guild_id {
         Some(id) => id,
         None => return Ok(()),
     };
 
-    if !koe_call::is_connected(ctx, guild_id).await? {
+    let state = app_state::get(ctx).await?;
+
+    if !state.voice_backend.is_connected(guild_id.into()).await? {
         return Ok(());
     }
 
-    let state = app_state::get(ctx).await?;
-    let mut guild_state = match state.connected_guild_states.get_mut(&guild_id) {
-        Some(status) => status,
+    let (bound, in_voice_chat) = match state.connected_guild_states.get(&guild_id) {
+        Some(guild_state) => (
+            guild_state.is_bound(msg.channel_id),
+            guild_state.voice_channel == msg.channel_id,
+        ),
         None => return Ok(()),
     };
-
-    if guild_state.bound_text_channel != msg.channel_id {
+    // 読み上げ対象のチャンネルであれば、ボイスチャンネルのチャットを兼ねていても1回だけ読み上げる
+    let readable = bound || (in_voice_chat && reads_voice_chat(&state, guild_id).await?);
+    if !readable {
         return Ok(());
     }
 
+    // 音声合成にかかる時間に関わらず届いた順に読み上げるよう、受け付けた時点で順番を確定する
+    let ticket = match state.connected_guild_states.get(&guild_id) {
+        Some(guild_state) => guild_state.speech_sequencer.issue(),
+        None => return Ok(()),
+    };
+
     // Skip message from Koe itself
     if msg.author.id == ctx.cache.current_user_id() {
+        state.skip_counter.record(&msg, SkipReason::OwnMessage);
         return Ok(());
     }
 
-    // Skip message that starts with semicolon
-    if msg.content.starts_with(';') {
-        return Ok(());
+    let mut conn = state.redis_client.get_async_connection().await?;
+    let read_prefix = koe_db::read_prefix::get(
+        &mut conn,
+        koe_db::read_prefix::GetOption {
+            guild_id: guild_id.into(),
+        },
+    )
+    .await?;
+
+    match read_prefix {
+        // 合図が設定されている場合は、セミコロンに関わらず合図で始まるメッセージのみを読み上げる
+        Some(prefix) => match msg.content.strip_prefix(prefix.as_str()) {
+            Some(rest) => msg.content = rest.trim_start().to_string(),
+            None => {
+                state
+                    .skip_counter
+                    .record(&msg, SkipReason::MissingReadPrefix);
+                return Ok(());
+            }
+        },
+        // Skip message that starts with semicolon
+        None => {
+            if msg.content.starts_with(';') {
+                state.skip_counter.record(&msg, SkipReason::SemicolonPrefix);
+                return Ok(());
+            }
+        }
+    }
+
+    if let Some(reason) = speak(ctx, msg.clone(), ticket).await? {
+        state.skip_counter.record(&msg, reason);
     }
 
+    Ok(())
+}
+
+/// 接続中のボイスチャンネルのテキストチャットも読み上げる設定かどうかを返す
+async fn reads_voice_chat(state: &AppState, guild_id: GuildId) -> Result<bool> {
+    let mut conn = state.redis_client.get_async_connection().await?;
+    let settings = state
+        .settings_cache
+        .get_or_fetch(&mut conn, guild_id)
+        .await?;
+    Ok(settings.get_bool(BoolKey::ReadVoiceChat))
+}
+
+/// 読み上げ対象のチャンネルかどうかに関わらず、メッセージを読み上げるキューに追加する
+/// `ticket`の順番が来るまで、キューへの追加を待つ
+/// 読み上げなかった場合は、何もせずにその理由を返す
+pub async fn speak(ctx: &Context, msg: Message, ticket: Ticket) -> Result<Option<SkipReason>> {
+    let guild_id = match msg.guild_id {
+        Some(id) => id,
+        None => return Ok(Some(SkipReason::EmptyText)),
+    };
+
+    let state = app_state::get(ctx).await?;
+
+    // 音声合成を待つ間も続くメッセージで名前を省略するか判断できるよう、先に直前のメッセージを更新する
+    let (last_message_read, volume_gain) = match state.connected_guild_states.get_mut(&guild_id) {
+        Some(mut guild_state) => (
+            guild_state.last_message_read.replace(msg.clone()),
+            guild_state.volume_boosts.get(&msg.author.id).copied(),
+        ),
+        None => return Ok(Some(SkipReason::EmptyText)),
+    };
+
     let mut conn = state.redis_client.get_async_connection().await?;
 
     let text = build_read_text(
@@ -44,40 +122,68 @@ pub async fn handle(ctx: &Context, msg: Message) -> Result<()> {
         &mut conn,
         guild_id,
         &msg,
-        &guild_state.last_message_read,
+        &last_message_read,
+        state.timezone,
     )
     .await?;
     trace!("Built text: {:?}", &text);
 
-    if text.is_empty() {
-        trace!("Text is empty");
-        return Ok(());
-    }
+    let text = match text {
+        Ok(text) if !text.trim().is_empty() => text,
+        Ok(_) => {
+            restore_last_message_read(&state, guild_id, &msg, last_message_read);
+            return Ok(Some(SkipReason::EmptyText));
+        }
+        Err(reason) => {
+            restore_last_message_read(&state, guild_id, &msg, last_message_read);
+            return Ok(Some(reason));
+        }
+    };
 
-    let available_preset_ids = list_preset_ids(&state.voicevox_client).await?;
-    let fallback_preset_id = available_preset_ids
-        .choose(&mut rand::thread_rng())
-        .ok_or_else(|| anyhow!("No presets available"))?
-        .into();
-    let preset_id = koe_db::voice::get(
+    // 本文には`build_read_text`で辞書とフィルターを適用済みのため、そのまま読み上げる
+    let result = speech_queue::push(
+        ctx,
         &mut conn,
-        GetOption {
-            guild_id: guild_id.into(),
-            user_id: msg.author.id.into(),
-            fallback: fallback_preset_id,
-        },
+        guild_id,
+        text,
+        Voice::Member(msg.author.id),
+        volume_gain,
+        &ticket,
     )
-    .await?
-    .into();
-
-    let encoded_audio = make_speech(&state.voicevox_client, SpeechRequest { text, preset_id })
-        .await
-        .context("Failed to execute Text-to-Speech")?;
-    let raw_audio = encoded_audio.decode().await?.into();
+    .await;
+    match result {
+        Ok(true) => {}
+        Ok(false) => {
+            restore_last_message_read(&state, guild_id, &msg, last_message_read);
+            return Ok(Some(SkipReason::UsageLimit));
+        }
+        Err(err) => {
+            if let Some(mut guild_state) = state.connected_guild_states.get_mut(&guild_id) {
+                guild_state.last_speech_error = Some(format!("{:#}", err));
+            }
+            restore_last_message_read(&state, guild_id, &msg, last_message_read);
+            return Err(err);
+        }
+    }
 
-    koe_call::enqueue(ctx, guild_id, raw_audio).await?;
+    if let Some(mut guild_state) = state.connected_guild_states.get_mut(&guild_id) {
+        guild_state.last_activity = Instant::now();
+    }
 
-    guild_state.last_message_read = Some(msg);
+    Ok(None)
+}
 
-    Ok(())
+/// 読み上げなかったメッセージを、直前に読み上げたメッセージとして扱わないよう元に戻す
+/// すでに後続のメッセージで更新されている場合はそのままにする
+fn restore_last_message_read(
+    state: &AppState,
+    guild_id: GuildId,
+    msg: &Message,
+    previous: Option<Message>,
+) {
+    if let Some(mut guild_state) = state.connected_guild_states.get_mut(&guild_id) {
+        if guild_state.last_message_read.as_ref().map(|m| m.id) == Some(msg.id) {
+            guild_state.last_message_read = previous;
+        }
+    }
 }
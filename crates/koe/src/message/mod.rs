@@ -1,2 +1,7 @@
+mod furigana;
 pub mod handler;
-mod read;
+pub mod read;
+mod repeat;
+pub mod skip;
+mod timestamp;
+mod width;
@@ -1,48 +1,272 @@
-use crate::regex::{custom_emoji_regex, url_regex};
+use super::{
+    furigana, repeat::collapse_repeats, skip::SkipReason, timestamp::replace_timestamps,
+    width::fold_width,
+};
+use crate::{
+    app_state,
+    regex::{custom_emoji_regex, mass_mention_regex, mention_only_regex, url_regex},
+};
 use aho_corasick::{AhoCorasickBuilder, MatchKind};
 use anyhow::Result;
 use discord_md::generate::{ToMarkdownString, ToMarkdownStringOption};
-use koe_db::{dict::GetAllOption, redis};
+use koe_db::{
+    embed_bot, empty_text,
+    filter::{self, FilterMode},
+    furigana::FuriganaSyntax,
+    guild_settings::{
+        BoolKey, ChoiceKey, GuildSettings, IntKey, TextKey, CONTENT_PLACEHOLDER, NAME_PLACEHOLDER,
+    },
+    mention_only::{self, MentionOnlyMode},
+    redis,
+};
+use log::warn;
 use serenity::{
     client::Context,
-    model::{channel::Message, id::GuildId},
+    http::{request::RequestBuilder, routing::RouteInfo},
+    model::{
+        channel::Message,
+        id::{GuildId, UserId},
+    },
     utils::ContentSafeOptions,
 };
+use std::collections::HashSet;
+use time::UtcOffset;
 
+/// 1件のメッセージで読み上げる最大の文字数
+const MAX_READ_LENGTH: usize = 60;
+/// 文字数や単語数の上限を超えて省略したことを示す文言
+const TRUNCATION_MARKER: &str = "、以下略";
+/// 読み上げる代替テキストの最大文字数、これを超える部分は読み上げない
+const MAX_ALT_TEXT_LENGTH: usize = 30;
+/// 読み上げる投票の選択肢の最大数、これを超える選択肢は読み上げない
+const MAX_POLL_ANSWERS: usize = 5;
+/// メンションだけのメッセージの代わりに読み上げる文言
+const MENTION_ONLY_NOTE: &str = "メンション";
+
+/// メッセージから読み上げる文章を組み立てる
+/// 読み上げる内容が残らなかった場合は、その理由を`Err`で返す
 pub async fn build_read_text(
     ctx: &Context,
     conn: &mut redis::aio::Connection,
     guild_id: GuildId,
     msg: &Message,
     last_msg: &Option<Message>,
-) -> Result<String> {
+    timezone: UtcOffset,
+) -> Result<Result<String, SkipReason>> {
     let author_name = build_author_name(ctx, msg).await;
+    let state = app_state::get(ctx).await?;
+    let settings = state.settings_cache.get_or_fetch(conn, guild_id).await?;
 
-    let content = plain_content(ctx, msg);
+    let mut content = if is_mention_only(msg) {
+        let mode = mention_only::get_mode(
+            conn,
+            mention_only::GetModeOption {
+                guild_id: guild_id.into(),
+            },
+        )
+        .await?;
+        match mode {
+            MentionOnlyMode::Read => plain_content(ctx, msg, &msg.content),
+            MentionOnlyMode::Skip => return Ok(Err(SkipReason::EmptyText)),
+            MentionOnlyMode::Note => MENTION_ONLY_NOTE.to_string(),
+        }
+    } else {
+        plain_content(ctx, msg, &msg.content)
+    };
+    if let Some(embed_text) = read_embeds(ctx, conn, guild_id, msg).await? {
+        content = if content.is_empty() {
+            embed_text
+        } else {
+            format!("{}。{}", content, embed_text)
+        };
+    }
+    if let Some(alt_text) = read_alt_texts(ctx, &settings, msg).await? {
+        content = if content.is_empty() {
+            alt_text
+        } else {
+            format!("{}。{}", content, alt_text)
+        };
+    }
+    if let Some(poll_text) = read_poll(ctx, &settings, msg).await? {
+        content = poll_text;
+    }
     let content = replace_custom_emojis(&content);
+    let content = replace_timestamps(&content, timezone);
     let content = discord_md::parse(&content).to_markdown_string(
         &ToMarkdownStringOption::new()
             .omit_format(true)
             .omit_spoiler(true),
     );
     let content = remove_url(&content);
+    // 指定された読み仮名を辞書より優先するよう、辞書による置換の間は目印に置き換えておく
+    let (content, readings) = match get_furigana_syntax(&settings) {
+        Some(syntax) => furigana::protect(&content, syntax),
+        None => (content, Vec::new()),
+    };
+    let content = replace_words(
+        ctx,
+        conn,
+        guild_id,
+        &settings,
+        Some(msg.author.id),
+        &content,
+    )
+    .await?;
+    let content = furigana::restore(&content, &readings);
+    // 辞書の読み方を経由してフィルターをすり抜けられないよう、辞書による置換の後に適用する
+    let filtered = apply_filter(conn, guild_id, &content).await?;
+
+    // すべての変換を終えて読み上げる内容が残っていなければ、設定に応じて読み飛ばすか代わりの文言を読み上げる
+    let content = if filtered.trim().is_empty() {
+        match get_empty_text_placeholder(conn, guild_id).await? {
+            Some(placeholder) => placeholder,
+            None if content.trim().is_empty() => return Ok(Err(SkipReason::EmptyText)),
+            None => return Ok(Err(SkipReason::Filtered)),
+        }
+    } else {
+        filtered
+    };
 
     let text = if should_read_author_name(msg, last_msg) {
-        format!("{}。{}", author_name, content)
+        let author_name = match read_author_role(ctx, &settings, guild_id, msg) {
+            Some(role_name) => format!("{}、{}", role_name, author_name),
+            None => author_name,
+        };
+        frame_message(ctx, conn, guild_id, &settings, msg, &author_name, &content).await?
     } else {
         content
     };
 
-    let text = replace_words_on_dict(conn, guild_id, &text).await?;
+    let max_words = settings.get_int(IntKey::MaxWords);
+    let max_words = usize::try_from(max_words).ok().filter(|&max| max > 0);
 
-    // 文字数を60文字に制限
-    if text.chars().count() > 60 {
-        Ok(text.chars().take(60 - 4).collect::<String>() + "、以下略")
+    Ok(Ok(truncate(&text, max_words)))
+}
+
+/// サーバーで設定された読み上げ方に、送信者の名前とメッセージの内容を埋め込む
+/// 内容にはすでに辞書を適用しているため、重ねて置換しないよう、内容を除いた部分にのみ辞書とフィルターを適用する
+async fn frame_message(
+    ctx: &Context,
+    conn: &mut redis::aio::Connection,
+    guild_id: GuildId,
+    settings: &GuildSettings,
+    msg: &Message,
+    author_name: &str,
+    content: &str,
+) -> Result<String> {
+    let template = settings.get_text(TextKey::MessageTemplate);
+    // 設定時に検証しているが、念のため含まれていない場合は既定の読み上げ方を使う
+    let (before, after) = template
+        .split_once(CONTENT_PLACEHOLDER)
+        .or_else(|| {
+            TextKey::MessageTemplate
+                .default_value()
+                .split_once(CONTENT_PLACEHOLDER)
+        })
+        .unwrap_or((NAME_PLACEHOLDER, ""));
+
+    let mut parts = Vec::with_capacity(2);
+    for part in [before, after] {
+        let part = part.replace(NAME_PLACEHOLDER, author_name);
+        let part = if part.trim().is_empty() {
+            part
+        } else {
+            let part =
+                replace_words(ctx, conn, guild_id, settings, Some(msg.author.id), &part).await?;
+            apply_filter(conn, guild_id, &part).await?
+        };
+        parts.push(part);
+    }
+
+    Ok(format!("{}{}{}", parts[0], content, parts[1]))
+}
+
+/// 文字数と、設定されている場合は単語数を制限する
+/// どちらかの上限を超えた場合は、先に上限に達した位置で切り詰めて省略したことを示す文言を付け加える
+fn truncate(text: &str, max_words: Option<usize>) -> String {
+    let char_limit = if text.chars().count() > MAX_READ_LENGTH {
+        // 省略したことを示す文言を含めて上限に収まるようにする
+        let keep = MAX_READ_LENGTH - TRUNCATION_MARKER.chars().count();
+        text.char_indices().nth(keep).map(|(i, _)| i)
     } else {
-        Ok(text)
+        None
+    };
+    let word_limit = max_words.and_then(|max| word_limit_index(text, max));
+
+    match [char_limit, word_limit].into_iter().flatten().min() {
+        Some(end) => text[..end].trim_end().to_string() + TRUNCATION_MARKER,
+        None => text.to_string(),
     }
 }
 
+/// `max_words`個を超える単語を含む場合に、最初にはみ出す単語の開始位置を返す
+/// 空白で区切られたものを1語とし、漢字・仮名・ハングルは1文字を1語として数える
+fn word_limit_index(text: &str, max_words: usize) -> Option<usize> {
+    let mut count = 0;
+    let mut in_word = false;
+
+    for (i, c) in text.char_indices() {
+        if c.is_whitespace() || is_cjk_punctuation(c) {
+            in_word = false;
+            continue;
+        }
+
+        let cjk = is_cjk(c);
+        if cjk || !in_word {
+            count += 1;
+            if count > max_words {
+                return Some(i);
+            }
+        }
+        in_word = !cjk;
+    }
+
+    None
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(
+        c,
+        '\u{3040}'..='\u{30ff}'
+            | '\u{3400}'..='\u{4dbf}'
+            | '\u{4e00}'..='\u{9fff}'
+            | '\u{f900}'..='\u{faff}'
+            | '\u{ff66}'..='\u{ff9f}'
+            | '\u{ac00}'..='\u{d7af}'
+    )
+}
+
+/// 句読点や括弧など、単語の区切りとして扱う全角の記号
+fn is_cjk_punctuation(c: char) -> bool {
+    matches!(c, '\u{3000}'..='\u{303f}' | '\u{ff01}'..='\u{ff0f}' | '\u{ff1a}'..='\u{ff20}')
+}
+
+/// メッセージ以外から読み上げる文章に、メッセージと同じ辞書とフィルターを適用する
+/// `author_id`を指定した場合は、そのユーザーの辞書も使う
+pub async fn preprocess_text(
+    ctx: &Context,
+    conn: &mut redis::aio::Connection,
+    guild_id: GuildId,
+    author_id: Option<UserId>,
+    text: &str,
+) -> Result<String> {
+    let state = app_state::get(ctx).await?;
+    let settings = state.settings_cache.get_or_fetch(conn, guild_id).await?;
+
+    let text = replace_custom_emojis(text);
+    let text = remove_url(&text);
+    let text = replace_words(ctx, conn, guild_id, &settings, author_id, &text).await?;
+    apply_filter(conn, guild_id, &text).await
+}
+
+/// メッセージがメンションだけで構成されているかを判定する
+/// 添付ファイルや埋め込みがある場合は、メンションだけとはみなさない
+fn is_mention_only(msg: &Message) -> bool {
+    msg.attachments.is_empty()
+        && msg.embeds.is_empty()
+        && mention_only_regex().is_match(&msg.content)
+}
+
 fn should_read_author_name(msg: &Message, last_msg: &Option<Message>) -> bool {
     let last_msg = match last_msg {
         Some(msg) => msg,
@@ -61,8 +285,37 @@ async fn build_author_name(ctx: &Context, msg: &Message) -> String {
         .unwrap_or_else(|| msg.author.name.clone())
 }
 
-/// [Message]の内容を返す。ID表記されたメンションやチャンネル名は読める形に書き換える。
-fn plain_content(ctx: &Context, msg: &Message) -> String {
+/// 送信者のロールを読み上げる設定の場合は、送信者のロールのうち、メンバー一覧で分けて表示されるものの中で最も上位のロールの名前を返す
+/// そのようなロールがない場合は[`None`]を返す
+fn read_author_role(
+    ctx: &Context,
+    settings: &GuildSettings,
+    guild_id: GuildId,
+    msg: &Message,
+) -> Option<String> {
+    if !settings.get_bool(BoolKey::ReadAuthorRole) {
+        return None;
+    }
+
+    let role_ids = match &msg.member {
+        Some(member) => member.roles.clone(),
+        None => match ctx.cache.member(guild_id, msg.author.id) {
+            Some(member) => member.roles,
+            None => return None,
+        },
+    };
+
+    role_ids
+        .into_iter()
+        .filter_map(|role_id| ctx.cache.role(guild_id, role_id))
+        .filter(|role| role.hoist)
+        .max_by_key(|role| role.position)
+        .map(|role| role.name)
+}
+
+/// [Message]に含まれる`text`を返す。ID表記されたメンションやチャンネル名は読める形に書き換える。
+/// `@everyone`と`@here`は読み上げない。
+fn plain_content(ctx: &Context, msg: &Message, text: &str) -> String {
     let mut options = ContentSafeOptions::new()
         .clean_channel(true)
         .clean_role(true)
@@ -75,7 +328,173 @@ fn plain_content(ctx: &Context, msg: &Message) -> String {
         options = options.display_as_member_from(guild_id);
     }
 
-    serenity::utils::content_safe(&ctx.cache, &msg.content, &options, &msg.mentions)
+    let text = mass_mention_regex().replace_all(text, "");
+    serenity::utils::content_safe(&ctx.cache, text, &options, &msg.mentions)
+}
+
+/// 埋め込みを読み上げるよう設定されたBotのメッセージであれば、埋め込みのタイトルと説明を返す
+async fn read_embeds(
+    ctx: &Context,
+    conn: &mut redis::aio::Connection,
+    guild_id: GuildId,
+    msg: &Message,
+) -> Result<Option<String>> {
+    if !msg.author.bot || msg.embeds.is_empty() {
+        return Ok(None);
+    }
+
+    let allowed = embed_bot::contains(
+        conn,
+        embed_bot::ContainsOption {
+            guild_id: guild_id.into(),
+            user_id: msg.author.id.into(),
+        },
+    )
+    .await?;
+    if !allowed {
+        return Ok(None);
+    }
+
+    let text = msg
+        .embeds
+        .iter()
+        .flat_map(|embed| [&embed.title, &embed.description])
+        .flatten()
+        .map(|text| plain_content(ctx, msg, text))
+        .collect::<Vec<_>>()
+        .join("。");
+
+    Ok(Some(text))
+}
+
+/// 代替テキストを読み上げる設定の場合は、添付ファイルに設定された代替テキストを返す
+/// 代替テキストが設定された添付ファイルがない場合は[`None`]を返す
+async fn read_alt_texts(
+    ctx: &Context,
+    settings: &GuildSettings,
+    msg: &Message,
+) -> Result<Option<String>> {
+    // 代替テキストを設定できるのは画像と動画のみのため、それ以外の添付ファイルではメッセージを取得し直さない
+    let has_media = msg.attachments.iter().any(|attachment| {
+        attachment
+            .content_type
+            .as_deref()
+            .is_some_and(|x| x.starts_with("image/") || x.starts_with("video/"))
+    });
+    if !has_media || !settings.get_bool(BoolKey::ReadAltText) {
+        return Ok(None);
+    }
+
+    // FIXME: Gatewayから受け取るメッセージには代替テキスト（`description`）が含まれているが、現在のSerenityの`Attachment`はこれを保持しない。
+    // そのため、代替テキストを設定できる添付ファイルがある場合に限ってメッセージを取得し直している。Serenityを更新したら`Attachment::description`を使う。
+    // 取得に失敗した場合でも、本文は読み上げられるようにする
+    let raw_message = match ctx
+        .http
+        .fire::<serde_json::Value>(
+            RequestBuilder::new(RouteInfo::GetMessage {
+                channel_id: msg.channel_id.into(),
+                message_id: msg.id.into(),
+            })
+            .build(),
+        )
+        .await
+    {
+        Ok(raw_message) => raw_message,
+        Err(err) => {
+            warn!("Failed to fetch alt text of message {}: {:?}", msg.id, err);
+            return Ok(None);
+        }
+    };
+
+    Ok(join_alt_texts(&raw_message["attachments"]))
+}
+
+/// 投票を読み上げる設定の場合は、投票の質問と選択肢を返す
+/// 投票でないメッセージや、取得に失敗した場合は[`None`]を返す
+async fn read_poll(
+    ctx: &Context,
+    settings: &GuildSettings,
+    msg: &Message,
+) -> Result<Option<String>> {
+    // 投票は本文・添付ファイル・埋め込み・スタンプのないメッセージとして届くため、それ以外のメッセージは取得し直さない
+    let may_be_poll = msg.content.is_empty()
+        && msg.attachments.is_empty()
+        && msg.embeds.is_empty()
+        && msg.sticker_items.is_empty();
+    if !may_be_poll || !settings.get_bool(BoolKey::ReadPolls) {
+        return Ok(None);
+    }
+
+    // FIXME: 現在のSerenityの`Message`は投票（`poll`）を保持しないため、メッセージを取得し直している。
+    // Serenityを更新したら`Message::poll`を使う。
+    let raw_message = match ctx
+        .http
+        .fire::<serde_json::Value>(
+            RequestBuilder::new(RouteInfo::GetMessage {
+                channel_id: msg.channel_id.into(),
+                message_id: msg.id.into(),
+            })
+            .build(),
+        )
+        .await
+    {
+        Ok(raw_message) => raw_message,
+        Err(err) => {
+            warn!("Failed to fetch poll of message {}: {:?}", msg.id, err);
+            return Ok(None);
+        }
+    };
+
+    Ok(format_poll(&raw_message["poll"]))
+}
+
+/// 投票の質問と選択肢を、読み上げる文章にする
+/// 選択肢が多すぎる場合は、[`MAX_POLL_ANSWERS`]個までを読み上げる
+fn format_poll(poll: &serde_json::Value) -> Option<String> {
+    let question = poll["question"]["text"].as_str()?.trim();
+    let answers = poll["answers"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|answer| answer["poll_media"]["text"].as_str())
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>();
+
+    let mut text = format!("投票: {}", question);
+    if !answers.is_empty() {
+        text.push_str(" 選択肢、");
+        text.push_str(&answers[..answers.len().min(MAX_POLL_ANSWERS)].join("、"));
+        if answers.len() > MAX_POLL_ANSWERS {
+            text.push_str(TRUNCATION_MARKER);
+        }
+    }
+    Some(text)
+}
+
+/// 添付ファイルの一覧から、空でない代替テキストを長すぎる部分を省略してつなげる
+fn join_alt_texts(attachments: &serde_json::Value) -> Option<String> {
+    let texts = attachments
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|attachment| attachment["description"].as_str())
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+        .map(|text| {
+            if text.chars().count() > MAX_ALT_TEXT_LENGTH {
+                text.chars().take(MAX_ALT_TEXT_LENGTH).collect::<String>() + TRUNCATION_MARKER
+            } else {
+                text.to_string()
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if texts.is_empty() {
+        None
+    } else {
+        Some(texts.join("。"))
+    }
 }
 
 /// カスタム絵文字を読める形に置き換える
@@ -83,30 +502,292 @@ fn replace_custom_emojis(text: &str) -> String {
     custom_emoji_regex().replace_all(text, "$1").into()
 }
 
-async fn replace_words_on_dict(
+/// 読み上げる内容が空になったメッセージの代わりに読み上げる文言を返す
+/// メッセージを読み飛ばす設定の場合は[`None`]を返す
+async fn get_empty_text_placeholder(
+    conn: &mut redis::aio::Connection,
+    guild_id: GuildId,
+) -> Result<Option<String>> {
+    empty_text::get(
+        conn,
+        empty_text::GetOption {
+            guild_id: guild_id.into(),
+        },
+    )
+    .await
+}
+
+/// 辞書に登録された語句を置き換え、同じ文字の繰り返しをまとめる
+/// 辞書に登録された語句は繰り返しをまとめる対象としないため、辞書で特定の繰り返しの読み方を指定できる
+/// メッセージの送信者が自分の辞書に登録した語句は、サーバーの辞書に同じ語句があってもそちらを優先する
+/// 文字幅を揃える設定の場合は、文章と辞書の語句の両方を揃えてから置き換える
+async fn replace_words(
+    ctx: &Context,
+    conn: &mut redis::aio::Connection,
+    guild_id: GuildId,
+    settings: &GuildSettings,
+    author_id: Option<UserId>,
+    text: &str,
+) -> Result<String> {
+    let state = app_state::get(ctx).await?;
+    let dict = state.dict_cache.get_or_fetch(conn, guild_id).await?;
+
+    let user_dict = match author_id {
+        Some(author_id) => state.user_dict_cache.get_or_fetch(conn, author_id).await?,
+        None => Default::default(),
+    };
+    let user_words = user_dict
+        .iter()
+        .map(|(word, _)| word)
+        .collect::<HashSet<_>>();
+    let entries = user_dict
+        .iter()
+        .chain(dict.iter().filter(|(word, _)| !user_words.contains(word)))
+        .collect::<Vec<_>>();
+
+    let normalize_width = settings.get_bool(BoolKey::NormalizeWidth);
+    let text = if normalize_width {
+        fold_width(text)
+    } else {
+        text.to_string()
+    };
+    let text = text.as_str();
+
+    let word_list = entries
+        .iter()
+        .map(|(word, _)| {
+            if normalize_width {
+                fold_width(word)
+            } else {
+                word.clone()
+            }
+        })
+        .collect::<Vec<_>>();
+    let read_as_list = entries
+        .iter()
+        .map(|(_, read_as)| read_as)
+        .collect::<Vec<_>>();
+
+    let ac = AhoCorasickBuilder::new()
+        .match_kind(MatchKind::LeftmostLongest)
+        .build(word_list)?;
+
+    let repeat_threshold = get_repeat_threshold(settings);
+    let collapse = |segment: &str| match repeat_threshold {
+        Some(threshold) => collapse_repeats(segment, threshold),
+        None => segment.to_string(),
+    };
+
+    let mut result = String::with_capacity(text.len());
+    let mut last_end = 0;
+    for mat in ac.find_iter(text) {
+        result.push_str(&collapse(&text[last_end..mat.start()]));
+        result.push_str(read_as_list[mat.pattern().as_usize()]);
+        last_end = mat.end();
+    }
+    result.push_str(&collapse(&text[last_end..]));
+
+    Ok(result)
+}
+
+/// メッセージの中で読み仮名を指定できる場合は、その書き方を返す
+fn get_furigana_syntax(settings: &GuildSettings) -> Option<FuriganaSyntax> {
+    if !settings.get_bool(BoolKey::Furigana) {
+        return None;
+    }
+
+    Some(FuriganaSyntax::from_setting(
+        settings.get_choice(ChoiceKey::FuriganaSyntax).as_deref(),
+    ))
+}
+
+/// 同じ文字の繰り返しをまとめる場合は、その閾値を返す
+fn get_repeat_threshold(settings: &GuildSettings) -> Option<usize> {
+    if !settings.get_bool(BoolKey::CollapseRepeats) {
+        return None;
+    }
+
+    let threshold = settings.get_int(IntKey::RepeatThreshold);
+    Some(threshold.max(1) as usize)
+}
+
+/// フィルターに登録された語句を除去するか「ピー」に置き換える
+/// ASCII文字の大文字と小文字は区別しない
+async fn apply_filter(
     conn: &mut redis::aio::Connection,
     guild_id: GuildId,
     text: &str,
 ) -> Result<String> {
-    let dict = koe_db::dict::get_all(
+    let word_list = filter::get_all(
         conn,
-        GetAllOption {
+        filter::GetAllOption {
             guild_id: guild_id.into(),
         },
     )
     .await?;
+    if word_list.is_empty() {
+        return Ok(text.to_string());
+    }
 
-    let word_list = dict.iter().map(|(word, _)| word).collect::<Vec<_>>();
-    let read_as_list = dict.iter().map(|(_, read_as)| read_as).collect::<Vec<_>>();
+    let mode = filter::get_mode(
+        conn,
+        filter::GetModeOption {
+            guild_id: guild_id.into(),
+        },
+    )
+    .await?;
+    let replacement = match mode {
+        FilterMode::Drop => "",
+        FilterMode::Bleep => "ピー",
+    };
+    let replacement_list = vec![replacement; word_list.len()];
 
     let ac = AhoCorasickBuilder::new()
         .match_kind(MatchKind::LeftmostLongest)
-        .build(word_list)?;
+        .ascii_case_insensitive(true)
+        .build(&word_list)?;
 
-    Ok(ac.replace_all(text, &read_as_list))
+    Ok(ac.replace_all(text, &replacement_list))
 }
 
 /// メッセージのURLを除去
 fn remove_url(text: &str) -> String {
     url_regex().replace_all(text, "、").into()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn message(content: &str, attachments: serde_json::Value) -> Message {
+        serde_json::from_value(json!({
+            "id": "1",
+            "channel_id": "2",
+            "author": { "id": "3", "username": "user", "discriminator": "0001", "avatar": null },
+            "content": content,
+            "timestamp": "2024-01-01T00:00:00+00:00",
+            "edited_timestamp": null,
+            "tts": false,
+            "mention_everyone": false,
+            "mentions": [],
+            "mention_roles": [],
+            "attachments": attachments,
+            "embeds": [],
+            "pinned": false,
+            "type": 0,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn detects_mention_only_messages() {
+        for content in [
+            "<@123>",
+            "<@!123>",
+            "<@&456>",
+            "<#789>",
+            "@everyone",
+            "@here",
+            "  <@123> <@&456>\n<#789>  ",
+            "<@123>@here",
+        ] {
+            assert!(is_mention_only(&message(content, json!([]))), "{}", content);
+        }
+    }
+
+    #[test]
+    fn messages_with_text_are_not_mention_only() {
+        for content in [
+            "",
+            "   ",
+            "<@123> こんにちは",
+            "よろしく @everyone",
+            "<@abc>",
+            "<:emoji:123>",
+            "@everyoneさん",
+            "email@here.example",
+        ] {
+            assert!(
+                !is_mention_only(&message(content, json!([]))),
+                "{}",
+                content
+            );
+        }
+    }
+
+    #[test]
+    fn messages_with_attachments_are_not_mention_only() {
+        let attachments = json!([{
+            "id": "4", "filename": "image.png", "size": 1,
+            "url": "https://example.com/image.png",
+            "proxy_url": "https://example.com/image.png"
+        }]);
+        assert!(!is_mention_only(&message("<@123>", attachments)));
+    }
+
+    #[test]
+    fn formats_polls() {
+        let poll = |answers: &[&str]| {
+            json!({
+                "question": { "text": "好きな色は?" },
+                "answers": answers
+                    .iter()
+                    .enumerate()
+                    .map(|(i, text)| json!({ "answer_id": i + 1, "poll_media": { "text": text } }))
+                    .collect::<Vec<_>>(),
+            })
+        };
+
+        assert_eq!(
+            format_poll(&poll(&["赤", "青", "緑"])).as_deref(),
+            Some("投票: 好きな色は? 選択肢、赤、青、緑")
+        );
+        assert_eq!(
+            format_poll(&poll(&["1", "2", "3", "4", "5", "6", "7"])).as_deref(),
+            Some("投票: 好きな色は? 選択肢、1、2、3、4、5、以下略")
+        );
+        // 絵文字のみの選択肢は読み上げない
+        assert_eq!(
+            format_poll(&json!({
+                "question": { "text": "好きな色は?" },
+                "answers": [{ "poll_media": { "emoji": { "name": "🔴" } } }],
+            }))
+            .as_deref(),
+            Some("投票: 好きな色は?")
+        );
+        assert_eq!(format_poll(&serde_json::Value::Null), None);
+    }
+
+    #[test]
+    fn joins_alt_texts() {
+        let attachments = json!([
+            { "id": "1", "description": " 猫の写真 " },
+            { "id": "2" },
+            { "id": "3", "description": "  " },
+            { "id": "4", "description": "夕焼け" },
+        ]);
+        assert_eq!(
+            join_alt_texts(&attachments).as_deref(),
+            Some("猫の写真。夕焼け")
+        );
+        assert_eq!(join_alt_texts(&json!([{ "id": "1" }])), None);
+        assert_eq!(join_alt_texts(&serde_json::Value::Null), None);
+
+        let long = "あ".repeat(MAX_ALT_TEXT_LENGTH + 1);
+        assert_eq!(
+            join_alt_texts(&json!([{ "description": long }])),
+            Some("あ".repeat(MAX_ALT_TEXT_LENGTH) + TRUNCATION_MARKER)
+        );
+    }
+
+    #[test]
+    fn mass_mentions_are_never_read() {
+        let strip = |text: &str| mass_mention_regex().replace_all(text, "").into_owned();
+        assert_eq!(strip("@everyone"), "");
+        assert_eq!(strip("@here 集合"), " 集合");
+        assert_eq!(strip("@everyone、@hereです"), "、です");
+        assert_eq!(strip("@everyoneさん"), "さん");
+        assert_eq!(strip("@everyones"), "@everyones");
+    }
+}
@@ -0,0 +1,30 @@
+/// 同じ文字が`threshold`を超えて続く部分を1文字にまとめる
+/// 「w」の繰り返しは笑い声として「わら」と読む
+pub fn collapse_repeats(text: &str, threshold: usize) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let mut count = 1;
+        while chars.peek() == Some(&c) {
+            chars.next();
+            count += 1;
+        }
+
+        if count > threshold {
+            if is_laugh(c) {
+                result.push_str("わら");
+            } else {
+                result.push(c);
+            }
+        } else {
+            result.extend(std::iter::repeat(c).take(count));
+        }
+    }
+
+    result
+}
+
+fn is_laugh(c: char) -> bool {
+    matches!(c, 'w' | 'W' | 'ｗ' | 'Ｗ')
+}
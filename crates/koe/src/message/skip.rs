@@ -0,0 +1,59 @@
+use crate::messages::Key;
+use dashmap::DashMap;
+use log::debug;
+use serenity::model::channel::Message;
+
+/// 読み上げ対象のチャンネルのメッセージを読み上げなかった理由
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SkipReason {
+    /// Koe自身のメッセージ
+    OwnMessage,
+    /// セミコロンで始まるメッセージ
+    SemicolonPrefix,
+    /// `/settings read-prefix`で設定した合図で始まらないメッセージ
+    MissingReadPrefix,
+    /// URLの除去などの結果、読み上げる文字列が空になった
+    EmptyText,
+    /// `/filter`で登録された語句を除去した結果、読み上げる文字列が空になった
+    Filtered,
+    /// サーバーの今月の文字数が上限に達している
+    UsageLimit,
+}
+
+impl SkipReason {
+    pub fn label(&self) -> Key {
+        match self {
+            SkipReason::OwnMessage => Key::SkipOwnMessage,
+            SkipReason::SemicolonPrefix => Key::SkipSemicolonPrefix,
+            SkipReason::MissingReadPrefix => Key::SkipMissingReadPrefix,
+            SkipReason::EmptyText => Key::SkipEmptyText,
+            SkipReason::Filtered => Key::SkipFiltered,
+            SkipReason::UsageLimit => Key::SkipUsageLimit,
+        }
+    }
+}
+
+/// 読み上げなかったメッセージの数を理由ごとに数える
+#[derive(Default)]
+pub struct SkipCounter {
+    counts: DashMap<SkipReason, u64>,
+}
+
+impl SkipCounter {
+    /// メッセージを読み上げなかったことを記録する
+    pub fn record(&self, msg: &Message, reason: SkipReason) {
+        debug!("Skipped message {}: {:?}", msg.id, reason);
+        *self.counts.entry(reason).or_insert(0) += 1;
+    }
+
+    /// 理由ごとの件数を返す
+    pub fn counts(&self) -> Vec<(SkipReason, u64)> {
+        let mut counts = self
+            .counts
+            .iter()
+            .map(|entry| (*entry.key(), *entry.value()))
+            .collect::<Vec<_>>();
+        counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        counts
+    }
+}
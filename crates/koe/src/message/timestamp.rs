@@ -0,0 +1,93 @@
+use crate::regex::timestamp_regex;
+use regex::Captures;
+use time::{OffsetDateTime, UtcOffset, Weekday};
+
+/// `<t:1700000000:R>`のようなタイムスタンプ表記を読める形に置き換える
+/// 絶対時刻は`offset`のタイムゾーンで表す
+pub fn replace_timestamps(text: &str, offset: UtcOffset) -> String {
+    let now = OffsetDateTime::now_utc();
+
+    timestamp_regex()
+        .replace_all(text, |caps: &Captures| {
+            let datetime = match caps[1]
+                .parse::<i64>()
+                .ok()
+                .and_then(|t| OffsetDateTime::from_unix_timestamp(t).ok())
+            {
+                Some(dt) => dt.to_offset(offset),
+                None => return caps[0].to_string(),
+            };
+            let style = caps.get(2).map_or("f", |m| m.as_str());
+
+            format_timestamp(datetime, now, style)
+        })
+        .into()
+}
+
+fn format_timestamp(datetime: OffsetDateTime, now: OffsetDateTime, style: &str) -> String {
+    match style {
+        "t" => format_time(datetime, false),
+        "T" => format_time(datetime, true),
+        "d" | "D" => format_date(datetime),
+        "F" => format!(
+            "{}{} {}",
+            format_date(datetime),
+            weekday_name(datetime.weekday()),
+            format_time(datetime, false)
+        ),
+        "R" => format_relative(datetime, now),
+        // "f"およびフラグ省略時
+        _ => format!("{} {}", format_date(datetime), format_time(datetime, false)),
+    }
+}
+
+fn format_date(datetime: OffsetDateTime) -> String {
+    format!(
+        "{}年{}月{}日",
+        datetime.year(),
+        u8::from(datetime.month()),
+        datetime.day()
+    )
+}
+
+fn format_time(datetime: OffsetDateTime, with_seconds: bool) -> String {
+    if with_seconds {
+        format!(
+            "{}時{}分{}秒",
+            datetime.hour(),
+            datetime.minute(),
+            datetime.second()
+        )
+    } else {
+        format!("{}時{}分", datetime.hour(), datetime.minute())
+    }
+}
+
+fn format_relative(datetime: OffsetDateTime, now: OffsetDateTime) -> String {
+    let diff = (datetime - now).whole_seconds();
+    let suffix = if diff < 0 { "前" } else { "後" };
+    let secs = diff.unsigned_abs();
+
+    let amount = match secs {
+        0..=44 => "数秒".to_string(),
+        45..=3599 => format!("{}分", (secs / 60).max(1)),
+        3600..=86399 => format!("{}時間", secs / 3600),
+        86400..=2591999 => format!("{}日", secs / 86400),
+        2592000..=31535999 => format!("{}か月", secs / 2592000),
+        _ => format!("{}年", secs / 31536000),
+    };
+
+    format!("{}{}", amount, suffix)
+}
+
+fn weekday_name(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Monday => "月曜日",
+        Weekday::Tuesday => "火曜日",
+        Weekday::Wednesday => "水曜日",
+        Weekday::Thursday => "木曜日",
+        Weekday::Friday => "金曜日",
+        Weekday::Saturday => "土曜日",
+        Weekday::Sunday => "日曜日",
+    }
+}
@@ -0,0 +1,120 @@
+/// 半角カタカナ（U+FF61〜U+FF9F）に対応する全角の文字
+const HALFWIDTH_KATAKANA: [char; 63] = [
+    '。', '「', '」', '、', '・', 'ヲ', 'ァ', 'ィ', 'ゥ', 'ェ', 'ォ', 'ャ', 'ュ', 'ョ', 'ッ', 'ー',
+    'ア', 'イ', 'ウ', 'エ', 'オ', 'カ', 'キ', 'ク', 'ケ', 'コ', 'サ', 'シ', 'ス', 'セ', 'ソ', 'タ',
+    'チ', 'ツ', 'テ', 'ト', 'ナ', 'ニ', 'ヌ', 'ネ', 'ノ', 'ハ', 'ヒ', 'フ', 'ヘ', 'ホ', 'マ', 'ミ',
+    'ム', 'メ', 'モ', 'ヤ', 'ユ', 'ヨ', 'ラ', 'リ', 'ル', 'レ', 'ロ', 'ワ', 'ン', '゛', '゜',
+];
+
+/// 半角の濁点
+const HALFWIDTH_VOICED_MARK: char = '\u{ff9e}';
+/// 半角の半濁点
+const HALFWIDTH_SEMI_VOICED_MARK: char = '\u{ff9f}';
+
+/// 全角の英数字・記号を半角に、半角カタカナを全角に揃える
+/// 絵文字や結合文字など、それ以外の文字は変更しない
+pub fn fold_width(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            // 全角の英数字・記号（！〜～）
+            '\u{ff01}'..='\u{ff5e}' => {
+                result.push(char::from_u32(c as u32 - 0xfee0).unwrap_or(c));
+            }
+            '\u{3000}' => result.push(' '),
+            '\u{ff61}'..='\u{ff9f}' => {
+                let katakana = HALFWIDTH_KATAKANA[(c as u32 - 0xff61) as usize];
+                let combined = match chars.peek() {
+                    Some(&HALFWIDTH_VOICED_MARK) => voiced(katakana),
+                    Some(&HALFWIDTH_SEMI_VOICED_MARK) => semi_voiced(katakana),
+                    _ => None,
+                };
+                match combined {
+                    Some(combined) => {
+                        result.push(combined);
+                        chars.next();
+                    }
+                    None => result.push(katakana),
+                }
+            }
+            _ => result.push(c),
+        }
+    }
+
+    result
+}
+
+/// 濁点のついたカタカナを返す
+fn voiced(c: char) -> Option<char> {
+    match c {
+        'ウ' => Some('ヴ'),
+        'カ' | 'キ' | 'ク' | 'ケ' | 'コ' | 'サ' | 'シ' | 'ス' | 'セ' | 'ソ' | 'タ' | 'チ'
+        | 'ツ' | 'テ' | 'ト' | 'ハ' | 'ヒ' | 'フ' | 'ヘ' | 'ホ' => {
+            char::from_u32(c as u32 + 1)
+        }
+        _ => None,
+    }
+}
+
+/// 半濁点のついたカタカナを返す
+fn semi_voiced(c: char) -> Option<char> {
+    match c {
+        'ハ' | 'ヒ' | 'フ' | 'ヘ' | 'ホ' => char::from_u32(c as u32 + 2),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn folds_fullwidth_latin_to_halfwidth() {
+        assert_eq!(fold_width("ＡＢＣ"), "ABC");
+        assert_eq!(fold_width("ｋｏｅ　１２３"), "koe 123");
+        assert_eq!(fold_width("！＃（）＠～"), "!#()@~");
+        assert_eq!(fold_width("ABC abc 123"), "ABC abc 123");
+    }
+
+    #[test]
+    fn folds_halfwidth_katakana_to_fullwidth() {
+        assert_eq!(fold_width("ｺｴ"), "コエ");
+        assert_eq!(fold_width("ｶﾞｷﾞｸﾞ"), "ガギグ");
+        assert_eq!(fold_width("ﾊﾟﾋﾟﾌﾟ"), "パピプ");
+        assert_eq!(fold_width("ｳﾞｧｲｵﾘﾝ"), "ヴァイオリン");
+        assert_eq!(fold_width("｢ｺｰﾋｰ｣､ﾃﾞｽ｡"), "「コーヒー」、デス。");
+    }
+
+    #[test]
+    fn keeps_unpaired_sound_marks() {
+        // 濁点・半濁点をつけられない文字の後では、全角の濁点・半濁点にする
+        assert_eq!(fold_width("ｱﾞ"), "ア゛");
+        assert_eq!(fold_width("ｶﾟ"), "カ゜");
+        assert_eq!(fold_width("ﾞ"), "゛");
+    }
+
+    #[test]
+    fn keeps_fullwidth_kana_and_kanji() {
+        let text = "ひらがな、カタカナ、漢字。";
+        assert_eq!(fold_width(text), text);
+        // 全角のカタカナに半角の濁点が続く場合は、濁点のみを全角にする
+        assert_eq!(fold_width("カﾞ"), "カ゛");
+    }
+
+    #[test]
+    fn keeps_emoji_and_combining_characters() {
+        for text in [
+            "👍🏽",
+            "👨‍👩‍👧",
+            "🇯🇵",
+            "e\u{301}",
+            "か\u{3099}",
+            "1\u{fe0f}\u{20e3}",
+        ] {
+            assert_eq!(fold_width(text), text);
+        }
+        assert_eq!(fold_width("Ａ👍🏽ｶﾞ"), "A👍🏽ガ");
+    }
+}
@@ -0,0 +1,1316 @@
+use crate::app_state;
+use koe_db::language::{self, Language};
+use log::warn;
+use serenity::{client::Context, model::id::GuildId};
+use std::fmt::Display;
+
+/// 応答の言語を決める
+/// サーバーで`/settings language`が設定されていればそれを使い、自動（未設定）の場合はDiscordの言語設定（`locale`）に合わせる
+/// コマンドへの応答ではないお知らせなど、`locale`がない場合は空文字列を渡す
+pub async fn resolve(ctx: &Context, guild_id: Option<GuildId>, locale: &str) -> Language {
+    let guild_language = match guild_id {
+        Some(guild_id) => match get_guild_language(ctx, guild_id).await {
+            Ok(language) => language,
+            Err(err) => {
+                warn!("Failed to get language of guild {}: {:?}", guild_id, err);
+                None
+            }
+        },
+        None => None,
+    };
+
+    choose(guild_language, locale)
+}
+
+/// サーバーの設定、Discordの言語設定、日本語の順に応答の言語を決める
+/// Discordの言語設定が日本語・英語のどちらでもない場合は日本語にする
+fn choose(guild_language: Option<Language>, locale: &str) -> Language {
+    guild_language
+        .or_else(|| from_locale(locale))
+        .unwrap_or(Language::Japanese)
+}
+
+async fn get_guild_language(ctx: &Context, guild_id: GuildId) -> anyhow::Result<Option<Language>> {
+    let state = app_state::get(ctx).await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+    language::get(
+        &mut conn,
+        language::GetOption {
+            guild_id: guild_id.into(),
+        },
+    )
+    .await
+}
+
+/// Discordの言語設定に対応する応答の言語を返す
+/// 対応する言語がない場合は[`None`]を返す
+fn from_locale(locale: &str) -> Option<Language> {
+    if locale.starts_with("ja") {
+        Some(Language::Japanese)
+    } else if locale.starts_with("en") {
+        Some(Language::English)
+    } else {
+        None
+    }
+}
+
+/// メッセージを`lang`の言語で返す
+pub fn text(lang: Language, key: Key) -> String {
+    template(lang, key).to_string()
+}
+
+/// メッセージを`lang`の言語で返す
+/// 文面の`{name}`は、`args`で`name`に対応する値に置き換える
+pub fn format(lang: Language, key: Key, args: &[(&str, &(dyn Display + Sync))]) -> String {
+    let template = template(lang, key);
+    let mut result = String::with_capacity(template.len());
+
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let value = rest.find('}').and_then(|end| {
+            let name = &rest[1..end];
+            args.iter()
+                .find(|(arg_name, _)| *arg_name == name)
+                .map(|(_, value)| (end, value))
+        });
+        match value {
+            Some((end, value)) => {
+                result.push_str(&value.to_string());
+                rest = &rest[end + 1..];
+            }
+            None => {
+                result.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+
+    result
+}
+
+/// 利用者に表示するメッセージの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    ErrorOccurred,
+    ParseUnknownCommand,
+    ParseMissingSubcommand,
+    ParseMissingOption,
+    ParseWrongType,
+    ParseInvalidValue,
+    TypeString,
+    TypeInteger,
+    TypeNumber,
+    TypeBoolean,
+    TypeUser,
+    TypeChannel,
+    TypeRole,
+    TypeMentionable,
+    TypeAttachment,
+    TypeSubcommand,
+    TypeUnknown,
+    GuildOnly,
+    GuildOnlyThisCommand,
+    JoinVoiceChannelFirst,
+    AlreadyReadingHere,
+    MovedAndRebound,
+    Rebound,
+    ConnectionLimitReached,
+    Joined,
+    NotConnected,
+    Left,
+    ReadingMessage,
+    NothingToRead,
+    Skipped,
+    AlreadyInVoiceChannel,
+    Moved,
+    ChannelAlreadyBound,
+    TooManyBoundChannels,
+    ChannelBound,
+    ChannelNotBound,
+    CannotUnbindLastChannel,
+    ChannelUnbound,
+    BoundChannelList,
+    StatusNotConnected,
+    StatusTitle,
+    StatusVoiceChannel,
+    StatusBoundChannels,
+    StatusConnectedFor,
+    QueuedMessages,
+    QueueWithOldest,
+    QueueCount,
+    LastSpeechError,
+    Nothing,
+    Recoveries,
+    Times,
+    VoiceSet,
+    VoiceInfoTitle,
+    Voice,
+    Speed,
+    Pitch,
+    ConfiguredValue,
+    PresetDefaultValue,
+    PresetUnavailable,
+    VoiceNotAssigned,
+    DefaultValue,
+    BoostRequiresPermission,
+    PreviewUserRequiresPermission,
+    PreviewUserEnqueued,
+    VoiceLanguageSet,
+    VoiceLanguageRemoved,
+    VoiceLanguageUnsupported,
+    VoiceLanguage,
+    VoiceLanguageUnavailable,
+    BoostOutOfRange,
+    BoostReset,
+    BoostSet,
+    DictAddNeedsBoth,
+    DictAdded,
+    DictWordExists,
+    DictFormTitle,
+    DictFormWord,
+    DictFormReadAs,
+    DictRemoved,
+    DictWordNotFound,
+    DictTitle,
+    PageFooter,
+    PreviousPage,
+    NextPage,
+    MyDictAdded,
+    MyDictWordExists,
+    MyDictTooMany,
+    MyDictRemoved,
+    MyDictWordNotFound,
+    MyDictEmpty,
+    DictCount,
+    DictAlreadyEmpty,
+    DictCleared,
+    MyDictTitle,
+    DictExported,
+    DictImportBadExtension,
+    DictImportTooLarge,
+    DictImportUnreadable,
+    DictImportConflicts,
+    DictImported,
+    DictImportErrors,
+    ImportNotUtf8,
+    ImportJsonNotArray,
+    ImportJsonInvalid,
+    ImportJsonItem,
+    ImportJsonItemInvalid,
+    ImportCsvBadHeader,
+    ImportCsvLine,
+    ImportCsvWrongColumns,
+    ImportCsvUnquoted,
+    ImportCsvUnclosedQuote,
+    ImportEntryEmpty,
+    ImportEntryDuplicate,
+    ListMore,
+    FilterAdded,
+    FilterWordExists,
+    FilterTooMany,
+    FilterRemoved,
+    FilterWordNotFound,
+    ServerFallbackName,
+    FilterTitle,
+    FilterEmpty,
+    FilterModeFooter,
+    FilterModeChanged,
+    FilterModeDrop,
+    FilterModeBleep,
+    AutoJoinEnabled,
+    AutoJoinDisabled,
+    AutoJoinNeedsBoth,
+    ToggleReadReactions,
+    ToggleReadDeletions,
+    ToggleReadAltText,
+    ToggleReadPolls,
+    ToggleReadAuthorRole,
+    ToggleShowDictReadings,
+    ToggleReadScheduledEvents,
+    ToggleNormalizeWidth,
+    ToggleReadVoiceChat,
+    ToggleChime,
+    ToggleFollowUsers,
+    ToggleVoiceByUserId,
+    RepeatThresholdOutOfRange,
+    CollapseRepeatsDisabled,
+    FuriganaEnabled,
+    FuriganaDisabled,
+    FuriganaSyntaxBraces,
+    FuriganaSyntaxRuby,
+    SettingFurigana,
+    ToggleFurigana,
+    ReadResponsesEnabled,
+    ReadResponsesDisabled,
+    ToggleReadResponses,
+    ToggleReadErrorResponses,
+    SettingReadResponses,
+    CollapseRepeatsEnabled,
+    EmptyTextTooLong,
+    EmptyTextSet,
+    EmptyTextRemoved,
+    IdleTimeoutSet,
+    IdleTimeoutDisabled,
+    IdleTimeoutReset,
+    IdleTimeoutOutOfRange,
+    IdleTimeoutNever,
+    IdleDisconnected,
+    Minutes,
+    FarewellSet,
+    FarewellRemoved,
+    MentionOnlyChanged,
+    SettingsViewTitle,
+    SettingsViewFooter,
+    SettingEnabled,
+    SettingDisabled,
+    SettingAutoJoin,
+    SettingCollapseRepeats,
+    SettingSkip,
+    SettingReadAll,
+    SettingLanguageFollow,
+    SettingVoiceRange,
+    SettingCollapseRepeatsLabel,
+    SettingRepeatThresholdLabel,
+    SettingMaxWordsLabel,
+    SettingMessageTemplateLabel,
+    SettingIdleTimeoutLabel,
+    SettingFuriganaSyntaxLabel,
+    SettingSpeechProviderLabel,
+    SettingNoticeChannelLabel,
+    SettingNoticeChannelDefault,
+    SettingNotSet,
+    SettingNotBool,
+    SettingNotInt,
+    SettingOutOfRange,
+    SettingTooLong,
+    SettingMissingPlaceholder,
+    SettingNotChoice,
+    SettingNotChannel,
+    SettingUpdated,
+    SettingReset,
+    MentionOnlyRead,
+    MentionOnlySkip,
+    MentionOnlyNote,
+    ReadPrefixTooLong,
+    ReadPrefixSet,
+    ReadPrefixRemoved,
+    SpeechProviderSet,
+    SpeechProviderUnavailable,
+    VoiceResetForProvider,
+    ScaleOutOfRange,
+    ScaleMinAboveMax,
+    VoiceRangeSet,
+    SystemVoiceReset,
+    SystemVoiceSet,
+    LanguageSet,
+    LanguageReset,
+    LanguageJapanese,
+    LanguageEnglish,
+    SpecifyBot,
+    EmbedBotAdded,
+    EmbedBotExists,
+    EmbedBotRemoved,
+    EmbedBotNotFound,
+    EmbedBotListEmpty,
+    EmbedBotList,
+    PermissionDenied,
+    RateLimited,
+    RequirementEveryone,
+    RequirementInVoiceChannel,
+    RequirementRole,
+    RequirementManageGuild,
+    PermissionSet,
+    PermissionsTitle,
+    PermissionsFooter,
+    OwnerOnly,
+    UsageLimitSet,
+    UsageLimitRemoved,
+    UsageTitle,
+    UsageGuild,
+    UsageLimit,
+    UsageNoLimit,
+    UsageTotal,
+    UsageTopGuilds,
+    UsageChars,
+    AdminStatusTitle,
+    SpeechEngines,
+    AudioCache,
+    AudioCacheStats,
+    SpeechEngineAvailable,
+    SpeechEngineUnavailable,
+    InvalidGuildId,
+    InspectNotConnected,
+    InspectTitle,
+    ConnectionState,
+    ConnectionStateConnected,
+    ConnectionStateConnectedTo,
+    ConnectionStateDisconnected,
+    LastMessageRead,
+    HealthOk,
+    HealthOkWithVersion,
+    HealthError,
+    HealthTimeout,
+    ConnectedGuilds,
+    CheckError,
+    CheckTimeout,
+    SkippedMessages,
+    SkipOwnMessage,
+    SkipSemicolonPrefix,
+    SkipMissingReadPrefix,
+    SkipEmptyText,
+    SkipFiltered,
+    SkipUsageLimit,
+    Help,
+    VersionTitle,
+    VersionCommit,
+    VersionBuiltAt,
+    VersionLibraries,
+    VersionUptime,
+    VersionGuilds,
+    VersionVoiceConnections,
+    HelpCategoryConnection,
+    HelpCategoryDictionary,
+    HelpCategoryVoice,
+    HelpCategoryOther,
+    HelpContinued,
+    HelpOptions,
+    HelpRequiredOption,
+    HelpContextMenu,
+    PermissionsMissing,
+    PermissionConnect,
+    PermissionSpeak,
+    PermissionMuteMembers,
+    QuotedName,
+    ListSeparator,
+    JoinMissingAccess,
+    JoinMissingPermissions,
+    JoinTimedOut,
+    PresetNotFound,
+    PresetChoice,
+    LabeledValue,
+    DurationSeconds,
+    DurationMinutes,
+    DurationHours,
+    InvokerOnly,
+    ComponentExpired,
+    ConfirmYes,
+    ConfirmNo,
+    ConfirmLeave,
+    ConfirmDictClear,
+    ConfirmCancelled,
+    ConfirmTimedOut,
+    VoiceChanged,
+}
+
+/// メッセージの文面を日本語と英語の組で返す
+fn template(lang: Language, key: Key) -> &'static str {
+    let (ja, en) = match key {
+        Key::ErrorOccurred => (
+            "エラーが発生しました。しばらくしてからもう一度お試しください。",
+            "An error occurred. Please try again later.",
+        ),
+        Key::ParseUnknownCommand => (
+            "`/{command}`は存在しないコマンドです。Discordを再起動してから、もう一度お試しください。",
+            "`/{command}` is not a known command. Please restart Discord and try again.",
+        ),
+        Key::ParseMissingSubcommand => (
+            "`/{command}`に続けて、実行する操作を選択してください。",
+            "Please choose what to do after `/{command}`.",
+        ),
+        Key::ParseMissingOption => ("`{name}`を指定してください。", "Please specify `{name}`."),
+        Key::ParseWrongType => (
+            "`{name}`には{expected}を指定してください（{actual}が指定されました）。",
+            "`{name}` must be {expected} (got {actual}).",
+        ),
+        Key::ParseInvalidValue => (
+            "`{name}`に{value}は指定できません。選択肢から選んでください。",
+            "{value} is not a valid value for `{name}`. Please choose one of the options.",
+        ),
+        Key::TypeString => ("文字列", "a string"),
+        Key::TypeInteger => ("整数", "an integer"),
+        Key::TypeNumber => ("数値", "a number"),
+        Key::TypeBoolean => ("真偽値", "a boolean"),
+        Key::TypeUser => ("ユーザー", "a user"),
+        Key::TypeChannel => ("チャンネル", "a channel"),
+        Key::TypeRole => ("ロール", "a role"),
+        Key::TypeMentionable => ("メンション", "a mention"),
+        Key::TypeAttachment => ("ファイル", "a file"),
+        Key::TypeSubcommand => ("サブコマンド", "a subcommand"),
+        Key::TypeUnknown => ("不明な値", "an unknown value"),
+        Key::GuildOnly => (
+            "{command} はサーバー内でのみ使えます。",
+            "{command} can only be used in a server.",
+        ),
+        Key::GuildOnlyThisCommand => (
+            "このコマンドはサーバー内でのみ使えます。サーバーのテキストチャンネルから送信してください。",
+            "This command can only be used in a server. Please send it from a text channel in a server.",
+        ),
+        Key::JoinVoiceChannelFirst => (
+            "ボイスチャンネルに接続してから `/{command}` を送信してください。",
+            "Please join a voice channel before sending `/{command}`.",
+        ),
+        Key::AlreadyReadingHere => (
+            "すでにこのチャンネルを読み上げています。",
+            "Already reading this channel.",
+        ),
+        Key::MovedAndRebound => (
+            "<#{voice}>に移動し、読み上げ対象を<#{text}>に変更しました。",
+            "Moved to <#{voice}> and switched to reading <#{text}>.",
+        ),
+        Key::Rebound => (
+            "読み上げ対象を<#{text}>に変更しました。",
+            "Switched to reading <#{text}>.",
+        ),
+        Key::ConnectionLimitReached => (
+            "現在接続数が上限に達しています。しばらくしてからお試しください。",
+            "The bot has reached its connection limit. Please try again later.",
+        ),
+        Key::Joined => ("接続しました。", "Connected."),
+        Key::NotConnected => (
+            "どのボイスチャンネルにも接続していません。",
+            "Not connected to any voice channel.",
+        ),
+        Key::Left => ("切断しました。", "Disconnected."),
+        Key::ReadingMessage => ("メッセージを読み上げます。", "Reading the message."),
+        Key::NothingToRead => (
+            "このメッセージには読み上げる内容がありません。",
+            "This message has nothing to read.",
+        ),
+        Key::Skipped => (
+            "読み上げ中のメッセージをスキップしました。",
+            "Skipped the message being read.",
+        ),
+        Key::AlreadyInVoiceChannel => (
+            "すでにこのボイスチャンネルに接続しています。",
+            "Already connected to this voice channel.",
+        ),
+        Key::Moved => ("<#{channel}>に移動しました。", "Moved to <#{channel}>."),
+        Key::ChannelAlreadyBound => (
+            "すでに<#{channel}>は読み上げ対象です。",
+            "<#{channel}> is already being read.",
+        ),
+        Key::TooManyBoundChannels => (
+            "読み上げ対象のテキストチャンネルは{max}個までです。",
+            "Up to {max} text channels can be read at once.",
+        ),
+        Key::ChannelBound => (
+            "<#{channel}>を読み上げ対象に追加しました。",
+            "Added <#{channel}> to the channels being read.",
+        ),
+        Key::ChannelNotBound => (
+            "<#{channel}>は読み上げ対象ではありません。",
+            "<#{channel}> is not being read.",
+        ),
+        Key::CannotUnbindLastChannel => (
+            "最後の読み上げ対象のチャンネルは削除できません。読み上げを終了するには `/leave` を送信してください。",
+            "The last channel being read cannot be removed. Send `/leave` to stop reading.",
+        ),
+        Key::ChannelUnbound => (
+            "<#{channel}>を読み上げ対象から削除しました。",
+            "Removed <#{channel}> from the channels being read.",
+        ),
+        Key::BoundChannelList => (
+            "読み上げ対象のチャンネル: {channels}",
+            "Channels being read: {channels}",
+        ),
+        Key::StatusNotConnected => (
+            "このサーバーではどのボイスチャンネルにも接続していません。",
+            "Not connected to any voice channel in this server.",
+        ),
+        Key::StatusTitle => ("📊 読み上げの状況", "📊 Reading status"),
+        Key::StatusVoiceChannel => ("ボイスチャンネル", "Voice channel"),
+        Key::StatusBoundChannels => ("読み上げ対象のチャンネル", "Channels being read"),
+        Key::StatusConnectedFor => ("接続時間", "Connected for"),
+        Key::QueuedMessages => ("キューに入っているメッセージ", "Queued messages"),
+        Key::QueueWithOldest => (
+            "{count}件（最も古いもの: {age}前）",
+            "{count} (oldest: {age} ago)",
+        ),
+        Key::QueueCount => ("{count}件", "{count}"),
+        Key::LastSpeechError => ("最後に発生した音声合成のエラー", "Last speech synthesis error"),
+        Key::Nothing => ("なし", "None"),
+        Key::Recoveries => (
+            "再生の失敗による接続の回復",
+            "Reconnections after playback failures",
+        ),
+        Key::Times => ("{count}回", "{count}"),
+        Key::VoiceSet => ("声を設定しました（{summary}）。", "Updated your voice ({summary})."),
+        Key::VoiceInfoTitle => ("🔊 {name}さんの声の設定", "🔊 Voice settings of {name}"),
+        Key::Voice => ("声", "Voice"),
+        Key::Speed => ("話速", "Speed"),
+        Key::Pitch => ("音高", "Pitch"),
+        Key::ConfiguredValue => ("{value}（設定値）", "{value} (configured)"),
+        Key::PresetDefaultValue => ("{value}（声の既定値）", "{value} (voice default)"),
+        Key::PresetUnavailable => (
+            "設定値 {id} は現在利用できません。",
+            "The configured voice {id} is currently unavailable.",
+        ),
+        Key::VoiceNotAssigned => (
+            "既定値（最初にメッセージを読み上げるときに割り当てられます）",
+            "Default (assigned when your first message is read)",
+        ),
+        Key::DefaultValue => ("既定値", "Default"),
+        Key::BoostRequiresPermission => (
+            "`/voice boost` を使うには「メンバーをミュート」の権限が必要です。",
+            "`/voice boost` requires the \"Mute Members\" permission.",
+        ),
+        Key::PreviewUserRequiresPermission => (
+            "`/voice preview-user` を使うには「メンバーをミュート」の権限が必要です。",
+            "`/voice preview-user` requires the \"Mute Members\" permission.",
+        ),
+        Key::PreviewUserEnqueued => (
+            "<@{user}>の声で見本の文章を読み上げます。",
+            "Reading a sample phrase in the voice of <@{user}>.",
+        ),
+        Key::VoiceLanguageSet => (
+            "あなたのメッセージを、言語「{language}」を読み上げられるエンジンで読み上げます。",
+            "Your messages will be read by an engine that supports the language \"{language}\".",
+        ),
+        Key::VoiceLanguageRemoved => (
+            "あなたのメッセージを、サーバーで選択されたエンジンで読み上げます。",
+            "Your messages will be read by the engine selected for this server.",
+        ),
+        Key::VoiceLanguageUnsupported => (
+            "言語「{language}」を読み上げられるエンジンはありません。使用できる言語: {list}",
+            "No engine supports the language \"{language}\". Available languages: {list}",
+        ),
+        Key::VoiceLanguage => ("読み上げの言語", "Reading language"),
+        Key::VoiceLanguageUnavailable => (
+            "`{language}`（読み上げられるエンジンがないため、サーバーで選択されたエンジンを使っています。`/voice language`で設定し直してください）",
+            "`{language}` (no engine supports it, so the engine selected for this server is used. Please set it again with `/voice language`)",
+        ),
+        Key::BoostOutOfRange => (
+            "音量の倍率は{min}から{max}の範囲で指定してください。",
+            "The volume multiplier must be between {min} and {max}.",
+        ),
+        Key::BoostReset => (
+            "<@{user}>の読み上げの音量を元に戻しました。",
+            "Reset the reading volume of <@{user}>.",
+        ),
+        Key::BoostSet => (
+            "<@{user}>の読み上げの音量を、退出するまで{amount}倍にしました。",
+            "Set the reading volume of <@{user}> to {amount}x until the bot leaves.",
+        ),
+        Key::DictAddNeedsBoth => (
+            "語句と読み方の両方を指定してください。",
+            "Please specify both the word and its reading.",
+        ),
+        Key::DictAdded => (
+            "{word}の読み方を{read_as}として辞書に登録しました。",
+            "Added {word} to the dictionary, read as {read_as}.",
+        ),
+        Key::DictWordExists => (
+            "すでに{word}は辞書に登録されています。",
+            "{word} is already in the dictionary.",
+        ),
+        Key::DictFormTitle => ("辞書に追加", "Add to dictionary"),
+        Key::DictFormWord => ("語句", "Word"),
+        Key::DictFormReadAs => ("読み方", "Reading"),
+        Key::DictRemoved => (
+            "辞書から{word}を削除しました。",
+            "Removed {word} from the dictionary.",
+        ),
+        Key::DictWordNotFound => (
+            "{word}は辞書に登録されていません。",
+            "{word} is not in the dictionary.",
+        ),
+        Key::DictTitle => ("📕 {guild}の辞書", "📕 Dictionary of {guild}"),
+        Key::PageFooter => ("{page} / {total} ページ", "Page {page} / {total}"),
+        Key::PreviousPage => ("◀ 前へ", "◀ Previous"),
+        Key::NextPage => ("次へ ▶", "Next ▶"),
+        Key::MyDictAdded => (
+            "{word}の読み方を{read_as}としてあなたの辞書に登録しました。",
+            "Added {word} to your dictionary, read as {read_as}.",
+        ),
+        Key::MyDictWordExists => (
+            "すでに{word}はあなたの辞書に登録されています。",
+            "{word} is already in your dictionary.",
+        ),
+        Key::MyDictTooMany => (
+            "あなたの辞書に登録できる語句は{max}個までです。",
+            "Your dictionary can hold up to {max} words.",
+        ),
+        Key::MyDictRemoved => (
+            "あなたの辞書から{word}を削除しました。",
+            "Removed {word} from your dictionary.",
+        ),
+        Key::MyDictWordNotFound => (
+            "{word}はあなたの辞書に登録されていません。",
+            "{word} is not in your dictionary.",
+        ),
+        Key::MyDictEmpty => (
+            "あなたの辞書には何も登録されていません。",
+            "Your dictionary is empty.",
+        ),
+        Key::MyDictTitle => ("📘 {name}さんの辞書", "📘 Dictionary of {name}"),
+        Key::DictCount => (
+            "辞書には{count}個の語句が登録されています。",
+            "The dictionary has {count} words.",
+        ),
+        Key::DictAlreadyEmpty => (
+            "辞書には何も登録されていません。",
+            "The dictionary is already empty.",
+        ),
+        Key::DictCleared => (
+            "辞書から{count}個の語句を削除しました。",
+            "Removed {count} words from the dictionary.",
+        ),
+        Key::DictExported => (
+            "辞書を書き出しました（{count}語）。",
+            "Exported the dictionary ({count} words).",
+        ),
+        Key::DictImportBadExtension => (
+            "拡張子が `.json` または `.csv` のファイルを指定してください。",
+            "Please attach a file with the `.json` or `.csv` extension.",
+        ),
+        Key::DictImportTooLarge => (
+            "ファイルが大きすぎます。1MB以下のファイルを指定してください。",
+            "The file is too large. Please attach a file of 1 MB or less.",
+        ),
+        Key::DictImportUnreadable => (
+            "ファイルを読み込めませんでした。\n{error}",
+            "Could not read the file.\n{error}",
+        ),
+        Key::DictImportConflicts => (
+            "次の語句はすでに辞書に登録されているため、インポートを中止しました。\n{list}",
+            "Cancelled the import because the following words are already in the dictionary.\n{list}",
+        ),
+        Key::DictImported => (
+            "辞書をインポートしました。\n追加: {inserted}語、上書き: {updated}語、スキップ: {skipped}語",
+            "Imported the dictionary.\nAdded: {inserted}, overwritten: {updated}, skipped: {skipped}",
+        ),
+        Key::DictImportErrors => (
+            "次の項目は読み込めませんでした。\n{list}",
+            "The following entries could not be read.\n{list}",
+        ),
+        Key::ImportNotUtf8 => (
+            "ファイルの文字コードをUTF-8にしてください。",
+            "Please save the file in UTF-8.",
+        ),
+        Key::ImportJsonNotArray => (
+            "JSONの最上位は配列にしてください。",
+            "The top level of the JSON must be an array.",
+        ),
+        Key::ImportJsonInvalid => (
+            "{line}行目: JSONとして読み込めませんでした。",
+            "Line {line}: Could not parse as JSON.",
+        ),
+        Key::ImportJsonItem => ("{index}番目の項目", "Item {index}"),
+        Key::ImportJsonItemInvalid => (
+            "`word`と`read_as`を文字列で指定してください。",
+            "`word` and `read_as` must be strings.",
+        ),
+        Key::ImportCsvBadHeader => (
+            "1行目の見出しを`{header}`にしてください。",
+            "The first line must be the header `{header}`.",
+        ),
+        Key::ImportCsvLine => ("{line}行目", "Line {line}"),
+        Key::ImportCsvWrongColumns => (
+            "列の数を2にしてください。",
+            "Each line must have exactly 2 columns.",
+        ),
+        Key::ImportCsvUnquoted => (
+            "{line}行目: ダブルクォートを含む値は、全体をダブルクォートで囲んでください。",
+            "Line {line}: Values containing double quotes must be enclosed in double quotes.",
+        ),
+        Key::ImportCsvUnclosedQuote => (
+            "{line}行目: ダブルクォートが閉じられていません。",
+            "Line {line}: A double quote is not closed.",
+        ),
+        Key::ImportEntryEmpty => (
+            "語句と読み方を空にすることはできません。",
+            "The word and its reading cannot be empty.",
+        ),
+        Key::ImportEntryDuplicate => (
+            "同じ語句がファイル内ですでに指定されています。",
+            "The same word appears earlier in the file.",
+        ),
+        Key::ListMore => ("ほか{count}件", "{count} more"),
+        Key::FilterAdded => (
+            "{word}をフィルターに登録しました。",
+            "Added {word} to the filter.",
+        ),
+        Key::FilterWordExists => (
+            "すでに{word}はフィルターに登録されています。",
+            "{word} is already in the filter.",
+        ),
+        Key::FilterTooMany => (
+            "フィルターに登録できる語句は{max}個までです。",
+            "The filter can hold up to {max} words.",
+        ),
+        Key::FilterRemoved => (
+            "フィルターから{word}を削除しました。",
+            "Removed {word} from the filter.",
+        ),
+        Key::FilterWordNotFound => (
+            "{word}はフィルターに登録されていません。",
+            "{word} is not in the filter.",
+        ),
+        Key::ServerFallbackName => ("サーバー", "this server"),
+        Key::FilterTitle => ("🚫 {guild}のフィルター", "🚫 Filter of {guild}"),
+        Key::FilterEmpty => (
+            "登録されている語句はありません。",
+            "No words are registered.",
+        ),
+        Key::FilterModeFooter => ("モード: {mode}", "Mode: {mode}"),
+        Key::FilterModeChanged => (
+            "フィルターのモードを「{mode}」に変更しました。",
+            "Changed the filter mode to \"{mode}\".",
+        ),
+        Key::FilterModeDrop => ("読み上げない", "Do not read"),
+        Key::FilterModeBleep => ("「ピー」に置き換える", "Replace with a bleep"),
+        Key::AutoJoinEnabled => (
+            "メンバーが<#{voice}>に参加したときに自動で接続し、<#{text}>を読み上げるように設定しました。",
+            "The bot will join automatically when a member joins <#{voice}> and read <#{text}>.",
+        ),
+        Key::AutoJoinDisabled => ("自動接続を無効にしました。", "Disabled auto-join."),
+        Key::AutoJoinNeedsBoth => (
+            "自動接続を有効にするには、ボイスチャンネルとテキストチャンネルの両方を指定してください。",
+            "To enable auto-join, please specify both a voice channel and a text channel.",
+        ),
+        Key::ToggleReadReactions => ("リアクションの読み上げ", "reading reactions"),
+        Key::ToggleReadDeletions => ("メッセージの削除の読み上げ", "announcing deleted messages"),
+        Key::ToggleReadAltText => (
+            "添付ファイルの代替テキストの読み上げ",
+            "reading the alt text of attachments",
+        ),
+        Key::ToggleReadPolls => ("投票の読み上げ", "reading polls"),
+        Key::ToggleReadAuthorRole => ("送信者のロールの読み上げ", "reading the author's role"),
+        Key::ToggleShowDictReadings => (
+            "`/dict view`での読み方の表示",
+            "showing readings in `/dict view`",
+        ),
+        Key::ToggleReadScheduledEvents => (
+            "イベントの開始の読み上げ",
+            "announcing scheduled events",
+        ),
+        Key::ToggleNormalizeWidth => ("文字幅の統一", "normalizing character width"),
+        Key::ToggleReadVoiceChat => (
+            "ボイスチャンネルのチャットの読み上げ",
+            "reading the voice channel chat",
+        ),
+        Key::ToggleChime => ("チャイム", "the chime"),
+        Key::ToggleFollowUsers => ("メンバーの移動への追従", "following members between channels"),
+        Key::ToggleVoiceByUserId => (
+            "ユーザーIDによる声の割り当て",
+            "assigning voices by user ID",
+        ),
+        Key::RepeatThresholdOutOfRange => (
+            "文字数は{min}から{max}の範囲で指定してください。",
+            "The number of characters must be between {min} and {max}.",
+        ),
+        Key::ReadResponsesEnabled => (
+            "コマンドへの応答をボイスチャンネルで読み上げるように設定しました（エラーメッセージの読み上げ: {errors}）。",
+            "Command responses will be read aloud in the voice channel (error messages: {errors}).",
+        ),
+        Key::ReadResponsesDisabled => (
+            "コマンドへの応答を読み上げないように設定しました。",
+            "Command responses will no longer be read aloud.",
+        ),
+        Key::ToggleReadResponses => ("コマンドへの応答の読み上げ", "reading command responses"),
+        Key::ToggleReadErrorResponses => ("エラーメッセージの読み上げ", "reading error messages"),
+        Key::SettingReadResponses => (
+            "有効（エラーメッセージの読み上げ: {errors}）",
+            "Enabled (error messages: {errors})",
+        ),
+        Key::CollapseRepeatsDisabled => (
+            "同じ文字の繰り返しをまとめる機能を無効にしました。",
+            "Disabled collapsing repeated characters.",
+        ),
+        Key::FuriganaEnabled => (
+            "読み仮名の指定を有効にしました。{syntax}のように書かれた部分は、指定した読み仮名で読み上げます。",
+            "Enabled furigana. Text written like {syntax} will be read with the given reading.",
+        ),
+        Key::FuriganaDisabled => (
+            "読み仮名の指定を無効にしました。",
+            "Disabled furigana.",
+        ),
+        Key::FuriganaSyntaxBraces => ("`{漢字|かんじ}`", "`{漢字|かんじ}`"),
+        Key::FuriganaSyntaxRuby => ("`｜漢字《かんじ》`", "`｜漢字《かんじ》`"),
+        Key::SettingFurigana => ("有効（{syntax}）", "Enabled ({syntax})"),
+        Key::ToggleFurigana => ("読み仮名の指定", "furigana"),
+        Key::CollapseRepeatsEnabled => (
+            "同じ文字の繰り返しをまとめる機能を有効にしました。{threshold}文字を超えて続く文字をまとめます。",
+            "Enabled collapsing repeated characters. Runs longer than {threshold} characters will be collapsed.",
+        ),
+        Key::EmptyTextTooLong => (
+            "文言は{max}文字以内で指定してください。",
+            "The text must be {max} characters or fewer.",
+        ),
+        Key::EmptyTextSet => (
+            "読み上げる内容がないメッセージの代わりに「{placeholder}」と読み上げるように設定しました。",
+            "Messages with nothing to read will be read as \"{placeholder}\".",
+        ),
+        Key::IdleTimeoutSet => (
+            "{minutes}分間読み上げがなかった場合に、ボイスチャンネルから退出するように設定しました。",
+            "The bot will leave the voice channel after {minutes} minutes without reading.",
+        ),
+        Key::IdleTimeoutDisabled => (
+            "読み上げがなくても、ボイスチャンネルから自動で退出しないように設定しました。",
+            "The bot will no longer leave the voice channel when nothing is read.",
+        ),
+        Key::IdleTimeoutReset => (
+            "読み上げがない場合に退出するまでの時間を既定値（{timeout}）に戻しました。",
+            "Reset the time until leaving when nothing is read to the default ({timeout}).",
+        ),
+        Key::IdleTimeoutOutOfRange => (
+            "時間は0分から{max}分の範囲で指定してください。",
+            "Please specify between 0 and {max} minutes.",
+        ),
+        Key::IdleTimeoutNever => ("自動で退出しない", "never leave automatically"),
+        Key::IdleDisconnected => (
+            "{minutes}分間読み上げがなかったため、ボイスチャンネルから切断しました。",
+            "Disconnected from the voice channel because nothing was read for {minutes} minutes.",
+        ),
+        Key::Minutes => ("{minutes}分", "{minutes} minutes"),
+        Key::EmptyTextRemoved => (
+            "読み上げる内容がないメッセージを読み飛ばすように設定しました。",
+            "Messages with nothing to read will be skipped.",
+        ),
+        Key::FarewellSet => (
+            "ボイスチャンネルから退出する前に「{text}」と読み上げるように設定しました。",
+            "The bot will say \"{text}\" before leaving the voice channel.",
+        ),
+        Key::FarewellRemoved => (
+            "挨拶せずにボイスチャンネルから退出するように設定しました。",
+            "The bot will leave the voice channel without a farewell.",
+        ),
+        Key::SettingsViewTitle => ("⚙️ {guild}の設定", "⚙️ Settings of {guild}"),
+        Key::SettingsViewFooter => (
+            "各項目は同じ名前のサブコマンドで変更できます。有効・無効と閾値は/settings setと/settings resetでも変更できます。",
+            "Change each setting with the subcommand of the same name. Toggles and the threshold can also be changed with /settings set and /settings reset.",
+        ),
+        Key::SettingEnabled => ("有効", "Enabled"),
+        Key::SettingDisabled => ("無効", "Disabled"),
+        Key::SettingAutoJoin => (
+            "<#{voice}>に参加したときに<#{text}>を読み上げる",
+            "Read <#{text}> when a member joins <#{voice}>",
+        ),
+        Key::SettingCollapseRepeats => (
+            "有効（{threshold}文字を超える繰り返しをまとめる）",
+            "Enabled (collapse more than {threshold} repeats)",
+        ),
+        Key::SettingSkip => ("読み飛ばす", "Skip"),
+        Key::SettingReadAll => ("すべて読み上げる", "Read all messages"),
+        Key::SettingLanguageFollow => (
+            "Discordの言語設定に合わせる",
+            "Follow each member's Discord language",
+        ),
+        Key::SettingVoiceRange => (
+            "話速 {speed_min}〜{speed_max}、音高 {pitch_min}〜{pitch_max}",
+            "Speed {speed_min}–{speed_max}, pitch {pitch_min}–{pitch_max}",
+        ),
+        Key::SettingCollapseRepeatsLabel => ("繰り返しをまとめる設定", "collapsing repeats"),
+        Key::SettingRepeatThresholdLabel => (
+            "繰り返しをまとめる閾値",
+            "the repeat threshold",
+        ),
+        Key::SettingMaxWordsLabel => ("読み上げる最大の単語数", "the maximum words to read"),
+        Key::SettingMessageTemplateLabel => ("メッセージの読み上げ方", "the message template"),
+        Key::SettingIdleTimeoutLabel => (
+            "自動で退出するまでの時間（分）",
+            "the idle timeout (minutes)",
+        ),
+        Key::SettingFuriganaSyntaxLabel => ("読み仮名を指定する書き方", "the furigana syntax"),
+        Key::SettingSpeechProviderLabel => ("音声合成のエンジン", "the speech engine"),
+        Key::SettingNoticeChannelLabel => (
+            "お知らせを送信するチャンネル",
+            "the notice channel",
+        ),
+        Key::SettingNoticeChannelDefault => (
+            "読み上げ中のチャンネル",
+            "The channel being read",
+        ),
+        Key::SettingNotSet => ("未設定", "not set"),
+        Key::SettingNotBool => (
+            "{label}には`true`か`false`を指定してください。",
+            "Please specify `true` or `false` for {label}.",
+        ),
+        Key::SettingNotInt => (
+            "{label}には整数を指定してください。",
+            "Please specify an integer for {label}.",
+        ),
+        Key::SettingOutOfRange => (
+            "{label}には{min}から{max}までの整数を指定してください。",
+            "Please specify an integer from {min} to {max} for {label}.",
+        ),
+        Key::SettingTooLong => (
+            "{label}は{max}文字以内で指定してください。",
+            "Please specify {label} in {max} characters or less.",
+        ),
+        Key::SettingMissingPlaceholder => (
+            "{label}には`{placeholder}`をちょうど1回含めてください。",
+            "Please include `{placeholder}` exactly once in {label}.",
+        ),
+        Key::SettingNotChoice => (
+            "{label}には{choices}のいずれかを指定してください。",
+            "Please specify one of {choices} for {label}.",
+        ),
+        Key::SettingNotChannel => (
+            "{label}にはチャンネルのメンション（`#チャンネル`）かIDを指定してください。",
+            "Please specify a channel mention (`#channel`) or ID for {label}.",
+        ),
+        Key::SettingUpdated => (
+            "{label}を「{value}」にしました。",
+            "Set {label} to \"{value}\".",
+        ),
+        Key::SettingReset => (
+            "{label}を既定値（{value}）に戻しました。",
+            "Reset {label} to the default ({value}).",
+        ),
+        Key::MentionOnlyChanged => (
+            "メンションだけのメッセージの扱いを「{mode}」に変更しました。",
+            "Changed how messages with only mentions are handled to \"{mode}\".",
+        ),
+        Key::MentionOnlyRead => ("名前を読み上げる", "Read the names"),
+        Key::MentionOnlySkip => ("読み上げない", "Do not read"),
+        Key::MentionOnlyNote => ("「メンション」と読み上げる", "Read \"mention\""),
+        Key::ReadPrefixTooLong => (
+            "合図は{max}文字以内で指定してください。",
+            "The prefix must be {max} characters or fewer.",
+        ),
+        Key::ReadPrefixSet => (
+            "{prefix}で始まるメッセージのみを読み上げるように設定しました。",
+            "Only messages starting with {prefix} will be read.",
+        ),
+        Key::ReadPrefixRemoved => (
+            "合図に関わらず、メッセージを読み上げるように設定しました。",
+            "All messages will be read regardless of the prefix.",
+        ),
+        Key::SpeechProviderSet => (
+            "音声合成のエンジンを{provider}に変更しました。",
+            "Changed the speech synthesis engine to {provider}.",
+        ),
+        Key::SpeechProviderUnavailable => (
+            "音声合成のエンジン「{name}」は使用できません。使用できるエンジン: {list}",
+            "The speech synthesis engine \"{name}\" is not available. Available engines: {list}",
+        ),
+        Key::VoiceResetForProvider => (
+            "設定されていた声は新しいエンジンで使用できないため、プリセット{id}に変更しました。ほかのメンバーの声も、次に読み上げるときに変更されます。",
+            "Your voice is not available with the new engine, so it was changed to preset {id}. Other members' voices will be changed the next time they are read.",
+        ),
+        Key::ScaleOutOfRange => (
+            "{kind}は{min}から{max}の範囲で指定してください。",
+            "{kind} must be between {min} and {max}.",
+        ),
+        Key::ScaleMinAboveMax => (
+            "{kind}の下限は上限以下にしてください。",
+            "The minimum of {kind} must not exceed the maximum.",
+        ),
+        Key::VoiceRangeSet => (
+            "メンバーが設定できる範囲を、話速は{speed_min}から{speed_max}、音高は{pitch_min}から{pitch_max}にしました。",
+            "Members can now set speed from {speed_min} to {speed_max} and pitch from {pitch_min} to {pitch_max}.",
+        ),
+        Key::SystemVoiceReset => (
+            "お知らせを読み上げる声を既定の声に戻しました。",
+            "Reset the announcement voice to the default.",
+        ),
+        Key::SystemVoiceSet => (
+            "お知らせを読み上げる声を設定しました（{summary}）。",
+            "Updated the announcement voice ({summary}).",
+        ),
+        Key::LanguageSet => (
+            "応答の言語を{language}にしました。",
+            "Responses will now be in {language}.",
+        ),
+        Key::LanguageReset => (
+            "応答の言語を、メンバーのDiscordの言語設定に合わせるようにしました。",
+            "Responses will now follow each member's Discord language.",
+        ),
+        Key::LanguageJapanese => ("日本語", "Japanese"),
+        Key::LanguageEnglish => ("英語", "English"),
+        Key::SpecifyBot => ("Botを指定してください。", "Please specify a bot."),
+        Key::EmbedBotAdded => (
+            "<@{bot}>の埋め込みを読み上げるように設定しました。",
+            "Embeds from <@{bot}> will be read.",
+        ),
+        Key::EmbedBotExists => (
+            "すでに<@{bot}>の埋め込みを読み上げるように設定されています。",
+            "Embeds from <@{bot}> are already being read.",
+        ),
+        Key::EmbedBotRemoved => (
+            "<@{bot}>の埋め込みを読み上げないように設定しました。",
+            "Embeds from <@{bot}> will no longer be read.",
+        ),
+        Key::EmbedBotNotFound => (
+            "<@{bot}>の埋め込みを読み上げるようには設定されていません。",
+            "Embeds from <@{bot}> are not being read.",
+        ),
+        Key::EmbedBotListEmpty => (
+            "埋め込みを読み上げるBotは設定されていません。",
+            "No bots have their embeds read.",
+        ),
+        Key::EmbedBotList => (
+            "埋め込みを読み上げるBot: {bots}",
+            "Bots whose embeds are read: {bots}",
+        ),
+        Key::RateLimited => (
+            "操作が多すぎます。少し待ってからお試しください。",
+            "Too many requests. Please wait a moment and try again.",
+        ),
+        Key::PermissionDenied => (
+            "`/{command}`は{requirement}のみが使えます。",
+            "`/{command}` can only be used by {requirement}.",
+        ),
+        Key::RequirementEveryone => ("すべてのメンバー", "everyone"),
+        Key::RequirementInVoiceChannel => (
+            "Botと同じボイスチャンネルに接続しているメンバー",
+            "members in the same voice channel as the bot",
+        ),
+        Key::RequirementRole => ("<@&{role}>のロールを持つメンバー", "members with <@&{role}>"),
+        Key::RequirementManageGuild => (
+            "「サーバー管理」の権限を持つメンバー",
+            "members with the \"Manage Server\" permission",
+        ),
+        Key::PermissionSet => (
+            "`/{command}`を{requirement}が使えるように設定しました。",
+            "`/{command}` can now be used by {requirement}.",
+        ),
+        Key::PermissionsTitle => (
+            "🔐 {guild}でコマンドを使えるメンバー",
+            "🔐 Who can use commands in {guild}",
+        ),
+        Key::PermissionsFooter => (
+            "「サーバー管理」の権限を持つメンバーは、設定にかかわらずすべてのコマンドを使えます。",
+            "Members with the \"Manage Server\" permission can use every command regardless of these settings.",
+        ),
+        Key::UsageLimitSet => (
+            "{guild}が1か月に読み上げられる文字数の上限を{limit}文字に設定しました。",
+            "Set the monthly character limit of {guild} to {limit}.",
+        ),
+        Key::UsageLimitRemoved => (
+            "{guild}が1か月に読み上げられる文字数の上限をなくしました。",
+            "Removed the monthly character limit of {guild}.",
+        ),
+        Key::UsageTitle => ("{month}の読み上げの文字数", "Characters read in {month}"),
+        Key::UsageGuild => ("このサーバー", "This server"),
+        Key::UsageLimit => ("上限", "Limit"),
+        Key::UsageNoLimit => ("なし", "None"),
+        Key::UsageTotal => ("すべてのサーバーの合計", "All servers"),
+        Key::UsageTopGuilds => ("文字数の多いサーバー", "Top servers"),
+        Key::UsageChars => ("{chars}文字", "{chars} characters"),
+        Key::OwnerOnly => (
+            "このコマンドはBotの所有者のみが使えます。",
+            "This command can only be used by the bot owner.",
+        ),
+        Key::AdminStatusTitle => ("🩺 稼働状況", "🩺 Health"),
+        Key::SpeechEngines => ("音声合成エンジン", "Speech engines"),
+        Key::AudioCache => ("音声のキャッシュ", "Audio cache"),
+        Key::AudioCacheStats => (
+            "保存済みの音声を使用: {hits}回、新たに合成: {misses}回、削除: {evictions}件\n{size} / {max_size} MiB",
+            "Hits: {hits}, misses: {misses}, evictions: {evictions}\n{size} / {max_size} MiB",
+        ),
+        Key::SpeechEngineAvailable => (
+            "{name}: {count}回",
+            "{name}: {count} times",
+        ),
+        Key::SpeechEngineUnavailable => (
+            "{name}: {count}回（失敗したため後回しにしています）",
+            "{name}: {count} times (skipped after a failure)",
+        ),
+        Key::InvalidGuildId => (
+            "{id}はサーバーのIDとして正しくありません。",
+            "{id} is not a valid server ID.",
+        ),
+        Key::InspectNotConnected => (
+            "{guild}ではどのボイスチャンネルにも接続していません。",
+            "Not connected to any voice channel in {guild}.",
+        ),
+        Key::InspectTitle => ("🔍 {guild}での読み上げの状況", "🔍 Reading status in {guild}"),
+        Key::ConnectionState => ("接続の状態", "Connection state"),
+        Key::ConnectionStateConnected => ("接続中", "Connected"),
+        Key::ConnectionStateConnectedTo => ("接続中（{server}）", "Connected ({server})"),
+        Key::ConnectionStateDisconnected => (
+            "切断されています（再接続を待っている可能性があります）",
+            "Disconnected (possibly waiting to reconnect)",
+        ),
+        Key::LastMessageRead => ("最後に読み上げたメッセージ", "Last message read"),
+        Key::HealthOk => ("✅ 正常", "✅ OK"),
+        Key::HealthOkWithVersion => ("✅ 正常（バージョン {version}）", "✅ OK (version {version})"),
+        Key::HealthError => ("❌ エラー: {error}", "❌ Error: {error}"),
+        Key::HealthTimeout => ("❌ タイムアウト", "❌ Timed out"),
+        Key::ConnectedGuilds => ("接続中のサーバー", "Connected servers"),
+        Key::CheckError => ("エラー: {error}", "Error: {error}"),
+        Key::CheckTimeout => ("タイムアウト", "Timed out"),
+        Key::SkippedMessages => ("読み上げなかったメッセージ", "Skipped messages"),
+        Key::SkipOwnMessage => ("Koe自身のメッセージ", "Koe's own messages"),
+        Key::SkipSemicolonPrefix => (
+            "セミコロンで始まるメッセージ",
+            "Messages starting with a semicolon",
+        ),
+        Key::SkipMissingReadPrefix => (
+            "合図で始まらないメッセージ",
+            "Messages without the read prefix",
+        ),
+        Key::SkipEmptyText => ("読み上げる文字列が空", "Nothing left to read"),
+        Key::SkipFiltered => (
+            "フィルターで除去されたメッセージ",
+            "Messages removed by the filter",
+        ),
+        Key::SkipUsageLimit => (
+            "今月の文字数の上限に達していた",
+            "Monthly character limit reached",
+        ),
+        Key::VersionTitle => ("Koe {version}", "Koe {version}"),
+        Key::VersionCommit => ("コミット", "Commit"),
+        Key::VersionBuiltAt => ("ビルド日時", "Built at"),
+        Key::VersionLibraries => ("ライブラリ", "Libraries"),
+        Key::VersionUptime => ("稼働時間", "Uptime"),
+        Key::VersionGuilds => ("参加しているサーバー", "Servers"),
+        Key::VersionVoiceConnections => ("接続中のボイスチャンネル", "Voice connections"),
+        Key::Help => (
+            "詳しい使い方はこちらをご覧ください:\n<{url}>",
+            "See the user guide for details:\n<{url}>",
+        ),
+        Key::HelpCategoryConnection => ("🔊 接続", "🔊 Connection"),
+        Key::HelpCategoryDictionary => ("📖 辞書", "📖 Dictionary"),
+        Key::HelpCategoryVoice => ("🗣️ 声の設定", "🗣️ Voice"),
+        Key::HelpCategoryOther => ("⚙️ その他", "⚙️ Other"),
+        Key::HelpContinued => ("{category}（続き）", "{category} (continued)"),
+        Key::HelpOptions => ("オプション: {options}", "Options: {options}"),
+        Key::HelpRequiredOption => ("{name}（必須）", "{name} (required)"),
+        Key::HelpContextMenu => ("「{name}」（右クリックメニュー）", "\"{name}\" (context menu)"),
+        Key::PermissionsMissing => (
+            "<#{channel}>で読み上げるには、Botに{permissions}の権限が必要です。",
+            "The bot needs the {permissions} permission to read in <#{channel}>.",
+        ),
+        Key::PermissionConnect => ("接続", "Connect"),
+        Key::PermissionSpeak => ("発言", "Speak"),
+        Key::PermissionMuteMembers => ("メンバーをミュート", "Mute Members"),
+        Key::QuotedName => ("「{name}」", "\"{name}\""),
+        Key::ListSeparator => ("、", ", "),
+        Key::JoinMissingAccess => (
+            "Botがボイスチャンネルにアクセスする権限がありません。",
+            "The bot does not have permission to access the voice channel.",
+        ),
+        Key::JoinMissingPermissions => (
+            "Botにボイスチャンネルで話すための権限がありません。",
+            "The bot does not have permission to speak in the voice channel.",
+        ),
+        Key::JoinTimedOut => (
+            "ボイスチャンネルへの接続がタイムアウトしました。Botに「接続」の権限があるか確認してください。",
+            "Connecting to the voice channel timed out. Please check that the bot has the \"Connect\" permission.",
+        ),
+        Key::PresetNotFound => (
+            "プリセットID {id} は存在しません。使用できるプリセットは次のとおりです。\n{list}",
+            "Preset ID {id} does not exist. The available presets are:\n{list}",
+        ),
+        Key::PresetChoice => ("{name}（ID: {id}）", "{name} (ID: {id})"),
+        Key::LabeledValue => ("{label}: {value}", "{label}: {value}"),
+        Key::DurationSeconds => ("{secs}秒", "{secs}s"),
+        Key::DurationMinutes => ("{mins}分{secs}秒", "{mins}m {secs}s"),
+        Key::DurationHours => ("{hours}時間{mins}分", "{hours}h {mins}m"),
+        Key::InvokerOnly => (
+            "コマンドを送信したメンバーのみが操作できます。",
+            "Only the member who sent the command can use this.",
+        ),
+        Key::ComponentExpired => (
+            "操作できる期間を過ぎました。もう一度コマンドを送信してください。",
+            "This has expired. Please send the command again.",
+        ),
+        Key::ConfirmYes => ("はい", "Yes"),
+        Key::ConfirmNo => ("いいえ", "No"),
+        Key::ConfirmLeave => (
+            "まだ{count}件の読み上げが残っています。切断しますか？",
+            "There are still {count} messages waiting to be read. Disconnect anyway?",
+        ),
+        Key::ConfirmDictClear => (
+            "辞書に登録された{count}個の語句をすべて削除します。よろしいですか？この操作は取り消せません。",
+            "This will remove all {count} words from the dictionary. Are you sure? This cannot be undone.",
+        ),
+        Key::ConfirmCancelled => ("キャンセルしました。", "Cancelled."),
+        Key::ConfirmTimedOut => (
+            "確認の期限を過ぎたため、キャンセルしました。",
+            "Cancelled because no answer was given in time.",
+        ),
+        Key::VoiceChanged => (
+            "<@{user}>さんの声を`{voice}`に変更しました。",
+            "Changed the voice of <@{user}> to `{voice}`.",
+        ),
+    };
+
+    match lang {
+        Language::Japanese => ja,
+        Language::English => en,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn guild_setting_takes_precedence() {
+        for locale in ["ja", "en-US", "fr", ""] {
+            assert_eq!(
+                choose(Some(Language::English), locale),
+                Language::English,
+                "{}",
+                locale
+            );
+            assert_eq!(
+                choose(Some(Language::Japanese), locale),
+                Language::Japanese,
+                "{}",
+                locale
+            );
+        }
+    }
+
+    #[test]
+    fn auto_follows_locale() {
+        assert_eq!(choose(None, "ja"), Language::Japanese);
+        assert_eq!(choose(None, "en-US"), Language::English);
+        assert_eq!(choose(None, "en-GB"), Language::English);
+    }
+
+    #[test]
+    fn unsupported_locale_falls_back_to_japanese() {
+        for locale in ["fr", "zh-CN", "es-ES", ""] {
+            assert_eq!(choose(None, locale), Language::Japanese, "{}", locale);
+        }
+    }
+
+    #[test]
+    fn from_locale_matches_prefix() {
+        assert_eq!(from_locale("ja"), Some(Language::Japanese));
+        assert_eq!(from_locale("en-US"), Some(Language::English));
+        assert_eq!(from_locale("ko"), None);
+    }
+
+    #[test]
+    fn user_text_is_kept_verbatim() {
+        // 利用者が入力した語句は翻訳せず、`{`を含む場合もそのまま埋め込む
+        let word = "{read_as}こんにちは";
+        let read_as = "konnichiwa";
+        for lang in [Language::Japanese, Language::English] {
+            let text = format(
+                lang,
+                Key::DictAdded,
+                &[("word", &word), ("read_as", &read_as)],
+            );
+            assert!(text.contains(word), "{}", text);
+            assert!(text.contains(read_as), "{}", text);
+        }
+        assert_eq!(
+            format(
+                Language::English,
+                Key::DictAdded,
+                &[("word", &word), ("read_as", &read_as)]
+            ),
+            "Added {read_as}こんにちは to the dictionary, read as konnichiwa."
+        );
+    }
+
+    #[test]
+    fn unknown_placeholders_are_left_as_is() {
+        assert_eq!(
+            format(Language::English, Key::DictAdded, &[("word", &"語")]),
+            "Added 語 to the dictionary, read as {read_as}."
+        );
+    }
+}
@@ -0,0 +1,51 @@
+use crate::app_state;
+use anyhow::{Context as _, Result};
+use koe_db::guild_settings::{self, ChannelKey};
+use log::warn;
+use serenity::{
+    client::Context,
+    model::id::{ChannelId, GuildId},
+};
+
+/// 自動で退出したときなど、Botからサーバーへのお知らせを送信する
+/// `/settings set`で`notice_channel`が設定されている場合はそのチャンネルに、
+/// 未設定の場合は`fallback`（読み上げていたテキストチャンネル）に送信する
+pub async fn send(
+    ctx: &Context,
+    guild_id: GuildId,
+    fallback: ChannelId,
+    content: impl std::fmt::Display,
+) -> Result<()> {
+    let channel_id = match notice_channel(ctx, guild_id).await {
+        Ok(channel_id) => channel_id.unwrap_or(fallback),
+        Err(err) => {
+            // 設定を読み出せなくても、お知らせは届くようにする
+            warn!(
+                "Failed to get notice channel in guild {}: {:?}",
+                guild_id, err
+            );
+            fallback
+        }
+    };
+
+    channel_id
+        .say(&ctx.http, content)
+        .await
+        .context("Failed to send notice")?;
+    Ok(())
+}
+
+async fn notice_channel(ctx: &Context, guild_id: GuildId) -> Result<Option<ChannelId>> {
+    let state = app_state::get(ctx).await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+
+    let channel_id = guild_settings::get_channel(
+        &mut conn,
+        guild_settings::GetChannelOption {
+            guild_id: guild_id.into(),
+            key: ChannelKey::NoticeChannel,
+        },
+    )
+    .await?;
+    Ok(channel_id.map(ChannelId))
+}
@@ -0,0 +1,156 @@
+use dashmap::DashMap;
+use serenity::model::id::UserId;
+use std::{
+    collections::VecDeque,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// コマンドの実行回数を数える期間
+pub const COMMAND_RATE_LIMIT_WINDOW: Duration = Duration::from_secs(10);
+/// 設定や状態を変更するコマンドを、期間内に実行できる回数
+pub const MAX_MUTATIONS_PER_WINDOW: usize = 5;
+/// 表示するだけのコマンドを、期間内に実行できる回数
+pub const MAX_READS_PER_WINDOW: usize = 10;
+
+/// メンバーごと・コマンドごとに、直近の一定時間に実行できる回数を制限する
+/// 連打によってRedisやボイスゲートウェイへの操作が集中しないようにする
+pub struct CommandRateLimiter {
+    window: Duration,
+    /// メンバーとコマンドの名前ごとの、直近に実行した時刻
+    history: DashMap<(UserId, String), VecDeque<Instant>>,
+    /// 最後に古い履歴を削除した時刻
+    last_pruned: Mutex<Instant>,
+}
+
+impl CommandRateLimiter {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            history: DashMap::new(),
+            last_pruned: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// コマンドの実行を記録し、実行してよいかを返す
+    /// 直近の`window`の間にすでに`max`回実行している場合は記録せずに`false`を返す
+    /// `now`には実行された時刻を渡す（通常は[`Instant::now`]）
+    pub fn check(&self, user_id: UserId, command: &str, max: usize, now: Instant) -> bool {
+        self.prune(now);
+
+        let mut history = self
+            .history
+            .entry((user_id, command.to_string()))
+            .or_default();
+        while let Some(&oldest) = history.front() {
+            if now.duration_since(oldest) < self.window {
+                break;
+            }
+            history.pop_front();
+        }
+
+        if history.len() >= max {
+            return false;
+        }
+        history.push_back(now);
+        true
+    }
+
+    /// 操作をやめたメンバーの履歴が残り続けないよう、期間を過ぎた履歴を削除する
+    /// 全体を走査するため、前回の削除から`window`以上経過した場合のみ行う
+    fn prune(&self, now: Instant) {
+        {
+            let mut last_pruned = self.last_pruned.lock().unwrap();
+            if now.duration_since(*last_pruned) < self.window {
+                return;
+            }
+            *last_pruned = now;
+        }
+
+        self.history.retain(|_, history| {
+            history
+                .back()
+                .is_some_and(|&latest| now.duration_since(latest) < self.window)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW: Duration = Duration::from_secs(10);
+    const ALICE: UserId = UserId(1);
+    const BOB: UserId = UserId(2);
+
+    #[test]
+    fn limits_within_window() {
+        let limiter = CommandRateLimiter::new(WINDOW);
+        let start = Instant::now();
+
+        for i in 0..3 {
+            assert!(limiter.check(ALICE, "join", 3, start + Duration::from_secs(i)));
+        }
+        assert!(!limiter.check(ALICE, "join", 3, start + Duration::from_secs(3)));
+        // 拒否された実行は記録しない
+        assert_eq!(
+            limiter
+                .history
+                .get(&(ALICE, "join".to_string()))
+                .unwrap()
+                .len(),
+            3
+        );
+    }
+
+    #[test]
+    fn window_slides_at_boundary() {
+        let limiter = CommandRateLimiter::new(WINDOW);
+        let start = Instant::now();
+
+        assert!(limiter.check(ALICE, "join", 2, start));
+        assert!(limiter.check(ALICE, "join", 2, start + Duration::from_secs(5)));
+
+        // 最初の実行からちょうど`window`経過する直前は、まだ数えられている
+        let just_before = start + WINDOW - Duration::from_millis(1);
+        assert!(!limiter.check(ALICE, "join", 2, just_before));
+        // ちょうど`window`経過すると、最初の実行は数えられなくなる
+        assert!(limiter.check(ALICE, "join", 2, start + WINDOW));
+        // 2回目の実行はまだ数えられている
+        assert!(!limiter.check(ALICE, "join", 2, start + WINDOW + Duration::from_secs(1)));
+        assert!(limiter.check(ALICE, "join", 2, start + Duration::from_secs(15)));
+    }
+
+    #[test]
+    fn counts_per_user_and_command() {
+        let limiter = CommandRateLimiter::new(WINDOW);
+        let now = Instant::now();
+
+        assert!(limiter.check(ALICE, "join", 1, now));
+        assert!(!limiter.check(ALICE, "join", 1, now));
+        assert!(limiter.check(ALICE, "leave", 1, now));
+        assert!(limiter.check(BOB, "join", 1, now));
+        assert!(!limiter.check(BOB, "join", 1, now));
+    }
+
+    #[test]
+    fn prunes_inactive_users() {
+        let limiter = CommandRateLimiter::new(WINDOW);
+        let start = Instant::now();
+
+        assert!(limiter.check(ALICE, "join", 5, start));
+        assert!(limiter.check(BOB, "join", 5, start + Duration::from_secs(5)));
+        assert_eq!(limiter.history.len(), 2);
+
+        // ALICEの履歴は期間を過ぎたので削除され、BOBの履歴は残る
+        assert!(limiter.check(BOB, "leave", 5, start + Duration::from_secs(12)));
+        assert!(!limiter.history.contains_key(&(ALICE, "join".to_string())));
+        assert!(limiter.history.contains_key(&(BOB, "join".to_string())));
+
+        // 前回の削除から`window`経過するまでは走査しない
+        assert!(limiter.check(ALICE, "join", 5, start + Duration::from_secs(13)));
+        assert!(limiter.check(BOB, "skip", 5, start + Duration::from_secs(30)));
+        assert_eq!(limiter.history.len(), 1);
+        assert!(limiter.history.contains_key(&(BOB, "skip".to_string())));
+    }
+}
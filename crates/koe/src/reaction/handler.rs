@@ -0,0 +1,101 @@
+use crate::{announcement, app_state};
+use anyhow::Result;
+use koe_db::guild_settings::{self, BoolKey, GetBoolOption};
+use serenity::{
+    client::Context,
+    model::channel::{Reaction, ReactionType},
+};
+use std::time::{Duration, Instant, SystemTime};
+
+/// リアクションを読み上げた後、次のリアクションを読み上げるまでの最短の間隔
+const DEBOUNCE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// これより古いメッセージへのリアクションは読み上げない
+const MAX_MESSAGE_AGE: Duration = Duration::from_secs(60 * 60);
+
+pub async fn handle_add(ctx: &Context, reaction: &Reaction) -> Result<()> {
+    let guild_id = match reaction.guild_id {
+        Some(id) => id,
+        None => return Ok(()),
+    };
+    let user_id = match reaction.user_id {
+        Some(id) => id,
+        None => return Ok(()),
+    };
+
+    // Koe自身のリアクションは読み上げない
+    if user_id == ctx.cache.current_user_id() {
+        return Ok(());
+    }
+
+    if is_old_message(reaction) {
+        return Ok(());
+    }
+
+    let state = app_state::get(ctx).await?;
+    {
+        let guild_state = match state.connected_guild_states.get(&guild_id) {
+            Some(guild_state) => guild_state,
+            None => return Ok(()),
+        };
+        if !guild_state.is_bound(reaction.channel_id) {
+            return Ok(());
+        }
+        if let Some(announced_at) = guild_state.last_reaction_announced {
+            if announced_at.elapsed() < DEBOUNCE_INTERVAL {
+                return Ok(());
+            }
+        }
+    }
+
+    let mut conn = state.redis_client.get_async_connection().await?;
+    let enabled = guild_settings::get_bool(
+        &mut conn,
+        GetBoolOption {
+            guild_id: guild_id.into(),
+            key: BoolKey::ReadReactions,
+        },
+    )
+    .await?;
+    if !enabled {
+        return Ok(());
+    }
+
+    match state.connected_guild_states.get_mut(&guild_id) {
+        Some(mut guild_state) => guild_state.last_reaction_announced = Some(Instant::now()),
+        None => return Ok(()),
+    }
+
+    let user_name = match reaction.member.as_ref().and_then(|m| m.nick.clone()) {
+        Some(nick) => nick,
+        None => {
+            let user = user_id.to_user(ctx).await?;
+            user.nick_in(&ctx.http, guild_id)
+                .await
+                .unwrap_or_else(|| user.name.clone())
+        }
+    };
+    let emoji_name = match &reaction.emoji {
+        ReactionType::Unicode(emoji) => emoji.clone(),
+        ReactionType::Custom { name, .. } => name.clone().unwrap_or_else(|| "絵文字".to_string()),
+        _ => "絵文字".to_string(),
+    };
+
+    announcement::announce(
+        ctx,
+        guild_id,
+        format!("{}が{}をつけました", user_name, emoji_name),
+    )
+    .await?;
+
+    Ok(())
+}
+
+fn is_old_message(reaction: &Reaction) -> bool {
+    let created_at = reaction.message_id.created_at().unix_timestamp();
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64);
+
+    now - created_at > MAX_MESSAGE_AGE.as_secs() as i64
+}
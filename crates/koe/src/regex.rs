@@ -15,3 +15,24 @@ pub fn url_regex() -> &'static Regex {
 pub fn custom_emoji_regex() -> &'static Regex {
     regex!(r"<(:\w+:)\d+>")
 }
+
+pub fn mass_mention_regex() -> &'static Regex {
+    // `@hereです`のように日本語が続く場合も取り除けるよう、英数字との境界のみを判定する
+    regex!(r"@(?:everyone|here)(?-u:\b)")
+}
+
+pub fn mention_only_regex() -> &'static Regex {
+    regex!(r"^(?:\s*(?:<@[!&]?\d+>|<#\d+>|@everyone|@here))+\s*$")
+}
+
+pub fn furigana_braces_regex() -> &'static Regex {
+    regex!(r"\{(?P<base>[^{}|\n]+)\|(?P<reading>[^{}|\n]+)\}")
+}
+
+pub fn furigana_ruby_regex() -> &'static Regex {
+    regex!(r"[|｜](?P<base>[^|｜《》\n]+)《(?P<reading>[^|｜《》\n]+)》")
+}
+
+pub fn timestamp_regex() -> &'static Regex {
+    regex!(r"<t:(-?\d+)(?::([tTdDfFR]))?>")
+}
@@ -0,0 +1,53 @@
+use crate::{announcement, app_state};
+use anyhow::Result;
+use koe_db::guild_settings::{self, BoolKey, GetBoolOption};
+use serenity::{
+    client::Context,
+    model::guild::{ScheduledEvent, ScheduledEventStatus},
+};
+
+/// サーバーのイベントが始まったときに、イベントの名前を読み上げる
+/// 更新の通知には変更前の状態が含まれないため、読み上げ済みのイベントを覚えておき、開催中の編集では読み上げない
+pub async fn handle_update(ctx: &Context, event: &ScheduledEvent) -> Result<()> {
+    let state = app_state::get(ctx).await?;
+
+    if !matches!(event.status, ScheduledEventStatus::Active) {
+        state.active_scheduled_events.remove(&event.id);
+        return Ok(());
+    }
+    if !state.active_scheduled_events.insert(event.id) {
+        return Ok(());
+    }
+
+    if !state.connected_guild_states.contains_key(&event.guild_id) {
+        return Ok(());
+    }
+
+    let mut conn = state.redis_client.get_async_connection().await?;
+    let enabled = guild_settings::get_bool(
+        &mut conn,
+        GetBoolOption {
+            guild_id: event.guild_id.into(),
+            key: BoolKey::ReadScheduledEvents,
+        },
+    )
+    .await?;
+    if !enabled {
+        return Ok(());
+    }
+
+    // イベントの名前には、読み上げと同じように辞書が適用される
+    announcement::announce(
+        ctx,
+        event.guild_id,
+        format!("イベントが始まりました: {}", event.name),
+    )
+    .await
+}
+
+/// 削除されたイベントを、読み上げ済みのイベントから取り除く
+pub async fn handle_delete(ctx: &Context, event: &ScheduledEvent) -> Result<()> {
+    let state = app_state::get(ctx).await?;
+    state.active_scheduled_events.remove(&event.id);
+    Ok(())
+}
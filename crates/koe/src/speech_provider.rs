@@ -0,0 +1,205 @@
+use crate::app_state::AppState;
+use anyhow::Result;
+use koe_db::{
+    guild_settings::{self, ChoiceKey},
+    redis::aio::Connection,
+    voice::{self, VoiceSettings},
+};
+use koe_speech::{
+    provider::{ProviderKind, SpeechProvider},
+    voicevox::Preset,
+};
+use log::warn;
+use serenity::model::id::{GuildId, UserId};
+use std::sync::Arc;
+
+/// サーバーで選択された音声合成のエンジンを返す
+/// 選択されていない場合や、選択されたエンジンが設定ファイルから削除された場合は既定のエンジンを返す
+pub async fn for_guild(
+    state: &AppState,
+    conn: &mut Connection,
+    guild_id: GuildId,
+) -> Result<(ProviderKind, Arc<dyn SpeechProvider>)> {
+    let selected = guild_settings::get_choice(
+        conn,
+        guild_settings::GetChoiceOption {
+            guild_id: guild_id.into(),
+            key: ChoiceKey::SpeechProvider,
+        },
+    )
+    .await?;
+    let selected = selected.as_deref().and_then(ProviderKind::parse);
+
+    Ok(state.speech_providers.resolve(selected))
+}
+
+/// メンバーの読み上げに使うエンジンを返す
+/// `/voice language`で言語が設定されていて、サーバーで選択されたエンジンがその言語を読み上げられない場合は、
+/// その言語を読み上げられる最初のエンジンを使う
+/// 言語が未設定の場合や、設定ファイルの変更でその言語を読み上げられるエンジンがなくなった場合は、
+/// サーバーで選択されたエンジンを使う（`/voice info`でメンバーに知らせる）
+pub async fn for_member(
+    state: &AppState,
+    conn: &mut Connection,
+    guild_id: GuildId,
+    user_id: UserId,
+) -> Result<(ProviderKind, Arc<dyn SpeechProvider>)> {
+    let language = voice::get_language(
+        conn,
+        voice::GetLanguageOption {
+            guild_id: guild_id.into(),
+            user_id: user_id.into(),
+        },
+    )
+    .await?;
+
+    let (kind, provider) = for_guild(state, conn, guild_id).await?;
+    match language {
+        Some(language) if provider.language() != language => {
+            match state.speech_providers.find_language(&language) {
+                Some(found) => Ok(found),
+                None => {
+                    warn!(
+                        "No speech provider supports language {} set by user {} in guild {}",
+                        language, user_id, guild_id
+                    );
+                    Ok((kind, provider))
+                }
+            }
+        }
+        _ => Ok((kind, provider)),
+    }
+}
+
+/// メンバーの読み上げに使うエンジンと、その声の一覧を返す
+pub async fn list_member_voices(
+    state: &AppState,
+    conn: &mut Connection,
+    guild_id: GuildId,
+    user_id: UserId,
+) -> Result<(ProviderKind, Vec<Preset>)> {
+    let (kind, provider) = for_member(state, conn, guild_id, user_id).await?;
+    Ok((kind, provider.list_voices().await?))
+}
+
+/// メンバーに設定されている声のうち、`kind`のエンジンで使えるプリセットのIDを返す
+/// プリセットIDはエンジンごとに1から割り当てるため、声を設定したときのエンジンが異なる場合は、同じIDがあっても使えないものとして扱う
+/// エンジンが保存されていない声は、エンジンを選べるようになる前に設定されたものなので、既定のエンジン（`default_kind`）の声として扱う
+pub fn usable_preset_id(
+    settings: &VoiceSettings,
+    kind: ProviderKind,
+    default_kind: ProviderKind,
+    available_preset_ids: &[i64],
+) -> Option<i64> {
+    let preset_id = settings.preset_id?;
+    let set_with = match settings.provider.as_deref() {
+        Some(provider) => ProviderKind::parse(provider)?,
+        None => default_kind,
+    };
+    (set_with == kind && available_preset_ids.contains(&preset_id)).then_some(preset_id)
+}
+
+/// エンジンの声の一覧を、オートコンプリートなどのためにキャッシュして返す
+pub async fn cached_voices(
+    state: &AppState,
+    kind: ProviderKind,
+    provider: Arc<dyn SpeechProvider>,
+) -> Result<Arc<Vec<Preset>>> {
+    state
+        .preset_cache
+        .get_or_try_insert_with(kind, || async {
+            Ok(Arc::new(provider.list_voices().await?))
+        })
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(preset_id: Option<i64>, provider: Option<&str>) -> VoiceSettings {
+        VoiceSettings {
+            preset_id,
+            provider: provider.map(|x| x.to_string()),
+            speed_scale: None,
+            pitch_scale: None,
+        }
+    }
+
+    #[test]
+    fn preset_is_usable_only_with_the_engine_it_was_set_with() {
+        // どちらのエンジンにもプリセット1から3がある
+        let available = [1, 2, 3];
+        let voicevox = settings(Some(2), Some("voicevox"));
+
+        assert_eq!(
+            usable_preset_id(
+                &voicevox,
+                ProviderKind::Voicevox,
+                ProviderKind::Voicevox,
+                &available
+            ),
+            Some(2)
+        );
+        assert_eq!(
+            usable_preset_id(
+                &voicevox,
+                ProviderKind::Polly,
+                ProviderKind::Voicevox,
+                &available
+            ),
+            None
+        );
+        assert_eq!(
+            usable_preset_id(
+                &settings(Some(4), Some("voicevox")),
+                ProviderKind::Voicevox,
+                ProviderKind::Voicevox,
+                &available
+            ),
+            None
+        );
+        assert_eq!(
+            usable_preset_id(
+                &settings(Some(2), Some("unknown")),
+                ProviderKind::Voicevox,
+                ProviderKind::Voicevox,
+                &available
+            ),
+            None
+        );
+        assert_eq!(
+            usable_preset_id(
+                &settings(None, None),
+                ProviderKind::Voicevox,
+                ProviderKind::Voicevox,
+                &available
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn preset_without_engine_belongs_to_the_default_engine() {
+        let legacy = settings(Some(2), None);
+
+        assert_eq!(
+            usable_preset_id(
+                &legacy,
+                ProviderKind::Voicevox,
+                ProviderKind::Voicevox,
+                &[1, 2]
+            ),
+            Some(2)
+        );
+        assert_eq!(
+            usable_preset_id(
+                &legacy,
+                ProviderKind::Polly,
+                ProviderKind::Voicevox,
+                &[1, 2]
+            ),
+            None
+        );
+    }
+}
@@ -0,0 +1,667 @@
+mod sentence;
+pub mod sequencer;
+
+use crate::{
+    app_state::{self, AppState},
+    default_voice,
+    message::read::preprocess_text,
+    speech_provider, usage,
+};
+use anyhow::{anyhow, Context as _, Result};
+use koe_call::VoiceBackend;
+use koe_db::{redis::aio::Connection, system_voice, voice};
+use koe_speech::{
+    provider::{ProviderKind, SpeechProvider},
+    speech::{PresetId, SpeechRequest},
+};
+use log::{debug, info, warn};
+use sequencer::Ticket;
+use serenity::{
+    client::Context,
+    model::id::{GuildId, UserId},
+};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::sync::mpsc;
+
+/// 先に受け付けた読み上げがキューに追加されるのを待つ最大の時間
+/// 音声合成が応答しない場合でも、これを過ぎると順番を待たずに追加する
+const MAX_TURN_WAIT: Duration = Duration::from_secs(30);
+
+/// 読み上げに使う声
+#[derive(Debug, Clone, Copy)]
+pub enum Voice {
+    /// メンバーが設定した声、未設定の場合は自動で割り当てる
+    Member(UserId),
+    /// サーバーのシステム音声
+    System,
+}
+
+#[derive(Debug, Clone)]
+pub struct EnqueueOption {
+    pub guild_id: GuildId,
+    pub text: String,
+    pub voice: Voice,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnqueueResponse {
+    Enqueued,
+    /// 辞書やフィルターを適用した結果、読み上げる内容が残らなかった
+    Empty,
+    /// サーバーの今月の文字数が上限に達していた
+    LimitReached,
+}
+
+/// 文章に辞書とフィルターを適用し、指定された声で読み上げキューに追加する
+pub async fn enqueue(ctx: &Context, option: EnqueueOption) -> Result<EnqueueResponse> {
+    let state = app_state::get(ctx).await?;
+    let ticket = issue_ticket(&state, option.guild_id);
+    let mut conn = state.redis_client.get_async_connection().await?;
+
+    let author_id = author_id(option.voice);
+    let text = preprocess_text(ctx, &mut conn, option.guild_id, author_id, &option.text).await?;
+    if text.trim().is_empty() {
+        return Ok(EnqueueResponse::Empty);
+    }
+
+    let volume_gain = author_id.and_then(|user_id| volume_boost(&state, option.guild_id, user_id));
+    let pushed = push(
+        ctx,
+        &mut conn,
+        option.guild_id,
+        text,
+        option.voice,
+        volume_gain,
+        &ticket,
+    )
+    .await?;
+    if !pushed {
+        return Ok(EnqueueResponse::LimitReached);
+    }
+
+    if let Some(mut guild_state) = state.connected_guild_states.get_mut(&option.guild_id) {
+        guild_state.last_activity = Instant::now();
+    }
+
+    Ok(EnqueueResponse::Enqueued)
+}
+
+/// 読み上げを受け付けた順番を表す番号を発行する
+/// 受け付けた時点で発行し、[`push`]に渡すことで、音声合成にかかる時間に関わらず受け付けた順に読み上げる
+pub fn issue_ticket(state: &AppState, guild_id: GuildId) -> Ticket {
+    match state.connected_guild_states.get(&guild_id) {
+        Some(guild_state) => guild_state.speech_sequencer.issue(),
+        None => Ticket::unsequenced(),
+    }
+}
+
+/// 変換済みの文章を、指定された声で音声に変換して読み上げキューに追加する
+/// 音声合成は並行して行い、キューへの追加は`ticket`の順番が来るまで待つ
+/// 複数の文からなる文章は、エンジンが対応していれば文ごとに合成し、先頭の文を再生している間に続きを合成する
+/// `KOE_PRINT_SPEECH`が設定されている場合は、音声に変換せずに文章をログに出力する
+/// 文字数の上限に達しているために読み上げなかった場合は`false`を返す
+pub async fn push(
+    ctx: &Context,
+    conn: &mut Connection,
+    guild_id: GuildId,
+    text: String,
+    voice: Voice,
+    volume_gain: Option<f64>,
+    ticket: &Ticket,
+) -> Result<bool> {
+    let state = app_state::get(ctx).await?;
+    if state.print_speech {
+        wait_turn(guild_id, ticket).await;
+        info!("Speech in guild {} ({:?}): {}", guild_id, voice, text);
+        return Ok(true);
+    }
+
+    // 文字数の上限に達している場合は、音声合成せずに読み飛ばす
+    if !usage::check(ctx, &state, conn, guild_id).await? {
+        return Ok(false);
+    }
+
+    let (kind, provider) = select_provider(&state, conn, guild_id, voice).await?;
+    let request = build_request(
+        &state,
+        conn,
+        guild_id,
+        (kind, &*provider),
+        text.clone(),
+        voice,
+        volume_gain,
+    )
+    .await?;
+    let output = Output {
+        provider: &provider,
+        backend: &*state.voice_backend,
+        max_duration: state.max_speech_duration,
+    };
+    output.speak(guild_id, request, ticket).await?;
+
+    usage::record(&state, conn, guild_id, &text).await?;
+    Ok(true)
+}
+
+/// 音声合成と再生を行う先
+/// Discordに接続せずに読み上げの順番などを確かめられるよう、[`Context`]から切り離している
+struct Output<'a> {
+    provider: &'a Arc<dyn SpeechProvider>,
+    backend: &'a dyn VoiceBackend,
+    /// 1件の読み上げを再生する最大の時間
+    max_duration: Option<Duration>,
+}
+
+impl Output<'_> {
+    /// 音声合成を行い、`ticket`の順番が来たら読み上げキューに追加する
+    async fn speak(
+        &self,
+        guild_id: GuildId,
+        request: SpeechRequest,
+        ticket: &Ticket,
+    ) -> Result<()> {
+        let started_at = Instant::now();
+
+        let sentences = sentence::split(&request.text);
+        if sentences.len() > 1 && self.provider.supports_streaming() {
+            return self
+                .speak_stream(guild_id, request, sentences, ticket, started_at)
+                .await;
+        }
+
+        let raw_audio = synthesize_request(&**self.provider, request).await?;
+        debug!(
+            "Time to first audio in guild {}: {:?} (buffered)",
+            guild_id,
+            started_at.elapsed()
+        );
+        wait_turn(guild_id, ticket).await;
+        self.backend
+            .enqueue(guild_id.into(), raw_audio, self.max_duration)
+            .await?;
+
+        Ok(())
+    }
+
+    /// 文ごとに音声合成を行い、合成できた文から順に読み上げキューに追加する
+    /// 先頭の文の合成に失敗した場合はエラーを返し、2文目以降の合成に失敗した場合はそこで読み上げを打ち切る
+    /// スキップされた場合は、続きの文の合成をやめる
+    async fn speak_stream(
+        &self,
+        guild_id: GuildId,
+        request: SpeechRequest,
+        sentences: Vec<String>,
+        ticket: &Ticket,
+        started_at: Instant,
+    ) -> Result<()> {
+        let sentence_count = sentences.len();
+        let mut sentences = sentences.into_iter();
+        let first_sentence = sentences.next().unwrap_or_default();
+
+        let first_audio = synthesize_request(
+            &**self.provider,
+            SpeechRequest {
+                text: first_sentence,
+                ..request.clone()
+            },
+        )
+        .await?;
+        debug!(
+            "Time to first audio in guild {}: {:?} (streaming, {} sentences)",
+            guild_id,
+            started_at.elapsed(),
+            sentence_count
+        );
+
+        // 再生より先に合成しすぎないよう、キューに追加されていない音声は1つまでにする
+        let (tx, rx) = mpsc::channel(1);
+        tx.send(first_audio).await?;
+
+        let provider = self.provider.clone();
+        tokio::spawn(async move {
+            for sentence in sentences {
+                let audio = synthesize_request(
+                    &*provider,
+                    SpeechRequest {
+                        text: sentence,
+                        ..request.clone()
+                    },
+                )
+                .await;
+                match audio {
+                    Ok(audio) => {
+                        if tx.send(audio).await.is_err() {
+                            // スキップされたか、キューへの追加に失敗した
+                            break;
+                        }
+                    }
+                    Err(err) => {
+                        warn!(
+                            "Failed to synthesize the rest of speech in guild {}: {:?}",
+                            guild_id, err
+                        );
+                        break;
+                    }
+                }
+            }
+        });
+
+        wait_turn(guild_id, ticket).await;
+        self.backend
+            .enqueue_stream(guild_id.into(), rx, self.max_duration)
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// 文章に辞書とフィルターを適用し、指定された声で音声に変換する
+/// 読み上げる内容が残らなかった場合、文字数の上限に達している場合と、`KOE_PRINT_SPEECH`が設定されている場合は[`None`]を返す
+pub async fn prepare(
+    ctx: &Context,
+    guild_id: GuildId,
+    text: &str,
+    voice: Voice,
+) -> Result<Option<Vec<u8>>> {
+    let state = app_state::get(ctx).await?;
+    let mut conn = state.redis_client.get_async_connection().await?;
+
+    let author_id = author_id(voice);
+    let text = preprocess_text(ctx, &mut conn, guild_id, author_id, text).await?;
+    if text.trim().is_empty() {
+        return Ok(None);
+    }
+    if state.print_speech {
+        info!("Speech in guild {} ({:?}): {}", guild_id, voice, text);
+        return Ok(None);
+    }
+
+    if !usage::check(ctx, &state, &mut conn, guild_id).await? {
+        return Ok(None);
+    }
+
+    let volume_gain = author_id.and_then(|user_id| volume_boost(&state, guild_id, user_id));
+    let raw_audio = synthesize(ctx, &mut conn, guild_id, text.clone(), voice, volume_gain).await?;
+    usage::record(&state, &mut conn, guild_id, &text).await?;
+    Ok(Some(raw_audio))
+}
+
+/// 先に受け付けた読み上げがキューに追加されるのを待つ
+/// 待ち時間を過ぎた場合は、先に受け付けた読み上げを待たずに追加し、後に受け付けた読み上げも待たせない
+async fn wait_turn(guild_id: GuildId, ticket: &Ticket) {
+    if !ticket.wait_turn_timeout(MAX_TURN_WAIT).await {
+        warn!(
+            "Timed out waiting for earlier speech in guild {}, skipping its turn",
+            guild_id
+        );
+    }
+}
+
+fn author_id(voice: Voice) -> Option<UserId> {
+    match voice {
+        Voice::Member(user_id) => Some(user_id),
+        Voice::System => None,
+    }
+}
+
+/// `/voice boost`で設定された、メンバーの音量の倍率を返す
+fn volume_boost(state: &AppState, guild_id: GuildId, user_id: UserId) -> Option<f64> {
+    state
+        .connected_guild_states
+        .get(&guild_id)
+        .and_then(|guild_state| guild_state.volume_boosts.get(&user_id).copied())
+}
+
+/// 変換済みの文章を、指定された声で音声に変換する
+/// `volume_gain`はプリセットの音量にかける倍率
+async fn synthesize(
+    ctx: &Context,
+    conn: &mut Connection,
+    guild_id: GuildId,
+    text: String,
+    voice: Voice,
+    volume_gain: Option<f64>,
+) -> Result<Vec<u8>> {
+    let state = app_state::get(ctx).await?;
+    let (kind, provider) = select_provider(&state, conn, guild_id, voice).await?;
+    let request = build_request(
+        &state,
+        conn,
+        guild_id,
+        (kind, &*provider),
+        text,
+        voice,
+        volume_gain,
+    )
+    .await?;
+    synthesize_request(&*provider, request).await
+}
+
+/// 読み上げに使うエンジンを返す
+/// メンバーの声は`/voice language`の設定に従い、システム音声はサーバーで選択されたエンジンを使う
+async fn select_provider(
+    state: &AppState,
+    conn: &mut Connection,
+    guild_id: GuildId,
+    voice: Voice,
+) -> Result<(ProviderKind, Arc<dyn SpeechProvider>)> {
+    match voice {
+        Voice::Member(user_id) => speech_provider::for_member(state, conn, guild_id, user_id).await,
+        Voice::System => speech_provider::for_guild(state, conn, guild_id).await,
+    }
+}
+
+/// 指定された声で文章を読み上げるための、音声合成のリクエストを組み立てる
+/// 声は`provider`のエンジンで使えるプリセットの中から選ぶ
+async fn build_request(
+    state: &AppState,
+    conn: &mut Connection,
+    guild_id: GuildId,
+    (kind, provider): (ProviderKind, &dyn SpeechProvider),
+    text: String,
+    voice: Voice,
+    volume_gain: Option<f64>,
+) -> Result<SpeechRequest> {
+    let available_preset_ids = provider
+        .list_voices()
+        .await?
+        .into_iter()
+        .map(|p| p.id)
+        .collect::<Vec<_>>();
+    let (preset_id, speed_scale, pitch_scale) = match voice {
+        Voice::Member(user_id) => {
+            resolve_member_voice(state, conn, guild_id, user_id, kind, &available_preset_ids)
+                .await?
+        }
+        Voice::System => resolve_system_voice(state, conn, guild_id, &available_preset_ids).await?,
+    };
+
+    Ok(SpeechRequest {
+        text,
+        preset_id,
+        speed_scale,
+        pitch_scale,
+        volume_gain,
+    })
+}
+
+async fn synthesize_request(
+    provider: &dyn SpeechProvider,
+    request: SpeechRequest,
+) -> Result<Vec<u8>> {
+    let encoded_audio = provider
+        .synthesize(request)
+        .await
+        .context("Failed to execute Text-to-Speech")?;
+    let raw_audio = encoded_audio.decode().await?.into();
+
+    Ok(raw_audio)
+}
+
+/// メンバーのプリセットと話速・音高を返す
+/// 設定されたプリセットが`kind`のエンジンで使えない場合は、設定を変更せずに使えるプリセットで読み上げる
+async fn resolve_member_voice(
+    state: &AppState,
+    conn: &mut Connection,
+    guild_id: GuildId,
+    user_id: UserId,
+    kind: ProviderKind,
+    available_preset_ids: &[i64],
+) -> Result<(PresetId, Option<f64>, Option<f64>)> {
+    let settings = voice::get_settings(
+        conn,
+        voice::GetSettingsOption {
+            guild_id: guild_id.into(),
+            user_id: user_id.into(),
+        },
+    )
+    .await?;
+
+    let preset_id = default_voice::resolve_preset_id(
+        state,
+        conn,
+        guild_id,
+        user_id,
+        &settings,
+        kind,
+        available_preset_ids,
+    )
+    .await?;
+
+    Ok((preset_id.into(), settings.speed_scale, settings.pitch_scale))
+}
+
+/// サーバーのシステム音声のプリセットと話速・音高を返す
+async fn resolve_system_voice(
+    state: &AppState,
+    conn: &mut Connection,
+    guild_id: GuildId,
+    available_preset_ids: &[i64],
+) -> Result<(PresetId, Option<f64>, Option<f64>)> {
+    let voice = system_voice::get(
+        conn,
+        system_voice::GetOption {
+            guild_id: guild_id.into(),
+        },
+    )
+    .await?;
+
+    // 設定されたプリセットが削除されている場合は、サーバー全体の既定値、IDが最小のプリセットの順に使う
+    let preset_id = [voice.preset_id, state.system_voice_preset_id]
+        .into_iter()
+        .flatten()
+        .find(|id| available_preset_ids.contains(id))
+        .or_else(|| available_preset_ids.iter().copied().min())
+        .ok_or_else(|| anyhow!("No presets available"))?;
+
+    Ok((preset_id.into(), voice.speed_scale, voice.pitch_scale))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sequencer::Sequencer, *};
+    use koe_call::backend::FakeBackend;
+    use koe_speech::test_support::MockProvider;
+    use serenity::model::id::ChannelId;
+
+    const GUILD_ID: GuildId = GuildId(1);
+
+    fn request(text: &str) -> SpeechRequest {
+        SpeechRequest {
+            text: text.to_string(),
+            preset_id: PresetId(1),
+            speed_scale: None,
+            pitch_scale: None,
+            volume_gain: None,
+        }
+    }
+
+    async fn joined_backend() -> FakeBackend {
+        let backend = FakeBackend::new();
+        backend
+            .join(GUILD_ID.into(), ChannelId(2).into(), false)
+            .await
+            .unwrap();
+        backend
+    }
+
+    /// キューに追加された音声を、元の文章に戻して返す
+    fn played(backend: &FakeBackend) -> Vec<String> {
+        backend
+            .call(GUILD_ID.into())
+            .unwrap()
+            .enqueued
+            .iter()
+            .map(|raw_audio| MockProvider::text_of(raw_audio))
+            .collect()
+    }
+
+    fn output<'a>(provider: &'a Arc<dyn SpeechProvider>, backend: &'a FakeBackend) -> Output<'a> {
+        Output {
+            provider,
+            backend,
+            max_duration: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn plays_synthesized_audio() {
+        let provider: Arc<dyn SpeechProvider> = Arc::new(MockProvider::new());
+        let backend = joined_backend().await;
+        let sequencer = Arc::new(Sequencer::default());
+
+        for text in ["こんにちは", "さようなら"] {
+            output(&provider, &backend)
+                .speak(GUILD_ID, request(text), &sequencer.issue())
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(played(&backend), ["こんにちは", "さようなら"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn plays_in_accepted_order_regardless_of_latency() {
+        // 受け付けた順に、合成にかかる時間がばらばらになるようにする
+        let latencies = [50, 5, 30, 0, 45, 10, 20, 35, 15, 25];
+        let provider: Arc<dyn SpeechProvider> =
+            Arc::new(MockProvider::new().with_latency(move |request| {
+                let index = request.text.parse::<usize>().unwrap();
+                Duration::from_millis(latencies[index])
+            }));
+        let backend = Arc::new(joined_backend().await);
+        let sequencer = Arc::new(Sequencer::default());
+
+        let tasks = (0..latencies.len())
+            .map(|index| {
+                let ticket = sequencer.issue();
+                let provider = provider.clone();
+                let backend = backend.clone();
+                tokio::spawn(async move {
+                    output(&provider, &backend)
+                        .speak(GUILD_ID, request(&index.to_string()), &ticket)
+                        .await
+                })
+            })
+            .collect::<Vec<_>>();
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+
+        let expected = (0..latencies.len())
+            .map(|index| index.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(played(&backend), expected);
+    }
+
+    #[tokio::test]
+    async fn streams_sentences_in_order() {
+        // 後の文ほど早く合成できる場合でも、文の順に追加する
+        let provider: Arc<dyn SpeechProvider> = Arc::new(
+            MockProvider::new()
+                .with_streaming()
+                .with_latency(|request| {
+                    Duration::from_millis(40 - 10 * request.text.chars().count().min(4) as u64)
+                }),
+        );
+        let backend = joined_backend().await;
+        let sequencer = Arc::new(Sequencer::default());
+
+        output(&provider, &backend)
+            .speak(GUILD_ID, request("あ。いい。ううう。"), &sequencer.issue())
+            .await
+            .unwrap();
+
+        assert_eq!(played(&backend), ["あ。", "いい。", "ううう。"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn skip_stops_synthesizing_rest_of_stream() {
+        let mock = Arc::new(
+            MockProvider::new()
+                .with_streaming()
+                .with_latency(|_| Duration::from_millis(10)),
+        );
+        let provider: Arc<dyn SpeechProvider> = mock.clone();
+        let backend = Arc::new(joined_backend().await);
+        let sequencer = Arc::new(Sequencer::default());
+
+        let task = {
+            let backend = backend.clone();
+            let ticket = sequencer.issue();
+            tokio::spawn(async move {
+                output(&provider, &backend)
+                    .speak(GUILD_ID, request("一。二。三。四。五。"), &ticket)
+                    .await
+            })
+        };
+
+        while played(&backend).is_empty() {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        backend.skip(GUILD_ID.into()).await.unwrap();
+        task.await.unwrap().unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        assert_eq!(played(&backend), ["一。"]);
+        assert!(mock.requests().len() < 5);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn failed_synthesis_does_not_block_later_speech() {
+        let provider: Arc<dyn SpeechProvider> = Arc::new(MockProvider::new().failing_on("失敗"));
+        let backend = joined_backend().await;
+        let sequencer = Arc::new(Sequencer::default());
+        let failed_ticket = sequencer.issue();
+        let next_ticket = sequencer.issue();
+        let started_at = tokio::time::Instant::now();
+
+        let result = output(&provider, &backend)
+            .speak(GUILD_ID, request("失敗"), &failed_ticket)
+            .await;
+        assert!(result.is_err());
+        // 呼び出し元は、エラーを受け取ると番号を破棄する
+        drop(failed_ticket);
+
+        output(&provider, &backend)
+            .speak(GUILD_ID, request("成功"), &next_ticket)
+            .await
+            .unwrap();
+
+        assert_eq!(played(&backend), ["成功"]);
+        assert!(started_at.elapsed() < MAX_TURN_WAIT);
+    }
+
+    #[tokio::test]
+    async fn failure_in_later_sentence_truncates_stream() {
+        let provider: Arc<dyn SpeechProvider> =
+            Arc::new(MockProvider::new().with_streaming().failing_on("二。"));
+        let backend = joined_backend().await;
+        let sequencer = Arc::new(Sequencer::default());
+
+        output(&provider, &backend)
+            .speak(GUILD_ID, request("一。二。三。"), &sequencer.issue())
+            .await
+            .unwrap();
+
+        assert_eq!(played(&backend), ["一。"]);
+    }
+
+    #[tokio::test]
+    async fn failure_in_first_sentence_is_returned() {
+        let provider: Arc<dyn SpeechProvider> =
+            Arc::new(MockProvider::new().with_streaming().failing_on("一。"));
+        let backend = joined_backend().await;
+        let sequencer = Arc::new(Sequencer::default());
+
+        let result = output(&provider, &backend)
+            .speak(GUILD_ID, request("一。二。"), &sequencer.issue())
+            .await;
+
+        assert!(result.is_err());
+        assert!(played(&backend).is_empty());
+    }
+}
@@ -0,0 +1,65 @@
+/// 文の終わりを表す文字
+/// 改行も文の区切りとして扱う
+fn is_sentence_end(c: char) -> bool {
+    matches!(c, '。' | '．' | '！' | '？' | '!' | '?' | '\n')
+}
+
+/// 文の終わりの直後に続く場合に、その文に含める文字
+fn is_closing(c: char) -> bool {
+    matches!(
+        c,
+        '」' | '』'
+            | '）'
+            | ')'
+            | '】'
+            | '〉'
+            | '》'
+            | '。'
+            | '！'
+            | '？'
+            | '!'
+            | '?'
+            | '…'
+            | 'ー'
+            | '〜'
+    )
+}
+
+/// 文章を文ごとに区切る
+/// 区切った文を連結すると、空白を除いて元の文章と一致する
+/// 読み上げる文字を含まない部分は、直前の文に含める
+pub fn split(text: &str) -> Vec<String> {
+    let mut sentences: Vec<String> = Vec::new();
+    let mut current = String::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        current.push(c);
+        if !is_sentence_end(c) {
+            continue;
+        }
+        while let Some(&next) = chars.peek() {
+            if !is_closing(next) {
+                break;
+            }
+            current.push(next);
+            chars.next();
+        }
+        push_sentence(&mut sentences, std::mem::take(&mut current));
+    }
+    push_sentence(&mut sentences, current);
+
+    sentences
+}
+
+fn push_sentence(sentences: &mut Vec<String>, sentence: String) {
+    let sentence = sentence.trim();
+    if sentence.is_empty() {
+        return;
+    }
+
+    match sentences.last_mut() {
+        Some(last) if !sentence.chars().any(char::is_alphanumeric) => last.push_str(sentence),
+        _ => sentences.push(sentence.to_string()),
+    }
+}
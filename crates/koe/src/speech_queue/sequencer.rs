@@ -0,0 +1,205 @@
+use std::{
+    collections::BTreeSet,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::sync::Notify;
+
+/// 読み上げキューに音声を追加する順番を、読み上げを受け付けた順に保つ
+/// 音声合成にかかる時間は文章によって異なるため、合成を並行して行うと後に受け付けたものが先に完成することがある
+/// 受け付けた時点で[`Ticket`]を発行し、キューに追加する直前に自分の番になるまで待つ
+#[derive(Debug, Default)]
+pub struct Sequencer {
+    state: Mutex<SequencerState>,
+    notify: Notify,
+}
+
+#[derive(Debug, Default)]
+struct SequencerState {
+    /// 次に発行する番号
+    next_number: u64,
+    /// キューへの追加を待っている番号のうち、最も小さいもの
+    serving: u64,
+    /// `serving`より後の番号のうち、すでに追加または破棄されたもの
+    finished: BTreeSet<u64>,
+}
+
+/// 読み上げキューに追加する順番を表す
+/// キューに追加した後や、読み上げる内容がなかった場合などに破棄すると、次の番号に順番が回る
+#[derive(Debug)]
+pub struct Ticket {
+    sequencer: Arc<Sequencer>,
+    number: u64,
+}
+
+impl Sequencer {
+    /// 受け付けた順に番号を発行する
+    pub fn issue(self: &Arc<Self>) -> Ticket {
+        let mut state = self.state.lock().unwrap();
+        let number = state.next_number;
+        state.next_number += 1;
+
+        Ticket {
+            sequencer: self.clone(),
+            number,
+        }
+    }
+
+    fn is_turn(&self, number: u64) -> bool {
+        self.state.lock().unwrap().serving == number
+    }
+
+    fn finish(&self, number: u64) {
+        {
+            let mut state = self.state.lock().unwrap();
+            // 待ち時間を過ぎて飛ばされた番号は、すでに順番が過ぎている
+            if number < state.serving {
+                return;
+            }
+            state.finished.insert(number);
+            state.advance();
+        }
+        self.notify.notify_waiters();
+    }
+
+    /// `number`より前の番号を、追加または破棄されたものとして扱う
+    /// 応答しない音声合成を待ち続けて、後に受け付けたすべての読み上げが遅れることを防ぐ
+    fn expire_before(&self, number: u64) {
+        {
+            let mut state = self.state.lock().unwrap();
+            if number <= state.serving {
+                return;
+            }
+            state.serving = number;
+            state.finished = state.finished.split_off(&number);
+            state.advance();
+        }
+        self.notify.notify_waiters();
+    }
+}
+
+impl SequencerState {
+    /// 追加または破棄された番号を飛ばして、次に待っている番号に順番を回す
+    fn advance(&mut self) {
+        while self.finished.remove(&self.serving) {
+            self.serving += 1;
+        }
+    }
+}
+
+impl Ticket {
+    /// 独立した番号を発行する
+    /// ボイスチャンネルに接続していない場合など、順番を保つ必要がない場合に使う
+    pub fn unsequenced() -> Self {
+        Arc::new(Sequencer::default()).issue()
+    }
+
+    /// これより前に発行されたすべての番号が、キューに追加されるか破棄されるまで待つ
+    /// `timeout`を過ぎた場合は前の番号を飛ばして順番を回し、`false`を返す
+    pub async fn wait_turn_timeout(&self, timeout: Duration) -> bool {
+        if tokio::time::timeout(timeout, self.wait_turn())
+            .await
+            .is_ok()
+        {
+            return true;
+        }
+        self.sequencer.expire_before(self.number);
+        false
+    }
+
+    /// これより前に発行されたすべての番号が、キューに追加されるか破棄されるまで待つ
+    async fn wait_turn(&self) {
+        loop {
+            // 順番を確かめてから待ち始めるまでの間に通知されても取りこぼさないよう、先に待ち受けを作る
+            let notified = self.sequencer.notify.notified();
+            if self.sequencer.is_turn(self.number) {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+impl Drop for Ticket {
+    fn drop(&mut self) {
+        self.sequencer.finish(self.number);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(30);
+
+    #[tokio::test(start_paused = true)]
+    async fn waits_for_earlier_tickets() {
+        let sequencer = Arc::new(Sequencer::default());
+        let first = sequencer.issue();
+        let second = sequencer.issue();
+
+        let waiting = tokio::spawn(async move {
+            let on_time = second.wait_turn_timeout(TIMEOUT).await;
+            (on_time, tokio::time::Instant::now())
+        });
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        let finished_at = tokio::time::Instant::now();
+        drop(first);
+
+        let (on_time, turned_at) = waiting.await.unwrap();
+        assert!(on_time);
+        assert_eq!(turned_at, finished_at);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn timeout_skips_stalled_ticket_for_later_tickets() {
+        let sequencer = Arc::new(Sequencer::default());
+        let stalled = sequencer.issue();
+        let second = sequencer.issue();
+        let third = sequencer.issue();
+
+        let started_at = tokio::time::Instant::now();
+        assert!(!second.wait_turn_timeout(TIMEOUT).await);
+        assert_eq!(started_at.elapsed(), TIMEOUT);
+        drop(second);
+
+        // 応答しない番号を待つのは、最初に待ち時間を過ぎた1件だけにする
+        assert!(third.wait_turn_timeout(TIMEOUT).await);
+        assert_eq!(started_at.elapsed(), TIMEOUT);
+        drop(third);
+
+        // 飛ばされた番号が後から破棄されても、順番は戻らない
+        drop(stalled);
+        let state = sequencer.state.lock().unwrap();
+        assert_eq!(state.serving, 3);
+        assert!(state.finished.is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn ticket_is_released_when_task_fails() {
+        let sequencer = Arc::new(Sequencer::default());
+
+        let panicked = {
+            let ticket = sequencer.issue();
+            tokio::spawn(async move {
+                let _ticket = ticket;
+                panic!("synthesis task failed");
+            })
+        };
+        assert!(panicked.await.is_err());
+
+        let aborted = {
+            let ticket = sequencer.issue();
+            tokio::spawn(async move {
+                let _ticket = ticket;
+                std::future::pending::<()>().await;
+            })
+        };
+        aborted.abort();
+        assert!(aborted.await.unwrap_err().is_cancelled());
+
+        let started_at = tokio::time::Instant::now();
+        assert!(sequencer.issue().wait_turn_timeout(TIMEOUT).await);
+        assert!(started_at.elapsed().is_zero());
+    }
+}
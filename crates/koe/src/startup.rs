@@ -0,0 +1,107 @@
+use anyhow::Result;
+use log::{info, warn};
+use rand::Rng;
+use serenity::http::HttpError;
+use std::{
+    future::Future,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+use tokio::sync::Semaphore;
+
+/// 再試行するまでの最初の待ち時間、再試行のたびに2倍にする
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// 起動直後にサーバーごとに行う処理（コマンドの整理など）の同時実行数を制限する
+/// 多数のサーバーに参加している場合でも、DiscordのAPIのレート制限に達しにくくする
+pub struct StartupLimiter {
+    semaphore: Semaphore,
+    /// 処理を始める前に待つ時間の上限、実際の待ち時間はこれ以下のランダムな値になる
+    jitter: Duration,
+    /// レート制限などで失敗した場合に再試行する最大の回数
+    max_retries: u32,
+    /// 起動時に参加していたサーバーの数
+    total: AtomicUsize,
+    /// 処理を終えたサーバーの数
+    completed: AtomicUsize,
+}
+
+impl StartupLimiter {
+    pub fn new(concurrency: usize, jitter: Duration, max_retries: u32) -> Self {
+        Self {
+            semaphore: Semaphore::new(concurrency.max(1)),
+            jitter,
+            max_retries,
+            total: AtomicUsize::new(0),
+            completed: AtomicUsize::new(0),
+        }
+    }
+
+    /// 起動時に参加しているサーバーの数を設定し、進捗を数え直す
+    pub fn begin(&self, total: usize) {
+        self.total.store(total, Ordering::Relaxed);
+        self.completed.store(0, Ordering::Relaxed);
+        info!("Starting up with {} guilds", total);
+    }
+
+    /// 同時実行数の制限に従って`f`を実行する
+    /// レート制限やDiscordのサーバーエラーで失敗した場合は、待ち時間を延ばしながら再試行する
+    pub async fn run<F, Fut, T>(&self, label: &str, mut f: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let _permit = self.semaphore.acquire().await?;
+
+        if !self.jitter.is_zero() {
+            let jitter = rand::thread_rng().gen_range(Duration::ZERO..=self.jitter);
+            tokio::time::sleep(jitter).await;
+        }
+
+        let mut backoff = INITIAL_BACKOFF;
+        let mut attempt = 0;
+        loop {
+            match f().await {
+                Err(err) if attempt < self.max_retries && is_retryable(&err) => {
+                    attempt += 1;
+                    warn!(
+                        "{} failed, retrying in {:?} ({}/{}): {:?}",
+                        label, backoff, attempt, self.max_retries, err
+                    );
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                }
+                result => break result,
+            }
+        }
+    }
+
+    /// サーバーごとの処理を1件終えたことを記録し、進捗をログに出力する
+    pub fn record_progress(&self) {
+        let total = self.total.load(Ordering::Relaxed);
+        let completed = self.completed.fetch_add(1, Ordering::Relaxed) + 1;
+
+        // 起動後に参加したサーバーは進捗に含めない
+        if completed > total {
+            return;
+        }
+        if completed == total || completed % 100 == 0 {
+            info!("Startup progress: {} / {} guilds", completed, total);
+        }
+    }
+}
+
+/// 時間をおいて再試行すれば成功する可能性があるエラーかどうかを返す
+fn is_retryable(err: &anyhow::Error) -> bool {
+    err.chain()
+        .any(|cause| match cause.downcast_ref::<serenity::Error>() {
+            Some(serenity::Error::Http(http_err)) => match http_err.as_ref() {
+                HttpError::UnsuccessfulRequest(response) => {
+                    let status = response.status_code;
+                    status.as_u16() == 429 || status.is_server_error()
+                }
+                _ => false,
+            },
+            _ => false,
+        })
+}
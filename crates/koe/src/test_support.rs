@@ -0,0 +1,239 @@
+//! Discordに接続せずにイベントやコマンドの処理を確かめるための、テスト用の状態とインタラクション
+//!
+//! Redisには接続できないアドレスを指定するため、Redisを使う処理は失敗する
+
+use crate::{
+    app_state::AppState,
+    autocomplete::cache::TtlCache,
+    command::responder::FakeResponder,
+    rate_limit::{CommandRateLimiter, COMMAND_RATE_LIMIT_WINDOW},
+    startup::StartupLimiter,
+};
+use dashmap::{DashMap, DashSet};
+use koe_call::backend::FakeBackend;
+use koe_db::redis;
+use koe_speech::{
+    provider::{ProviderKind, Providers},
+    test_support::MockProvider,
+};
+use serde_json::{json, Value};
+use serenity::{
+    cache::Cache,
+    client::{bridge::gateway::ShardMessenger, Context},
+    http::Http,
+    model::{
+        application::interaction::application_command::ApplicationCommandInteraction,
+        event::GuildCreateEvent,
+    },
+    prelude::{RwLock, TypeMap},
+};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use time::UtcOffset;
+
+pub const GUILD_ID: u64 = 1;
+pub const TEXT_CHANNEL_ID: u64 = 2;
+pub const VOICE_CHANNEL_ID: u64 = 3;
+pub const USER_ID: u64 = 4;
+
+/// テスト用の状態と、その状態を持つ[`Context`]
+pub struct TestContext {
+    pub ctx: Context,
+    pub state: Arc<AppState>,
+    pub backend: Arc<FakeBackend>,
+    pub responder: Arc<FakeResponder>,
+}
+
+impl TestContext {
+    pub async fn new() -> Self {
+        let backend = Arc::new(FakeBackend::new());
+        let responder = Arc::new(FakeResponder::default());
+        let state = Arc::new(app_state(backend.clone(), responder.clone()));
+
+        let mut data = TypeMap::new();
+        data.insert::<AppState>(state.clone());
+        let (tx, _rx) = futures::channel::mpsc::unbounded();
+        let ctx = Context {
+            data: Arc::new(RwLock::new(data)),
+            shard: ShardMessenger::new(tx),
+            shard_id: 0,
+            http: Arc::new(Http::new("")),
+            cache: Arc::new(Cache::new()),
+        };
+
+        Self {
+            ctx,
+            state,
+            backend,
+            responder,
+        }
+    }
+
+    /// ボイスチャンネルを1つ持つサーバーをキャッシュに追加する
+    /// `in_voice`が`true`の場合は、コマンドを送信するメンバーをそのボイスチャンネルに参加させる
+    pub fn cache_guild(&self, in_voice: bool) {
+        let voice_states = if in_voice {
+            json!([{
+                "guild_id": GUILD_ID.to_string(),
+                "channel_id": VOICE_CHANNEL_ID.to_string(),
+                "user_id": USER_ID.to_string(),
+                "session_id": "session",
+                "deaf": false,
+                "mute": false,
+                "self_deaf": false,
+                "self_mute": false,
+                "self_video": false,
+                "suppress": false,
+                "request_to_speak_timestamp": null,
+            }])
+        } else {
+            json!([])
+        };
+        let mut event: GuildCreateEvent = serde_json::from_value(json!({
+            "id": GUILD_ID.to_string(),
+            "name": "guild",
+            "icon": null,
+            "owner_id": USER_ID.to_string(),
+            "afk_channel_id": null,
+            "afk_timeout": 300,
+            "verification_level": 0,
+            "default_message_notifications": 0,
+            "explicit_content_filter": 0,
+            "roles": [],
+            "emojis": [],
+            "features": [],
+            "mfa_level": 0,
+            "system_channel_id": null,
+            "system_channel_flags": 0,
+            "rules_channel_id": null,
+            "joined_at": "2024-01-01T00:00:00.000000+00:00",
+            "large": false,
+            "member_count": 1,
+            "members": [],
+            "presences": [],
+            "voice_states": voice_states,
+            "channels": [{
+                "id": VOICE_CHANNEL_ID.to_string(),
+                "guild_id": GUILD_ID.to_string(),
+                "type": 2,
+                "name": "voice",
+                "position": 0,
+                "permission_overwrites": [],
+            }],
+            "threads": [],
+            "premium_tier": 0,
+            "preferred_locale": "ja",
+            "public_updates_channel_id": null,
+            "nsfw_level": 0,
+            "stickers": [],
+            "premium_progress_bar_enabled": false,
+        }))
+        .unwrap();
+        self.ctx.cache.update(&mut event);
+    }
+}
+
+fn app_state(backend: Arc<FakeBackend>, responder: Arc<FakeResponder>) -> AppState {
+    AppState {
+        // 接続を拒否されるアドレス
+        redis_client: redis::Client::open("redis://127.0.0.1:1/").unwrap(),
+        voicevox_client: None,
+        speech_providers: Providers::new(ProviderKind::Voicevox, Arc::new(MockProvider::new())),
+        voice_backend: backend,
+        responder,
+        connected_guild_states: DashMap::new(),
+        manual_leave_times: DashMap::new(),
+        timezone: UtcOffset::UTC,
+        max_speech_duration: None,
+        owner_ids: Vec::new(),
+        dev_guild_id: None,
+        system_voice_preset_id: None,
+        idle_timeout: None,
+        dict_cache: Default::default(),
+        user_dict_cache: Default::default(),
+        settings_cache: Default::default(),
+        preset_cache: TtlCache::new(Duration::from_secs(60)),
+        self_deaf: false,
+        max_connections: None,
+        max_reconnect_attempts: 0,
+        alert_channel_id: None,
+        alert_webhook_url: None,
+        skip_counter: Default::default(),
+        recovery_attempts_total: Default::default(),
+        startup_limiter: StartupLimiter::new(1, Duration::ZERO, 0),
+        command_rate_limiter: CommandRateLimiter::new(COMMAND_RATE_LIMIT_WINDOW),
+        pending_confirmations: DashSet::new(),
+        active_scheduled_events: DashSet::new(),
+        print_speech: false,
+        started_at: Instant::now(),
+    }
+}
+
+/// サーバーで送信されたスラッシュコマンドのインタラクションを作る
+/// `manage_guild`が`true`の場合は、送信したメンバーに「サーバー管理」の権限を与える
+pub fn guild_command(
+    name: &str,
+    options: Value,
+    manage_guild: bool,
+) -> ApplicationCommandInteraction {
+    let permissions = if manage_guild { "32" } else { "0" };
+    command(json!({
+        "guild_id": GUILD_ID.to_string(),
+        "member": {
+            "user": user(),
+            "roles": [],
+            "joined_at": "2024-01-01T00:00:00.000000+00:00",
+            "deaf": false,
+            "mute": false,
+            "permissions": permissions,
+        },
+        "data": {
+            "id": "100",
+            "name": name,
+            "type": 1,
+            "options": options,
+        },
+    }))
+}
+
+/// DMで送信されたスラッシュコマンドのインタラクションを作る
+pub fn dm_command(name: &str) -> ApplicationCommandInteraction {
+    command(json!({
+        "user": user(),
+        "data": {
+            "id": "100",
+            "name": name,
+            "type": 1,
+        },
+    }))
+}
+
+fn command(mut fields: Value) -> ApplicationCommandInteraction {
+    let base = json!({
+        "id": "10",
+        "application_id": "11",
+        "type": 2,
+        "channel_id": TEXT_CHANNEL_ID.to_string(),
+        "user": user(),
+        "token": "token",
+        "version": 1,
+        "locale": "ja",
+    });
+    if let (Value::Object(fields), Value::Object(base)) = (&mut fields, base) {
+        for (key, value) in base {
+            fields.entry(key).or_insert(value);
+        }
+    }
+    serde_json::from_value(fields).unwrap()
+}
+
+fn user() -> Value {
+    json!({
+        "id": USER_ID.to_string(),
+        "username": "member",
+        "discriminator": "0000",
+        "avatar": null,
+    })
+}
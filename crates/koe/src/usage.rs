@@ -0,0 +1,104 @@
+use crate::{app_state::AppState, notice};
+use anyhow::{Context as _, Result};
+use koe_db::{redis::aio::Connection, usage};
+use serenity::{client::Context, model::id::GuildId};
+use time::OffsetDateTime;
+
+/// 音声合成した文字数を数える単位となる、現在の年月を`2024-01`の形式で返す
+/// 月の切り替わりは、設定ファイルで指定されたタイムゾーンに従う
+pub fn current_month(state: &AppState) -> String {
+    let now = OffsetDateTime::now_utc().to_offset(state.timezone);
+    format!("{:04}-{:02}", now.year(), u8::from(now.month()))
+}
+
+/// 音声合成してよいかを返す
+/// サーバーの今月の文字数が上限に達している場合は`false`を返し、その月に初めて達した場合は読み上げ対象のチャンネルで知らせる
+pub async fn check(
+    ctx: &Context,
+    state: &AppState,
+    conn: &mut Connection,
+    guild_id: GuildId,
+) -> Result<bool> {
+    let month = current_month(state);
+
+    let limit = usage::get_limit(
+        conn,
+        usage::GetLimitOption {
+            guild_id: guild_id.into(),
+        },
+    )
+    .await?;
+    if let Some(limit) = limit {
+        let used = usage::get(
+            conn,
+            usage::GetOption {
+                guild_id: guild_id.into(),
+                month: month.clone(),
+            },
+        )
+        .await?;
+        if used >= limit {
+            notify_limit_reached(ctx, state, conn, guild_id, month).await?;
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+/// 音声合成した文字数を記録する
+/// 音声合成に失敗した文章を数えないよう、合成に成功した後に呼ぶ
+pub async fn record(
+    state: &AppState,
+    conn: &mut Connection,
+    guild_id: GuildId,
+    text: &str,
+) -> Result<()> {
+    usage::add(
+        conn,
+        usage::AddOption {
+            guild_id: guild_id.into(),
+            month: current_month(state),
+            chars: text.chars().count() as u64,
+        },
+    )
+    .await?;
+
+    Ok(())
+}
+
+async fn notify_limit_reached(
+    ctx: &Context,
+    state: &AppState,
+    conn: &mut Connection,
+    guild_id: GuildId,
+    month: String,
+) -> Result<()> {
+    let text_channel_id = match state.connected_guild_states.get(&guild_id) {
+        Some(guild_state) => guild_state.primary_text_channel(),
+        None => return Ok(()),
+    };
+
+    let first_time = usage::mark_limit_notified(
+        conn,
+        usage::MarkLimitNotifiedOption {
+            guild_id: guild_id.into(),
+            month,
+        },
+    )
+    .await?;
+    if !first_time {
+        return Ok(());
+    }
+
+    notice::send(
+        ctx,
+        guild_id,
+        text_channel_id,
+        "このサーバーで今月読み上げられる文字数の上限に達したため、来月まで読み上げを停止します。",
+    )
+    .await
+    .context("Failed to send message")?;
+
+    Ok(())
+}
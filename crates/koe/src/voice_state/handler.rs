@@ -1,17 +1,262 @@
-use crate::app_state;
+use crate::{announcement, app_state, connection, error::report_error, notice};
 use anyhow::{Context as _, Result};
-use log::debug;
+use koe_db::{
+    auto_join,
+    guild_settings::{self, BoolKey},
+};
+use log::{debug, info};
 use serenity::{
     client::Context,
-    model::id::{ChannelId, GuildId, UserId},
+    model::{
+        id::{ChannelId, GuildId, UserId},
+        voice::VoiceState,
+    },
 };
+use std::time::{Duration, Instant};
+
+/// `/leave`で切断された後、自動接続を行わない期間
+const AUTO_JOIN_COOLDOWN: Duration = Duration::from_secs(3 * 60);
+
+/// 誰もいないチャンネルに移動させられた後、退出するまでの猶予
+const EMPTY_CHANNEL_GRACE_PERIOD: Duration = Duration::from_secs(60);
+
+/// メンバーの移動に追従する前に、状況が変わらないことを確かめる時間
+/// 移動を繰り返すメンバーにつられて、Botが行き来しないようにする
+const FOLLOW_STABLE_DELAY: Duration = Duration::from_secs(5);
+/// ボイスチャンネルから抜けたメンバーを、移動に追従する対象とみなす期間
+const FOLLOW_WINDOW: Duration = Duration::from_secs(60);
+
+pub async fn handle_update(
+    ctx: &Context,
+    old_voice_state: Option<&VoiceState>,
+    voice_state: &VoiceState,
+) -> Result<()> {
+    let guild_id = match voice_state.guild_id {
+        Some(id) => id,
+        None => return Ok(()),
+    };
+
+    // Bot自身の移動は`handle_self_move`で扱う
+    if voice_state.user_id == ctx.cache.current_user_id() {
+        return Ok(());
+    }
+
+    if !is_bot(ctx, voice_state) {
+        record_departure(ctx, guild_id, old_voice_state, voice_state).await?;
+    }
+
+    // メンバーが移動した直後は、すぐに退出せず追従するかどうかを確かめる
+    if find_follow_target(ctx, guild_id).await?.is_some() {
+        schedule_follow(ctx, guild_id).await?;
+        return Ok(());
+    }
+
+    leave_if_alone(ctx, guild_id).await
+}
+
+/// 接続中のボイスチャンネルから抜けたメンバーを記録する
+async fn record_departure(
+    ctx: &Context,
+    guild_id: GuildId,
+    old_voice_state: Option<&VoiceState>,
+    voice_state: &VoiceState,
+) -> Result<()> {
+    let state = app_state::get(ctx).await?;
+    let mut guild_state = match state.connected_guild_states.get_mut(&guild_id) {
+        Some(guild_state) => guild_state,
+        None => return Ok(()),
+    };
+
+    let current_channel = Some(guild_state.voice_channel);
+    if voice_state.channel_id == current_channel {
+        guild_state.departed_members.remove(&voice_state.user_id);
+    } else if old_voice_state.and_then(|s| s.channel_id) == current_channel {
+        guild_state
+            .departed_members
+            .insert(voice_state.user_id, Instant::now());
+    }
+
+    guild_state
+        .departed_members
+        .retain(|_, departed_at| departed_at.elapsed() < FOLLOW_WINDOW);
+
+    Ok(())
+}
+
+/// Botのいるボイスチャンネルから人間のメンバーがいなくなり、最近抜けたメンバー全員が同じボイスチャンネルにいる場合は、そのチャンネルを返す
+/// サーバーの設定で追従が無効になっている場合は[`None`]を返す
+async fn find_follow_target(ctx: &Context, guild_id: GuildId) -> Result<Option<ChannelId>> {
+    let state = app_state::get(ctx).await?;
+    let (current_channel_id, departed_members) = match state.connected_guild_states.get(&guild_id) {
+        Some(guild_state) => (
+            guild_state.voice_channel,
+            guild_state
+                .departed_members
+                .iter()
+                .filter(|(_, departed_at)| departed_at.elapsed() < FOLLOW_WINDOW)
+                .map(|(user_id, _)| *user_id)
+                .collect::<Vec<_>>(),
+        ),
+        None => return Ok(None),
+    };
+    if departed_members.is_empty() {
+        return Ok(None);
+    }
+
+    let voice_states = guild_id
+        .to_guild_cached(&ctx.cache)
+        .context("Failed to find guild in the cache")?
+        .voice_states;
+
+    let someone_remains = voice_states.values().any(|voice_state| {
+        voice_state.channel_id == Some(current_channel_id) && !is_bot(ctx, voice_state)
+    });
+    if someone_remains {
+        return Ok(None);
+    }
+
+    let mut destinations = departed_members.iter().map(|user_id| {
+        voice_states
+            .get(user_id)
+            .and_then(|voice_state| voice_state.channel_id)
+    });
+    let target = match destinations.next().flatten() {
+        Some(channel_id) if channel_id != current_channel_id => channel_id,
+        _ => return Ok(None),
+    };
+    if !destinations.all(|channel_id| channel_id == Some(target)) {
+        return Ok(None);
+    }
+
+    let mut conn = state.redis_client.get_async_connection().await?;
+    let enabled = guild_settings::get_bool(
+        &mut conn,
+        guild_settings::GetBoolOption {
+            guild_id: guild_id.into(),
+            key: BoolKey::FollowUsers,
+        },
+    )
+    .await?;
+
+    Ok(enabled.then_some(target))
+}
+
+/// 状況が変わらないことを確かめてから、メンバーの移動先に移動する
+async fn schedule_follow(ctx: &Context, guild_id: GuildId) -> Result<()> {
+    let state = app_state::get(ctx).await?;
+    match state.connected_guild_states.get_mut(&guild_id) {
+        Some(mut guild_state) if !guild_state.follow_scheduled => {
+            guild_state.follow_scheduled = true;
+        }
+        _ => return Ok(()),
+    }
+
+    let ctx = ctx.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(FOLLOW_STABLE_DELAY).await;
+        if let Err(err) = follow_users(&ctx, guild_id)
+            .await
+            .context("Failed to follow users to another voice channel")
+        {
+            report_error(err);
+        }
+    });
+
+    Ok(())
+}
+
+async fn follow_users(ctx: &Context, guild_id: GuildId) -> Result<()> {
+    let state = app_state::get(ctx).await?;
+    if let Some(mut guild_state) = state.connected_guild_states.get_mut(&guild_id) {
+        guild_state.follow_scheduled = false;
+    }
+
+    let target = match find_follow_target(ctx, guild_id).await? {
+        Some(channel_id) => channel_id,
+        None => return leave_if_alone(ctx, guild_id).await,
+    };
+    if connection::missing_permissions(ctx, target).is_some_and(|missing| !missing.is_empty()) {
+        return leave_if_alone(ctx, guild_id).await;
+    }
+
+    connection::move_to(ctx, guild_id, target).await?;
+
+    info!(
+        "Followed users to channel {} in guild {}",
+        target.as_u64(),
+        guild_id.as_u64()
+    );
+
+    announcement::announce(ctx, guild_id, "移動しました")
+        .await
+        .context("Failed to announce channel move")?;
+
+    Ok(())
+}
 
-pub async fn handle_update(ctx: &Context, guild_id: Option<GuildId>) -> Result<()> {
-    let guild_id = match guild_id {
+/// Bot自身が別のボイスチャンネルに移動させられた場合は、移動先で読み上げを続ける
+pub async fn handle_self_move(
+    ctx: &Context,
+    old_voice_state: Option<&VoiceState>,
+    new_voice_state: &VoiceState,
+) -> Result<()> {
+    let guild_id = match new_voice_state.guild_id {
+        Some(id) => id,
+        None => return Ok(()),
+    };
+
+    if new_voice_state.user_id != ctx.cache.current_user_id() {
+        return Ok(());
+    }
+
+    let new_channel_id = match new_voice_state.channel_id {
         Some(id) => id,
         None => return Ok(()),
     };
+    if old_voice_state.and_then(|s| s.channel_id) == Some(new_channel_id) {
+        return Ok(());
+    }
 
+    let state = app_state::get(ctx).await?;
+    {
+        let mut guild_state = match state.connected_guild_states.get_mut(&guild_id) {
+            Some(guild_state) => guild_state,
+            None => return Ok(()),
+        };
+        // 接続時のボイスステートの更新も同様に届くため、チャンネルが変わっていなければ何もしない
+        if guild_state.voice_channel == new_channel_id {
+            return Ok(());
+        }
+        guild_state.voice_channel = new_channel_id;
+    }
+
+    debug!(
+        "Moved to channel {} in guild {}",
+        new_channel_id.as_u64(),
+        guild_id.as_u64()
+    );
+
+    announcement::announce(ctx, guild_id, "移動しました")
+        .await
+        .context("Failed to announce channel move")?;
+
+    // 誰もいないチャンネルに移動させられた場合は、メンバーが後から参加するのを待ってから退出する
+    let ctx = ctx.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(EMPTY_CHANNEL_GRACE_PERIOD).await;
+        if let Err(err) = leave_if_alone(&ctx, guild_id)
+            .await
+            .context("Failed to leave empty voice channel")
+        {
+            report_error(err);
+        }
+    });
+
+    Ok(())
+}
+
+/// VCのメンバーがKoe自身のみになっている場合は抜ける
+async fn leave_if_alone(ctx: &Context, guild_id: GuildId) -> Result<()> {
     let current_voice_channel_id = match get_current_voice_channel_id(ctx, guild_id)? {
         Some(id) => id,
         None => return Ok(()),
@@ -21,21 +266,131 @@ pub async fn handle_update(ctx: &Context, guild_id: Option<GuildId>) -> Result<(
         list_users_in_voice_channel(ctx, guild_id, current_voice_channel_id)
             .context("Failed to count the number of users in the bot's channel")?;
 
-    // VCのメンバーがKoe自身のみになった場合は抜ける
     if current_channel_user_list.len() == 1 {
-        koe_call::leave(ctx, guild_id)
+        connection::leave(ctx, guild_id)
             .await
             .context("Failed to leave voice channel")?;
 
-        let state = app_state::get(ctx).await?;
-        state.connected_guild_states.remove(&guild_id);
-
         debug!("Automatically disconnected in guild {}", guild_id.as_u64());
     }
 
     Ok(())
 }
 
+/// Bot自身がモデレーターなどによってボイスチャンネルから切断された場合は読み上げを終了する
+pub async fn handle_self_disconnect(ctx: &Context, voice_state: &VoiceState) -> Result<()> {
+    let guild_id = match voice_state.guild_id {
+        Some(id) => id,
+        None => return Ok(()),
+    };
+
+    if voice_state.user_id != ctx.cache.current_user_id() || voice_state.channel_id.is_some() {
+        return Ok(());
+    }
+
+    // `/leave`などによる切断の場合はすでに状態が削除されている
+    let state = app_state::get(ctx).await?;
+    let guild_state = match state.connected_guild_states.remove(&guild_id) {
+        Some((_, guild_state)) => guild_state,
+        None => return Ok(()),
+    };
+
+    state
+        .voice_backend
+        .leave(guild_id.into())
+        .await
+        .context("Failed to clean up voice connection")?;
+
+    info!("Disconnected by someone in guild {}", guild_id.as_u64());
+
+    notice::send(
+        ctx,
+        guild_id,
+        guild_state.primary_text_channel(),
+        "ボイスチャンネルから切断されました。",
+    )
+    .await
+    .context("Failed to send message")?;
+
+    Ok(())
+}
+
+/// メンバーが自動接続の対象となっているボイスチャンネルに参加した場合は接続する
+pub async fn handle_auto_join(ctx: &Context, voice_state: &VoiceState) -> Result<()> {
+    let guild_id = match voice_state.guild_id {
+        Some(id) => id,
+        None => return Ok(()),
+    };
+    let joined_channel_id = match voice_state.channel_id {
+        Some(id) => id,
+        None => return Ok(()),
+    };
+
+    if is_bot(ctx, voice_state) {
+        return Ok(());
+    }
+
+    let state = app_state::get(ctx).await?;
+
+    if state.voice_backend.is_connected(guild_id.into()).await? {
+        return Ok(());
+    }
+
+    if let Some(left_at) = state.manual_leave_times.get(&guild_id) {
+        if left_at.elapsed() < AUTO_JOIN_COOLDOWN {
+            return Ok(());
+        }
+    }
+
+    let mut conn = state.redis_client.get_async_connection().await?;
+    let setting = match auto_join::get(
+        &mut conn,
+        auto_join::GetOption {
+            guild_id: guild_id.into(),
+        },
+    )
+    .await?
+    {
+        Some(setting) => setting,
+        None => return Ok(()),
+    };
+
+    if joined_channel_id != ChannelId(setting.voice_channel_id) {
+        return Ok(());
+    }
+
+    if state.is_at_connection_limit() {
+        info!(
+            "Skipped auto-join in guild {} because the connection limit is reached",
+            guild_id
+        );
+        return Ok(());
+    }
+
+    connection::join(
+        ctx,
+        guild_id,
+        joined_channel_id,
+        ChannelId(setting.text_channel_id),
+    )
+    .await
+    .context("Failed to join voice channel automatically")?;
+
+    debug!("Automatically connected in guild {}", guild_id.as_u64());
+
+    Ok(())
+}
+
+fn is_bot(ctx: &Context, voice_state: &VoiceState) -> bool {
+    match &voice_state.member {
+        Some(member) => member.user.bot,
+        None => ctx
+            .cache
+            .user(voice_state.user_id)
+            .is_some_and(|user| user.bot),
+    }
+}
+
 fn get_current_voice_channel_id(ctx: &Context, guild_id: GuildId) -> Result<Option<ChannelId>> {
     let current_user_id = ctx.cache.current_user_id();
 